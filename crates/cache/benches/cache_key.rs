@@ -0,0 +1,70 @@
+//! Cache key generation, which runs for every task of every run
+//!
+//! Run with `cargo bench -p cuenv-cache --bench cache_key`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use cuenv_cache::manager::hash_task_config;
+use cuenv_cache::{CacheKeyFilterConfig, CacheKeyGenerator};
+use cuenv_config::TaskConfig;
+use std::collections::HashMap;
+use std::path::Path;
+
+fn task_config() -> TaskConfig {
+    TaskConfig {
+        description: Some("Build the release binaries".to_string()),
+        command: Some("cargo build --release --workspace".to_string()),
+        dependencies: Some(vec!["codegen".to_string(), "lint".to_string()]),
+        inputs: Some(vec!["src/**/*.rs".to_string(), "Cargo.toml".to_string()]),
+        tags: Some(vec!["release".to_string()]),
+        owners: Some(vec!["team-platform".to_string()]),
+        timeout: Some(600),
+        ..Default::default()
+    }
+}
+
+/// A process environment of a developer machine or CI runner
+fn env_vars() -> HashMap<String, String> {
+    let mut env: HashMap<String, String> = (0..150)
+        .map(|i| (format!("VAR_{i}"), format!("value-{i}")))
+        .collect();
+    for (key, value) in [
+        ("PATH", "/usr/local/bin:/usr/bin:/bin"),
+        ("HOME", "/home/ci"),
+        ("CARGO_HOME", "/home/ci/.cargo"),
+        ("RUSTFLAGS", "-C target-cpu=native"),
+        ("CC", "clang"),
+    ] {
+        env.insert(key.to_string(), value.to_string());
+    }
+    env
+}
+
+fn bench_cache_key(c: &mut Criterion) {
+    let config = task_config();
+    let env = env_vars();
+    let generator = CacheKeyGenerator::with_config(CacheKeyFilterConfig::default()).unwrap();
+    let input_files = HashMap::new();
+
+    c.bench_function("hash_task_config", |b| {
+        b.iter(|| hash_task_config(black_box(&config)).unwrap())
+    });
+
+    c.bench_function("generate_cache_key", |b| {
+        b.iter(|| {
+            let config_hash = hash_task_config(black_box(&config)).unwrap();
+            generator
+                .generate_cache_key(
+                    black_box("build"),
+                    &config_hash,
+                    Path::new("/workspace/app"),
+                    &input_files,
+                    black_box(&env),
+                    config.command.as_deref(),
+                )
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, bench_cache_key);
+criterion_main!(benches);
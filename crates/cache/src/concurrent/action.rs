@@ -2,17 +2,34 @@
 //!
 //! This module provides caching for task actions, including memoization
 //! of results and integration with content-addressed storage.
+//!
+//! Inside a cuenv.workspace.cue, actions are keyed by the path of their
+//! package from the workspace root instead of where the workspace is checked
+//! out, and stored under that package.
+//!
+//! While a process executes an action it holds a lock on the action's file
+//! in the `leases` directory, so that maintenance in other processes leaves
+//! the action and the outputs it is storing alone.
+//!
+//! With a remote cache, results missing here are fetched from it along with
+//! their outputs, and successful results are shared through it.
 
-use super::ConcurrentCache;
+use super::artifacts::{self, StoredArtifact};
+use super::{file_hashes, ConcurrentCache};
 use crate::content_addressed_store::ContentAddressedStore;
 use crate::keys::CacheKeyGenerator;
+use crate::remote::{RemoteCache, RemoteStore};
 use crate::security::signing::{CacheSigner, SignedCacheEntry};
+use cuenv_config::WorkspaceConfig;
 use cuenv_core::{Error, Result};
 use cuenv_core::{TaskDefinition, TaskExecutionMode};
+use cuenv_utils::atomic_file::write_atomic_string;
 use dashmap::DashMap;
-use serde::{Deserialize, Serialize};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize, Serializer};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -27,8 +44,17 @@ pub struct ActionResult {
     pub stdout_hash: Option<String>,
     /// Stderr content hash (stored in CAS)
     pub stderr_hash: Option<String>,
-    /// Output file hashes (path -> CAS hash)
+    /// Output file hashes (path -> CAS hash); the files of named
+    /// artifacts are under the artifact's name, or `<name>/<path>` inside a
+    /// directory
+    #[serde(serialize_with = "sorted")]
     pub output_files: HashMap<String, String>,
+    /// Named output artifacts (name -> path)
+    #[serde(default, serialize_with = "sorted")]
+    pub artifacts: HashMap<String, PathBuf>,
+    /// Keys of `output_files` that are executable
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub executables: Vec<String>,
     /// When this action was executed
     pub executed_at: SystemTime,
     /// Duration of execution in milliseconds
@@ -49,9 +75,13 @@ pub struct ActionDigest {
 pub struct ActionComponents {
     /// Task name
     pub task_name: String,
+    /// Path of the workspace package from the workspace root, `.` for the
+    /// root itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
     /// Command or script
     pub command: Option<String>,
-    /// Working directory
+    /// Working directory, relative to the workspace root inside a workspace
     pub working_dir: PathBuf,
     /// Environment variables that affect the action
     pub env_vars: HashMap<String, String>,
@@ -61,6 +91,30 @@ pub struct ActionComponents {
     pub config_hash: String,
 }
 
+/// A cached action as it is stored in the `actions` directory
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredAction {
+    task_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    result: crate::types::CachedTaskResult,
+}
+
+/// A cached action on disk, as listed for maintenance
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionEntry {
+    /// Hash of the action
+    pub hash: String,
+    /// Task the action ran
+    pub task_name: String,
+    /// Workspace package the task belongs to
+    pub package: Option<String>,
+    /// When the action was executed
+    pub executed_at: SystemTime,
+    /// Bytes of the entry and of its outputs in CAS
+    pub size_bytes: u64,
+}
+
 /// Action cache that integrates with CAS
 pub struct ActionCache {
     /// Concurrent cache for action results
@@ -73,6 +127,12 @@ pub struct ActionCache {
     signer: Arc<CacheSigner>,
     /// Cache key generator with selective environment variable filtering
     key_generator: Arc<CacheKeyGenerator>,
+    /// Directory holding an entry per cached action, to reuse across runs
+    actions_dir: PathBuf,
+    /// Directory holding the lock files of actions being executed
+    leases_dir: PathBuf,
+    /// Remote cache to share results with other machines through
+    remote: Option<Arc<RemoteCache>>,
 }
 
 impl ActionCache {
@@ -98,10 +158,20 @@ impl ActionCache {
             in_flight: Arc::new(DashMap::new()),
             signer,
             key_generator,
+            actions_dir: cache_dir.join("actions"),
+            leases_dir: cache_dir.join("leases"),
+            remote: None,
         })
     }
 
+    /// Also look results up in and share them through `remote`
+    pub fn with_remote(mut self, remote: Arc<RemoteCache>) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
     /// Compute action digest for a task
+    #[tracing::instrument(name = "hash", level = "debug", skip_all, fields(task = task_name))]
     pub async fn compute_digest(
         &self,
         task_name: &str,
@@ -117,13 +187,27 @@ impl ActionCache {
             TaskExecutionMode::Script { content } => Some(content.clone()),
         };
 
+        let (package, config_hash) = match workspace_package(working_dir) {
+            Some((root, package)) => {
+                let mut definition = task_definition.clone();
+                if let Ok(relative) = definition.working_directory.strip_prefix(&root) {
+                    definition.working_directory = relative.to_path_buf();
+                }
+                (Some(package), hash_task_definition(&definition)?)
+            }
+            None => (None, hash_task_definition(task_definition)?),
+        };
+
         let mut components = ActionComponents {
             task_name: task_name.to_string(),
             command,
-            working_dir: working_dir.to_path_buf(),
+            working_dir: package
+                .as_ref()
+                .map_or_else(|| working_dir.to_path_buf(), PathBuf::from),
+            package,
             env_vars: filtered_env_vars,
             input_files: HashMap::new(),
-            config_hash: hash_task_definition(task_definition)?,
+            config_hash,
         };
 
         // Hash input files
@@ -131,8 +215,7 @@ impl ActionCache {
             for pattern in &task_definition.inputs {
                 let files = crate::hashing::expand_glob_pattern(pattern, working_dir)?;
                 for file in files {
-                    // Use streaming hash computation for large files
-                    let hash = compute_file_hash(&file).await?;
+                    let hash = file_hashes::hash(&file).await?;
                     let relative_path = file
                         .strip_prefix(working_dir)
                         .unwrap_or(&file)
@@ -154,7 +237,8 @@ impl ActionCache {
 
     /// Get cached action result from storage with signature verification
     pub fn get_cached_action_result(&self, hash: &str) -> Option<ActionResult> {
-        self.result_cache.get(hash).and_then(|cached| {
+        let cached = self.result_cache.get(hash).or_else(|| self.load(hash));
+        cached.and_then(|cached| {
             // Deserialize signed cache entry from stdout field
             if let Some(stdout_bytes) = &cached.stdout {
                 if let Ok(stdout_str) = String::from_utf8(stdout_bytes.clone()) {
@@ -194,6 +278,8 @@ impl ActionCache {
                 stdout_hash,
                 stderr_hash,
                 output_files: cached.output_files.clone(),
+                artifacts: cached.artifacts.clone(),
+                executables: Vec::new(),
                 executed_at: cached.executed_at,
                 duration_ms: 0, // Not stored in CachedTaskResult
             })
@@ -201,10 +287,16 @@ impl ActionCache {
     }
 
     /// Check if an action result is cached
+    #[tracing::instrument(name = "cache_lookup", level = "debug", skip_all, fields(hit))]
     pub async fn get_cached_result(&self, digest: &ActionDigest) -> Option<ActionResult> {
         // Just check cache, don't wait for in-flight actions
         // The execute_action method handles in-flight coordination
-        self.get_cached_action_result(&digest.hash)
+        let result = match self.get_cached_action_result(&digest.hash) {
+            Some(result) => Some(result),
+            None => self.fetch_remote(digest).await,
+        };
+        tracing::Span::current().record("hit", result.is_some());
+        result
     }
 
     /// Execute an action with caching
@@ -296,7 +388,9 @@ impl ActionCache {
             }
         }
 
-        // Execute the action (we already inserted ourselves into in_flight)
+        // Execute the action (we already inserted ourselves into in_flight),
+        // leased until its entry is stored
+        let _lease = self.lease(&digest.hash);
         let result = match execute_fn().await {
             Ok(mut result) => {
                 // Store outputs in CAS
@@ -311,10 +405,21 @@ impl ActionCache {
             }
         };
 
-        // Cache the result with cryptographic signing
+        self.record(digest, &result)?;
+
+        // Remove from in-flight and notify waiters
+        self.in_flight.remove(&digest.hash);
+        notify.notify_waiters();
+
+        self.share(digest, &result).await;
+        Ok(result)
+    }
+
+    /// Cache `result` as the result of `digest` with cryptographic signing
+    fn record(&self, digest: &ActionDigest, result: &ActionResult) -> Result<()> {
         let signed_result = self
             .signer
-            .sign(&result)
+            .sign(result)
             .map_err(|e| Error::configuration(format!("Failed to sign cache entry: {e}")))?;
 
         let signed_json = serde_json::to_string(&signed_result).map_err(|e| Error::Json {
@@ -329,16 +434,87 @@ impl ActionCache {
             stdout: Some(signed_json.as_bytes().to_vec()),
             stderr: None, // Not used in signed format
             output_files: result.output_files.clone(),
+            artifacts: result.artifacts.clone(),
         };
 
+        // A failure to store the entry only costs a later run a cache hit
+        if let Err(e) = self.persist(&digest.components, &cached_result) {
+            log::warn!("Failed to store cache entry for {}: {e}", digest.hash);
+        }
         self.result_cache
             .insert(digest.hash.clone(), cached_result)?;
+        Ok(())
+    }
 
-        // Remove from in-flight and notify waiters
-        self.in_flight.remove(&digest.hash);
-        notify.notify_waiters();
+    /// Fetch the result of `digest` another machine shared through the
+    /// remote cache, with its outputs, and cache it here
+    ///
+    /// The remote is trusted with results, the way the local cache is, but
+    /// outputs are checked against their hash before they are stored.
+    async fn fetch_remote(&self, digest: &ActionDigest) -> Option<ActionResult> {
+        let remote = self.remote.as_ref()?;
+        let content = remote.get(RemoteStore::Actions, &digest.hash).await?;
+        let result: ActionResult = match serde_json::from_slice(&content) {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("Ignoring unreadable remote result {}: {e}", digest.hash);
+                return None;
+            }
+        };
 
-        Ok(result)
+        let missing: Vec<String> = result_outputs(&result)
+            .into_iter()
+            .filter(|hash| !self.cas.contains(hash))
+            .collect();
+        let contents = futures::future::join_all(
+            missing
+                .iter()
+                .map(|hash| remote.get(RemoteStore::Objects, hash)),
+        )
+        .await;
+        for (hash, content) in missing.iter().zip(contents) {
+            let stored = self.cas.store(Cursor::new(content?.as_slice())).ok()?;
+            if &stored != hash {
+                log::warn!(
+                    "Ignoring remote result {}: output {hash} is corrupt",
+                    digest.hash
+                );
+                let _ = self.cas.release(&stored);
+                return None;
+            }
+        }
+
+        if let Err(e) = self.record(digest, &result) {
+            log::warn!("Failed to cache remote result {}: {e}", digest.hash);
+        }
+        Some(result)
+    }
+
+    /// Share a successful result through the remote cache, after its outputs
+    /// so that it never refers to outputs the remote lacks
+    async fn share(&self, digest: &ActionDigest, result: &ActionResult) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        if result.exit_code != 0 {
+            return;
+        }
+        let Ok(json) = serde_json::to_vec(result) else {
+            return;
+        };
+
+        let outputs = result_outputs(result);
+        let mut puts = Vec::new();
+        for hash in &outputs {
+            match self.cas.retrieve(hash) {
+                Ok(content) => puts.push(remote.put(RemoteStore::Objects, hash, content)),
+                Err(_) => return,
+            }
+        }
+        let stored = futures::future::join_all(puts).await;
+        if stored.into_iter().all(|stored| stored) {
+            remote.put(RemoteStore::Actions, &digest.hash, json).await;
+        }
     }
 
     /// Store action outputs in CAS
@@ -355,14 +531,17 @@ impl ActionCache {
             result.stderr_hash = Some(hash);
         }
 
-        // Store output files
-        let mut new_output_files = HashMap::new();
-        for (path, content_hash) in &result.output_files {
-            // In a real implementation, we'd read the file and store it
-            // For now, we'll assume the hash is already computed
-            new_output_files.insert(path.clone(), content_hash.clone());
+        // Store the named artifacts of a successful run, which only costs
+        // them being fetched later when it fails
+        if result.exit_code == 0 && !result.artifacts.is_empty() {
+            match artifacts::store(&self.cas, &result.artifacts) {
+                Ok((files, executables)) => {
+                    result.output_files.extend(files);
+                    result.executables = executables;
+                }
+                Err(e) => log::warn!("Failed to store artifacts: {e}"),
+            }
         }
-        result.output_files = new_output_files;
 
         Ok(result)
     }
@@ -377,6 +556,311 @@ impl ActionCache {
         self.result_cache.clear();
         self.in_flight.clear();
     }
+
+    /// Whether the action with `hash` is being executed by this or another
+    /// process
+    pub fn is_in_flight(&self, hash: &str) -> bool {
+        self.in_flight.contains_key(hash) || self.is_leased(hash)
+    }
+
+    /// Whether any action is being executed by this or another process
+    pub fn has_leases(&self) -> bool {
+        if !self.in_flight.is_empty() {
+            return true;
+        }
+        let Ok(read_dir) = fs::read_dir(&self.leases_dir) else {
+            return false;
+        };
+        read_dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .any(|hash| self.is_leased(&hash))
+    }
+
+    /// Hashes of the actions stored on disk, including unreadable entries
+    pub fn stored_hashes(&self) -> Result<Vec<String>> {
+        let read_dir = match fs::read_dir(&self.actions_dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(Error::file_system(
+                    &self.actions_dir,
+                    "read actions directory",
+                    e,
+                ))
+            }
+        };
+
+        let mut hashes: Vec<String> = read_dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                name.strip_suffix(".json").map(str::to_string)
+            })
+            .collect();
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    /// The readable actions stored on disk
+    pub fn entries(&self) -> Result<Vec<ActionEntry>> {
+        Ok(self
+            .stored_hashes()?
+            .into_iter()
+            .filter_map(|hash| {
+                let path = self.action_path(&hash);
+                let stored = read_stored_action(&path).ok()?;
+                let record_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                let output_bytes: u64 = output_hashes(&stored.result)
+                    .iter()
+                    .filter_map(|hash| self.cas.get_metadata(hash))
+                    .map(|metadata| metadata.size)
+                    .sum();
+                Some(ActionEntry {
+                    hash,
+                    task_name: stored.task_name,
+                    package: stored.package,
+                    executed_at: stored.result.executed_at,
+                    size_bytes: record_bytes + output_bytes,
+                })
+            })
+            .collect())
+    }
+
+    /// Hashes of the CAS objects holding the outputs of the actions stored
+    /// on disk
+    pub fn referenced_objects(&self) -> Result<HashSet<String>> {
+        let mut referenced = HashSet::new();
+        for hash in self.stored_hashes()? {
+            if let Ok(stored) = read_stored_action(&self.action_path(&hash)) {
+                referenced.extend(output_hashes(&stored.result));
+            }
+        }
+        Ok(referenced)
+    }
+
+    /// Remove a stored action
+    ///
+    /// Its outputs stay in CAS, where other processes may have stored the
+    /// same content, until they are collected against
+    /// [`Self::referenced_objects`].
+    pub fn remove_entry(&self, hash: &str) -> Result<()> {
+        self.result_cache.remove(hash);
+
+        let path = self.action_path(hash);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(Error::file_system(&path, "remove cache entry", e)),
+        }
+    }
+
+    /// Check that a stored action is readable, correctly signed and that its
+    /// outputs are intact, returning what is wrong otherwise
+    pub fn verify_entry(&self, hash: &str) -> std::result::Result<(), String> {
+        let stored =
+            read_stored_action(&self.action_path(hash)).map_err(|e| format!("unreadable: {e}"))?;
+        let signed =
+            signed_action(&stored.result).ok_or_else(|| "not a signed entry".to_string())?;
+        match self.signer.verify(&signed) {
+            Ok(true) => {}
+            Ok(false) => return Err("signature is invalid or expired".to_string()),
+            Err(e) => return Err(format!("signature cannot be checked: {e}")),
+        }
+
+        for output in output_hashes(&stored.result) {
+            match self.cas.verify(&output) {
+                Ok(true) => {}
+                Ok(false) => return Err(format!("output {output} is corrupt")),
+                Err(_) => return Err(format!("output {output} is missing")),
+            }
+        }
+        Ok(())
+    }
+
+    /// The artifact `name` stored by the cached action `hash`
+    pub fn artifact(&self, hash: &str, name: &str) -> Result<StoredArtifact> {
+        let result = self.get_cached_action_result(hash).ok_or_else(|| {
+            Error::configuration(format!("No valid cached result with key {hash}"))
+        })?;
+        if result.exit_code != 0 {
+            return Err(Error::configuration(format!(
+                "The run cached under {hash} failed with exit code {}",
+                result.exit_code
+            )));
+        }
+        StoredArtifact::find(hash, &result, name).ok_or_else(|| {
+            Error::configuration(format!(
+                "The result cached under {hash} did not store artifact '{name}'"
+            ))
+        })
+    }
+
+    /// The artifact `name` of the newest successful cached run of
+    /// `task_name` that produced it at `path`
+    pub fn latest_artifact(
+        &self,
+        task_name: &str,
+        name: &str,
+        path: &Path,
+    ) -> Result<StoredArtifact> {
+        let mut entries: Vec<ActionEntry> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.task_name == task_name)
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.executed_at));
+        entries
+            .iter()
+            .find_map(|entry| {
+                let result = self.get_cached_action_result(&entry.hash)?;
+                if result.exit_code != 0 {
+                    return None;
+                }
+                StoredArtifact::find(&entry.hash, &result, name)
+                    .filter(|artifact| artifact.path == path)
+            })
+            .ok_or_else(|| {
+                Error::configuration(format!(
+                    "No cached run of '{task_name}' stored artifact '{name}'; run the task with caching enabled first"
+                ))
+            })
+    }
+
+    /// Copy a stored artifact into the directory `out`, returning the files
+    /// written
+    pub fn restore_artifact(&self, artifact: &StoredArtifact, out: &Path) -> Result<Vec<PathBuf>> {
+        artifact.restore(&self.cas, out)
+    }
+
+    fn action_path(&self, hash: &str) -> PathBuf {
+        self.actions_dir.join(format!("{hash}.json"))
+    }
+
+    /// Lock the lease of an action, until the returned file is dropped
+    ///
+    /// Leases are shared locks, so that processes executing the same action
+    /// do not wait on each other; maintenance only checks that none is held.
+    fn lease(&self, hash: &str) -> Option<fs::File> {
+        let file = fs::create_dir_all(&self.leases_dir)
+            .and_then(|()| {
+                OpenOptions::new()
+                    .create(true)
+                    .truncate(false)
+                    .write(true)
+                    .open(self.leases_dir.join(hash))
+            })
+            .and_then(|file| file.lock_shared().map(|()| file));
+        match file {
+            Ok(file) => Some(file),
+            Err(e) => {
+                log::warn!("Failed to lease action {hash}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Whether a process holds the lease of an action
+    fn is_leased(&self, hash: &str) -> bool {
+        let Ok(file) = fs::File::open(self.leases_dir.join(hash)) else {
+            return false;
+        };
+        match file.try_lock_exclusive() {
+            Ok(()) => {
+                let _ = FileExt::unlock(&file);
+                false
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// Store a cached action on disk
+    fn persist(
+        &self,
+        components: &ActionComponents,
+        result: &crate::types::CachedTaskResult,
+    ) -> Result<()> {
+        fs::create_dir_all(&self.actions_dir)
+            .map_err(|e| Error::file_system(&self.actions_dir, "create actions directory", e))?;
+        let stored = StoredAction {
+            task_name: components.task_name.clone(),
+            package: components.package.clone(),
+            result: result.clone(),
+        };
+        let json = serde_json::to_string(&stored).map_err(|e| Error::Json {
+            message: "Failed to serialize cache entry".to_string(),
+            source: e,
+        })?;
+        write_atomic_string(&self.action_path(&result.cache_key), &json)
+    }
+
+    /// Load a cached action stored by an earlier run
+    fn load(&self, hash: &str) -> Option<crate::types::CachedTaskResult> {
+        let stored = read_stored_action(&self.action_path(hash)).ok()?;
+        self.result_cache
+            .insert(hash.to_string(), stored.result.clone())
+            .ok()?;
+        Some(stored.result)
+    }
+}
+
+fn read_stored_action(path: &Path) -> Result<StoredAction> {
+    let content =
+        fs::read_to_string(path).map_err(|e| Error::file_system(path, "read cache entry", e))?;
+    serde_json::from_str(&content).map_err(|e| Error::Json {
+        message: "Failed to parse cache entry".to_string(),
+        source: e,
+    })
+}
+
+/// The signed action result kept in the stdout of a cached result
+fn signed_action(
+    cached: &crate::types::CachedTaskResult,
+) -> Option<SignedCacheEntry<ActionResult>> {
+    let stdout = std::str::from_utf8(cached.stdout.as_deref()?).ok()?;
+    serde_json::from_str(stdout).ok()
+}
+
+/// Hashes of the CAS objects holding the outputs of a cached result
+fn output_hashes(cached: &crate::types::CachedTaskResult) -> Vec<String> {
+    signed_action(cached)
+        .map(|signed| result_outputs(&signed.data))
+        .unwrap_or_default()
+}
+
+/// Hashes of the CAS objects holding the outputs of a result
+fn result_outputs(result: &ActionResult) -> Vec<String> {
+    result
+        .stdout_hash
+        .iter()
+        .chain(&result.stderr_hash)
+        .chain(result.output_files.values())
+        .cloned()
+        .collect()
+}
+
+/// Serialize a map in the order of its keys, so that signing a result
+/// does not depend on the order of a `HashMap`
+fn sorted<K, V, S>(map: &HashMap<K, V>, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+/// The root of the workspace `working_dir` is in, and the path of the
+/// package there from it, `.` for the root itself
+fn workspace_package(working_dir: &Path) -> Option<(PathBuf, String)> {
+    let root = WorkspaceConfig::find_root(working_dir)?;
+    let relative = working_dir.strip_prefix(&root).ok()?;
+    let package = if relative.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        relative.to_string_lossy().replace('\\', "/")
+    };
+    Some((root, package))
 }
 
 /// Compute hash of task definition for cache key
@@ -406,33 +890,6 @@ fn compute_hash(data: &[u8]) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-/// Compute SHA256 hash of a file using streaming
-async fn compute_file_hash(file_path: &Path) -> Result<String> {
-    use tokio::io::AsyncReadExt;
-
-    let mut file = tokio::fs::File::open(file_path)
-        .await
-        .map_err(|e| Error::file_system(file_path, "open file for hashing", e))?;
-
-    let mut hasher = Sha256::new();
-    let mut buffer = vec![0u8; 8192];
-
-    loop {
-        let bytes_read = file
-            .read(&mut buffer)
-            .await
-            .map_err(|e| Error::file_system(file_path, "read file chunk for hashing", e))?;
-
-        if bytes_read == 0 {
-            break;
-        }
-
-        hasher.update(&buffer[..bytes_read]);
-    }
-
-    Ok(format!("{:x}", hasher.finalize()))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -456,8 +913,10 @@ mod tests {
             dependencies: vec![],
             working_directory: temp_dir.path().to_path_buf(),
             shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
             inputs: vec![],
             outputs: vec![],
+            artifacts: HashMap::new(),
             security: None,
             cache: TaskCache {
                 enabled: true,
@@ -465,6 +924,7 @@ mod tests {
                 env_filter: None,
             },
             timeout: Duration::from_secs(30),
+            max_output_bytes: None,
         };
 
         let digest = cache
@@ -493,8 +953,10 @@ mod tests {
             dependencies: vec![],
             working_directory: temp_dir.path().to_path_buf(),
             shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
             inputs: vec![],
             outputs: vec![],
+            artifacts: HashMap::new(),
             security: None,
             cache: TaskCache {
                 enabled: true,
@@ -502,6 +964,7 @@ mod tests {
                 env_filter: None,
             },
             timeout: Duration::from_secs(30),
+            max_output_bytes: None,
         };
 
         let digest = cache
@@ -517,6 +980,8 @@ mod tests {
                     stdout_hash: Some("hello\n".to_string()),
                     stderr_hash: None,
                     output_files: HashMap::new(),
+                    artifacts: HashMap::new(),
+                    executables: Vec::new(),
                     executed_at: SystemTime::now(),
                     duration_ms: 10,
                 })
@@ -536,6 +1001,121 @@ mod tests {
         assert_eq!(stats.writes, 1);
     }
 
+    #[tokio::test]
+    async fn test_stored_actions() {
+        let temp_dir = TempDir::new().unwrap();
+        let cas =
+            Arc::new(ContentAddressedStore::new(temp_dir.path().to_path_buf(), 4096).unwrap());
+        let cache = ActionCache::new(Arc::clone(&cas), 0, temp_dir.path()).unwrap();
+
+        let task_definition = TaskDefinition {
+            name: "ci.test".to_string(),
+            description: None,
+            execution_mode: TaskExecutionMode::Command {
+                command: "echo hello".to_string(),
+            },
+            dependencies: vec![],
+            working_directory: temp_dir.path().to_path_buf(),
+            shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
+            inputs: vec![],
+            outputs: vec![],
+            artifacts: HashMap::new(),
+            security: None,
+            cache: TaskCache {
+                enabled: true,
+                key: None,
+                env_filter: None,
+            },
+            timeout: Duration::from_secs(30),
+            max_output_bytes: None,
+        };
+        let digest = cache
+            .compute_digest("ci.test", &task_definition, temp_dir.path(), HashMap::new())
+            .await
+            .unwrap();
+        cache
+            .execute_action(&digest, || async {
+                Ok(ActionResult {
+                    exit_code: 0,
+                    stdout_hash: Some("hello\n".to_string()),
+                    stderr_hash: None,
+                    output_files: HashMap::new(),
+                    artifacts: HashMap::new(),
+                    executables: Vec::new(),
+                    executed_at: SystemTime::now(),
+                    duration_ms: 10,
+                })
+            })
+            .await
+            .unwrap();
+
+        // A later run finds the stored action
+        let later = ActionCache::new(Arc::clone(&cas), 0, temp_dir.path()).unwrap();
+        assert!(later.get_cached_result(&digest).await.is_some());
+
+        let entries = later.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].hash, digest.hash);
+        assert_eq!(entries[0].task_name, "ci.test");
+        assert!(later.verify_entry(&digest.hash).is_ok());
+
+        later.remove_entry(&digest.hash).unwrap();
+        assert!(later.stored_hashes().unwrap().is_empty());
+        assert!(later.get_cached_result(&digest).await.is_none());
+        assert!(later.referenced_objects().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_workspace_package_digest() {
+        let temp_dir = TempDir::new().unwrap();
+        let cas =
+            Arc::new(ContentAddressedStore::new(temp_dir.path().join("cache"), 4096).unwrap());
+        let cache = ActionCache::new(cas, 0, &temp_dir.path().join("cache")).unwrap();
+
+        // The same workspace checked out twice, with two packages
+        let mut digests = Vec::new();
+        for (checkout, package) in [("a", "apps/web"), ("b", "apps/web"), ("a", "apps/api")] {
+            let root = temp_dir.path().join(checkout);
+            let dir = root.join(package);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                root.join(cuenv_core::constants::WORKSPACE_FILENAME),
+                "package workspace\n\npackages: [\"apps/*\"]\n",
+            )
+            .unwrap();
+            let task_definition = TaskDefinition {
+                name: "build".to_string(),
+                description: None,
+                execution_mode: TaskExecutionMode::Command {
+                    command: "make".to_string(),
+                },
+                dependencies: vec![],
+                working_directory: dir.clone(),
+                shell: "sh".to_string(),
+                shell_args: vec!["-e".to_string(), "-c".to_string()],
+                inputs: vec![],
+                outputs: vec![],
+                artifacts: HashMap::new(),
+                security: None,
+                cache: TaskCache::default(),
+                timeout: Duration::from_secs(30),
+                max_output_bytes: None,
+            };
+            digests.push(
+                cache
+                    .compute_digest("build", &task_definition, &dir, HashMap::new())
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        assert_eq!(digests[0].components.package.as_deref(), Some("apps/web"));
+        assert_eq!(digests[0].components.working_dir, PathBuf::from("apps/web"));
+        assert_eq!(digests[0].hash, digests[1].hash);
+        assert_ne!(digests[0].hash, digests[2].hash);
+    }
+
     #[tokio::test]
     async fn test_concurrent_action_execution() {
         let temp_dir = TempDir::new().unwrap();
@@ -552,8 +1132,10 @@ mod tests {
             dependencies: vec![],
             working_directory: temp_dir.path().to_path_buf(),
             shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
             inputs: vec![],
             outputs: vec![],
+            artifacts: HashMap::new(),
             security: None,
             cache: TaskCache {
                 enabled: true,
@@ -561,6 +1143,7 @@ mod tests {
                 env_filter: None,
             },
             timeout: Duration::from_secs(30),
+            max_output_bytes: None,
         };
 
         let digest = cache
@@ -583,6 +1166,8 @@ mod tests {
                         stdout_hash: Some("hello from task 1\n".to_string()),
                         stderr_hash: None,
                         output_files: HashMap::new(),
+                        artifacts: HashMap::new(),
+                        executables: Vec::new(),
                         executed_at: SystemTime::now(),
                         duration_ms: 100,
                     })
@@ -608,6 +1193,8 @@ mod tests {
                         stdout_hash: Some("hello from task 2\n".to_string()),
                         stderr_hash: None,
                         output_files: HashMap::new(),
+                        artifacts: HashMap::new(),
+                        executables: Vec::new(),
                         executed_at: SystemTime::now(),
                         duration_ms: 10,
                     })
@@ -636,4 +1223,25 @@ mod tests {
         println!("Cache stats: {stats:?}");
         assert_eq!(stats.writes, 1);
     }
+
+    #[test]
+    fn test_leases() {
+        let temp_dir = TempDir::new().unwrap();
+        let cas =
+            Arc::new(ContentAddressedStore::new(temp_dir.path().to_path_buf(), 4096).unwrap());
+        let cache = ActionCache::new(Arc::clone(&cas), 0, temp_dir.path()).unwrap();
+        assert!(!cache.is_in_flight("abc"));
+        assert!(!cache.has_leases());
+
+        // Another cache stands in for another process executing the action
+        let other = ActionCache::new(cas, 0, temp_dir.path()).unwrap();
+        let lease = other.lease("abc").unwrap();
+        assert!(cache.is_in_flight("abc"));
+        assert!(cache.has_leases());
+        assert!(!cache.is_in_flight("def"));
+
+        drop(lease);
+        assert!(!cache.is_in_flight("abc"));
+        assert!(!cache.has_leases());
+    }
 }
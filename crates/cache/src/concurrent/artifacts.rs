@@ -0,0 +1,336 @@
+//! Named outputs of cached actions
+//!
+//! When a task declaring named artifacts succeeds, the files of each
+//! artifact are stored in CAS with its result, keyed in `output_files` by
+//! the artifact's name, or by `<name>/<path>` for the files of a directory.
+//! An artifact can then be copied out of whichever cached run produced it,
+//! without running the task again.
+
+use super::action::ActionResult;
+use crate::content_addressed_store::ContentAddressedStore;
+use cuenv_core::{Error, Result};
+use cuenv_utils::atomic_file::write_atomic;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// A file of a stored artifact
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredFile {
+    /// Hash of its content in CAS
+    pub hash: String,
+    pub executable: bool,
+}
+
+/// A named artifact stored with a cached action
+#[derive(Debug, Clone)]
+pub struct StoredArtifact {
+    pub name: String,
+    /// Hash of the action that produced it
+    pub action: String,
+    pub executed_at: SystemTime,
+    /// Where the task produced it
+    pub path: PathBuf,
+    /// Whether it is a directory rather than a file
+    pub directory: bool,
+    /// Its files by their path inside the directory, or the file itself
+    /// under an empty path
+    pub files: BTreeMap<PathBuf, StoredFile>,
+}
+
+impl StoredArtifact {
+    /// The artifact `name` among the outputs `result` of the action `action`,
+    /// if its files were stored
+    pub(super) fn find(action: &str, result: &ActionResult, name: &str) -> Option<Self> {
+        let path = result.artifacts.get(name)?;
+        let executable = |key: &str| result.executables.iter().any(|file| file == key);
+
+        let mut files = BTreeMap::new();
+        let directory = match result.output_files.get(name) {
+            Some(hash) => {
+                files.insert(
+                    PathBuf::new(),
+                    StoredFile {
+                        hash: hash.clone(),
+                        executable: executable(name),
+                    },
+                );
+                false
+            }
+            None => {
+                let prefix = format!("{name}/");
+                for (key, hash) in &result.output_files {
+                    if let Some(relative) = key.strip_prefix(&prefix) {
+                        files.insert(
+                            PathBuf::from(relative),
+                            StoredFile {
+                                hash: hash.clone(),
+                                executable: executable(key),
+                            },
+                        );
+                    }
+                }
+                true
+            }
+        };
+        if files.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.to_string(),
+            action: action.to_string(),
+            executed_at: result.executed_at,
+            path: path.clone(),
+            directory,
+            files,
+        })
+    }
+
+    /// Copy the artifact from `cas` into the directory `out`: a file under
+    /// its own name, the files of a directory under their paths inside it
+    pub(super) fn restore(&self, cas: &ContentAddressedStore, out: &Path) -> Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+        for (relative, file) in &self.files {
+            let target = if self.directory {
+                if !relative
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+                {
+                    return Err(Error::configuration(format!(
+                        "Artifact '{}' holds the path '{}' outside of it",
+                        self.name,
+                        relative.display()
+                    )));
+                }
+                out.join(relative)
+            } else {
+                out.join(self.path.file_name().unwrap_or(OsStr::new(&self.name)))
+            };
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| Error::file_system(parent, "create artifact directory", e))?;
+            }
+            write_atomic(&target, &cas.retrieve(&file.hash)?)?;
+            if file.executable {
+                set_executable(&target)?;
+            }
+            written.push(target);
+        }
+        Ok(written)
+    }
+}
+
+/// Store the files of `artifacts` in `cas`, returning their hashes by key
+/// in `output_files` and the keys of the executable ones
+///
+/// Artifacts the task did not produce are left out. When a file cannot be
+/// stored, those stored before it are released again.
+pub(super) fn store(
+    cas: &ContentAddressedStore,
+    artifacts: &HashMap<String, PathBuf>,
+) -> Result<(HashMap<String, String>, Vec<String>)> {
+    let mut stored = HashMap::new();
+    let mut executables = Vec::new();
+    let sorted: BTreeMap<&String, &PathBuf> = artifacts.iter().collect();
+    for (name, path) in sorted {
+        let files = match artifact_files(name, path) {
+            Ok(files) => files,
+            Err(e) => {
+                release(cas, &stored);
+                return Err(e);
+            }
+        };
+        for (key, file) in files {
+            let hash = fs::File::open(&file)
+                .map_err(|e| Error::file_system(&file, "open artifact", e))
+                .and_then(|reader| cas.store(reader));
+            match hash {
+                Ok(hash) => {
+                    if fs::metadata(&file).is_ok_and(|metadata| is_executable(&metadata)) {
+                        executables.push(key.clone());
+                    }
+                    stored.insert(key, hash);
+                }
+                Err(e) => {
+                    release(cas, &stored);
+                    return Err(e);
+                }
+            }
+        }
+    }
+    Ok((stored, executables))
+}
+
+/// The files of the artifact `name` at `path` by their key, none when the
+/// task did not produce it
+fn artifact_files(name: &str, path: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            log::debug!("Artifact '{name}' was not produced at {}", path.display());
+            return Ok(Vec::new());
+        }
+        Err(e) => return Err(Error::file_system(path, "read artifact metadata", e)),
+    };
+    if !metadata.is_dir() {
+        return Ok(vec![(name.to_string(), path.to_path_buf())]);
+    }
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(path).sort_by_file_name() {
+        let entry = entry.map_err(|e| {
+            Error::file_system(path, "read artifact directory", std::io::Error::other(e))
+        })?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+        let key = format!("{name}/{}", relative.to_string_lossy().replace('\\', "/"));
+        files.push((key, entry.into_path()));
+    }
+    Ok(files)
+}
+
+fn release(cas: &ContentAddressedStore, stored: &HashMap<String, String>) {
+    for hash in stored.values() {
+        if let Err(e) = cas.release(hash) {
+            log::warn!("Failed to release artifact file {hash}: {e}");
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| Error::file_system(path, "make artifact executable", e))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::action::ActionCache;
+    use cuenv_core::{TaskCache, TaskDefinition, TaskExecutionMode};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_artifacts_are_stored_and_restored() {
+        let cache_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let binary = project.path().join("target/app");
+        let docs = project.path().join("docs");
+        fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        fs::create_dir_all(docs.join("api")).unwrap();
+        fs::write(&binary, "#!/bin/sh\necho app\n").unwrap();
+        set_executable(&binary).unwrap();
+        fs::write(docs.join("index.html"), "index").unwrap();
+        fs::write(docs.join("api/a.html"), "a").unwrap();
+        let artifacts = HashMap::from([
+            ("binary".to_string(), binary.clone()),
+            ("docs".to_string(), docs.clone()),
+            ("missing".to_string(), project.path().join("missing")),
+        ]);
+
+        let cas = Arc::new(ContentAddressedStore::new(cache_dir.path().to_path_buf(), 4).unwrap());
+        let cache = ActionCache::new(Arc::clone(&cas), 0, cache_dir.path()).unwrap();
+        let task_definition = TaskDefinition {
+            name: "build".to_string(),
+            description: None,
+            execution_mode: TaskExecutionMode::Command {
+                command: "make".to_string(),
+            },
+            dependencies: vec![],
+            working_directory: project.path().to_path_buf(),
+            shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
+            inputs: vec![],
+            outputs: vec![],
+            artifacts: artifacts.clone(),
+            security: None,
+            cache: TaskCache {
+                enabled: true,
+                key: None,
+                env_filter: None,
+            },
+            timeout: Duration::from_secs(30),
+            max_output_bytes: None,
+        };
+        let digest = cache
+            .compute_digest("build", &task_definition, project.path(), HashMap::new())
+            .await
+            .unwrap();
+        cache
+            .execute_action(&digest, || async {
+                Ok(ActionResult {
+                    exit_code: 0,
+                    stdout_hash: None,
+                    stderr_hash: None,
+                    output_files: HashMap::new(),
+                    artifacts,
+                    executables: Vec::new(),
+                    executed_at: SystemTime::now(),
+                    duration_ms: 10,
+                })
+            })
+            .await
+            .unwrap();
+
+        // Read back from disk, with the signature checked
+        let later = ActionCache::new(Arc::clone(&cas), 0, cache_dir.path()).unwrap();
+        assert!(later.verify_entry(&digest.hash).is_ok());
+        let stored = later.latest_artifact("build", "binary", &binary).unwrap();
+        assert_eq!(stored.action, digest.hash);
+        assert!(!stored.directory);
+        assert!(later.artifact(&digest.hash, "missing").is_err());
+        assert!(later.latest_artifact("build", "binary", &docs).is_err());
+
+        let out = TempDir::new().unwrap();
+        let written = later.restore_artifact(&stored, out.path()).unwrap();
+        assert_eq!(written, vec![out.path().join("app")]);
+        assert_eq!(
+            fs::read_to_string(out.path().join("app")).unwrap(),
+            "#!/bin/sh\necho app\n"
+        );
+        assert_eq!(
+            is_executable(&fs::metadata(out.path().join("app")).unwrap()),
+            cfg!(unix)
+        );
+
+        let stored = later.artifact(&digest.hash, "docs").unwrap();
+        assert!(stored.directory);
+        later.restore_artifact(&stored, out.path()).unwrap();
+        assert_eq!(
+            fs::read_to_string(out.path().join("api/a.html")).unwrap(),
+            "a"
+        );
+
+        // Removing the entry leaves the stored files unreferenced
+        later.remove_entry(&digest.hash).unwrap();
+        let referenced = later.referenced_objects().unwrap();
+        cas.remove_unreferenced(&referenced, Duration::ZERO)
+            .unwrap();
+        assert_eq!(cas.object_count(), 0);
+    }
+}
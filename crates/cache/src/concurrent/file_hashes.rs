@@ -0,0 +1,134 @@
+//! Hashes of input files, remembered for as long as the process runs
+//!
+//! A file is only read again once its size or modification time changed,
+//! so a long-running process such as `cuenv daemon` hashes each input once
+//! per change instead of once per run. Files modified within the last
+//! [`RACY_WINDOW`] are not remembered: a write landing in the same tick of
+//! the file system's clock as the hash would otherwise go unnoticed.
+
+use cuenv_core::{Error, Result};
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+/// How old a modification must be for the hash of its file to be reused
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// What identifies the content of a file without reading it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    len: u64,
+    modified: SystemTime,
+}
+
+/// The hash of each file, with the stamp it had when read
+static HASHES: OnceLock<DashMap<PathBuf, (Stamp, String)>> = OnceLock::new();
+
+fn hashes() -> &'static DashMap<PathBuf, (Stamp, String)> {
+    HASHES.get_or_init(DashMap::new)
+}
+
+/// The SHA256 of the content of `file_path`, read again only when it changed
+pub(crate) async fn hash(file_path: &Path) -> Result<String> {
+    let metadata = tokio::fs::metadata(file_path)
+        .await
+        .map_err(|e| Error::file_system(file_path, "read metadata for hashing", e))?;
+    let stamp = metadata.modified().ok().map(|modified| Stamp {
+        len: metadata.len(),
+        modified,
+    });
+
+    if let Some(stamp) = stamp {
+        if let Some(known) = hashes().get(file_path) {
+            if known.0 == stamp {
+                return Ok(known.1.clone());
+            }
+        }
+    }
+
+    let hash = read_hash(file_path).await?;
+    let settled = stamp.filter(|stamp| {
+        SystemTime::now()
+            .duration_since(stamp.modified)
+            .is_ok_and(|age| age >= RACY_WINDOW)
+    });
+    match settled {
+        Some(stamp) => {
+            hashes().insert(file_path.to_path_buf(), (stamp, hash.clone()));
+        }
+        None => {
+            hashes().remove(file_path);
+        }
+    }
+    Ok(hash)
+}
+
+/// Compute SHA256 hash of a file using streaming
+async fn read_hash(file_path: &Path) -> Result<String> {
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(file_path)
+        .await
+        .map_err(|e| Error::file_system(file_path, "open file for hashing", e))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 8192];
+
+    loop {
+        let bytes_read = file
+            .read(&mut buffer)
+            .await
+            .map_err(|e| Error::file_system(file_path, "read file chunk for hashing", e))?;
+
+        if bytes_read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn write(path: &Path, content: &str, modified: SystemTime) {
+        std::fs::write(path, content).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_files_are_read_again_once_changed() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("input.txt");
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+
+        write(&file, "a", an_hour_ago);
+        let first = hash(&file).await.unwrap();
+        assert_eq!(first, read_hash(&file).await.unwrap());
+
+        // Same size and modification time: the remembered hash is used
+        write(&file, "b", an_hour_ago);
+        assert_eq!(hash(&file).await.unwrap(), first);
+
+        write(&file, "b", an_hour_ago + Duration::from_secs(1));
+        let second = hash(&file).await.unwrap();
+        assert_ne!(second, first);
+
+        // Modified just now, so not remembered
+        write(&file, "c", SystemTime::now());
+        let third = hash(&file).await.unwrap();
+        assert_ne!(third, second);
+        assert!(hashes().get(&file).is_none());
+    }
+}
@@ -4,6 +4,8 @@
 //! using DashMap for concurrent access without explicit locking.
 
 pub mod action;
+pub mod artifacts;
+mod file_hashes;
 
 use crate::CachedTaskResult;
 use cuenv_core::{Error, Result};
@@ -241,7 +243,7 @@ impl ConcurrentCache {
                 .collect();
 
             // Sort by age (oldest first - largest duration)
-            all_entries.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+            all_entries.sort_unstable_by_key(|b| std::cmp::Reverse(b.1));
 
             for (key, _, size) in all_entries {
                 if freed_bytes >= needed_to_free {
@@ -346,6 +348,7 @@ mod tests {
             stdout: None,
             stderr: None,
             output_files: HashMap::new(),
+            artifacts: HashMap::new(),
         };
 
         // Insert
@@ -384,6 +387,7 @@ mod tests {
                             stdout: None,
                             stderr: None,
                             output_files: HashMap::new(),
+                            artifacts: HashMap::new(),
                         };
 
                         // Write
@@ -422,6 +426,7 @@ mod tests {
                     ("file1.txt".to_string(), "hash1".to_string()),
                     ("file2.txt".to_string(), "hash2".to_string()),
                 ]),
+                artifacts: HashMap::new(),
             };
             cache.insert(format!("key_{i}"), result).unwrap();
         }
@@ -445,6 +450,7 @@ mod tests {
                 stdout: None,
                 stderr: None,
                 output_files: HashMap::new(),
+                artifacts: HashMap::new(),
             };
             cache.insert(format!("key_{i}"), result).unwrap();
         }
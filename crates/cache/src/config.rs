@@ -1,6 +1,7 @@
 //! Cache configuration management with precedence and validation
 use super::{keys::CacheKeyFilterConfig, CacheMode};
 use crate::errors::{Error, RecoveryHint, Result, SerializationOp};
+use cuenv_config::{CacheSettings, UserConfig};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -20,6 +21,8 @@ pub struct CacheConfig {
     pub env_filter: CacheKeyFilterConfig,
     /// Task-specific environment filtering configurations
     pub task_env_filters: HashMap<String, CacheKeyFilterConfig>,
+    /// Endpoint of a remote cache to share task results through
+    pub remote: Option<String>,
 }
 
 impl Default for CacheConfig {
@@ -33,10 +36,31 @@ impl Default for CacheConfig {
             inline_threshold: 1024, // 1KB
             env_filter: CacheKeyFilterConfig::default(),
             task_env_filters: HashMap::new(),
+            remote: None,
         }
     }
 }
 
+impl From<&GlobalCacheConfig> for CacheConfig {
+    fn from(global: &GlobalCacheConfig) -> Self {
+        let mut config = Self::default();
+        if let Some(base_dir) = &global.base_dir {
+            config.base_dir = base_dir.clone();
+        }
+        if let Some(max_size) = global.max_size {
+            config.max_size = max_size;
+        }
+        if let Some(inline_threshold) = global.inline_threshold {
+            config.inline_threshold = inline_threshold;
+        }
+        if let Some(env_filter) = &global.env_filter {
+            config.env_filter = env_filter.clone();
+        }
+        config.mode = global.mode;
+        config
+    }
+}
+
 /// Global cache configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalCacheConfig {
@@ -183,8 +207,32 @@ pub struct CacheConfigLoader;
 impl CacheConfigLoader {
     /// Load configuration with full precedence handling
     pub fn load() -> Result<CacheConfiguration> {
+        Self::load_with(None)
+    }
+
+    /// Load configuration for a package, with `settings`, the cache settings
+    /// of the package and its workspace, over the user configuration
+    pub fn load_for(settings: &CacheSettings) -> Result<CacheConfiguration> {
+        Self::load_with(Some(settings))
+    }
+
+    fn load_with(settings: Option<&CacheSettings>) -> Result<CacheConfiguration> {
         let mut config = Self::load_defaults()?;
 
+        // Layer the [cache] table of the user's config.toml
+        if let Some(user_config) = Self::load_from_user_config() {
+            config = Self::merge_config(
+                config,
+                user_config,
+                ConfigSource::ConfigFile(UserConfig::path()),
+            )?;
+        }
+
+        // Then the settings of the workspace and the package
+        if let Some(max_size) = settings.and_then(|settings| settings.max_size) {
+            config.global.max_size = Some(max_size);
+        }
+
         // Try to load from config file
         if let Some(file_config) = Self::load_from_config_file()? {
             config = Self::merge_config(
@@ -215,6 +263,26 @@ impl CacheConfigLoader {
         })
     }
 
+    /// Load configuration from the [cache] table of the user configuration
+    fn load_from_user_config() -> Option<CacheConfiguration> {
+        let cache = &UserConfig::get().cache;
+        if cache.enabled.is_none() && cache.max_size.is_none() {
+            return None;
+        }
+
+        let mut global = GlobalCacheConfig::default();
+        if let Some(enabled) = cache.enabled {
+            global.enabled = enabled;
+        }
+        global.max_size = cache.max_size;
+
+        Some(CacheConfiguration {
+            global,
+            task_configs: HashMap::new(),
+            source: ConfigSource::ConfigFile(UserConfig::path()),
+        })
+    }
+
     /// Load configuration from config file
     fn load_from_config_file() -> Result<Option<CacheConfiguration>> {
         let config_path = Self::get_config_file_path()?;
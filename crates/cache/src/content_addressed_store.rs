@@ -9,6 +9,7 @@ use cuenv_utils::atomic_file::{write_atomic, write_atomic_string};
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
 use std::io::Read;
 use std::path::PathBuf;
@@ -107,7 +108,9 @@ impl ContentAddressedStore {
         // Check if already exists
         if let Some(mut entry) = self.index.get_mut(&hash) {
             entry.ref_count += 1;
+            let inlined = entry.inlined;
             drop(entry); // Release the lock before persisting
+            self.touch(&hash, inlined);
             self.persist_index()?;
             return Ok(hash);
         }
@@ -236,6 +239,25 @@ impl ContentAddressedStore {
         self.total_bytes.load(Ordering::Relaxed)
     }
 
+    /// Get the number of objects stored
+    pub fn object_count(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Check that an object still matches its hash, without removing it
+    /// when it does not
+    pub fn verify(&self, hash: &str) -> Result<bool> {
+        let inlined = self
+            .index
+            .get(hash)
+            .map(|metadata| metadata.inlined)
+            .ok_or_else(|| Error::configuration(format!("Object not found in CAS: {hash}")))?;
+        let path = self.path_of(hash, inlined);
+        let content =
+            fs::read(&path).map_err(|e| Error::file_system(&path, "read CAS object", e))?;
+        Ok(self.hash_content(&content) == hash)
+    }
+
     /// Clean up unreferenced objects
     pub fn garbage_collect(&self) -> Result<(usize, u64)> {
         let mut removed_count = 0;
@@ -271,6 +293,58 @@ impl ContentAddressedStore {
         Ok((removed_count, removed_bytes))
     }
 
+    /// Remove the objects not in `referenced` that were last stored longer
+    /// ago than `grace`
+    ///
+    /// Other processes share the store and keep their own reference counts,
+    /// so the index is merged with the one on disk first, and an object is
+    /// unused when no reference passed in names it rather than when this
+    /// process counts none. The grace period keeps objects another process
+    /// is storing again for an action it has not stored yet.
+    pub fn remove_unreferenced(
+        &self,
+        referenced: &HashSet<String>,
+        grace: Duration,
+    ) -> Result<(usize, u64)> {
+        let mut added_bytes = 0;
+        for metadata in self.read_index()? {
+            if !self.index.contains_key(&metadata.hash) {
+                added_bytes += metadata.size;
+                self.index.insert(metadata.hash.clone(), metadata);
+            }
+        }
+        self.total_bytes.fetch_add(added_bytes, Ordering::Relaxed);
+
+        let now = SystemTime::now();
+        let unused: Vec<ObjectMetadata> = self
+            .index
+            .iter()
+            .filter(|entry| !referenced.contains(entry.key()))
+            .map(|entry| entry.value().clone())
+            .collect();
+
+        let mut removed_count = 0;
+        let mut removed_bytes = 0u64;
+        for metadata in unused {
+            let path = self.path_of(&metadata.hash, metadata.inlined);
+            let age = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .map(|stored| now.duration_since(stored).unwrap_or_default());
+            if age.is_ok_and(|age| age < grace) {
+                continue;
+            }
+            self.remove_object(&metadata.hash)?;
+            removed_count += 1;
+            removed_bytes += metadata.size;
+        }
+
+        log::info!(
+            "CAS cleanup: removed {removed_count} unreferenced objects, freed {removed_bytes} bytes"
+        );
+
+        Ok((removed_count, removed_bytes))
+    }
+
     /// Check if garbage collection is needed and run it
     fn maybe_garbage_collect(&self) -> Result<()> {
         let should_gc = {
@@ -320,6 +394,27 @@ impl ContentAddressedStore {
         self.base_dir.join("inline").join(hash)
     }
 
+    fn path_of(&self, hash: &str, inlined: bool) -> PathBuf {
+        if inlined {
+            self.get_inline_path(hash)
+        } else {
+            self.get_object_path(hash)
+        }
+    }
+
+    /// Mark an object as stored now, so that cleanup in other processes
+    /// keeps it for the grace period
+    fn touch(&self, hash: &str, inlined: bool) {
+        let path = self.path_of(hash, inlined);
+        let touched = fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            log::debug!("Failed to touch CAS object {}: {e}", path.display());
+        }
+    }
+
     /// Remove an object from storage
     fn remove_object(&self, hash: &str) -> Result<()> {
         if let Some((_, metadata)) = self.index.remove(hash) {
@@ -347,22 +442,8 @@ impl ContentAddressedStore {
 
     /// Load index from disk
     fn load_index(&self) -> Result<()> {
-        let index_path = self.base_dir.join("index.json");
-        if !index_path.exists() {
-            return Ok(());
-        }
-
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| Error::file_system(&index_path, "read CAS index", e))?;
-
-        let index_data: Vec<ObjectMetadata> =
-            serde_json::from_str(&content).map_err(|e| Error::Json {
-                message: "Failed to parse CAS index".to_string(),
-                source: e,
-            })?;
-
         let mut total_bytes = 0u64;
-        for metadata in index_data {
+        for metadata in self.read_index()? {
             total_bytes += metadata.size;
             self.index.insert(metadata.hash.clone(), metadata);
         }
@@ -371,6 +452,22 @@ impl ContentAddressedStore {
         Ok(())
     }
 
+    /// The index on disk, as last persisted by any process
+    fn read_index(&self) -> Result<Vec<ObjectMetadata>> {
+        let index_path = self.base_dir.join("index.json");
+        if !index_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&index_path)
+            .map_err(|e| Error::file_system(&index_path, "read CAS index", e))?;
+
+        serde_json::from_str(&content).map_err(|e| Error::Json {
+            message: "Failed to parse CAS index".to_string(),
+            source: e,
+        })
+    }
+
     /// Persist index to disk
     fn persist_index(&self) -> Result<()> {
         let _guard = self.index_lock.write();
@@ -481,4 +578,31 @@ mod tests {
         assert_eq!(removed_count, 0); // Already removed by release
         assert_eq!(cas.total_bytes(), 0);
     }
+
+    #[test]
+    fn test_cas_remove_unreferenced() {
+        let temp_dir = TempDir::new().unwrap();
+        let cas = ContentAddressedStore::new(temp_dir.path().to_path_buf(), 100).unwrap();
+        let kept = cas.store(Cursor::new(b"kept")).unwrap();
+        let dropped = cas.store(Cursor::new(b"dropped")).unwrap();
+
+        // Another process stores an object this one never loaded
+        let other = ContentAddressedStore::new(temp_dir.path().to_path_buf(), 100).unwrap();
+        let theirs = other.store(Cursor::new(vec![b'x'; 200])).unwrap();
+
+        let referenced = HashSet::from([kept.clone()]);
+        let (removed, _) = cas
+            .remove_unreferenced(&referenced, Duration::from_secs(600))
+            .unwrap();
+        assert_eq!(removed, 0);
+
+        let (removed, _) = cas
+            .remove_unreferenced(&referenced, Duration::ZERO)
+            .unwrap();
+        assert_eq!(removed, 2);
+        assert!(cas.contains(&kept));
+        assert!(!cas.contains(&dropped));
+        assert!(!cas.contains(&theirs));
+        assert!(!cas.get_object_path(&theirs).exists());
+    }
 }
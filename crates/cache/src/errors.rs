@@ -610,7 +610,7 @@ impl From<serde_json::Error> for CacheError {
 /// Convert cache errors to core errors
 impl From<CacheError> for cuenv_core::Error {
     fn from(error: CacheError) -> Self {
-        cuenv_core::Error::configuration(error.to_string())
+        cuenv_core::Error::cache(error.to_string())
     }
 }
 
@@ -50,6 +50,8 @@ impl SmartDefaults {
             "BUILD_*",
             "BAZEL_*",
             "NIX_*",
+            // Versions pinned for asdf and mise
+            "CUENV_TOOL_VERSIONS",
             // Version control
             "GIT_*",
             "SVN_*",
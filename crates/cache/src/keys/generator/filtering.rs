@@ -95,24 +95,48 @@ impl FilterLogic {
         include_patterns: &[Regex],
         exclude_patterns: &[Regex],
     ) -> HashMap<String, String> {
-        let mut filtered = HashMap::new();
+        Self::filter_env_pairs(
+            task_name,
+            env_vars,
+            task_configs,
+            global_config,
+            task_patterns,
+            include_patterns,
+            exclude_patterns,
+        )
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
 
+    /// The environment variables to include, borrowed and sorted by name
+    pub fn filter_env_pairs<'a>(
+        task_name: &str,
+        env_vars: &'a HashMap<String, String>,
+        task_configs: &HashMap<String, CacheKeyFilterConfig>,
+        global_config: &CacheKeyFilterConfig,
+        task_patterns: &HashMap<String, (Vec<Regex>, Vec<Regex>)>,
+        include_patterns: &[Regex],
+        exclude_patterns: &[Regex],
+    ) -> Vec<(&'a str, &'a str)> {
         // Get task-specific config or fall back to global config
         let config = task_configs.get(task_name).unwrap_or(global_config);
 
-        for (key, value) in env_vars {
-            if Self::should_include_var(
-                key,
-                task_name,
-                config,
-                task_patterns,
-                include_patterns,
-                exclude_patterns,
-            ) {
-                filtered.insert(key.clone(), value.clone());
-            }
-        }
-
+        let mut filtered: Vec<(&str, &str)> = env_vars
+            .iter()
+            .filter(|(key, _)| {
+                Self::should_include_var(
+                    key,
+                    task_name,
+                    config,
+                    task_patterns,
+                    include_patterns,
+                    exclude_patterns,
+                )
+            })
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        filtered.sort_unstable();
         filtered
     }
 }
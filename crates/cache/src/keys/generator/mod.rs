@@ -107,11 +107,19 @@ impl CacheKeyGenerator {
         // Normalize working directory
         let normalized_dir = HashComputer::normalize_working_dir(working_dir);
 
-        // Filter environment variables
-        let filtered_env = self.filter_env_vars(task_name, env_vars);
+        // Filter environment variables, borrowing rather than copying them
+        let filtered_env = FilterLogic::filter_env_pairs(
+            task_name,
+            env_vars,
+            &self.task_configs,
+            &self.global_config,
+            &self.task_patterns,
+            &self.include_patterns,
+            &self.exclude_patterns,
+        );
 
         // Compute hash
-        let hash = HashComputer::compute_hash(
+        let hash = HashComputer::compute_hash_sorted(
             task_name,
             task_config_hash,
             &normalized_dir,
@@ -2,9 +2,13 @@
 
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
 
 /// Compute hash for cache key generation
+///
+/// Every input is fed to a single hasher as it is, so a key costs no
+/// intermediate strings beyond the hex of the result.
 pub struct HashComputer;
 
 impl HashComputer {
@@ -16,6 +20,31 @@ impl HashComputer {
         input_files: &HashMap<String, String>,
         env_vars: &HashMap<String, String>,
         command: Option<&str>,
+    ) -> String {
+        let mut sorted_env: Vec<(&str, &str)> = env_vars
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        sorted_env.sort_unstable();
+        Self::compute_hash_sorted(
+            task_name,
+            task_config_hash,
+            working_dir,
+            input_files,
+            &sorted_env,
+            command,
+        )
+    }
+
+    /// Like [`HashComputer::compute_hash`], with the environment variables
+    /// already sorted by name
+    pub fn compute_hash_sorted(
+        task_name: &str,
+        task_config_hash: &str,
+        working_dir: &str,
+        input_files: &HashMap<String, String>,
+        sorted_env: &[(&str, &str)],
+        command: Option<&str>,
     ) -> String {
         let mut hasher = Sha256::new();
 
@@ -34,16 +63,16 @@ impl HashComputer {
         }
 
         // Include input file hashes
-        let mut sorted_files: Vec<_> = input_files.iter().collect();
-        sorted_files.sort_by(|(a, _), (b, _)| a.cmp(b));
-        for (path, hash) in sorted_files {
-            hasher.update(path.as_bytes());
-            hasher.update(hash.as_bytes());
+        if !input_files.is_empty() {
+            let mut sorted_files: Vec<_> = input_files.iter().collect();
+            sorted_files.sort_unstable_by_key(|(a, _)| *a);
+            for (path, hash) in sorted_files {
+                hasher.update(path.as_bytes());
+                hasher.update(hash.as_bytes());
+            }
         }
 
         // Include environment variables
-        let mut sorted_env: Vec<_> = env_vars.iter().collect();
-        sorted_env.sort_by(|(a, _), (b, _)| a.cmp(b));
         for (key, value) in sorted_env {
             hasher.update(key.as_bytes());
             hasher.update(value.as_bytes());
@@ -111,6 +140,42 @@ impl HashComputer {
     }
 }
 
+/// The lowercase hex of a SHA-256 digest, kept on the stack
+#[derive(Clone, Copy)]
+pub struct HexDigest([u8; 64]);
+
+impl HexDigest {
+    pub fn new(digest: &[u8]) -> Self {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        let mut hex = [0; 64];
+        for (i, byte) in digest.iter().take(32).enumerate() {
+            hex[2 * i] = DIGITS[usize::from(byte >> 4)];
+            hex[2 * i + 1] = DIGITS[usize::from(byte & 0xf)];
+        }
+        Self(hex)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only ASCII digits are ever written
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+/// Feeds whatever is written to it into a hasher, so values can be
+/// serialized straight into a hash
+pub struct HashWriter<'a>(pub &'a mut Sha256);
+
+impl Write for HashWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +223,20 @@ mod tests {
         assert_ne!(hash1, hash3);
     }
 
+    #[test]
+    fn test_hex_digest_matches_formatting() {
+        let digest = Sha256::digest(b"cuenv");
+        assert_eq!(
+            HexDigest::new(&digest).as_str(),
+            format!("{digest:x}").as_str()
+        );
+
+        let mut hasher = Sha256::new();
+        let name = "nv";
+        write!(HashWriter(&mut hasher), "cue{name}").unwrap();
+        assert_eq!(hasher.finalize(), digest);
+    }
+
     #[test]
     fn test_normalize_working_dir() {
         assert_eq!(
@@ -7,6 +7,7 @@
 //! - Performance monitoring
 //! - Eviction policies
 //! - Streaming support
+//! - Sharing task results through a remote cache
 
 pub mod bridge;
 pub mod cleanup;
@@ -31,6 +32,7 @@ pub mod mode;
 pub mod monitored;
 pub mod monitoring;
 pub mod performance;
+pub mod remote;
 pub mod security;
 pub mod serialization;
 pub mod storage;
@@ -65,6 +67,7 @@ pub use mode::*;
 pub use monitored::MonitoredCache;
 pub use monitoring::CacheMonitor;
 pub use performance::*;
+pub use remote::{RemoteCache, RemoteStore};
 pub use security::*;
 pub use serialization::*;
 pub use storage::*;
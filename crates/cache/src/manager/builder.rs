@@ -9,6 +9,7 @@ use crate::config::CacheConfig;
 use crate::content_addressed_store::ContentAddressedStore;
 use crate::engine::CacheEngine;
 use crate::keys::{CacheKeyFilterConfig, CacheKeyGenerator};
+use crate::remote::RemoteCache;
 use crate::security::signing::CacheSigner;
 use cuenv_core::{Error, Result};
 use std::collections::HashMap;
@@ -103,6 +104,7 @@ impl CacheManagerBuilder {
                 inline_threshold: self.inline_threshold.unwrap_or(4096), // 4KB default
                 env_filter: self.env_filter.unwrap_or_default(),
                 task_env_filters: HashMap::new(),
+                remote: None,
             })
         }
     }
@@ -131,12 +133,17 @@ pub async fn initialize_components(config: &CacheConfig) -> Result<CacheComponen
         config.inline_threshold,
     )?);
 
-    // Initialize action cache with CAS and max size
-    let action_cache = Arc::new(ActionCache::new(
+    // Initialize action cache with CAS and max size, sharing results
+    // through the remote cache when one is configured
+    let mut action_cache = ActionCache::new(
         Arc::clone(&content_store),
         config.max_size,
         &config.base_dir,
-    )?);
+    )?;
+    if let Some(remote) = &config.remote {
+        action_cache = action_cache.with_remote(Arc::new(RemoteCache::new(remote)?));
+    }
+    let action_cache = Arc::new(action_cache);
 
     // Initialize cache engine for legacy compatibility
     let engine = Arc::new(CacheEngine::new().map_err(|e| Error::Configuration {
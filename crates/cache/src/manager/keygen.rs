@@ -1,5 +1,6 @@
 //! Cache key generation utilities
 
+use crate::keys::hash::{HashWriter, HexDigest};
 use crate::keys::{CacheKeyFilterConfig, CacheKeyGenerator};
 use cuenv_config::TaskConfig;
 use cuenv_core::{Error, Result};
@@ -36,7 +37,7 @@ impl KeyGenManager {
         working_dir: &Path,
     ) -> Result<String> {
         // Use the selective cache key generator for improved cache hit rates
-        let config_hash = hash_task_config(task_config)?;
+        let config_hash = task_config_digest(task_config)?;
         let command = task_config.command.as_ref().or(task_config.script.as_ref());
 
         // For now, use empty input files since we don't have them in this context
@@ -45,7 +46,7 @@ impl KeyGenManager {
         self.key_generator
             .generate_cache_key(
                 task_name,
-                &config_hash,
+                config_hash.as_str(),
                 working_dir,
                 &input_files,
                 env_vars,
@@ -87,14 +88,19 @@ impl KeyGenManager {
 
 /// Compute hash of task configuration for cache key generation
 pub fn hash_task_config(config: &TaskConfig) -> Result<String> {
-    let serialized = serde_json::to_string(config).map_err(|e| Error::Json {
+    Ok(task_config_digest(config)?.as_str().to_string())
+}
+
+/// The hash of the JSON of `config`, serialized straight into the hasher.
+/// Its fields are written in declaration order and its maps are sorted, so
+/// the same configuration always hashes the same.
+fn task_config_digest(config: &TaskConfig) -> Result<HexDigest> {
+    let mut hasher = Sha256::new();
+    serde_json::to_writer(HashWriter(&mut hasher), config).map_err(|e| Error::Json {
         message: "Failed to serialize task config for hashing".to_string(),
         source: e,
     })?;
-
-    let mut hasher = Sha256::new();
-    hasher.update(serialized.as_bytes());
-    Ok(format!("{:x}", hasher.finalize()))
+    Ok(HexDigest::new(&hasher.finalize()))
 }
 
 #[cfg(test)]
@@ -122,6 +128,13 @@ mod tests {
         let hash3 = hash_task_config(&config2)?;
         assert_ne!(hash1, hash3);
 
+        // The same hash as of the serialized configuration
+        let serialized = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            hash1,
+            format!("{:x}", Sha256::digest(serialized.as_bytes()))
+        );
+
         Ok(())
     }
 
@@ -0,0 +1,425 @@
+//! Maintenance of the cache on disk: usage, pruning, clearing and verifying
+//!
+//! Entries are removed one file at a time, never by removing directories.
+//! Maintenance holds a lock on the cache so that two processes do not prune
+//! at once, skips the actions any process holds a lease on while executing
+//! them, and keeps results and objects stored within a grace period. Objects
+//! are only removed when no stored result refers to them, and not at all
+//! while another process is executing an action. This keeps maintenance safe
+//! while tasks run: a run that loses an entry it was about to reuse executes
+//! the task again.
+
+use super::CacheManager;
+use crate::concurrent::action::ActionEntry;
+use cuenv_core::{Error, Result};
+use fs2::FileExt;
+use globset::{Glob, GlobBuilder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub use cuenv_config::user::{parse_age, parse_size};
+
+/// How long results and objects are kept after they were stored, for runs
+/// that are about to reuse them
+const GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+/// Cached results of one task, or of the tasks of one package
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskUsage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// What the cache holds on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheUsage {
+    pub path: PathBuf,
+    /// Bytes of everything in the cache directory
+    pub total_bytes: u64,
+    /// Size the cache is pruned to by default
+    pub max_size: u64,
+    /// Cached task results
+    pub entries: usize,
+    /// Bytes of the cached task results and their outputs
+    pub entry_bytes: u64,
+    /// Objects in the content-addressed store
+    pub objects: usize,
+    pub object_bytes: u64,
+    pub oldest: Option<SystemTime>,
+    pub newest: Option<SystemTime>,
+    pub tasks: BTreeMap<String, TaskUsage>,
+    /// Results by workspace package, by its path from the workspace root
+    pub packages: BTreeMap<String, TaskUsage>,
+}
+
+/// Which cached task results to remove
+#[derive(Debug, Clone, Default)]
+pub struct PruneOptions {
+    /// Remove results executed longer ago than this
+    pub older_than: Option<Duration>,
+    /// Remove the oldest results until the results take at most this many
+    /// bytes
+    pub max_size: Option<u64>,
+    /// Only consider results of tasks matching this glob, such as `ci.*`
+    pub tasks: Option<String>,
+    /// Only consider results of workspace packages whose path matches this
+    /// glob, such as `apps/*`
+    pub packages: Option<String>,
+    /// Report what would be removed without removing it
+    pub dry_run: bool,
+}
+
+/// The cached task results a prune removed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub removed: Vec<ActionEntry>,
+    pub freed_bytes: u64,
+}
+
+/// A cached task result that failed verification
+#[derive(Debug, Clone, Serialize)]
+pub struct CorruptEntry {
+    pub hash: String,
+    pub problem: String,
+}
+
+/// The outcome of verifying the cache
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub corrupt: Vec<CorruptEntry>,
+    /// Whether the corrupt entries were removed
+    pub repaired: bool,
+}
+
+impl CacheManager {
+    /// The directory holding the cache
+    pub fn path(&self) -> &Path {
+        &self.config.base_dir
+    }
+
+    /// What the cache holds on disk
+    pub fn usage(&self) -> Result<CacheUsage> {
+        let entries = self.action_cache().entries()?;
+        let store = self.content_store();
+
+        let mut tasks: BTreeMap<String, TaskUsage> = BTreeMap::new();
+        let mut packages: BTreeMap<String, TaskUsage> = BTreeMap::new();
+        for entry in &entries {
+            let usage = tasks.entry(entry.task_name.clone()).or_default();
+            usage.entries += 1;
+            usage.bytes += entry.size_bytes;
+            if let Some(package) = &entry.package {
+                let usage = packages.entry(package.clone()).or_default();
+                usage.entries += 1;
+                usage.bytes += entry.size_bytes;
+            }
+        }
+
+        Ok(CacheUsage {
+            path: self.config.base_dir.clone(),
+            total_bytes: dir_size(&self.config.base_dir),
+            max_size: self.config.max_size,
+            entries: entries.len(),
+            entry_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+            objects: store.object_count(),
+            object_bytes: store.total_bytes(),
+            oldest: entries.iter().map(|entry| entry.executed_at).min(),
+            newest: entries.iter().map(|entry| entry.executed_at).max(),
+            tasks,
+            packages,
+        })
+    }
+
+    /// Remove the cached task results selected by `options`
+    ///
+    /// Without an age or size, results of the tasks matching `tasks` or
+    /// `packages` are all removed, or, without a pattern either, the oldest
+    /// results beyond the configured maximum size.
+    pub fn prune(&self, options: &PruneOptions) -> Result<PruneReport> {
+        let mut options = options.clone();
+        if options.older_than.is_none()
+            && options.max_size.is_none()
+            && options.tasks.is_none()
+            && options.packages.is_none()
+        {
+            options.max_size = Some(self.config.max_size);
+        }
+
+        let _lock = self.lock()?;
+        let now = SystemTime::now();
+        let action_cache = self.action_cache();
+        let entries = action_cache
+            .entries()?
+            .into_iter()
+            .filter(|entry| {
+                now.duration_since(entry.executed_at).unwrap_or_default() >= GRACE_PERIOD
+                    && !action_cache.is_in_flight(&entry.hash)
+            })
+            .collect();
+        let selected = select(entries, &options, now)?;
+
+        if !options.dry_run {
+            for entry in &selected {
+                action_cache.remove_entry(&entry.hash)?;
+            }
+            self.remove_unreferenced_objects()?;
+        }
+
+        Ok(PruneReport {
+            freed_bytes: selected.iter().map(|entry| entry.size_bytes).sum(),
+            removed: selected,
+        })
+    }
+
+    /// Remove every cached task result, including unreadable ones, except
+    /// those being executed
+    pub fn clear(&self) -> Result<PruneReport> {
+        let _lock = self.lock()?;
+        let action_cache = self.action_cache();
+        let removed = action_cache
+            .entries()?
+            .into_iter()
+            .filter(|entry| !action_cache.is_in_flight(&entry.hash))
+            .collect::<Vec<_>>();
+        for hash in action_cache.stored_hashes()? {
+            if !action_cache.is_in_flight(&hash) {
+                action_cache.remove_entry(&hash)?;
+            }
+        }
+        self.operations.clear_cache()?;
+        self.remove_unreferenced_objects()?;
+
+        Ok(PruneReport {
+            freed_bytes: removed.iter().map(|entry| entry.size_bytes).sum(),
+            removed,
+        })
+    }
+
+    /// Check every cached task result, removing the corrupt ones with
+    /// `repair`
+    pub fn verify(&self, repair: bool) -> Result<VerifyReport> {
+        let _lock = self.lock()?;
+        let action_cache = self.action_cache();
+        let hashes = action_cache.stored_hashes()?;
+
+        let mut corrupt = Vec::new();
+        for hash in &hashes {
+            if let Err(problem) = action_cache.verify_entry(hash) {
+                if repair {
+                    action_cache.remove_entry(hash)?;
+                }
+                corrupt.push(CorruptEntry {
+                    hash: hash.clone(),
+                    problem,
+                });
+            }
+        }
+
+        if repair && !corrupt.is_empty() {
+            self.remove_unreferenced_objects()?;
+        }
+
+        Ok(VerifyReport {
+            checked: hashes.len(),
+            repaired: repair && !corrupt.is_empty(),
+            corrupt,
+        })
+    }
+
+    /// Wait for any other process maintaining the cache, and hold it until
+    /// the returned file is dropped
+    fn lock(&self) -> Result<File> {
+        let path = self.config.base_dir.join("maintenance.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| Error::file_system(&path, "open cache lock", e))?;
+        file.lock_exclusive()
+            .map_err(|e| Error::file_system(&path, "lock cache", e))?;
+        Ok(file)
+    }
+
+    /// Remove the objects no stored result refers to, unless an action is
+    /// being executed whose outputs may not be referred to yet
+    fn remove_unreferenced_objects(&self) -> Result<()> {
+        let action_cache = self.action_cache();
+        if action_cache.has_leases() {
+            log::debug!("Keeping unreferenced cache objects while actions are executing");
+            return Ok(());
+        }
+        self.content_store()
+            .remove_unreferenced(&action_cache.referenced_objects()?, GRACE_PERIOD)?;
+        Ok(())
+    }
+}
+
+/// The entries to remove, oldest first
+fn select(
+    mut entries: Vec<ActionEntry>,
+    options: &PruneOptions,
+    now: SystemTime,
+) -> Result<Vec<ActionEntry>> {
+    let matcher = options
+        .tasks
+        .as_deref()
+        .map(|pattern| {
+            Glob::new(pattern)
+                .map(|glob| glob.compile_matcher())
+                .map_err(|e| Error::configuration(format!("Invalid task pattern '{pattern}': {e}")))
+        })
+        .transpose()?;
+    let package_matcher = options
+        .packages
+        .as_deref()
+        .map(|pattern| {
+            GlobBuilder::new(pattern.trim_end_matches('/'))
+                .literal_separator(true)
+                .build()
+                .map(|glob| glob.compile_matcher())
+                .map_err(|e| {
+                    Error::configuration(format!("Invalid package pattern '{pattern}': {e}"))
+                })
+        })
+        .transpose()?;
+    entries.sort_by_key(|entry| entry.executed_at);
+
+    let mut total: u64 = entries.iter().map(|entry| entry.size_bytes).sum();
+    let candidates = entries.into_iter().filter(|entry| {
+        matcher
+            .as_ref()
+            .is_none_or(|m| m.is_match(&entry.task_name))
+            && package_matcher.as_ref().is_none_or(|m| {
+                entry
+                    .package
+                    .as_deref()
+                    .is_some_and(|package| m.is_match(package))
+            })
+    });
+
+    let mut selected = Vec::new();
+    for entry in candidates {
+        let age = now.duration_since(entry.executed_at).unwrap_or_default();
+        let too_old = options.older_than.is_some_and(|max_age| age > max_age);
+        let too_large = options.max_size.is_some_and(|max_size| total > max_size);
+        let all = options.older_than.is_none() && options.max_size.is_none();
+        if too_old || too_large || all {
+            total -= entry.size_bytes;
+            selected.push(entry);
+        }
+    }
+    Ok(selected)
+}
+
+/// Bytes of the files under `dir`
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(task_name: &str, age_hours: u64, size_bytes: u64, now: SystemTime) -> ActionEntry {
+        ActionEntry {
+            hash: format!("{task_name}-{age_hours}"),
+            task_name: task_name.to_string(),
+            package: task_name.starts_with("ci.").then(|| "tools/ci".to_string()),
+            executed_at: now - Duration::from_secs(age_hours * 3600),
+            size_bytes,
+        }
+    }
+
+    fn hashes(entries: &[ActionEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.hash.as_str()).collect()
+    }
+
+    #[test]
+    fn test_select() {
+        let now = SystemTime::now();
+        let entries = vec![
+            entry("build", 1, 100, now),
+            entry("ci.test", 48, 300, now),
+            entry("ci.lint", 2, 200, now),
+            entry("build", 72, 400, now),
+        ];
+
+        let older = PruneOptions {
+            older_than: Some(Duration::from_secs(24 * 3600)),
+            ..Default::default()
+        };
+        assert_eq!(
+            hashes(&select(entries.clone(), &older, now).unwrap()),
+            ["build-72", "ci.test-48"]
+        );
+
+        let smaller = PruneOptions {
+            max_size: Some(400),
+            ..Default::default()
+        };
+        assert_eq!(
+            hashes(&select(entries.clone(), &smaller, now).unwrap()),
+            ["build-72", "ci.test-48"]
+        );
+
+        let ci = PruneOptions {
+            tasks: Some("ci.*".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            hashes(&select(entries.clone(), &ci, now).unwrap()),
+            ["ci.test-48", "ci.lint-2"]
+        );
+
+        let ci_smaller = PruneOptions {
+            max_size: Some(700),
+            tasks: Some("ci.*".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            hashes(&select(entries.clone(), &ci_smaller, now).unwrap()),
+            ["ci.test-48"]
+        );
+
+        let tools = PruneOptions {
+            packages: Some("tools/*".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            hashes(&select(entries, &tools, now).unwrap()),
+            ["ci.test-48", "ci.lint-2"]
+        );
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 24 * 3600)));
+        assert!(parse_age("7y").is_err());
+        assert!(parse_age("d").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("500MB"), Ok(500 << 20));
+        assert_eq!(parse_size("1.5gb"), Ok(3 << 29));
+        assert!(parse_size("10PB").is_err());
+    }
+}
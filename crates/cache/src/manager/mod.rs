@@ -2,12 +2,17 @@
 
 mod builder;
 mod keygen;
+mod maintenance;
 mod migration;
 mod operations;
 mod statistics;
 
 pub use builder::CacheManagerBuilder;
 pub use keygen::hash_task_config;
+pub use maintenance::{
+    parse_age, parse_size, CacheUsage, CorruptEntry, PruneOptions, PruneReport, TaskUsage,
+    VerifyReport,
+};
 pub use migration::CACHE_VERSION;
 pub use statistics::CacheStatistics;
 
@@ -74,7 +79,7 @@ impl CacheManager {
 
     /// Clear all cache entries
     pub fn clear_cache(&self) -> Result<()> {
-        self.operations.clear_cache()
+        self.clear().map(drop)
     }
 
     /// Get the content-addressed store
@@ -141,6 +141,7 @@ impl CacheOperations {
             stdout: action_result.stdout_hash.map(|s| s.as_bytes().to_vec()),
             stderr: action_result.stderr_hash.map(|s| s.as_bytes().to_vec()),
             output_files: action_result.output_files,
+            artifacts: action_result.artifacts,
         }
     }
 }
@@ -174,6 +175,7 @@ mod tests {
             stdout: Some(b"output".to_vec()),
             stderr: None,
             output_files: HashMap::new(),
+            artifacts: HashMap::new(),
         };
 
         operations.store_result("test_key".to_string(), result.clone())?;
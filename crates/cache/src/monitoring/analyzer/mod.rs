@@ -147,7 +147,7 @@ impl HitRateAnalyzer {
                 });
             }
         }
-        key_patterns.sort_by(|a, b| b.total_accesses.cmp(&a.total_accesses));
+        key_patterns.sort_by_key(|b| std::cmp::Reverse(b.total_accesses));
 
         let mut operation_types = Vec::new();
         for (op_type, stats) in self.operation_types.read().iter() {
@@ -162,7 +162,7 @@ impl HitRateAnalyzer {
                 });
             }
         }
-        operation_types.sort_by(|a, b| b.total_calls.cmp(&a.total_calls));
+        operation_types.sort_by_key(|b| std::cmp::Reverse(b.total_calls));
 
         HitRateReport {
             one_minute: windows.one_minute.hit_rate(),
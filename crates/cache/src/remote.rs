@@ -0,0 +1,385 @@
+//! Client of a remote cache, to share task results between machines
+//!
+//! The remote speaks the HTTP protocol of Bazel's remote caches: results are
+//! stored under `ac/<action hash>` and the outputs they refer to under
+//! `cas/<object hash>`, fetched with GET and stored with PUT.
+//!
+//! The tasks of a level often miss on the same outputs at once, so
+//! concurrent fetches of one key share a single request, and at most a
+//! bounded number of requests are in flight. Transient failures are retried
+//! with exponential backoff, and a circuit breaker stops asking a remote that
+//! keeps failing: runs then continue with the local cache only until a probe
+//! succeeds again.
+
+use cuenv_core::{Error, Result};
+use cuenv_utils::resilience::{
+    retry_with_circuit_breaker, CircuitBreaker, CircuitBreakerConfig, CircuitState, RetryConfig,
+    RetryOn,
+};
+use dashmap::DashMap;
+use reqwest::{StatusCode, Url};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OnceCell, Semaphore};
+
+/// Requests in flight to the remote at most
+const DEFAULT_MAX_REQUESTS: usize = 16;
+
+/// Timeout of a single request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where a key is stored on the remote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteStore {
+    /// Results of actions, by the hash of the action
+    Actions,
+    /// Contents, by their hash
+    Objects,
+}
+
+impl RemoteStore {
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Actions => "ac",
+            Self::Objects => "cas",
+        }
+    }
+}
+
+/// The outcome of a fetch, shared by every caller of a coalesced request
+type Fetch = Arc<OnceCell<Option<Arc<Vec<u8>>>>>;
+
+/// Client of a remote cache
+///
+/// Failing to reach the remote never fails a run: fetches then miss and
+/// stores are dropped, with a single warning until the remote recovers.
+pub struct RemoteCache {
+    endpoint: Url,
+    client: reqwest::Client,
+    /// Slots for the requests in flight
+    requests: Semaphore,
+    /// Fetches in flight, by key
+    fetches: DashMap<String, Fetch>,
+    retry: RetryConfig,
+    breaker: CircuitBreaker,
+    /// Whether a failure to reach the remote was reported
+    degraded: AtomicBool,
+}
+
+impl RemoteCache {
+    /// A client of the remote cache at `endpoint`, such as
+    /// `https://cache.example.com/cuenv`
+    pub fn new(endpoint: &str) -> Result<Self> {
+        let mut url = Url::parse(endpoint).map_err(|e| {
+            Error::configuration(format!("Invalid remote cache endpoint '{endpoint}': {e}"))
+        })?;
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("cuenv/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| Error::configuration(format!("Failed to create HTTP client: {e}")))?;
+
+        Ok(Self {
+            endpoint: url,
+            client,
+            requests: Semaphore::new(DEFAULT_MAX_REQUESTS),
+            fetches: DashMap::new(),
+            retry: RetryConfig {
+                max_retries: 3,
+                base_delay: Duration::from_millis(100),
+                max_delay: Duration::from_secs(2),
+                jitter_factor: 0.2,
+                retry_on: RetryOn::Network,
+            },
+            breaker: CircuitBreaker::new(CircuitBreakerConfig::default()),
+            degraded: AtomicBool::new(false),
+        })
+    }
+
+    /// Keep at most `max_requests` requests in flight
+    pub fn with_max_requests(mut self, max_requests: usize) -> Self {
+        self.requests = Semaphore::new(max_requests.max(1));
+        self
+    }
+
+    /// Retry failed requests as `retry` says
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Stop asking the remote as `config` says when it keeps failing
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.breaker = CircuitBreaker::new(config);
+        self
+    }
+
+    /// The host of the remote, which is all that is reported of it since
+    /// endpoints may carry credentials
+    pub fn host(&self) -> &str {
+        self.endpoint.host_str().unwrap_or_default()
+    }
+
+    /// Whether requests are passed on to the remote, rather than the circuit
+    /// breaker having opened on its failures
+    pub async fn is_available(&self) -> bool {
+        self.breaker.state().await != CircuitState::Open
+    }
+
+    /// Check that the remote answers, without retrying
+    pub async fn probe(&self) -> Result<()> {
+        let url = self.url(RemoteStore::Actions, &"0".repeat(64))?;
+        self.request_get(url).await.map(|_| ())
+    }
+
+    /// The content stored under `hash`, or `None` when the remote does not
+    /// have it or cannot be reached
+    pub async fn get(&self, store: RemoteStore, hash: &str) -> Option<Arc<Vec<u8>>> {
+        let key = format!("{}/{hash}", store.prefix());
+        let fetch = self.fetches.entry(key.clone()).or_default().clone();
+        let content = fetch.get_or_init(|| self.fetch(store, hash)).await.clone();
+        self.fetches
+            .remove_if(&key, |_, current| Arc::ptr_eq(current, &fetch));
+        content
+    }
+
+    /// Store `content` under `hash`, returning whether the remote took it
+    pub async fn put(&self, store: RemoteStore, hash: &str, content: Vec<u8>) -> bool {
+        let Ok(url) = self.url(store, hash) else {
+            return false;
+        };
+        let Ok(_slot) = self.requests.acquire().await else {
+            return false;
+        };
+        let stored = retry_with_circuit_breaker(&self.retry, &self.breaker, || {
+            self.request_put(url.clone(), content.clone())
+        })
+        .await;
+        self.record(stored).is_some()
+    }
+
+    async fn fetch(&self, store: RemoteStore, hash: &str) -> Option<Arc<Vec<u8>>> {
+        let url = self.url(store, hash).ok()?;
+        let _slot = self.requests.acquire().await.ok()?;
+        let fetched = retry_with_circuit_breaker(&self.retry, &self.breaker, || {
+            self.request_get(url.clone())
+        })
+        .await;
+        self.record(fetched).flatten().map(Arc::new)
+    }
+
+    /// Report the remote becoming unavailable or available again once
+    fn record<T>(&self, outcome: Result<T>) -> Option<T> {
+        match outcome {
+            Ok(value) => {
+                if self.degraded.swap(false, Ordering::Relaxed) {
+                    log::info!("Remote cache {} is available again", self.host());
+                }
+                Some(value)
+            }
+            Err(e) => {
+                if !self.degraded.swap(true, Ordering::Relaxed) {
+                    log::warn!(
+                        "Remote cache {} is unavailable, using the local cache only: {e}",
+                        self.host()
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    fn url(&self, store: RemoteStore, hash: &str) -> Result<Url> {
+        if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::configuration(format!("Invalid cache key '{hash}'")));
+        }
+        self.endpoint
+            .join(&format!("{}/{hash}", store.prefix()))
+            .map_err(|e| Error::configuration(format!("Invalid cache key '{hash}': {e}")))
+    }
+
+    async fn request_get(&self, url: Url) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| self.error(e))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .bytes()
+                .await
+                .map(|bytes| Some(bytes.to_vec()))
+                .map_err(|e| self.error(e)),
+            status => Err(self.status_error(status)),
+        }
+    }
+
+    async fn request_put(&self, url: Url, content: Vec<u8>) -> Result<()> {
+        let response = self
+            .client
+            .put(url)
+            .body(content)
+            .send()
+            .await
+            .map_err(|e| self.error(e))?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(self.status_error(status)),
+        }
+    }
+
+    fn error(&self, e: reqwest::Error) -> Error {
+        Error::network(self.host(), e.without_url().to_string())
+    }
+
+    /// Server errors are retried; the remote refusing a request is not
+    fn status_error(&self, status: StatusCode) -> Error {
+        let message = format!("Remote cache {} answered with status {status}", self.host());
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            Error::network(self.host(), message)
+        } else {
+            Error::configuration(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode as AxumStatus;
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Requests the test remote answered, and how many of them fail first
+    #[derive(Default)]
+    struct Remote {
+        requests: AtomicUsize,
+        failures: AtomicUsize,
+    }
+
+    async fn answer(
+        State(remote): State<Arc<Remote>>,
+        Path((_, hash)): Path<(String, String)>,
+    ) -> (AxumStatus, Vec<u8>) {
+        remote.requests.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let failing = remote
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            (AxumStatus::SERVICE_UNAVAILABLE, Vec::new())
+        } else if hash.starts_with('a') {
+            (AxumStatus::OK, hash.into_bytes())
+        } else {
+            (AxumStatus::NOT_FOUND, Vec::new())
+        }
+    }
+
+    async fn serve(remote: Arc<Remote>) -> String {
+        let app = Router::new()
+            .route("/:store/:hash", get(answer).put(answer))
+            .with_state(remote);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{address}")
+    }
+
+    fn quick_retries() -> RetryConfig {
+        RetryConfig {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            jitter_factor: 0.0,
+            retry_on: RetryOn::Network,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_fetches_share_a_request() {
+        let remote = Arc::new(Remote::default());
+        let cache = Arc::new(RemoteCache::new(&serve(Arc::clone(&remote)).await).unwrap());
+
+        let fetches = (0..50).map(|_| {
+            let cache = Arc::clone(&cache);
+            tokio::spawn(async move { cache.get(RemoteStore::Objects, "abc").await })
+        });
+        for fetch in futures::future::join_all(fetches).await {
+            assert_eq!(fetch.unwrap().as_deref(), Some(&b"abc".to_vec()));
+        }
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 1);
+
+        assert_eq!(cache.get(RemoteStore::Actions, "def").await, None);
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let remote = Arc::new(Remote::default());
+        remote.failures.store(2, Ordering::SeqCst);
+        let cache = RemoteCache::new(&serve(Arc::clone(&remote)).await)
+            .unwrap()
+            .with_retry(quick_retries());
+
+        assert!(
+            cache
+                .put(RemoteStore::Objects, "abc", b"abc".to_vec())
+                .await
+        );
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_an_unhealthy_remote_is_left_alone() {
+        let remote = Arc::new(Remote::default());
+        remote.failures.store(usize::MAX, Ordering::SeqCst);
+        let cache = RemoteCache::new(&serve(Arc::clone(&remote)).await)
+            .unwrap()
+            .with_retry(quick_retries())
+            .with_circuit_breaker(CircuitBreakerConfig {
+                failure_threshold: 3,
+                break_duration: Duration::from_secs(60),
+                ..Default::default()
+            });
+
+        assert_eq!(cache.get(RemoteStore::Objects, "abc").await, None);
+        assert!(!cache.is_available().await);
+        let requests = remote.requests.load(Ordering::SeqCst);
+        assert_eq!(requests, 3);
+
+        // Runs continue with the local cache only
+        assert_eq!(cache.get(RemoteStore::Objects, "abd").await, None);
+        assert!(!cache.put(RemoteStore::Objects, "abd", Vec::new()).await);
+        assert_eq!(remote.requests.load(Ordering::SeqCst), requests);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_requests() {
+        let remote = Arc::new(Remote::default());
+        let cache = Arc::new(
+            RemoteCache::new(&serve(Arc::clone(&remote)).await)
+                .unwrap()
+                .with_max_requests(2),
+        );
+
+        let start = std::time::Instant::now();
+        let fetches = (0..4).map(|i| {
+            let cache = Arc::clone(&cache);
+            tokio::spawn(async move { cache.get(RemoteStore::Objects, &format!("a{i}")).await })
+        });
+        futures::future::join_all(fetches).await;
+        // Four requests of 50ms, two at a time
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(remote.requests.load(Ordering::SeqCst), 4);
+    }
+}
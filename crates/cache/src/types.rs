@@ -2,6 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::SystemTime;
 
 /// Represents a cached task execution result
@@ -19,4 +20,7 @@ pub struct CachedTaskResult {
     pub stderr: Option<Vec<u8>>,
     /// Output files produced by the task
     pub output_files: HashMap<String, String>,
+    /// Named output artifacts and the paths they were produced at
+    #[serde(default)]
+    pub artifacts: HashMap<String, PathBuf>,
 }
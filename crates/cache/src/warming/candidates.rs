@@ -68,7 +68,7 @@ impl<C: Cache + Clone> CandidateWarmer<C> {
             // Use standard access-count based selection
             let mut c = tracker.get_candidates(self.config.min_access_count);
             // Sort by access count (descending)
-            c.sort_by(|a, b| b.1.cmp(&a.1));
+            c.sort_by_key(|b| std::cmp::Reverse(b.1));
             c
         };
 
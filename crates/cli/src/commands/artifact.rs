@@ -0,0 +1,157 @@
+//! `cuenv artifact get <task>:<artifact>`, a named output out of the cache
+//!
+//! Copies the files of an artifact a task declares from the cached run that
+//! produced it, so that a deployment script takes the build it needs
+//! without building again. The newest successful run is used unless a run
+//! is named by its id, whose manifest in `.cuenv/runs` holds the cache key
+//! of each task.
+
+use clap::Subcommand;
+use cuenv_core::{Error, Result};
+use cuenv_task::{RunManifest, TaskExecutor};
+use std::path::{Path, PathBuf};
+
+#[derive(Subcommand)]
+pub enum ArtifactCommands {
+    /// Copy a named artifact of a task out of the cache, such as
+    /// `cuenv artifact get build:binary --out ./dist/`
+    Get {
+        /// The task and its artifact, as <task>:<artifact>
+        artifact: String,
+        /// Directory to copy the artifact into
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        out: PathBuf,
+        /// Take the artifact from the run with this id instead of the newest
+        /// successful one
+        #[arg(long, value_name = "RUN_ID")]
+        run: Option<String>,
+    },
+}
+
+impl ArtifactCommands {
+    pub async fn execute(self) -> Result<()> {
+        match self {
+            ArtifactCommands::Get { artifact, out, run } => {
+                get(&artifact, &out, run.as_deref()).await
+            }
+        }
+    }
+}
+
+async fn get(reference: &str, out: &Path, run: Option<&str>) -> Result<()> {
+    let (task, name) = parse_reference(reference)?;
+    let (current_dir, env_manager) = super::task::load_task_environment(None, Vec::new()).await?;
+    if env_manager.get_task(task).is_none() {
+        return Err(Error::usage(format!(
+            "Task '{task}' not found\nRun 'cuenv task' to see available tasks"
+        )));
+    }
+    let cache_key = run
+        .map(|run_id| cache_key_of_run(&current_dir, run_id, task))
+        .transpose()?;
+
+    let executor = TaskExecutor::new(env_manager, current_dir).await?;
+    let artifact = executor.cached_artifact(task, name, cache_key.as_deref())?;
+    let written = executor.restore_artifact(&artifact, out)?;
+    match written.as_slice() {
+        [file] if !artifact.directory => println!("✓ {reference} → {}", file.display()),
+        _ => println!(
+            "✓ {reference} → {} ({} files)",
+            out.display(),
+            written.len()
+        ),
+    }
+    Ok(())
+}
+
+/// The task and artifact of `build:binary`; task names may hold colons
+/// themselves, as `npm:build:dist` does
+fn parse_reference(reference: &str) -> Result<(&str, &str)> {
+    reference
+        .rsplit_once(':')
+        .filter(|(task, name)| !task.is_empty() && !name.is_empty())
+        .ok_or_else(|| {
+            Error::usage(format!(
+                "'{reference}' names no artifact; use <task>:<artifact>, such as build:binary"
+            ))
+        })
+}
+
+/// The key `task` was cached under in the run `run_id` of `dir`
+fn cache_key_of_run(dir: &Path, run_id: &str, task: &str) -> Result<String> {
+    let path = RunManifest::dir(dir).join(format!("{run_id}.json"));
+    if !path.is_file() {
+        return Err(Error::usage(format!(
+            "No manifest of run '{run_id}' at {}",
+            path.display()
+        )));
+    }
+    RunManifest::read(&path)?
+        .summary
+        .tasks
+        .into_iter()
+        .find(|summary| summary.name == task)
+        .and_then(|summary| summary.cache_key)
+        .ok_or_else(|| {
+            Error::usage(format!(
+                "Run '{run_id}' did not run '{task}' with caching enabled"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_task::{CacheStatus, RunSummary, TaskSummary};
+
+    #[test]
+    fn test_parse_reference() {
+        assert_eq!(
+            parse_reference("build:binary").unwrap(),
+            ("build", "binary")
+        );
+        assert_eq!(
+            parse_reference("npm:build:dist").unwrap(),
+            ("npm:build", "dist")
+        );
+        assert!(parse_reference("build").is_err());
+        assert!(parse_reference("build:").is_err());
+    }
+
+    #[test]
+    fn test_cache_key_of_run() {
+        let temp = tempfile::tempdir().unwrap();
+        let summary = RunSummary {
+            duration_ms: 10,
+            plan: vec![vec!["build".to_string()]],
+            tasks: vec![TaskSummary {
+                name: "build".to_string(),
+                duration_ms: 10,
+                queue_wait_ms: 0,
+                cache: CacheStatus::Miss,
+                exit_code: Some(0),
+                owners: vec![],
+                cache_key: Some("abc123".to_string()),
+            }],
+            critical_path: vec!["build".to_string()],
+            critical_path_ms: 10,
+        };
+        RunManifest::new(
+            "run-1",
+            "build",
+            temp.path(),
+            chrono::Utc::now(),
+            Some(0),
+            summary,
+        )
+        .write()
+        .unwrap();
+
+        assert_eq!(
+            cache_key_of_run(temp.path(), "run-1", "build").unwrap(),
+            "abc123"
+        );
+        assert!(cache_key_of_run(temp.path(), "run-1", "test").is_err());
+        assert!(cache_key_of_run(temp.path(), "run-2", "build").is_err());
+    }
+}
@@ -1,59 +1,223 @@
+use crate::commands::doctor::format_size;
 use clap::Subcommand;
+use cuenv_cache::config::CacheConfigLoader;
+use cuenv_cache::manager::{parse_age, parse_size, PruneOptions, PruneReport};
 use cuenv_cache::{CacheConfig, CacheManager};
-use cuenv_core::Result;
+use cuenv_config::{CacheSettings, Config, CueCache};
+use cuenv_core::{Error, Result};
+use std::time::{Duration, SystemTime};
 
 #[derive(Subcommand)]
 pub enum CacheCommands {
-    /// Clear all cache entries
+    /// Clear all cache entries, including cached CUE evaluations
     Clear,
-    /// Show cache statistics
-    Stats,
-    /// Clean up stale cache entries
-    Cleanup {
-        /// Maximum age of cache entries to keep (in hours)
-        #[arg(long, default_value = "168")]
-        max_age_hours: u64,
+    /// Show what the cache holds, by task
+    Stats {
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
     },
+    /// Remove cached task results by age, size, task or package; without
+    /// options, those older than the configured maxAge and the oldest beyond
+    /// the cache's maximum size
+    #[command(visible_alias = "cleanup")]
+    Prune {
+        /// Remove results older than AGE, e.g. 12h, 7d or 2w
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+        /// Remove the oldest results until the cache holds at most SIZE,
+        /// e.g. 500MB or 2GB
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Only remove results of tasks matching PATTERN, e.g. 'ci.*'
+        #[arg(long, value_name = "PATTERN")]
+        task: Option<String>,
+        /// Only remove results of workspace packages whose path matches
+        /// PATTERN, e.g. 'apps/*'
+        #[arg(long, value_name = "PATTERN")]
+        package: Option<String>,
+        /// Show what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check that cached task results are intact
+    Verify {
+        /// Remove the entries that are not
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Print the cache directory
+    Path,
 }
 
 impl CacheCommands {
-    pub async fn execute(self) -> Result<()> {
+    pub async fn execute(self, config: &Config) -> Result<()> {
+        let settings = settings(config);
+        let configuration = CacheConfigLoader::load_for(&settings)?;
+        let manager = CacheManager::new(CacheConfig::from(&configuration.global)).await?;
         match self {
             CacheCommands::Clear => {
-                let config = CacheConfig::default();
-                let manager = CacheManager::new(config).await?;
-                manager.clear_cache()?;
-                println!("✓ Cache cleared successfully");
+                let report = manager.clear()?;
+                CueCache::clear()?;
+                println!(
+                    "✓ Cache cleared: {} task result(s), {}, and cached CUE evaluations",
+                    report.removed.len(),
+                    format_size(report.freed_bytes)
+                );
                 Ok(())
             }
-            CacheCommands::Stats => {
-                let config = CacheConfig::default();
-                let manager = CacheManager::new(config).await?;
-                let stats = manager.get_statistics();
-                println!("Cache Statistics:");
-                println!("  Hits: {}", stats.hits);
-                println!("  Misses: {}", stats.misses);
-                println!("  Writes: {}", stats.writes);
-                println!("  Errors: {}", stats.errors);
-                let hit_rate = if stats.hits + stats.misses > 0 {
-                    (stats.hits as f64 / (stats.hits + stats.misses) as f64) * 100.0
-                } else {
-                    0.0
-                };
-                println!("  Hit rate: {hit_rate:.1}%");
+            CacheCommands::Stats { json } => {
+                let usage = manager.usage()?;
+                if json {
+                    let output = serde_json::to_string_pretty(&usage).map_err(|e| Error::Json {
+                        message: "failed to encode cache statistics".to_string(),
+                        source: e,
+                    })?;
+                    println!("{output}");
+                    return Ok(());
+                }
+
+                println!("Cache: {}", usage.path.display());
                 println!(
-                    "  Total bytes saved: {:.2} MB",
-                    stats.total_bytes_saved as f64 / 1_048_576.0
+                    "  Size: {} (pruned to {})",
+                    format_size(usage.total_bytes),
+                    format_size(usage.max_size)
                 );
+                println!(
+                    "  Task results: {} ({})",
+                    usage.entries,
+                    format_size(usage.entry_bytes)
+                );
+                if let (Some(oldest), Some(newest)) = (usage.oldest, usage.newest) {
+                    println!(
+                        "  Oldest: {}, newest: {}",
+                        format_age(oldest),
+                        format_age(newest)
+                    );
+                }
+                println!(
+                    "  Stored outputs: {} ({})",
+                    usage.objects,
+                    format_size(usage.object_bytes)
+                );
+
+                for (heading, groups) in
+                    [("By task", &usage.tasks), ("By package", &usage.packages)]
+                {
+                    if groups.is_empty() {
+                        continue;
+                    }
+                    println!("  {heading}:");
+                    let width = groups.keys().map(String::len).max().unwrap_or(0);
+                    for (name, group_usage) in groups {
+                        println!(
+                            "    {name:<width$}  {:>4}  {}",
+                            group_usage.entries,
+                            format_size(group_usage.bytes)
+                        );
+                    }
+                }
                 Ok(())
             }
-            CacheCommands::Cleanup { max_age_hours: _ } => {
-                let config = CacheConfig::default();
-                let manager = CacheManager::new(config).await?;
-                manager.cleanup_stale_entries()?;
-                println!("✓ Cleaned up stale cache entries");
+            CacheCommands::Prune {
+                mut older_than,
+                mut max_size,
+                task,
+                package,
+                dry_run,
+            } => {
+                // Without options, prune as the workspace or package retains
+                if older_than.is_none() && max_size.is_none() && task.is_none() && package.is_none()
+                {
+                    if let Some(max_age) = &settings.max_age {
+                        older_than = Some(parse_age(max_age).map_err(Error::configuration)?);
+                        max_size = Some(manager.config().max_size);
+                    }
+                }
+                let report = manager.prune(&PruneOptions {
+                    older_than,
+                    max_size,
+                    tasks: task,
+                    packages: package,
+                    dry_run,
+                })?;
+                print_prune(&report, dry_run);
+                Ok(())
+            }
+            CacheCommands::Verify { repair } => {
+                let report = manager.verify(repair)?;
+                for entry in &report.corrupt {
+                    println!("✗ {}: {}", entry.hash, entry.problem);
+                }
+                if report.corrupt.is_empty() {
+                    println!("✓ {} cached task result(s) are intact", report.checked);
+                } else if report.repaired {
+                    println!(
+                        "✓ Removed {} of {} cached task result(s)",
+                        report.corrupt.len(),
+                        report.checked
+                    );
+                } else {
+                    println!(
+                        "{} of {} cached task result(s) are corrupt; run 'cuenv cache verify --repair' to remove them",
+                        report.corrupt.len(),
+                        report.checked
+                    );
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+            CacheCommands::Path => {
+                println!("{}", manager.path().display());
                 Ok(())
             }
         }
     }
 }
+
+/// The cache settings of the package `config` was loaded from, over those
+/// of its workspace and of the user
+fn settings(config: &Config) -> CacheSettings {
+    let package = config
+        .parse_result
+        .config
+        .as_ref()
+        .and_then(|settings| settings.cache.as_ref());
+    CacheSettings::resolve(&config.working_dir, package)
+}
+
+fn print_prune(report: &PruneReport, dry_run: bool) {
+    for entry in &report.removed {
+        let task = match &entry.package {
+            Some(package) => format!("{package}: {}", entry.task_name),
+            None => entry.task_name.clone(),
+        };
+        println!(
+            "  {task}  {}  {}",
+            format_age(entry.executed_at),
+            format_size(entry.size_bytes)
+        );
+    }
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    println!(
+        "✓ {verb} {} cached task result(s), {}",
+        report.removed.len(),
+        format_size(report.freed_bytes)
+    );
+}
+
+/// How long ago `time` was, in its largest unit
+pub(crate) fn format_age(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    let (count, unit) = match seconds {
+        0..60 => return "just now".to_string(),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} ago")
+}
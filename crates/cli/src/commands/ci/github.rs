@@ -0,0 +1,259 @@
+//! GitHub Actions workflows
+
+use super::Pipeline;
+use cuenv_core::{Error, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Action installing cuenv on the runner
+const SETUP_ACTION: &str = "rawkode/cuenv/github/action/setup-cuenv@main";
+
+/// Runner of the jobs when none is given
+const DEFAULT_RUNNER: &str = "ubuntu-latest";
+
+/// Where jobs keep the task cache, so `actions/cache` can share it
+const CACHE_DIR: &str = "${{ github.workspace }}/.cuenv-cache";
+
+#[derive(Serialize)]
+struct Workflow {
+    name: String,
+    on: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    jobs: BTreeMap<String, Job>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Job {
+    name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    needs: Vec<String>,
+    runs_on: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strategy: Option<Strategy>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    steps: Vec<Step>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Strategy {
+    fail_fast: bool,
+    matrix: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Default)]
+struct Step {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uses: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    with: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run: Option<String>,
+}
+
+/// The workflow running the pipeline, as YAML
+pub fn workflow(pipeline: &Pipeline) -> Result<String> {
+    let mut env = BTreeMap::from([("CUENV_CACHE_BASE_DIR".to_string(), CACHE_DIR.to_string())]);
+    for secret in &pipeline.secrets {
+        env.insert(secret.clone(), format!("${{{{ secrets.{secret} }}}}"));
+    }
+
+    let jobs = pipeline
+        .tasks
+        .iter()
+        .map(|(task, needs)| (job_id(task), job(pipeline, task, needs)))
+        .collect();
+
+    let workflow = Workflow {
+        name: "cuenv".to_string(),
+        on: vec!["push".to_string(), "pull_request".to_string()],
+        env,
+        jobs,
+    };
+    let yaml = serde_yaml::to_string(&workflow)
+        .map_err(|e| Error::configuration(format!("Failed to render the workflow: {e}")))?;
+    Ok(format!(
+        "# Generated by `cuenv ci export --provider github`\n{yaml}"
+    ))
+}
+
+fn job(pipeline: &Pipeline, task: &str, needs: &[String]) -> Job {
+    let mut matrix = BTreeMap::new();
+    let runs_on = match pipeline.runners.as_slice() {
+        [] => DEFAULT_RUNNER.to_string(),
+        [runner] => runner.clone(),
+        runners => {
+            matrix.insert("os".to_string(), runners.to_vec());
+            "${{ matrix.os }}".to_string()
+        }
+    };
+    let mut env = BTreeMap::new();
+    match pipeline.environments.as_slice() {
+        [] => {}
+        [environment] => {
+            env.insert("CUENV_ENV".to_string(), environment.clone());
+        }
+        environments => {
+            matrix.insert("environment".to_string(), environments.to_vec());
+            env.insert(
+                "CUENV_ENV".to_string(),
+                "${{ matrix.environment }}".to_string(),
+            );
+        }
+    }
+
+    Job {
+        name: task.to_string(),
+        needs: needs.iter().map(|need| job_id(need)).collect(),
+        runs_on,
+        strategy: (!matrix.is_empty()).then(|| Strategy {
+            fail_fast: false,
+            matrix: matrix.clone(),
+        }),
+        env,
+        steps: vec![
+            Step {
+                uses: Some("actions/checkout@v4".to_string()),
+                ..Default::default()
+            },
+            Step {
+                uses: Some(SETUP_ACTION.to_string()),
+                ..Default::default()
+            },
+            Step {
+                uses: Some("actions/cache@v4".to_string()),
+                with: cache_keys(task, needs, &matrix),
+                ..Default::default()
+            },
+            Step {
+                run: Some("cuenv env allow .".to_string()),
+                ..Default::default()
+            },
+            Step {
+                name: Some(task.to_string()),
+                run: Some(format!("cuenv task run {task}")),
+                ..Default::default()
+            },
+        ],
+    }
+}
+
+/// The cache of a job, which starts from the results of the jobs it needs
+/// for the same commit and else from its own last results
+fn cache_keys(
+    task: &str,
+    needs: &[String],
+    matrix: &BTreeMap<String, Vec<String>>,
+) -> BTreeMap<String, String> {
+    let variant: String = matrix
+        .keys()
+        .map(|axis| format!("-${{{{ matrix.{axis} }}}}"))
+        .collect();
+    let prefix = |task: &str| format!("cuenv-${{{{ runner.os }}}}{variant}-{}-", job_id(task));
+
+    let mut restore: Vec<String> = needs
+        .iter()
+        .map(|need| format!("{}${{{{ github.sha }}}}", prefix(need)))
+        .collect();
+    restore.push(prefix(task));
+
+    BTreeMap::from([
+        ("path".to_string(), CACHE_DIR.to_string()),
+        (
+            "key".to_string(),
+            format!("{}${{{{ github.sha }}}}", prefix(task)),
+        ),
+        ("restore-keys".to_string(), restore.join("\n")),
+    ])
+}
+
+/// A job id for the task, which GitHub restricts to letters, digits, `-`
+/// and `_`, starting with a letter or `_`
+fn job_id(task: &str) -> String {
+    let id: String = task
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        id
+    } else {
+        format!("_{id}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn pipeline(environments: &[&str], runners: &[&str]) -> Pipeline {
+        Pipeline {
+            tasks: BTreeMap::from([
+                ("build".to_string(), vec![]),
+                ("test:unit".to_string(), vec!["build".to_string()]),
+            ]),
+            environments: environments.iter().map(|e| e.to_string()).collect(),
+            runners: runners.iter().map(|r| r.to_string()).collect(),
+            secrets: BTreeSet::from(["OP_SERVICE_ACCOUNT_TOKEN".to_string()]),
+        }
+    }
+
+    #[test]
+    fn test_workflow() {
+        let yaml = workflow(&pipeline(&[], &[])).unwrap();
+        assert!(yaml.starts_with("# Generated by `cuenv ci export --provider github`\n"));
+
+        let workflow: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(
+            workflow["env"]["OP_SERVICE_ACCOUNT_TOKEN"],
+            "${{ secrets.OP_SERVICE_ACCOUNT_TOKEN }}"
+        );
+        let job = &workflow["jobs"]["test-unit"];
+        assert_eq!(job["name"], "test:unit");
+        assert_eq!(job["needs"][0], "build");
+        assert_eq!(job["runs-on"], "ubuntu-latest");
+        assert!(job.get("strategy").is_none());
+        assert_eq!(job["steps"][4]["run"], "cuenv task run test:unit");
+        assert_eq!(
+            job["steps"][2]["with"]["restore-keys"],
+            "cuenv-${{ runner.os }}-build-${{ github.sha }}\ncuenv-${{ runner.os }}-test-unit-"
+        );
+    }
+
+    #[test]
+    fn test_workflow_matrix() {
+        let yaml = workflow(&pipeline(
+            &["dev", "prod"],
+            &["ubuntu-latest", "macos-latest"],
+        ))
+        .unwrap();
+        let workflow: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        let job = &workflow["jobs"]["build"];
+        assert_eq!(job["runs-on"], "${{ matrix.os }}");
+        assert_eq!(job["strategy"]["fail-fast"], false);
+        assert_eq!(job["strategy"]["matrix"]["environment"][1], "prod");
+        assert_eq!(job["env"]["CUENV_ENV"], "${{ matrix.environment }}");
+        assert_eq!(
+            job["steps"][2]["with"]["key"],
+            "cuenv-${{ runner.os }}-${{ matrix.environment }}-${{ matrix.os }}-build-${{ github.sha }}"
+        );
+    }
+
+    #[test]
+    fn test_job_id() {
+        assert_eq!(job_id("build"), "build");
+        assert_eq!(job_id("test:unit"), "test-unit");
+        assert_eq!(job_id("2fa.check"), "_2fa-check");
+    }
+}
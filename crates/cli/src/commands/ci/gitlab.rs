@@ -0,0 +1,249 @@
+//! GitLab CI pipelines
+
+use super::Pipeline;
+use cuenv_core::{Error, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Image of the jobs, which installs cuenv with Nix
+const IMAGE: &str = "nixos/nix:latest";
+
+const INSTALL: &str =
+    "nix --extra-experimental-features 'nix-command flakes' profile install github:rawkode/cuenv";
+
+/// Where jobs keep the task cache, relative to the checkout
+const CACHE_DIR: &str = ".cuenv-cache";
+
+/// Top-level keys of .gitlab-ci.yml a job cannot be named after
+const KEYWORDS: &[&str] = &[
+    "after_script",
+    "before_script",
+    "cache",
+    "default",
+    "image",
+    "include",
+    "services",
+    "stages",
+    "types",
+    "variables",
+    "workflow",
+];
+
+#[derive(Serialize)]
+struct GitlabCi {
+    stages: Vec<String>,
+    variables: BTreeMap<String, String>,
+    default: Defaults,
+    #[serde(flatten)]
+    jobs: BTreeMap<String, Job>,
+}
+
+#[derive(Serialize)]
+struct Defaults {
+    image: String,
+    before_script: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Job {
+    stage: String,
+    /// Always present, so a job starts when the jobs it needs are done
+    /// instead of after the whole stage before it
+    needs: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    variables: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallel: Option<Parallel>,
+    cache: Cache,
+    script: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Parallel {
+    matrix: Vec<BTreeMap<String, Vec<String>>>,
+}
+
+#[derive(Serialize)]
+struct Cache {
+    key: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fallback_keys: Vec<String>,
+    paths: Vec<String>,
+}
+
+/// The .gitlab-ci.yml running the pipeline, with a stage per dependency
+/// level
+pub fn pipeline(pipeline: &Pipeline) -> Result<String> {
+    let stages = pipeline.stages()?;
+    let mut jobs = BTreeMap::new();
+    for (level, stage) in stages.iter().enumerate() {
+        for task in stage {
+            jobs.insert(job_name(task), job(pipeline, task, level));
+        }
+    }
+
+    let gitlab_ci = GitlabCi {
+        stages: (0..stages.len()).map(stage_name).collect(),
+        variables: BTreeMap::from([(
+            "CUENV_CACHE_BASE_DIR".to_string(),
+            format!("$CI_PROJECT_DIR/{CACHE_DIR}"),
+        )]),
+        default: Defaults {
+            image: IMAGE.to_string(),
+            before_script: vec![INSTALL.to_string(), "cuenv env allow .".to_string()],
+        },
+        jobs,
+    };
+    let yaml = serde_yaml::to_string(&gitlab_ci)
+        .map_err(|e| Error::configuration(format!("Failed to render the pipeline: {e}")))?;
+
+    let mut header = "# Generated by `cuenv ci export --provider gitlab`\n".to_string();
+    // GitLab passes CI/CD variables to every job, so they only need to exist
+    if !pipeline.secrets.is_empty() {
+        let secrets: Vec<&str> = pipeline.secrets.iter().map(String::as_str).collect();
+        header.push_str(&format!(
+            "# Needs the CI/CD variables {}\n",
+            secrets.join(", ")
+        ));
+    }
+    Ok(format!("{header}{yaml}"))
+}
+
+fn job(pipeline: &Pipeline, task: &str, level: usize) -> Job {
+    let mut matrix = BTreeMap::new();
+    let mut variables = BTreeMap::new();
+    let tags = match pipeline.runners.as_slice() {
+        [] => vec![],
+        [runner] => vec![runner.clone()],
+        runners => {
+            matrix.insert("RUNNER".to_string(), runners.to_vec());
+            vec!["$RUNNER".to_string()]
+        }
+    };
+    match pipeline.environments.as_slice() {
+        [] => {}
+        [environment] => {
+            variables.insert("CUENV_ENV".to_string(), environment.clone());
+        }
+        environments => {
+            matrix.insert("CUENV_ENV".to_string(), environments.to_vec());
+        }
+    }
+
+    let needs = &pipeline.tasks[task];
+    let variant: String = matrix.keys().map(|name| format!("-${name}")).collect();
+    let key = |task: &str, git_ref: &str| format!("cuenv-{task}{variant}-{git_ref}");
+    let mut fallback_keys: Vec<String> = needs
+        .iter()
+        .map(|need| key(need, "$CI_COMMIT_REF_SLUG"))
+        .collect();
+    fallback_keys.push(key(task, "$CI_DEFAULT_BRANCH"));
+
+    Job {
+        stage: stage_name(level),
+        needs: needs.iter().map(|need| job_name(need)).collect(),
+        tags,
+        variables,
+        parallel: (!matrix.is_empty()).then(|| Parallel {
+            matrix: vec![matrix.clone()],
+        }),
+        cache: Cache {
+            key: key(task, "$CI_COMMIT_REF_SLUG"),
+            fallback_keys,
+            paths: vec![CACHE_DIR.to_string()],
+        },
+        script: vec![format!("cuenv task run {task}")],
+    }
+}
+
+fn stage_name(level: usize) -> String {
+    format!("stage-{}", level + 1)
+}
+
+/// The job of the task, named after it unless that is a keyword of
+/// .gitlab-ci.yml or would hide the job
+fn job_name(task: &str) -> String {
+    if KEYWORDS.contains(&task) || task.starts_with('.') {
+        format!("task:{task}")
+    } else {
+        task.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn tasks() -> BTreeMap<String, Vec<String>> {
+        BTreeMap::from([
+            ("build".to_string(), vec![]),
+            ("lint".to_string(), vec![]),
+            ("test".to_string(), vec!["build".to_string()]),
+            (
+                "deploy".to_string(),
+                vec!["test".to_string(), "lint".to_string()],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let yaml = pipeline(&Pipeline {
+            tasks: tasks(),
+            environments: vec!["staging".to_string()],
+            runners: vec![],
+            secrets: BTreeSet::from(["OP_SERVICE_ACCOUNT_TOKEN".to_string()]),
+        })
+        .unwrap();
+        assert!(yaml.contains("# Needs the CI/CD variables OP_SERVICE_ACCOUNT_TOKEN\n"));
+
+        let gitlab_ci: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(gitlab_ci["stages"][2], "stage-3");
+        assert_eq!(gitlab_ci["build"]["stage"], "stage-1");
+        assert_eq!(gitlab_ci["lint"]["stage"], "stage-1");
+        assert!(gitlab_ci["build"]["needs"]
+            .as_sequence()
+            .unwrap()
+            .is_empty());
+
+        let deploy = &gitlab_ci["deploy"];
+        assert_eq!(deploy["stage"], "stage-3");
+        assert_eq!(deploy["needs"][0], "test");
+        assert_eq!(deploy["needs"][1], "lint");
+        assert_eq!(deploy["variables"]["CUENV_ENV"], "staging");
+        assert_eq!(deploy["script"][0], "cuenv task run deploy");
+        assert_eq!(deploy["cache"]["key"], "cuenv-deploy-$CI_COMMIT_REF_SLUG");
+        assert_eq!(
+            deploy["cache"]["fallback_keys"][2],
+            "cuenv-deploy-$CI_DEFAULT_BRANCH"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_matrix() {
+        let yaml = pipeline(&Pipeline {
+            tasks: tasks(),
+            environments: vec!["dev".to_string(), "prod".to_string()],
+            runners: vec!["linux".to_string(), "macos".to_string()],
+            secrets: BTreeSet::new(),
+        })
+        .unwrap();
+        let gitlab_ci: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        let test = &gitlab_ci["test"];
+        assert_eq!(test["tags"][0], "$RUNNER");
+        assert_eq!(test["parallel"]["matrix"][0]["CUENV_ENV"][1], "prod");
+        assert_eq!(
+            test["cache"]["key"],
+            "cuenv-test-$CUENV_ENV-$RUNNER-$CI_COMMIT_REF_SLUG"
+        );
+    }
+
+    #[test]
+    fn test_job_name() {
+        assert_eq!(job_name("test:unit"), "test:unit");
+        assert_eq!(job_name("default"), "task:default");
+    }
+}
@@ -0,0 +1,98 @@
+//! The pipeline as JSON, to template workflows of other CIs from
+//!
+//! `version` is raised when a field changes meaning or goes away, so
+//! templates can reject a pipeline they don't understand.
+
+use super::Pipeline;
+use cuenv_core::{Error, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+const VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Export<'a> {
+    version: u32,
+    /// Tasks by dependency level, each level only needing those before it
+    stages: Vec<Vec<String>>,
+    tasks: Vec<Task<'a>>,
+    environments: &'a [String],
+    runners: &'a [String],
+    /// Variables the CI has to provide from its secrets
+    secrets: &'a BTreeSet<String>,
+}
+
+#[derive(Serialize)]
+struct Task<'a> {
+    name: &'a str,
+    needs: &'a [String],
+    /// Index of the task's level in `stages`
+    stage: usize,
+    command: String,
+}
+
+/// The pipeline as pretty-printed JSON
+pub fn pipeline(pipeline: &Pipeline) -> Result<String> {
+    let stages = pipeline.stages()?;
+    let tasks = pipeline
+        .tasks
+        .iter()
+        .map(|(name, needs)| Task {
+            name,
+            needs,
+            stage: stages
+                .iter()
+                .position(|stage| stage.contains(name))
+                .unwrap_or_default(),
+            command: format!("cuenv task run {name}"),
+        })
+        .collect();
+
+    let export = Export {
+        version: VERSION,
+        stages,
+        tasks,
+        environments: &pipeline.environments,
+        runners: &pipeline.runners,
+        secrets: &pipeline.secrets,
+    };
+    let json = serde_json::to_string_pretty(&export).map_err(|e| Error::Json {
+        message: "failed to serialize the pipeline".to_string(),
+        source: e,
+    })?;
+    Ok(format!("{json}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_pipeline() {
+        let json = pipeline(&Pipeline {
+            tasks: BTreeMap::from([
+                ("build".to_string(), vec![]),
+                ("test".to_string(), vec!["build".to_string()]),
+            ]),
+            environments: vec!["ci".to_string()],
+            runners: vec![],
+            secrets: BTreeSet::new(),
+        })
+        .unwrap();
+
+        let export: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(export["version"], 1);
+        assert_eq!(export["stages"], serde_json::json!([["build"], ["test"]]));
+        assert_eq!(
+            export["tasks"][1],
+            serde_json::json!({
+                "name": "test",
+                "needs": ["build"],
+                "stage": 1,
+                "command": "cuenv task run test"
+            })
+        );
+        assert_eq!(export["environments"][0], "ci");
+    }
+}
@@ -0,0 +1,227 @@
+//! `cuenv ci export`, the task graph as a CI workflow
+//!
+//! Each task becomes a job running `cuenv task run <task>`, which waits for
+//! the jobs of the task's dependencies, so CI runs the same graph as a
+//! local run and shows which task failed. Jobs are expanded over a matrix of
+//! runners and environments, get the tokens the secrets of env.cue need,
+//! and share task results through the CI's cache.
+//!
+//! The `json` provider prints the [`Pipeline`] itself, for workflows of
+//! other CIs to be templated from.
+
+mod github;
+mod gitlab;
+mod json;
+
+use clap::Subcommand;
+use cuenv_config::Config;
+use cuenv_core::constants::CUENV_RESOLVER_PREFIX;
+use cuenv_core::{Error, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+/// Tokens a secret resolver needs in CI, by the command resolving it
+const SECRET_TOKENS: &[(&str, &str)] = &[("op", "OP_SERVICE_ACCOUNT_TOKEN")];
+
+#[derive(Subcommand)]
+pub enum CiCommands {
+    /// Print a workflow running the tasks, and the tasks they depend on
+    Export {
+        /// CI the workflow is for, or `json` for the pipeline as JSON
+        #[arg(long, value_parser = ["github", "gitlab", "json"])]
+        provider: String,
+
+        /// Tasks to run, all tasks when none are given
+        tasks: Vec<String>,
+
+        /// Run every task in each of these environments
+        #[arg(short = 'e', long = "env", value_name = "ENVIRONMENT")]
+        environments: Vec<String>,
+
+        /// Run every task on each of these runners, or runner tags on GitLab
+        #[arg(long = "runs-on", value_name = "RUNNER")]
+        runners: Vec<String>,
+
+        /// Write the workflow to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// The tasks of a workflow with what they need to run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    /// Each task with the tasks it waits for
+    pub tasks: BTreeMap<String, Vec<String>>,
+    pub environments: Vec<String>,
+    pub runners: Vec<String>,
+    /// Variables the workflow gets from the CI's secrets
+    pub secrets: BTreeSet<String>,
+}
+
+impl Pipeline {
+    /// The tasks by dependency level: the first stage needs nothing, and
+    /// each later one only tasks of the stages before it
+    pub fn stages(&self) -> Result<Vec<Vec<String>>> {
+        let dependencies: HashMap<String, Vec<String>> = self
+            .tasks
+            .iter()
+            .map(|(task, needs)| (task.clone(), needs.clone()))
+            .collect();
+        let mut stages = cuenv_task::topological_sort(&dependencies)?;
+        for stage in &mut stages {
+            stage.sort();
+        }
+        Ok(stages)
+    }
+}
+
+impl CiCommands {
+    pub fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            CiCommands::Export {
+                provider,
+                tasks,
+                environments,
+                runners,
+                output,
+            } => {
+                let dependencies: HashMap<String, Vec<String>> = config
+                    .get_tasks()
+                    .iter()
+                    .map(|(name, task)| {
+                        (name.clone(), task.dependencies.clone().unwrap_or_default())
+                    })
+                    .collect();
+                let pipeline = Pipeline {
+                    tasks: task_graph(&dependencies, &tasks)?,
+                    environments,
+                    runners,
+                    secrets: secret_tokens(&config.get_env_vars()?),
+                };
+                let workflow = match provider.as_str() {
+                    "github" => github::workflow(&pipeline)?,
+                    "gitlab" => gitlab::pipeline(&pipeline)?,
+                    _ => json::pipeline(&pipeline)?,
+                };
+
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, workflow)
+                            .map_err(|e| Error::file_system(&path, "write", e))?;
+                        println!("✓ Wrote {}", path.display());
+                    }
+                    None => print!("{workflow}"),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `selected` and every task they depend on, with the dependencies of each
+/// that are tasks of this configuration
+fn task_graph(
+    dependencies: &HashMap<String, Vec<String>>,
+    selected: &[String],
+) -> Result<BTreeMap<String, Vec<String>>> {
+    let mut pending: Vec<String> = if selected.is_empty() {
+        dependencies.keys().cloned().collect()
+    } else {
+        selected.to_vec()
+    };
+    if let Some(name) = pending
+        .iter()
+        .find(|name| !dependencies.contains_key(*name))
+    {
+        return Err(Error::usage(format!(
+            "Task '{name}' not found\nRun 'cuenv task' to see available tasks"
+        )));
+    }
+
+    let mut graph = BTreeMap::new();
+    while let Some(name) = pending.pop() {
+        if graph.contains_key(&name) {
+            continue;
+        }
+        // Tasks of other packages run as part of the task needing them
+        let needs: Vec<String> = dependencies[&name]
+            .iter()
+            .filter(|dependency| dependencies.contains_key(*dependency))
+            .cloned()
+            .collect();
+        pending.extend(needs.iter().cloned());
+        graph.insert(name, needs);
+    }
+    Ok(graph)
+}
+
+/// The tokens of [`SECRET_TOKENS`] the secret references among `variables`
+/// need
+pub(crate) fn secret_tokens(variables: &HashMap<String, String>) -> BTreeSet<String> {
+    variables
+        .values()
+        .filter_map(|value| value.strip_prefix(CUENV_RESOLVER_PREFIX))
+        .filter_map(|resolver| serde_json::from_str::<serde_json::Value>(resolver).ok())
+        .filter_map(|resolver| {
+            let command = resolver["cmd"].as_str()?;
+            SECRET_TOKENS
+                .iter()
+                .find(|(resolver, _)| *resolver == command)
+                .map(|(_, token)| token.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependencies(tasks: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        tasks
+            .iter()
+            .map(|(name, needs)| {
+                (
+                    name.to_string(),
+                    needs.iter().map(|need| need.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_task_graph() {
+        let tasks = dependencies(&[
+            ("fmt", &[]),
+            ("build", &[]),
+            ("test", &["build", "shared:setup"]),
+            ("deploy", &["test"]),
+        ]);
+
+        let graph = task_graph(&tasks, &["test".to_string()]).unwrap();
+        assert_eq!(
+            graph,
+            BTreeMap::from([
+                ("build".to_string(), vec![]),
+                ("test".to_string(), vec!["build".to_string()]),
+            ])
+        );
+        assert_eq!(task_graph(&tasks, &[]).unwrap().len(), 4);
+        assert!(task_graph(&tasks, &["lint".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_secret_tokens() {
+        let variables = HashMap::from([
+            (
+                "API_KEY".to_string(),
+                r#"cuenv-resolver://{"cmd":"op","args":["read","op://ci/api/key"]}"#.to_string(),
+            ),
+            ("LOG_LEVEL".to_string(), "debug".to_string()),
+        ]);
+        assert_eq!(
+            secret_tokens(&variables),
+            BTreeSet::from(["OP_SERVICE_ACCOUNT_TOKEN".to_string()])
+        );
+    }
+}
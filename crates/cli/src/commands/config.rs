@@ -0,0 +1,229 @@
+//! `cuenv config`, the effective settings and where each comes from
+//!
+//! The settings a project declares under `config:` in env.cue take
+//! precedence over the `[defaults]` of the user's config.toml, which take
+//! precedence over cuenv's own defaults. Settings only the user configures,
+//! such as `color` and `jobs`, come from config.toml or the defaults.
+
+use cuenv_config::{Config, ConfigSettings, UserConfig};
+use cuenv_core::{Error, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Where the value of a setting comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Project,
+    User,
+    Default,
+}
+
+impl Source {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Project => "project",
+            Self::User => "user",
+            Self::Default => "default",
+        }
+    }
+}
+
+/// A setting with its effective value, `null` when unset
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Setting {
+    pub name: String,
+    pub value: Value,
+    pub source: Source,
+}
+
+#[derive(Serialize)]
+struct Report {
+    user_config: PathBuf,
+    settings: Vec<Setting>,
+}
+
+/// Print the effective settings for `config`
+pub fn execute(config: &Config, json: bool) -> Result<()> {
+    // Report a broken config.toml instead of the defaults it falls back to
+    let user = UserConfig::load()?;
+    let report = Report {
+        user_config: UserConfig::path(),
+        settings: settings(&user, config.parse_result.config.as_ref())?,
+    };
+
+    if json {
+        let output = serde_json::to_string_pretty(&report).map_err(|e| Error::Json {
+            message: "failed to encode settings".to_string(),
+            source: e,
+        })?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    println!("User configuration: {}", report.user_config.display());
+    let name_width = report
+        .settings
+        .iter()
+        .map(|setting| setting.name.len())
+        .max()
+        .unwrap_or(0);
+    let values: Vec<String> = report
+        .settings
+        .iter()
+        .map(|setting| display(&setting.value))
+        .collect();
+    let value_width = values
+        .iter()
+        .map(|value| value.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (setting, value) in report.settings.iter().zip(&values) {
+        println!(
+            "  {:<name_width$}  {value:<value_width$}  {}",
+            setting.name,
+            setting.source.as_str()
+        );
+    }
+    Ok(())
+}
+
+/// The settings of `user` and of the project's `config:`, in that order
+fn settings(user: &UserConfig, project: Option<&ConfigSettings>) -> Result<Vec<Setting>> {
+    let mut settings = Vec::new();
+
+    let defaults: HashMap<String, Value> = flatten(to_object(&UserConfig::default())?)
+        .into_iter()
+        .collect();
+    for (name, value) in flatten(to_object(user)?) {
+        if name.starts_with("defaults.") {
+            continue;
+        }
+        let source = if defaults.get(&name) == Some(&value) {
+            Source::Default
+        } else {
+            Source::User
+        };
+        settings.push(Setting {
+            name,
+            value,
+            source,
+        });
+    }
+
+    let project = to_object(&project.cloned().unwrap_or_default())?;
+    let user_defaults = to_object(&user.defaults)?;
+    for (name, value) in project {
+        let setting = match (value, user_defaults.get(&name)) {
+            (Value::Null, Some(value)) if !value.is_null() => Setting {
+                name,
+                value: value.clone(),
+                source: Source::User,
+            },
+            (Value::Null, _) => Setting {
+                name,
+                value: Value::Null,
+                source: Source::Default,
+            },
+            (value, _) => Setting {
+                name,
+                value,
+                source: Source::Project,
+            },
+        };
+        settings.push(setting);
+    }
+    Ok(settings)
+}
+
+fn to_object(value: &impl Serialize) -> Result<Map<String, Value>> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err(Error::configuration("settings must be a table")),
+        Err(e) => Err(Error::Json {
+            message: "failed to encode settings".to_string(),
+            source: e,
+        }),
+    }
+}
+
+/// The values of `object`, with those of nested tables named `table.key`
+fn flatten(object: Map<String, Value>) -> Vec<(String, Value)> {
+    let mut values = Vec::new();
+    for (key, value) in object {
+        match value {
+            Value::Object(table) => values.extend(
+                flatten(table)
+                    .into_iter()
+                    .map(|(name, value)| (format!("{key}.{name}"), value)),
+            ),
+            value => values.push((key, value)),
+        }
+    }
+    values
+}
+
+/// A value as it is printed, `-` when unset
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(string) => string.clone(),
+        Value::Array(items) if items.is_empty() => "-".to_string(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn find<'a>(settings: &'a [Setting], name: &str) -> &'a Setting {
+        settings
+            .iter()
+            .find(|setting| setting.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_settings() {
+        let user = UserConfig {
+            jobs: Some(4),
+            defaults: ConfigSettings {
+                output_format: Some("simple".to_string()),
+                cache_mode: Some("read".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let project = ConfigSettings {
+            output_format: Some("tui".to_string()),
+            ..Default::default()
+        };
+        let settings = settings(&user, Some(&project)).unwrap();
+
+        assert_eq!(find(&settings, "jobs").value, json!(4));
+        assert_eq!(find(&settings, "jobs").source, Source::User);
+        assert_eq!(find(&settings, "color").source, Source::Default);
+        assert_eq!(find(&settings, "cache.max_size").value, Value::Null);
+        assert_eq!(find(&settings, "outputFormat").value, json!("tui"));
+        assert_eq!(find(&settings, "outputFormat").source, Source::Project);
+        assert_eq!(find(&settings, "cacheMode").value, json!("read"));
+        assert_eq!(find(&settings, "cacheMode").source, Source::User);
+        assert_eq!(find(&settings, "auditMode").source, Source::Default);
+        assert!(settings
+            .iter()
+            .all(|setting| !setting.name.starts_with("defaults")));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(display(&Value::Null), "-");
+        assert_eq!(display(&json!("auto")), "auto");
+        assert_eq!(display(&json!(["~/src", "/work"])), "~/src, /work");
+        assert_eq!(display(&json!(true)), "true");
+    }
+}
@@ -0,0 +1,127 @@
+use super::task::daemon::DaemonTasks;
+use clap::Subcommand;
+use cuenv_core::{Error, Result};
+use cuenv_env::daemon::{self, Request, Response, TaskRunner};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long `start` waits for the daemon to answer
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Subcommand)]
+pub enum DaemonCommands {
+    /// Start the daemon in the background
+    Start {
+        /// Serve Prometheus metrics on http://ADDR/metrics
+        #[arg(long, value_name = "ADDR")]
+        metrics: Option<SocketAddr>,
+        /// Also run the tasks of `cuenv task <task>`
+        #[arg(long)]
+        tasks: bool,
+    },
+    /// Stop the running daemon
+    Stop,
+    /// Show whether the daemon is running
+    Status,
+    /// Run the daemon in the foreground
+    Run {
+        /// Serve Prometheus metrics on http://ADDR/metrics
+        #[arg(long, value_name = "ADDR")]
+        metrics: Option<SocketAddr>,
+        /// Also run the tasks of `cuenv task <task>`
+        #[arg(long)]
+        tasks: bool,
+    },
+}
+
+impl DaemonCommands {
+    pub async fn execute(self) -> Result<()> {
+        let socket = daemon::socket_path();
+        match self {
+            DaemonCommands::Start { metrics, tasks } => start(&socket, metrics, tasks).await,
+            DaemonCommands::Stop => match daemon::request(&socket, &Request::Shutdown) {
+                Ok(_) => {
+                    println!("✓ Stopped the cuenv daemon");
+                    Ok(())
+                }
+                Err(_) => {
+                    println!("The cuenv daemon is not running");
+                    Ok(())
+                }
+            },
+            DaemonCommands::Status => {
+                match daemon::request(&socket, &Request::Status) {
+                    Ok(Response::Status(status)) => {
+                        println!("cuenv daemon running (pid {})", status.pid);
+                        println!("  Socket: {}", socket.display());
+                        println!("  Cached environments: {}", status.cached);
+                        println!("  Uptime: {}s", status.uptime_secs);
+                        if let Some(addr) = status.metrics_addr {
+                            println!("  Metrics: http://{addr}/metrics");
+                        }
+                        if status.runs_tasks {
+                            println!("  Runs tasks: yes");
+                        }
+                    }
+                    _ => println!("The cuenv daemon is not running"),
+                }
+                Ok(())
+            }
+            DaemonCommands::Run { metrics, tasks } => {
+                let runner = tasks.then(|| Arc::new(DaemonTasks) as Arc<dyn TaskRunner>);
+                daemon::serve(&socket, metrics, runner).await
+            }
+        }
+    }
+}
+
+async fn start(socket: &Path, metrics: Option<SocketAddr>, tasks: bool) -> Result<()> {
+    if daemon::request(socket, &Request::Status).is_ok() {
+        println!("The cuenv daemon is already running");
+        return Ok(());
+    }
+
+    let exe = std::env::current_exe()
+        .map_err(|e| Error::configuration(format!("Failed to locate the cuenv binary: {e}")))?;
+    let mut args = vec!["daemon".to_string(), "run".to_string()];
+    if let Some(addr) = metrics {
+        args.extend(["--metrics".to_string(), addr.to_string()]);
+    }
+    if tasks {
+        args.push("--tasks".to_string());
+    }
+    let mut command = Command::new(&exe);
+    command
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // Keep the daemon out of the shell's process group, so Ctrl-C at the
+    // prompt does not stop it
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    command.spawn().map_err(|e| {
+        Error::command_execution(
+            exe.to_string_lossy(),
+            args,
+            format!("Failed to start the cuenv daemon: {e}"),
+            None,
+        )
+    })?;
+
+    let deadline = Instant::now() + START_TIMEOUT;
+    while Instant::now() < deadline {
+        if daemon::request(socket, &Request::Status).is_ok() {
+            println!("✓ Started the cuenv daemon on {}", socket.display());
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    Err(Error::configuration(format!(
+        "The cuenv daemon did not start listening on {}",
+        socket.display()
+    )))
+}
@@ -0,0 +1,262 @@
+//! `cuenv devcontainer generate`, a dev container with cuenv
+//!
+//! Writes `.devcontainer/devcontainer.json` and a local feature installing
+//! cuenv with its shell hook, so terminals of the container, whether on a
+//! machine or in Codespaces, load env.cue like the shell of a developer
+//! does. Creating the container allows the workspace and runs the setup
+//! task, and the tokens the secrets of env.cue need are passed on from the
+//! host or from the secrets of Codespaces.
+
+use clap::Subcommand;
+use cuenv_config::Config;
+use cuenv_core::{Error, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Directory of the dev container configuration
+const DEVCONTAINER_DIR: &str = ".devcontainer";
+
+/// Image the container is built from unless another is given
+const DEFAULT_IMAGE: &str = "mcr.microsoft.com/devcontainers/base:ubuntu";
+
+/// Directory of the feature, relative to [`DEVCONTAINER_DIR`]
+const FEATURE: &str = "cuenv";
+
+const FEATURE_JSON: &str = r#"{
+  "id": "cuenv",
+  "version": "1.0.0",
+  "name": "cuenv",
+  "description": "Installs cuenv and loads env.cue in interactive shells",
+  "options": {
+    "version": {
+      "type": "string",
+      "default": "latest",
+      "description": "Release of cuenv to install"
+    }
+  },
+  "installsAfter": ["ghcr.io/devcontainers/features/common-utils"]
+}
+"#;
+
+const INSTALL_SH: &str = r#"#!/bin/sh
+# Installs cuenv and its shell hook, generated by `cuenv devcontainer generate`
+set -e
+
+case "$(uname -m)" in
+    x86_64 | amd64) ARCH=x86_64 ;;
+    aarch64 | arm64) ARCH=aarch64 ;;
+    *)
+        echo "cuenv: unsupported architecture $(uname -m)" >&2
+        exit 1
+        ;;
+esac
+if [ "${VERSION:-latest}" = latest ]; then
+    URL="https://github.com/rawkode/cuenv/releases/latest/download/cuenv-linux-$ARCH"
+else
+    URL="https://github.com/rawkode/cuenv/releases/download/$VERSION/cuenv-linux-$ARCH"
+fi
+
+if command -v curl >/dev/null 2>&1; then
+    curl -fsSL "$URL" -o /usr/local/bin/cuenv
+else
+    wget -qO /usr/local/bin/cuenv "$URL"
+fi
+chmod +x /usr/local/bin/cuenv
+
+# Load env.cue in the interactive shells of every user
+echo 'eval "$(cuenv shell init bash)"' >> /etc/bash.bashrc
+if [ -d /etc/zsh ]; then
+    echo 'eval "$(cuenv shell init zsh)"' >> /etc/zsh/zshrc
+fi
+"#;
+
+#[derive(Subcommand)]
+pub enum DevcontainerCommands {
+    /// Write .devcontainer/devcontainer.json and a feature installing cuenv
+    Generate {
+        /// Image the container is built from
+        #[arg(long, default_value = DEFAULT_IMAGE)]
+        image: String,
+
+        /// Task run once the container is created, when it exists
+        #[arg(long, default_value = "setup")]
+        task: String,
+
+        /// Environment the container loads
+        #[arg(short = 'e', long = "env", value_name = "ENVIRONMENT")]
+        environment: Option<String>,
+
+        /// Overwrite an existing devcontainer.json
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// The parts of devcontainer.json cuenv sets
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DevContainer {
+    name: String,
+    image: String,
+    features: BTreeMap<String, serde_json::Value>,
+    post_create_command: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    remote_env: BTreeMap<String, String>,
+    /// Secrets Codespaces asks for when the container is created
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    secrets: BTreeMap<String, Secret>,
+}
+
+#[derive(Debug, Serialize)]
+struct Secret {
+    description: String,
+}
+
+/// The dev container of the project `name`, running `setup` when it is a
+/// task
+fn devcontainer(
+    name: &str,
+    image: &str,
+    setup: Option<&str>,
+    environment: Option<&str>,
+    tokens: &BTreeSet<String>,
+) -> DevContainer {
+    let mut post_create_command = "cuenv env allow .".to_string();
+    if let Some(setup) = setup {
+        post_create_command.push_str(&format!(" && cuenv task run {setup}"));
+    }
+
+    let mut remote_env: BTreeMap<String, String> = tokens
+        .iter()
+        .map(|token| (token.clone(), format!("${{localEnv:{token}}}")))
+        .collect();
+    if let Some(environment) = environment {
+        remote_env.insert("CUENV_ENV".to_string(), environment.to_string());
+    }
+
+    DevContainer {
+        name: name.to_string(),
+        image: image.to_string(),
+        features: BTreeMap::from([(format!("./{FEATURE}"), serde_json::json!({}))]),
+        post_create_command,
+        remote_env,
+        secrets: tokens
+            .iter()
+            .map(|token| {
+                let description = "Resolves the secrets of env.cue".to_string();
+                (token.clone(), Secret { description })
+            })
+            .collect(),
+    }
+}
+
+impl DevcontainerCommands {
+    pub fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            DevcontainerCommands::Generate {
+                image,
+                task,
+                environment,
+                force,
+            } => {
+                let dir = config.working_dir.join(DEVCONTAINER_DIR);
+                let file = dir.join("devcontainer.json");
+                if file.exists() && !force {
+                    return Err(Error::usage(format!(
+                        "{} already exists. Use --force to overwrite it.",
+                        file.display()
+                    )));
+                }
+
+                let name = config
+                    .working_dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "cuenv".to_string());
+                let has_task = config.get_tasks().contains_key(&task);
+                let tokens = super::ci::secret_tokens(&config.get_env_vars()?);
+                let devcontainer = devcontainer(
+                    &name,
+                    &image,
+                    has_task.then_some(task.as_str()),
+                    environment.as_deref(),
+                    &tokens,
+                );
+                let json =
+                    serde_json::to_string_pretty(&devcontainer).map_err(|e| Error::Json {
+                        message: "Failed to serialize devcontainer.json".to_string(),
+                        source: e,
+                    })?;
+
+                let feature = dir.join(FEATURE);
+                std::fs::create_dir_all(&feature)
+                    .map_err(|e| Error::file_system(&feature, "create", e))?;
+                write(&file, &format!("{json}\n"))?;
+                write(&feature.join("devcontainer-feature.json"), FEATURE_JSON)?;
+                let install = feature.join("install.sh");
+                write(&install, INSTALL_SH)?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&install, std::fs::Permissions::from_mode(0o755))
+                        .map_err(|e| Error::file_system(&install, "set permissions", e))?;
+                }
+
+                println!("✓ Wrote {DEVCONTAINER_DIR}/devcontainer.json and the cuenv feature");
+                if !has_task {
+                    println!("! No '{task}' task, so creating the container only allows env.cue");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write(path: &Path, content: &str) -> Result<()> {
+    std::fs::write(path, content).map_err(|e| Error::file_system(path, "write", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devcontainer() {
+        let tokens = BTreeSet::from(["OP_SERVICE_ACCOUNT_TOKEN".to_string()]);
+        let json = serde_json::to_value(devcontainer(
+            "app",
+            DEFAULT_IMAGE,
+            Some("setup"),
+            Some("dev"),
+            &tokens,
+        ))
+        .unwrap();
+
+        assert_eq!(json["features"], serde_json::json!({ "./cuenv": {} }));
+        assert_eq!(
+            json["postCreateCommand"],
+            "cuenv env allow . && cuenv task run setup"
+        );
+        assert_eq!(
+            json["remoteEnv"],
+            serde_json::json!({
+                "CUENV_ENV": "dev",
+                "OP_SERVICE_ACCOUNT_TOKEN": "${localEnv:OP_SERVICE_ACCOUNT_TOKEN}",
+            })
+        );
+        assert!(json["secrets"]["OP_SERVICE_ACCOUNT_TOKEN"]["description"].is_string());
+
+        let json = serde_json::to_value(devcontainer(
+            "app",
+            DEFAULT_IMAGE,
+            None,
+            None,
+            &BTreeSet::new(),
+        ))
+        .unwrap();
+        assert_eq!(json["postCreateCommand"], "cuenv env allow .");
+        assert!(json.get("remoteEnv").is_none());
+        assert!(json.get("secrets").is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use cuenv_config::{CueParser, ParseOptions, ParseResult};
+use cuenv_config::{package_name, primary_file, CueParser, ParseOptions, ParseResult};
 use cuenv_core::{Error, Result};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
@@ -61,12 +61,16 @@ impl PackageDiscovery {
         }
     }
 
-    /// Discover all env.cue files from the module root
+    /// Discover all configured directories from the module root
+    ///
+    /// Each is returned as its primary file: env.cue, or the first `.cue`
+    /// file of the package when the configuration is split across files.
     pub fn discover_env_files(&mut self, start_path: &Path) -> Result<Vec<PathBuf>> {
         // Find the module root first
         let module_root = Self::find_module_root(start_path)?;
         self.module_root = Some(module_root.clone());
 
+        let package = package_name();
         let mut env_files = Vec::new();
 
         // Walk the directory tree from module root
@@ -83,9 +87,11 @@ impl PackageDiscovery {
                 continue;
             }
 
-            // Check if this is an env.cue file
-            if path.is_file() && path.file_name() == Some(std::ffi::OsStr::new("env.cue")) {
-                env_files.push(path.to_path_buf());
+            // Each configured directory is reported by its primary file
+            if path.is_dir() {
+                if let Some(file) = primary_file(path, &package) {
+                    env_files.push(file);
+                }
             }
         }
 
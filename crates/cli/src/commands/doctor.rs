@@ -0,0 +1,498 @@
+//! `cuenv doctor`: check that cuenv works on this machine
+//!
+//! Each check reports what it found and, when something is wrong, what to
+//! do about it: whether the shell hook is installed, whether CUE evaluation
+//! works, for the bridge and for the configuration in the current
+//! directory, whether a devenv.nix there is loaded, whether the user
+//! configuration file is valid, whether the cache directory is writable and
+//! how large it is, whether a configured remote cache answers,
+//! and which of the sandboxing features tasks can ask for the kernel
+//! supports. Doctor runs without loading the configuration, so that it
+//! also works when the configuration is broken.
+
+use crate::commands::init::hook_line;
+use crate::commands::vet;
+use crate::platform::PlatformOps;
+use cuenv_cache::config::CacheConfigLoader;
+use cuenv_cache::{CacheConfig, RemoteCache};
+use cuenv_config::{
+    package_name, primary_file, CacheSettings, CueParser, ParseOptions, UserConfig,
+};
+use cuenv_core::{Error, Result, DEFAULT_PACKAGE_NAME};
+use cuenv_security::AccessRestrictions;
+use serde::Serialize;
+use std::path::Path;
+
+#[cfg(unix)]
+use crate::platform::UnixPlatform as Platform;
+#[cfg(windows)]
+use crate::platform::WindowsPlatform as Platform;
+
+/// The outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Works, but not as well as it could
+    Warning,
+    Failed,
+    /// Not applicable here
+    Skipped,
+}
+
+impl Status {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Ok => "✓",
+            Self::Warning => "!",
+            Self::Failed => "✗",
+            Self::Skipped => "-",
+        }
+    }
+}
+
+/// What a check found
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+/// Run every check, for the configuration in `directory`
+pub async fn checks(directory: &Path) -> Vec<Check> {
+    let mut checks = vec![
+        shell_hook(),
+        cue_bridge(),
+        configuration(directory),
+        devenv(directory),
+        user_configuration(),
+        cache_directory(directory),
+        remote_cache(directory).await,
+    ];
+    checks.extend(sandbox());
+    checks
+}
+
+fn shell_hook() -> Check {
+    const NAME: &str = "Shell hook";
+
+    // The hook runs the cuenv found on PATH
+    let current = std::env::current_exe().ok();
+    let on_path = which::which("cuenv").ok();
+    let same_binary = match (&current, &on_path) {
+        (Some(current), Some(on_path)) => {
+            current.canonicalize().ok() == on_path.canonicalize().ok()
+        }
+        _ => false,
+    };
+    let Some(on_path) = on_path else {
+        return Check::new(
+            NAME,
+            Status::Failed,
+            "cuenv is not on PATH, so the hook cannot run it",
+        )
+        .fix("Add the directory holding cuenv to PATH");
+    };
+
+    let shell = match Platform::get_current_shell() {
+        Ok(shell) => shell,
+        Err(e) => return Check::new(NAME, Status::Skipped, e),
+    };
+    let Some((rc_file, line)) = hook_line(shell) else {
+        return Check::new(
+            NAME,
+            Status::Skipped,
+            format!("The profile of {} is not checked", shell.as_str()),
+        );
+    };
+    let content = std::fs::read_to_string(&rc_file).unwrap_or_default();
+    let rc = rc_file.display();
+
+    if content.contains("cuenv shell init") {
+        if same_binary {
+            Check::new(NAME, Status::Ok, format!("Loaded by {rc}"))
+        } else {
+            Check::new(
+                NAME,
+                Status::Warning,
+                format!(
+                    "Loaded by {rc}, but the hook runs {}, not this cuenv",
+                    on_path.display()
+                ),
+            )
+            .fix("Put this cuenv first on PATH, or remove the other installation")
+        }
+    } else if content.contains("_cuenv_hook") {
+        // A copy of a hook script is not updated with cuenv
+        Check::new(
+            NAME,
+            Status::Warning,
+            format!("{rc} has a copy of the hook, which may be out of date"),
+        )
+        .fix(format!("Replace the copy in {rc} with: {line}"))
+    } else {
+        Check::new(NAME, Status::Failed, format!("Not loaded by {rc}")).fix(format!(
+            "Run 'cuenv init --hook {}', or add to {rc}: {line}",
+            shell.as_str()
+        ))
+    }
+}
+
+/// Evaluate a configuration of one variable
+fn cue_bridge() -> Check {
+    const NAME: &str = "CUE evaluation";
+
+    let evaluate = || -> Result<bool> {
+        let dir = tempfile::tempdir().map_err(|e| Error::configuration(e.to_string()))?;
+        let file = dir.path().join("env.cue");
+        std::fs::write(
+            &file,
+            format!("package {DEFAULT_PACKAGE_NAME}\n\nenv: CUENV_DOCTOR: \"ok\"\n"),
+        )
+        .map_err(|e| Error::file_system(&file, "write", e))?;
+        let result = CueParser::eval_package_with_options(
+            dir.path(),
+            DEFAULT_PACKAGE_NAME,
+            &ParseOptions::default(),
+        )?;
+        Ok(result.variables.get("CUENV_DOCTOR").map(String::as_str) == Some("ok"))
+    };
+
+    match evaluate() {
+        Ok(true) => Check::new(NAME, Status::Ok, "The CUE bridge evaluates configurations"),
+        Ok(false) => Check::new(
+            NAME,
+            Status::Failed,
+            "The CUE bridge returned a wrong result",
+        )
+        .fix("Reinstall cuenv; the bundled CUE bridge is damaged"),
+        Err(e) => Check::new(NAME, Status::Failed, format!("The CUE bridge failed: {e}"))
+            .fix("Reinstall cuenv; building it needs a Go toolchain for the CUE bridge"),
+    }
+}
+
+/// Check the configuration in `directory`, as `cuenv vet` does
+fn configuration(directory: &Path) -> Check {
+    const NAME: &str = "Configuration";
+
+    let package = package_name();
+    let Some(file) = primary_file(directory, &package) else {
+        return Check::new(
+            NAME,
+            Status::Skipped,
+            format!("No configuration in {}", directory.display()),
+        );
+    };
+    let problems = vet::vet(directory);
+    match problems.first() {
+        None => Check::new(NAME, Status::Ok, format!("{} is valid", file.display())),
+        Some(problem) => Check::new(
+            NAME,
+            Status::Failed,
+            format!(
+                "{} has {} problem(s), the first: {}",
+                file.display(),
+                problems.len(),
+                problem.message
+            ),
+        )
+        .fix(format!(
+            "Run 'cuenv vet {}' to see them",
+            directory.display()
+        )),
+    }
+}
+
+/// Whether the configuration in `directory` builds on a devenv.nix there
+fn devenv(directory: &Path) -> Check {
+    const NAME: &str = "devenv";
+
+    if !directory.join("devenv.nix").is_file() {
+        return Check::new(
+            NAME,
+            Status::Skipped,
+            format!("No devenv.nix in {}", directory.display()),
+        );
+    }
+
+    let package = package_name();
+    if primary_file(directory, &package).is_none() {
+        return Check::new(NAME, Status::Warning, "devenv.nix is not loaded")
+            .fix("Run 'cuenv init' for a configuration building on its environment");
+    }
+    // An invalid configuration is reported by its own check
+    let Ok(result) =
+        CueParser::eval_package_with_options(directory, &package, &ParseOptions::default())
+    else {
+        return Check::new(NAME, Status::Skipped, "The configuration does not evaluate");
+    };
+
+    if result.devenv.is_none() {
+        Check::new(NAME, Status::Warning, "devenv.nix is not loaded")
+            .fix("Add 'devenv: {}' to the configuration to build on its environment")
+    } else if which::which("devenv").is_err() {
+        Check::new(NAME, Status::Failed, "devenv is not installed")
+            .fix("Install devenv, see https://devenv.sh/getting-started/")
+    } else {
+        Check::new(NAME, Status::Ok, "The environment builds on devenv.nix")
+    }
+}
+
+fn user_configuration() -> Check {
+    const NAME: &str = "User configuration";
+
+    let path = UserConfig::path();
+    if !path.exists() {
+        return Check::new(
+            NAME,
+            Status::Skipped,
+            format!("{} does not exist, so defaults apply", path.display()),
+        );
+    }
+    match UserConfig::load() {
+        Ok(_) => Check::new(NAME, Status::Ok, format!("{} is valid", path.display())),
+        Err(e) => Check::new(NAME, Status::Failed, e.to_string())
+            .fix("Fix the file; until then, cuenv ignores it"),
+    }
+}
+
+fn cache_directory(directory: &Path) -> Check {
+    const NAME: &str = "Cache directory";
+
+    let config = CacheConfigLoader::load_for(&CacheSettings::resolve(directory, None))
+        .map(|configuration| CacheConfig::from(&configuration.global))
+        .unwrap_or_default();
+    let dir = config.base_dir;
+    let writable = std::fs::create_dir_all(&dir)
+        .and_then(|()| tempfile::NamedTempFile::new_in(&dir).map(drop));
+    if let Err(e) = writable {
+        return Check::new(
+            NAME,
+            Status::Failed,
+            format!("{} is not writable: {e}", dir.display()),
+        )
+        .fix(format!(
+            "Fix the permissions of {}, or point XDG_CACHE_HOME to a writable directory",
+            dir.display()
+        ));
+    }
+
+    let size: u64 = walkdir::WalkDir::new(&dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum();
+    let max_size = config.max_size;
+    let detail = format!("{} in {}", format_size(size), dir.display());
+    if size > max_size {
+        Check::new(
+            NAME,
+            Status::Warning,
+            format!("{detail}, more than the {} limit", format_size(max_size)),
+        )
+        .fix("Run 'cuenv cache prune', or 'cuenv cache clear' to start over")
+    } else {
+        Check::new(NAME, Status::Ok, detail)
+    }
+}
+
+/// The remote cache of the user configuration, or of the workspace
+/// `directory` is in
+async fn remote_cache(directory: &Path) -> Check {
+    const NAME: &str = "Remote cache";
+
+    let Some(remote) = CacheSettings::resolve(directory, None).remote else {
+        return Check::new(
+            NAME,
+            Status::Skipped,
+            "Not configured; task results are cached on this machine only",
+        );
+    };
+    let remote = match RemoteCache::new(&remote) {
+        Ok(remote) => remote,
+        Err(e) => {
+            return Check::new(NAME, Status::Failed, e.to_string()).fix(
+                "Set 'remote' to the URL of the cache, such as https://cache.example.com/cuenv",
+            )
+        }
+    };
+    match remote.probe().await {
+        Ok(()) => Check::new(
+            NAME,
+            Status::Ok,
+            format!(
+                "{} answers; task results are shared through it",
+                remote.host()
+            ),
+        ),
+        Err(e) => Check::new(
+            NAME,
+            Status::Warning,
+            format!("{e}; tasks run with the local cache only until it answers"),
+        )
+        .fix("Check that the remote cache is up and reachable from this machine"),
+    }
+}
+
+/// The restrictions tasks can ask for in `security`
+fn sandbox() -> Vec<Check> {
+    let landlock = if AccessRestrictions::is_landlock_supported() {
+        Check::new(
+            "Landlock",
+            Status::Ok,
+            "Disk access of tasks can be restricted",
+        )
+    } else {
+        Check::new(
+            "Landlock",
+            Status::Warning,
+            "Tasks that restrict disk access fail without --insecure-allow",
+        )
+        .fix("Use Linux 5.13+ with Landlock enabled, e.g. 'landlock' in the lsm= boot parameter")
+    };
+
+    let network = AccessRestrictions {
+        restrict_network: true,
+        ..Default::default()
+    };
+    let landlock_network = if network.is_enforceable() {
+        Check::new(
+            "Landlock network",
+            Status::Ok,
+            "Network access of tasks can be restricted",
+        )
+    } else {
+        Check::new(
+            "Landlock network",
+            Status::Warning,
+            "Tasks that restrict network access fail without --insecure-allow",
+        )
+        .fix("Use Linux 6.7+, whose Landlock restricts TCP connections")
+    };
+
+    vec![landlock, landlock_network, seccomp()]
+}
+
+#[cfg(target_os = "linux")]
+fn seccomp() -> Check {
+    const NAME: &str = "Seccomp";
+
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    if !status.lines().any(|line| line.starts_with("Seccomp:")) {
+        return Check::new(NAME, Status::Warning, "The kernel has no seccomp support")
+            .fix("Use a kernel built with CONFIG_SECCOMP_FILTER");
+    }
+    match cuenv_security::SeccompProfile::load("default").and_then(|profile| profile.compile()) {
+        Ok(_) => Check::new(NAME, Status::Ok, "Syscalls of tasks can be filtered"),
+        Err(e) => Check::new(
+            NAME,
+            Status::Failed,
+            format!("The default profile does not compile: {e}"),
+        )
+        .fix("Report this as a bug; seccomp profiles are built for this architecture"),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn seccomp() -> Check {
+    Check::new(
+        "Seccomp",
+        Status::Skipped,
+        "Seccomp is only available on Linux",
+    )
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Print the checks, as JSON with `json`, and fail when one failed
+pub fn report(checks: &[Check], json: bool) -> Result<()> {
+    if json {
+        let output = serde_json::to_string_pretty(checks).map_err(|e| Error::Json {
+            message: "failed to encode checks".to_string(),
+            source: e,
+        })?;
+        println!("{output}");
+    } else {
+        for check in checks {
+            println!("{} {}: {}", check.status.symbol(), check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                println!("    fix: {fix}");
+            }
+        }
+    }
+
+    if checks.iter().any(|check| check.status == Status::Failed) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+pub async fn execute(json: bool) -> Result<()> {
+    let directory = std::env::current_dir()?;
+    report(&checks(&directory).await, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_configuration_is_skipped_without_one() {
+        let temp = tempfile::tempdir().unwrap();
+        let check = configuration(temp.path());
+        assert_eq!(check.status, Status::Skipped);
+        assert!(check.fix.is_none());
+    }
+
+    #[test]
+    fn test_devenv_without_configuration() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(devenv(temp.path()).status, Status::Skipped);
+
+        std::fs::write(temp.path().join("devenv.nix"), "{ pkgs, ... }: { }").unwrap();
+        let check = devenv(temp.path());
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.fix.unwrap().contains("cuenv init"));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::directory::DirectoryManager;
-use cuenv_core::{Result, ENV_CUE_FILENAME};
+use cuenv_config::{has_package, package_name};
+use cuenv_core::Result;
 use cuenv_env::EnvManager;
 use std::{env, path::PathBuf};
 
@@ -15,8 +16,8 @@ pub async fn execute(directory: PathBuf) -> Result<()> {
     dir_manager.allow_directory(&abs_dir)?;
     println!("✓ Allowed directory: {}", abs_dir.display());
 
-    // If there's an env.cue file in the allowed directory, load it (which will execute hooks)
-    if abs_dir.join(ENV_CUE_FILENAME).exists() {
+    // If the allowed directory has a configuration, load it (which will execute hooks)
+    if has_package(&abs_dir, &package_name()) {
         let mut env_manager = EnvManager::new();
         match env_manager.load_env(&abs_dir).await {
             Ok(_) => {
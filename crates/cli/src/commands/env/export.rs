@@ -1,5 +1,6 @@
 use crate::platform::{PlatformOps, Shell};
-use cuenv_core::{Result, ENV_CUE_FILENAME};
+use cuenv_config::{has_package, package_name};
+use cuenv_core::{masking, Error, Result};
 use cuenv_env::EnvManager;
 use cuenv_shell::ShellType;
 use std::env;
@@ -10,7 +11,7 @@ use crate::platform::UnixPlatform as Platform;
 #[cfg(windows)]
 use crate::platform::WindowsPlatform as Platform;
 
-pub async fn execute(shell: Option<String>, all: bool) -> Result<()> {
+pub async fn execute(shell: Option<String>, all: bool, reveal: bool) -> Result<()> {
     let shell_type = match shell {
         Some(s) => ShellType::from_name(&s),
         None => match Platform::get_current_shell() {
@@ -25,27 +26,43 @@ pub async fn execute(shell: Option<String>, all: bool) -> Result<()> {
 
     let shell_impl = shell_type.as_shell();
 
+    // Sensitive values are masked unless explicitly revealed
+    let print = |output: &str| {
+        if reveal {
+            print!("{output}");
+        } else {
+            print!("{}", masking::mask(output));
+        }
+    };
+
     if all {
-        // Export all system environment variables
-        for (key, value) in env::vars() {
-            println!("{}", shell_impl.export(&key, &value));
+        // Export all system environment variables, including values that
+        // are not Unicode
+        for (key, value) in env::vars_os() {
+            match key.to_str() {
+                Some(key) if shell_impl.accepts_name(key) => {
+                    print(&format!("{}\n", shell_impl.export_os(key, &value)));
+                }
+                _ => log::warn!(
+                    "Not exporting {}: the name cannot be assigned in this shell",
+                    key.to_string_lossy()
+                ),
+            }
         }
     } else {
         // Export only the loaded environment from env.cue
         let current_dir = env::current_dir()
             .map_err(|e| cuenv_core::Error::file_system(".", "get current directory", e))?;
 
-        if current_dir.join(ENV_CUE_FILENAME).exists() {
+        if has_package(&current_dir, &package_name()) {
             let mut env_manager = EnvManager::new();
             env_manager.load_env(&current_dir).await?;
 
-            match env_manager.export_for_shell(shell_type.name()) {
-                Ok(output) => print!("{output}"),
-                Err(e) => return Err(e),
-            }
+            print(&shell_impl.apply(&env_manager.changes()?));
         } else {
-            eprintln!("No {ENV_CUE_FILENAME} found in current directory");
-            std::process::exit(1);
+            return Err(Error::configuration(
+                "No cuenv configuration found in current directory",
+            ));
         }
     }
 
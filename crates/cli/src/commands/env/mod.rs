@@ -1,10 +1,15 @@
 use clap::Subcommand;
 use std::path::PathBuf;
 
+use self::snapshot::SnapshotCommands;
+
 mod allow;
 mod deny;
 mod export;
+mod print;
 mod prune;
+mod select;
+mod snapshot;
 mod status;
 
 #[derive(Subcommand)]
@@ -45,10 +50,52 @@ pub enum EnvCommands {
         /// Export all system environment variables, not just loaded ones
         #[arg(long)]
         all: bool,
+
+        /// Print sensitive values instead of masking them
+        #[arg(long)]
+        reveal: bool,
+    },
+
+    /// Print the variables set by the environment
+    Print {
+        /// Only show variables whose name matches a glob, e.g. 'AWS_*'
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Show the file and line that defined each variable
+        #[arg(long)]
+        source: bool,
+
+        /// Only show variables that differ from the surrounding environment
+        #[arg(long)]
+        only_changed: bool,
+
+        /// Output format (table or json)
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Choose the environments loaded in this directory, e.g. base+gpu+staging
+    ///
+    /// Environments joined with '+' apply in order, later ones overriding
+    /// earlier ones. Without an argument, prints the current selection.
+    Use {
+        /// Environment names joined with '+'
+        environments: Option<String>,
+
+        /// Forget the selection for this directory
+        #[arg(long, conflicts_with = "environments")]
+        clear: bool,
     },
 
     /// Prune stale environment state
     Prune,
+
+    /// Save resolved environments by name and apply them again later
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
 }
 
 impl EnvCommands {
@@ -61,8 +108,19 @@ impl EnvCommands {
                 format,
                 verbose,
             } => status::execute(hooks, format, verbose).await,
-            EnvCommands::Export { shell, all } => export::execute(shell, all).await,
+            EnvCommands::Export { shell, all, reveal } => export::execute(shell, all, reveal).await,
+            EnvCommands::Print {
+                filter,
+                source,
+                only_changed,
+                format,
+            } => print::execute(filter, source, only_changed, format).await,
+            EnvCommands::Use {
+                environments,
+                clear,
+            } => select::execute(environments, clear).await,
             EnvCommands::Prune => prune::execute().await,
+            EnvCommands::Snapshot { command } => command.execute().await,
         }
     }
 }
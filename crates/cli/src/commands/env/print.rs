@@ -0,0 +1,98 @@
+use cuenv_config::{has_package, package_name};
+use cuenv_core::{masking, Error, Result};
+use cuenv_env::{EnvManager, LoadedVariable};
+use globset::Glob;
+use std::env;
+
+pub async fn execute(
+    filter: Option<String>,
+    source: bool,
+    only_changed: bool,
+    format: String,
+) -> Result<()> {
+    let current_dir =
+        env::current_dir().map_err(|e| Error::file_system(".", "get current directory", e))?;
+    if !has_package(&current_dir, &package_name()) {
+        return Err(Error::configuration(
+            "No cuenv configuration found in current directory",
+        ));
+    }
+
+    let matcher = filter
+        .map(|pattern| {
+            Glob::new(&pattern)
+                .map(|glob| glob.compile_matcher())
+                .map_err(|e| Error::configuration(format!("Invalid filter {pattern:?}: {e}")))
+        })
+        .transpose()?;
+
+    let mut env_manager = EnvManager::new();
+    env_manager.load_env(&current_dir).await?;
+
+    let variables: Vec<LoadedVariable> = env_manager
+        .loaded_variables()
+        .into_iter()
+        .filter(|var| !only_changed || var.changed)
+        .filter(|var| matcher.as_ref().is_none_or(|m| m.is_match(&var.name)))
+        .map(|mut var| {
+            var.value = masking::mask(&var.value).into_owned();
+            if !source {
+                var.source = None;
+            }
+            var
+        })
+        .collect();
+
+    match format.as_str() {
+        "json" => {
+            let json = serde_json::to_string_pretty(&variables).map_err(|e| Error::Json {
+                message: "failed to serialize variables".to_string(),
+                source: e,
+            })?;
+            println!("{json}");
+        }
+        "table" => print_table(&variables, source),
+        other => {
+            return Err(Error::configuration(format!(
+                "Unknown format '{other}', expected table or json"
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+fn print_table(variables: &[LoadedVariable], source: bool) {
+    let name_width = variables
+        .iter()
+        .map(|var| var.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("NAME".len());
+
+    if source {
+        let value_width = variables
+            .iter()
+            .map(|var| var.value.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("VALUE".len());
+        println!("{:name_width$}  {:value_width$}  SOURCE", "NAME", "VALUE");
+        for var in variables {
+            let origin = var
+                .source
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:name_width$}  {:value_width$}  {origin}",
+                var.name, var.value
+            );
+        }
+    } else {
+        println!("{:name_width$}  VALUE", "NAME");
+        for var in variables {
+            println!("{:name_width$}  {}", var.name, var.value);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use cuenv_config::ENVIRONMENT_SEPARATOR;
+use cuenv_core::{Error, Result};
+use cuenv_env::EnvironmentSelection;
+use std::env;
+
+pub async fn execute(environments: Option<String>, clear: bool) -> Result<()> {
+    let current_dir =
+        env::current_dir().map_err(|e| Error::file_system(".", "get current directory", e))?;
+    let selection = EnvironmentSelection::user();
+
+    if clear {
+        if selection.clear(&current_dir)? {
+            println!("✓ Cleared the environment selection");
+        } else {
+            println!("No environment selected for this directory");
+        }
+        return Ok(());
+    }
+
+    let Some(spec) = environments else {
+        match selection.get(&current_dir) {
+            Some(spec) => println!("{spec}"),
+            None => println!("No environment selected for this directory"),
+        }
+        return Ok(());
+    };
+
+    let names: Vec<&str> = spec.split(ENVIRONMENT_SEPARATOR).map(str::trim).collect();
+    if names
+        .iter()
+        .any(|name| name.is_empty() || name.contains(char::is_whitespace))
+    {
+        return Err(Error::configuration(format!(
+            "Invalid environment list {spec:?}, expected names joined with '{ENVIRONMENT_SEPARATOR}' such as base+gpu+staging"
+        )));
+    }
+
+    let spec = names.join(&ENVIRONMENT_SEPARATOR.to_string());
+    selection.set(&current_dir, &spec)?;
+    println!("✓ Using {spec} in {}", current_dir.display());
+    Ok(())
+}
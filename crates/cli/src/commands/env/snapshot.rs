@@ -0,0 +1,168 @@
+//! `cuenv env snapshot`, resolved environments saved to apply again later
+//!
+//! Saving evaluates the configuration of the current directory over the
+//! environment from before cuenv loaded one, as the shell hook does.
+//! Restoring prints the commands that apply the saved variables to the
+//! shell, and records them as the loaded environment, so the hook keeps
+//! them until the configuration changes or the shell leaves the directory:
+//!
+//! ```text
+//! eval "$(cuenv env snapshot restore pre-upgrade)"
+//! ```
+
+use crate::commands::cache::format_age;
+use crate::commands::shell::print_shell_changes;
+use crate::platform::PlatformOps;
+use clap::Subcommand;
+use cuenv_config::{has_package, package_name};
+use cuenv_core::{Error, Result, CUENV_CAPABILITIES_VAR, CUENV_ENV_VAR};
+use cuenv_env::manager::environment::SupervisorMode;
+use cuenv_env::{EnvManager, SavedSnapshots, StateManager};
+use cuenv_shell::ShellType;
+use cuenv_utils::sync::env::{InstanceLock, SyncEnv};
+use std::collections::HashMap;
+use std::env;
+
+// Import the platform-specific implementation
+#[cfg(unix)]
+use crate::platform::UnixPlatform as Platform;
+#[cfg(windows)]
+use crate::platform::WindowsPlatform as Platform;
+
+#[derive(Subcommand)]
+pub enum SnapshotCommands {
+    /// Save the resolved environment of this directory under a name
+    Save {
+        /// Name of the snapshot, e.g. pre-upgrade
+        name: String,
+
+        /// Environment to use (e.g., dev, staging, production)
+        #[arg(short = 'e', long = "env")]
+        environment: Option<String>,
+
+        /// Capabilities to enable (can be specified multiple times)
+        #[arg(short = 'c', long = "capability")]
+        capabilities: Vec<String>,
+    },
+
+    /// Print the shell commands applying a saved environment again
+    Restore {
+        /// Name of the snapshot
+        name: String,
+    },
+
+    /// List the saved snapshots
+    List,
+}
+
+impl SnapshotCommands {
+    pub async fn execute(self) -> Result<()> {
+        match self {
+            SnapshotCommands::Save {
+                name,
+                environment,
+                capabilities,
+            } => save(&name, environment, capabilities).await,
+            SnapshotCommands::Restore { name } => restore(&name).await,
+            SnapshotCommands::List => list(),
+        }
+    }
+}
+
+async fn save(name: &str, environment: Option<String>, capabilities: Vec<String>) -> Result<()> {
+    let _lock = InstanceLock::acquire()?;
+    let current_dir =
+        env::current_dir().map_err(|e| Error::file_system(".", "get current directory", e))?;
+    if !has_package(&current_dir, &package_name()) {
+        return Err(Error::configuration(
+            "No cuenv configuration found in current directory",
+        ));
+    }
+
+    let env_name = environment.or_else(|| env::var(CUENV_ENV_VAR).ok());
+    let mut caps = capabilities;
+    if caps.is_empty() {
+        if let Ok(env_caps) = env::var(CUENV_CAPABILITIES_VAR) {
+            caps = env_caps
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+    }
+
+    let snapshot = EnvManager::new()
+        .evaluate(
+            &current_dir,
+            base_env()?,
+            env_name,
+            caps,
+            None,
+            SupervisorMode::Foreground,
+        )
+        .await?;
+    SavedSnapshots::user().save(name, &snapshot)?;
+    println!(
+        "✓ Saved {} variables of {} as '{name}'",
+        snapshot.variables().len(),
+        current_dir.display()
+    );
+    Ok(())
+}
+
+async fn restore(name: &str) -> Result<()> {
+    let _lock = InstanceLock::acquire()?;
+    let saved = SavedSnapshots::user().load(name)?;
+    let current_dir =
+        env::current_dir().map_err(|e| Error::file_system(".", "get current directory", e))?;
+    if saved.snapshot.dir() != current_dir {
+        return Err(Error::configuration(format!(
+            "Snapshot '{name}' was saved in {}; restore it from there",
+            saved.snapshot.dir().display()
+        )));
+    }
+
+    let shell_env: HashMap<String, String> = env::vars().collect();
+    let base = base_env()?;
+    saved.snapshot.rebase(base).apply().await?;
+
+    let shell = Platform::get_current_shell()
+        .map(|shell| ShellType::from_name(shell.as_str()))
+        .unwrap_or(ShellType::Bash);
+    print_shell_changes(shell.as_shell().as_ref(), &shell_env);
+    eprintln!(
+        "# cuenv: restored snapshot '{name}', saved {}",
+        format_age(saved.saved_at.into())
+    );
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let snapshots = SavedSnapshots::user().list()?;
+    if snapshots.is_empty() {
+        println!("No saved snapshots");
+        return Ok(());
+    }
+    let width = snapshots
+        .iter()
+        .map(|saved| saved.name.len())
+        .max()
+        .unwrap_or(0);
+    for saved in snapshots {
+        println!(
+            "{:<width$}  {}  {}",
+            saved.name,
+            format_age(saved.saved_at.into()),
+            saved.snapshot.dir().display()
+        );
+    }
+    Ok(())
+}
+
+/// The environment of this process from before cuenv loaded one into it
+fn base_env() -> Result<HashMap<String, String>> {
+    if let Some(diff) = StateManager::get_diff()? {
+        diff.restore()?;
+    }
+    Ok(SyncEnv::vars()?.into_iter().collect())
+}
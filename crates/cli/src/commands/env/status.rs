@@ -1,9 +1,11 @@
+use cuenv_config::{has_package, local_override, package_name};
 use cuenv_core::Result;
 use cuenv_env::EnvManager;
 use cuenv_utils::hooks_status::{
     calculate_elapsed, should_show_completed_status, HookState, HooksStatusManager,
 };
 use std::env;
+use std::path::{Path, PathBuf};
 
 pub async fn execute(hooks: bool, format: String, verbose: bool) -> Result<()> {
     // Get status for current directory (directory-aware)
@@ -45,6 +47,16 @@ pub async fn execute(hooks: bool, format: String, verbose: bool) -> Result<()> {
                 println!(); // Add spacing
             }
 
+            let local_files = local_overrides(&current_dir);
+            if !local_files.is_empty() {
+                println!("Local Overrides");
+                println!("===============");
+                for file in &local_files {
+                    println!("  {}", file.display());
+                }
+                println!();
+            }
+
             // Also show environment diff unless hooks flag is set
             if !hooks {
                 println!("Environment Status");
@@ -58,6 +70,15 @@ pub async fn execute(hooks: bool, format: String, verbose: bool) -> Result<()> {
     Ok(())
 }
 
+/// env.local.cue files in the configured directories of `dir` and its ancestors
+fn local_overrides(dir: &Path) -> Vec<PathBuf> {
+    let package = package_name();
+    dir.ancestors()
+        .filter(|ancestor| has_package(ancestor, &package))
+        .filter_map(local_override)
+        .collect()
+}
+
 fn format_starship_output(status: &cuenv_utils::hooks_status::HooksStatus, verbose: bool) {
     let running_count = status
         .hooks
@@ -3,16 +3,20 @@ use cuenv_core::{Result, CUENV_CAPABILITIES_VAR, CUENV_ENV_VAR};
 use cuenv_env::manager::environment::SupervisorMode;
 use cuenv_env::EnvManager;
 use std::env;
+use std::path::Path;
 use std::sync::Arc;
 
 pub async fn execute(
     _config: Arc<Config>,
     environment: Option<String>,
     capabilities: Vec<String>,
-    command: String,
-    args: Vec<String>,
+    command: Vec<String>,
     _audit: bool,
 ) -> Result<()> {
+    let Some((command, args)) = command.split_first() else {
+        return Err(cuenv_core::Error::configuration("No command to run"));
+    };
+
     let current_dir = env::current_dir()
         .map_err(|e| cuenv_core::Error::file_system(".", "get current directory", e))?;
     let mut env_manager = EnvManager::new();
@@ -29,20 +33,29 @@ pub async fn execute(
         }
     }
 
-    // Load environment using the same approach as task commands
+    // Load environment using the same approach as task commands. Without
+    // explicit capabilities they are inferred from the program's name.
+    let program = Path::new(command)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(command);
     env_manager
         .load_env_with_options(
             &current_dir,
             env_name,
             caps,
-            None,
+            Some(program),
             SupervisorMode::Synchronous,
         )
         .await?;
 
-    // Execute the command in the prepared environment
-    // Use run_command_with_current_env to include variables set by preload hooks
-    let exit_code = env_manager.run_command_with_current_env(&command, &args)?;
+    // Secrets deferred by lazySecrets are only resolved now that a process starts
+    env_manager.resolve_deferred_secrets()?;
+
+    // Execute the command in the prepared environment, which on Unix replaces
+    // this process. Use run_command_with_current_env to include variables set
+    // by preload hooks
+    let exit_code = env_manager.run_command_with_current_env(command, args)?;
 
     std::process::exit(exit_code);
 }
@@ -0,0 +1,61 @@
+//! `cuenv fmt`: format configuration files like `cue fmt`
+
+use super::vet::{report, Problem};
+use cuenv_config::{package_files, package_name, CueParser};
+use cuenv_core::{Error, Result, ENV_LOCAL_CUE_FILENAME};
+use std::path::{Path, PathBuf};
+
+/// The CUE files to format for a path: the file itself, or the package
+/// files and `env.local.cue` of a directory
+fn files_for(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+
+    let mut files = package_files(path, &package_name());
+    let local = path.join(ENV_LOCAL_CUE_FILENAME);
+    if local.is_file() {
+        files.push(local);
+    }
+    files
+}
+
+/// Format `file`, returning whether its contents changed
+fn format_file(file: &Path, check: bool) -> Result<bool> {
+    let source =
+        std::fs::read_to_string(file).map_err(|e| Error::file_system(file, "read CUE file", e))?;
+    let formatted = CueParser::format(file, &source)?;
+    if formatted == source {
+        return Ok(false);
+    }
+
+    if !check {
+        std::fs::write(file, formatted)
+            .map_err(|e| Error::file_system(file, "write CUE file", e))?;
+    }
+    Ok(true)
+}
+
+pub async fn execute(paths: Vec<PathBuf>, check: bool, json: bool) -> Result<()> {
+    let paths = if paths.is_empty() {
+        vec![std::env::current_dir()?]
+    } else {
+        paths
+    };
+
+    let mut problems = Vec::new();
+    for file in paths.iter().flat_map(|path| files_for(path)) {
+        match format_file(&file, check) {
+            Ok(false) => {}
+            Ok(true) if check => problems.push(Problem::in_file(&file, "File is not formatted")),
+            Ok(true) => {
+                if !json {
+                    println!("Formatted {}", file.display());
+                }
+            }
+            Err(e) => problems.extend(Problem::from_error(&file, &e)),
+        }
+    }
+
+    report(&problems, json)
+}
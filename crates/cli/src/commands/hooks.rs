@@ -0,0 +1,339 @@
+//! `cuenv hooks install`, tasks run as git hooks
+//!
+//! The tasks are run by a pre-commit or pre-push hook written to the
+//! repository's hooks directory, or by entries of `.pre-commit-config.yaml`
+//! for projects using the pre-commit framework. Either way the files the
+//! commit or push changes are in `CUENV_CHANGED_FILES`, one per line, and
+//! optionally also given to the tasks as arguments.
+
+use clap::Subcommand;
+use cuenv_config::Config;
+use cuenv_core::constants::CUENV_CHANGED_FILES_VAR;
+use cuenv_core::{Error, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// First line of the hooks cuenv writes, telling them from others
+const MARKER: &str = "# Installed by `cuenv hooks install`";
+
+/// Configuration file of the pre-commit framework
+const PRE_COMMIT_CONFIG: &str = ".pre-commit-config.yaml";
+
+/// Lists the files staged for the commit
+const STAGED_FILES: &str = r#"# Runs cuenv tasks on the files staged for the commit
+CUENV_CHANGED_FILES="$(git diff --cached --name-only --diff-filter=ACMR)"
+"#;
+
+/// Lists the files changed by the pushed commits, read from the refs git
+/// gives the hook; a new branch is compared with all remote branches
+const PUSHED_FILES: &str = r#"# Runs cuenv tasks on the files changed by the pushed commits
+CUENV_CHANGED_FILES="$(
+    while read -r local_ref local_sha remote_ref remote_sha; do
+        # Deleted refs change no files
+        case "$local_sha" in *[!0]*) ;; *) continue ;; esac
+        case "$remote_sha" in
+            *[!0]*) git diff --name-only --diff-filter=ACMR "$remote_sha" "$local_sha" ;;
+            *) git log --name-only --format= --diff-filter=ACMR "$local_sha" --not --remotes ;;
+        esac
+    done | sort -u
+)"
+"#;
+
+#[derive(Subcommand)]
+pub enum HooksCommands {
+    /// Run tasks from a git hook, or from the pre-commit framework
+    Install {
+        /// Tasks to run, in order
+        #[arg(required = true)]
+        tasks: Vec<String>,
+
+        /// Hook running the tasks
+        #[arg(long, default_value = "pre-commit", value_parser = ["pre-commit", "pre-push"])]
+        stage: String,
+
+        /// Also give the changed files to the tasks as arguments
+        #[arg(long)]
+        pass_files: bool,
+
+        /// Add the tasks to .pre-commit-config.yaml instead of writing a git
+        /// hook
+        #[arg(long)]
+        pre_commit_config: bool,
+
+        /// Overwrite a hook cuenv did not write
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+impl HooksCommands {
+    pub fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            HooksCommands::Install {
+                tasks,
+                stage,
+                pass_files,
+                pre_commit_config,
+                force,
+            } => {
+                for task in &tasks {
+                    if !config.get_tasks().contains_key(task) {
+                        return Err(Error::usage(format!("No task named '{task}'")));
+                    }
+                }
+
+                if pre_commit_config {
+                    let path = config.working_dir.join(PRE_COMMIT_CONFIG);
+                    let existing = match std::fs::read_to_string(&path) {
+                        Ok(content) => Some(content),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                        Err(e) => return Err(Error::file_system(&path, "read", e)),
+                    };
+                    let content =
+                        pre_commit_entries(existing.as_deref(), &stage, &tasks, pass_files)?;
+                    std::fs::write(&path, content)
+                        .map_err(|e| Error::file_system(&path, "write", e))?;
+
+                    println!("✓ Added {} to {PRE_COMMIT_CONFIG}", tasks.join(", "));
+                    println!("  Run `pre-commit install --hook-type {stage}` to enable them");
+                    return Ok(());
+                }
+
+                let path = hooks_dir(&config.working_dir)?.join(&stage);
+                if let Ok(content) = std::fs::read_to_string(&path) {
+                    if !content.contains(MARKER) && !force {
+                        return Err(Error::usage(format!(
+                            "{} already exists. Use --force to overwrite it.",
+                            path.display()
+                        )));
+                    }
+                }
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| Error::file_system(parent, "create", e))?;
+                }
+                std::fs::write(&path, hook_script(&stage, &tasks, pass_files))
+                    .map_err(|e| Error::file_system(&path, "write", e))?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                        .map_err(|e| Error::file_system(&path, "set permissions", e))?;
+                }
+
+                println!("✓ Installed the {stage} hook running {}", tasks.join(", "));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The hooks directory of the repository `dir` is in, following
+/// `core.hooksPath` and worktrees
+fn hooks_dir(dir: &Path) -> Result<PathBuf> {
+    let args = vec![
+        "rev-parse".to_string(),
+        "--git-path".to_string(),
+        "hooks".to_string(),
+    ];
+    let output = Command::new("git")
+        .args(&args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| {
+            Error::command_execution("git", args.clone(), format!("failed to run git: {e}"), None)
+        })?;
+    if !output.status.success() {
+        return Err(Error::command_execution(
+            "git",
+            args,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            output.status.code(),
+        ));
+    }
+    Ok(dir.join(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// The git hook of `stage` running `tasks`
+fn hook_script(stage: &str, tasks: &[String], pass_files: bool) -> String {
+    let mut script = format!("#!/bin/sh\n{MARKER}\n");
+    script.push_str(if stage == "pre-push" {
+        PUSHED_FILES
+    } else {
+        STAGED_FILES
+    });
+    script.push_str(&format!(
+        "export {CUENV_CHANGED_FILES_VAR}\n[ -n \"${CUENV_CHANGED_FILES_VAR}\" ] || exit 0\nset -e\n"
+    ));
+    if pass_files {
+        // Split the files on newlines only, without expanding globs
+        script.push_str("IFS='\n'\nset -f\n");
+    }
+    for task in tasks {
+        script.push_str(&format!("cuenv task run {}", quote(task)));
+        if pass_files {
+            script.push_str(&format!(" ${CUENV_CHANGED_FILES_VAR}"));
+        }
+        script.push('\n');
+    }
+    script
+}
+
+/// `existing`, the content of .pre-commit-config.yaml if any, with a local
+/// hook for each of `tasks`, replacing those added before
+fn pre_commit_entries(
+    existing: Option<&str>,
+    stage: &str,
+    tasks: &[String],
+    pass_files: bool,
+) -> Result<String> {
+    use serde_yaml::{Mapping, Value};
+
+    let invalid = |message: String| Error::configuration(format!("{PRE_COMMIT_CONFIG}: {message}"));
+    let mut config: Value = match existing {
+        Some(content) if !content.trim().is_empty() => {
+            serde_yaml::from_str(content).map_err(|e| invalid(e.to_string()))?
+        }
+        _ => Value::Mapping(Mapping::new()),
+    };
+    let config = config
+        .as_mapping_mut()
+        .ok_or_else(|| invalid("not a mapping".to_string()))?;
+    let repos = config
+        .entry("repos".into())
+        .or_insert_with(|| Value::Sequence(Vec::new()))
+        .as_sequence_mut()
+        .ok_or_else(|| invalid("repos is not a list".to_string()))?;
+
+    let local = match repos
+        .iter()
+        .position(|repo| repo.get("repo").and_then(Value::as_str) == Some("local"))
+    {
+        Some(index) => index,
+        None => {
+            let mut repo = Mapping::new();
+            repo.insert("repo".into(), "local".into());
+            repo.insert("hooks".into(), Value::Sequence(Vec::new()));
+            repos.push(Value::Mapping(repo));
+            repos.len() - 1
+        }
+    };
+    let hooks = repos[local]
+        .as_mapping_mut()
+        .ok_or_else(|| invalid("the local repo is not a mapping".to_string()))?
+        .entry("hooks".into())
+        .or_insert_with(|| Value::Sequence(Vec::new()))
+        .as_sequence_mut()
+        .ok_or_else(|| invalid("the hooks of the local repo are not a list".to_string()))?;
+
+    for task in tasks {
+        let id = format!("cuenv-{task}");
+        let command = format!("cuenv task run {}", quote(task));
+        // pre-commit gives the files as arguments; the shell moves them to
+        // the variable unless the task takes them
+        let entry = if pass_files {
+            command.clone()
+        } else {
+            let script = format!(
+                "export {CUENV_CHANGED_FILES_VAR}=\"$(printf '%s\\n' \"$@\")\"; exec {command}"
+            );
+            format!("sh -c {} cuenv", quote(&script))
+        };
+
+        let mut hook = Mapping::new();
+        hook.insert("id".into(), id.clone().into());
+        hook.insert("name".into(), command.into());
+        hook.insert("entry".into(), entry.into());
+        hook.insert("language".into(), "system".into());
+        hook.insert(
+            "stages".into(),
+            Value::Sequence(vec![stage.to_string().into()]),
+        );
+        hook.insert("require_serial".into(), true.into());
+
+        let hook = Value::Mapping(hook);
+        match hooks
+            .iter_mut()
+            .find(|existing| existing.get("id").and_then(Value::as_str) == Some(id.as_str()))
+        {
+            Some(existing) => *existing = hook,
+            None => hooks.push(hook),
+        }
+    }
+
+    serde_yaml::to_string(config).map_err(|e| invalid(e.to_string()))
+}
+
+/// `word` as one shell word
+fn quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:/@+=".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_hook_script() {
+        let script = hook_script("pre-commit", &tasks(&["lint", "npm:test"]), false);
+        assert!(script.starts_with(&format!("#!/bin/sh\n{MARKER}\n")));
+        assert!(script.contains("git diff --cached"));
+        assert!(script.ends_with("cuenv task run lint\ncuenv task run npm:test\n"));
+
+        let script = hook_script("pre-push", &tasks(&["check"]), true);
+        assert!(script.contains("while read -r local_ref"));
+        assert!(script.ends_with("cuenv task run check $CUENV_CHANGED_FILES\n"));
+    }
+
+    #[test]
+    fn test_pre_commit_entries() {
+        let existing = "repos:
+- repo: https://github.com/pre-commit/pre-commit-hooks
+  rev: v4.6.0
+  hooks:
+  - id: trailing-whitespace
+- repo: local
+  hooks:
+  - id: cuenv-lint
+    entry: old
+";
+        let content =
+            pre_commit_entries(Some(existing), "pre-commit", &tasks(&["lint"]), true).unwrap();
+        let config: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        let repos = config["repos"].as_sequence().unwrap();
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0]["hooks"][0]["id"], "trailing-whitespace");
+        let hooks = repos[1]["hooks"].as_sequence().unwrap();
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(hooks[0]["entry"], "cuenv task run lint");
+        assert_eq!(hooks[0]["stages"][0], "pre-commit");
+
+        let content = pre_commit_entries(None, "pre-push", &tasks(&["test"]), false).unwrap();
+        let config: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        let hook = &config["repos"][0]["hooks"][0];
+        assert_eq!(config["repos"][0]["repo"], "local");
+        assert_eq!(hook["id"], "cuenv-test");
+        assert_eq!(
+            hook["entry"],
+            r#"sh -c 'export CUENV_CHANGED_FILES="$(printf '\''%s\n'\'' "$@")"; exec cuenv task run test' cuenv"#
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("npm:build"), "npm:build");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+}
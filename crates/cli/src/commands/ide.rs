@@ -0,0 +1,406 @@
+//! `cuenv ide export`, the tasks and environment of env.cue in an editor
+//!
+//! For VS Code, each task becomes an entry of `.vscode/tasks.json` running
+//! `cuenv task run`, and the loaded environment is written to
+//! `.vscode/cuenv.env`, which the launch configurations of
+//! `.vscode/launch.json` read as their `envFile`. Programs started from the
+//! debugger then see the variables a shell in the project does.
+//!
+//! Secrets are written as the references cuenv exports for them, never
+//! resolved, so the file holds nothing the configuration does not. It is
+//! still created readable by the user only and kept out of git by
+//! `.vscode/.gitignore`, as env.local.cue may contribute to it.
+
+use clap::Subcommand;
+use cuenv_config::Config;
+use cuenv_core::{Error, Result};
+use cuenv_env::manager::environment::SupervisorMode;
+use cuenv_env::EnvManager;
+use cuenv_utils::atomic_file::write_private;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Directory of the VS Code workspace settings
+const VSCODE_DIR: &str = ".vscode";
+
+/// File the environment is written to, relative to [`VSCODE_DIR`]
+const ENV_FILE: &str = "cuenv.env";
+
+/// Prefix of the labels of the tasks cuenv writes, telling them from others
+const LABEL_PREFIX: &str = "cuenv: ";
+
+#[derive(Subcommand)]
+pub enum IdeCommands {
+    /// Write the tasks and the environment for an editor
+    Export {
+        /// Editor to export for
+        #[arg(long, default_value = "vscode", value_parser = ["vscode"])]
+        editor: String,
+
+        /// Environment to load
+        #[arg(short = 'e', long = "env")]
+        environment: Option<String>,
+
+        /// Capabilities to enable (can be specified multiple times)
+        #[arg(short = 'c', long = "capability")]
+        capabilities: Vec<String>,
+    },
+}
+
+impl IdeCommands {
+    pub async fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            IdeCommands::Export {
+                editor: _,
+                environment,
+                capabilities,
+            } => {
+                let dir = config.working_dir.join(VSCODE_DIR);
+                std::fs::create_dir_all(&dir).map_err(|e| Error::file_system(&dir, "create", e))?;
+
+                let tasks: BTreeMap<String, Option<String>> = config
+                    .get_tasks()
+                    .iter()
+                    .map(|(name, task)| (name.clone(), task.description.clone()))
+                    .collect();
+                let path = dir.join("tasks.json");
+                let existing = read_optional(&path)?;
+                let content = tasks_json(
+                    existing.as_deref(),
+                    &tasks,
+                    environment.as_deref(),
+                    &capabilities,
+                )?;
+                write(&path, &content)?;
+
+                let mut env_manager = EnvManager::new();
+                env_manager
+                    .load_env_with_options(
+                        &config.working_dir,
+                        environment,
+                        capabilities,
+                        None,
+                        SupervisorMode::Synchronous,
+                    )
+                    .await?;
+                let variables: BTreeMap<String, String> = env_manager
+                    .loaded_variables()
+                    .into_iter()
+                    .filter(|variable| variable.changed)
+                    .map(|variable| (variable.name, variable.value))
+                    .collect();
+                let env_path = dir.join(ENV_FILE);
+                write_private(&env_path, env_file(&variables).as_bytes())?;
+                let gitignore = dir.join(".gitignore");
+                if let Some(content) = ignore_env_file(read_optional(&gitignore)?.as_deref()) {
+                    write(&gitignore, &content)?;
+                }
+
+                println!(
+                    "✓ Wrote {} tasks to {VSCODE_DIR}/tasks.json and {} variables to {VSCODE_DIR}/{ENV_FILE}",
+                    tasks.len(),
+                    variables.len()
+                );
+
+                let launch = dir.join("launch.json");
+                match read_optional(&launch)? {
+                    Some(content) => {
+                        let (content, updated) = launch_json(&content)?;
+                        if updated > 0 {
+                            write(&launch, &content)?;
+                            println!("✓ {updated} launch configurations now read {ENV_FILE}");
+                        }
+                    }
+                    None => println!(
+                        "  Add \"envFile\": \"{}\" to your launch configurations",
+                        env_file_reference()
+                    ),
+                }
+                println!(
+                    "  Secrets are written as references; run programs needing their values with `cuenv exec`"
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The `envFile` of the launch configurations
+fn env_file_reference() -> String {
+    format!("${{workspaceFolder}}/{VSCODE_DIR}/{ENV_FILE}")
+}
+
+/// `existing`, the content of tasks.json if any, with an entry running each
+/// of `tasks`, replacing the entries written before
+fn tasks_json(
+    existing: Option<&str>,
+    tasks: &BTreeMap<String, Option<String>>,
+    environment: Option<&str>,
+    capabilities: &[String],
+) -> Result<String> {
+    let mut document = match existing {
+        Some(content) => parse_jsonc(content, "tasks.json")?,
+        None => json!({ "version": "2.0.0" }),
+    };
+    let object = document
+        .as_object_mut()
+        .ok_or_else(|| Error::configuration("tasks.json is not an object"))?;
+    let entries = object
+        .entry("tasks")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .ok_or_else(|| Error::configuration("The tasks of tasks.json are not a list"))?;
+    entries.retain(|entry| {
+        !entry["label"]
+            .as_str()
+            .is_some_and(|label| label.starts_with(LABEL_PREFIX))
+    });
+
+    for (name, description) in tasks {
+        // Options go before the task, as what follows it is passed to it
+        let mut args = vec!["task".to_string()];
+        if let Some(environment) = environment {
+            args.extend(["-e".to_string(), environment.to_string()]);
+        }
+        for capability in capabilities {
+            args.extend(["-c".to_string(), capability.clone()]);
+        }
+        args.extend(["run".to_string(), name.clone()]);
+
+        let mut entry = json!({
+            "label": format!("{LABEL_PREFIX}{name}"),
+            "type": "process",
+            "command": "cuenv",
+            "args": args,
+            "options": { "cwd": "${workspaceFolder}" },
+            "problemMatcher": [],
+        });
+        if let Some(description) = description {
+            entry["detail"] = json!(description);
+        }
+        if name == "build" || name == "test" {
+            entry["group"] = json!(name);
+        }
+        entries.push(entry);
+    }
+
+    to_string(&document, "tasks.json")
+}
+
+/// launch.json with each configuration without an `envFile` reading the
+/// exported environment, and the number of configurations changed
+fn launch_json(content: &str) -> Result<(String, usize)> {
+    let mut document = parse_jsonc(content, "launch.json")?;
+    let mut updated = 0;
+    if let Some(configurations) = document["configurations"].as_array_mut() {
+        for configuration in configurations {
+            if let Some(configuration) = configuration.as_object_mut() {
+                if !configuration.contains_key("envFile") {
+                    configuration.insert("envFile".to_string(), json!(env_file_reference()));
+                    updated += 1;
+                }
+            }
+        }
+    }
+    Ok((to_string(&document, "launch.json")?, updated))
+}
+
+/// The variables in the format of `envFile`, quoting values with newlines
+/// or surrounding spaces
+fn env_file(variables: &BTreeMap<String, String>) -> String {
+    let mut content = String::new();
+    for (name, value) in variables {
+        let quoted =
+            value.contains('\n') || value.trim() != value || value.starts_with(['"', '\'']);
+        if quoted {
+            content.push_str(&format!("{name}=\"{}\"\n", value.replace('\n', "\\n")));
+        } else {
+            content.push_str(&format!("{name}={value}\n"));
+        }
+    }
+    content
+}
+
+/// Parse the JSON with comments and trailing commas VS Code writes; the
+/// comments are not kept
+fn parse_jsonc(content: &str, file: &str) -> Result<Value> {
+    let mut json = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            json.push(c);
+            match c {
+                '\\' => json.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                json.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        json.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            (',', _) => {
+                // A comma before a closing bracket is dropped
+                let rest: String = chars.clone().collect();
+                let next = rest.trim_start().chars().next();
+                if !matches!(next, Some(']' | '}') | None) {
+                    json.push(c);
+                }
+            }
+            _ => json.push(c),
+        }
+    }
+
+    serde_json::from_str(&json).map_err(|e| Error::Json {
+        message: format!("Failed to parse {file}"),
+        source: e,
+    })
+}
+
+fn to_string(document: &Value, file: &str) -> Result<String> {
+    serde_json::to_string_pretty(document)
+        .map(|json| format!("{json}\n"))
+        .map_err(|e| Error::Json {
+            message: format!("Failed to serialize {file}"),
+            source: e,
+        })
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::file_system(path, "read", e)),
+    }
+}
+
+fn write(path: &Path, content: &str) -> Result<()> {
+    std::fs::write(path, content).map_err(|e| Error::file_system(path, "write", e))
+}
+
+/// `existing`, the content of .vscode/.gitignore if any, ignoring the
+/// environment file, or `None` when it already does
+fn ignore_env_file(existing: Option<&str>) -> Option<String> {
+    let existing = existing.unwrap_or_default();
+    if existing
+        .lines()
+        .any(|line| matches!(line.trim(), ENV_FILE | "/cuenv.env"))
+    {
+        return None;
+    }
+    let mut content = existing.to_string();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(ENV_FILE);
+    content.push('\n');
+    Some(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_json() {
+        let existing = r#"{
+    // See https://go.microsoft.com/fwlink/?LinkId=733558
+    "version": "2.0.0",
+    "tasks": [
+        { "label": "watch", "type": "npm", "script": "watch" },
+        { "label": "cuenv: old", "type": "process", "command": "cuenv" },
+    ]
+}"#;
+        let tasks = BTreeMap::from([
+            ("build".to_string(), Some("Build it".to_string())),
+            ("lint".to_string(), None),
+        ]);
+        let content = tasks_json(Some(existing), &tasks, Some("dev"), &[]).unwrap();
+        let document: Value = serde_json::from_str(&content).unwrap();
+
+        let labels: Vec<&str> = document["tasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|task| task["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels, ["watch", "cuenv: build", "cuenv: lint"]);
+        let build = &document["tasks"][1];
+        assert_eq!(build["args"], json!(["task", "-e", "dev", "run", "build"]));
+        assert_eq!(build["detail"], "Build it");
+        assert_eq!(build["group"], "build");
+        assert!(document["tasks"][2].get("group").is_none());
+    }
+
+    #[test]
+    fn test_launch_json() {
+        let (content, updated) = launch_json(
+            r#"{
+    "version": "0.2.0",
+    /* Debug the server */
+    "configurations": [
+        { "name": "server", "type": "node", "request": "launch" },
+        { "name": "tests", "type": "node", "envFile": "${workspaceFolder}/.env" }
+    ]
+}"#,
+        )
+        .unwrap();
+        assert_eq!(updated, 1);
+        let document: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(
+            document["configurations"][0]["envFile"],
+            "${workspaceFolder}/.vscode/cuenv.env"
+        );
+        assert_eq!(
+            document["configurations"][1]["envFile"],
+            "${workspaceFolder}/.env"
+        );
+    }
+
+    #[test]
+    fn test_env_file() {
+        let variables = BTreeMap::from([
+            (
+                "URL".to_string(),
+                "http://localhost // not a comment".to_string(),
+            ),
+            ("KEY".to_string(), "line 1\nline 2".to_string()),
+        ]);
+        assert_eq!(
+            env_file(&variables),
+            "KEY=\"line 1\\nline 2\"\nURL=http://localhost // not a comment\n"
+        );
+    }
+
+    #[test]
+    fn test_ignore_env_file() {
+        assert_eq!(ignore_env_file(None).as_deref(), Some("cuenv.env\n"));
+        assert_eq!(
+            ignore_env_file(Some("settings.json")).as_deref(),
+            Some("settings.json\ncuenv.env\n")
+        );
+        assert_eq!(ignore_env_file(Some("*.log\n/cuenv.env\n")), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Recipes of a justfile
+
+use super::ImportedTask;
+
+/// Lines of a justfile that are not recipes
+const STATEMENTS: &[&str] = &["set ", "alias ", "export ", "import ", "mod ", "unexport "];
+
+/// The recipes of the justfile, with the comment above each as its
+/// description
+pub(super) fn parse(content: &str) -> Vec<ImportedTask> {
+    let mut tasks = Vec::new();
+    let mut recipe: Option<(ImportedTask, Vec<String>)> = None;
+    let mut comment = None;
+
+    for line in content.lines() {
+        if line.starts_with([' ', '\t']) || (line.trim().is_empty() && recipe.is_some()) {
+            if let Some((_, body)) = &mut recipe {
+                body.push(line.to_string());
+            }
+            continue;
+        }
+        if let Some((task, body)) = recipe.take() {
+            tasks.push(finish(task, &body));
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            comment = None;
+        } else if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim().to_string());
+        } else if line.starts_with('[') {
+            // Attributes keep the comment above them
+        } else if STATEMENTS
+            .iter()
+            .any(|statement| line.starts_with(statement))
+            || is_assignment(line)
+        {
+            comment = None;
+        } else if let Some(mut task) = header(line) {
+            task.description = comment.take().filter(|text| !text.is_empty());
+            recipe = Some((task, Vec::new()));
+        }
+    }
+    if let Some((task, body)) = recipe {
+        tasks.push(finish(task, &body));
+    }
+    tasks
+}
+
+/// Whether the line sets a variable, `name := value`
+fn is_assignment(line: &str) -> bool {
+    match (line.find(":="), colon(line)) {
+        (Some(assignment), Some(colon)) => assignment <= colon,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// The position of the `:` ending a recipe's parameters, outside the
+/// quotes of their default values
+fn colon(line: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ':') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The recipe a line like `@test filter="": build (lint "all")` declares
+fn header(line: &str) -> Option<ImportedTask> {
+    let line = line.strip_prefix('@').unwrap_or(line);
+    let end = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(line.len());
+    let colon = colon(line)?;
+    if end == 0 || end > colon {
+        return None;
+    }
+
+    let mut task = ImportedTask::new(&line[..end]);
+    let parameters = line[end..colon].trim();
+    if !parameters.is_empty() {
+        task.note(format!(
+            "Takes the parameters `{parameters}`, which were not imported"
+        ));
+    }
+
+    // Dependencies after `&&` run after the recipe, so they are not
+    // dependencies of the task
+    let dependencies = line[colon + 1..].split("&&").next().unwrap_or_default();
+    let dependencies = dependencies.split(" #").next().unwrap_or_default();
+    let mut depth = 0;
+    for token in dependencies.split_whitespace() {
+        if depth == 0 {
+            let name = token.trim_start_matches('(').trim_end_matches(')');
+            task.dependencies.push(name.to_string());
+        }
+        if token.starts_with('(') {
+            task.note("Passes arguments to its dependencies, which were not imported");
+        }
+        depth += token.matches('(').count();
+        depth -= token.matches(')').count().min(depth);
+    }
+    Some(task)
+}
+
+/// The task with the recipe's body as its commands
+fn finish(mut task: ImportedTask, body: &[String]) -> ImportedTask {
+    let lines: Vec<&str> = body
+        .iter()
+        .map(String::as_str)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let indent = lines
+        .first()
+        .map(|line| line.len() - line.trim_start().len())
+        .unwrap_or_default();
+
+    if lines
+        .first()
+        .is_some_and(|line| line.trim_start().starts_with("#!"))
+    {
+        task.note("Is a #! recipe, which cuenv runs with bash instead of its interpreter");
+    }
+    for line in lines {
+        let line = line.get(indent..).unwrap_or(line.trim_start());
+        if line.contains("{{") {
+            task.note("Uses just's {{…}} interpolation, which cuenv does not expand");
+        }
+        // `@` only stops just from echoing the line
+        let line = line.strip_prefix('@').unwrap_or(line);
+        match line.strip_prefix('-') {
+            Some(line) => task.commands.push(format!("{line} || true")),
+            None => task.commands.push(line.to_string()),
+        }
+    }
+    task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUSTFILE: &str = r#"set dotenv-load
+version := "1.0"
+
+# Build the project
+build: lint
+    cargo build --release
+
+lint:
+    @cargo fmt --check
+    -cargo clippy
+
+# Run the tests matching a filter
+[no-cd]
+test filter="": build (fixture "db") && notify
+    cargo test {{filter}}
+
+fixture name:
+    ./fixtures.sh {{name}}
+
+notify:
+    echo done
+"#;
+
+    #[test]
+    fn test_parse() {
+        let tasks = parse(JUSTFILE);
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["build", "lint", "test", "fixture", "notify"]);
+
+        assert_eq!(tasks[0].description.as_deref(), Some("Build the project"));
+        assert_eq!(tasks[0].dependencies, ["lint"]);
+        assert_eq!(tasks[0].commands, ["cargo build --release"]);
+        assert!(tasks[0].notes.is_empty());
+
+        assert_eq!(tasks[1].description, None);
+        assert_eq!(
+            tasks[1].commands,
+            ["cargo fmt --check", "cargo clippy || true"]
+        );
+
+        let test = &tasks[2];
+        assert_eq!(
+            test.description.as_deref(),
+            Some("Run the tests matching a filter")
+        );
+        assert_eq!(test.dependencies, ["build", "fixture"]);
+        assert_eq!(test.commands, ["cargo test {{filter}}"]);
+        assert_eq!(test.notes.len(), 3);
+    }
+}
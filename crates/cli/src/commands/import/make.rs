@@ -0,0 +1,233 @@
+//! Targets of a Makefile
+
+use super::ImportedTask;
+
+/// Directives, whose lines are not rules
+const DIRECTIVES: &[&str] = &[
+    "include", "-include", "sinclude", "ifeq", "ifneq", "ifdef", "ifndef", "else", "endif",
+    "export", "unexport", "override", "vpath", "define", "endef",
+];
+
+/// The explicit targets of the Makefile, with the `## ` comment after them
+/// or the comment above them as their description
+///
+/// Special targets such as `.PHONY`, pattern rules and targets named by
+/// variables are left out.
+pub(super) fn parse(content: &str) -> Vec<ImportedTask> {
+    let mut tasks: Vec<ImportedTask> = Vec::new();
+    // The tasks the recipe lines being read belong to
+    let mut current: Vec<usize> = Vec::new();
+    let mut comment = None;
+    let mut in_define = false;
+
+    for line in logical_lines(content) {
+        if let Some(recipe) = line.strip_prefix('\t') {
+            for &index in &current {
+                let task = &mut tasks[index];
+                let command = command(task, recipe);
+                task.commands.push(command);
+            }
+            continue;
+        }
+        if line.trim().is_empty() {
+            comment = None;
+            continue;
+        }
+        current.clear();
+
+        let first = line.split_whitespace().next().unwrap_or_default();
+        if in_define {
+            in_define = first != "endef";
+            continue;
+        }
+        if let Some(text) = line.trim_start().strip_prefix('#') {
+            comment = Some(text.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+        if DIRECTIVES.contains(&first) {
+            in_define = first == "define";
+            comment = None;
+            continue;
+        }
+
+        let description = comment.take();
+        let Some((targets, rest)) = rule(&line) else {
+            continue;
+        };
+        let (rest, inline) = match rest.split_once(';') {
+            Some((rest, inline)) => (rest, Some(inline.trim())),
+            None => (rest, None),
+        };
+        let (prerequisites, help) = match rest.split_once('#') {
+            Some((prerequisites, help)) => (prerequisites, help.strip_prefix('#')),
+            None => (rest, None),
+        };
+        let description = help
+            .map(|help| help.trim().to_string())
+            .or(description)
+            .filter(|text| !text.is_empty());
+
+        for target in targets.split_whitespace() {
+            if target.starts_with('.') || target.contains(['%', '$']) {
+                continue;
+            }
+            let index = match tasks.iter().position(|task| task.name == target) {
+                Some(index) => index,
+                None => {
+                    tasks.push(ImportedTask::new(target));
+                    tasks.len() - 1
+                }
+            };
+            let task = &mut tasks[index];
+            // A target can be given prerequisites by several rules, but
+            // only one recipe
+            task.dependencies.extend(
+                prerequisites
+                    .split_whitespace()
+                    .filter(|prerequisite| *prerequisite != "|")
+                    .map(str::to_string),
+            );
+            if description.is_some() {
+                task.description = description.clone();
+            }
+            if let Some(inline) = inline.filter(|inline| !inline.is_empty()) {
+                let command = command(task, inline);
+                task.commands.push(command);
+            }
+            current.push(index);
+        }
+    }
+    tasks
+}
+
+/// The lines of the Makefile, with lines outside recipes joined to the
+/// next when they end in `\`
+fn logical_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut continued = false;
+    for line in content.lines() {
+        match lines.last_mut() {
+            Some(last) if continued => {
+                last.pop();
+                last.push(' ');
+                last.push_str(line.trim_start());
+            }
+            _ => lines.push(line.to_string()),
+        }
+        // The shell reads the continuations of recipe lines
+        continued = !line.starts_with('\t') && line.ends_with('\\');
+    }
+    lines
+}
+
+/// The targets and the rest of a rule, `targets: prerequisites`, or `None`
+/// for a variable assignment
+fn rule(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    if line[..colon].contains('=') {
+        return None;
+    }
+    let rest = &line[colon + 1..];
+    // `::` rules run their recipes independently, which one recipe gives
+    let rest = rest.strip_prefix(':').unwrap_or(rest);
+    if rest.starts_with('=') || rest.starts_with(":=") {
+        return None;
+    }
+    Some((&line[..colon], rest))
+}
+
+/// The shell command of a recipe line, without the prefixes make reads
+fn command(task: &mut ImportedTask, line: &str) -> String {
+    let mut line = line.trim_start();
+    let mut ignore_errors = false;
+    while let Some(rest) = line.strip_prefix(['@', '-', '+']) {
+        ignore_errors |= line.starts_with('-');
+        line = rest;
+    }
+
+    let mut command = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            command.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('$') => {
+                chars.next();
+                command.push('$');
+            }
+            Some('@') => {
+                chars.next();
+                command.push_str(&task.name);
+            }
+            _ => {
+                task.note("Uses make variables, which cuenv does not expand");
+                command.push('$');
+            }
+        }
+    }
+    if ignore_errors {
+        command.push_str(" || true");
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAKEFILE: &str = "CC ?= gcc
+SOURCES := main.c \\
+\tutil.c
+
+.PHONY: all test clean
+
+all: app test ## Build and test
+
+# Build the binary
+app: $(SOURCES) config.h
+\t@echo \"building $@\"
+\t$(CC) -o app $(SOURCES)
+
+test: app
+\t-./app --self-test
+\tfor f in tests/*; do $$f; done
+
+clean: ; rm -f app
+
+%.o: %.c
+\t$(CC) -c $<
+";
+
+    #[test]
+    fn test_parse() {
+        let tasks = parse(MAKEFILE);
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["all", "app", "test", "clean"]);
+
+        let all = &tasks[0];
+        assert_eq!(all.description.as_deref(), Some("Build and test"));
+        assert_eq!(all.dependencies, ["app", "test"]);
+        assert!(all.commands.is_empty());
+
+        let app = &tasks[1];
+        assert_eq!(app.description.as_deref(), Some("Build the binary"));
+        assert_eq!(app.dependencies, ["$(SOURCES)", "config.h"]);
+        assert_eq!(
+            app.commands,
+            ["echo \"building app\"", "$(CC) -o app $(SOURCES)"]
+        );
+        assert_eq!(app.notes.len(), 1);
+
+        let test = &tasks[2];
+        assert_eq!(test.description, None);
+        assert_eq!(
+            test.commands,
+            ["./app --self-test || true", "for f in tests/*; do $f; done"]
+        );
+        assert!(test.notes.is_empty());
+
+        assert_eq!(tasks[3].commands, ["rm -f app"]);
+    }
+}
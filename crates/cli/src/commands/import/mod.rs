@@ -0,0 +1,357 @@
+//! `cuenv import`, the tasks of a justfile, Makefile or Taskfile.yml
+//!
+//! Recipes, targets and tasks become cuenv tasks with their descriptions,
+//! dependencies and commands, so a project can move to cuenv without
+//! writing its tasks again. What has no equivalent, such as the variables
+//! of the task runner, is kept as it is with a comment above the task
+//! saying what to change. The tasks are added to env.cue, or printed.
+
+mod just;
+mod make;
+mod taskfile;
+
+use super::init::{cue_list, cue_string, header};
+use cuenv_config::{file_names, package_name, Config};
+use cuenv_core::{Error, Result, ENV_CUE_FILENAME};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The task runners tasks are imported from, with the file names each looks
+/// for, in order
+const SOURCES: &[(&str, &[&str])] = &[
+    ("justfile", &["justfile", "Justfile", ".justfile"]),
+    ("makefile", &["GNUmakefile", "makefile", "Makefile"]),
+    (
+        "taskfile",
+        &[
+            "Taskfile.yml",
+            "taskfile.yml",
+            "Taskfile.yaml",
+            "taskfile.yaml",
+        ],
+    ),
+];
+
+/// CUE keywords, which a task name is quoted as
+const CUE_KEYWORDS: &[&str] = &[
+    "package", "import", "for", "in", "if", "let", "true", "false", "null",
+];
+
+/// A task read from another task runner
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ImportedTask {
+    name: String,
+    description: Option<String>,
+    dependencies: Vec<String>,
+    /// Shell commands, run in order until one fails
+    commands: Vec<String>,
+    working_dir: Option<String>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    /// What was not imported and has to be changed by hand
+    notes: Vec<String>,
+}
+
+impl ImportedTask {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn note(&mut self, note: impl Into<String>) {
+        let note = note.into();
+        if !self.notes.contains(&note) {
+            self.notes.push(note);
+        }
+    }
+
+    /// The task as a CUE field
+    fn to_cue(&self) -> String {
+        let mut cue = String::new();
+        for note in &self.notes {
+            cue.push_str(&format!("\t// TODO: {note}\n"));
+        }
+        cue.push_str(&format!("\t{}: {{\n", cue_label(&self.name)));
+        if let Some(description) = &self.description {
+            cue.push_str(&format!("\t\tdescription: {}\n", cue_string(description)));
+        }
+        if !self.dependencies.is_empty() {
+            cue.push_str(&format!(
+                "\t\tdependencies: {}\n",
+                cue_list(&self.dependencies)
+            ));
+        }
+        if let Some(dir) = &self.working_dir {
+            cue.push_str(&format!("\t\tworkingDir: {}\n", cue_string(dir)));
+        }
+        match self.commands.as_slice() {
+            // Only runs its dependencies
+            [] => cue.push_str("\t\tcommand: \"true\"\n"),
+            [command] if !command.contains('\n') => {
+                cue.push_str(&format!("\t\tcommand: {}\n", cue_string(command)))
+            }
+            commands => {
+                cue.push_str("\t\tscript: \"\"\"\n");
+                // The task runners stop at the first command that fails
+                if !commands[0].starts_with("#!") {
+                    cue.push_str("\t\t\tset -e\n");
+                }
+                for line in commands.iter().flat_map(|command| command.lines()) {
+                    cue.push_str(&format!("\t\t\t{}\n", cue_multiline(line)));
+                }
+                cue.push_str("\t\t\t\"\"\"\n");
+            }
+        }
+        if !self.inputs.is_empty() {
+            cue.push_str(&format!("\t\tinputs: {}\n", cue_list(&self.inputs)));
+        }
+        if !self.outputs.is_empty() {
+            cue.push_str(&format!("\t\toutputs: {}\n", cue_list(&self.outputs)));
+        }
+        cue.push_str("\t}\n");
+        cue
+    }
+}
+
+/// Rename the tasks to names cuenv resolves as they are, which dots and
+/// colons would split into groups, and drop dependencies on what is not a
+/// task, such as the files a make target is built from
+///
+/// A task renamed to the name of another task, such as `a.b` next to
+/// `a-b`, gets a numbered name instead.
+fn normalize(tasks: Vec<ImportedTask>) -> Vec<ImportedTask> {
+    // Tasks keeping their names go first
+    let mut taken: HashSet<String> = tasks
+        .iter()
+        .filter(|task| task_name(&task.name) == task.name)
+        .map(|task| task.name.clone())
+        .collect();
+    let mut names: HashMap<String, String> = HashMap::new();
+    for task in &tasks {
+        let name = task_name(&task.name);
+        if name == task.name || names.contains_key(&task.name) {
+            names.entry(task.name.clone()).or_insert(name);
+            continue;
+        }
+        let mut unique = name.clone();
+        let mut number = 2;
+        while !taken.insert(unique.clone()) {
+            unique = format!("{name}-{number}");
+            number += 1;
+        }
+        names.insert(task.name.clone(), unique);
+    }
+    tasks
+        .into_iter()
+        .map(|mut task| {
+            let name = names[&task.name].clone();
+            let wanted = task_name(&task.name);
+            if name != wanted {
+                task.note(format!(
+                    "Imported from '{}' as '{name}', since '{wanted}' is another task",
+                    task.name
+                ));
+            }
+            ImportedTask {
+                name,
+                dependencies: task
+                    .dependencies
+                    .iter()
+                    .filter_map(|dependency| names.get(dependency).cloned())
+                    .collect(),
+                ..task
+            }
+        })
+        .collect()
+}
+
+fn task_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// The task name as a CUE label, quoted unless it is an identifier; a
+/// leading `_` would hide the field
+fn cue_label(name: &str) -> String {
+    let identifier = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !CUE_KEYWORDS.contains(&name);
+    if identifier {
+        name.to_string()
+    } else {
+        cue_string(name)
+    }
+}
+
+/// A line of a CUE multi-line string, in which `\` starts an escape
+fn cue_multiline(line: &str) -> String {
+    line.replace('\\', "\\\\").replace("\"\"\"", "\\\"\"\"")
+}
+
+/// The `tasks` block of env.cue holding the tasks
+fn tasks_block(tasks: &[ImportedTask]) -> String {
+    let mut cue = "tasks: {\n".to_string();
+    for (index, task) in tasks.iter().enumerate() {
+        if index > 0 {
+            cue.push('\n');
+        }
+        cue.push_str(&task.to_cue());
+    }
+    cue.push_str("}\n");
+    cue
+}
+
+/// The file tasks are imported from: `file`, or the first of the file names
+/// of `source` in `dir`
+fn source_file(source: &str, file: Option<PathBuf>, dir: &Path) -> Result<PathBuf> {
+    if let Some(file) = file {
+        return Ok(file);
+    }
+    let names = SOURCES
+        .iter()
+        .find(|(name, _)| *name == source)
+        .map(|(_, names)| *names)
+        .unwrap_or_default();
+    names
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            Error::configuration(format!(
+                "No {} found in {}",
+                names.join(", "),
+                dir.display()
+            ))
+        })
+}
+
+pub fn execute(config: &Config, from: String, file: Option<PathBuf>, print: bool) -> Result<()> {
+    let path = source_file(&from, file, &config.working_dir)?;
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| Error::file_system(&path, "read", e))?;
+    let tasks = match from.as_str() {
+        "justfile" => just::parse(&content),
+        "makefile" => make::parse(&content),
+        _ => taskfile::parse(&content)?,
+    };
+    if tasks.is_empty() {
+        return Err(Error::configuration(format!(
+            "No tasks found in {}",
+            path.display()
+        )));
+    }
+    let tasks = normalize(tasks);
+    let block = tasks_block(&tasks);
+
+    if print {
+        print!("{block}");
+        return Ok(());
+    }
+
+    let file_name = file_names()
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| ENV_CUE_FILENAME.to_string());
+    let env_file = config.working_dir.join(&file_name);
+    let source = path.file_name().unwrap_or_default().to_string_lossy();
+    let content = match std::fs::read_to_string(&env_file) {
+        Ok(content) => {
+            if content.lines().any(|line| line.starts_with("tasks:")) {
+                return Err(Error::usage(format!(
+                    "{file_name} already has tasks. Use --print and merge them by hand."
+                )));
+            }
+            let separator = if content.ends_with('\n') { "" } else { "\n" };
+            format!("{content}{separator}\n// Imported from {source}\n{block}")
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            format!(
+                "{}// Imported from {source}\n{block}",
+                header(&package_name())
+            )
+        }
+        Err(e) => return Err(Error::file_system(&env_file, "read", e)),
+    };
+    std::fs::write(&env_file, content).map_err(|e| Error::file_system(&env_file, "write", e))?;
+
+    println!(
+        "✓ Imported {} tasks from {source} into {file_name}",
+        tasks.len()
+    );
+    let unfinished = tasks.iter().filter(|task| !task.notes.is_empty()).count();
+    if unfinished > 0 {
+        println!("! {unfinished} tasks need changes, see the TODO comments in {file_name}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_cue() {
+        let mut task = ImportedTask::new("build");
+        task.description = Some("Build the project".to_string());
+        task.dependencies = vec!["lint".to_string()];
+        task.commands = vec!["cargo build".to_string()];
+        assert_eq!(
+            task.to_cue(),
+            "\tbuild: {\n\t\tdescription: \"Build the project\"\n\t\tdependencies: [\"lint\"]\n\t\tcommand: \"cargo build\"\n\t}\n"
+        );
+
+        task.commands = vec![
+            "cd web".to_string(),
+            "npm run build \\\n  --prod".to_string(),
+        ];
+        task.note("Uses make variables");
+        assert!(task
+            .to_cue()
+            .starts_with("\t// TODO: Uses make variables\n"));
+        assert!(task.to_cue().contains(
+            "\t\tscript: \"\"\"\n\t\t\tset -e\n\t\t\tcd web\n\t\t\tnpm run build \\\\\n\t\t\t  --prod\n\t\t\t\"\"\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut deploy = ImportedTask::new("docker:push");
+        deploy.dependencies = vec!["docs.html".to_string(), "Dockerfile".to_string()];
+        let tasks = normalize(vec![ImportedTask::new("docs.html"), deploy]);
+
+        assert_eq!(tasks[0].name, "docs-html");
+        assert_eq!(tasks[1].name, "docker-push");
+        assert_eq!(tasks[1].dependencies, vec!["docs-html".to_string()]);
+
+        let mut release = ImportedTask::new("release");
+        release.dependencies = vec!["a.b".to_string(), "a-b".to_string()];
+        let tasks = normalize(vec![
+            ImportedTask::new("a.b"),
+            ImportedTask::new("a:b"),
+            ImportedTask::new("a-b"),
+            release,
+        ]);
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["a-b-2", "a-b-3", "a-b", "release"]);
+        assert_eq!(tasks[3].dependencies, ["a-b-2", "a-b"]);
+        assert_eq!(tasks[0].notes.len(), 1);
+        assert!(tasks[2].notes.is_empty());
+    }
+
+    #[test]
+    fn test_cue_label() {
+        assert_eq!(cue_label("build"), "build");
+        assert_eq!(cue_label("docker-push"), "\"docker-push\"");
+        assert_eq!(cue_label("_setup"), "\"_setup\"");
+        assert_eq!(cue_label("if"), "\"if\"");
+    }
+}
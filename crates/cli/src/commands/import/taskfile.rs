@@ -0,0 +1,174 @@
+//! Tasks of a Taskfile.yml
+
+use super::ImportedTask;
+use cuenv_core::{Error, Result};
+use serde_yaml::Value;
+
+/// The tasks of the Taskfile, with their `sources` and `generates` as
+/// inputs and outputs
+pub(super) fn parse(content: &str) -> Result<Vec<ImportedTask>> {
+    let taskfile: Value = serde_yaml::from_str(content)
+        .map_err(|e| Error::configuration(format!("Invalid Taskfile: {e}")))?;
+    let Some(tasks) = taskfile.get("tasks").and_then(Value::as_mapping) else {
+        return Ok(Vec::new());
+    };
+
+    let mut imported = Vec::new();
+    for (name, definition) in tasks {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let mut task = ImportedTask::new(name);
+        match definition {
+            // `build: go build ./...`
+            Value::String(command) => task.commands.push(command.clone()),
+            // `build: [go generate, go build ./...]`
+            Value::Sequence(commands) => {
+                for command in commands {
+                    push_command(&mut task, command);
+                }
+            }
+            Value::Mapping(_) => import_task(&mut task, definition),
+            _ => continue,
+        }
+        if task.commands.iter().any(|command| command.contains("{{")) {
+            task.note("Uses Taskfile's {{…}} templating, which cuenv does not expand");
+        }
+        imported.push(task);
+    }
+    Ok(imported)
+}
+
+fn import_task(task: &mut ImportedTask, definition: &Value) {
+    let strings = |key: &str| -> Vec<String> {
+        definition
+            .get(key)
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect()
+    };
+
+    task.description = definition
+        .get("desc")
+        .or_else(|| definition.get("summary"))
+        .and_then(Value::as_str)
+        .and_then(|text| text.lines().next())
+        .map(|text| text.trim().to_string());
+    for dependency in definition
+        .get("deps")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+    {
+        // `- lint` or `- task: lint`, with variables when it has `vars`
+        match dependency.as_str().or_else(|| dependency["task"].as_str()) {
+            Some(name) => task.dependencies.push(name.to_string()),
+            None => continue,
+        }
+        if dependency.get("vars").is_some() {
+            task.note("Passes variables to its dependencies, which were not imported");
+        }
+    }
+
+    if let Some(command) = definition.get("cmd") {
+        push_command(task, command);
+    }
+    for command in definition
+        .get("cmds")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+    {
+        push_command(task, command);
+    }
+
+    task.working_dir = definition
+        .get("dir")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    task.inputs = strings("sources");
+    task.outputs = strings("generates");
+    if definition.get("vars").is_some() || definition.get("env").is_some() {
+        task.note("Sets variables, which belong in the env of env.cue");
+    }
+}
+
+/// A command of `cmds`: a line of shell, `cmd: ...`, or `task: ...` running
+/// another task
+fn push_command(task: &mut ImportedTask, command: &Value) {
+    if let Some(command) = command.as_str().or_else(|| command["cmd"].as_str()) {
+        task.commands.push(command.to_string());
+    } else if let Some(name) = command["task"].as_str() {
+        task.commands
+            .push(format!("cuenv task run {}", super::task_name(name)));
+    } else {
+        task.note("Has commands other than shell lines and tasks, which were not imported");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASKFILE: &str = r#"
+version: '3'
+
+vars:
+  BINARY: app
+
+tasks:
+  build:
+    desc: Build the binary
+    deps: [generate]
+    sources: ["**/*.go"]
+    generates: [app]
+    cmds:
+      - go build -o {{.BINARY}}
+      - task: docker:build
+
+  generate: go generate ./...
+
+  docker:build:
+    dir: deploy
+    cmds:
+      - cmd: docker build .
+      - defer: docker image prune -f
+
+  fmt:
+    - gofmt -w .
+    - goimports -w .
+"#;
+
+    #[test]
+    fn test_parse() {
+        let tasks = parse(TASKFILE).unwrap();
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["build", "generate", "docker:build", "fmt"]);
+
+        let build = &tasks[0];
+        assert_eq!(build.description.as_deref(), Some("Build the binary"));
+        assert_eq!(build.dependencies, ["generate"]);
+        assert_eq!(build.inputs, ["**/*.go"]);
+        assert_eq!(build.outputs, ["app"]);
+        assert_eq!(
+            build.commands,
+            ["go build -o {{.BINARY}}", "cuenv task run docker-build"]
+        );
+        assert_eq!(build.notes.len(), 1);
+
+        assert_eq!(tasks[1].commands, ["go generate ./..."]);
+        assert_eq!(tasks[2].working_dir.as_deref(), Some("deploy"));
+        assert_eq!(tasks[2].commands, ["docker build ."]);
+        assert_eq!(tasks[2].notes.len(), 1);
+        assert_eq!(tasks[3].commands, ["gofmt -w .", "goimports -w ."]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("tasks: [").is_err());
+        assert!(parse("version: '3'").unwrap().is_empty());
+    }
+}
@@ -1,80 +1,435 @@
-use cuenv_config::Config;
-use cuenv_core::{Result, ENV_CUE_FILENAME};
+//! `cuenv init`, a starter configuration for a project
+//!
+//! The file has an `env` block and tasks with inputs, outputs and caching,
+//! all commented. The tasks are picked from the project in the directory:
+//! cargo commands next to a Cargo.toml, the scripts of a package.json run
+//! by its package manager, or go commands next to a go.mod. Next to a
+//! devenv.nix, the environment is layered on devenv's. With `--hook`, the
+//! line loading cuenv's shell hook is also added to the shell's rc file.
+
+use crate::platform::{PlatformOps, Shell};
+use cuenv_config::{file_names, package_name, Config};
+use cuenv_core::{Error, Result, ENV_CUE_FILENAME};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-pub async fn execute(config: Arc<Config>, force: bool) -> Result<()> {
-    let env_file = config.working_dir.join(ENV_CUE_FILENAME);
+#[cfg(unix)]
+use crate::platform::UnixPlatform as Platform;
+#[cfg(windows)]
+use crate::platform::WindowsPlatform as Platform;
 
-    if env_file.exists() && !force {
-        eprintln!("Error: {ENV_CUE_FILENAME} already exists. Use --force to overwrite.");
-        std::process::exit(1);
-    }
+/// The package.json scripts that become tasks, in the order they are listed
+const NODE_SCRIPTS: &[(&str, &str)] = &[
+    ("dev", "Start the development server"),
+    ("build", "Build the project"),
+    ("test", "Run the tests"),
+    ("lint", "Lint the code"),
+];
 
-    let template = r#"package main
+/// The kind of project in a directory, by its manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Project {
+    Rust,
+    /// A package.json, with the package manager its lock file belongs to and
+    /// the scripts it has of [`NODE_SCRIPTS`]
+    Node {
+        manager: &'static str,
+        scripts: Vec<String>,
+    },
+    Go,
+    Unknown,
+}
 
-import "cuenv.io/env"
+impl Project {
+    fn detect(dir: &Path) -> Self {
+        if dir.join("Cargo.toml").is_file() {
+            return Self::Rust;
+        }
+        if let Ok(content) = std::fs::read_to_string(dir.join("package.json")) {
+            let package: serde_json::Value = serde_json::from_str(&content).unwrap_or_default();
+            let scripts = NODE_SCRIPTS
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .filter(|name| package["scripts"].get(name).is_some())
+                .collect();
+            let manager = [
+                ("pnpm-lock.yaml", "pnpm"),
+                ("yarn.lock", "yarn"),
+                ("bun.lockb", "bun"),
+            ]
+            .into_iter()
+            .find(|(lock_file, _)| dir.join(lock_file).is_file())
+            .map_or("npm", |(_, manager)| manager);
+            return Self::Node { manager, scripts };
+        }
+        if dir.join("go.mod").is_file() {
+            return Self::Go;
+        }
+        Self::Unknown
+    }
 
-// Define your environment configuration
-environment: env.#Environment & {
-    // Development environment
-    dev: {
-        variables: {
-            NODE_ENV: {
-                value: "development"
-                description: "Node environment"
-            }
-            DEBUG: {
-                value: "true"
-                description: "Enable debug mode"
-            }
+    fn description(&self) -> Option<&'static str> {
+        match self {
+            Self::Rust => Some("Rust"),
+            Self::Node { .. } => Some("Node.js"),
+            Self::Go => Some("Go"),
+            Self::Unknown => None,
         }
     }
-    
-    // Production environment
-    production: {
-        variables: {
-            NODE_ENV: {
-                value: "production"
-                description: "Node environment"
+
+    fn tasks(&self) -> Vec<StarterTask> {
+        match self {
+            Self::Rust => {
+                let sources = &["Cargo.toml", "Cargo.lock", "src/**/*.rs"];
+                vec![
+                    StarterTask::new("build", "Build the project", "cargo build")
+                        .inputs(sources)
+                        .outputs(&["target/debug"]),
+                    StarterTask::new("test", "Run the tests", "cargo test")
+                        .inputs(sources)
+                        .inputs(&["tests/**/*.rs"]),
+                    StarterTask::new(
+                        "lint",
+                        "Lint the code",
+                        "cargo clippy --all-targets -- -D warnings",
+                    )
+                    .inputs(sources),
+                ]
+            }
+            Self::Node { manager, scripts } => {
+                let lock_file = match *manager {
+                    "pnpm" => "pnpm-lock.yaml",
+                    "yarn" => "yarn.lock",
+                    "bun" => "bun.lockb",
+                    _ => "package-lock.json",
+                };
+                let sources = &["package.json", lock_file, "src/**"];
+                NODE_SCRIPTS
+                    .iter()
+                    .filter(|(name, _)| scripts.iter().any(|script| script == name))
+                    .map(|(name, description)| {
+                        let task =
+                            StarterTask::new(name, description, &format!("{manager} run {name}"));
+                        match *name {
+                            // A server runs until it is stopped
+                            "dev" => task.uncached(),
+                            "build" => task.inputs(sources).outputs(&["dist"]),
+                            _ => task.inputs(sources),
+                        }
+                    })
+                    .collect()
             }
-            DEBUG: {
-                value: "false"
-                description: "Disable debug mode"
+            Self::Go => {
+                let sources = &["go.mod", "go.sum", "**/*.go"];
+                vec![
+                    StarterTask::new("build", "Build the project", "go build ./...")
+                        .inputs(sources),
+                    StarterTask::new("test", "Run the tests", "go test ./...").inputs(sources),
+                    StarterTask::new("lint", "Vet the code", "go vet ./...").inputs(sources),
+                ]
             }
+            Self::Unknown => Vec::new(),
         }
     }
 }
 
-// Define tasks
-tasks: env.#Tasks & {
-    dev: {
-        description: "Start development server"
-        command: ["npm", "run", "dev"]
+/// A task of the starter configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StarterTask {
+    name: String,
+    description: String,
+    command: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    cache: bool,
+}
+
+impl StarterTask {
+    fn new(name: &str, description: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            command: command.to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            cache: true,
+        }
+    }
+
+    fn inputs(mut self, inputs: &[&str]) -> Self {
+        self.inputs
+            .extend(inputs.iter().map(|input| input.to_string()));
+        self
     }
-    
-    build: {
-        description: "Build for production"
-        command: ["npm", "run", "build"]
+
+    fn outputs(mut self, outputs: &[&str]) -> Self {
+        self.outputs
+            .extend(outputs.iter().map(|output| output.to_string()));
+        self
     }
-    
-    test: {
-        description: "Run tests"
-        command: ["npm", "test"]
+
+    fn uncached(mut self) -> Self {
+        self.cache = false;
+        self
+    }
+
+    /// The task as a CUE field, explaining its fields when `commented`
+    fn to_cue(&self, commented: bool) -> String {
+        let comment = |text: &str| {
+            if commented {
+                format!("\t\t// {text}\n")
+            } else {
+                String::new()
+            }
+        };
+
+        let mut cue = format!("\t{}: {{\n", self.name);
+        cue.push_str(&format!(
+            "\t\tdescription: {}\n",
+            cue_string(&self.description)
+        ));
+        cue.push_str(&comment("Run by the task's shell, bash by default"));
+        cue.push_str(&format!("\t\tcommand: {}\n", cue_string(&self.command)));
+        if !self.inputs.is_empty() {
+            cue.push_str(&comment(
+                "Files the task reads; its cached result is reused until one of them changes",
+            ));
+            cue.push_str(&format!("\t\tinputs: {}\n", cue_list(&self.inputs)));
+        }
+        if !self.outputs.is_empty() {
+            cue.push_str(&comment(
+                "Files the task writes, restored when its result comes from the cache",
+            ));
+            cue.push_str(&format!("\t\toutputs: {}\n", cue_list(&self.outputs)));
+        }
+        if commented || !self.cache {
+            cue.push_str(&comment("Whether results are cached by their inputs"));
+            cue.push_str(&format!("\t\tcache: {}\n", self.cache));
+        }
+        cue.push_str("\t}\n");
+        cue
     }
 }
+
+/// Layers the environment on that of devenv.nix
+const DEVENV: &str = r#"// The environment of devenv.nix, which env and tasks build on
+devenv: {}
+
 "#;
 
-    std::fs::write(&env_file, template)
-        .map_err(|e| cuenv_core::Error::file_system(&env_file, "write", e))?;
+/// A CUE string literal; JSON strings are CUE strings
+pub(crate) fn cue_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+pub(crate) fn cue_list(values: &[String]) -> String {
+    let items: Vec<String> = values.iter().map(|value| cue_string(value)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// The start of a configuration in the package `package`, checked against
+/// the cuenv schema
+pub(crate) fn header(package: &str) -> String {
+    format!(
+        r#"package {package}
+
+import "github.com/rawkode/cuenv/schema"
+
+schema.#Cuenv
+
+"#
+    )
+}
+
+/// The starter configuration for `project`, in the package `package`,
+/// loading the environment of a devenv.nix with `devenv`
+fn template(package: &str, project: &Project, devenv: bool) -> String {
+    let mut tasks = project.tasks();
+    if tasks.is_empty() {
+        tasks.push(
+            StarterTask::new("build", "Build the project", "echo 'Building...'")
+                .inputs(&["src/**"])
+                .outputs(&["build"]),
+        );
+    }
+
+    let mut cue = header(package);
+    if devenv {
+        cue.push_str(DEVENV);
+    }
+    cue.push_str(
+        r#"// Variables set when you enter this directory
+env: {
+	APP_ENV:   "development"
+	LOG_LEVEL: "debug"
+
+	// Overrides for an environment, selected with `cuenv -e production`
+	environment: production: {
+		APP_ENV:   "production"
+		LOG_LEVEL: "info"
+	}
+}
+
+// Tasks, run with `cuenv task <name>`
+tasks: {
+"#,
+    );
+    for (index, task) in tasks.iter().enumerate() {
+        if index > 0 {
+            cue.push('\n');
+        }
+        cue.push_str(&task.to_cue(index == 0));
+    }
+    cue.push_str("}\n");
+    cue
+}
+
+/// The rc file of `shell` and the line loading cuenv's hook in it
+pub(crate) fn hook_line(shell: Shell) -> Option<(PathBuf, &'static str)> {
+    let home = dirs::home_dir()?;
+    match shell {
+        Shell::Bash => Some((home.join(".bashrc"), r#"eval "$(cuenv shell init bash)""#)),
+        Shell::Zsh => {
+            let dir = std::env::var_os("ZDOTDIR").map_or(home, PathBuf::from);
+            Some((dir.join(".zshrc"), r#"eval "$(cuenv shell init zsh)""#))
+        }
+        Shell::Fish => Some((
+            dirs::config_dir()
+                .unwrap_or_else(|| home.join(".config"))
+                .join("fish")
+                .join("config.fish"),
+            "cuenv shell init fish | source",
+        )),
+        Shell::Pwsh | Shell::Cmd => None,
+    }
+}
+
+/// Add the hook line of `shell`, or of the current shell, to its rc file
+fn install_hook(shell: Option<&str>) -> Result<()> {
+    let shell = match shell {
+        Some(shell) => shell.parse::<Shell>().map_err(Error::configuration)?,
+        None => Platform::get_current_shell().map_err(Error::configuration)?,
+    };
+    let Some((rc_file, line)) = hook_line(shell) else {
+        return Err(Error::configuration(format!(
+            "Installing the hook for {} is not supported; see 'cuenv shell init {}'",
+            shell.as_str(),
+            shell.as_str()
+        )));
+    };
+
+    let content = match std::fs::read_to_string(&rc_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::file_system(&rc_file, "read", e)),
+    };
+    if content.contains("cuenv shell init") {
+        println!("✓ {} already loads the cuenv hook", rc_file.display());
+        return Ok(());
+    }
+
+    if let Some(parent) = rc_file.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| Error::file_system(parent, "create directory", e))?;
+    }
+    let separator = if content.is_empty() || content.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    let updated = format!("{content}{separator}\n# Load cuenv environments\n{line}\n");
+    std::fs::write(&rc_file, updated).map_err(|e| Error::file_system(&rc_file, "write", e))?;
+    println!("✓ Added the cuenv hook to {}", rc_file.display());
+    Ok(())
+}
+
+pub async fn execute(config: Arc<Config>, force: bool, hook: Option<Option<String>>) -> Result<()> {
+    // The preferred of the file names configurations are discovered by
+    let file_name = file_names()
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| ENV_CUE_FILENAME.to_string());
+    let env_file = config.working_dir.join(&file_name);
+
+    if env_file.exists() && !force {
+        return Err(Error::usage(format!(
+            "{file_name} already exists. Use --force to overwrite."
+        )));
+    }
+
+    let project = Project::detect(&config.working_dir);
+    let devenv = config.working_dir.join("devenv.nix").is_file();
+    std::fs::write(&env_file, template(&package_name(), &project, devenv))
+        .map_err(|e| Error::file_system(&env_file, "write", e))?;
+
+    match project.description() {
+        Some(kind) => println!("✓ Created {file_name} with tasks for the {kind} project"),
+        None => println!("✓ Created {file_name} with example configuration"),
+    }
+    if devenv {
+        println!("✓ Found devenv.nix, whose environment {file_name} builds on");
+    }
+    if let Some(shell) = &hook {
+        install_hook(shell.as_deref())?;
+    }
 
-    println!("✓ Created {ENV_CUE_FILENAME} with example configuration");
     println!("\nNext steps:");
-    println!("  1. Edit {ENV_CUE_FILENAME} to customize your environment");
+    println!("  1. Edit {file_name} to customize your environment");
     println!(
         "  2. Run 'cuenv allow {}' to allow this directory",
         config.working_dir.display()
     );
-    println!("  3. Add shell hook with 'eval \"$(cuenv shell init <shell>)\"'");
+    if hook.is_none() {
+        println!("  3. Add shell hook with 'eval \"$(cuenv shell init <shell>)\"'");
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_project() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(Project::detect(temp.path()), Project::Unknown);
+
+        std::fs::write(
+            temp.path().join("package.json"),
+            r#"{"scripts": {"test": "vitest", "build": "vite build", "preview": "vite preview"}}"#,
+        )
+        .unwrap();
+        std::fs::write(temp.path().join("pnpm-lock.yaml"), "").unwrap();
+        assert_eq!(
+            Project::detect(temp.path()),
+            Project::Node {
+                manager: "pnpm",
+                scripts: vec!["build".to_string(), "test".to_string()],
+            }
+        );
+
+        std::fs::write(temp.path().join("Cargo.toml"), "[package]").unwrap();
+        assert_eq!(Project::detect(temp.path()), Project::Rust);
+    }
+
+    #[test]
+    fn test_template() {
+        let project = Project::Node {
+            manager: "npm",
+            scripts: vec!["dev".to_string(), "build".to_string()],
+        };
+        let cue = template("cuenv", &project, false);
+        assert!(cue.starts_with("package cuenv\n"));
+        assert!(cue.contains(
+            "\tdev: {\n\t\tdescription: \"Start the development server\"\n\t\t// Run by the task's shell, bash by default\n\t\tcommand: \"npm run dev\"\n"
+        ));
+        assert!(cue.contains("\t\toutputs: [\"dist\"]\n"));
+        assert!(cue.contains("\t\tinputs: [\"package.json\", \"package-lock.json\", \"src/**\"]\n"));
+
+        // Projects without a manifest get one example task
+        assert!(template("cuenv", &Project::Unknown, false).contains("\tbuild: {\n"));
+        assert!(!cue.contains("devenv"));
+        assert!(template("cuenv", &project, true).contains("\ndevenv: {}\n\n// Variables"));
+    }
+}
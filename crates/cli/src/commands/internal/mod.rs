@@ -206,6 +206,18 @@ async fn handle_task_protocol(
             task_nodes: HashMap::new(), // Empty for internal commands
             hooks: HashMap::new(),
             config: None,
+            root: false,
+            constraints: HashMap::new(),
+            list_variables: HashMap::new(),
+            command_variables: HashMap::new(),
+            terraform_variables: HashMap::new(),
+            overlays: Default::default(),
+            environment_sources: HashMap::new(),
+            typed_values: HashMap::new(),
+            environments: Vec::new(),
+            notify: None,
+            nix: None,
+            devenv: None,
         };
 
         let config = Arc::new(Config::new(
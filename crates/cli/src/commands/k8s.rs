@@ -0,0 +1,310 @@
+//! `cuenv k8s sync`, the variables of env.cue in a Kubernetes cluster
+//!
+//! The variables env.cue loads are rendered as a ConfigMap, and those that
+//! are secrets or marked sensitive as a Secret, so workloads of a dev
+//! cluster read the same values as a local shell. The manifests are
+//! printed, or applied with kubectl, whose three-way merge also removes the
+//! keys env.cue no longer has.
+
+use base64::Engine;
+use clap::Subcommand;
+use cuenv_config::Config;
+use cuenv_core::{Error, Result};
+use cuenv_env::manager::environment::SupervisorMode;
+use cuenv_env::EnvManager;
+use cuenv_utils::atomic_file::write_private;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Label marking the objects cuenv writes
+const MANAGED_BY: (&str, &str) = ("app.kubernetes.io/managed-by", "cuenv");
+
+#[derive(Subcommand)]
+pub enum K8sCommands {
+    /// Render the variables as a ConfigMap and a Secret, or apply them
+    Sync {
+        /// Namespace of the ConfigMap and Secret
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+
+        /// Name of the ConfigMap and Secret, the directory's name by default
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Environment to load
+        #[arg(short = 'e', long = "env")]
+        environment: Option<String>,
+
+        /// Only sync the variables tagged with these capabilities
+        #[arg(short = 'c', long = "capability")]
+        capabilities: Vec<String>,
+
+        /// Apply the manifests with kubectl instead of printing them
+        #[arg(long)]
+        apply: bool,
+
+        /// kubectl context to apply the manifests in
+        #[arg(long, requires = "apply")]
+        context: Option<String>,
+
+        /// Write the manifests to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE", conflicts_with = "apply")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    api_version: &'static str,
+    kind: &'static str,
+    metadata: Metadata,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    secret_type: Option<&'static str>,
+    data: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Metadata {
+    name: String,
+    namespace: String,
+    labels: BTreeMap<String, String>,
+}
+
+/// A loaded variable and whether it goes in the Secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Variable {
+    value: String,
+    secret: bool,
+}
+
+/// The ConfigMap of the plain variables and the Secret of the others,
+/// leaving out the one that would be empty
+fn manifests(name: &str, namespace: &str, variables: &BTreeMap<String, Variable>) -> Vec<Manifest> {
+    let metadata = || Metadata {
+        name: name.to_string(),
+        namespace: namespace.to_string(),
+        labels: BTreeMap::from([(MANAGED_BY.0.to_string(), MANAGED_BY.1.to_string())]),
+    };
+    let data = |secret: bool| -> BTreeMap<String, String> {
+        variables
+            .iter()
+            .filter(|(_, variable)| variable.secret == secret)
+            .map(|(key, variable)| (key.clone(), variable.value.clone()))
+            .collect()
+    };
+
+    let mut manifests = Vec::new();
+    let config = data(false);
+    if !config.is_empty() {
+        manifests.push(Manifest {
+            api_version: "v1",
+            kind: "ConfigMap",
+            metadata: metadata(),
+            secret_type: None,
+            data: config,
+        });
+    }
+    let secrets = data(true);
+    if !secrets.is_empty() {
+        let engine = base64::engine::general_purpose::STANDARD;
+        manifests.push(Manifest {
+            api_version: "v1",
+            kind: "Secret",
+            metadata: metadata(),
+            secret_type: Some("Opaque"),
+            data: secrets
+                .into_iter()
+                .map(|(key, value)| (key, engine.encode(value)))
+                .collect(),
+        });
+    }
+    manifests
+}
+
+/// The manifests as one YAML stream
+fn render(manifests: &[Manifest]) -> Result<String> {
+    let documents = manifests
+        .iter()
+        .map(|manifest| {
+            serde_yaml::to_string(manifest)
+                .map_err(|e| Error::configuration(format!("Failed to render manifest: {e}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(documents.join("---\n"))
+}
+
+/// `name` as the name of a Kubernetes object: lowercase letters, digits
+/// and `-`
+fn object_name(name: &str) -> String {
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let name = name.trim_matches('-');
+    if name.is_empty() {
+        "cuenv".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+impl K8sCommands {
+    pub async fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            K8sCommands::Sync {
+                namespace,
+                name,
+                environment,
+                capabilities,
+                apply,
+                context,
+                output,
+            } => {
+                let mut env_manager = EnvManager::new();
+                env_manager
+                    .load_env_with_options(
+                        &config.working_dir,
+                        environment,
+                        capabilities.clone(),
+                        None,
+                        SupervisorMode::Synchronous,
+                    )
+                    .await?;
+                env_manager.resolve_deferred_secrets()?;
+
+                let variables: BTreeMap<String, Variable> = env_manager
+                    .get_cue_vars()
+                    .iter()
+                    .filter(|(key, _)| {
+                        capabilities.is_empty()
+                            || config
+                                .get_metadata(key)
+                                .and_then(|metadata| metadata.capability.as_ref())
+                                .is_some_and(|capability| capabilities.contains(capability))
+                    })
+                    .map(|(key, value)| {
+                        let value = value.clone();
+                        let secret = env_manager.is_secret(key);
+                        (key.clone(), Variable { value, secret })
+                    })
+                    .collect();
+                if variables.is_empty() {
+                    return Err(Error::configuration("No variables to sync"));
+                }
+
+                let name = object_name(&name.unwrap_or_else(|| {
+                    config
+                        .working_dir
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default()
+                }));
+                let yaml = render(&manifests(&name, &namespace, &variables))?;
+
+                if apply {
+                    return kubectl_apply(&yaml, context.as_deref());
+                }
+                match output {
+                    Some(path) => {
+                        // The Secret carries resolved values
+                        write_private(&path, yaml.as_bytes())?;
+                        println!("✓ Wrote {}", path.display());
+                    }
+                    None => print!("{yaml}"),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Apply the manifests with `kubectl apply`, in `context` when given
+fn kubectl_apply(yaml: &str, context: Option<&str>) -> Result<()> {
+    let mut args = vec!["apply".to_string(), "-f".to_string(), "-".to_string()];
+    if let Some(context) = context {
+        args.push(format!("--context={context}"));
+    }
+    let failed = |message: String, code: Option<i32>| {
+        Error::command_execution("kubectl", args.clone(), message, code)
+    };
+
+    let mut child = Command::new("kubectl")
+        .args(&args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(format!("failed to run kubectl: {e}"), None))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(yaml.as_bytes())
+            .map_err(|e| failed(format!("failed to write the manifests: {e}"), None))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| failed(format!("failed to wait for kubectl: {e}"), None))?;
+    if !status.success() {
+        return Err(failed(
+            "kubectl could not apply the manifests".to_string(),
+            status.code(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifests() {
+        let variable = |value: &str, secret: bool| Variable {
+            value: value.to_string(),
+            secret,
+        };
+        let variables = BTreeMap::from([
+            ("API_URL".to_string(), variable("http://api", false)),
+            ("DB_PASSWORD".to_string(), variable("hunter2", true)),
+        ]);
+
+        let yaml = render(&manifests("my-app", "dev", &variables)).unwrap();
+        assert_eq!(
+            yaml,
+            "apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: my-app
+  namespace: dev
+  labels:
+    app.kubernetes.io/managed-by: cuenv
+data:
+  API_URL: http://api
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: my-app
+  namespace: dev
+  labels:
+    app.kubernetes.io/managed-by: cuenv
+type: Opaque
+data:
+  DB_PASSWORD: aHVudGVyMg==
+"
+        );
+
+        let variables = BTreeMap::from([("API_URL".to_string(), variable("http://api", false))]);
+        let manifests = manifests("my-app", "dev", &variables);
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(manifests[0].kind, "ConfigMap");
+    }
+
+    #[test]
+    fn test_object_name() {
+        assert_eq!(object_name("My_App"), "my-app");
+        assert_eq!(object_name("_"), "cuenv");
+    }
+}
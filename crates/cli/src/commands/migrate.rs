@@ -0,0 +1,471 @@
+//! `cuenv migrate direnv`, an env.cue from a direnv `.envrc`
+//!
+//! The common lines of an `.envrc` are translated: exported variables,
+//! `$(…)` values, `PATH_add` and `path_add`, `dotenv`, and the Nix and
+//! devenv shells of `use`. An `.envrc` is a shell script, so anything else,
+//! such as conditionals or `layout`, is left for the user, reported with
+//! its line number and as a TODO comment in env.cue.
+
+use super::init::{cue_list, cue_string, header};
+use clap::Subcommand;
+use cuenv_config::{file_names, package_name, Config};
+use cuenv_core::{Error, Result, ENV_CUE_FILENAME};
+use std::path::{Path, PathBuf};
+
+/// Lines with nothing to translate: parent directories are inherited, and
+/// cuenv always fails on errors
+const NO_OPS: &[&str] = &["source_up", "source_up_if_exists", "strict_env"];
+
+#[derive(Subcommand)]
+pub enum MigrateCommands {
+    /// Write an env.cue equivalent to a direnv .envrc
+    Direnv {
+        /// File to migrate (defaults to .envrc in the current directory)
+        file: Option<PathBuf>,
+
+        /// Print env.cue instead of writing it
+        #[arg(long)]
+        print: bool,
+
+        /// Overwrite an existing env.cue
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// A variable value read from an `.envrc`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    /// A string, with `$` references cuenv expands
+    Literal(String),
+    /// The output of a command
+    Command(String),
+}
+
+/// What an `.envrc` translates to
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Migration {
+    variables: Vec<(String, Value)>,
+    /// Entries prepended to list variables such as PATH
+    prepends: Vec<(String, Vec<String>)>,
+    /// Hooks sourcing variables, as CUE structs
+    hooks: Vec<String>,
+    nix: Option<String>,
+    devenv: bool,
+    /// Lines left for the user, with their line numbers
+    untranslated: Vec<(usize, String)>,
+}
+
+impl Migration {
+    fn set(&mut self, name: String, value: Value) {
+        match self
+            .variables
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.variables.push((name, value)),
+        }
+    }
+
+    /// Prepend `entries` to `name`; direnv puts the entries of later lines
+    /// first
+    fn prepend(&mut self, name: &str, entries: Vec<String>) {
+        match self
+            .prepends
+            .iter_mut()
+            .find(|(existing, _)| existing == name)
+        {
+            Some((_, existing)) => {
+                existing.splice(0..0, entries);
+            }
+            None => self.prepends.push((name.to_string(), entries)),
+        }
+    }
+
+    /// Translate the line, returning `false` when it cannot be
+    fn translate(&mut self, line: &str, dir: &Path) -> bool {
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "export" => {
+                let Some((name, value)) = rest.split_once('=') else {
+                    return false;
+                };
+                if !is_env_name(name) {
+                    return false;
+                }
+                match value_of(value) {
+                    Some(value) => self.set(name.to_string(), value),
+                    None => return false,
+                }
+            }
+            "PATH_add" | "MANPATH_add" | "path_add" => {
+                let Some(words) = words(rest) else {
+                    return false;
+                };
+                let (name, dirs) = match command {
+                    "PATH_add" => ("PATH", words.as_slice()),
+                    "MANPATH_add" => ("MANPATH", words.as_slice()),
+                    _ => match words.split_first() {
+                        Some((name, dirs)) if is_env_name(name) => (name.as_str(), dirs),
+                        _ => return false,
+                    },
+                };
+                if dirs.is_empty() {
+                    return false;
+                }
+                let entries = dirs.iter().map(|dir| relative_entry(dir)).collect();
+                self.prepend(name, entries);
+            }
+            "dotenv" | "dotenv_if_exists" => {
+                let file = match words(rest).as_deref() {
+                    Some([]) => ".env".to_string(),
+                    Some([file]) => file.clone(),
+                    _ => return false,
+                };
+                // Sourced with `set -a`, exporting what the file assigns
+                let quoted = shell_quote(&file);
+                let script = if command == "dotenv" {
+                    format!("echo set -a; cat {quoted}")
+                } else {
+                    format!("[ ! -f {quoted} ] || {{ echo set -a; cat {quoted}; }}")
+                };
+                self.hooks
+                    .push(hook("sh", &["-c".to_string(), script], &[file]));
+            }
+            "use" => match words(rest).as_deref() {
+                Some([kind]) if kind == "flake" => self.nix = Some(".".to_string()),
+                Some([kind, flake]) if kind == "flake" => self.nix = Some(flake.clone()),
+                Some([kind]) if kind == "devenv" => self.devenv = true,
+                Some([kind]) if kind == "nix" => {
+                    let file =
+                        if dir.join("shell.nix").exists() || !dir.join("default.nix").exists() {
+                            "shell.nix"
+                        } else {
+                            "default.nix"
+                        };
+                    let args = ["print-dev-env", "--file", file].map(String::from);
+                    self.hooks.push(hook("nix", &args, &[file.to_string()]));
+                }
+                _ => return false,
+            },
+            command if NO_OPS.contains(&command) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// The env.cue in the package `package`
+    fn to_cue(&self, package: &str, source: &str) -> String {
+        let mut cue = header(package);
+        cue.push_str(&format!("// Migrated from {source}\n"));
+        for (number, line) in &self.untranslated {
+            cue.push_str(&format!("// TODO: translate line {number}: {line}\n"));
+        }
+
+        if let Some(flake) = &self.nix {
+            cue.push_str(&format!("\nnix: flake: {}\n", cue_string(flake)));
+        }
+        if self.devenv {
+            cue.push_str("\ndevenv: {}\n");
+        }
+        match self.hooks.as_slice() {
+            [] => {}
+            [hook] => cue.push_str(&format!("\nhooks: onEnter: {hook}\n")),
+            hooks => {
+                cue.push_str("\nhooks: onEnter: [\n");
+                for hook in hooks {
+                    cue.push_str(&format!("\t{hook},\n"));
+                }
+                cue.push_str("]\n");
+            }
+        }
+
+        cue.push_str("\nenv: {\n");
+        for (name, entries) in &self.prepends {
+            cue.push_str(&format!("\t{name}: prepend: {}\n", cue_list(entries)));
+        }
+        for (name, value) in &self.variables {
+            match value {
+                Value::Literal(value) => {
+                    cue.push_str(&format!("\t{name}: {}\n", cue_string(value)))
+                }
+                Value::Command(command) => {
+                    cue.push_str(&format!("\t{name}: fromCommand: {}\n", cue_string(command)))
+                }
+            }
+        }
+        cue.push_str("}\n");
+        cue
+    }
+}
+
+/// Translate an `.envrc` read from `dir`
+fn migrate(content: &str, dir: &Path) -> Migration {
+    let mut migration = Migration::default();
+    let mut lines = content.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        // Join the lines a trailing backslash continues
+        let mut line = line.to_string();
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, next)) => line.push_str(next),
+                None => break,
+            }
+        }
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !migration.translate(line, dir) {
+            migration.untranslated.push((index + 1, line.to_string()));
+        }
+    }
+    migration
+}
+
+fn is_env_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// The value of an assignment, or `None` for what is not a single word,
+/// such as `A=1 B=2` or `A=1; run`
+fn value_of(raw: &str) -> Option<Value> {
+    let unquoted = raw
+        .strip_prefix('"')
+        .and_then(|raw| raw.strip_suffix('"'))
+        .unwrap_or(raw);
+    let command = unquoted
+        .strip_prefix("$(")
+        .and_then(|raw| raw.strip_suffix(')'))
+        .or_else(|| {
+            unquoted
+                .strip_prefix('`')
+                .and_then(|raw| raw.strip_suffix('`'))
+        });
+    if let Some(command) = command {
+        if !command.contains(['(', ')', '`']) {
+            return Some(Value::Command(command.trim().to_string()));
+        }
+    }
+    match words(raw)?.as_slice() {
+        [value] => Some(Value::Literal(value.clone())),
+        [] => Some(Value::Literal(String::new())),
+        _ => None,
+    }
+}
+
+/// The shell words of `line` as cuenv values, in which `$` references are
+/// kept and literal `$` are escaped; `None` for what is more than words,
+/// such as command substitutions, pipes or redirections
+fn words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+                continue;
+            }
+            '#' if word.is_none() => break,
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        '$' => current.push_str("\\$"),
+                        c => current.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            '$' => current.push_str("\\$"),
+                            c @ ('"' | '\\' | '`') => current.push(c),
+                            c => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                        },
+                        '`' => return None,
+                        '$' if chars.peek() == Some(&'(') => return None,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                let current = word.get_or_insert_with(String::new);
+                match chars.next()? {
+                    '$' => current.push_str("\\$"),
+                    c => current.push(c),
+                }
+            }
+            ';' | '&' | '|' | '<' | '>' | '(' | ')' | '`' => return None,
+            '$' if chars.peek() == Some(&'(') => return None,
+            '~' if word.is_none() => word = Some("$HOME".to_string()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// A directory as a list entry, relative ones resolved against env.cue
+fn relative_entry(dir: &str) -> String {
+    if dir.starts_with(['/', '.', '$']) {
+        dir.to_string()
+    } else {
+        format!("./{dir}")
+    }
+}
+
+/// A hook sourcing the output of `command`, as a CUE struct
+fn hook(command: &str, args: &[String], inputs: &[String]) -> String {
+    format!(
+        "{{command: {}, args: {}, source: true, inputs: {}}}",
+        cue_string(command),
+        cue_list(args),
+        cue_list(inputs)
+    )
+}
+
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+impl MigrateCommands {
+    pub fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            MigrateCommands::Direnv { file, print, force } => {
+                let path = file.unwrap_or_else(|| config.working_dir.join(".envrc"));
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| Error::file_system(&path, "read", e))?;
+                let dir = path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+                let migration = migrate(&content, dir);
+                let source = path.file_name().unwrap_or_default().to_string_lossy();
+                let cue = migration.to_cue(&package_name(), &source);
+
+                if print {
+                    print!("{cue}");
+                } else {
+                    let file_name = file_names()
+                        .into_iter()
+                        .next()
+                        .unwrap_or_else(|| ENV_CUE_FILENAME.to_string());
+                    let env_file = config.working_dir.join(&file_name);
+                    if env_file.exists() && !force {
+                        return Err(Error::usage(format!(
+                            "{file_name} already exists. Use --print, or --force to overwrite it."
+                        )));
+                    }
+                    std::fs::write(&env_file, cue)
+                        .map_err(|e| Error::file_system(&env_file, "write", e))?;
+                    println!("✓ Migrated {source} to {file_name}");
+                }
+
+                if !migration.untranslated.is_empty() {
+                    eprintln!(
+                        "! {} lines could not be translated, see the TODO comments:",
+                        migration.untranslated.len()
+                    );
+                    for (number, line) in &migration.untranslated {
+                        eprintln!("  {source}:{number}: {line}");
+                    }
+                }
+                if !print {
+                    println!("  Run `cuenv env allow .` to load it");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let envrc = r#"#!/usr/bin/env bash
+source_up
+use flake .#dev
+dotenv_if_exists
+PATH_add bin
+PATH_add node_modules/.bin
+export DATABASE_URL="postgres://localhost:${PGPORT}/app"
+export PRICE='$5'
+export GIT_SHA=$(git rev-parse --short HEAD)
+export GREETING=hello \
+  world
+layout python
+if [ -f local.sh ]; then source local.sh; fi
+"#;
+        let migration = migrate(envrc, Path::new("/nonexistent"));
+        assert_eq!(migration.nix.as_deref(), Some(".#dev"));
+        assert_eq!(
+            migration.prepends,
+            vec![(
+                "PATH".to_string(),
+                vec!["./node_modules/.bin".to_string(), "./bin".to_string()]
+            )]
+        );
+        assert_eq!(
+            migration.variables,
+            vec![
+                (
+                    "DATABASE_URL".to_string(),
+                    Value::Literal("postgres://localhost:${PGPORT}/app".to_string())
+                ),
+                ("PRICE".to_string(), Value::Literal("\\$5".to_string())),
+                (
+                    "GIT_SHA".to_string(),
+                    Value::Command("git rev-parse --short HEAD".to_string())
+                ),
+            ]
+        );
+        assert_eq!(
+            migration.untranslated,
+            vec![
+                (10, "export GREETING=hello   world".to_string()),
+                (12, "layout python".to_string()),
+                (
+                    13,
+                    "if [ -f local.sh ]; then source local.sh; fi".to_string()
+                ),
+            ]
+        );
+
+        let cue = migration.to_cue("cuenv", ".envrc");
+        assert!(cue.contains("// TODO: translate line 12: layout python\n"));
+        assert!(cue.contains("\nnix: flake: \".#dev\"\n"));
+        assert!(cue.contains(
+            "\nhooks: onEnter: {command: \"sh\", args: [\"-c\", \"[ ! -f '.env' ] || { echo set -a; cat '.env'; }\"], source: true, inputs: [\".env\"]}\n"
+        ));
+        assert!(cue.contains("\tPATH: prepend: [\"./node_modules/.bin\", \"./bin\"]\n"));
+        assert!(cue.contains("\tGIT_SHA: fromCommand: \"git rev-parse --short HEAD\"\n"));
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words(r#"a "b c" d\ e ~/x # comment"#).unwrap(),
+            vec!["a", "b c", "d e", "$HOME/x"]
+        );
+        assert_eq!(words("a | b"), None);
+        assert_eq!(words("\"unterminated"), None);
+    }
+}
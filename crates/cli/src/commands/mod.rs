@@ -1,24 +1,60 @@
 use clap::Subcommand;
 use std::path::PathBuf;
 
+pub mod artifact;
 pub mod cache;
+pub mod ci;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod devcontainer;
 pub mod discover;
+pub mod doctor;
 pub mod env;
 pub mod exec;
+pub mod fmt;
+pub mod hooks;
+pub mod ide;
+pub mod import;
 pub mod init;
 pub mod internal;
+pub mod k8s;
 pub mod mcp;
+pub mod migrate;
+pub mod prompt;
+pub mod secret;
+pub mod service;
 pub mod shell;
 pub mod task;
+pub mod tmux;
+pub mod vet;
+pub mod workspace;
 
+use self::artifact::ArtifactCommands;
 use self::cache::CacheCommands;
+use self::ci::CiCommands;
+#[cfg(unix)]
+use self::daemon::DaemonCommands;
+use self::devcontainer::DevcontainerCommands;
 use self::env::EnvCommands;
+use self::hooks::HooksCommands;
+use self::ide::IdeCommands;
 use self::internal::InternalCommands;
+use self::k8s::K8sCommands;
+use self::migrate::MigrateCommands;
+use self::secret::SecretCommands;
+use self::service::ServiceCommands;
 use self::shell::ShellCommands;
+use self::tmux::TmuxCommands;
+use self::workspace::WorkspaceCommands;
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// List or execute tasks
+    /// List or execute tasks, infer a task's inputs and outputs with
+    /// `cuenv task infer <task>`, or show how a task resolves with
+    /// `cuenv task explain <task>`, or run a task in every package with
+    /// `cuenv task run-many <task>`; `cuenv task run <task>` runs a task
+    /// whatever its name
     #[command(visible_alias = "t")]
     Task {
         /// Task or group name (optional - lists all if not provided)
@@ -51,6 +87,47 @@ pub enum Commands {
         /// Generate Chrome trace output file
         #[arg(long)]
         trace_output: bool,
+
+        /// Record the run as a Chrome trace in FILE, to open in
+        /// chrome://tracing or Perfetto
+        #[arg(long, value_name = "FILE")]
+        profile: Option<PathBuf>,
+
+        /// Print a summary of the run with its critical path (text or json);
+        /// by default a table is printed when more than one task ran
+        #[arg(long, value_name = "FORMAT", value_parser = ["text", "json"])]
+        summary: Option<String>,
+
+        /// List tasks as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// List tasks as a table
+        #[arg(long, conflicts_with = "json")]
+        table: bool,
+
+        /// List only tasks tagged TAG (can be specified multiple times)
+        #[arg(long, value_name = "TAG")]
+        tagged: Vec<String>,
+
+        /// Run in each workspace package matching PATTERN, by name or path,
+        /// instead of the current directory (can be specified multiple times)
+        #[arg(
+            long,
+            value_name = "PATTERN",
+            conflicts_with_all = ["trace_output", "profile", "summary"]
+        )]
+        package: Vec<String>,
+
+        /// Run only the tasks whose inputs changed since --since, and the
+        /// tasks depending on them; names given after `run` narrow them
+        #[arg(long, conflicts_with = "package")]
+        affected: bool,
+
+        /// Git revision --affected compares with, from where the current
+        /// branch forked off it (default: origin/main)
+        #[arg(long, value_name = "REF", requires = "affected")]
+        since: Option<String>,
     },
 
     /// Manage environment configuration
@@ -60,11 +137,32 @@ pub enum Commands {
         command: EnvCommands,
     },
 
-    /// Initialize a new env.cue file with example configuration
+    /// Initialize a new env.cue file with tasks for the project in the
+    /// current directory
     Init {
         /// Force overwrite existing file
         #[arg(short, long)]
         force: bool,
+
+        /// Also add the shell hook to the rc file of SHELL, or of the
+        /// current shell (bash, zsh, fish)
+        #[arg(long, value_name = "SHELL")]
+        hook: Option<Option<String>>,
+    },
+
+    /// Add the tasks of a justfile, Makefile or Taskfile.yml to env.cue
+    Import {
+        /// Task runner to import from
+        #[arg(long, value_parser = ["justfile", "makefile", "taskfile"])]
+        from: String,
+
+        /// File to import (defaults to the task runner's file in the
+        /// current directory)
+        file: Option<PathBuf>,
+
+        /// Print the tasks instead of adding them to env.cue
+        #[arg(long)]
+        print: bool,
     },
 
     /// Discover all CUE packages in the repository
@@ -80,24 +178,142 @@ pub enum Commands {
         dump: bool,
     },
 
+    /// Format CUE configuration files like `cue fmt`
+    Fmt {
+        /// Files or directories to format (defaults to the current directory)
+        paths: Vec<PathBuf>,
+        /// Report unformatted files instead of rewriting them
+        #[arg(long)]
+        check: bool,
+        /// Print problems as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check a configuration against the cuenv schema and its task graph
+    Vet {
+        /// Directory to check (defaults to the current directory)
+        directory: Option<PathBuf>,
+        /// Print problems as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check that cuenv works on this machine, with fixes for what does not
+    Doctor {
+        /// Print the checks as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the effective settings, from the project, the user's
+    /// config.toml or the defaults
+    Config {
+        /// Print the settings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Manage the task and environment cache
     Cache {
         #[command(subcommand)]
         command: CacheCommands,
     },
 
+    /// Copy the named artifacts of tasks out of the cache
+    Artifact {
+        #[command(subcommand)]
+        command: ArtifactCommands,
+    },
+
+    /// Run the task graph in CI
+    Ci {
+        #[command(subcommand)]
+        command: CiCommands,
+    },
+
+    /// Set up a dev container that installs cuenv and loads env.cue
+    Devcontainer {
+        #[command(subcommand)]
+        command: DevcontainerCommands,
+    },
+
+    /// Manage the daemon that keeps evaluated environments warm
+    #[cfg(unix)]
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommands,
+    },
+
+    /// Run tasks as git hooks on the files a commit or push changes
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommands,
+    },
+
+    /// Export the tasks and environment to an editor
+    Ide {
+        #[command(subcommand)]
+        command: IdeCommands,
+    },
+
+    /// Sync the variables of env.cue to a Kubernetes cluster
+    K8s {
+        #[command(subcommand)]
+        command: K8sCommands,
+    },
+
+    /// Write an env.cue equivalent to the configuration of another tool
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommands,
+    },
+
+    /// Manage secrets kept encrypted on this machine
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommands,
+    },
+
+    /// Run service tasks as systemd user services
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommands,
+    },
+
     /// Configure shell integration for automatic environment loading
     Shell {
         #[command(subcommand)]
         command: ShellCommands,
     },
 
+    /// Share the loaded environment with new tmux panes and windows
+    Tmux {
+        #[command(subcommand)]
+        command: TmuxCommands,
+    },
+
+    /// List the packages a cuenv.workspace.cue declares
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommands,
+    },
+
     /// Generate shell completion scripts
     Completion {
         /// Shell to generate completion for
+        #[arg(value_parser = ["bash", "zsh", "fish", "nu", "powershell", "pwsh", "elvish"])]
         shell: String,
     },
 
+    /// Print the loaded directory, environments and number of secrets for
+    /// a shell prompt
+    Prompt {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Execute a command with the loaded environment
     Exec {
         /// Environment to use (e.g., dev, staging, production)
@@ -108,18 +324,30 @@ pub enum Commands {
         #[arg(short = 'c', long = "capability")]
         capabilities: Vec<String>,
 
-        /// Command to run
-        command: String,
-
-        /// Arguments to pass to the command
-        args: Vec<String>,
-
         /// Run in audit mode to see file and network access without restrictions
         #[arg(long)]
         audit: bool,
+
+        /// Command to run, followed by its arguments. Everything after the
+        /// command is passed to it, options included.
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_names = ["COMMAND", "ARGS"]
+        )]
+        command: Vec<String>,
     },
 
     // Internal commands
+    /// Print completions for the words after `cuenv`, the last one being
+    /// completed, as called by the completion scripts
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        words: Vec<String>,
+    },
+
     /// Internal completion helper - complete task names
     #[command(name = "_complete_tasks", hide = true)]
     CompleteTasks,
@@ -0,0 +1,117 @@
+//! `cuenv prompt`, a status segment for shell prompts
+//!
+//! Prompts run this on every render, so it only reads the state the shell
+//! hook keeps in the environment and never evaluates the configuration.
+
+use cuenv_core::Result;
+use cuenv_env::state::CuenvState;
+use cuenv_env::StateManager;
+use serde::Serialize;
+
+/// The environment every directory loads unless another is selected
+const DEFAULT_ENVIRONMENT: &str = "default";
+
+/// What the prompt shows about the loaded environment
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct PromptStatus {
+    /// The name of the loaded directory
+    pub project: String,
+    /// The selected environments, unless the default one is loaded
+    pub environment: Option<String>,
+    /// How many of the loaded variables are secrets
+    pub secrets: usize,
+}
+
+impl PromptStatus {
+    pub fn from_state(state: &CuenvState) -> Self {
+        let project = state
+            .dir
+            .file_name()
+            .unwrap_or(state.dir.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        Self {
+            project,
+            environment: state
+                .environment
+                .clone()
+                .filter(|environment| environment != DEFAULT_ENVIRONMENT),
+            secrets: state.secrets,
+        }
+    }
+}
+
+impl std::fmt::Display for PromptStatus {
+    /// `project (environment, 2 secrets)`, leaving out what is not there
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.project)?;
+
+        let mut details = Vec::new();
+        if let Some(environment) = &self.environment {
+            details.push(environment.clone());
+        }
+        match self.secrets {
+            0 => {}
+            1 => details.push("1 secret".to_string()),
+            n => details.push(format!("{n} secrets")),
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Print the segment, or nothing outside a loaded directory
+pub fn execute(json: bool) -> Result<()> {
+    let status = StateManager::get_state()
+        .ok()
+        .flatten()
+        .map(|state| PromptStatus::from_state(&state));
+
+    match (status, json) {
+        (Some(status), true) => println!("{}", serde_json::to_string(&status)?),
+        (None, true) => println!("{{}}"),
+        (Some(status), false) => println!("{status}"),
+        (None, false) => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn state(environment: Option<&str>, secrets: usize) -> CuenvState {
+        CuenvState {
+            dir: PathBuf::from("/home/dev/api"),
+            file: PathBuf::from("/home/dev/api/env.cue"),
+            environment: environment.map(str::to_string),
+            capabilities: Vec::new(),
+            secrets,
+        }
+    }
+
+    #[test]
+    fn test_segment() {
+        let segment = |environment, secrets| {
+            PromptStatus::from_state(&state(environment, secrets)).to_string()
+        };
+        assert_eq!(segment(Some("default"), 0), "api");
+        assert_eq!(segment(None, 1), "api (1 secret)");
+        assert_eq!(
+            segment(Some("base+staging"), 3),
+            "api (base+staging, 3 secrets)"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let status = PromptStatus::from_state(&state(Some("staging"), 2));
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"project":"api","environment":"staging","secrets":2}"#
+        );
+    }
+}
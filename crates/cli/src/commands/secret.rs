@@ -0,0 +1,110 @@
+use clap::Subcommand;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use cuenv_core::{Error, Result};
+use cuenv_security::SecretStore;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+#[derive(Subcommand)]
+pub enum SecretCommands {
+    /// Store a secret, read from stdin or prompted for without echo
+    Set {
+        /// Name to reference with `{ fromLocalStore: "NAME" }`
+        name: String,
+    },
+    /// Print a stored secret
+    Get { name: String },
+    /// List the names of stored secrets
+    List,
+    /// Delete a stored secret
+    Remove { name: String },
+}
+
+impl SecretCommands {
+    pub async fn execute(self) -> Result<()> {
+        let store = SecretStore::user();
+        match self {
+            SecretCommands::Set { name } => {
+                let value = read_secret(&name)?;
+                if value.is_empty() {
+                    return Err(Error::configuration(format!(
+                        "No value given for secret {name}"
+                    )));
+                }
+                store.set(&name, &value)?;
+                eprintln!("✓ Stored {name} in the local secret store");
+                Ok(())
+            }
+            SecretCommands::Get { name } => match store.get(&name)? {
+                Some(value) => {
+                    println!("{value}");
+                    Ok(())
+                }
+                None => Err(Error::configuration(format!(
+                    "Secret {name} is not in the local store"
+                ))),
+            },
+            SecretCommands::List => {
+                for name in store.list()? {
+                    println!("{name}");
+                }
+                Ok(())
+            }
+            SecretCommands::Remove { name } => {
+                if store.remove(&name)? {
+                    eprintln!("✓ Removed {name} from the local secret store");
+                } else {
+                    eprintln!("Secret {name} is not in the local store");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Read the value from piped stdin, or prompt for it without echoing
+fn read_secret(name: &str) -> Result<String> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        let mut line = String::new();
+        stdin
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| Error::configuration(format!("Failed to read secret from stdin: {e}")))?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    eprint!("Value for {name}: ");
+    let _ = io::stderr().flush();
+    terminal::enable_raw_mode()
+        .map_err(|e| Error::configuration(format!("Failed to prompt for secret: {e}")))?;
+    let value = read_hidden_line();
+    let _ = terminal::disable_raw_mode();
+    eprintln!();
+    value
+}
+
+fn read_hidden_line() -> Result<String> {
+    let mut value = String::new();
+    loop {
+        let event = event::read()
+            .map_err(|e| Error::configuration(format!("Failed to read secret: {e}")))?;
+        let Event::Key(key) = event else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Enter => return Ok(value),
+            KeyCode::Backspace => {
+                value.pop();
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Err(Error::configuration("Cancelled"));
+            }
+            KeyCode::Char(c) => value.push(c),
+            KeyCode::Esc => return Err(Error::configuration("Cancelled")),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! `cuenv service install`, service tasks as systemd user services
+//!
+//! A task declaring `service` is rendered as a systemd user unit running it
+//! through `cuenv exec`, which loads the environment and resolves its
+//! secrets each time the service starts, so the unit holds no secret.
+//! systemd restarts it as the task's `service` says, and it keeps running
+//! after the terminal that started it is closed.
+
+use clap::Subcommand;
+use cuenv_config::{Config, RestartPolicy, ServiceConfig, TaskConfig};
+use cuenv_core::constants::DEFAULT_SHELL;
+use cuenv_core::{default_shell_args, Error, Result};
+use cuenv_utils::atomic_file::write_atomic_string;
+use cuenv_utils::tracing::{message, Level};
+use std::path::Path;
+use std::process::Command;
+
+/// Window `retries` counts restarts in
+const RETRY_WINDOW_SECS: u32 = 300;
+
+#[derive(Subcommand)]
+pub enum ServiceCommands {
+    /// Write a systemd user unit running a service task
+    Install {
+        /// Task to run, which must declare `service`
+        task: String,
+
+        /// Environment to load
+        #[arg(short = 'e', long = "env")]
+        environment: Option<String>,
+
+        /// Capabilities to enable (can be specified multiple times)
+        #[arg(short = 'c', long = "capability")]
+        capabilities: Vec<String>,
+
+        /// Enable and start the service once installed
+        #[arg(long)]
+        now: bool,
+
+        /// Print the unit instead of installing it
+        #[arg(long, conflicts_with = "now")]
+        print: bool,
+    },
+}
+
+impl ServiceCommands {
+    pub async fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            ServiceCommands::Install {
+                task,
+                environment,
+                capabilities,
+                now,
+                print,
+            } => {
+                let task_config = config
+                    .get_tasks()
+                    .get(&task)
+                    .ok_or_else(|| Error::usage(format!("No task named '{task}'")))?;
+                let service = task_config.service.as_ref().ok_or_else(|| {
+                    Error::usage(format!(
+                        "Task '{task}' is not a service; declare `service: {{}}` on it"
+                    ))
+                })?;
+
+                let exe = std::env::current_exe().map_err(|e| {
+                    Error::configuration(format!("Failed to locate the cuenv binary: {e}"))
+                })?;
+                let mut exec_start = vec![exe.to_string_lossy().into_owned(), "exec".to_string()];
+                if let Some(environment) = &environment {
+                    exec_start.extend(["-e".to_string(), environment.clone()]);
+                }
+                for capability in &capabilities {
+                    exec_start.extend(["-c".to_string(), capability.clone()]);
+                }
+                exec_start.extend(task_command(&task, task_config)?);
+
+                let project = config
+                    .working_dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let content = unit(
+                    &format!("cuenv task {task} of {project}"),
+                    &exec_start,
+                    &config.working_dir,
+                    service,
+                );
+                if print {
+                    message(Level::INFO, content.trim_end());
+                    return Ok(());
+                }
+
+                let name = unit_name(&project, &task);
+                let dir = dirs::config_dir()
+                    .ok_or_else(|| Error::configuration("Could not determine config directory"))?
+                    .join("systemd")
+                    .join("user");
+                let path = dir.join(&name);
+                write_atomic_string(&path, &content)?;
+                message(Level::INFO, &format!("✓ Wrote {}", path.display()));
+
+                systemctl(&["daemon-reload"])?;
+                if now {
+                    systemctl(&["enable", "--now", &name])?;
+                    message(Level::INFO, &format!("✓ Started {name}"));
+                } else {
+                    message(
+                        Level::INFO,
+                        &format!("  Start it with `systemctl --user enable --now {name}`"),
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The shell command running the task, in its working directory
+fn task_command(name: &str, task: &TaskConfig) -> Result<Vec<String>> {
+    let command = task
+        .command
+        .as_ref()
+        .or(task.script.as_ref())
+        .ok_or_else(|| Error::usage(format!("Task '{name}' has no command or script")))?;
+    let command = match &task.working_dir {
+        Some(dir) => format!("cd {} && {command}", shell_quote(dir)),
+        None => command.clone(),
+    };
+    let (shell, args) = match &task.shell {
+        Some(shell) => (shell.program().to_string(), shell.args()),
+        None => (DEFAULT_SHELL.to_string(), default_shell_args(DEFAULT_SHELL)),
+    };
+    Ok([vec![shell], args, vec![command]].concat())
+}
+
+/// The systemd user unit running `exec_start` in `working_dir`
+fn unit(
+    description: &str,
+    exec_start: &[String],
+    working_dir: &Path,
+    service: &ServiceConfig,
+) -> String {
+    let mut unit =
+        format!("# Generated by `cuenv service install`\n[Unit]\nDescription={description}\n");
+    if let Some(retries) = service.retries {
+        unit.push_str(&format!(
+            "StartLimitIntervalSec={RETRY_WINDOW_SECS}\nStartLimitBurst={}\n",
+            retries + 1
+        ));
+    }
+
+    unit.push_str("\n[Service]\nType=simple\n");
+    unit.push_str(&format!(
+        "WorkingDirectory={}\n",
+        working_dir.to_string_lossy().replace('%', "%%")
+    ));
+    let exec_start: Vec<String> = exec_start
+        .iter()
+        .map(|word| quote(word).replace('$', "$$"))
+        .collect();
+    unit.push_str(&format!("ExecStart={}\n", exec_start.join(" ")));
+    let restart = match service.restart {
+        RestartPolicy::Always => "always",
+        RestartPolicy::OnFailure => "on-failure",
+        RestartPolicy::No => "no",
+    };
+    unit.push_str(&format!("Restart={restart}\n"));
+    if let Some(delay) = service.delay {
+        unit.push_str(&format!("RestartSec={delay}\n"));
+    }
+
+    unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    unit
+}
+
+/// The unit file name of `task` of `project`
+fn unit_name(project: &str, task: &str) -> String {
+    let name: String = format!("cuenv-{project}-{task}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{name}.service")
+}
+
+/// `word` as one word of a unit file, with specifiers escaped
+fn quote(word: &str) -> String {
+    let word = word.replace('%', "%%");
+    let plain = !word.is_empty()
+        && !word
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\;".contains(c));
+    if plain {
+        return word;
+    }
+    let escaped = word
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let mut command_args = vec!["--user".to_string()];
+    command_args.extend(args.iter().map(|arg| arg.to_string()));
+    let status = Command::new("systemctl")
+        .args(&command_args)
+        .status()
+        .map_err(|e| {
+            Error::command_execution(
+                "systemctl",
+                command_args.clone(),
+                format!("failed to run systemctl: {e}"),
+                None,
+            )
+        })?;
+    if !status.success() {
+        return Err(Error::command_execution(
+            "systemctl",
+            command_args,
+            "systemctl failed".to_string(),
+            status.code(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit() {
+        let task = TaskConfig {
+            command: Some("npm run dev -- --port $PORT".to_string()),
+            working_dir: Some("web".to_string()),
+            ..Default::default()
+        };
+        let mut exec_start = vec!["/usr/bin/cuenv".to_string(), "exec".to_string()];
+        exec_start.extend(task_command("dev", &task).unwrap());
+        let service = ServiceConfig {
+            restart: RestartPolicy::Always,
+            retries: Some(3),
+            delay: Some(5),
+        };
+
+        assert_eq!(
+            unit(
+                "cuenv task dev of app",
+                &exec_start,
+                Path::new("/home/me/app"),
+                &service
+            ),
+            r#"# Generated by `cuenv service install`
+[Unit]
+Description=cuenv task dev of app
+StartLimitIntervalSec=300
+StartLimitBurst=4
+
+[Service]
+Type=simple
+WorkingDirectory=/home/me/app
+ExecStart=/usr/bin/cuenv exec bash -e -c "cd 'web' && npm run dev -- --port $$PORT"
+Restart=always
+RestartSec=5
+
+[Install]
+WantedBy=default.target
+"#
+        );
+    }
+
+    #[test]
+    fn test_unit_name() {
+        assert_eq!(unit_name("my app", "db:up"), "cuenv-my-app-db-up.service");
+    }
+}
@@ -1,11 +1,18 @@
-use crate::directory::DirectoryManager;
+use crate::commands::prompt::PromptStatus;
+use crate::directory::{DirectoryManager, TrustStatus};
 use crate::platform::{PlatformOps, Shell};
 use clap::Subcommand;
-use cuenv_core::{Result, CUENV_CAPABILITIES_VAR, CUENV_ENV_VAR, ENV_CUE_FILENAME};
-use cuenv_env::{manager::environment::SupervisorMode, EnvManager, StateManager};
-use cuenv_shell::{ShellHook, ShellType};
+use cuenv_config::{configured_file_names, has_package, package_name, FILE_NAME_SEPARATOR};
+use cuenv_core::{Result, CUENV_CAPABILITIES_VAR, CUENV_ENV_VAR, CUENV_FILE_VAR};
+use cuenv_env::{
+    manager::environment::SupervisorMode, ChangeSummary, EnvDiff, EnvManager, EnvironmentSelection,
+    StateManager,
+};
+use cuenv_shell::{osc, tmux, ShellHook, ShellType};
 use cuenv_utils::sync::env::InstanceLock;
+use std::collections::HashMap;
 use std::env;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 
 // Import the platform-specific implementation
@@ -48,6 +55,18 @@ impl ShellCommands {
         match self {
             ShellCommands::Init { shell } => match ShellHook::generate_hook(&shell) {
                 Ok(output) => {
+                    // The hook discovers configurations by the file names
+                    // given with --file, or set in CUENV_FILE
+                    if let Some(names) = configured_file_names() {
+                        let shell_impl = ShellType::from_name(&shell).as_shell();
+                        println!(
+                            "{}",
+                            shell_impl.hook_export(
+                                CUENV_FILE_VAR,
+                                &names.join(&FILE_NAME_SEPARATOR.to_string())
+                            )
+                        );
+                    }
                     print!("{output}");
                     Ok(())
                 }
@@ -87,35 +106,25 @@ impl ShellCommands {
                     .load_env_with_options(&dir, env_name, caps, None, SupervisorMode::Foreground)
                     .await?;
 
-                let shell = Platform::get_current_shell()
-                    .unwrap_or(Shell::Bash)
-                    .as_str();
-
-                match env_manager.export_for_shell(shell) {
-                    Ok(output) => {
-                        print!("{output}");
-                        Ok(())
-                    }
-                    Err(e) => Err(e),
-                }
+                let shell = Platform::get_current_shell().unwrap_or(Shell::Bash);
+                let shell_impl = ShellType::from_name(shell.as_str()).as_shell();
+                print!("{}", shell_impl.apply(&env_manager.changes()?));
+                Ok(())
             }
             ShellCommands::Unload => {
                 let _lock = InstanceLock::acquire()?;
 
-                let mut env_manager = EnvManager::new();
-                env_manager.unload_env()?;
-
-                let shell = Platform::get_current_shell()
-                    .unwrap_or(Shell::Bash)
-                    .as_str();
+                let shell_env: HashMap<String, String> = env::vars().collect();
+                StateManager::restore_and_unload().await.map_err(|e| {
+                    cuenv_core::Error::configuration(format!("Failed to unload environment: {e}"))
+                })?;
 
-                match env_manager.export_for_shell(shell) {
-                    Ok(output) => {
-                        print!("{output}");
-                        Ok(())
-                    }
-                    Err(e) => Err(e),
-                }
+                let shell_type = match Platform::get_current_shell() {
+                    Ok(shell) => ShellType::from_name(shell.as_str()),
+                    Err(_) => ShellType::Bash,
+                };
+                print_shell_changes(shell_type.as_shell().as_ref(), &shell_env);
+                Ok(())
             }
             ShellCommands::Hook { shell } => {
                 // Set environment variable to indicate we're in shell hook mode
@@ -142,49 +151,59 @@ impl ShellCommands {
                 let shell_impl = shell_type.as_shell();
                 let current_dir = env::current_dir()?;
 
+                // The environment the shell currently has; everything printed
+                // below is the difference between this and our final state
+                let shell_env: HashMap<String, String> = env::vars().collect();
+                let previous_diff = StateManager::get_diff().ok().flatten();
+
                 // First check if we need to unload
                 if StateManager::should_unload(&current_dir) {
-                    if let Ok(Some(diff)) = StateManager::get_diff() {
-                        for key in diff.removed() {
-                            println!("{}", shell_impl.unset(key));
-                        }
-                        for (key, _) in diff.added_or_changed() {
-                            if diff.prev.contains_key(key) {
-                                if let Some(orig_value) = diff.prev.get(key) {
-                                    println!("{}", shell_impl.export(key, orig_value));
-                                }
-                            } else {
-                                println!("{}", shell_impl.unset(key));
-                            }
-                        }
-                    }
-                    StateManager::unload().await.map_err(|e| {
-                        cuenv_core::Error::configuration(format!("Failed to unload state: {e}"))
+                    StateManager::restore_and_unload().await.map_err(|e| {
+                        cuenv_core::Error::configuration(format!(
+                            "Failed to unload environment: {e}"
+                        ))
                     })?;
                 }
 
                 // Then check if current directory has an environment to load
-                if current_dir.join(ENV_CUE_FILENAME).exists() {
+                if has_package(&current_dir, &package_name()) {
                     let dir_manager = DirectoryManager::new();
-                    if dir_manager
-                        .is_directory_allowed(&current_dir)
-                        .unwrap_or(false)
-                    {
+                    let trust = dir_manager
+                        .trust_status(&current_dir)
+                        .unwrap_or(TrustStatus::NotAllowed);
+
+                    // Evaluating env.cue can run hooks and secret resolvers, so
+                    // nothing is loaded until the current contents are approved
+                    if trust == TrustStatus::Allowed {
                         // Check for completed background hooks ONLY if directory is allowed
                         if let Some(completed_env) =
                             cuenv_env::manager::environment::hooks::load_captured_environment()
                         {
-                            // Apply newly available environment
+                            // The captured environment holds every variable the
+                            // hooks saw; only those that differ from the shell's
+                            // end up in the output below
                             for (key, value) in completed_env {
-                                println!("{}", shell_impl.export(&key, &value));
+                                env::set_var(key, value);
                             }
 
                             // Show subtle notification
                             eprintln!("# cuenv: ✓ Background hooks completed, environment updated");
                         }
 
-                        if StateManager::files_changed() || StateManager::should_load(&current_dir)
-                        {
+                        let reloading = !StateManager::should_load(&current_dir)
+                            && (StateManager::files_changed() || environment_changed(&current_dir));
+
+                        if reloading || StateManager::should_load(&current_dir) {
+                            // Re-evaluate from the pre-cuenv environment so that
+                            // variables removed from env.cue disappear as well
+                            if let (true, Some(diff)) = (reloading, &previous_diff) {
+                                diff.restore().map_err(|e| {
+                                    cuenv_core::Error::configuration(format!(
+                                        "Failed to restore environment: {e}"
+                                    ))
+                                })?;
+                            }
+
                             let mut env_manager = EnvManager::new();
                             if let Err(e) = env_manager
                                 .load_env_with_options(
@@ -197,23 +216,104 @@ impl ShellCommands {
                                 .await
                             {
                                 eprintln!("# cuenv: failed to load environment: {e}");
-                            } else if let Ok(Some(diff)) = StateManager::get_diff() {
-                                for (key, value) in diff.added_or_changed() {
-                                    println!("{}", shell_impl.export(key, value));
-                                }
-                                for key in diff.removed() {
-                                    println!("{}", shell_impl.unset(key));
-                                }
+                            } else if let (true, Some(previous), Ok(Some(current))) =
+                                (reloading, &previous_diff, StateManager::get_diff())
+                            {
+                                eprintln!(
+                                    "# cuenv: reloaded environment ({})",
+                                    ChangeSummary::between(previous, &current)
+                                );
                             }
                         }
+                    } else if trust == TrustStatus::Changed {
+                        eprintln!(
+                            "# cuenv: env.cue changed since it was allowed. Review it and run 'cuenv env allow' to trust the new contents.",
+                        );
                     } else {
                         eprintln!(
                             "# cuenv: Directory not allowed. Run 'cuenv env allow' to allow this directory.",
                         );
                     }
                 }
+
+                print_shell_changes(shell_impl.as_ref(), &shell_env);
+                if osc::enabled() {
+                    announce_to_terminal(&current_dir);
+                }
                 Ok(())
             }
         }
     }
 }
+
+/// Whether `cuenv env use` or `CUENV_ENV` selected other environments than
+/// the loaded ones
+fn environment_changed(dir: &std::path::Path) -> bool {
+    let selected = env::var(CUENV_ENV_VAR)
+        .ok()
+        .or_else(|| EnvironmentSelection::user().get(dir))
+        .unwrap_or_else(|| "default".to_string());
+    StateManager::get_state()
+        .ok()
+        .flatten()
+        .is_some_and(|state| state.environment.as_deref() != Some(selected.as_str()))
+}
+
+/// Tell the terminal the working directory and the loaded environment
+///
+/// The hook's output is evaluated by the shell, so the sequences go to
+/// stderr, which is the terminal itself in an interactive shell.
+fn announce_to_terminal(dir: &std::path::Path) {
+    let mut stderr = std::io::stderr();
+    if !stderr.is_terminal() {
+        return;
+    }
+
+    let state = StateManager::get_state().ok().flatten();
+    let project = state
+        .as_ref()
+        .map(|state| PromptStatus::from_state(state).project);
+    let environment = state.and_then(|state| state.environment);
+    let sequences = osc::sequences(dir, project.as_deref(), environment.as_deref());
+    let _ = stderr.write_all(sequences.as_bytes());
+}
+
+/// Print the commands that bring the shell from `shell_env` to our environment
+///
+/// Only variables whose value differs from the shell's are printed, so a
+/// prompt where nothing changed prints nothing. Besides the variables
+/// themselves this exports the cuenv state, so the next prompt knows what is
+/// loaded and how to undo it.
+pub(crate) fn print_shell_changes(
+    shell: &dyn cuenv_shell::Shell,
+    shell_env: &HashMap<String, String>,
+) {
+    let final_env: HashMap<String, String> = env::vars().collect();
+    let changes = EnvDiff::new(shell_env.clone(), final_env);
+    print!("{}", shell.apply(&changes));
+
+    let mut state_changes = Vec::new();
+    for (name, value) in StateManager::exported_vars() {
+        match value {
+            Some(value) if shell_env.get(&name) != Some(&value) => {
+                println!("{}", shell.export(&name, &value));
+                state_changes.push((name, Some(value)));
+            }
+            None if shell_env.contains_key(&name) => {
+                println!("{}", shell.unset(&name));
+                state_changes.push((name, None));
+            }
+            _ => {}
+        }
+    }
+
+    // New tmux panes start from the session environment rather than this
+    // shell's, so it gets the same changes, state included
+    if tmux::enabled() {
+        let mut tmux_changes = tmux::changes(&changes);
+        tmux_changes.extend(state_changes);
+        if let Err(e) = tmux::propagate(&tmux_changes) {
+            eprintln!("# cuenv: failed to update the tmux environment: {e}");
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! `cuenv task run --affected`, the tasks the changes of a branch touch
+//!
+//! The files changed since the merge base with `--since`, committed or not,
+//! are matched against the `inputs` of every task, relative to its working
+//! directory. A task without `inputs` is affected by any change below its
+//! working directory. The tasks depending on an affected task are affected
+//! too, across packages in a monorepo, and only those run.
+
+use super::{finish_run, formatter, profile, TaskRunOptions};
+use cuenv_config::Config;
+use cuenv_core::{Error, Result};
+use cuenv_task::{parse_reference, CrossPackageReference, TaskExecutor};
+use cuenv_utils::tracing::{message, Level};
+use globset::Glob;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The revision changes are compared with when `--since` is not given
+pub const DEFAULT_SINCE: &str = "origin/main";
+
+/// A task the changed files are matched against
+#[derive(Debug, Clone)]
+struct Candidate {
+    name: String,
+    /// Directory its inputs are relative to
+    dir: PathBuf,
+    inputs: Option<Vec<String>>,
+    dependencies: Vec<String>,
+}
+
+/// Run the tasks affected by the changes since `since`, or those of them
+/// named `only`
+pub async fn execute(
+    config: Arc<Config>,
+    since: String,
+    only: Vec<String>,
+    options: &TaskRunOptions,
+) -> Result<()> {
+    let started = Instant::now();
+    let current_dir = config.working_dir.clone();
+    let changed = changed_files(&current_dir, &since)?;
+
+    // Tasks run through the registry, without the local environment, only
+    // when other packages can depend on them
+    let registry = if crate::monorepo::is_monorepo(&current_dir) {
+        Some(crate::monorepo::task_registry(&current_dir).await?)
+            .filter(|registry| registry.package_count() > 1)
+    } else {
+        None
+    };
+    let candidates: Vec<Candidate> = match &registry {
+        Some(registry) => registry
+            .list_all_tasks()
+            .into_iter()
+            .filter_map(|(name, _)| registry.get_task(&name))
+            .map(|task| Candidate {
+                name: task.full_name.clone(),
+                dir: task_dir(&task.package_path, task.config.working_dir.as_deref()),
+                inputs: task.config.inputs.clone(),
+                dependencies: task
+                    .config
+                    .dependencies
+                    .iter()
+                    .flatten()
+                    .filter_map(|dependency| {
+                        full_name(&task.package_name, dependency, |name| {
+                            registry.get_task(name).is_some()
+                        })
+                    })
+                    .collect(),
+            })
+            .collect(),
+        None => config
+            .get_tasks()
+            .iter()
+            .map(|(name, task)| Candidate {
+                name: name.clone(),
+                dir: task_dir(&current_dir, task.working_dir.as_deref()),
+                inputs: task.inputs.clone(),
+                dependencies: task.dependencies.clone().unwrap_or_default(),
+            })
+            .collect(),
+    };
+
+    let tasks: Vec<String> = affected(&candidates, &changed)?
+        .into_iter()
+        .filter(|name| {
+            only.is_empty()
+                || only
+                    .iter()
+                    .any(|task| name == task || name.ends_with(&format!(":{task}")))
+        })
+        .collect();
+    if tasks.is_empty() {
+        message(
+            Level::INFO,
+            &format!(
+                "No task is affected by the {} files changed since {since}",
+                changed.len()
+            ),
+        );
+        return Ok(());
+    }
+    message(
+        Level::INFO,
+        &format!(
+            "{} files changed since {since} affect {}",
+            changed.len(),
+            tasks.join(", ")
+        ),
+    );
+
+    let (executor, status) = match registry {
+        Some(registry) => {
+            registry.validate_all_dependencies()?;
+            let executor = TaskExecutor::new_with_registry(registry).await?;
+            let status = executor
+                .execute_tasks_with_dependencies(&tasks, &[], options.audit)
+                .await;
+            (executor, status)
+        }
+        None => {
+            let (current_dir, env_manager) = super::load_task_environment(
+                options.environment.clone(),
+                options.capabilities.clone(),
+            )
+            .await?;
+            let executor = TaskExecutor::new(env_manager, current_dir).await?;
+            let status = formatter::execute_tasks_with_formatter(
+                &executor,
+                &tasks,
+                &[],
+                options.audit,
+                &options.output_format,
+                options.trace_output,
+            )
+            .await;
+            (executor, status)
+        }
+    };
+    finish_run(&config, Some(&executor), "affected", &status, started).await;
+    let status = status?;
+    if status != 0 {
+        profile::exit("affected", status);
+    }
+    Ok(())
+}
+
+/// The directory the inputs of a task of the package in `package_dir` are
+/// relative to
+fn task_dir(package_dir: &Path, working_dir: Option<&str>) -> PathBuf {
+    let dir = match working_dir {
+        Some(working_dir) => package_dir.join(working_dir),
+        None => package_dir.to_path_buf(),
+    };
+    dir.canonicalize().unwrap_or(dir)
+}
+
+/// The full name of a dependency of a task of `package`
+///
+/// As when the tasks run, a task of the same package, per `is_task`, wins
+/// over a cross-package reading of a name such as `npm:build`.
+fn full_name(package: &str, dependency: &str, is_task: impl Fn(&str) -> bool) -> Option<String> {
+    let local = format!("{package}:{dependency}");
+    if is_task(&local) {
+        return Some(local);
+    }
+    match parse_reference(dependency).ok()? {
+        CrossPackageReference::LocalTask { task } => Some(format!("{package}:{task}")),
+        CrossPackageReference::PackageTask { package, task }
+        | CrossPackageReference::PackageTaskOutput { package, task, .. } => {
+            Some(format!("{package}:{task}"))
+        }
+    }
+}
+
+/// The files changed since the merge base of `since` and HEAD, including
+/// uncommitted and untracked files
+fn changed_files(dir: &Path, since: &str) -> Result<Vec<PathBuf>> {
+    let root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+    let base = git(dir, &["merge-base", since, "HEAD"])?;
+    let mut files = BTreeSet::new();
+    for output in [
+        git(
+            &root,
+            &["diff", "-z", "--name-only", "--no-renames", base.trim()],
+        )?,
+        git(&root, &["ls-files", "-z", "--others", "--exclude-standard"])?,
+    ] {
+        files.extend(
+            output
+                .split('\0')
+                .filter(|file| !file.is_empty())
+                .map(|file| root.join(file)),
+        );
+    }
+    Ok(files.into_iter().collect())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let output = Command::new("git")
+        .args(&args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| {
+            Error::command_execution("git", args.clone(), format!("failed to run git: {e}"), None)
+        })?;
+    if !output.status.success() {
+        return Err(Error::command_execution(
+            "git",
+            args,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            output.status.code(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The tasks whose inputs match one of `changed`, and the tasks depending on
+/// them
+fn affected(candidates: &[Candidate], changed: &[PathBuf]) -> Result<BTreeSet<String>> {
+    let mut affected = BTreeSet::new();
+    for candidate in candidates {
+        let relative: Vec<&Path> = changed
+            .iter()
+            .filter_map(|file| file.strip_prefix(&candidate.dir).ok())
+            .collect();
+        let touched = match &candidate.inputs {
+            None => !relative.is_empty(),
+            Some(inputs) => {
+                let mut touched = false;
+                for input in inputs {
+                    if input_matches(input, &relative)? {
+                        touched = true;
+                        break;
+                    }
+                }
+                touched
+            }
+        };
+        if touched {
+            affected.insert(candidate.name.clone());
+        }
+    }
+
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for candidate in candidates {
+        for dependency in &candidate.dependencies {
+            dependents
+                .entry(dependency.as_str())
+                .or_default()
+                .push(candidate.name.as_str());
+        }
+    }
+    let mut queue: VecDeque<String> = affected.iter().cloned().collect();
+    while let Some(name) = queue.pop_front() {
+        for dependent in dependents.get(name.as_str()).into_iter().flatten() {
+            if affected.insert(dependent.to_string()) {
+                queue.push_back(dependent.to_string());
+            }
+        }
+    }
+    Ok(affected)
+}
+
+/// Whether the input `pattern` matches one of `files`: a path without glob
+/// characters matches the file and everything below the directory it names
+fn input_matches(pattern: &str, files: &[&Path]) -> Result<bool> {
+    let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    if !pattern.contains(['*', '?', '[']) {
+        let path = Path::new(pattern);
+        return Ok(files.iter().any(|file| file.starts_with(path)));
+    }
+    let matcher = Glob::new(pattern)
+        .map_err(|e| Error::configuration(format!("Invalid glob pattern '{pattern}': {e}")))?
+        .compile_matcher();
+    Ok(files.iter().any(|file| matcher.is_match(file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        name: &str,
+        dir: &str,
+        inputs: Option<Vec<&str>>,
+        dependencies: &[&str],
+    ) -> Candidate {
+        Candidate {
+            name: name.to_string(),
+            dir: PathBuf::from(dir),
+            inputs: inputs.map(|inputs| inputs.iter().map(|i| i.to_string()).collect()),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_affected() {
+        let candidates = [
+            candidate(
+                "libs:ui:build",
+                "/repo/libs/ui",
+                Some(vec!["src/**/*.ts", "package.json"]),
+                &[],
+            ),
+            candidate("libs:ui:lint", "/repo/libs/ui", Some(vec!["*.md"]), &[]),
+            candidate(
+                "apps:web:build",
+                "/repo/apps/web",
+                Some(vec!["src"]),
+                &["libs:ui:build"],
+            ),
+            candidate(
+                "apps:web:deploy",
+                "/repo/apps/web",
+                Some(vec![]),
+                &["apps:web:build"],
+            ),
+            candidate("apps:api:test", "/repo/apps/api", None, &[]),
+            candidate("docs:build", "/repo/docs", None, &[]),
+        ];
+        let changed = [
+            PathBuf::from("/repo/libs/ui/src/button/index.ts"),
+            PathBuf::from("/repo/apps/api/main.go"),
+        ];
+
+        let affected = affected(&candidates, &changed).unwrap();
+        assert_eq!(
+            affected.into_iter().collect::<Vec<_>>(),
+            [
+                "apps:api:test",
+                "apps:web:build",
+                "apps:web:deploy",
+                "libs:ui:build"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_name() {
+        let is_task = |name: &str| ["apps:web:npm:build", "libs:ui:build"].contains(&name);
+        assert_eq!(
+            full_name("apps:web", "build", is_task).as_deref(),
+            Some("apps:web:build")
+        );
+        assert_eq!(
+            full_name("apps:web", "libs:ui:build#dist", is_task).as_deref(),
+            Some("libs:ui:build")
+        );
+        assert_eq!(
+            full_name("apps:web", "npm:build", is_task).as_deref(),
+            Some("apps:web:npm:build")
+        );
+        assert_eq!(
+            full_name("apps:api", "npm:build", is_task).as_deref(),
+            Some("npm:build")
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! `cuenv task <task>` through a daemon started with `--tasks`
+//!
+//! The daemon keeps evaluated environments and the hashes of input files
+//! in memory, so a run it serves skips loading the configuration and
+//! hashing unchanged inputs. The command line only forwards plain runs,
+//! and runs the task itself whenever the daemon does not start it.
+
+use super::{explain, infer, list, run_many, RUN_COMMAND};
+use crate::commands::Commands;
+use cuenv_core::{Error, Result, CUENV_CAPABILITIES_VAR, CUENV_ENV_VAR, CUENV_RUN_ID_VAR};
+use cuenv_env::daemon::{self, TaskOutput, TaskRun, TaskRunner};
+use cuenv_task::{
+    Environment, LoadOptions, OutputStream, RunManifest, Runner, TaskGraph, TaskObserver,
+    TaskOutcome,
+};
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Run `command` through the daemon if it is a plain task run the daemon
+/// takes, exiting with the status of the task; `None` to run it in process
+pub fn forward(command: &Commands) -> Option<Result<()>> {
+    let Commands::Task {
+        task_or_group: Some(task),
+        args,
+        environment,
+        capabilities,
+        audit: false,
+        verbose: false,
+        output,
+        trace_output: false,
+        profile: None,
+        summary: None,
+        json: false,
+        table: false,
+        tagged,
+        package,
+        affected: false,
+        since: _,
+    } = command
+    else {
+        return None;
+    };
+    // The daemon prints the output of tasks as they write it, which is what
+    // the default spinner shows too
+    if output != "spinner" || !tagged.is_empty() || !package.is_empty() {
+        return None;
+    }
+
+    let run = TaskRun {
+        dir: std::env::current_dir().ok()?,
+        task: task.clone(),
+        args: args.clone(),
+        environment: environment.clone(),
+        capabilities: capabilities.clone(),
+        env: std::env::vars().collect(),
+    };
+    let (stdout, stderr) = (std::io::stdout(), std::io::stderr());
+    let finished = daemon::run_task(&daemon::socket_path(), run, |line, to_stderr| {
+        let _ = if to_stderr {
+            writeln!(stderr.lock(), "{line}")
+        } else {
+            writeln!(stdout.lock(), "{line}")
+        };
+    })?;
+    match finished {
+        Ok((task, status)) => super::profile::exit(&task, status),
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// Runs the tasks the daemon is asked for, as `cuenv task` would
+pub struct DaemonTasks;
+
+impl TaskRunner for DaemonTasks {
+    fn run(
+        &self,
+        run: TaskRun,
+        output: TaskOutput,
+    ) -> Pin<Box<dyn Future<Output = Result<i32>> + Send + '_>> {
+        Box::pin(async move {
+            let started_at = chrono::Utc::now();
+            let mut options = LoadOptions::default();
+            // The variables are the client's, not those of the daemon
+            if let Some(name) = run
+                .environment
+                .or_else(|| run.env.get(CUENV_ENV_VAR).cloned())
+            {
+                options = options.environment(name);
+            }
+            let mut capabilities = run.capabilities;
+            if capabilities.is_empty() {
+                if let Some(env_caps) = run.env.get(CUENV_CAPABILITIES_VAR) {
+                    capabilities = env_caps
+                        .split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                }
+            }
+            for capability in capabilities {
+                options = options.capability(capability);
+            }
+            // The client's run id, which its variables carry
+            let run_id = run
+                .env
+                .get(CUENV_RUN_ID_VAR)
+                .cloned()
+                .unwrap_or_else(|| cuenv_utils::tracing::run_id().to_string());
+            options = options.base_env(run.env);
+
+            let dir = run.dir.clone();
+            let environment = Environment::load_with_options(&dir, options).await?;
+            let (task, args) = resolve(&environment.task_names(), run.task, run.args)?;
+            let graph = TaskGraph::plan(environment, &[&task]).await?;
+            output.started(&task);
+            let outcome = Runner::new()
+                .with_args(args)
+                .with_observer(Arc::new(ClientObserver(output.clone())))
+                .execute(graph)
+                .await?;
+            if let Some(summary) = outcome.summary() {
+                let manifest = RunManifest::new(
+                    run_id,
+                    &task,
+                    dir,
+                    started_at,
+                    Some(outcome.exit_code()),
+                    summary.clone(),
+                );
+                if let Err(e) = manifest.write() {
+                    output.line(
+                        &format!("Could not write the manifest of the run: {e}"),
+                        true,
+                    );
+                }
+            }
+            Ok(outcome.exit_code())
+        })
+    }
+}
+
+/// The task `cuenv task <task> <args>` runs and its arguments, among the
+/// tasks `names`; the commands spelled like tasks and groups are left to the
+/// command line
+fn resolve(names: &[String], task: String, args: Vec<String>) -> Result<(String, Vec<String>)> {
+    let is_task = |name: &str| names.iter().any(|task| task == name);
+    let (task, args) = match args.split_first() {
+        Some((first, rest)) if task == RUN_COMMAND && !is_task(&task) => {
+            (first.clone(), rest.to_vec())
+        }
+        _ => (task, args),
+    };
+    if is_task(&task) {
+        return Ok((task, args));
+    }
+    if let Some((first, rest)) = args.split_first() {
+        let subtask = format!("{task}.{first}");
+        if is_task(&subtask) {
+            return Ok((subtask, rest.to_vec()));
+        }
+    }
+    let command = [
+        RUN_COMMAND,
+        list::COMMAND,
+        infer::COMMAND,
+        explain::COMMAND,
+        run_many::COMMAND,
+    ]
+    .contains(&task.as_str());
+    Err(Error::usage(if command {
+        format!("'{task}' is left to the command line")
+    } else {
+        format!("Task '{task}' not found")
+    }))
+}
+
+/// Sends the output of tasks to the client, and why a task failed
+struct ClientObserver(TaskOutput);
+
+impl TaskObserver for ClientObserver {
+    fn on_output_line(&self, _task: &str, stream: OutputStream, line: &str) {
+        self.0.line(line, stream == OutputStream::Stderr);
+    }
+
+    fn on_task_finish(&self, task: &str, outcome: &TaskOutcome) {
+        if let Some(error) = &outcome.error {
+            self.0.line(&format!("✗ {task}: {error}"), true);
+        } else if !outcome.success() && !outcome.owners.is_empty() {
+            self.0.line(
+                &format!("✗ {task} failed; owners: {}", outcome.owners.join(", ")),
+                true,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_like_the_command_line() {
+        let names: Vec<String> = ["build", "fmt.check"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let resolve = |task: &str, args: &[&str]| {
+            resolve(
+                &names,
+                task.to_string(),
+                args.iter().map(|arg| arg.to_string()).collect(),
+            )
+        };
+
+        assert_eq!(
+            resolve("build", &["-v"]).unwrap(),
+            ("build".to_string(), vec!["-v".to_string()])
+        );
+        assert_eq!(resolve("run", &["build"]).unwrap().0, "build");
+        assert_eq!(
+            resolve("fmt", &["check", "src"]).unwrap(),
+            ("fmt.check".to_string(), vec!["src".to_string()])
+        );
+        assert!(resolve("list", &[]).is_err());
+        assert!(resolve("deploy", &[]).is_err());
+    }
+}
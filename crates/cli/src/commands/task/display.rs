@@ -1,5 +1,5 @@
-use crossterm::style::Stylize;
 use cuenv_config::{TaskGroupMode, TaskNode};
+use cuenv_core::style::{self, Painter, Role};
 use std::collections::{BTreeMap, HashMap};
 
 /// Box drawing characters for tree visualization
@@ -8,16 +8,32 @@ const TREE_LAST: &str = "└── ";
 const TREE_PIPE: &str = "│   ";
 const TREE_EMPTY: &str = "    ";
 
-/// Format the execution mode as a colored badge
-pub fn format_mode_badge(mode: &TaskGroupMode) -> String {
+/// The painter of the theme, coloring only with `use_color`
+fn painter(use_color: bool) -> Painter {
+    Painter::new(use_color, style::theme())
+}
+
+/// The role each execution mode is drawn in
+fn mode_role(mode: &TaskGroupMode) -> Role {
     match mode {
-        TaskGroupMode::Workflow => "[WORKFLOW]".cyan().to_string(),
-        TaskGroupMode::Sequential => "[SEQUENTIAL]".yellow().to_string(),
-        TaskGroupMode::Parallel => "[PARALLEL]".green().to_string(),
-        TaskGroupMode::Group => "[GROUP]".dark_grey().to_string(),
+        TaskGroupMode::Workflow => Role::Accent,
+        TaskGroupMode::Sequential => Role::Warning,
+        TaskGroupMode::Parallel => Role::Success,
+        TaskGroupMode::Group => Role::Muted,
     }
 }
 
+/// Format the execution mode as a colored badge
+pub fn format_mode_badge(mode: &TaskGroupMode) -> String {
+    let badge = match mode {
+        TaskGroupMode::Workflow => "[WORKFLOW]",
+        TaskGroupMode::Sequential => "[SEQUENTIAL]",
+        TaskGroupMode::Parallel => "[PARALLEL]",
+        TaskGroupMode::Group => "[GROUP]",
+    };
+    painter(true).paint(mode_role(mode), badge)
+}
+
 /// Count tasks recursively in a node
 pub fn count_tasks(node: &TaskNode) -> usize {
     match node {
@@ -33,22 +49,15 @@ pub fn display_task_tree(nodes: &HashMap<String, TaskNode>, verbose: bool, use_c
 
     // Simple, clean header
     println!();
-    if use_color {
-        println!("{}", "Tasks".bold());
-    } else {
-        println!("Tasks");
-    }
+    let painter = painter(use_color);
+    println!("{}", painter.paint(Role::Heading, "Tasks"));
 
     // Display all tasks in a unified format
     for (name, node) in sorted {
         match node {
             TaskNode::Task(_config) => {
                 // Display single task with a simple bullet
-                if use_color {
-                    println!("  {} {}", "•".dark_grey(), name);
-                } else {
-                    println!("  • {name}");
-                }
+                println!("  {} {name}", painter.paint(Role::Muted, "•"));
             }
             TaskNode::Group {
                 description: _,
@@ -74,13 +83,15 @@ pub fn display_task_tree(nodes: &HashMap<String, TaskNode>, verbose: bool, use_c
     if verbose {
         // Full footer with hints
         if use_color {
-            println!("{}", "─".repeat(40).dark_grey());
+            println!("{}", painter.paint(Role::Muted, "─".repeat(40)));
             println!("Usage: cuenv task <name> [args...]");
             println!();
             println!(
                 "{}",
-                "Task groups can be executed directly or you can run specific subtasks."
-                    .dark_grey()
+                painter.paint(
+                    Role::Muted,
+                    "Task groups can be executed directly or you can run specific subtasks."
+                )
             );
         } else {
             println!("{}", "-".repeat(40));
@@ -91,17 +102,20 @@ pub fn display_task_tree(nodes: &HashMap<String, TaskNode>, verbose: bool, use_c
     } else {
         // Minimal footer with icon key
         if use_color {
-            println!("{}", "─".repeat(40).dark_grey());
+            println!("{}", painter.paint(Role::Muted, "─".repeat(40)));
             println!(
                 "{} workflow  {} sequential  {} parallel  {} group  {} single",
-                "⚡".cyan(),
-                "⇢".yellow(),
-                "⇉".green(),
-                "◉".white(),
-                "•".dark_grey()
+                painter.paint(Role::Accent, "⚡"),
+                painter.paint(Role::Warning, "⇢"),
+                painter.paint(Role::Success, "⇉"),
+                painter.paint(Role::Heading, "◉"),
+                painter.paint(Role::Muted, "•")
             );
             println!();
-            println!("Run: cuenv task <name>  •  Use {} for details", "-v".cyan());
+            println!(
+                "Run: cuenv task <name>  •  Use {} for details",
+                painter.paint(Role::Accent, "-v")
+            );
         } else {
             println!("{}", "-".repeat(40));
             println!("Run: cuenv task <name>  •  Use -v for details");
@@ -139,18 +153,19 @@ fn display_group_compact(
 
     if use_color {
         // Distinct icons for each mode
-        let (symbol, color) = match mode {
-            TaskGroupMode::Workflow => ("⚡", name.cyan()),
-            TaskGroupMode::Sequential => ("⇢", name.yellow()),
-            TaskGroupMode::Parallel => ("⇉", name.green()),
-            TaskGroupMode::Group => ("◉", name.white()),
+        let (symbol, role) = match mode {
+            TaskGroupMode::Workflow => ("⚡", Role::Accent),
+            TaskGroupMode::Sequential => ("⇢", Role::Warning),
+            TaskGroupMode::Parallel => ("⇉", Role::Success),
+            TaskGroupMode::Group => ("◉", Role::Heading),
         };
 
+        let painter = painter(use_color);
         println!(
             "  {} {} {}",
             symbol,
-            color.bold(),
-            format!("[{task_list}]").dark_grey()
+            painter.bold(role, name),
+            painter.paint(Role::Muted, format!("[{task_list}]"))
         );
     } else {
         println!("  {name} [{task_list}]");
@@ -186,12 +201,13 @@ fn display_group(
 
     // Display group header
     let group_line = if use_color {
+        let painter = painter(use_color);
         format!(
             "{}{} {} {}",
             prefix,
-            name.bold().cyan(),
+            painter.bold(Role::Accent, name),
             mode_badge,
-            format!("({task_count} tasks)").dark_grey()
+            painter.paint(Role::Muted, format!("({task_count} tasks)"))
         )
     } else {
         format!("{prefix}{name} {mode_badge} ({task_count} tasks)")
@@ -202,7 +218,11 @@ fn display_group(
     if verbose {
         if let Some(desc) = description {
             let desc_line = if use_color {
-                format!("{}  {}", prefix, desc.dark_grey())
+                format!(
+                    "{}  {}",
+                    prefix,
+                    painter(use_color).paint(Role::Muted, desc)
+                )
             } else {
                 format!("{prefix}  {desc}")
             };
@@ -262,19 +282,20 @@ fn format_task_line(
     verbose: bool,
     use_color: bool,
 ) -> String {
-    if verbose && description.is_some() {
-        if use_color {
-            format!(
-                "{}{} {}",
-                connector,
-                name,
-                format!("– {}", description.unwrap()).dark_grey()
-            )
-        } else {
-            format!("{}{} – {}", connector, name, description.unwrap())
+    match description {
+        Some(description) if verbose => {
+            if use_color {
+                format!(
+                    "{}{} {}",
+                    connector,
+                    name,
+                    painter(use_color).paint(Role::Muted, format!("– {description}"))
+                )
+            } else {
+                format!("{connector}{name} – {description}")
+            }
         }
-    } else {
-        format!("{connector}{name}")
+        _ => format!("{connector}{name}"),
     }
 }
 
@@ -362,14 +383,18 @@ pub fn display_group_contents(
 
     // Header
     if use_color {
-        println!("{} {}", group_name.bold().cyan(), mode_badge);
+        println!(
+            "{} {}",
+            painter(use_color).bold(Role::Accent, group_name),
+            mode_badge
+        );
     } else {
         println!("{group_name} {mode_badge}");
     }
 
     if let Some(desc) = description {
         if use_color {
-            println!("  {}", desc.dark_grey());
+            println!("  {}", painter(use_color).paint(Role::Muted, desc));
         } else {
             println!("  {desc}");
         }
@@ -398,7 +423,7 @@ pub fn display_group_contents(
     };
 
     if use_color {
-        println!("{}", action_hint.dark_grey());
+        println!("{}", painter(use_color).paint(Role::Muted, action_hint));
     } else {
         println!("{action_hint}");
     }
@@ -416,6 +441,8 @@ mod tests {
             command: Some("echo test".to_string()),
             script: None,
             dependencies: None,
+            order_only_dependencies: None,
+            weak_dependencies: None,
             working_dir: None,
             shell: None,
             inputs: None,
@@ -425,6 +452,10 @@ mod tests {
             cache_key: None,
             cache_env: None,
             timeout: None,
+            max_output_bytes: None,
+            tags: None,
+            owners: None,
+            service: None,
         }))
     }
 
@@ -444,19 +475,19 @@ mod tests {
     fn test_format_mode_badge() {
         assert_eq!(
             format_mode_badge(&TaskGroupMode::Workflow),
-            "[WORKFLOW]".cyan().to_string()
+            "\x1b[36m[WORKFLOW]\x1b[0m"
         );
         assert_eq!(
             format_mode_badge(&TaskGroupMode::Sequential),
-            "[SEQUENTIAL]".yellow().to_string()
+            "\x1b[33m[SEQUENTIAL]\x1b[0m"
         );
         assert_eq!(
             format_mode_badge(&TaskGroupMode::Parallel),
-            "[PARALLEL]".green().to_string()
+            "\x1b[32m[PARALLEL]\x1b[0m"
         );
         assert_eq!(
             format_mode_badge(&TaskGroupMode::Group),
-            "[GROUP]".dark_grey().to_string()
+            "\x1b[90m[GROUP]\x1b[0m"
         );
     }
 
@@ -0,0 +1,222 @@
+//! `cuenv task explain <task>`, a task as it would run
+//!
+//! Shows the task after CUE merged it, its expanded command, the variables
+//! it gets, the files its globs match, what its cache key is computed from
+//! and every task that runs before it. Nothing is run.
+
+use cuenv_core::{masking, Error, Result};
+use cuenv_task::{ExpandedGlob, TaskExecutor, TaskExplanation};
+use serde_json::Value;
+use std::fmt::Write;
+
+/// The word selecting the explanation instead of a task to run
+pub const COMMAND: &str = "explain";
+
+/// Print how `task_name` resolves, as JSON with `json`
+pub async fn execute(
+    environment: Option<String>,
+    capabilities: Vec<String>,
+    task_name: String,
+    json: bool,
+) -> Result<()> {
+    let (current_dir, env_manager) =
+        super::load_task_environment(environment, capabilities).await?;
+    if env_manager.get_task(&task_name).is_none() {
+        return Err(Error::usage(format!(
+            "Task '{task_name}' not found\nRun 'cuenv task' to see available tasks"
+        )));
+    }
+
+    let executor = TaskExecutor::new(env_manager, current_dir).await?;
+    let explanation = executor.explain_task(&task_name).await?;
+    let output = if json {
+        let json = serde_json::to_string_pretty(&explanation).map_err(|e| Error::Json {
+            message: "failed to encode task explanation".to_string(),
+            source: e,
+        })?;
+        format!("{json}\n")
+    } else {
+        render(&explanation)
+    };
+    // Variables and expanded commands can hold secrets
+    print!("{}", masking::mask(&output));
+    Ok(())
+}
+
+/// The explanation as text
+fn render(task: &TaskExplanation) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Task: {}", task.name);
+    if let Some(description) = &task.description {
+        let _ = writeln!(out, "  {description}");
+    }
+
+    let _ = writeln!(
+        out,
+        "\nShell:             {} {}",
+        task.shell,
+        task.shell_args.join(" ")
+    );
+    let _ = writeln!(
+        out,
+        "Working directory: {}",
+        task.working_directory.display()
+    );
+    let _ = writeln!(out, "Timeout:           {}s", task.timeout_secs);
+    if let Some(command) = &task.command {
+        let _ = writeln!(out, "\nCommand:\n{}", indent(command));
+    }
+    if let Some(script) = &task.script {
+        let _ = writeln!(out, "\nScript:\n{}", indent(script));
+    }
+
+    section(
+        &mut out,
+        "Environment",
+        task.env
+            .iter()
+            .map(|(name, value)| format!("{name}={value}")),
+    );
+    globs(&mut out, "Inputs", &task.inputs);
+    globs(&mut out, "Outputs", &task.outputs);
+    section(
+        &mut out,
+        "Artifacts",
+        task.artifacts
+            .iter()
+            .map(|(name, path)| format!("{name}: {}", path.display())),
+    );
+    section(&mut out, "Dependencies", task.dependencies.iter().cloned());
+    section(
+        &mut out,
+        "Runs after",
+        task.dependency_closure.iter().cloned(),
+    );
+
+    let cache = &task.cache;
+    let _ = writeln!(
+        out,
+        "\nCache: {}",
+        if cache.enabled { "enabled" } else { "disabled" }
+    );
+    let _ = writeln!(out, "  Key:         {}", cache.digest);
+    if let Some(key) = &cache.custom_key {
+        let _ = writeln!(out, "  Custom key:  {key}");
+    }
+    let _ = writeln!(out, "  Config hash: {}", cache.config_hash);
+    for (name, value) in &cache.env {
+        let _ = writeln!(out, "  Variable:    {name}={value}");
+    }
+    for (path, hash) in &cache.input_files {
+        let _ = writeln!(out, "  Input:       {path} {hash}");
+    }
+
+    // The declared task, without the fields it leaves unset
+    if let Ok(Value::Object(config)) = serde_json::to_value(&task.config) {
+        let config: serde_json::Map<String, Value> = config
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .collect();
+        if let Ok(config) = serde_json::to_string_pretty(&config) {
+            let _ = writeln!(out, "\nDeclared:\n{}", indent(&config));
+        }
+    }
+    out
+}
+
+/// A titled list, left out when empty
+fn section(out: &mut String, title: &str, lines: impl Iterator<Item = String>) {
+    let lines: Vec<String> = lines.collect();
+    if lines.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "\n{title}:");
+    for line in lines {
+        let _ = writeln!(out, "  {line}");
+    }
+}
+
+/// Globs with the files each matches
+fn globs(out: &mut String, title: &str, globs: &[ExpandedGlob]) {
+    if globs.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "\n{title}:");
+    for glob in globs {
+        let _ = writeln!(out, "  {}", glob.pattern);
+        if glob.files.is_empty() {
+            let _ = writeln!(out, "    (no files)");
+        }
+        for file in &glob.files {
+            let _ = writeln!(out, "    {}", file.display());
+        }
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("  {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_config::TaskConfig;
+    use cuenv_task::CacheKeyComponents;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_render() {
+        let explanation = TaskExplanation {
+            name: "test".to_string(),
+            description: Some("Run the tests".to_string()),
+            config: TaskConfig {
+                command: Some("cargo test ${PROFILE}".to_string()),
+                dependencies: Some(vec!["build".to_string()]),
+                ..Default::default()
+            },
+            shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
+            command: Some("cargo test release".to_string()),
+            script: None,
+            working_directory: PathBuf::from("/project"),
+            timeout_secs: 3600,
+            env: BTreeMap::from([("PROFILE".to_string(), "release".to_string())]),
+            inputs: vec![ExpandedGlob {
+                pattern: "src/*.rs".to_string(),
+                files: vec![PathBuf::from("src/lib.rs")],
+            }],
+            outputs: vec![ExpandedGlob {
+                pattern: "report.xml".to_string(),
+                files: Vec::new(),
+            }],
+            artifacts: BTreeMap::new(),
+            cache: CacheKeyComponents {
+                enabled: true,
+                custom_key: None,
+                digest: "d1g3st".to_string(),
+                config_hash: "c0nf1g".to_string(),
+                env: BTreeMap::new(),
+                input_files: BTreeMap::from([("src/lib.rs".to_string(), "h4sh".to_string())]),
+            },
+            dependencies: vec!["build".to_string()],
+            dependency_closure: vec!["fetch".to_string(), "build".to_string()],
+        };
+
+        let text = render(&explanation);
+        assert!(text.starts_with("Task: test\n  Run the tests\n"));
+        assert!(text.contains("\nCommand:\n  cargo test release\n"));
+        assert!(text.contains("\nEnvironment:\n  PROFILE=release\n"));
+        assert!(text.contains("\nInputs:\n  src/*.rs\n    src/lib.rs\n"));
+        assert!(text.contains("\nOutputs:\n  report.xml\n    (no files)\n"));
+        assert!(text.contains("\nRuns after:\n  fetch\n  build\n"));
+        assert!(text.contains("\nCache: enabled\n  Key:         d1g3st\n"));
+        assert!(text.contains("  Input:       src/lib.rs h4sh\n"));
+        assert!(text.contains("\"command\": \"cargo test ${PROFILE}\""));
+        assert!(!text.contains("Artifacts"));
+        assert!(!text.contains("null"));
+    }
+}
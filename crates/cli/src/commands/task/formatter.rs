@@ -8,6 +8,7 @@ use cuenv_tui::app::TuiApp;
 use cuenv_tui::event_bus::EventBus;
 use cuenv_tui::events::{TaskRegistry, TaskState};
 use cuenv_tui::spinner::SpinnerFormatter;
+use cuenv_utils::tracing::{log_format, message, task_message, Level, LogFormat};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::time::{interval, Duration};
@@ -28,11 +29,20 @@ pub async fn execute_with_formatter(
     let shutdown_tx_clone = shutdown_tx.clone();
     tokio::spawn(async move {
         if let Ok(()) = tokio::signal::ctrl_c().await {
-            eprintln!("\n⚠️  Received interrupt signal, stopping tasks...");
+            message(
+                Level::WARN,
+                "\n⚠️  Received interrupt signal, stopping tasks...",
+            );
             let _ = shutdown_tx_clone.send(()).await;
         }
     });
 
+    // Spinners and the TUI draw on the terminal, where JSON logs go
+    let output_format = match log_format() {
+        LogFormat::Json => "simple",
+        LogFormat::Text => output_format,
+    };
+
     match output_format {
         "spinner" => execute_with_spinner(executor, task_name, args, audit, &mut shutdown_rx).await,
         "simple" | "tree" => {
@@ -60,7 +70,10 @@ pub async fn execute_with_formatter(
         }
         _ => {
             // Fall back to simple output for unknown formats
-            eprintln!("Unknown output format '{output_format}', using simple output");
+            message(
+                Level::WARN,
+                &format!("Unknown output format '{output_format}', using simple output"),
+            );
             execute_with_simple(
                 executor,
                 task_name,
@@ -365,39 +378,47 @@ async fn execute_with_simple(
     }
 
     // For simple output, just use the standard executor with some status messages
-    println!("Executing task: {task_name}");
+    task_message(
+        Level::INFO,
+        task_name,
+        &format!("Executing task: {task_name}"),
+    );
     if !args.is_empty() {
-        println!("Arguments: {args:?}");
+        task_message(Level::INFO, task_name, &format!("Arguments: {args:?}"));
     }
 
     // Execute with cancellation support
     let result = tokio::select! {
         result = async {
             if audit {
-                println!("Running in audit mode...");
+                task_message(Level::INFO, task_name, "Running in audit mode...");
                 executor.execute_task_with_audit(task_name, args).await
             } else {
                 executor.execute_task(task_name, args).await
             }
         } => result,
         _ = shutdown_rx.recv() => {
-            eprintln!("\n⚠️  Task cancelled by user");
+            task_message(Level::WARN, task_name, "\n⚠️  Task cancelled by user");
             Ok(130) // Standard exit code for SIGINT
         }
     };
 
     match result {
         Ok(0) => {
-            println!("✓ Task completed successfully");
+            task_message(Level::INFO, task_name, "✓ Task completed successfully");
         }
         Ok(130) => {
             // Don't print extra message for cancellation
         }
         Ok(code) => {
-            eprintln!("✗ Task failed with exit code: {code}");
+            task_message(
+                Level::ERROR,
+                task_name,
+                &format!("✗ Task failed with exit code: {code}"),
+            );
         }
         Err(ref e) => {
-            eprintln!("✗ Task failed: {e}");
+            task_message(Level::ERROR, task_name, &format!("✗ Task failed: {e}"));
         }
     }
 
@@ -419,11 +440,20 @@ pub async fn execute_tasks_with_formatter(
     let shutdown_tx_clone = shutdown_tx.clone();
     tokio::spawn(async move {
         if let Ok(()) = tokio::signal::ctrl_c().await {
-            eprintln!("\n⚠️  Received interrupt signal, stopping tasks...");
+            message(
+                Level::WARN,
+                "\n⚠️  Received interrupt signal, stopping tasks...",
+            );
             let _ = shutdown_tx_clone.send(()).await;
         }
     });
 
+    // Spinners and the TUI draw on the terminal, where JSON logs go
+    let output_format = match log_format() {
+        LogFormat::Json => "simple",
+        LogFormat::Text => output_format,
+    };
+
     match output_format {
         "spinner" => {
             execute_tasks_spinner(executor, task_names, args, audit, &mut shutdown_rx).await
@@ -722,20 +752,26 @@ async fn execute_tasks_simple(
         eprintln!("Note: Chrome trace output is not yet implemented");
     }
 
-    println!("Executing {} tasks", task_names.len());
+    message(
+        Level::INFO,
+        &format!("Executing {} tasks", task_names.len()),
+    );
 
     // Execute with cancellation support
     tokio::select! {
         result = executor.execute_tasks_with_dependencies(task_names, &[], audit) => {
             match result {
-                Ok(0) => println!("✓ All tasks completed successfully"),
-                Ok(code) => eprintln!("✗ Tasks failed with exit code: {code}"),
-                Err(ref e) => eprintln!("✗ Tasks failed: {e}"),
+                Ok(0) => message(Level::INFO, "✓ All tasks completed successfully"),
+                Ok(code) => message(
+                    Level::ERROR,
+                    &format!("✗ Tasks failed with exit code: {code}"),
+                ),
+                Err(ref e) => message(Level::ERROR, &format!("✗ Tasks failed: {e}")),
             }
             result
         },
         _ = shutdown_rx.recv() => {
-            eprintln!("\n⚠️  Tasks cancelled by user");
+            message(Level::WARN, "\n⚠️  Tasks cancelled by user");
             Ok(130)
         }
     }
@@ -0,0 +1,79 @@
+//! `cuenv task infer <task>`, inputs and outputs from a traced run
+//!
+//! The task runs under strace after its dependencies, and the files it read
+//! and wrote below its working directory become `inputs` and `outputs`
+//! globs. They are printed as CUE fields to paste into the task.
+
+use cuenv_core::{Error, Result};
+use cuenv_task::{InferredTaskIo, TaskExecutor};
+use cuenv_utils::tracing::{message, task_message, Level};
+
+/// The word selecting inference instead of a task to run
+pub const COMMAND: &str = "infer";
+
+/// Run `task_name` under tracing and print the inputs and outputs it used
+pub async fn execute(
+    environment: Option<String>,
+    capabilities: Vec<String>,
+    task_name: String,
+    args: Vec<String>,
+) -> Result<()> {
+    let (current_dir, env_manager) =
+        super::load_task_environment(environment, capabilities).await?;
+    if env_manager.get_task(&task_name).is_none() {
+        return Err(Error::usage(format!(
+            "Task '{task_name}' not found\nRun 'cuenv task' to see available tasks"
+        )));
+    }
+
+    let executor = TaskExecutor::new(env_manager, current_dir).await?;
+    let inferred = executor.infer_task_io(&task_name, &args).await?;
+    if inferred.exit_code != 0 {
+        task_message(
+            Level::WARN,
+            &task_name,
+            &format!(
+                "Warning: task '{task_name}' exited with status {}, so it may not have used all its inputs and outputs",
+                inferred.exit_code
+            ),
+        );
+    }
+
+    print!("{}", cue_fields(&inferred));
+    Ok(())
+}
+
+/// The `inputs` and `outputs` fields, leaving out empty ones
+fn cue_fields(inferred: &InferredTaskIo) -> String {
+    let mut fields = String::new();
+    for (name, globs) in [("inputs", &inferred.inputs), ("outputs", &inferred.outputs)] {
+        if globs.is_empty() {
+            continue;
+        }
+        fields.push_str(&format!("{name}: [\n"));
+        for glob in globs {
+            // JSON strings are CUE strings
+            fields.push_str(&format!("\t{},\n", serde_json::Value::from(glob.as_str())));
+        }
+        fields.push_str("]\n");
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cue_fields() {
+        let inferred = InferredTaskIo {
+            exit_code: 0,
+            inputs: vec!["Cargo.toml".to_string(), "src/**/*.rs".to_string()],
+            outputs: Vec::new(),
+        };
+        assert_eq!(
+            cue_fields(&inferred),
+            "inputs: [\n\t\"Cargo.toml\",\n\t\"src/**/*.rs\",\n]\n"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! `cuenv task list [pattern]`, the tasks with their details
+//!
+//! Each task is listed with its description, tags, owners, dependencies and
+//! whether its results are cached, as a tree of its groups, a table, or JSON for
+//! tooling. `--tagged` keeps the tasks having every given tag, and a pattern
+//! the tasks whose full name matches the glob, such as `ci.*`.
+
+use cuenv_cache::config::CacheConfigLoader;
+use cuenv_config::Config;
+use cuenv_core::style::{self, Painter, Role};
+use cuenv_core::{Error, Result};
+use cuenv_task::TaskInfo;
+use globset::Glob;
+use std::collections::BTreeMap;
+
+/// The word selecting the detailed listing instead of a task to run
+pub const COMMAND: &str = "list";
+
+/// How tasks are listed
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub json: bool,
+    pub table: bool,
+    /// Tags a task must all have to be listed
+    pub tags: Vec<String>,
+}
+
+impl ListOptions {
+    /// Whether a plain `cuenv task` asks for the detailed listing
+    pub fn is_detailed(&self) -> bool {
+        self.json || self.table || !self.tags.is_empty()
+    }
+}
+
+/// List the tasks of `config` matching `options` and the glob `pattern`
+pub fn execute(config: &Config, options: &ListOptions, pattern: Option<&str>) -> Result<()> {
+    let matcher = pattern
+        .map(|pattern| {
+            Glob::new(pattern)
+                .map(|glob| glob.compile_matcher())
+                .map_err(|e| Error::configuration(format!("Invalid task pattern '{pattern}': {e}")))
+        })
+        .transpose()?;
+
+    let cache = CacheConfigLoader::load()?.global;
+    let tasks: Vec<TaskInfo> = TaskInfo::from_tasks(config.get_tasks(), &cache)
+        .into_iter()
+        .filter(|task| options.tags.iter().all(|tag| task.tags.contains(tag)))
+        .filter(|task| matcher.as_ref().is_none_or(|m| m.is_match(&task.name)))
+        .collect();
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&tasks).map_err(|e| Error::Json {
+            message: "failed to encode tasks".to_string(),
+            source: e,
+        })?;
+        println!("{json}");
+    } else if tasks.is_empty() {
+        println!("No tasks match");
+    } else if options.table {
+        print!("{}", table(&tasks));
+    } else {
+        print!("{}", tree(&tasks, &style::stdout()));
+    }
+    Ok(())
+}
+
+/// The tasks as a table with a column per detail
+fn table(tasks: &[TaskInfo]) -> String {
+    let rows: Vec<[String; 6]> = tasks
+        .iter()
+        .map(|task| {
+            [
+                task.name.clone(),
+                task.description.clone().unwrap_or_default(),
+                task.tags.join(", "),
+                task.owners.join(", "),
+                task.dependencies.join(", "),
+                if task.cached { "yes" } else { "no" }.to_string(),
+            ]
+        })
+        .collect();
+    let header = [
+        "Task",
+        "Description",
+        "Tags",
+        "Owners",
+        "Depends on",
+        "Cached",
+    ];
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: [&str; 6]| {
+        let mut line = String::new();
+        for (index, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            if index == cells.len() - 1 {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{cell:<width$}  "));
+            }
+        }
+        format!("{}\n", line.trim_end())
+    };
+
+    let mut output = line(header);
+    for row in &rows {
+        output.push_str(&line(row.each_ref().map(String::as_str)));
+    }
+    output
+}
+
+/// A group of the tree, or a task when it has `task`
+#[derive(Default)]
+struct Branch<'a> {
+    task: Option<&'a TaskInfo>,
+    children: BTreeMap<&'a str, Branch<'a>>,
+}
+
+/// The tasks as a tree of their groups
+fn tree(tasks: &[TaskInfo], painter: &Painter) -> String {
+    let mut root = Branch::default();
+    for task in tasks {
+        let branch = task.name.split('.').fold(&mut root, |branch, segment| {
+            branch.children.entry(segment).or_default()
+        });
+        branch.task = Some(task);
+    }
+
+    let mut output = String::new();
+    render(&root, "", true, painter, &mut output);
+    output
+}
+
+fn render(branch: &Branch, prefix: &str, top: bool, painter: &Painter, output: &mut String) {
+    let count = branch.children.len();
+    for (index, (name, child)) in branch.children.iter().enumerate() {
+        // Top-level entries have no connectors
+        let (connector, indent) = if top {
+            ("", " ")
+        } else if index + 1 == count {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        output.push_str(&format!("{prefix}{connector}{name}"));
+        if let Some(task) = child.task {
+            output.push_str(&details(task, painter));
+        }
+        output.push('\n');
+        render(child, &format!("{prefix}{indent}"), false, painter, output);
+    }
+}
+
+/// The description, tags, owners, dependencies and cache status after a
+/// task's name
+fn details(task: &TaskInfo, painter: &Painter) -> String {
+    let mut parts = Vec::new();
+    if let Some(description) = &task.description {
+        parts.push(format!("- {description}"));
+    }
+    if !task.tags.is_empty() {
+        let tags = format!("[{}]", task.tags.join(", "));
+        parts.push(painter.paint(Role::Accent, tags));
+    }
+    if !task.owners.is_empty() {
+        parts.push(format!("owned by {}", task.owners.join(", ")));
+    }
+    if !task.dependencies.is_empty() {
+        let dependencies = format!("← {}", task.dependencies.join(", "));
+        parts.push(painter.paint(Role::Muted, dependencies));
+    }
+    if !task.cached {
+        parts.push(painter.paint(Role::Warning, "(not cached)"));
+    }
+
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("  {}", parts.join("  "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(name: &str, tags: &[&str], dependencies: &[&str], cached: bool) -> TaskInfo {
+        TaskInfo {
+            name: name.to_string(),
+            description: Some(format!("Run {name}")),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            owners: vec![],
+            dependencies: dependencies.iter().map(|dep| dep.to_string()).collect(),
+            cached,
+        }
+    }
+
+    fn tasks() -> Vec<TaskInfo> {
+        vec![
+            task("build", &["ci"], &[], true),
+            task("ci.lint", &["ci"], &[], true),
+            task("ci.test", &[], &["build"], true),
+            TaskInfo {
+                owners: vec!["team-platform".to_string()],
+                ..task("deploy", &[], &["build"], false)
+            },
+        ]
+    }
+
+    #[test]
+    fn test_tree() {
+        assert_eq!(
+            tree(&tasks(), &Painter::plain()),
+            "build  - Run build  [ci]\n\
+             ci\n \
+             ├── lint  - Run ci.lint  [ci]\n \
+             └── test  - Run ci.test  ← build\n\
+             deploy  - Run deploy  owned by team-platform  ← build  (not cached)\n"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            table(&tasks()[2..]),
+            "Task     Description  Tags  Owners         Depends on  Cached\n\
+             ci.test  Run ci.test                       build       yes\n\
+             deploy   Run deploy         team-platform  build       no\n"
+        );
+    }
+}
@@ -1,31 +1,99 @@
+mod affected;
+#[cfg(unix)]
+pub mod daemon;
 mod display;
+mod explain;
 mod formatter;
+mod infer;
+mod list;
+mod packages;
+mod profile;
+mod run_many;
+mod suggest;
+mod summary;
 
 use clap::Subcommand;
 use cuenv_config::{Config, TaskGroupMode, TaskNode};
-use cuenv_core::{Result, CUENV_CAPABILITIES_VAR, CUENV_ENV_VAR};
+use cuenv_core::{Error, Result, CUENV_CAPABILITIES_VAR, CUENV_ENV_VAR};
 use cuenv_env::manager::environment::SupervisorMode;
 use cuenv_env::EnvManager;
-use cuenv_task::TaskExecutor;
+use cuenv_hooks::notify::RunOutcome;
+use cuenv_task::{RunManifest, TaskExecutor};
+use cuenv_utils::tracing::{message, task_message, Level};
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 
+pub use self::affected::DEFAULT_SINCE;
 use self::display::{display_group_contents, display_task_tree};
+pub use self::list::ListOptions;
+pub use self::packages::execute as execute_in_packages;
+
+/// The word running the task named after it, whatever its name
+pub const RUN_COMMAND: &str = "run";
+
+/// How `cuenv task` runs the tasks it is given
+#[derive(Debug, Clone, Default)]
+pub struct TaskRunOptions {
+    /// Environment to load
+    pub environment: Option<String>,
+    /// Capabilities to enable
+    pub capabilities: Vec<String>,
+    /// Run in audit mode
+    pub audit: bool,
+    /// Output format of the run (tui, simple or spinner)
+    pub output_format: String,
+    /// Generate a Chrome trace output file
+    pub trace_output: bool,
+    /// File to record the run in as a Chrome trace
+    pub profile: Option<PathBuf>,
+    /// Format of the summary printed after the run
+    pub summary: Option<String>,
+    /// Run only the tasks affected by the changes since this revision
+    pub affected_since: Option<String>,
+}
 
 /// Execute the simplified task command
-#[allow(clippy::too_many_arguments)]
 pub async fn execute_task_command(
     config: Arc<Config>,
     task_or_group: Option<String>,
     args: Vec<String>,
-    environment: Option<String>,
-    capabilities: Vec<String>,
-    audit: bool,
     verbose: bool,
-    output_format: String,
-    trace_output: bool,
+    list_options: ListOptions,
+    mut options: TaskRunOptions,
 ) -> Result<()> {
+    if let Some(path) = options.profile.take() {
+        profile::write_on_finish(path)?;
+    }
+    if let Some(format) = options.summary.take() {
+        summary::set_format(format.parse()?);
+    }
+    let result =
+        run_task_command(config, task_or_group, args, verbose, list_options, options).await;
+    result.and(profile::finish())
+}
+
+async fn run_task_command(
+    config: Arc<Config>,
+    task_or_group: Option<String>,
+    args: Vec<String>,
+    verbose: bool,
+    list_options: ListOptions,
+    options: TaskRunOptions,
+) -> Result<()> {
+    // `cuenv task run --affected [task...]`
+    if let Some(since) = options.affected_since.clone() {
+        let only = match task_or_group {
+            Some(name) if name == RUN_COMMAND => args,
+            Some(name) => std::iter::once(name).chain(args).collect(),
+            None => Vec::new(),
+        };
+        return affected::execute(config, since, only, &options).await;
+    }
+
     match task_or_group {
+        None if list_options.is_detailed() => list::execute(&config, &list_options, None),
         None => {
             // No arguments: list all tasks
             list_tasks(config, verbose, None).await
@@ -34,20 +102,80 @@ pub async fn execute_task_command(
             // Check if it's a task or a group
             let tasks = config.get_tasks();
 
+            // `cuenv task run <task>` runs a task even when it is named like
+            // one of the words below, unless a task is called run
+            let (name, args, run) = if name == RUN_COMMAND && !tasks.contains_key(&name) {
+                let mut args = args.into_iter();
+                let Some(task_name) = args.next() else {
+                    return Err(Error::usage("Usage: cuenv task run <task> [args...]"));
+                };
+                (task_name, args.collect(), true)
+            } else {
+                (name, args, false)
+            };
+
+            // `cuenv task list [pattern]`, unless a task is called list
+            if name == list::COMMAND && !run && !tasks.contains_key(&name) {
+                return list::execute(&config, &list_options, args.first().map(String::as_str));
+            }
+
+            // `cuenv task infer <task>`, unless a task is called infer
+            if name == infer::COMMAND && !run && !tasks.contains_key(&name) {
+                let mut args = args.into_iter();
+                let Some(task_name) = args.next() else {
+                    return Err(Error::usage("Usage: cuenv task infer <task> [args...]"));
+                };
+                return infer::execute(
+                    options.environment,
+                    options.capabilities,
+                    task_name,
+                    args.collect(),
+                )
+                .await;
+            }
+
+            // `cuenv task explain <task>`, unless a task is called explain
+            if name == explain::COMMAND && !run && !tasks.contains_key(&name) {
+                // Options after the task name are taken as its arguments
+                let json = list_options.json || args.iter().any(|arg| arg == "--json");
+                let Some(task_name) = args.into_iter().find(|arg| arg != "--json") else {
+                    return Err(Error::usage("Usage: cuenv task explain <task> [--json]"));
+                };
+                return explain::execute(
+                    options.environment,
+                    options.capabilities,
+                    task_name,
+                    json,
+                )
+                .await;
+            }
+
+            // `cuenv task run-many <task>`, unless a task is called run-many
+            if name == run_many::COMMAND && !run && !tasks.contains_key(&name) {
+                return run_many::execute(config, args, &options).await;
+            }
+
+            // With `config: taskPrefixMatch`, `dep` runs `deploy` when nothing
+            // else starts with it
+            let names = suggest::task_names(&config);
+            let prefix_match = suggest::prefix_match_enabled(&config);
+            let name = match suggest::by_prefix(&name, &names) {
+                suggest::Prefix::Unique(full) if prefix_match && !names.contains(&name) => {
+                    full.to_string()
+                }
+                suggest::Prefix::Ambiguous(matches) if prefix_match && !names.contains(&name) => {
+                    return Err(Error::usage(format!(
+                        "Task or group '{name}' is ambiguous, it could be: {}",
+                        matches.join(", ")
+                    )));
+                }
+                _ => name,
+            };
+
             // First check if it's a direct task
             if tasks.contains_key(&name) {
                 // It's a task - run it
-                execute_task(
-                    config,
-                    environment,
-                    capabilities,
-                    name,
-                    args,
-                    audit,
-                    output_format.clone(),
-                    trace_output,
-                )
-                .await
+                execute_task(config, &options, name, args).await
             } else if args.is_empty() {
                 // No additional args - check if it's a group
                 let prefix = format!("{name}.");
@@ -66,16 +194,7 @@ pub async fn execute_task_command(
                             | TaskGroupMode::Sequential
                             | TaskGroupMode::Workflow => {
                                 // Executable modes: run all tasks in the group
-                                execute_task_group(
-                                    config.clone(),
-                                    environment,
-                                    capabilities,
-                                    name,
-                                    audit,
-                                    output_format,
-                                    trace_output,
-                                )
-                                .await
+                                execute_task_group(config.clone(), &options, name).await
                             }
                         }
                     } else {
@@ -84,46 +203,38 @@ pub async fn execute_task_command(
                     }
                 } else {
                     // Not found as task or group
-                    eprintln!("Task or group '{name}' not found");
-                    eprintln!("Run 'cuenv task' to see available tasks");
-                    std::process::exit(1)
+                    Err(Error::usage(suggest::not_found(
+                        "Task or group",
+                        &name,
+                        &names,
+                    )))
                 }
             } else {
                 // Has additional args - try as group + subtask
-                let subtask_name = format!("{}.{}", name, args[0]);
+                let mut subtask_name = format!("{}.{}", name, args[0]);
+                if prefix_match && !tasks.contains_key(&subtask_name) {
+                    if let suggest::Prefix::Unique(full) = suggest::by_prefix(&subtask_name, &names)
+                    {
+                        subtask_name = full.to_string();
+                    }
+                }
                 if tasks.contains_key(&subtask_name) {
                     // It's a subtask - run it with remaining args
                     let mut remaining_args = args;
                     remaining_args.remove(0);
-                    execute_task(
-                        config,
-                        environment,
-                        capabilities,
-                        subtask_name,
-                        remaining_args,
-                        audit,
-                        output_format.clone(),
-                        trace_output,
-                    )
-                    .await
+                    execute_task(config, &options, subtask_name, remaining_args).await
                 } else {
                     // Try running the original name as a task with all args
                     if tasks.contains_key(&name) {
-                        execute_task(
-                            config,
-                            environment,
-                            capabilities,
-                            name,
-                            args,
-                            audit,
-                            output_format,
-                            trace_output,
-                        )
-                        .await
+                        execute_task(config, &options, name, args).await
                     } else {
-                        eprintln!("Task '{name}' not found");
-                        eprintln!("Run 'cuenv task' to see available tasks");
-                        std::process::exit(1)
+                        // A group was named, so its task is the one missing
+                        let missing = if names.contains(&name) {
+                            &subtask_name
+                        } else {
+                            &name
+                        };
+                        Err(Error::usage(suggest::not_found("Task", missing, &names)))
                     }
                 }
             }
@@ -173,6 +284,16 @@ pub enum TaskCommands {
         /// Generate Chrome trace output file
         #[arg(long)]
         trace_output: bool,
+
+        /// Record the run as a Chrome trace in FILE, to open in
+        /// chrome://tracing or Perfetto
+        #[arg(long, value_name = "FILE")]
+        profile: Option<PathBuf>,
+
+        /// Print a summary of the run with its critical path (text or json);
+        /// by default a table is printed when more than one task ran
+        #[arg(long, value_name = "FORMAT", value_parser = ["text", "json"])]
+        summary: Option<String>,
     },
 }
 
@@ -192,8 +313,8 @@ async fn list_tasks(
         return Ok(());
     }
 
-    // Check if terminal supports colors
-    let use_color = atty::is(atty::Stream::Stdout);
+    // Color when the terminal supports it, unless the user configured otherwise
+    let use_color = cuenv_core::style::stdout().enabled();
 
     // If a group filter is specified, show that specific group
     if let Some(ref group) = group_filter {
@@ -234,42 +355,15 @@ async fn list_tasks(
 
 // Display functions moved to display module
 
-#[allow(clippy::too_many_arguments)]
 async fn execute_task(
-    _config: std::sync::Arc<cuenv_config::Config>,
-    environment: Option<String>,
-    capabilities: Vec<String>,
+    config: std::sync::Arc<cuenv_config::Config>,
+    options: &TaskRunOptions,
     task_name: String,
     task_args: Vec<String>,
-    audit: bool,
-    output_format: String,
-    trace_output: bool,
 ) -> Result<()> {
-    let current_dir = env::current_dir()
-        .map_err(|e| cuenv_core::Error::file_system(".", "get current directory", e))?;
-    let mut env_manager = EnvManager::new();
-
-    let env_name = environment.or_else(|| env::var(CUENV_ENV_VAR).ok());
-    let mut caps = capabilities;
-    if caps.is_empty() {
-        if let Ok(env_caps) = env::var(CUENV_CAPABILITIES_VAR) {
-            caps = env_caps
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-        }
-    }
-
-    env_manager
-        .load_env_with_options(
-            &current_dir,
-            env_name,
-            caps,
-            None,
-            SupervisorMode::Foreground,
-        )
-        .await?;
+    let started = Instant::now();
+    let (current_dir, env_manager) =
+        load_task_environment(options.environment.clone(), options.capabilities.clone()).await?;
 
     // Check if this might be a group/subtask pattern (e.g., "fmt" with first arg "check")
     // First try the task as-is, then try as group.subtask if not found
@@ -295,17 +389,20 @@ async fn execute_task(
         actual_task_name = task_name.clone();
     };
 
-    // Check if this is a cross-package task reference OR a local task with cross-package dependencies
+    // Check if this is a cross-package task reference OR a local task with cross-package dependencies;
+    // a local task such as `npm:build` is not one
+    let is_cross_package =
+        |name: &String| name.contains(':') && env_manager.get_task(name).is_none();
     let has_cross_package_deps = if let Some(task) = env_manager.get_task(&actual_task_name) {
         task.dependencies
             .as_ref()
-            .map(|deps| deps.iter().any(|d| d.contains(':')))
+            .map(|deps| deps.iter().any(is_cross_package))
             .unwrap_or(false)
     } else {
         false
     };
 
-    if (actual_task_name.contains(':') || has_cross_package_deps)
+    if (is_cross_package(&actual_task_name) || has_cross_package_deps)
         && crate::monorepo::is_monorepo(&current_dir)
     {
         // Handle cross-package task execution
@@ -313,10 +410,11 @@ async fn execute_task(
             &current_dir,
             &actual_task_name,
             &actual_args,
-            audit,
+            options.audit,
         )
-        .await?;
-        std::process::exit(status);
+        .await;
+        finish_run(&config, None, &actual_task_name, &status, started).await;
+        profile::exit(&actual_task_name, status?);
     } else if env_manager.get_task(&actual_task_name).is_some() {
         // Execute the specified task
         let executor = TaskExecutor::new(env_manager, current_dir).await?;
@@ -325,12 +423,20 @@ async fn execute_task(
             &executor,
             &actual_task_name,
             &actual_args,
-            audit,
-            &output_format,
-            trace_output,
+            options.audit,
+            &options.output_format,
+            options.trace_output,
         )
-        .await?;
-        std::process::exit(status);
+        .await;
+        finish_run(
+            &config,
+            Some(&executor),
+            &actual_task_name,
+            &status,
+            started,
+        )
+        .await;
+        profile::exit(&actual_task_name, status?);
     } else {
         // Check if this might be a task group
         let prefix = format!("{task_name}.");
@@ -341,36 +447,76 @@ async fn execute_task(
             .collect();
 
         if !group_tasks.is_empty() {
-            eprintln!("'{task_name}' is a task group. Available tasks:");
+            let mut text = format!("'{task_name}' is a task group. Available tasks:\n");
             for (name, _) in group_tasks {
-                let task_name = &name[prefix.len()..];
-                eprintln!("  {task_name}");
+                text.push_str(&format!("  {}\n", &name[prefix.len()..]));
             }
-            eprintln!();
-            eprintln!("Run 'cuenv task {task_name} <task>' to execute a task");
+            text.push_str(&format!(
+                "\nRun 'cuenv task {task_name} <task>' to execute a task"
+            ));
+            Err(Error::usage(text))
         } else {
-            eprintln!("Task '{task_name}' not found");
-            eprintln!("Run 'cuenv task list' to see available tasks");
+            Err(Error::usage(suggest::not_found(
+                "Task",
+                &task_name,
+                &suggest::task_names(&config),
+            )))
         }
-        std::process::exit(1);
     }
 }
 
-async fn execute_task_group(
-    config: std::sync::Arc<cuenv_config::Config>,
+/// Load the environment tasks run in, for the current directory
+pub(crate) async fn load_task_environment(
     environment: Option<String>,
     capabilities: Vec<String>,
-    group_name: String,
-    audit: bool,
-    output_format: String,
-    trace_output: bool,
-) -> Result<()> {
+) -> Result<(std::path::PathBuf, EnvManager)> {
     let current_dir = env::current_dir()
         .map_err(|e| cuenv_core::Error::file_system(".", "get current directory", e))?;
     let mut env_manager = EnvManager::new();
 
     let env_name = environment.or_else(|| env::var(CUENV_ENV_VAR).ok());
     let mut caps = capabilities;
+    if caps.is_empty() {
+        if let Ok(env_caps) = env::var(CUENV_CAPABILITIES_VAR) {
+            caps = env_caps
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+    }
+
+    env_manager
+        .load_env_with_options(
+            &current_dir,
+            env_name,
+            caps,
+            None,
+            SupervisorMode::Foreground,
+        )
+        .await?;
+
+    // Secrets deferred by lazySecrets are only resolved now that tasks will run
+    env_manager.resolve_deferred_secrets()?;
+
+    Ok((current_dir, env_manager))
+}
+
+async fn execute_task_group(
+    config: std::sync::Arc<cuenv_config::Config>,
+    options: &TaskRunOptions,
+    group_name: String,
+) -> Result<()> {
+    let started = Instant::now();
+    let current_dir = env::current_dir()
+        .map_err(|e| cuenv_core::Error::file_system(".", "get current directory", e))?;
+    let mut env_manager = EnvManager::new();
+
+    let env_name = options
+        .environment
+        .clone()
+        .or_else(|| env::var(CUENV_ENV_VAR).ok());
+    let mut caps = options.capabilities.clone();
 
     // Add capabilities from environment variable if set
     if let Ok(env_caps) = env::var(CUENV_CAPABILITIES_VAR) {
@@ -387,6 +533,7 @@ async fn execute_task_group(
             SupervisorMode::Foreground,
         )
         .await?;
+    env_manager.resolve_deferred_secrets()?;
 
     // Get all tasks in the group
     let prefix = format!("{group_name}.");
@@ -398,8 +545,9 @@ async fn execute_task_group(
         .collect();
 
     if group_tasks.is_empty() {
-        eprintln!("No tasks found in group '{group_name}'");
-        std::process::exit(1);
+        return Err(Error::usage(format!(
+            "No tasks found in group '{group_name}'"
+        )));
     }
 
     // Get the group's execution mode
@@ -410,15 +558,18 @@ async fn execute_task_group(
         TaskGroupMode::Parallel // Default to parallel if we can't determine
     };
 
-    println!(
-        "Executing group '{}' in {} mode",
-        group_name,
-        match &mode {
-            TaskGroupMode::Workflow => "workflow",
-            TaskGroupMode::Sequential => "sequential",
-            TaskGroupMode::Parallel => "parallel",
-            TaskGroupMode::Group => "group",
-        }
+    message(
+        Level::INFO,
+        &format!(
+            "Executing group '{}' in {} mode",
+            group_name,
+            match &mode {
+                TaskGroupMode::Workflow => "workflow",
+                TaskGroupMode::Sequential => "sequential",
+                TaskGroupMode::Parallel => "parallel",
+                TaskGroupMode::Group => "group",
+            }
+        ),
     );
 
     // Create executor and run based on mode
@@ -432,16 +583,25 @@ async fn execute_task_group(
                     &executor,
                     task_name,
                     &[],
-                    audit,
-                    &output_format,
-                    trace_output,
+                    options.audit,
+                    &options.output_format,
+                    options.trace_output,
                 )
-                .await?;
+                .await;
+                if !matches!(status, Ok(0)) {
+                    finish_run(&config, Some(&executor), &group_name, &status, started).await;
+                }
+                let status = status?;
                 if status != 0 {
-                    eprintln!("Task '{task_name}' failed with status {status}");
-                    std::process::exit(status);
+                    task_message(
+                        Level::ERROR,
+                        task_name,
+                        &format!("Task '{task_name}' failed with status {status}"),
+                    );
+                    profile::exit(task_name, status);
                 }
             }
+            finish_run(&config, Some(&executor), &group_name, &Ok(0), started).await;
         }
         TaskGroupMode::Parallel | TaskGroupMode::Workflow => {
             // Execute with dependencies using formatter
@@ -449,22 +609,81 @@ async fn execute_task_group(
                 &executor,
                 &group_tasks,
                 &[],
-                audit,
-                &output_format,
-                trace_output,
+                options.audit,
+                &options.output_format,
+                options.trace_output,
             )
-            .await?;
+            .await;
+            finish_run(&config, Some(&executor), &group_name, &status, started).await;
+            let status = status?;
             if status != 0 {
-                std::process::exit(status);
+                profile::exit(&group_name, status);
             }
         }
         TaskGroupMode::Group => {
             // This shouldn't happen as we filter this out earlier, but handle it anyway
-            eprintln!("Group '{group_name}' is for organization only and cannot be executed");
-            eprintln!("Run 'cuenv task {group_name}' to see available tasks");
-            std::process::exit(1);
+            return Err(Error::usage(format!(
+                "Group '{group_name}' is for organization only and cannot be executed\nRun 'cuenv task {group_name}' to see available tasks"
+            )));
         }
     }
 
     Ok(())
 }
+
+/// Print the summary of a finished run, write its manifest and send the
+/// notifications env.cue asks for
+async fn finish_run(
+    config: &cuenv_config::Config,
+    executor: Option<&TaskExecutor>,
+    task: &str,
+    status: &Result<i32>,
+    started: Instant,
+) {
+    if let Some(executor) = executor {
+        summary::print(executor);
+        write_manifest(config, executor, task, status, started);
+    }
+    if let Some(notify) = &config.parse_result.notify {
+        let outcome = RunOutcome {
+            task: task.to_string(),
+            directory: config.working_dir.clone(),
+            run_id: cuenv_utils::tracing::run_id().to_string(),
+            exit_code: status.as_ref().ok().copied(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            summary: executor.and_then(TaskExecutor::run_summary),
+        };
+        cuenv_hooks::notify::send(notify, &outcome).await;
+    }
+}
+
+/// Write the manifest of the run to `.cuenv/runs`, warning when it cannot
+/// be written
+fn write_manifest(
+    config: &cuenv_config::Config,
+    executor: &TaskExecutor,
+    target: &str,
+    status: &Result<i32>,
+    started: Instant,
+) {
+    let Some(summary) = executor.run_summary() else {
+        return;
+    };
+    let started_at =
+        chrono::Utc::now() - chrono::Duration::from_std(started.elapsed()).unwrap_or_default();
+    let manifest = RunManifest::new(
+        cuenv_utils::tracing::run_id(),
+        target,
+        &config.working_dir,
+        started_at,
+        status.as_ref().ok().copied(),
+        summary,
+    );
+    match manifest.write() {
+        Ok(path) => tracing::debug!(path = %path.display(), "Wrote run manifest"),
+        Err(e) => message(
+            Level::WARN,
+            &format!("Could not write the manifest of the run: {e}"),
+        ),
+    }
+}
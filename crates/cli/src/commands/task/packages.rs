@@ -0,0 +1,150 @@
+//! `cuenv task --package <pattern>`, a task in each package of a workspace
+//!
+//! The command is run again by `cuenv` in every package of the workspace
+//! matching one of the patterns, one after the other, stopping at the first
+//! failure. Packages without the task are skipped, so `--package '*' test`
+//! runs the tests of every package having them.
+
+use super::{explain, infer, list, ListOptions, TaskRunOptions, RUN_COMMAND};
+use crate::commands::workspace;
+use cuenv_config::{package_name, CueParser, ParseOptions};
+use cuenv_core::{Error, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Run the task command in each package matching `patterns`
+pub fn execute(
+    working_dir: &Path,
+    patterns: &[String],
+    task_or_group: Option<String>,
+    args: Vec<String>,
+    verbose: bool,
+    list_options: ListOptions,
+    options: &TaskRunOptions,
+) -> Result<()> {
+    let (_, packages) = workspace::load(working_dir)?;
+    let packages = workspace::filter(packages, patterns)?;
+    if packages.is_empty() {
+        return Err(Error::usage(format!(
+            "No workspace package matches {}",
+            patterns.join(", ")
+        )));
+    }
+
+    let task = target(task_or_group.as_deref(), &args);
+    let exe = std::env::current_exe()
+        .map_err(|e| Error::configuration(format!("Failed to locate the cuenv binary: {e}")))?;
+    // Options go before the task, as what follows it is passed to it
+    let mut command_args = vec![
+        "task".to_string(),
+        "--output".to_string(),
+        options.output_format.clone(),
+    ];
+    if let Some(environment) = &options.environment {
+        command_args.extend(["-e".to_string(), environment.clone()]);
+    }
+    for capability in &options.capabilities {
+        command_args.extend(["-c".to_string(), capability.clone()]);
+    }
+    for (flag, set) in [
+        ("--audit", options.audit),
+        ("--verbose", verbose),
+        ("--json", list_options.json),
+        ("--table", list_options.table),
+    ] {
+        if set {
+            command_args.push(flag.to_string());
+        }
+    }
+    for tag in list_options.tags {
+        command_args.extend(["--tagged".to_string(), tag]);
+    }
+    command_args.extend(task_or_group);
+    command_args.extend(args);
+
+    let mut ran = 0;
+    for package in &packages {
+        if let Some(task) = task {
+            if !has_task(&package.path, task) {
+                tracing::debug!("Package {} has no task '{task}'", package.name);
+                continue;
+            }
+        }
+
+        println!("▶ {} ({})", package.name, package.relative.display());
+        let status = Command::new(&exe)
+            .args(&command_args)
+            .current_dir(&package.path)
+            .status()
+            .map_err(|e| {
+                Error::command_execution(
+                    "cuenv",
+                    command_args.clone(),
+                    format!("failed to run in {}: {e}", package.name),
+                    None,
+                )
+            })?;
+        if !status.success() {
+            return Err(Error::command_execution(
+                "cuenv",
+                command_args,
+                format!("failed in package {}", package.name),
+                status.code(),
+            ));
+        }
+        ran += 1;
+    }
+
+    if ran == 0 {
+        if let Some(task) = task {
+            return Err(Error::usage(format!(
+                "No package matching {} has a task '{task}'",
+                patterns.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The task the command runs, if it runs one rather than listing
+fn target<'a>(task_or_group: Option<&'a str>, args: &'a [String]) -> Option<&'a str> {
+    let name = task_or_group?;
+    if name == list::COMMAND {
+        None
+    } else if name == RUN_COMMAND || name == infer::COMMAND {
+        args.first().map(String::as_str)
+    } else if name == explain::COMMAND {
+        args.iter().map(String::as_str).find(|arg| *arg != "--json")
+    } else {
+        Some(name)
+    }
+}
+
+/// Whether the package in `dir` has a task or group named `task`
+fn has_task(dir: &Path, task: &str) -> bool {
+    match CueParser::eval_package_with_options(dir, &package_name(), &ParseOptions::default()) {
+        Ok(result) => result.task_nodes.contains_key(task) || result.tasks.contains_key(task),
+        Err(e) => {
+            tracing::warn!("Failed to load package at {}: {e}", dir.display());
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(target(Some("build"), &args(&["--release"])), Some("build"));
+        assert_eq!(target(Some("run"), &args(&["list"])), Some("list"));
+        assert_eq!(
+            target(Some("explain"), &args(&["--json", "test"])),
+            Some("test")
+        );
+        assert_eq!(target(Some("list"), &args(&["ci.*"])), None);
+        assert_eq!(target(None, &[]), None);
+    }
+}
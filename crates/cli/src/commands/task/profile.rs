@@ -0,0 +1,59 @@
+//! `cuenv task --profile <file>`, a Chrome trace of the run
+//!
+//! The trace covers task start and end, scheduling, hashing and cache
+//! lookups. Recording starts with the tracing system; the trace is written
+//! when the task command finishes, including when it exits with the status
+//! of a failed task.
+
+use cuenv_core::{Error, Result};
+use cuenv_utils::tracing::Level;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Where the trace of the run goes
+static PROFILE_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Write the trace of the run to `path` when the command finishes
+pub fn write_on_finish(path: PathBuf) -> Result<()> {
+    if cuenv_utils::tracing::recorded_trace().is_none() {
+        return Err(Error::configuration(
+            "The run is not being profiled".to_string(),
+        ));
+    }
+    if let Ok(mut profile_path) = PROFILE_PATH.lock() {
+        *profile_path = Some(path);
+    }
+    Ok(())
+}
+
+/// Write the trace of the run, if it was asked for
+pub fn finish() -> Result<()> {
+    let Some(path) = PROFILE_PATH.lock().ok().and_then(|mut path| path.take()) else {
+        return Ok(());
+    };
+    let Some(trace) = cuenv_utils::tracing::recorded_trace() else {
+        return Ok(());
+    };
+    trace
+        .write(&path)
+        .map_err(|e| Error::file_system(&path, "write profile", e))?;
+    cuenv_utils::tracing::message(
+        Level::INFO,
+        &format!(
+            "Profile written to {} (open in chrome://tracing or https://ui.perfetto.dev)",
+            path.display()
+        ),
+    );
+    Ok(())
+}
+
+/// Write the trace of the run and exit with the `status` of `task`
+pub fn exit(task: &str, status: i32) -> ! {
+    if let Err(e) = finish() {
+        cuenv_utils::tracing::message(Level::WARN, &format!("Warning: {e}"));
+    }
+    if status == 0 {
+        std::process::exit(0)
+    }
+    crate::exit::with_error(Error::task_failed(task, status))
+}
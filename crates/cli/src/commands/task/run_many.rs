@@ -0,0 +1,301 @@
+//! `cuenv task run-many <task>`, a task in every package defining it
+//!
+//! The packages are those of the cuenv.workspace.cue the current directory
+//! is in, or of its CUE module. The tasks of every package run in one
+//! executor, so `--parallel` (or `jobs` of the user configuration) limits
+//! them all together, and a failing package does not stop the others of
+//! its level. A table of how each package did follows the run.
+
+use super::{finish_run, formatter, profile, summary, write_manifest, TaskRunOptions};
+use crate::commands::workspace;
+use cuenv_config::{package_name, Config, CueParser, ParseOptions, WorkspaceConfig};
+use cuenv_core::style::{self, Painter, Role};
+use cuenv_core::{Error, Result};
+use cuenv_task::{
+    DiscoveredPackage, MonorepoTaskRegistry, ParseResult as TaskParseResult, RunSummary,
+    TaskExecutor,
+};
+use cuenv_utils::tracing::{log_format, message, Level, LogFormat};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The word running a task in every package instead of a task to run
+pub const COMMAND: &str = "run-many";
+
+const USAGE: &str = "Usage: cuenv task run-many <task>... [--parallel <jobs>]";
+
+/// Run the tasks named in `args` in every package defining them
+pub async fn execute(
+    config: Arc<Config>,
+    args: Vec<String>,
+    options: &TaskRunOptions,
+) -> Result<()> {
+    let started = Instant::now();
+    let (tasks, jobs) = parse_args(args)?;
+    if options.environment.is_some() || !options.capabilities.is_empty() {
+        return Err(Error::usage(
+            "run-many runs the tasks of each package like cross-package dependencies, without -e or -c",
+        ));
+    }
+
+    let registry = registry(&config.working_dir).await?;
+    // (package, full task name) of every task to run
+    let mut targets: Vec<(String, String)> = registry
+        .list_all_tasks()
+        .into_iter()
+        .filter_map(|(name, _)| registry.get_task(&name))
+        .filter(|task| tasks.contains(&task.task_name))
+        .map(|task| (task.package_name.clone(), task.full_name.clone()))
+        .collect();
+    targets.sort();
+    if targets.is_empty() {
+        return Err(Error::usage(format!(
+            "No package defines {}",
+            tasks
+                .iter()
+                .map(|task| format!("a task '{task}'"))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )));
+    }
+    let names: Vec<String> = targets.iter().map(|(_, name)| name.clone()).collect();
+    message(
+        Level::INFO,
+        &format!(
+            "Running {} in {} packages",
+            tasks.join(", "),
+            targets
+                .iter()
+                .map(|(package, _)| package)
+                .collect::<std::collections::BTreeSet<_>>()
+                .len()
+        ),
+    );
+
+    registry.validate_all_dependencies()?;
+    let mut executor = TaskExecutor::new_with_registry(registry).await?;
+    if let Some(jobs) = jobs {
+        executor = executor.with_jobs(jobs);
+    }
+    let status = formatter::execute_tasks_with_formatter(
+        &executor,
+        &names,
+        &[],
+        options.audit,
+        &options.output_format,
+        options.trace_output,
+    )
+    .await;
+
+    if summary::is_json() {
+        summary::print(&executor);
+    } else if let Some(run) = executor.run_summary() {
+        let painter = if log_format() == LogFormat::Text {
+            style::stdout()
+        } else {
+            Painter::plain()
+        };
+        message(
+            Level::INFO,
+            &format!("\n{}", package_table(&run, &targets, &painter)),
+        );
+    }
+    write_manifest(&config, &executor, COMMAND, &status, started);
+    finish_run(&config, None, COMMAND, &status, started).await;
+    let status = status?;
+    if status != 0 {
+        profile::exit(COMMAND, status);
+    }
+    Ok(())
+}
+
+/// The task names and the `--parallel` limit of `cuenv task run-many`
+fn parse_args(args: Vec<String>) -> Result<(Vec<String>, Option<usize>)> {
+    let mut tasks = Vec::new();
+    let mut jobs = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = if arg == "--parallel" || arg == "-j" {
+            Some(args.next().ok_or_else(|| Error::usage(USAGE))?)
+        } else {
+            arg.strip_prefix("--parallel=").map(str::to_string)
+        };
+        match value {
+            Some(value) => {
+                let parsed = value.parse::<usize>().ok().filter(|jobs| *jobs > 0);
+                jobs = Some(parsed.ok_or_else(|| {
+                    Error::usage(format!(
+                        "--parallel takes a number of tasks of at least 1, not '{value}'"
+                    ))
+                })?);
+            }
+            None if arg.starts_with('-') => {
+                return Err(Error::usage(format!("Unknown option '{arg}'\n{USAGE}")));
+            }
+            None => tasks.push(arg),
+        }
+    }
+    if tasks.is_empty() {
+        return Err(Error::usage(USAGE));
+    }
+    Ok((tasks, jobs))
+}
+
+/// The tasks of every package of the workspace `dir` is in, or of its CUE
+/// module without one
+async fn registry(dir: &Path) -> Result<MonorepoTaskRegistry> {
+    if WorkspaceConfig::find_root(dir).is_none() {
+        return crate::monorepo::task_registry(dir).await;
+    }
+
+    let (_, packages) = workspace::load(dir)?;
+    let package = package_name();
+    let packages = packages
+        .into_iter()
+        .map(|workspace_package| {
+            let parse_result = match CueParser::eval_package_with_options(
+                &workspace_package.path,
+                &package,
+                &ParseOptions::default(),
+            ) {
+                Ok(result) => Some(TaskParseResult {
+                    tasks: result.tasks,
+                }),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to load package at {}: {e}",
+                        workspace_package.path.display()
+                    );
+                    None
+                }
+            };
+            DiscoveredPackage {
+                name: workspace_package.name,
+                path: workspace_package.path,
+                parse_result,
+            }
+        })
+        .collect();
+    MonorepoTaskRegistry::from_packages(packages)
+}
+
+/// How the task of each package did: its status, how long it took and
+/// whether its result came from the cache
+fn package_table(run: &RunSummary, targets: &[(String, String)], painter: &Painter) -> String {
+    let width = targets
+        .iter()
+        .map(|(package, _)| package.len())
+        .max()
+        .unwrap_or(0)
+        .max("Package".len());
+    let task_width = targets
+        .iter()
+        .map(|(package, name)| name.len() - package.len() - 1)
+        .max()
+        .unwrap_or(0)
+        .max("Task".len());
+
+    // Columns are padded before they are painted, as escape codes have no
+    // width on screen
+    let header = format!(
+        "{:<width$}  {:<task_width$}  {:>9}  {:<8}  Status",
+        "Package", "Task", "Duration", "Cache"
+    );
+    let mut out = format!("{}\n", painter.paint(Role::Heading, header));
+    let mut passed = 0;
+    for (package, name) in targets {
+        let task = &name[package.len() + 1..];
+        let Some(summary) = run.tasks.iter().find(|task| task.name == *name) else {
+            let _ = writeln!(
+                out,
+                "{package:<width$}  {task:<task_width$}  {:>9}  {:<8}  {}",
+                "-",
+                "-",
+                painter.paint(Role::Muted, "not run")
+            );
+            continue;
+        };
+        let mut status = match summary.exit_code {
+            Some(0) => {
+                passed += 1;
+                painter.paint(Role::Success, "ok")
+            }
+            Some(code) => painter.paint(Role::Failure, format!("exit {code}")),
+            None => painter.paint(Role::Failure, "error"),
+        };
+        if let Some(note) = summary.owner_note() {
+            status = format!("{status}  {}", painter.paint(Role::Muted, note));
+        }
+        let _ = writeln!(
+            out,
+            "{package:<width$}  {task:<task_width$}  {:>9}  {:<8}  {status}",
+            format!("{:.2}s", summary.duration_ms as f64 / 1000.0),
+            summary.cache.to_string(),
+        );
+    }
+    let _ = write!(
+        out,
+        "{} of {} passed in {:.2}s",
+        passed,
+        targets.len(),
+        run.duration_ms as f64 / 1000.0
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_task::{CacheStatus, TaskSummary};
+
+    #[test]
+    fn test_parse_args() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            parse_args(args(&["test", "--parallel", "4"])).unwrap(),
+            (vec!["test".to_string()], Some(4))
+        );
+        assert_eq!(
+            parse_args(args(&["--parallel=2", "lint", "test"])).unwrap(),
+            (vec!["lint".to_string(), "test".to_string()], Some(2))
+        );
+        assert!(parse_args(args(&["test", "--parallel", "0"])).is_err());
+        assert!(parse_args(args(&["--parallel", "4"])).is_err());
+        assert!(parse_args(args(&["test", "--force"])).is_err());
+    }
+
+    #[test]
+    fn test_package_table() {
+        let task = |name: &str, exit_code| TaskSummary {
+            name: name.to_string(),
+            duration_ms: 1200,
+            queue_wait_ms: 0,
+            cache: CacheStatus::Miss,
+            exit_code: Some(exit_code),
+            owners: vec!["team-ui".to_string()],
+            cache_key: None,
+        };
+        let run = RunSummary {
+            duration_ms: 2500,
+            plan: vec![],
+            tasks: vec![task("apps:web:test", 0), task("libs:ui:test", 1)],
+            critical_path: vec![],
+            critical_path_ms: 0,
+        };
+        let targets: Vec<(String, String)> = ["apps:web", "libs:ui", "root"]
+            .iter()
+            .map(|package| (package.to_string(), format!("{package}:test")))
+            .collect();
+
+        assert_eq!(
+            package_table(&run, &targets, &Painter::plain()),
+            "Package   Task   Duration  Cache     Status
+apps:web  test      1.20s  miss      ok
+libs:ui   test      1.20s  miss      exit 1  owners: team-ui
+root      test          -  -         not run
+1 of 3 passed in 2.50s"
+        );
+    }
+}
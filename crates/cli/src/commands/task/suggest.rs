@@ -0,0 +1,176 @@
+//! Suggestions for task names that are not found, and prefix invocation
+//!
+//! A name that matches no task or group is compared with the names that do
+//! exist, so that `cuenv task biuld` can ask "did you mean 'build'?". With
+//! `config: taskPrefixMatch: true`, a name that is the prefix of exactly one
+//! task or group at the same depth runs it, so `cuenv task dep` runs
+//! `deploy` and `cuenv task ci.t` runs `ci.test`.
+
+use cuenv_config::{Config, ConfigSettings};
+use std::collections::BTreeSet;
+
+/// Most names suggested at once
+const MAX_SUGGESTIONS: usize = 3;
+
+/// What a name that is not a task or group resolves to by prefix
+#[derive(Debug, PartialEq, Eq)]
+pub enum Prefix<'a> {
+    /// The one task or group the name is a prefix of
+    Unique(&'a str),
+    /// The tasks and groups the name is a prefix of
+    Ambiguous(Vec<&'a str>),
+    None,
+}
+
+/// Whether `taskPrefixMatch` is enabled by the project or the user
+pub fn prefix_match_enabled(config: &Config) -> bool {
+    ConfigSettings::effective(config.parse_result.config.as_ref())
+        .task_prefix_match
+        .unwrap_or(false)
+}
+
+/// The names of the tasks of `config` and of the groups holding them
+pub fn task_names(config: &Config) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for task in config.get_tasks().keys() {
+        let mut group = String::new();
+        for segment in task.split('.') {
+            if !group.is_empty() {
+                group.push('.');
+            }
+            group.push_str(segment);
+            names.insert(group.clone());
+        }
+    }
+    names
+}
+
+/// The names at the depth of `name` that start with it
+pub fn by_prefix<'a>(name: &str, names: &'a BTreeSet<String>) -> Prefix<'a> {
+    let depth = name.split('.').count();
+    let matches: Vec<&str> = names
+        .iter()
+        .filter(|candidate| candidate.split('.').count() == depth)
+        .filter(|candidate| candidate.starts_with(name))
+        .map(String::as_str)
+        .collect();
+    match matches.as_slice() {
+        [] => Prefix::None,
+        [only] => Prefix::Unique(only),
+        _ => Prefix::Ambiguous(matches),
+    }
+}
+
+/// The names closest to `name`, most similar first
+pub fn similar<'a>(name: &str, names: &'a BTreeSet<String>) -> Vec<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, &str)> = names
+        .iter()
+        .filter_map(|candidate| {
+            let distance = if candidate.starts_with(name) {
+                0
+            } else {
+                edit_distance(name, candidate)
+            };
+            (distance <= max_distance).then_some((distance, candidate.as_str()))
+        })
+        .collect();
+    close.sort();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// The message for a task or group `name` that does not exist
+pub fn not_found(kind: &str, name: &str, names: &BTreeSet<String>) -> String {
+    let quoted: Vec<String> = similar(name, names)
+        .into_iter()
+        .map(|candidate| format!("'{candidate}'"))
+        .collect();
+    let hint = match quoted.as_slice() {
+        [] => String::new(),
+        [only] => format!("\nDid you mean {only}?"),
+        _ => format!("\nDid you mean one of {}?", quoted.join(", ")),
+    };
+    format!("{kind} '{name}' not found{hint}\nRun 'cuenv task' to see available tasks")
+}
+
+/// The number of insertions, deletions, substitutions and swaps of
+/// adjacent characters turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for j in 0..=b.len() {
+        rows[0][j] = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> BTreeSet<String> {
+        ["build", "ci", "ci.lint", "ci.test", "deploy", "dev"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("build", "build"), 0);
+        assert_eq!(edit_distance("biuld", "build"), 1);
+        assert_eq!(edit_distance("bild", "build"), 1);
+        assert_eq!(edit_distance("deploy", "dev"), 4);
+    }
+
+    #[test]
+    fn test_similar() {
+        let names = names();
+        assert_eq!(similar("biuld", &names), ["build"]);
+        assert_eq!(similar("ci.tset", &names), ["ci.test"]);
+        assert_eq!(similar("de", &names), ["deploy", "dev"]);
+        assert!(similar("release", &names).is_empty());
+    }
+
+    #[test]
+    fn test_by_prefix() {
+        let names = names();
+        assert_eq!(by_prefix("dep", &names), Prefix::Unique("deploy"));
+        assert_eq!(by_prefix("ci.t", &names), Prefix::Unique("ci.test"));
+        assert_eq!(by_prefix("c", &names), Prefix::Unique("ci"));
+        assert_eq!(
+            by_prefix("de", &names),
+            Prefix::Ambiguous(vec!["deploy", "dev"])
+        );
+        assert_eq!(by_prefix("x", &names), Prefix::None);
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert_eq!(
+            not_found("Task", "biuld", &names()),
+            "Task 'biuld' not found\nDid you mean 'build'?\nRun 'cuenv task' to see available tasks"
+        );
+    }
+}
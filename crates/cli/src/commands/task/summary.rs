@@ -0,0 +1,82 @@
+//! `cuenv task --summary <format>`, the summary printed after a run
+//!
+//! The summary lists how long each task took, how long it waited once its
+//! dependencies were done, whether its result came from the cache, and the
+//! critical path of the run. Without `--summary` it is printed as a table
+//! when more than one task ran; `--summary json` prints it to stdout as JSON
+//! for tooling.
+
+use cuenv_core::style::{self, Painter};
+use cuenv_core::{Error, Result};
+use cuenv_task::{RunSummary, TaskExecutor};
+use cuenv_utils::tracing::{log_format, message, Level, LogFormat};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// How the summary of a run is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for SummaryFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(Error::configuration(format!(
+                "Unknown summary format '{other}', expected text or json"
+            ))),
+        }
+    }
+}
+
+/// The format asked for with `--summary`
+static SUMMARY_FORMAT: Mutex<Option<SummaryFormat>> = Mutex::new(None);
+
+/// Print the summary of the run in `format`, even for a single task
+pub fn set_format(format: SummaryFormat) {
+    if let Ok(mut summary_format) = SUMMARY_FORMAT.lock() {
+        *summary_format = Some(format);
+    }
+}
+
+/// Whether `--summary json` was given
+pub fn is_json() -> bool {
+    SUMMARY_FORMAT
+        .lock()
+        .is_ok_and(|format| *format == Some(SummaryFormat::Json))
+}
+
+/// Print the summary of the tasks `executor` ran
+pub fn print(executor: &TaskExecutor) {
+    let Some(summary) = executor.run_summary() else {
+        return;
+    };
+    let format = SUMMARY_FORMAT.lock().ok().and_then(|format| *format);
+    match format {
+        Some(SummaryFormat::Json) => match serde_json::to_string_pretty(&summary) {
+            Ok(json) => println!("{json}"),
+            Err(e) => message(
+                Level::WARN,
+                &format!("Warning: Failed to serialize run summary: {e}"),
+            ),
+        },
+        Some(SummaryFormat::Text) => print_table(&summary),
+        None if summary.tasks.len() > 1 => print_table(&summary),
+        None => {}
+    }
+}
+
+/// Print the summary as a table, colored unless logs are JSON
+fn print_table(summary: &RunSummary) {
+    let painter = if log_format() == LogFormat::Text {
+        style::stdout()
+    } else {
+        Painter::plain()
+    };
+    message(Level::INFO, &format!("\n{}", summary.render(&painter)));
+}
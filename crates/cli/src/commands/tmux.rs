@@ -0,0 +1,40 @@
+use clap::Subcommand;
+use cuenv_core::{Error, Result};
+use cuenv_env::StateManager;
+use cuenv_shell::tmux;
+
+#[derive(Subcommand)]
+pub enum TmuxCommands {
+    /// Copy the environment loaded in this shell to the tmux session, so
+    /// new panes and windows start with it
+    Refresh,
+}
+
+impl TmuxCommands {
+    pub async fn execute(self) -> Result<()> {
+        match self {
+            TmuxCommands::Refresh => refresh(),
+        }
+    }
+}
+
+fn refresh() -> Result<()> {
+    if !tmux::inside_tmux() {
+        return Err(Error::configuration("Not running inside tmux"));
+    }
+
+    let Some(diff) = StateManager::get_diff().ok().flatten() else {
+        eprintln!("No cuenv environment is loaded in this shell");
+        return Ok(());
+    };
+
+    // The state lets the hook in a new pane unload the environment when the
+    // pane is outside the project
+    let mut changes = tmux::changes(&diff);
+    changes.extend(StateManager::exported_vars());
+    tmux::propagate(&changes)
+        .map_err(|e| Error::configuration(format!("Failed to update the tmux environment: {e}")))?;
+
+    eprintln!("✓ New tmux panes and windows will start with this environment");
+    Ok(())
+}
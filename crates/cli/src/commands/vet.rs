@@ -0,0 +1,150 @@
+//! `cuenv vet`: check a configuration without loading it
+//!
+//! The package is evaluated against the embedded schema, which reports
+//! unknown fields and values of the wrong type, and its tasks are built,
+//! which reports dependencies on tasks that do not exist and cycles.
+
+use cuenv_config::{package_name, primary_file, tags_from_env, CueParser, ParseOptions};
+use cuenv_core::{Diagnostic, Error, Result};
+use cuenv_task::TaskBuilder;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A problem found in a configuration file
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    pub file: PathBuf,
+    pub message: String,
+    /// 1-based position, when the problem has one
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// How the problem is shown to people
+    #[serde(skip)]
+    rendered: String,
+}
+
+impl Problem {
+    /// A problem concerning a whole file
+    pub fn in_file(file: &Path, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            rendered: format!("error: {message}\n --> {}", file.display()),
+            file: file.to_path_buf(),
+            message,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// The problems behind an error, positioned when the error has diagnostics
+    pub fn from_error(file: &Path, error: &Error) -> Vec<Self> {
+        let diagnostics = error.diagnostics();
+        if diagnostics.is_empty() {
+            return vec![Self::in_file(file, error.to_string())];
+        }
+        diagnostics.iter().map(Self::from).collect()
+    }
+}
+
+impl From<&Diagnostic> for Problem {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        Self {
+            file: diagnostic.file.clone(),
+            message: diagnostic.message.clone(),
+            line: Some(diagnostic.line),
+            column: Some(diagnostic.column),
+            rendered: diagnostic.to_string(),
+        }
+    }
+}
+
+/// Print problems, as a JSON array with `json`, and fail when there are any
+pub fn report(problems: &[Problem], json: bool) -> Result<()> {
+    if json {
+        let output = serde_json::to_string_pretty(problems).map_err(|e| Error::Json {
+            message: "failed to encode problems".to_string(),
+            source: e,
+        })?;
+        println!("{output}");
+    } else {
+        for problem in problems {
+            eprintln!("{}\n", problem.rendered);
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        std::process::exit(1);
+    }
+}
+
+/// Check the configuration of `directory`
+pub fn vet(directory: &Path) -> Vec<Problem> {
+    let package = package_name();
+    let Some(file) = primary_file(directory, &package) else {
+        return vec![Problem::in_file(
+            directory,
+            "No cuenv configuration found in directory",
+        )];
+    };
+
+    let options = ParseOptions {
+        tags: tags_from_env(),
+        full_evaluation: true,
+        ..Default::default()
+    };
+    let result = match CueParser::eval_package_with_options(directory, &package, &options) {
+        Ok(result) => result,
+        Err(e) => return Problem::from_error(&file, &e),
+    };
+
+    match TaskBuilder::new(directory.to_path_buf())
+        .build_tasks_with_nodes(result.tasks, result.task_nodes)
+    {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![Problem::in_file(&file, e.to_string())],
+    }
+}
+
+pub async fn execute(directory: Option<PathBuf>, json: bool) -> Result<()> {
+    let directory = match directory {
+        Some(directory) => directory,
+        None => std::env::current_dir()?,
+    };
+
+    let problems = vet(&directory);
+    if problems.is_empty() && !json {
+        println!("✓ {} is valid", directory.display());
+    }
+    report(&problems, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems_keep_diagnostic_positions() {
+        let diagnostic = Diagnostic::from_source("field not allowed", "/project/env.cue", 4, 2);
+        let error = Error::cue_parse_with_diagnostics("/project", "invalid", vec![diagnostic]);
+
+        let problems = Problem::from_error(Path::new("/project/env.cue"), &error);
+        assert_eq!(
+            serde_json::to_value(&problems).unwrap(),
+            serde_json::json!([{
+                "file": "/project/env.cue",
+                "message": "field not allowed",
+                "line": 4,
+                "column": 2
+            }])
+        );
+
+        let problems = Problem::from_error(
+            Path::new("/project/env.cue"),
+            &Error::configuration("Task 'build' depends on 'missing'"),
+        );
+        assert_eq!(problems[0].line, None);
+        assert!(problems[0].rendered.ends_with("--> /project/env.cue"));
+    }
+}
@@ -0,0 +1,192 @@
+//! `cuenv workspace`, the packages a cuenv.workspace.cue declares
+//!
+//! The packages of a workspace are the directories matching its `packages`
+//! globs, and none of its `exclude` globs, that have a configuration. They
+//! are named like `cuenv discover` names them: the components of their path
+//! from the root joined by `:`, and `root` for the root itself.
+
+use clap::Subcommand;
+use cuenv_config::{package_name, primary_file, Config, WorkspaceConfig};
+use cuenv_core::constants::WORKSPACE_FILENAME;
+use cuenv_core::{Error, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directories never searched for packages
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "cue.mod"];
+
+#[derive(Subcommand)]
+pub enum WorkspaceCommands {
+    /// List the packages of the workspace
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl WorkspaceCommands {
+    pub fn execute(self, config: &Config) -> Result<()> {
+        match self {
+            WorkspaceCommands::List { json } => {
+                let (_, packages) = load(&config.working_dir)?;
+                if json {
+                    let output =
+                        serde_json::to_string_pretty(&packages).map_err(|e| Error::Json {
+                            message: "Failed to serialize the packages".to_string(),
+                            source: e,
+                        })?;
+                    println!("{output}");
+                    return Ok(());
+                }
+
+                let width = packages.iter().map(|p| p.name.len()).max().unwrap_or(0);
+                for package in &packages {
+                    println!(
+                        "{:width$}  {}",
+                        package.name,
+                        package.relative.display(),
+                        width = width
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A configured directory of a workspace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WorkspacePackage {
+    /// Path components from the root joined by `:`
+    pub name: String,
+    pub path: PathBuf,
+    /// Path from the root of the workspace
+    pub relative: PathBuf,
+}
+
+/// The root of the workspace `dir` is in and its packages
+pub fn load(dir: &Path) -> Result<(PathBuf, Vec<WorkspacePackage>)> {
+    let root = WorkspaceConfig::find_root(dir).ok_or_else(|| {
+        Error::configuration(format!(
+            "No {WORKSPACE_FILENAME} found in {} or its parents",
+            dir.display()
+        ))
+    })?;
+    let workspace = WorkspaceConfig::load(&root)?;
+    let packages = packages(&root, &workspace)?;
+    Ok((root, packages))
+}
+
+/// The packages under `root` declared by `workspace`, sorted by path
+pub fn packages(root: &Path, workspace: &WorkspaceConfig) -> Result<Vec<WorkspacePackage>> {
+    let included = glob_set(&workspace.packages)?;
+    let excluded = glob_set(&workspace.exclude)?;
+    let package = package_name();
+
+    let mut packages = Vec::new();
+    let walker = WalkDir::new(root)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || SKIPPED_DIRS.contains(&&*name))
+        });
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let selected = if relative.as_os_str().is_empty() {
+            workspace.packages.iter().any(|glob| glob == ".")
+        } else {
+            included.is_match(relative) && !excluded.is_match(relative)
+        };
+        if selected && primary_file(entry.path(), &package).is_some() {
+            packages.push(WorkspacePackage {
+                name: name(relative),
+                path: entry.path().to_path_buf(),
+                relative: relative.to_path_buf(),
+            });
+        }
+    }
+    Ok(packages)
+}
+
+/// The packages matching one of `patterns`, by name or by relative path
+pub fn filter(
+    packages: Vec<WorkspacePackage>,
+    patterns: &[String],
+) -> Result<Vec<WorkspacePackage>> {
+    let by_name = glob_set(patterns)?;
+    Ok(packages
+        .into_iter()
+        .filter(|package| by_name.is_match(&package.name) || by_name.is_match(&package.relative))
+        .collect())
+}
+
+/// The name of the package at `relative`
+fn name(relative: &Path) -> String {
+    let components: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if components.is_empty() {
+        "root".to_string()
+    } else {
+        components.join(":")
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.trim_end_matches('/'))
+            .literal_separator(true)
+            .build()
+            .map_err(|e| Error::configuration(format!("Invalid package glob '{pattern}': {e}")))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| Error::configuration(format!("Invalid package globs: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_packages() {
+        let root = TempDir::new().unwrap();
+        for dir in [
+            "apps/web",
+            "apps/api",
+            "apps/legacy",
+            "libs/ui",
+            "libs/ui/nested",
+            "docs",
+        ] {
+            let dir = root.path().join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("cuenv.yaml"), "env: {}\n").unwrap();
+        }
+        let workspace = WorkspaceConfig {
+            packages: vec!["apps/*".to_string(), "libs/*".to_string()],
+            exclude: vec!["apps/legacy".to_string()],
+            ..Default::default()
+        };
+
+        let packages = packages(root.path(), &workspace).unwrap();
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["apps:api", "apps:web", "libs:ui"]);
+
+        let filtered = filter(packages, &["apps:w*".to_string(), "libs/ui".to_string()]).unwrap();
+        let names: Vec<&str> = filtered.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["apps:web", "libs:ui"]);
+    }
+}
@@ -0,0 +1,408 @@
+//! Candidates for `cuenv __complete`
+//!
+//! The completion scripts pass the words typed after `cuenv`, the last one
+//! being completed, and print what this returns. Subcommands and options come
+//! from the clap definition, so they never go stale; task and environment
+//! names come from the package in the current directory, which is only
+//! evaluated when one of them is completed.
+
+use clap::Command;
+use cuenv_config::{
+    has_package, package_name, tags_from_env, CueParser, ParseOptions, ParseResult, TaskNode,
+    ENVIRONMENT_SEPARATOR,
+};
+use std::path::PathBuf;
+
+/// A completion, with the description shells that support one show next to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub value: String,
+    pub description: Option<String>,
+}
+
+impl Candidate {
+    fn new(value: impl Into<String>, description: Option<String>) -> Self {
+        Self {
+            value: value.into(),
+            description: description.filter(|d| !d.is_empty()),
+        }
+    }
+}
+
+impl std::fmt::Display for Candidate {
+    /// The `value<TAB>description` line the completion scripts read
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.description {
+            Some(description) => write!(f, "{}\t{}", self.value, description),
+            None => f.write_str(&self.value),
+        }
+    }
+}
+
+pub struct Completer {
+    command: Command,
+    dir: PathBuf,
+    /// The evaluated package, once a candidate needed it
+    package: Option<Option<ParseResult>>,
+}
+
+/// Where the words before the one being completed leave the command line
+struct Position<'a> {
+    command: &'a Command,
+    /// The option whose value is being completed
+    option: Option<&'a clap::Arg>,
+    /// Positional values given to the command so far
+    positionals: Vec<&'a str>,
+}
+
+impl Completer {
+    pub fn new(mut command: Command, dir: PathBuf) -> Self {
+        // Building propagates global options to every subcommand
+        command.build();
+        Self {
+            command,
+            dir,
+            package: None,
+        }
+    }
+
+    /// Candidates for the last of `words`, the arguments after `cuenv`
+    pub fn complete(&mut self, words: &[String]) -> Vec<Candidate> {
+        let (current, preceding) = match words.split_last() {
+            Some((current, preceding)) => (current.as_str(), preceding),
+            None => ("", &[][..]),
+        };
+
+        let command = self.command.clone();
+        let position = locate(&command, preceding);
+        let mut candidates = if let Some(option) = position.option {
+            self.values(option, current)
+        } else if let Some((name, value)) = current
+            .strip_prefix("--")
+            .and_then(|long| long.split_once('='))
+        {
+            match find_long(position.command, name) {
+                Some(option) => self
+                    .values(option, value)
+                    .into_iter()
+                    .map(|c| Candidate::new(format!("--{name}={}", c.value), c.description))
+                    .collect(),
+                None => Vec::new(),
+            }
+        } else if current.starts_with('-') {
+            options(position.command)
+        } else {
+            let mut candidates = subcommands(position.command);
+            candidates.extend(self.positional_values(&position, current));
+            candidates
+        };
+
+        candidates.retain(|candidate| candidate.value.starts_with(current));
+        candidates.sort_by(|a, b| a.value.cmp(&b.value));
+        candidates.dedup_by(|a, b| a.value == b.value);
+        candidates
+    }
+
+    /// Values for the positional argument after those already given
+    fn positional_values(&mut self, position: &Position, current: &str) -> Vec<Candidate> {
+        let positionals: Vec<&clap::Arg> = position
+            .command
+            .get_positionals()
+            .filter(|arg| !arg.is_hide_set())
+            .collect();
+        let index = position.positionals.len();
+        let arg = positionals.get(index).copied().or_else(|| {
+            positionals
+                .last()
+                .copied()
+                .filter(|arg| arg.get_num_args().is_some_and(|n| n.max_values() > 1))
+        });
+
+        match (position.command.get_name(), arg) {
+            // `cuenv task <group> <task>` runs a task of a group
+            ("task", Some(arg)) if arg.get_id() == "args" && index == 1 => {
+                self.tasks(Some(position.positionals[0]))
+            }
+            ("task", Some(arg)) if arg.get_id() == "args" => Vec::new(),
+            (_, Some(arg)) => self.values(arg, current),
+            (_, None) => Vec::new(),
+        }
+    }
+
+    /// Values an argument accepts
+    fn values(&mut self, arg: &clap::Arg, current: &str) -> Vec<Candidate> {
+        let possible: Vec<Candidate> = arg
+            .get_possible_values()
+            .into_iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| {
+                Candidate::new(value.get_name(), value.get_help().map(ToString::to_string))
+            })
+            .collect();
+        if !possible.is_empty() {
+            return possible;
+        }
+
+        match arg.get_id().as_str() {
+            "task_or_group" => self.tasks(None),
+            // Several environments are joined with `+`, so complete the last
+            "environment" | "environments" => {
+                let prefix = current
+                    .rfind(ENVIRONMENT_SEPARATOR)
+                    .map_or("", |i| &current[..=i]);
+                self.package()
+                    .map(|package| {
+                        package
+                            .environments
+                            .iter()
+                            .map(|name| Candidate::new(format!("{prefix}{name}"), None))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Tasks and groups of the package, or the tasks of a group
+    fn tasks(&mut self, group: Option<&str>) -> Vec<Candidate> {
+        let Some(package) = self.package() else {
+            return Vec::new();
+        };
+        let nodes = match group {
+            None => &package.task_nodes,
+            Some(group) => match package.task_nodes.get(group) {
+                Some(TaskNode::Group { tasks, .. }) => tasks,
+                _ => return Vec::new(),
+            },
+        };
+
+        nodes
+            .iter()
+            .map(|(name, node)| {
+                let description = match node {
+                    TaskNode::Task(task) => task.description.clone(),
+                    TaskNode::Group { description, .. } => description.clone(),
+                };
+                Candidate::new(name, description)
+            })
+            .collect()
+    }
+
+    /// The package in the directory, evaluated on first use. Completion
+    /// stays quiet about configurations that do not evaluate.
+    fn package(&mut self) -> Option<&ParseResult> {
+        let dir = &self.dir;
+        self.package
+            .get_or_insert_with(|| {
+                let package = package_name();
+                if !has_package(dir, &package) {
+                    return None;
+                }
+                let options = ParseOptions {
+                    tags: tags_from_env(),
+                    ..Default::default()
+                };
+                CueParser::eval_package_with_options(dir, &package, &options).ok()
+            })
+            .as_ref()
+    }
+
+    #[cfg(test)]
+    fn with_package(mut self, package: ParseResult) -> Self {
+        self.package = Some(Some(package));
+        self
+    }
+}
+
+/// Follow the words through subcommands and options
+fn locate<'a>(root: &'a Command, words: &'a [String]) -> Position<'a> {
+    let mut position = Position {
+        command: root,
+        option: None,
+        positionals: Vec::new(),
+    };
+    let mut options_ended = false;
+
+    for word in words {
+        if position.option.take().is_some() {
+            continue;
+        }
+        if options_ended || word == "-" || !word.starts_with('-') {
+            match position.command.find_subcommand(word) {
+                Some(subcommand) if position.positionals.is_empty() && !options_ended => {
+                    position.command = subcommand;
+                }
+                _ => position.positionals.push(word),
+            }
+            continue;
+        }
+
+        if word == "--" {
+            options_ended = true;
+        } else if let Some(long) = word.strip_prefix("--") {
+            if !long.contains('=') {
+                position.option = find_long(position.command, long).filter(|arg| takes_value(arg));
+            }
+        } else {
+            // Short flags can be combined, and only the last may take a value
+            // that is not attached to it
+            let short = word.chars().last();
+            if word.len() == 2 {
+                position.option = position
+                    .command
+                    .get_arguments()
+                    .find(|arg| arg.get_short() == short)
+                    .filter(|arg| takes_value(arg));
+            }
+        }
+    }
+    position
+}
+
+fn find_long<'a>(command: &'a Command, name: &str) -> Option<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(name))
+}
+
+fn takes_value(arg: &clap::Arg) -> bool {
+    arg.get_action().takes_values()
+}
+
+fn subcommands(command: &Command) -> Vec<Candidate> {
+    command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .flat_map(|subcommand| {
+            let about = subcommand.get_about().map(ToString::to_string);
+            std::iter::once(subcommand.get_name())
+                .chain(subcommand.get_visible_aliases())
+                .map(move |name| Candidate::new(name, about.clone()))
+        })
+        .collect()
+}
+
+fn options(command: &Command) -> Vec<Candidate> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .filter_map(|arg| {
+            let help = arg.get_help().map(ToString::to_string);
+            arg.get_long()
+                .map(|long| Candidate::new(format!("--{long}"), help))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Commands;
+    use clap::{Arg, Subcommand};
+    use cuenv_config::{TaskConfig, TaskGroupMode};
+    use std::collections::HashMap;
+
+    fn completer() -> Completer {
+        let command = Commands::augment_subcommands(
+            Command::new("cuenv").arg(
+                Arg::new("environment")
+                    .short('e')
+                    .long("env")
+                    .global(true)
+                    .help("Environment to use"),
+            ),
+        );
+
+        let task = |description: &str| {
+            TaskNode::Task(Box::new(TaskConfig {
+                description: Some(description.to_string()),
+                ..Default::default()
+            }))
+        };
+        let package = ParseResult {
+            task_nodes: HashMap::from([
+                ("build".to_string(), task("Build the project")),
+                (
+                    "ci".to_string(),
+                    TaskNode::Group {
+                        description: Some("Checks run in CI".to_string()),
+                        mode: TaskGroupMode::default(),
+                        tasks: HashMap::from([
+                            ("lint".to_string(), task("Lint")),
+                            ("test".to_string(), task("Run the tests")),
+                        ]),
+                    },
+                ),
+            ]),
+            environments: vec!["production".to_string(), "staging".to_string()],
+            ..Default::default()
+        };
+
+        Completer::new(command, PathBuf::from(".")).with_package(package)
+    }
+
+    fn complete(words: &[&str]) -> Vec<String> {
+        let words: Vec<String> = words.iter().map(ToString::to_string).collect();
+        completer()
+            .complete(&words)
+            .into_iter()
+            .map(|candidate| candidate.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_subcommands_and_aliases() {
+        let values = complete(&["ca"]);
+        assert_eq!(values, vec!["cache"]);
+
+        let values = complete(&["cache", ""]);
+        assert!(values.contains(&"clear".to_string()), "{values:?}");
+        assert!(values.contains(&"prune".to_string()), "{values:?}");
+        assert!(values.contains(&"cleanup".to_string()), "{values:?}");
+        assert!(values.contains(&"stats".to_string()), "{values:?}");
+
+        // Hidden commands are never offered
+        assert!(!complete(&[""]).iter().any(|value| value.starts_with('_')));
+    }
+
+    #[test]
+    fn test_task_names_come_from_the_package() {
+        let candidates = completer().complete(&["task".to_string(), String::new()]);
+        assert_eq!(
+            candidates,
+            vec![
+                Candidate::new("build", Some("Build the project".to_string())),
+                Candidate::new("ci", Some("Checks run in CI".to_string())),
+            ]
+        );
+        assert_eq!(candidates[0].to_string(), "build\tBuild the project");
+
+        assert_eq!(complete(&["t", "b"]), vec!["build"]);
+        assert_eq!(complete(&["task", "ci", ""]), vec!["lint", "test"]);
+        assert!(complete(&["task", "build", ""]).is_empty());
+        assert!(complete(&["task", "ci", "lint", ""]).is_empty());
+    }
+
+    #[test]
+    fn test_environment_names_come_from_the_package() {
+        assert_eq!(complete(&["task", "-e", ""]), vec!["production", "staging"]);
+        assert_eq!(complete(&["--env", "st"]), vec!["staging"]);
+        assert_eq!(complete(&["task", "--env=p"]), vec!["--env=production"]);
+        assert_eq!(
+            complete(&["env", "use", "staging+p"]),
+            vec!["staging+production"]
+        );
+        // The option's value is not taken for the task
+        assert_eq!(complete(&["task", "-e", "staging", "bu"]), vec!["build"]);
+    }
+
+    #[test]
+    fn test_options_and_possible_values() {
+        let values = complete(&["cache", "prune", "--"]);
+        assert!(values.contains(&"--older-than".to_string()), "{values:?}");
+        assert!(values.contains(&"--env".to_string()), "{values:?}");
+
+        assert_eq!(complete(&["completion", "fi"]), vec!["fish"]);
+    }
+}
@@ -1,9 +1,12 @@
 //! Shell completion generation for cuenv
 
+mod candidates;
 mod shells;
 
+use candidates::Completer;
+use clap::Command;
 use cuenv_core::Result;
-use shells::{bash, elvish, fish, powershell, zsh};
+use shells::{bash, elvish, fish, nu, powershell, zsh};
 
 /// Generate shell completion script for the specified shell
 pub fn generate_completion(shell: &str) -> Result<()> {
@@ -11,12 +14,20 @@ pub fn generate_completion(shell: &str) -> Result<()> {
         "bash" => bash::generate(),
         "zsh" => zsh::generate(),
         "fish" => fish::generate(),
+        "nu" | "nushell" => nu::generate(),
         "powershell" | "pwsh" => powershell::generate(),
         "elvish" => elvish::generate(),
-        _ => {
-            eprintln!("Unsupported shell: {shell}");
-            eprintln!("Supported shells: bash, zsh, fish, powershell, elvish");
-            std::process::exit(1);
-        }
+        _ => Err(cuenv_core::Error::usage(format!(
+            "Unsupported shell: {shell}\nSupported shells: bash, zsh, fish, nu, powershell, elvish"
+        ))),
     }
 }
+
+/// Print the completions for the words after `cuenv`, one per line
+pub fn complete(command: Command, words: &[String]) -> Result<()> {
+    let dir = std::env::current_dir()?;
+    for candidate in Completer::new(command, dir).complete(words) {
+        println!("{candidate}");
+    }
+    Ok(())
+}
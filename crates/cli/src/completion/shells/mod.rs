@@ -3,5 +3,6 @@
 pub mod bash;
 pub mod elvish;
 pub mod fish;
+pub mod nu;
 pub mod powershell;
 pub mod zsh;
@@ -0,0 +1,32 @@
+//! Nushell completion generator
+
+use cuenv_core::Result;
+
+/// Generate nushell completion script
+pub fn generate() -> Result<()> {
+    let script = r#"
+# cuenv completions, chained with any external completer already configured
+let cuenv_completer = {|spans|
+    ^cuenv __complete -- ...($spans | skip 1)
+    | lines
+    | each {|line|
+        # Candidates are printed as value<TAB>description
+        let parts = ($line | split row "\t")
+        { value: $parts.0, description: ($parts.1? | default "") }
+    }
+}
+
+let previous_completer = $env.config.completions.external.completer?
+
+$env.config.completions.external.enable = true
+$env.config.completions.external.completer = {|spans|
+    if ($spans.0 | path basename) == "cuenv" {
+        do $cuenv_completer $spans
+    } else if $previous_completer != null {
+        do $previous_completer $spans
+    }
+}
+"#;
+    print!("{script}");
+    Ok(())
+}
@@ -1,11 +1,23 @@
-use cuenv_core::{Error, Result};
+use cuenv_config::{approved_files, package_name, UserConfig};
+use cuenv_core::{Error, Result, ENV_CUE_FILENAME};
 use cuenv_utils::XdgPaths;
 use sha2::{Digest, Sha256};
 use std::env;
-use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Read, Write};
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 
+/// Trust state of a directory's env.cue file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustStatus {
+    /// The directory was allowed and env.cue is unchanged since
+    Allowed,
+    /// The directory was allowed, but env.cue has changed since
+    Changed,
+    /// The directory was never allowed, or has been denied
+    NotAllowed,
+}
+
 pub struct DirectoryManager;
 
 impl DirectoryManager {
@@ -30,34 +42,28 @@ impl DirectoryManager {
             .canonicalize()
             .map_err(|e| Error::file_system(dir.to_path_buf(), "canonicalize path", e))?;
 
-        // Check if already allowed
+        // Check if already allowed with the current contents
         if self.is_directory_allowed(&canonical_dir)? {
-            return Ok(()); // Already allowed
+            return Ok(());
         }
 
-        // Calculate hash of env.cue if it exists
-        let env_cue = canonical_dir.join("env.cue");
-        let hash = if env_cue.exists() {
-            self.calculate_file_hash(&env_cue)?
-        } else {
-            String::new()
-        };
+        // Calculate hash of the configuration if there is one
+        let hash = self.trust_hash(&canonical_dir)?;
 
-        // Append to allowed file with hash
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&allowed_file)
-            .map_err(|e| Error::file_system(allowed_file.clone(), "open allowed file", e))?;
+        // Drop entries recorded for previous contents of this directory
+        let canonical_str = canonical_dir.to_string_lossy();
+        let mut lines: Vec<String> = self
+            .read_allowed_lines(&allowed_file)?
+            .into_iter()
+            .filter(|line| !matches!(parse_entry(line), Some((path, _)) if path == canonical_str))
+            .collect();
 
-        if hash.is_empty() {
-            writeln!(file, "{}", canonical_dir.display())
-        } else {
-            writeln!(file, "{}:{}", canonical_dir.display(), hash)
-        }
-        .map_err(|e| Error::file_system(allowed_file, "write to allowed file", e))?;
+        lines.push(match hash {
+            Some(hash) => format!("{canonical_str}:{hash}"),
+            None => canonical_str.to_string(),
+        });
 
-        Ok(())
+        self.write_allowed_lines(&allowed_file, &lines)
     }
 
     pub fn deny_directory(&self, dir: &Path) -> Result<()> {
@@ -71,82 +77,136 @@ impl DirectoryManager {
         let canonical_dir = dir
             .canonicalize()
             .map_err(|e| Error::file_system(dir.to_path_buf(), "canonicalize path", e))?;
+        let canonical_str = canonical_dir.to_string_lossy();
 
-        // Read all allowed directories
-        let file = fs::File::open(&allowed_file)
-            .map_err(|e| Error::file_system(allowed_file.clone(), "open allowed file", e))?;
-        let reader = BufReader::new(file);
-
-        let mut allowed_dirs: Vec<String> = Vec::new();
-        for line in reader.lines() {
-            let line =
-                line.map_err(|e| Error::file_system(allowed_file.clone(), "read allowed file", e))?;
-            let line = line.trim();
-            if !line.is_empty() && line != canonical_dir.to_string_lossy() {
-                allowed_dirs.push(line.to_string());
-            }
-        }
-
-        // Write back the filtered list
-        fs::write(&allowed_file, allowed_dirs.join("\n") + "\n")
-            .map_err(|e| Error::file_system(allowed_file, "write allowed file", e))?;
+        // Keep every entry that doesn't belong to this directory
+        let allowed_dirs: Vec<String> = self
+            .read_allowed_lines(&allowed_file)?
+            .into_iter()
+            .filter(|line| !matches!(parse_entry(line), Some((path, _)) if path == canonical_str))
+            .collect();
 
-        Ok(())
+        self.write_allowed_lines(&allowed_file, &allowed_dirs)
     }
 
     pub fn is_directory_allowed(&self, dir: &Path) -> Result<bool> {
-        let allowed_file = self.get_allowed_file()?;
-
-        if !allowed_file.exists() {
-            return Ok(false);
-        }
+        Ok(self.trust_status(dir)? == TrustStatus::Allowed)
+    }
 
+    /// Determine whether a directory's env.cue may be loaded
+    ///
+    /// A directory is only trusted while its env.cue, and the configuration
+    /// of every ancestor it may inherit from, still match the hash recorded
+    /// by `cuenv env allow`; any edit requires allowing it again.
+    /// Directories under those listed as `trusted` in the user configuration
+    /// are always trusted.
+    pub fn trust_status(&self, dir: &Path) -> Result<TrustStatus> {
         // Get canonical path
         let canonical_dir = dir
             .canonicalize()
             .map_err(|e| Error::file_system(dir.to_path_buf(), "canonicalize path", e))?;
+        if UserConfig::get().is_trusted(&canonical_dir) {
+            return Ok(TrustStatus::Allowed);
+        }
+
+        let allowed_file = self.get_allowed_file()?;
+        if !allowed_file.exists() {
+            return Ok(TrustStatus::NotAllowed);
+        }
+
+        let canonical_str = canonical_dir.to_string_lossy();
+
+        let lines = self.read_allowed_lines(&allowed_file)?;
+        let recorded: Vec<Option<&str>> = lines
+            .iter()
+            .filter_map(|line| parse_entry(line))
+            .filter(|(path, _)| *path == canonical_str)
+            .map(|(_, hash)| hash)
+            .collect();
+
+        let actual_hash = self.trust_hash(&canonical_dir)?;
+        Ok(evaluate_trust(&recorded, actual_hash.as_deref()))
+    }
 
-        // Read allowed directories
-        let file = fs::File::open(&allowed_file)
-            .map_err(|e| Error::file_system(allowed_file.clone(), "open allowed file", e))?;
-        let reader = BufReader::new(file);
-
-        for line in reader.lines() {
-            let line =
-                line.map_err(|e| Error::file_system(allowed_file.clone(), "read allowed file", e))?;
-            let line = line.trim();
-
-            // Parse line which can be either "path" or "path:hash"
-            let (allowed_path, allowed_hash) = if let Some(colon_pos) = line.rfind(':') {
-                (
-                    line[..colon_pos].to_string(),
-                    Some(line[colon_pos + 1..].to_string()),
-                )
-            } else {
-                (line.to_string(), None)
-            };
-
-            if allowed_path == canonical_dir.to_string_lossy() {
-                // Path matches, now check hash if present
-                if let Some(expected_hash) = allowed_hash {
-                    let env_cue = canonical_dir.join("env.cue");
-                    if env_cue.exists() {
-                        let actual_hash = self.calculate_file_hash(&env_cue)?;
-                        if actual_hash == expected_hash {
-                            return Ok(true);
-                        }
-                        // Hash doesn't match, continue checking other entries
-                    } else {
-                        // env.cue doesn't exist but hash was expected, continue checking
-                    }
-                } else {
-                    // No hash requirement, directory is allowed
-                    return Ok(true);
+    /// Hash of the configuration of `dir` and of the ancestors whose
+    /// configuration is merged into it
+    ///
+    /// Every ancestor with a configuration is covered, as whether one ends
+    /// the hierarchy with `root: true` is only known once it is evaluated.
+    /// Without any, this is the hash of the directory's own configuration,
+    /// so approvals recorded before ancestors were covered stay valid.
+    fn trust_hash(&self, dir: &Path) -> Result<Option<String>> {
+        let Some(leaf) = self.env_file_hash(dir)? else {
+            return Ok(None);
+        };
+        let mut ancestors = Vec::new();
+        for ancestor in dir.ancestors().skip(1) {
+            if let Some(hash) = self.env_file_hash(ancestor)? {
+                ancestors.push(format!("{}:{hash}\n", ancestor.display()));
+            }
+        }
+        if ancestors.is_empty() {
+            return Ok(Some(leaf));
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{leaf}\n"));
+        for ancestor in ancestors {
+            hasher.update(ancestor);
+        }
+        Ok(Some(format!("{:x}", hasher.finalize())))
+    }
+
+    /// Hash of every file in the directory's package, or of its JSON or
+    /// YAML configuration, and of its env.local.cue
+    ///
+    /// A package that is just env.cue hashes like the file itself, so
+    /// approvals recorded before packages could span files stay valid.
+    fn env_file_hash(&self, dir: &Path) -> Result<Option<String>> {
+        let files = approved_files(dir, &package_name());
+        match files.as_slice() {
+            [] => Ok(None),
+            [file] if file.file_name() == Some(std::ffi::OsStr::new(ENV_CUE_FILENAME)) => {
+                self.calculate_file_hash(file).map(Some)
+            }
+            _ => {
+                let mut hasher = Sha256::new();
+                for file in &files {
+                    let name = file.file_name().unwrap_or_default().to_string_lossy();
+                    hasher.update(format!("{name}:{}\n", self.calculate_file_hash(file)?));
                 }
+                Ok(Some(format!("{:x}", hasher.finalize())))
             }
         }
+    }
 
-        Ok(false)
+    fn read_allowed_lines(&self, allowed_file: &Path) -> Result<Vec<String>> {
+        if !allowed_file.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = fs::File::open(allowed_file)
+            .map_err(|e| Error::file_system(allowed_file.to_path_buf(), "open allowed file", e))?;
+
+        BufReader::new(file)
+            .lines()
+            .map(|line| {
+                line.map(|l| l.trim().to_string()).map_err(|e| {
+                    Error::file_system(allowed_file.to_path_buf(), "read allowed file", e)
+                })
+            })
+            .filter(|line| !matches!(line, Ok(l) if l.is_empty()))
+            .collect()
+    }
+
+    fn write_allowed_lines(&self, allowed_file: &Path, lines: &[String]) -> Result<()> {
+        let contents = if lines.is_empty() {
+            String::new()
+        } else {
+            lines.join("\n") + "\n"
+        };
+        fs::write(allowed_file, contents)
+            .map_err(|e| Error::file_system(allowed_file.to_path_buf(), "write allowed file", e))
     }
 
     fn get_allowed_file(&self) -> Result<PathBuf> {
@@ -205,6 +265,36 @@ impl DirectoryManager {
     }
 }
 
+/// Parse an allow-list line of the form `path` or `path:sha256`
+fn parse_entry(line: &str) -> Option<(&str, Option<&str>)> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    // Only treat the suffix as a hash when it looks like one, so paths
+    // containing ':' (e.g. Windows drive letters) are parsed correctly
+    match line.rsplit_once(':') {
+        Some((path, hash)) if hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            Some((path, Some(hash)))
+        }
+        _ => Some((line, None)),
+    }
+}
+
+/// Compare the hashes recorded for a directory with its current env.cue hash
+fn evaluate_trust(recorded: &[Option<&str>], actual_hash: Option<&str>) -> TrustStatus {
+    if recorded.is_empty() {
+        return TrustStatus::NotAllowed;
+    }
+
+    if recorded.contains(&actual_hash) {
+        TrustStatus::Allowed
+    } else {
+        TrustStatus::Changed
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,4 +307,63 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_entry() {
+        let hash = "a".repeat(64);
+        let line = format!("/home/user/project:{hash}");
+
+        assert_eq!(
+            parse_entry(&line),
+            Some(("/home/user/project", Some(hash.as_str())))
+        );
+        assert_eq!(
+            parse_entry("/home/user/project"),
+            Some(("/home/user/project", None))
+        );
+        assert_eq!(parse_entry(r"C:\project"), Some((r"C:\project", None)));
+        assert_eq!(parse_entry("   "), None);
+    }
+
+    #[test]
+    fn test_trust_hash_covers_ancestors() -> Result<()> {
+        let temp = tempfile::tempdir().unwrap();
+        let child = temp.path().join("child");
+        fs::create_dir(&child).unwrap();
+        fs::write(child.join("env.cue"), "package cuenv\n").unwrap();
+        let manager = DirectoryManager::new();
+
+        // Without ancestors the hash is that of env.cue itself
+        let own = manager.calculate_file_hash(&child.join("env.cue"))?;
+        assert_eq!(manager.trust_hash(&child)?, Some(own.clone()));
+
+        fs::write(temp.path().join("env.cue"), "package cuenv\n").unwrap();
+        let inherited = manager.trust_hash(&child)?;
+        assert_ne!(inherited, Some(own));
+
+        fs::write(
+            temp.path().join("env.cue"),
+            "package cuenv\nhooks: onEnter: []\n",
+        )
+        .unwrap();
+        assert_ne!(manager.trust_hash(&child)?, inherited);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_trust() {
+        assert_eq!(evaluate_trust(&[], Some("abc")), TrustStatus::NotAllowed);
+        assert_eq!(
+            evaluate_trust(&[Some("abc")], Some("abc")),
+            TrustStatus::Allowed
+        );
+        assert_eq!(
+            evaluate_trust(&[Some("abc")], Some("def")),
+            TrustStatus::Changed
+        );
+        // An env.cue created after allowing an empty directory must be reviewed
+        assert_eq!(evaluate_trust(&[None], Some("abc")), TrustStatus::Changed);
+        assert_eq!(evaluate_trust(&[None], None), TrustStatus::Allowed);
+    }
 }
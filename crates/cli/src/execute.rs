@@ -16,26 +16,85 @@ impl Commands {
                 verbose,
                 output,
                 trace_output,
+                profile,
+                summary,
+                json,
+                table,
+                tagged,
+                package,
+                affected,
+                since,
             } => {
+                let list_options = crate::commands::task::ListOptions {
+                    json,
+                    table,
+                    tags: tagged,
+                };
+                let options = crate::commands::task::TaskRunOptions {
+                    environment,
+                    capabilities,
+                    audit,
+                    output_format: output,
+                    trace_output,
+                    profile,
+                    summary,
+                    affected_since: affected.then(|| {
+                        since.unwrap_or_else(|| crate::commands::task::DEFAULT_SINCE.to_string())
+                    }),
+                };
+                if !package.is_empty() {
+                    return crate::commands::task::execute_in_packages(
+                        &config.working_dir,
+                        &package,
+                        task_or_group,
+                        args,
+                        verbose,
+                        list_options,
+                        &options,
+                    );
+                }
                 crate::commands::task::execute_task_command(
                     Arc::clone(&config),
                     task_or_group,
                     args,
-                    environment,
-                    capabilities,
-                    audit,
                     verbose,
-                    output,
-                    trace_output,
+                    list_options,
+                    options,
                 )
                 .await
             }
             Commands::Env { command } => command.execute().await,
             Commands::Shell { command } => command.execute().await,
-            Commands::Cache { command } => command.execute().await,
+            Commands::Tmux { command } => command.execute().await,
+            Commands::Cache { command } => command.execute(&config).await,
+            Commands::Artifact { command } => command.execute().await,
+            Commands::Ci { command } => command.execute(&config),
+            Commands::Devcontainer { command } => command.execute(&config),
+            Commands::Hooks { command } => command.execute(&config),
+            Commands::Ide { command } => command.execute(&config).await,
+            Commands::K8s { command } => command.execute(&config).await,
+            #[cfg(unix)]
+            Commands::Daemon { command } => command.execute().await,
+            Commands::Migrate { command } => command.execute(&config),
+            Commands::Secret { command } => command.execute().await,
+            Commands::Service { command } => command.execute(&config).await,
+            Commands::Workspace { command } => command.execute(&config),
             Commands::Internal { command } => command.execute().await,
 
-            Commands::Init { force } => crate::commands::init::execute(config, force).await,
+            Commands::Init { force, hook } => {
+                crate::commands::init::execute(config, force, hook).await
+            }
+            Commands::Import { from, file, print } => {
+                crate::commands::import::execute(&config, from, file, print)
+            }
+            Commands::Fmt { paths, check, json } => {
+                crate::commands::fmt::execute(paths, check, json).await
+            }
+            Commands::Vet { directory, json } => {
+                crate::commands::vet::execute(directory, json).await
+            }
+            Commands::Doctor { json } => crate::commands::doctor::execute(json).await,
+            Commands::Config { json } => crate::commands::config::execute(&config, json),
             Commands::Discover {
                 max_depth,
                 load,
@@ -45,19 +104,17 @@ impl Commands {
             Commands::Exec {
                 environment,
                 capabilities,
-                command,
-                args,
                 audit,
+                command,
             } => {
-                crate::commands::exec::execute(
-                    config,
-                    environment,
-                    capabilities,
-                    command,
-                    args,
-                    audit,
-                )
-                .await
+                crate::commands::exec::execute(config, environment, capabilities, command, audit)
+                    .await
+            }
+            Commands::Prompt { json } => crate::commands::prompt::execute(json),
+            Commands::Complete { words } => {
+                use clap::Subcommand;
+                let command = Commands::augment_subcommands(clap::Command::new("cuenv"));
+                crate::completion::complete(command, &words)
             }
             Commands::CompleteTasks => complete_tasks(config).await,
             Commands::CompleteEnvironments => complete_environments(config).await,
@@ -0,0 +1,95 @@
+//! How cuenv exits on an error
+//!
+//! The exit code tells the class of failure apart, as documented in the
+//! commands reference: a failed task passes its own exit code through, and
+//! cuenv's own errors exit with the code of their [`ErrorKind`]. With
+//! `--error-format json`, the error is printed as one JSON object on stderr
+//! instead of a report, for wrappers to read.
+//!
+//! [`ErrorKind`]: cuenv_core::ErrorKind
+
+use cuenv_core::style::{self, Role};
+use cuenv_core::Error;
+use cuenv_utils::tracing::{Level, LogFormat};
+use std::sync::OnceLock;
+
+/// How the error cuenv exits on is printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Invalid error format '{format}', expected text or json"
+            )),
+        }
+    }
+}
+
+static ERROR_FORMAT: OnceLock<ErrorFormat> = OnceLock::new();
+
+/// Select how the error cuenv exits on is printed, once at startup
+pub fn set_error_format(format: ErrorFormat) {
+    let _ = ERROR_FORMAT.set(format);
+}
+
+fn error_format() -> ErrorFormat {
+    ERROR_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Print `error` and exit with the code of its class
+///
+/// Errors in CUE files are first shown against the source, like compiler
+/// errors. Error messages can quote values, so they go through the secret
+/// masker like all other output.
+pub fn with_error(error: Error) -> ! {
+    let code = error.exit_code();
+
+    if error_format() == ErrorFormat::Json {
+        let json = serde_json::json!({ "error": error.to_json() });
+        eprintln!("{}", cuenv_core::masking::mask(&json.to_string()));
+    } else if matches!(error, Error::TaskFailed { .. }) {
+        // The task's failure was reported while it ran
+    } else if cuenv_utils::tracing::log_format() == LogFormat::Json {
+        // JSON logs get the error as a line of its own rather than a report
+        for diagnostic in error.diagnostics() {
+            cuenv_utils::tracing::message(Level::ERROR, &diagnostic.to_string());
+        }
+        cuenv_utils::tracing::message(Level::ERROR, &error.to_string());
+    } else {
+        let painter = style::stderr();
+        for diagnostic in error.diagnostics() {
+            let diagnostic = diagnostic.render(&painter);
+            eprintln!("{}\n", cuenv_core::masking::mask(&diagnostic));
+        }
+        let report: eyre::Report = error.into();
+        eprintln!(
+            "{} {}",
+            painter.bold(Role::Failure, "Error:"),
+            cuenv_core::masking::mask(&format!("{report:?}"))
+        );
+    }
+
+    std::process::exit(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_format() {
+        assert_eq!("json".parse(), Ok(ErrorFormat::Json));
+        assert_eq!("text".parse(), Ok(ErrorFormat::Text));
+        assert!("yaml".parse::<ErrorFormat>().is_err());
+    }
+}
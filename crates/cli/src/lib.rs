@@ -7,4 +7,4 @@ pub mod platform;
 
 // Re-export commonly used types
 pub use commands::Commands;
-pub use directory::DirectoryManager;
+pub use directory::{DirectoryManager, TrustStatus};
@@ -1,12 +1,18 @@
 use clap::Parser;
 use cuenv_cache::CacheMode;
 use cuenv_config::{ConfigLoader, RuntimeOptions};
+use cuenv_core::constants::{
+    CUENV_FILE_VAR, CUENV_INSECURE_ALLOW_VAR, CUENV_LOG_FORMAT_VAR, CUENV_RUN_ID_VAR,
+    CUENV_TAGS_VAR,
+};
+use cuenv_utils::tracing::{LogFormat, Verbosity};
 use std::env;
 
 mod commands;
 mod completion;
 mod directory;
 mod execute;
+mod exit;
 mod monorepo;
 mod platform;
 
@@ -33,10 +39,24 @@ struct Cli {
     #[arg(short = 'c', long = "capability", global = true)]
     capabilities: Vec<String>,
 
+    /// Set a CUE @tag() value as name=value (can be specified multiple times)
+    #[arg(short = 't', long = "tag", global = true, value_parser = cuenv_config::parse_tag)]
+    tags: Vec<String>,
+
+    /// File name a configuration is discovered by, instead of env.cue (can be
+    /// specified multiple times, in order of preference)
+    #[arg(long = "file", global = true)]
+    files: Vec<String>,
+
     /// Run in audit mode to see file and network access without restrictions
     #[arg(long, global = true)]
     audit: bool,
 
+    /// Run tasks without the access restrictions they ask for when this
+    /// system cannot enforce them, instead of failing
+    #[arg(long, global = true)]
+    insecure_allow: bool,
+
     /// Output format for task execution (tui, spinner, simple, tree)
     #[arg(long, value_parser = ["tui", "spinner", "simple", "tree"])]
     output_format: Option<String>,
@@ -45,14 +65,44 @@ struct Cli {
     #[arg(long)]
     trace_output: Option<bool>,
 
+    /// Format of messages for the user (text, or json for one JSON object
+    /// per line on stderr)
+    #[arg(long, global = true, value_parser = ["text", "json"])]
+    log_format: Option<String>,
+
+    /// Format of the error cuenv exits on (text, or json for one JSON object
+    /// with its kind, exit code and context on stderr)
+    #[arg(long, global = true, default_value = "text")]
+    error_format: exit::ErrorFormat,
+
+    /// Log diagnostics to stderr (-v for debug, -vv for trace); CUENV_LOG
+    /// selects them per module instead
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Print only warnings and errors
+    #[arg(short, long)]
+    quiet: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
-    // Parse command-line arguments
-    let cli = Cli::parse();
+    // Parse command-line arguments; help and version exit successfully,
+    // other errors with the usage exit code
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        if !e.use_stderr() {
+            e.exit();
+        }
+        let _ = e.print();
+        std::process::exit(cuenv_core::errors::exit_code::USAGE)
+    });
+    exit::set_error_format(cli.error_format);
+
+    // Colors and theme of config.toml, under NO_COLOR and CLICOLOR_FORCE
+    cuenv_config::UserConfig::get().init_style();
 
     // Build runtime options from CLI arguments
     let runtime = RuntimeOptions {
@@ -81,6 +131,24 @@ async fn main() -> eyre::Result<()> {
         env::set_var("CUENV_CACHE_ENABLED", enabled.to_string());
     }
 
+    // Tasks run by child cuenv processes are allowed the same
+    if cli.insecure_allow {
+        env::set_var(CUENV_INSECURE_ALLOW_VAR, "1");
+    }
+
+    // Tags reach every evaluation, including those of child cuenv processes
+    if !cli.tags.is_empty() {
+        env::set_var(CUENV_TAGS_VAR, cuenv_config::join_tags(&cli.tags));
+    }
+
+    if !cli.files.is_empty() {
+        env::set_var(
+            CUENV_FILE_VAR,
+            cli.files
+                .join(&cuenv_config::FILE_NAME_SEPARATOR.to_string()),
+        );
+    }
+
     // Determine the command to execute
     let command = match cli.command {
         Some(cmd) => cmd,
@@ -92,13 +160,76 @@ async fn main() -> eyre::Result<()> {
         }
     };
 
+    // Child cuenv processes log in the same format, under the same run ID
+    let log_format: LogFormat = match cli.log_format {
+        Some(format) => format.parse(),
+        None => env::var(CUENV_LOG_FORMAT_VAR).map_or(Ok(LogFormat::Text), |format| format.parse()),
+    }
+    .map_err(|e| eyre::eyre!("{CUENV_LOG_FORMAT_VAR}: {e}"))?;
+    env::set_var(CUENV_LOG_FORMAT_VAR, log_format.as_str());
+    env::set_var(CUENV_RUN_ID_VAR, cuenv_utils::tracing::run_id());
+
+    let profile = matches!(
+        &command,
+        Commands::Task {
+            profile: Some(_),
+            ..
+        }
+    );
+    let verbosity = Verbosity::from_flags(cli.verbose, cli.quiet);
+    cuenv_utils::tracing::init_run(log_format, verbosity, profile)
+        .map_err(|e| eyre::eyre!("Failed to initialize logging: {e}"))?;
+
+    // fmt, vet and doctor report problems in the configuration, so they
+    // must run without loading it first; completion and the prompt must not
+    // fail on them either
+    let command = match command {
+        Commands::Fmt { paths, check, json } => {
+            return commands::fmt::execute(paths, check, json)
+                .await
+                .map_err(report_error);
+        }
+        Commands::Vet { directory, json } => {
+            return commands::vet::execute(directory, json)
+                .await
+                .map_err(report_error);
+        }
+        Commands::Doctor { json } => {
+            return commands::doctor::execute(json).await.map_err(report_error);
+        }
+        // Completion runs on every <TAB>, so it only evaluates the package
+        // when a task or environment name is completed
+        Commands::Complete { words } => {
+            use clap::CommandFactory;
+            return completion::complete(Cli::command(), &words).map_err(report_error);
+        }
+        // Prompts render on every command, so the status is read from the
+        // state the shell hook exported
+        Commands::Prompt { json } => {
+            return commands::prompt::execute(json).map_err(report_error);
+        }
+        command => command,
+    };
+
+    // A daemon started with --tasks runs plain task runs with what it keeps
+    // in memory, so the configuration is not loaded here
+    #[cfg(unix)]
+    if let Some(result) = commands::task::daemon::forward(&command) {
+        return result.map_err(report_error);
+    }
+
     // Load configuration once at startup
     let config = ConfigLoader::new()
         .runtime(runtime)
         .load()
-        .await?
+        .await
+        .map_err(report_error)?
         .into_arc();
 
-    // Execute the command with configuration
-    command.execute(config).await.map_err(Into::into)
+    command.execute(config).await.map_err(report_error)
+}
+
+/// Exit on an error, printed as `--error-format` selects
+fn report_error(e: cuenv_core::Error) -> eyre::Report {
+    exit::with_error(e)
 }
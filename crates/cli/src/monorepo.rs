@@ -116,6 +116,22 @@ async fn execute_cross_package_task(
     _task_args: &[String],
     _audit: bool,
 ) -> Result<i32> {
+    let registry = task_registry(current_dir).await?;
+
+    // Validate all dependencies
+    registry.validate_all_dependencies()?;
+
+    // Create executor with the monorepo registry
+    let mut executor = TaskExecutor::new_with_registry(registry).await?;
+
+    // Execute the task
+    executor.execute(task_ref).await?;
+
+    Ok(0)
+}
+
+/// The tasks of every package of the monorepo `current_dir` is in
+pub async fn task_registry(current_dir: &Path) -> Result<MonorepoTaskRegistry> {
     // Find the module root
     let mut discovery = PackageDiscovery::new(32);
 
@@ -141,18 +157,7 @@ async fn execute_cross_package_task(
         .collect();
 
     // Build the task registry
-    let registry = MonorepoTaskRegistry::from_packages(task_packages)?;
-
-    // Validate all dependencies
-    registry.validate_all_dependencies()?;
-
-    // Create executor with the monorepo registry
-    let mut executor = TaskExecutor::new_with_registry(registry).await?;
-
-    // Execute the task
-    executor.execute(task_ref).await?;
-
-    Ok(0)
+    MonorepoTaskRegistry::from_packages(task_packages)
 }
 
 /// Check if we're in a monorepo context
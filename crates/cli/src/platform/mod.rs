@@ -49,9 +49,9 @@ impl Shell {
         }
     }
 
-    /// Check if this is a Unix shell
+    /// Check if this shell runs on Unix, which PowerShell does as well
     pub const fn is_unix(&self) -> bool {
-        matches!(self, Self::Bash | Self::Zsh | Self::Fish)
+        matches!(self, Self::Bash | Self::Zsh | Self::Fish | Self::Pwsh)
     }
 
     /// Check if this is a Windows shell
@@ -1,6 +1,6 @@
-use super::{PlatformOps, Shell};
 #[cfg(test)]
 use super::{_escape_cmd_value, _escape_powershell_value, escape_shell_value, ExportFormat};
+use super::{PlatformOps, Shell};
 use std::collections::HashMap;
 use std::env;
 
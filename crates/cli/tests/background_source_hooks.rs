@@ -39,8 +39,8 @@ env: {
     let cuenv_bin = env!("CARGO_BIN_EXE_cuenv");
 
     // Step 1: Allow the directory
-    let output = Command::new(&cuenv_bin)
-        .args(&["env", "allow", temp_path.to_str().unwrap()])
+    let output = Command::new(cuenv_bin)
+        .args(["env", "allow", temp_path.to_str().unwrap()])
         .output()
         .expect("Failed to run cuenv env allow");
 
@@ -61,7 +61,7 @@ env: {
     // Start hooks in a background thread
     let handle = std::thread::spawn(move || {
         Command::new(&cuenv_bin_clone)
-            .args(&["env", "allow", temp_path_clone.to_str().unwrap()])
+            .args(["env", "allow", temp_path_clone.to_str().unwrap()])
             .output()
             .expect("Failed to run cuenv env allow in background");
     });
@@ -70,8 +70,8 @@ env: {
     std::thread::sleep(Duration::from_millis(500));
 
     // Step 3: Check status while hooks are running
-    let output = Command::new(&cuenv_bin)
-        .args(&["env", "status", "--hooks"])
+    let output = Command::new(cuenv_bin)
+        .args(["env", "status", "--hooks"])
         .output()
         .expect("Failed to run cuenv env status");
 
@@ -85,8 +85,8 @@ env: {
     handle.join().expect("Background thread panicked");
 
     // Step 5: Run shell hook to capture environment
-    let output = Command::new(&cuenv_bin)
-        .args(&["shell", "hook", "bash"])
+    let output = Command::new(cuenv_bin)
+        .args(["shell", "hook", "bash"])
         .current_dir(temp_path)
         .output()
         .expect("Failed to run cuenv shell hook");
@@ -96,14 +96,14 @@ env: {
     // Check if environment was captured
     if shell_output.contains("TEST_BG_VAR") {
         assert!(
-            shell_output.contains("export TEST_BG_VAR=\"hook_completed\""),
+            shell_output.contains("export TEST_BG_VAR=hook_completed"),
             "Environment variable not properly exported: {}",
             shell_output
         );
 
         // Step 6: Run shell hook again to verify it was cleared
-        let output = Command::new(&cuenv_bin)
-            .args(&["shell", "hook", "bash"])
+        let output = Command::new(cuenv_bin)
+            .args(["shell", "hook", "bash"])
             .current_dir(temp_path)
             .output()
             .expect("Failed to run cuenv shell hook second time");
@@ -157,8 +157,8 @@ env: {
     let cuenv_bin = env!("CARGO_BIN_EXE_cuenv");
 
     // Allow and run hooks
-    let output = Command::new(&cuenv_bin)
-        .args(&["env", "allow", temp_path.to_str().unwrap()])
+    let output = Command::new(cuenv_bin)
+        .args(["env", "allow", temp_path.to_str().unwrap()])
         .output()
         .expect("Failed to run cuenv env allow");
 
@@ -168,8 +168,8 @@ env: {
     std::thread::sleep(Duration::from_millis(500));
 
     // Check if environment was captured
-    let output = Command::new(&cuenv_bin)
-        .args(&["shell", "hook", "bash"])
+    let output = Command::new(cuenv_bin)
+        .args(["shell", "hook", "bash"])
         .current_dir(temp_path)
         .output()
         .expect("Failed to run cuenv shell hook");
@@ -179,7 +179,7 @@ env: {
     // With ENABLE_HOOK=true, the variable should be exported
     if shell_output.contains("CONSTRAINED_VAR") {
         assert!(
-            shell_output.contains("export CONSTRAINED_VAR=\"test_value\""),
+            shell_output.contains("export CONSTRAINED_VAR=test_value"),
             "Constrained hook should export variable when condition is met"
         );
     }
@@ -223,8 +223,8 @@ env: {
     let cuenv_bin = env!("CARGO_BIN_EXE_cuenv");
 
     // Allow and run hooks
-    let output = Command::new(&cuenv_bin)
-        .args(&["env", "allow", temp_path.to_str().unwrap()])
+    let output = Command::new(cuenv_bin)
+        .args(["env", "allow", temp_path.to_str().unwrap()])
         .output()
         .expect("Failed to run cuenv env allow");
 
@@ -234,8 +234,8 @@ env: {
     std::thread::sleep(Duration::from_millis(500));
 
     // Check captured environment
-    let output = Command::new(&cuenv_bin)
-        .args(&["shell", "hook", "bash"])
+    let output = Command::new(cuenv_bin)
+        .args(["shell", "hook", "bash"])
         .current_dir(temp_path)
         .output()
         .expect("Failed to run cuenv shell hook");
@@ -245,11 +245,11 @@ env: {
     // Both variables should be present if hooks completed
     if shell_output.contains("VAR1") || shell_output.contains("VAR2") {
         assert!(
-            shell_output.contains("export VAR1=\"value1\""),
+            shell_output.contains("export VAR1=value1"),
             "First hook variable missing"
         );
         assert!(
-            shell_output.contains("export VAR2=\"value2\""),
+            shell_output.contains("export VAR2=value2"),
             "Second hook variable missing"
         );
     }
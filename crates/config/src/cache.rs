@@ -1,64 +1,96 @@
+//! Cache of evaluated CUE packages
+//!
+//! Evaluating a package through the bridge dominates the time it takes to
+//! load an environment. The bridge's output is stored under the XDG cache
+//! directory, keyed on the directory, package and the options the bridge was
+//! given, such as tags and system facts, together with the content hash of
+//! every file that contributed to it: the package files, `env.local.cue` and
+//! the packages they import from the module or `cue.mod`. A load whose
+//! sources still hash the same reads the stored output instead of evaluating
+//! again, so editing a file, or adding one to the package, invalidates the
+//! entry without any bookkeeping.
+//!
+//! Within a process, the configuration extracted from each package is kept
+//! too, keyed the same way. Loading a hierarchy again, as a daemon or a
+//! long-running command does, only evaluates the packages whose sources
+//! changed and merges them with the layers extracted before.
+
+use crate::package::package_files;
 use crate::ParseResult;
+use cuenv_core::constants::{CUENV_EVAL_CACHE_VAR, ENV_LOCAL_CUE_FILENAME};
 use cuenv_utils::cleanup::handler::TempFileGuard;
 use cuenv_utils::network::retry::{retry_blocking, RetryConfig};
 use cuenv_utils::xdg::XdgPaths;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::Write;
-use std::path::Path;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Directories under `cue.mod` that imports outside the module resolve to
+const IMPORT_ROOTS: &[&str] = &["pkg", "gen", "usr"];
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CachedParseResult {
-    pub result: ParseResult,
-    pub mtime: SystemTime,
+struct CachedEvaluation {
+    /// Content hash of each contributing file, empty for files that were absent
+    sources: BTreeMap<PathBuf, String>,
+    /// The bridge's JSON output
+    output: String,
+}
+
+/// The configuration extracted from a package, and the sources it was
+/// evaluated from
+struct CachedLayer {
+    sources: BTreeMap<PathBuf, String>,
+    result: ParseResult,
 }
 
+/// Layers extracted by this process, by directory, package and options
+static LAYERS: OnceLock<Mutex<HashMap<String, CachedLayer>>> = OnceLock::new();
+
 pub struct CueCache;
 
 impl CueCache {
-    /// Get cached parse result if it's still valid
-    pub fn get(cue_file: &Path) -> Option<ParseResult> {
-        let cache_file = XdgPaths::cache_file(&cue_file.to_path_buf());
+    /// Whether evaluations are cached; `CUENV_EVAL_CACHE=off` disables it
+    pub fn enabled() -> bool {
+        !matches!(
+            std::env::var(CUENV_EVAL_CACHE_VAR).as_deref(),
+            Ok("off" | "false" | "0")
+        )
+    }
 
-        // Check if cache file exists
-        if !cache_file.exists() {
+    /// Get the cached bridge output if none of its sources changed
+    pub fn get(dir: &Path, package_name: &str, bridge_options: &str) -> Option<String> {
+        if !Self::enabled() {
             return None;
         }
 
-        // Get modification time of source file
-        let source_mtime = match fs::metadata(cue_file) {
-            Ok(metadata) => match metadata.modified() {
-                Ok(mtime) => mtime,
-                Err(_) => return None,
-            },
-            Err(_) => return None,
-        };
-
-        // Read cache file with retry for transient failures
-        let cache_content =
-            match retry_blocking(RetryConfig::fast(), || fs::read_to_string(&cache_file)) {
-                Ok(content) => content,
-                Err(_) => return None,
-            };
+        let cache_file = Self::cache_file(dir, package_name, bridge_options);
+        let content =
+            retry_blocking(RetryConfig::fast(), || fs::read_to_string(&cache_file)).ok()?;
+        let cached: CachedEvaluation = serde_json::from_str(&content).ok()?;
 
-        // Deserialize cache
-        let cached: CachedParseResult = match serde_json::from_str(&cache_content) {
-            Ok(cached) => cached,
-            Err(_) => return None,
-        };
-
-        // Check if cache is still valid
-        if cached.mtime >= source_mtime {
-            Some(cached.result)
+        if cached.sources == sources(dir, package_name) {
+            Some(cached.output)
         } else {
             None
         }
     }
 
-    /// Save parse result to cache
-    pub fn save(cue_file: &Path, result: &ParseResult) -> Result<(), std::io::Error> {
-        let cache_file = XdgPaths::cache_file(&cue_file.to_path_buf());
+    /// Save the bridge output of a successful evaluation
+    pub fn save(
+        dir: &Path,
+        package_name: &str,
+        bridge_options: &str,
+        output: &str,
+    ) -> Result<(), std::io::Error> {
+        if !Self::enabled() {
+            return Ok(());
+        }
+
+        let cache_file = Self::cache_file(dir, package_name, bridge_options);
         let cache_dir = cache_file.parent().ok_or_else(|| {
             std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -72,16 +104,10 @@ impl CueCache {
                 .map_err(|e| std::io::Error::other(e.to_string()))?;
         }
 
-        // Get modification time of source file
-        let source_mtime = fs::metadata(cue_file)?.modified()?;
-
-        // Create cache entry
-        let cached = CachedParseResult {
-            result: result.clone(),
-            mtime: source_mtime,
+        let cached = CachedEvaluation {
+            sources: sources(dir, package_name),
+            output: output.to_string(),
         };
-
-        // Serialize cache content
         let cache_content = serde_json::to_string(&cached)?;
 
         // Write to a temporary file first to ensure atomicity
@@ -106,61 +132,376 @@ impl CueCache {
         Ok(())
     }
 
-    /// Clear all cached files
+    /// The configuration extracted from `dir` with `options_key`, reused
+    /// from an earlier call in this process when none of its sources
+    /// changed, or else extracted by `evaluate`
+    ///
+    /// Only successful evaluations are kept.
+    pub fn layer<F>(
+        dir: &Path,
+        package_name: &str,
+        options_key: &str,
+        evaluate: F,
+    ) -> cuenv_core::Result<ParseResult>
+    where
+        F: FnOnce() -> cuenv_core::Result<ParseResult>,
+    {
+        if !Self::enabled() {
+            return evaluate();
+        }
+
+        let layers = LAYERS.get_or_init(Mutex::default);
+        let key = Self::key(dir, package_name, options_key);
+        let sources = sources(dir, package_name);
+        if let Ok(layers) = layers.lock() {
+            if let Some(layer) = layers.get(&key).filter(|layer| layer.sources == sources) {
+                return Ok(layer.result.clone());
+            }
+        }
+
+        let result = evaluate()?;
+        if let Ok(mut layers) = layers.lock() {
+            layers.insert(
+                key,
+                CachedLayer {
+                    sources,
+                    result: result.clone(),
+                },
+            );
+        }
+        Ok(result)
+    }
+
+    /// Clear all cached evaluations
     pub fn clear() -> Result<(), std::io::Error> {
-        let cache_dir = XdgPaths::cache_dir();
+        if let Some(layers) = LAYERS.get() {
+            if let Ok(mut layers) = layers.lock() {
+                layers.clear();
+            }
+        }
+        let cache_dir = Self::cache_dir();
         if cache_dir.exists() {
             fs::remove_dir_all(&cache_dir)?;
         }
         Ok(())
     }
+
+    fn cache_dir() -> PathBuf {
+        XdgPaths::cache_dir().join("eval")
+    }
+
+    /// The entry for an evaluation
+    fn cache_file(dir: &Path, package_name: &str, bridge_options: &str) -> PathBuf {
+        Self::cache_dir().join(format!(
+            "{}.json",
+            Self::key(dir, package_name, bridge_options)
+        ))
+    }
+
+    /// The key of an evaluation. The crate version is part of it, as the
+    /// embedded schema the output was validated against changes with it.
+    fn key(dir: &Path, package_name: &str, options: &str) -> String {
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update(b"\0");
+        hasher.update(canonical.to_string_lossy().as_bytes());
+        hasher.update(b"\0");
+        hasher.update(package_name.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(options.as_bytes());
+
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Content hashes of the files an evaluation of `dir` reads
+fn sources(dir: &Path, package_name: &str) -> BTreeMap<PathBuf, String> {
+    let mut sources = BTreeMap::new();
+    read_sources(dir, package_name, |file, content| {
+        let hash = match content {
+            Some(content) => format!("{:x}", Sha256::digest(content)),
+            None => String::new(),
+        };
+        sources.insert(file, hash);
+    });
+    sources
+}
+
+/// Visit every file an evaluation of `dir` reads with its content, `None`
+/// when it does not exist: the package, env.local.cue and the files of the
+/// packages they import from their CUE module
+pub(crate) fn read_sources(
+    dir: &Path,
+    package_name: &str,
+    mut visit: impl FnMut(PathBuf, Option<&[u8]>),
+) {
+    let mut files = package_files(dir, package_name);
+    files.push(dir.join(ENV_LOCAL_CUE_FILENAME));
+
+    let module = find_module(dir);
+    let mut visited = HashSet::new();
+
+    while let Some(file) = files.pop() {
+        let content = fs::read(&file).unwrap_or_default();
+        let exists = !content.is_empty() || file.exists();
+
+        for import in imports(&String::from_utf8_lossy(&content)) {
+            let Some(import_dir) = resolve_import(module.as_ref(), &import) else {
+                continue;
+            };
+            if visited.insert(import_dir.clone()) {
+                files.extend(cue_files(&import_dir));
+            }
+        }
+
+        visit(file, exists.then_some(content.as_slice()));
+    }
+}
+
+/// The module root and path of the CUE module containing `dir`
+fn find_module(dir: &Path) -> Option<(PathBuf, Option<String>)> {
+    let root = dir
+        .ancestors()
+        .find(|ancestor| ancestor.join("cue.mod").is_dir())?;
+    let module_path = fs::read_to_string(root.join("cue.mod").join("module.cue"))
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("module:").and_then(quoted))
+                .map(str::to_string)
+        });
+    Some((root.to_path_buf(), module_path))
+}
+
+/// The directory an import path is loaded from, if it exists
+fn resolve_import(module: Option<&(PathBuf, Option<String>)>, import: &str) -> Option<PathBuf> {
+    let (root, module_path) = module?;
+    let import = import.split(':').next().unwrap_or(import);
+
+    if let Some(module_path) = module_path {
+        if let Some(relative) = import
+            .strip_prefix(module_path.as_str())
+            .filter(|relative| relative.is_empty() || relative.starts_with('/'))
+        {
+            let dir = root.join(relative.trim_start_matches('/'));
+            return dir.is_dir().then_some(dir);
+        }
+    }
+
+    IMPORT_ROOTS
+        .iter()
+        .map(|import_root| root.join("cue.mod").join(import_root).join(import))
+        .find(|dir| dir.is_dir())
+}
+
+/// Every `.cue` file directly inside `dir`
+fn cue_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "cue"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The import paths declared in a CUE file
+fn imports(content: &str) -> Vec<String> {
+    let mut imports = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines().map(str::trim) {
+        if in_block {
+            if line.starts_with(')') {
+                in_block = false;
+            } else if let Some(path) = quoted(line) {
+                imports.push(path.to_string());
+            }
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix("import") else {
+            continue;
+        };
+        if let Some(block) = rest.trim_start().strip_prefix('(') {
+            match block.split_once(')') {
+                Some((single, _)) => imports.extend(quoted(single).map(str::to_string)),
+                None => in_block = true,
+            }
+        } else if rest.starts_with([' ', '\t']) {
+            imports.extend(quoted(rest).map(str::to_string));
+        }
+    }
+
+    imports
+}
+
+/// The first double-quoted string in `s`
+fn quoted(s: &str) -> Option<&str> {
+    let start = s.find('"')? + 1;
+    let end = start + s[start..].find('"')?;
+    Some(&s[start..end])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ParseResult;
+    use serial_test::serial;
     use tempfile::TempDir;
 
+    fn with_cache_home<F: FnOnce()>(test: F) {
+        let cache_home = TempDir::new().unwrap();
+        let original = std::env::var("XDG_CACHE_HOME").ok();
+        std::env::set_var("XDG_CACHE_HOME", cache_home.path());
+        test();
+        match original {
+            Some(value) => std::env::set_var("XDG_CACHE_HOME", value),
+            None => std::env::remove_var("XDG_CACHE_HOME"),
+        }
+    }
+
     #[test]
-    fn test_cache_save_and_get() -> std::io::Result<()> {
-        let temp_dir = TempDir::new()?;
-        let cue_file = temp_dir.path().join("test.cue");
-        fs::write(&cue_file, "package cuenv")?;
+    fn test_imports_are_parsed() {
+        let content = r#"package cuenv
 
-        // Create a parse result
-        let mut result = ParseResult::default();
-        result
-            .variables
-            .insert("FOO".to_string(), "bar".to_string());
+import "strings"
+import shared "example.com/app/shared:config"
+import (
+    "list"
+    schema "github.com/rawkode/cuenv/schema"
+)
 
-        // Save to cache
-        CueCache::save(&cue_file, &result).expect("Failed to save to cache");
+imports: "not an import"
+"#;
+        assert_eq!(
+            imports(content),
+            vec![
+                "strings",
+                "example.com/app/shared:config",
+                "list",
+                "github.com/rawkode/cuenv/schema"
+            ]
+        );
+    }
 
-        // Get from cache
-        let cached = CueCache::get(&cue_file).expect("Failed to get from cache");
-        assert_eq!(cached.variables.get("FOO"), Some(&"bar".to_string()));
+    #[test]
+    #[serial]
+    fn test_cache_save_and_get() {
+        with_cache_home(|| {
+            let temp_dir = TempDir::new().unwrap();
+            fs::write(temp_dir.path().join("env.cue"), "package cuenv").unwrap();
 
-        Ok(())
+            CueCache::save(temp_dir.path(), "cuenv", "{}", r#"{"env":{}}"#).unwrap();
+
+            assert_eq!(
+                CueCache::get(temp_dir.path(), "cuenv", "{}").as_deref(),
+                Some(r#"{"env":{}}"#)
+            );
+            assert!(CueCache::get(temp_dir.path(), "cuenv", r#"{"tags":["env=prod"]}"#).is_none());
+        });
     }
 
     #[test]
-    fn test_cache_invalidation() -> std::io::Result<()> {
-        let temp_dir = TempDir::new()?;
-        let cue_file = temp_dir.path().join("test.cue");
-        fs::write(&cue_file, "package cuenv")?;
+    #[serial]
+    fn test_cache_invalidation() {
+        with_cache_home(|| {
+            let temp_dir = TempDir::new().unwrap();
+            fs::write(temp_dir.path().join("env.cue"), "package cuenv").unwrap();
+            CueCache::save(temp_dir.path(), "cuenv", "{}", "{}").unwrap();
 
-        // Create and save a parse result
-        let result = ParseResult::default();
-        CueCache::save(&cue_file, &result).expect("Failed to save to cache");
+            // A modified file invalidates the entry, whatever its mtime
+            fs::write(
+                temp_dir.path().join("env.cue"),
+                "package cuenv\n// modified",
+            )
+            .unwrap();
+            assert!(CueCache::get(temp_dir.path(), "cuenv", "{}").is_none());
 
-        // Modify the file (with a small delay to ensure different mtime)
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        fs::write(&cue_file, "package cuenv\n// modified")?;
+            // So does a file joining the package, or a local override
+            CueCache::save(temp_dir.path(), "cuenv", "{}", "{}").unwrap();
+            fs::write(temp_dir.path().join("tasks.cue"), "package cuenv").unwrap();
+            assert!(CueCache::get(temp_dir.path(), "cuenv", "{}").is_none());
 
-        // Cache should be invalidated
-        assert!(CueCache::get(&cue_file).is_none());
+            CueCache::save(temp_dir.path(), "cuenv", "{}", "{}").unwrap();
+            fs::write(temp_dir.path().join("env.local.cue"), "package cuenv").unwrap();
+            assert!(CueCache::get(temp_dir.path(), "cuenv", "{}").is_none());
+        });
+    }
 
-        Ok(())
+    #[test]
+    #[serial]
+    fn test_imported_packages_invalidate_the_cache() {
+        with_cache_home(|| {
+            let temp_dir = TempDir::new().unwrap();
+            let root = temp_dir.path();
+            fs::create_dir_all(root.join("cue.mod")).unwrap();
+            fs::write(
+                root.join("cue.mod").join("module.cue"),
+                "module: \"example.com/app\"\n",
+            )
+            .unwrap();
+            fs::create_dir_all(root.join("shared")).unwrap();
+            fs::write(root.join("shared").join("shared.cue"), "package shared").unwrap();
+            fs::create_dir_all(root.join("service")).unwrap();
+            fs::write(
+                root.join("service").join("env.cue"),
+                "package cuenv\n\nimport \"example.com/app/shared\"\n",
+            )
+            .unwrap();
+
+            let service = root.join("service");
+            CueCache::save(&service, "cuenv", "{}", "{}").unwrap();
+            assert!(CueCache::get(&service, "cuenv", "{}").is_some());
+
+            fs::write(
+                root.join("shared").join("shared.cue"),
+                "package shared\nx: 1",
+            )
+            .unwrap();
+            assert!(CueCache::get(&service, "cuenv", "{}").is_none());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_layers_are_only_extracted_when_their_sources_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let parent = temp_dir.path();
+        let child = parent.join("service");
+        fs::create_dir_all(&child).unwrap();
+        fs::write(parent.join("env.cue"), "package cuenv").unwrap();
+        fs::write(child.join("env.cue"), "package cuenv").unwrap();
+
+        let evaluations = std::cell::Cell::new(0);
+        let load = |dir: &Path| {
+            CueCache::layer(dir, "cuenv", "{}", || {
+                evaluations.set(evaluations.get() + 1);
+                Ok(ParseResult::default())
+            })
+            .unwrap()
+        };
+
+        load(parent);
+        load(&child);
+        assert_eq!(evaluations.get(), 2);
+
+        // Editing the child only evaluates the child again
+        fs::write(child.join("env.cue"), "package cuenv\n// modified").unwrap();
+        load(parent);
+        load(&child);
+        assert_eq!(evaluations.get(), 3);
+
+        // Other options are another entry
+        CueCache::layer(parent, "cuenv", r#"{"tags":["env=prod"]}"#, || {
+            evaluations.set(evaluations.get() + 1);
+            Ok(ParseResult::default())
+        })
+        .unwrap();
+        assert_eq!(evaluations.get(), 4);
     }
 }
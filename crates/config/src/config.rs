@@ -137,6 +137,10 @@ impl Config {
                 deny_paths: None,
                 allowed_hosts: None,
                 infer_from_inputs_outputs: None,
+                seccomp: None,
+                fail_on_secret_leak: None,
+                user: None,
+                drop_capabilities: None,
             },
             monorepo: None,
             original_env: std::env::vars().collect(),
@@ -197,16 +201,14 @@ impl Config {
     }
 
     /// Check if a variable is marked as sensitive
-    pub fn is_sensitive(&self, _var_name: &str) -> bool {
-        // TODO: Add sensitive field to VariableMetadata
-        // For now, return false
-        false
+    pub fn is_sensitive(&self, var_name: &str) -> bool {
+        self.get_metadata(var_name)
+            .is_some_and(|metadata| metadata.sensitive)
     }
 
     /// Get the list of available environments
     pub fn get_environments(&self) -> Vec<String> {
-        // TODO: Extract from ParseResult when environment support is added
-        vec![]
+        self.parse_result.environments.clone()
     }
 
     /// Check if running in monorepo mode
@@ -252,6 +254,10 @@ impl ConfigBuilder {
                 deny_paths: None,
                 allowed_hosts: None,
                 infer_from_inputs_outputs: None,
+                seccomp: None,
+                fail_on_secret_leak: None,
+                user: None,
+                drop_capabilities: None,
             },
             monorepo: None,
         }
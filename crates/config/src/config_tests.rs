@@ -19,12 +19,14 @@ mod tests {
             "TEST_VAR".to_string(),
             VariableMetadata {
                 capability: Some("basic".to_string()),
+                ..Default::default()
             },
         );
         metadata.insert(
             "SECRET_VAR".to_string(),
             VariableMetadata {
                 capability: Some("secrets".to_string()),
+                sensitive: true,
             },
         );
 
@@ -36,6 +38,18 @@ mod tests {
             task_nodes: HashMap::new(),
             hooks: HashMap::new(),
             config: None,
+            root: false,
+            constraints: HashMap::new(),
+            list_variables: HashMap::new(),
+            command_variables: HashMap::new(),
+            terraform_variables: HashMap::new(),
+            overlays: Default::default(),
+            environment_sources: HashMap::new(),
+            typed_values: HashMap::new(),
+            environments: Vec::new(),
+            notify: None,
+            nix: None,
+            devenv: None,
         }
     }
 
@@ -76,9 +90,8 @@ mod tests {
             RuntimeOptions::default(),
         );
 
-        // TODO: When sensitive field is added to VariableMetadata, update this test
         assert!(!config.is_sensitive("TEST_VAR"));
-        assert!(!config.is_sensitive("SECRET_VAR"));
+        assert!(config.is_sensitive("SECRET_VAR"));
         assert!(!config.is_sensitive("NONEXISTENT"));
     }
 
@@ -149,6 +162,10 @@ mod tests {
             deny_paths: Some(vec!["/secret".to_string()]),
             allowed_hosts: Some(vec!["github.com".to_string()]),
             infer_from_inputs_outputs: Some(false),
+            seccomp: Some("strict".to_string()),
+            fail_on_secret_leak: Some(true),
+            user: Some("nobody".to_string()),
+            drop_capabilities: Some(true),
         };
 
         assert_eq!(config.security.restrict_disk, Some(true));
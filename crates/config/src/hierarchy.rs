@@ -0,0 +1,293 @@
+//! Hierarchical loading of env.cue files
+//!
+//! Like direnv's `source_up`, a directory inherits the configuration of its
+//! ancestors. Starting from the requested directory, every parent directory
+//! containing an env.cue file is evaluated until a package declares
+//! `root: true` or the filesystem root is reached. Layers are then merged
+//! from the outermost ancestor down, so values defined closer to the
+//! requested directory override those inherited from parents.
+//!
+//! A directory takes part when any of its `.cue` files belongs to the
+//! package, not only env.cue; see [`crate::package`].
+//!
+//! Each directory may also contain an untracked `env.local.cue`. The CUE
+//! bridge merges it over that directory's package, so it takes part in the
+//! hierarchy as part of its directory's layer.
+
+use crate::package::primary_file;
+use crate::{CueParser, ParseOptions, ParseResult};
+use cuenv_core::{
+    constants::{ENV_CUE_FILENAME, ENV_LOCAL_CUE_FILENAME},
+    Error, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A single evaluated env.cue file within the hierarchy
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    /// The env.cue file this layer was evaluated from
+    pub file: PathBuf,
+    pub result: ParseResult,
+}
+
+/// The merged result of all layers together with provenance information
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HierarchicalParseResult {
+    pub result: ParseResult,
+    /// The env.cue file that provided the effective value of each variable
+    pub variable_sources: HashMap<String, PathBuf>,
+    /// All env.cue files that were merged, ordered from root to leaf
+    pub files: Vec<PathBuf>,
+    /// The env.local.cue files merged over their directory's env.cue
+    pub local_files: Vec<PathBuf>,
+}
+
+/// The env.local.cue override in `dir`, if there is one
+pub fn local_override(dir: &Path) -> Option<PathBuf> {
+    let file = dir.join(ENV_LOCAL_CUE_FILENAME);
+    file.is_file().then_some(file)
+}
+
+/// Evaluate `dir` and its ancestors, merging them into a single result
+pub fn eval_hierarchy(
+    dir: &Path,
+    package_name: &str,
+    options: &ParseOptions,
+) -> Result<HierarchicalParseResult> {
+    let leaf = CueParser::eval_package_with_options(dir, package_name, options)?;
+    let mut layers = vec![ConfigLayer {
+        file: primary_file(dir, package_name).unwrap_or_else(|| dir.join(ENV_CUE_FILENAME)),
+        result: leaf,
+    }];
+
+    if !layers[0].result.root {
+        for ancestor in dir.ancestors().skip(1) {
+            let Some(file) = primary_file(ancestor, package_name) else {
+                continue;
+            };
+
+            let result = CueParser::eval_package_with_options(ancestor, package_name, options)
+                .map_err(|e| {
+                    Error::cue_parse_with_source(
+                        &file,
+                        format!("Failed to evaluate parent package: {}", file.display()),
+                        e,
+                    )
+                })?;
+            let is_root = result.root;
+            layers.push(ConfigLayer { file, result });
+
+            if is_root {
+                break;
+            }
+        }
+    }
+
+    // Merge from the outermost ancestor towards the requested directory
+    layers.reverse();
+    let mut merged = merge_layers(layers);
+    crate::npm::add_script_tasks(&mut merged);
+    Ok(merged)
+}
+
+/// Merge layers ordered from root to leaf, later layers overriding earlier ones
+pub fn merge_layers(layers: Vec<ConfigLayer>) -> HierarchicalParseResult {
+    let mut merged = HierarchicalParseResult::default();
+
+    for layer in layers {
+        let ConfigLayer { file, result } = layer;
+
+        for name in result.variables.keys() {
+            merged.variable_sources.insert(name.clone(), file.clone());
+            // A string declaration in a deeper layer drops an outer typed value
+            merged.result.typed_values.remove(name);
+            // A plain declaration in a deeper layer hides an outer environment's override
+            match result.environment_sources.get(name) {
+                Some(env_name) => {
+                    merged
+                        .result
+                        .environment_sources
+                        .insert(name.clone(), env_name.clone());
+                }
+                None => {
+                    merged.result.environment_sources.remove(name);
+                }
+            }
+        }
+
+        merged.result.variables.extend(result.variables);
+        merged.result.typed_values.extend(result.typed_values);
+        merged.result.metadata.extend(result.metadata);
+        merged.result.commands.extend(result.commands);
+        merged.result.tasks.extend(result.tasks);
+        merged.result.task_nodes.extend(result.task_nodes);
+        merged.result.hooks.extend(result.hooks);
+        merged.result.constraints.extend(result.constraints);
+        if result.notify.is_some() {
+            merged.result.notify = result.notify;
+        }
+        for name in result.environments {
+            if !merged.result.environments.contains(&name) {
+                merged.result.environments.push(name);
+            }
+        }
+        merged.result.environments.sort();
+        // Relative entries and commands belong to the directory that declared them
+        let dir = file.parent().unwrap_or(Path::new("."));
+        merged.result.list_variables.extend(
+            result
+                .list_variables
+                .into_iter()
+                .map(|(name, modifier)| (name, modifier.resolved_against(dir))),
+        );
+        merged.result.command_variables.extend(
+            result
+                .command_variables
+                .into_iter()
+                .map(|(name, value)| (name, value.declared_in(dir))),
+        );
+        merged.result.terraform_variables.extend(
+            result
+                .terraform_variables
+                .into_iter()
+                .map(|(name, value)| (name, value.declared_in(dir))),
+        );
+        if let Some(nix) = result.nix {
+            merged.result.nix = Some(nix.declared_in(dir));
+        }
+        if let Some(devenv) = result.devenv {
+            merged.result.devenv = Some(devenv.declared_in(dir));
+        }
+        merged.result.overlays.extend(result.overlays);
+        if result.config.is_some() {
+            merged.result.config = result.config;
+        }
+        merged.result.root = result.root;
+        merged.local_files.extend(local_override(dir));
+        merged.files.push(file);
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ListModifier, TaskConfig};
+
+    fn layer(file: &str, vars: &[(&str, &str)], tasks: &[&str]) -> ConfigLayer {
+        ConfigLayer {
+            file: PathBuf::from(file),
+            result: ParseResult {
+                variables: vars
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                tasks: tasks
+                    .iter()
+                    .map(|name| (name.to_string(), TaskConfig::default()))
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_child_overrides_parent() {
+        let merged = merge_layers(vec![
+            layer(
+                "/repo/env.cue",
+                &[("LEVEL", "root"), ("SHARED", "yes")],
+                &[],
+            ),
+            layer("/repo/pkg/env.cue", &[("LEVEL", "pkg")], &[]),
+        ]);
+
+        assert_eq!(merged.result.variables["LEVEL"], "pkg");
+        assert_eq!(merged.result.variables["SHARED"], "yes");
+        assert_eq!(
+            merged.files,
+            vec![
+                PathBuf::from("/repo/env.cue"),
+                PathBuf::from("/repo/pkg/env.cue")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variable_sources_track_effective_file() {
+        let merged = merge_layers(vec![
+            layer(
+                "/repo/env.cue",
+                &[("LEVEL", "root"), ("SHARED", "yes")],
+                &[],
+            ),
+            layer("/repo/pkg/env.cue", &[("LEVEL", "pkg")], &[]),
+        ]);
+
+        assert_eq!(
+            merged.variable_sources["LEVEL"],
+            PathBuf::from("/repo/pkg/env.cue")
+        );
+        assert_eq!(
+            merged.variable_sources["SHARED"],
+            PathBuf::from("/repo/env.cue")
+        );
+    }
+
+    #[test]
+    fn test_tasks_are_inherited() {
+        let merged = merge_layers(vec![
+            layer("/repo/env.cue", &[], &["lint"]),
+            layer("/repo/pkg/env.cue", &[], &["build"]),
+        ]);
+
+        assert!(merged.result.tasks.contains_key("lint"));
+        assert!(merged.result.tasks.contains_key("build"));
+    }
+
+    #[test]
+    fn test_single_layer_is_unchanged() {
+        let merged = merge_layers(vec![layer("/repo/env.cue", &[("A", "1")], &[])]);
+
+        assert_eq!(merged.result.variables.len(), 1);
+        assert_eq!(merged.files.len(), 1);
+    }
+
+    #[test]
+    fn test_list_entries_resolve_against_declaring_dir() {
+        let mut root = layer("/repo/env.cue", &[], &[]);
+        root.result.list_variables.insert(
+            "PATH".to_string(),
+            ListModifier {
+                prepend: vec!["./bin".to_string(), "/usr/local/bin".to_string()],
+                ..Default::default()
+            },
+        );
+
+        let merged = merge_layers(vec![root, layer("/repo/pkg/env.cue", &[], &[])]);
+
+        assert_eq!(
+            merged.result.list_variables["PATH"].prepend,
+            vec!["/repo/bin".to_string(), "/usr/local/bin".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_local_override_is_reported() {
+        let temp = tempfile::tempdir().unwrap();
+        let env_cue = temp.path().join("env.cue");
+        std::fs::write(&env_cue, "package cuenv\n").unwrap();
+
+        let merged = merge_layers(vec![layer(env_cue.to_str().unwrap(), &[], &[])]);
+        assert!(merged.local_files.is_empty());
+
+        let local = temp.path().join(ENV_LOCAL_CUE_FILENAME);
+        std::fs::write(&local, "package cuenv\n").unwrap();
+
+        let merged = merge_layers(vec![layer(env_cue.to_str().unwrap(), &[], &[])]);
+        assert_eq!(merged.local_files, vec![local]);
+    }
+}
@@ -4,13 +4,27 @@
 
 pub mod cache;
 pub mod config;
+pub mod hierarchy;
 pub mod loader;
+pub mod npm;
+pub mod package;
 pub mod parser;
+pub mod schema;
+pub mod signature;
+pub mod user;
+pub mod workspace;
 
 #[cfg(test)]
 mod config_tests;
 
 pub use cache::*;
 pub use config::*;
+pub use hierarchy::*;
 pub use loader::*;
+pub use package::{
+    approved_files, config_files, configured_file_names, data_file, file_names, has_package,
+    package_files, package_name, primary_file, DATA_FILE_NAMES, FILE_NAME_SEPARATOR,
+};
 pub use parser::*;
+pub use user::{ColorChoice, UserCacheConfig, UserConfig};
+pub use workspace::{CacheSettings, WorkspaceConfig};
@@ -6,12 +6,10 @@
 
 use crate::{
     config::{Config, ConfigBuilder, MonorepoContext, RuntimeOptions},
-    CueParser, ParseOptions, ParseResult, SecurityConfig,
-};
-use cuenv_core::{
-    constants::{CUENV_PACKAGE_VAR, DEFAULT_PACKAGE_NAME, ENV_CUE_FILENAME},
-    Error, Result,
+    eval_hierarchy, has_package, package_name, primary_file, tags_from_env, ConfigSettings,
+    ParseOptions, ParseResult, SecurityConfig,
 };
+use cuenv_core::{Error, Result};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
@@ -96,14 +94,25 @@ impl ConfigLoader {
                 task_nodes: HashMap::new(),
                 hooks: HashMap::new(),
                 config: None,
+                root: false,
+                constraints: HashMap::new(),
+                list_variables: HashMap::new(),
+                command_variables: HashMap::new(),
+                terraform_variables: HashMap::new(),
+                overlays: Default::default(),
+                environment_sources: HashMap::new(),
+                typed_values: HashMap::new(),
+                environments: Vec::new(),
+                notify: None,
+                nix: None,
+                devenv: None,
             }
         };
 
-        // Merge config settings with runtime options (CLI takes precedence)
+        // Merge the project's config settings, over the user's, with runtime
+        // options (CLI takes precedence)
         let mut runtime = self.runtime.clone();
-        if let Some(ref config_settings) = parse_result.config {
-            runtime.merge_with_config(config_settings);
-        }
+        runtime.merge_with_config(&ConfigSettings::effective(parse_result.config.as_ref()));
 
         // Extract security configuration from parse result
         let security = self.extract_security_config(&parse_result);
@@ -133,13 +142,12 @@ impl ConfigLoader {
         builder.build()
     }
 
-    /// Find the env.cue file in the given directory or its parents
+    /// Find the configuration in the given directory or its parents
     fn find_env_file(&self, start_dir: &Path) -> Result<Option<PathBuf>> {
         let mut current = start_dir.to_path_buf();
 
         loop {
-            let env_file = current.join(ENV_CUE_FILENAME);
-            if env_file.exists() {
+            if let Some(env_file) = primary_file(&current, &package_name()) {
                 return Ok(Some(env_file));
             }
 
@@ -171,13 +179,10 @@ impl ConfigLoader {
             options.environment = Some(env.clone());
         }
         options.capabilities = self.runtime.capabilities.clone();
+        options.tags = tags_from_env();
 
-        // Get the package name from environment or use default
-        let package_name =
-            std::env::var(CUENV_PACKAGE_VAR).unwrap_or_else(|_| DEFAULT_PACKAGE_NAME.to_string());
-
-        // Parse the CUE package
-        CueParser::eval_package_with_options(dir, &package_name, &options)
+        // Parse the CUE package together with its ancestors
+        eval_hierarchy(dir, &package_name(), &options).map(|merged| merged.result)
     }
 
     /// Extract security configuration from parse result
@@ -194,6 +199,10 @@ impl ConfigLoader {
             deny_paths: None,
             allowed_hosts: None,
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         }
     }
 
@@ -241,8 +250,7 @@ impl ConfigLoader {
             let path = entry.path();
 
             if path.is_dir() {
-                let env_file = path.join(ENV_CUE_FILENAME);
-                if env_file.exists() {
+                if has_package(&path, &package_name()) {
                     if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
                         packages.insert(name.to_string(), path);
                     }
@@ -0,0 +1,203 @@
+//! package.json scripts as tasks
+//!
+//! Each script of a package.json next to a configuration of the hierarchy
+//! is a task named `npm:<script>`, run by the project's package manager in
+//! the directory of the package.json. The package.json closest to the
+//! requested directory provides a script, and a task of the same name in
+//! env.cue replaces it. `config: npmScripts: false` leaves the scripts out.
+
+use crate::{ConfigSettings, HierarchicalParseResult, TaskConfig, TaskNode};
+use std::path::Path;
+
+/// Prefix of the tasks running package.json scripts
+pub const NPM_TASK_PREFIX: &str = "npm:";
+
+/// Lock files, by the package manager they belong to
+const LOCK_FILES: &[(&str, &str)] = &[
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lockb", "bun"),
+    ("bun.lock", "bun"),
+    ("package-lock.json", "npm"),
+];
+
+/// The tasks running the scripts of the package.json in `dir`, sorted by
+/// name
+pub fn script_tasks(dir: &Path) -> Vec<(String, TaskConfig)> {
+    let Ok(content) = std::fs::read_to_string(dir.join("package.json")) else {
+        return Vec::new();
+    };
+    let package: serde_json::Value = match serde_json::from_str(&content) {
+        Ok(package) => package,
+        Err(e) => {
+            log::warn!("Ignoring the scripts of {}: {e}", dir.display());
+            return Vec::new();
+        }
+    };
+    let Some(scripts) = package["scripts"].as_object() else {
+        return Vec::new();
+    };
+
+    let manager = package_manager(dir, &package);
+    let mut tasks: Vec<(String, TaskConfig)> = scripts
+        .iter()
+        .filter_map(|(name, script)| {
+            let task = TaskConfig {
+                description: Some(script.as_str()?.to_string()),
+                command: Some(format!("{manager} run {name}")),
+                working_dir: Some(dir.to_string_lossy().into_owned()),
+                ..Default::default()
+            };
+            Some((format!("{NPM_TASK_PREFIX}{name}"), task))
+        })
+        .collect();
+    tasks.sort_by(|a, b| a.0.cmp(&b.0));
+    tasks
+}
+
+/// The package manager of the package: its `packageManager` field, else the
+/// one whose lock file is in its directory or, in a workspace, above it
+fn package_manager(dir: &Path, package: &serde_json::Value) -> &'static str {
+    let declared = package["packageManager"]
+        .as_str()
+        .and_then(|manager| manager.split('@').next());
+    if let Some(manager) = declared.and_then(|declared| {
+        LOCK_FILES
+            .iter()
+            .find(|(_, manager)| *manager == declared)
+            .map(|(_, manager)| *manager)
+    }) {
+        return manager;
+    }
+
+    dir.ancestors()
+        .find_map(|dir| {
+            LOCK_FILES
+                .iter()
+                .find(|(lock_file, _)| dir.join(lock_file).is_file())
+                .map(|(_, manager)| *manager)
+        })
+        .unwrap_or("npm")
+}
+
+/// Add the script tasks of the hierarchy's directories, unless the project
+/// or the user turned them off
+pub fn add_script_tasks(merged: &mut HierarchicalParseResult) {
+    let settings = ConfigSettings::effective(merged.result.config.as_ref());
+    if settings.npm_scripts == Some(false) {
+        return;
+    }
+
+    // From the requested directory up, so the closest package.json wins
+    for file in merged.files.iter().rev() {
+        let Some(dir) = file.parent() else {
+            continue;
+        };
+        for (name, task) in script_tasks(dir) {
+            if merged.result.tasks.contains_key(&name)
+                || merged.result.task_nodes.contains_key(&name)
+            {
+                continue;
+            }
+            merged
+                .result
+                .task_nodes
+                .insert(name.clone(), TaskNode::Task(Box::new(task.clone())));
+            merged.result.tasks.insert(name, task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn workspace() -> TempDir {
+        let root = TempDir::new().unwrap();
+        let web = root.path().join("packages/web");
+        std::fs::create_dir_all(&web).unwrap();
+        std::fs::write(root.path().join("pnpm-lock.yaml"), "").unwrap();
+        std::fs::write(
+            root.path().join("package.json"),
+            r#"{"scripts": {"lint": "eslint .", "build": "turbo build"}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            web.join("package.json"),
+            r#"{"scripts": {"build": "vite build", "dev": "vite"}}"#,
+        )
+        .unwrap();
+        root
+    }
+
+    #[test]
+    fn test_script_tasks() {
+        let root = workspace();
+        let web = root.path().join("packages/web");
+
+        let tasks = script_tasks(&web);
+        let names: Vec<&str> = tasks.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["npm:build", "npm:dev"]);
+        let build = &tasks[0].1;
+        assert_eq!(build.description.as_deref(), Some("vite build"));
+        assert_eq!(build.command.as_deref(), Some("pnpm run build"));
+        assert_eq!(build.working_dir, Some(web.to_string_lossy().into_owned()));
+
+        std::fs::write(
+            web.join("package.json"),
+            r#"{"packageManager": "yarn@4.1.0", "scripts": {"dev": "vite"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            script_tasks(&web)[0].1.command.as_deref(),
+            Some("yarn run dev")
+        );
+        assert!(script_tasks(&root.path().join("packages")).is_empty());
+    }
+
+    #[test]
+    fn test_add_script_tasks() {
+        let root = workspace();
+        let mut merged = HierarchicalParseResult {
+            files: vec![
+                root.path().join("env.cue"),
+                root.path().join("packages/web/env.cue"),
+            ],
+            ..Default::default()
+        };
+        merged.result.tasks.insert(
+            "npm:dev".to_string(),
+            TaskConfig {
+                command: Some("vite --host".to_string()),
+                ..Default::default()
+            },
+        );
+
+        add_script_tasks(&mut merged);
+        let tasks = &merged.result.tasks;
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks["npm:dev"].command.as_deref(), Some("vite --host"));
+        assert_eq!(
+            tasks["npm:build"].working_dir.as_deref().map(PathBuf::from),
+            Some(root.path().join("packages/web"))
+        );
+        assert_eq!(
+            tasks["npm:lint"].working_dir.as_deref().map(PathBuf::from),
+            Some(root.path().to_path_buf())
+        );
+        assert!(merged.result.task_nodes.contains_key("npm:lint"));
+
+        let mut merged = HierarchicalParseResult {
+            files: vec![root.path().join("env.cue")],
+            ..Default::default()
+        };
+        merged.result.config = Some(ConfigSettings {
+            npm_scripts: Some(false),
+            ..Default::default()
+        });
+        add_script_tasks(&mut merged);
+        assert!(merged.result.tasks.is_empty());
+    }
+}
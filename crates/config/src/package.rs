@@ -0,0 +1,259 @@
+//! The CUE files that make up a directory's configuration
+//!
+//! A directory's configuration is the CUE package of every `.cue` file in it
+//! whose package clause names the cuenv package, so env, tasks and secrets
+//! can be split into `env.cue`, `tasks.cue` and `ci.cue`. The files are
+//! unified by the CUE loader. `env.cue` is not required, but when present it
+//! is the file the directory is reported by. `env.local.cue` is merged over
+//! the package separately and is not part of it.
+//!
+//! Repositories where `env.cue` belongs to other tooling can name the files
+//! cuenv looks for in `CUENV_FILE`, in order of preference. A directory is
+//! then only configured when its package contains one of them.
+//!
+//! Teams not using CUE yet can configure a directory without a package with
+//! a `cuenv.yaml` or `cuenv.json` file of the same shape instead.
+
+use cuenv_core::constants::{
+    CUENV_FILE_VAR, CUENV_PACKAGE_VAR, DEFAULT_PACKAGE_NAME, ENV_CUE_FILENAME,
+    ENV_LOCAL_CUE_FILENAME,
+};
+use std::path::{Path, PathBuf};
+
+/// Separates the file names listed in `CUENV_FILE`
+pub const FILE_NAME_SEPARATOR: char = ',';
+
+/// JSON and YAML files configuring a directory without a CUE package
+pub const DATA_FILE_NAMES: &[&str] = &["cuenv.yaml", "cuenv.yml", "cuenv.json"];
+
+/// The package name configured through `CUENV_PACKAGE`, or the default
+pub fn package_name() -> String {
+    std::env::var(CUENV_PACKAGE_VAR).unwrap_or_else(|_| DEFAULT_PACKAGE_NAME.to_string())
+}
+
+/// The file names configured through `CUENV_FILE`, in order of preference
+pub fn configured_file_names() -> Option<Vec<String>> {
+    let value = std::env::var(CUENV_FILE_VAR).ok()?;
+    let names: Vec<String> = value
+        .split(FILE_NAME_SEPARATOR)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    (!names.is_empty()).then_some(names)
+}
+
+/// The file names a configuration is discovered by, `env.cue` by default
+pub fn file_names() -> Vec<String> {
+    configured_file_names().unwrap_or_else(|| vec![ENV_CUE_FILENAME.to_string()])
+}
+
+/// The `.cue` files in `dir` declaring `package_name`, sorted by path
+pub fn package_files(dir: &Path, package_name: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cue"))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name != ENV_LOCAL_CUE_FILENAME)
+        })
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            std::fs::read_to_string(path)
+                .is_ok_and(|content| declared_package(&content) == Some(package_name))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// The JSON or YAML file configuring `dir`, if it has one
+pub fn data_file(dir: &Path) -> Option<PathBuf> {
+    DATA_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// The files configuring `dir`: its package files, or its data file when it
+/// has no package
+pub fn config_files(dir: &Path, package_name: &str) -> Vec<PathBuf> {
+    let files = package_files(dir, package_name);
+    if files.is_empty() {
+        return data_file(dir).into_iter().collect();
+    }
+    files
+}
+
+/// The files a directory is approved by `cuenv allow` for: its
+/// configuration and its env.local.cue, which can add hooks and tasks too
+pub fn approved_files(dir: &Path, package_name: &str) -> Vec<PathBuf> {
+    let mut files = config_files(dir, package_name);
+    let local = dir.join(ENV_LOCAL_CUE_FILENAME);
+    if local.is_file() {
+        files.push(local);
+    }
+    files
+}
+
+/// The file a directory's configuration is reported by: the first of the
+/// configured file names in the package, otherwise its first file. With
+/// `CUENV_FILE` set, a package without any of the named files is ignored.
+/// A directory without a package is reported by its data file.
+pub fn primary_file(dir: &Path, package_name: &str) -> Option<PathBuf> {
+    let files = package_files(dir, package_name);
+    if files.is_empty() {
+        return data_file(dir);
+    }
+
+    let configured = configured_file_names();
+    let names = configured
+        .clone()
+        .unwrap_or_else(|| vec![ENV_CUE_FILENAME.to_string()]);
+
+    if let Some(file) = names
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|file| files.contains(file))
+    {
+        return Some(file);
+    }
+    match configured {
+        Some(_) => None,
+        None => files.into_iter().next(),
+    }
+}
+
+/// Whether `dir` holds a configuration for `package_name`
+pub fn has_package(dir: &Path, package_name: &str) -> bool {
+    primary_file(dir, package_name).is_some()
+}
+
+/// The name in a file's package clause, skipping comments and attributes
+fn declared_package(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with('@'))
+        .and_then(|line| line.strip_prefix("package "))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use std::fs;
+
+    #[test]
+    fn test_declared_package() {
+        assert_eq!(declared_package("package cuenv\n"), Some("cuenv"));
+        assert_eq!(
+            declared_package("// Tasks\n\n@extern(embed)\npackage cuenv\n"),
+            Some("cuenv")
+        );
+        assert_eq!(declared_package("env: {}\n"), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_package_files_honor_the_package_clause() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("tasks.cue"), "package cuenv\n").unwrap();
+        fs::write(temp.path().join("ci.cue"), "package cuenv\n").unwrap();
+        fs::write(temp.path().join("other.cue"), "package other\n").unwrap();
+        fs::write(temp.path().join("env.local.cue"), "package cuenv\n").unwrap();
+
+        let files = package_files(temp.path(), "cuenv");
+        assert_eq!(
+            files,
+            vec![temp.path().join("ci.cue"), temp.path().join("tasks.cue")]
+        );
+        assert_eq!(
+            primary_file(temp.path(), "cuenv"),
+            Some(temp.path().join("ci.cue"))
+        );
+
+        fs::write(temp.path().join("env.cue"), "package cuenv\n").unwrap();
+        assert_eq!(
+            primary_file(temp.path(), "cuenv"),
+            Some(temp.path().join("env.cue"))
+        );
+        assert!(!has_package(temp.path(), "missing"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_configured_file_names_are_preferred_in_order() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("env.cue"), "package cuenv\n").unwrap();
+
+        std::env::set_var(CUENV_FILE_VAR, "cuenv.cue, project.cue");
+        assert_eq!(file_names(), vec!["cuenv.cue", "project.cue"]);
+        // env.cue belongs to other tooling, so the directory is not configured
+        assert!(!has_package(temp.path(), "cuenv"));
+
+        fs::write(temp.path().join("project.cue"), "package cuenv\n").unwrap();
+        assert_eq!(
+            primary_file(temp.path(), "cuenv"),
+            Some(temp.path().join("project.cue"))
+        );
+        fs::write(temp.path().join("cuenv.cue"), "package cuenv\n").unwrap();
+        assert_eq!(
+            primary_file(temp.path(), "cuenv"),
+            Some(temp.path().join("cuenv.cue"))
+        );
+
+        std::env::remove_var(CUENV_FILE_VAR);
+        assert_eq!(file_names(), vec![ENV_CUE_FILENAME]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_data_files_configure_directories_without_a_package() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("cuenv.json"), "{}").unwrap();
+        fs::write(temp.path().join("cuenv.yaml"), "env: {}\n").unwrap();
+
+        assert_eq!(
+            primary_file(temp.path(), "cuenv"),
+            Some(temp.path().join("cuenv.yaml"))
+        );
+        assert_eq!(
+            config_files(temp.path(), "cuenv"),
+            vec![temp.path().join("cuenv.yaml")]
+        );
+
+        // A CUE package takes precedence
+        fs::write(temp.path().join("env.cue"), "package cuenv\n").unwrap();
+        assert_eq!(
+            config_files(temp.path(), "cuenv"),
+            vec![temp.path().join("env.cue")]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_approved_files_include_the_local_override() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("env.cue"), "package cuenv\n").unwrap();
+        assert_eq!(
+            approved_files(temp.path(), "cuenv"),
+            vec![temp.path().join("env.cue")]
+        );
+
+        fs::write(temp.path().join("env.local.cue"), "package cuenv\n").unwrap();
+        assert_eq!(
+            approved_files(temp.path(), "cuenv"),
+            vec![
+                temp.path().join("env.cue"),
+                temp.path().join("env.local.cue")
+            ]
+        );
+    }
+}
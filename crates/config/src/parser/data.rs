@@ -0,0 +1,73 @@
+//! JSON and YAML configuration files
+//!
+//! A `cuenv.yaml` or `cuenv.json` holds the same structure a CUE package
+//! exports, so it is decoded into the value the bridge would have returned
+//! and processed from there like any other configuration.
+
+use cuenv_core::errors::{Error, Result};
+use std::path::Path;
+
+/// Decode a JSON or YAML configuration file, chosen by its extension
+pub fn read_data_file(path: &Path) -> Result<serde_json::Value> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| Error::file_system(path, "read configuration file", e))?;
+
+    let is_json = path.extension().is_some_and(|ext| ext == "json");
+    let value = if is_json {
+        serde_json::from_str(&content)
+            .map_err(|e| Error::configuration(format!("Invalid JSON in {}: {e}", path.display())))?
+    } else {
+        serde_yaml::from_str(&content)
+            .map_err(|e| Error::configuration(format!("Invalid YAML in {}: {e}", path.display())))?
+    };
+
+    // An empty YAML document configures nothing rather than being null
+    Ok(match value {
+        serde_json::Value::Null => serde_json::Value::Object(Default::default()),
+        value => value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_yaml_and_json_decode_to_the_same_value() {
+        let temp = tempfile::tempdir().unwrap();
+        let yaml = temp.path().join("cuenv.yaml");
+        let json_file = temp.path().join("cuenv.json");
+        fs::write(
+            &yaml,
+            "env:\n  PORT: 8080\ntasks:\n  build:\n    command: cargo build\n",
+        )
+        .unwrap();
+        fs::write(
+            &json_file,
+            r#"{"env": {"PORT": 8080}, "tasks": {"build": {"command": "cargo build"}}}"#,
+        )
+        .unwrap();
+
+        let expected = json!({
+            "env": { "PORT": 8080 },
+            "tasks": { "build": { "command": "cargo build" } }
+        });
+        assert_eq!(read_data_file(&yaml).unwrap(), expected);
+        assert_eq!(read_data_file(&json_file).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_invalid_files_are_reported() {
+        let temp = tempfile::tempdir().unwrap();
+        let yaml = temp.path().join("cuenv.yaml");
+        fs::write(&yaml, "env: [unclosed\n").unwrap();
+
+        let error = read_data_file(&yaml).unwrap_err().to_string();
+        assert!(error.contains("Invalid YAML"), "{error}");
+
+        fs::write(&yaml, "").unwrap();
+        assert_eq!(read_data_file(&yaml).unwrap(), json!({}));
+    }
+}
@@ -3,16 +3,54 @@
 //! Provides the main interface for evaluating CUE packages through FFI.
 
 use super::memory::CStringPtr;
+use crate::cache::CueCache;
+use crate::package::{data_file, package_files};
+use crate::parser::data::read_data_file;
+use crate::parser::native;
 use crate::parser::processing::{build_parse_result, ParseOptions, ParseResult};
+use crate::parser::system::SystemInfo;
 use crate::parser::types::{CueParseResult, RawCueResult};
 use crate::parser::validation::{
     create_ffi_string, validate_directory_path, validate_package_name,
 };
-use cuenv_core::errors::{Error, Result};
+use crate::schema::schema_files;
+use crate::signature;
+use cuenv_core::errors::{Diagnostic, Error, Result};
 use cuenv_utils::resilience::suggest_recovery;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi::CStr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Options passed to the bridge alongside the directory and package
+#[derive(Serialize)]
+struct BridgeOptions<'a> {
+    /// Schema package files, importable and validated against
+    schema: &'a HashMap<&'static str, &'static str>,
+    /// Values for `@tag()` attributes
+    tags: &'a [String],
+    /// Facts about the machine, provided as `_cuenv`
+    system: SystemInfo,
+}
+
+impl<'a> BridgeOptions<'a> {
+    fn new(dir: &Path, options: &'a ParseOptions) -> Self {
+        static SCHEMA: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+        Self {
+            schema: SCHEMA.get_or_init(schema_files),
+            tags: &options.tags,
+            system: SystemInfo::current(dir),
+        }
+    }
+
+    fn encode(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::Json {
+            message: "failed to encode CUE bridge options".to_string(),
+            source: e,
+        })
+    }
+}
 
 pub struct CueParser;
 
@@ -37,50 +75,127 @@ impl CueParser {
         validate_package_name(package_name)?;
         let dir_str = validate_directory_path(dir)?;
 
-        // Create FFI strings
-        let c_dir = create_ffi_string(&dir_str, "invalid directory path")?;
-        let c_package = create_ffi_string(package_name, "invalid package name")?;
+        // Deploy hosts only evaluate configurations the release pipeline signed
+        signature::verify_if_required(dir, package_name)?;
 
-        // Call CUE evaluation
-        let result_ptr = call_cue_eval_package(&c_dir, &c_package);
+        // A directory without a package may be configured in JSON or YAML,
+        // which decodes to what the bridge would have returned
+        if package_files(dir, package_name).is_empty() {
+            if let Some(file) = data_file(dir) {
+                let cue_result = deserialize_cue_result(read_data_file(&file)?)?;
+                return build_parse_result(cue_result, options);
+            }
+        }
 
-        // Wrap the result pointer for automatic cleanup
-        // Safety: result_ptr is either null or a valid pointer returned from cue_eval_package
-        let result_wrapper = unsafe { CStringPtr::new(result_ptr) };
+        // A package whose sources did not change since this process last
+        // evaluated it with the same options reuses what was extracted then
+        let bridge_options = BridgeOptions::new(dir, options);
+        let layer_key = layer_key(options, &bridge_options.system);
+        CueCache::layer(dir, package_name, &layer_key, || {
+            Self::extract_package(dir, &dir_str, package_name, options, bridge_options)
+        })
+    }
 
-        if result_wrapper.is_null() {
-            return Err(Error::cue_parse(dir, "CUE parser returned null pointer"));
+    /// Evaluate the package and extract its configuration
+    fn extract_package(
+        dir: &Path,
+        dir_str: &str,
+        package_name: &str,
+        options: &ParseOptions,
+        bridge_options: BridgeOptions<'_>,
+    ) -> Result<ParseResult> {
+        // Packages within the subset the native evaluator handles skip the
+        // CUE runtime
+        if !options.full_evaluation && native::enabled() {
+            if let Some(value) = native::evaluate(dir, package_name, &bridge_options.system) {
+                return build_parse_result(deserialize_cue_result(value)?, options);
+            }
         }
 
-        // Safety: We've verified the pointer is not null
-        let result_str = unsafe { result_wrapper.to_str()? };
+        // Unchanged sources evaluated with the same tags and system facts
+        // reuse the output of an earlier evaluation
+        let bridge_options = bridge_options.encode()?;
+        let cached = CueCache::get(dir, package_name, &bridge_options);
+        let from_cache = cached.is_some();
+        let result_str = match cached {
+            Some(output) => output,
+            None => Self::evaluate(dir_str, package_name, &bridge_options)?,
+        };
 
         let parse_result = if result_str.is_empty() {
             ParseResult::default()
         } else {
             // Parse and validate JSON response
-            let json_value = parse_json_response(result_str)?;
+            let json_value = parse_json_response(&result_str)?;
             check_for_error_response(&json_value, dir)?;
 
+            if !from_cache {
+                if let Err(e) = CueCache::save(dir, package_name, &bridge_options, &result_str) {
+                    log::debug!("Failed to cache the evaluation of {}: {e}", dir.display());
+                }
+            }
+
             // Deserialize and build final result
             let cue_result = deserialize_cue_result(json_value)?;
             build_parse_result(cue_result, options)?
         };
 
-        // The CStringPtr will be automatically freed when it goes out of scope
         Ok(parse_result)
     }
 
-    pub fn value_to_string(val: &serde_json::Value) -> Option<String> {
-        match val {
-            serde_json::Value::String(s) => Some(s.clone()),
-            serde_json::Value::Number(n) => Some(n.to_string()),
-            serde_json::Value::Bool(b) => Some(b.to_string()),
-            serde_json::Value::Null => None,
-            _ => {
-                log::warn!("Skipping non-primitive value");
-                None
-            }
+    /// Evaluate the package through the bridge, returning its JSON output
+    fn evaluate(dir_str: &str, package_name: &str, bridge_options: &str) -> Result<String> {
+        // Create FFI strings
+        let c_dir = create_ffi_string(dir_str, "invalid directory path")?;
+        let c_package = create_ffi_string(package_name, "invalid package name")?;
+        let c_options = create_ffi_string(bridge_options, "invalid bridge options")?;
+
+        // Call CUE evaluation
+        let result_ptr = call_cue_eval_package(&c_dir, &c_package, &c_options);
+
+        // Wrap the result pointer for automatic cleanup
+        // Safety: result_ptr is either null or a valid pointer returned from cue_eval_package
+        let result_wrapper = unsafe { CStringPtr::new(result_ptr) };
+
+        if result_wrapper.is_null() {
+            return Err(Error::cue_parse(
+                dir_str,
+                "CUE parser returned null pointer",
+            ));
+        }
+
+        // Safety: We've verified the pointer is not null
+        let result_str = unsafe { result_wrapper.to_str()? };
+
+        // The CStringPtr will be automatically freed when it goes out of scope
+        Ok(result_str.to_string())
+    }
+
+    /// Format the source of a CUE file the way `cue fmt` does
+    pub fn format(path: &Path, source: &str) -> Result<String> {
+        let c_path = create_ffi_string(&validate_directory_path(path)?, "invalid file path")?;
+        let c_source = create_ffi_string(source, "invalid CUE source")?;
+
+        // Safety: cue_format takes two non-null C strings and returns a
+        // heap-allocated C string that must be freed with cue_free_string
+        let result_ptr = unsafe { super::cue_format(c_path.as_ptr(), c_source.as_ptr()) };
+        // Safety: result_ptr is either null or a valid pointer returned from cue_format
+        let result_wrapper = unsafe { CStringPtr::new(result_ptr) };
+
+        if result_wrapper.is_null() {
+            return Err(Error::cue_parse(
+                path,
+                "CUE formatter returned null pointer",
+            ));
+        }
+
+        // Safety: We've verified the pointer is not null
+        let json_value = parse_json_response(unsafe { result_wrapper.to_str()? })?;
+        check_for_error_response(&json_value, path)?;
+
+        match json_value.get("formatted") {
+            Some(serde_json::Value::String(formatted)) => Ok(formatted.clone()),
+            _ => Err(Error::cue_parse(path, "CUE formatter returned no output")),
         }
     }
 }
@@ -91,13 +206,36 @@ impl Default for CueParser {
     }
 }
 
-fn call_cue_eval_package(dir_path: &CStr, package_name: &CStr) -> *mut std::os::raw::c_char {
-    // Safety: cue_eval_package is an external C function that:
-    // - Takes two non-null C string pointers as arguments
+/// Everything besides the sources that the configuration extracted from a
+/// package depends on
+fn layer_key(options: &ParseOptions, system: &SystemInfo) -> String {
+    serde_json::json!({
+        "environment": options.environment,
+        "capabilities": options.capabilities,
+        "tags": options.tags,
+        "native": !options.full_evaluation && native::enabled(),
+        "system": system,
+    })
+    .to_string()
+}
+
+fn call_cue_eval_package(
+    dir_path: &CStr,
+    package_name: &CStr,
+    options: &CStr,
+) -> *mut std::os::raw::c_char {
+    // Safety: cue_eval_package_with_options is an external C function that:
+    // - Takes three non-null C string pointers as arguments
     // - Returns a heap-allocated C string that must be freed with cue_free_string
     // - Returns null on allocation failure
     // We ensure the input pointers are valid for the duration of the call
-    unsafe { super::cue_eval_package(dir_path.as_ptr(), package_name.as_ptr()) }
+    unsafe {
+        super::cue_eval_package_with_options(
+            dir_path.as_ptr(),
+            package_name.as_ptr(),
+            options.as_ptr(),
+        )
+    }
 }
 
 fn parse_json_response(json_str: &str) -> Result<serde_json::Value> {
@@ -112,10 +250,26 @@ fn parse_json_response(json_str: &str) -> Result<serde_json::Value> {
     })
 }
 
+/// A position reported by the bridge alongside an error
+#[derive(Deserialize)]
+struct RawDiagnostic {
+    message: String,
+    file: PathBuf,
+    line: usize,
+    column: usize,
+}
+
 fn check_for_error_response(json_value: &serde_json::Value, dir: &Path) -> Result<()> {
     if let serde_json::Value::Object(ref map) = json_value {
         if let Some(serde_json::Value::String(error)) = map.get("error") {
-            let cue_error = Error::cue_parse(dir, error.clone());
+            let diagnostics = map
+                .get("diagnostics")
+                .and_then(|value| Vec::<RawDiagnostic>::deserialize(value).ok())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|raw| Diagnostic::from_source(raw.message, raw.file, raw.line, raw.column))
+                .collect();
+            let cue_error = Error::cue_parse_with_diagnostics(dir, error.clone(), diagnostics);
 
             // Provide specific recovery suggestions based on error content
             let recovery_hint = get_recovery_hint(error);
@@ -129,7 +283,9 @@ fn check_for_error_response(json_value: &serde_json::Value, dir: &Path) -> Resul
 }
 
 fn get_recovery_hint(error: &str) -> &'static str {
-    if error.contains("cannot find package") {
+    if error.contains("cannot import") {
+        "Vendor the imported package under cue.mod/pkg, creating the module with 'cue mod init' if needed"
+    } else if error.contains("cannot find package") {
         "Ensure your .cue files have 'package cuenv' at the top"
     } else if error.contains("expected") || error.contains("syntax") {
         "Check for missing commas, brackets, or quotes in your CUE file"
@@ -248,9 +404,17 @@ fn convert_raw_to_cue_result(raw: RawCueResult) -> Result<CueParseResult> {
         variables,
         metadata,
         environments,
+        platforms: raw.env.platform,
+        hosts: raw.env.hosts,
         commands,
         tasks: raw.tasks,
         hooks,
         config: raw.config,
+        root: raw.root,
+        constraints: raw.constraints,
+        notify: raw.notify,
+        nix: raw.nix,
+        devenv: raw.devenv,
+        shells: raw.shells,
     })
 }
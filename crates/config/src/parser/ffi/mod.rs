@@ -10,9 +10,14 @@ pub use bridge::CueParser;
 
 #[link(name = "cue_bridge")]
 extern "C" {
-    fn cue_eval_package(
+    fn cue_eval_package_with_options(
         dir_path: *const std::os::raw::c_char,
         package_name: *const std::os::raw::c_char,
+        options_json: *const std::os::raw::c_char,
+    ) -> *mut std::os::raw::c_char;
+    fn cue_format(
+        file_name: *const std::os::raw::c_char,
+        source: *const std::os::raw::c_char,
     ) -> *mut std::os::raw::c_char;
     fn cue_free_string(s: *mut std::os::raw::c_char);
 }
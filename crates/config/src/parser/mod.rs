@@ -3,17 +3,26 @@
 //! This module provides functionality to parse CUE files and extract
 //! environment variables, metadata, commands, tasks, and hooks.
 
+mod data;
 mod ffi;
+pub(crate) mod native;
 mod processing;
+mod system;
+mod tags;
 mod types;
 mod validation;
 
 pub use ffi::CueParser;
-pub use processing::{ParseOptions, ParseResult};
+pub use processing::{ParseOptions, ParseResult, ENVIRONMENT_SEPARATOR};
+pub use system::SystemInfo;
+pub use tags::{join_tags, parse_tag, tags_from_env};
 pub use types::{
-    CacheEnvConfig, CommandConfig, ConfigSettings, Hook, HookConfig, HookConstraint, HookType,
-    HookValue, SecurityConfig, TaskCacheConfig, TaskConfig, TaskGroupMode, TaskNode,
-    VariableMetadata,
+    ArtifactType, CacheEnvConfig, CommandConfig, CommandValue, ConfigSettings, DevenvConfig,
+    EnvOverlays, Hook, HookConfig, HookConstraint, HookType, HookValue, ListModifier, NixConfig,
+    Notification, NotifyConfig, OutputArtifact, RestartPolicy, SecurityConfig, SensitiveValue,
+    ServiceConfig, ShellProfile, TaskCacheConfig, TaskConfig, TaskGroupMode, TaskNode, TaskOutputs,
+    TaskShell, TerraformOutput, TerraformValue, VariableConstraint, VariableMetadata,
+    DEFAULT_LIST_SEPARATOR,
 };
 
 #[cfg(test)]
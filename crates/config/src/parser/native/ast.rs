@@ -0,0 +1,376 @@
+//! Syntax of the CUE subset
+
+use super::lexer::{tokenize, Fragment, Token};
+use super::value::Value;
+use super::{Eval, Unsupported};
+
+/// The fields declared by a struct literal, or by a file
+#[derive(Debug, Default)]
+pub struct StructLit {
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub label: String,
+    /// Quoted labels cannot be referenced
+    pub quoted: bool,
+    pub value: Expr,
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Vec<Part>),
+    Ident(String),
+    Select(Box<Expr>, String),
+    Struct(StructLit),
+    List(Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// Unification of the expressions, `a & b`
+    And(Vec<Expr>),
+    /// A disjunction, each alternative marked when it is a default
+    Or(Vec<(Expr, bool)>),
+    /// A value provided by cuenv rather than written in a file
+    Value(Value),
+}
+
+#[derive(Debug)]
+pub enum Part {
+    Text(String),
+    Interpolation(Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Ne,
+    Match,
+    NotMatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+/// Words that start declarations outside the subset
+const KEYWORDS: &[&str] = &["package", "import", "let", "for", "if", "in"];
+
+/// Parse a file of `package_name` into the fields it declares
+pub fn parse_file(source: &str, package_name: &str) -> Eval<StructLit> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+    };
+
+    if !parser.eat_ident("package") {
+        return Err(Unsupported::new("file without a package clause"));
+    }
+    match parser.next() {
+        Some(Token::Ident(name)) if name == package_name => {}
+        _ => return Err(Unsupported::new("file of another package")),
+    }
+    let fields = parser.fields(None)?;
+    Ok(StructLit { fields })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn at_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.at_punct(punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(i)) if i == ident);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Eval<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(Unsupported::new(format!("expected '{punct}'")))
+        }
+    }
+
+    fn skip_commas(&mut self) {
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+        }
+    }
+
+    /// Fields up to the closing punctuation, or to the end of the file
+    fn fields(&mut self, closing: Option<&str>) -> Eval<Vec<Field>> {
+        let mut fields = Vec::new();
+        loop {
+            self.skip_commas();
+            let done = match closing {
+                Some(closing) => self.eat_punct(closing),
+                None => self.peek().is_none(),
+            };
+            if done {
+                return Ok(fields);
+            }
+
+            fields.push(self.field()?);
+            let separated = self.peek() == Some(&Token::Comma)
+                || closing.is_some_and(|closing| self.at_punct(closing));
+            if !separated {
+                return Err(Unsupported::new("declaration"));
+            }
+        }
+    }
+
+    fn field(&mut self) -> Eval<Field> {
+        let (label, quoted) = match self.next() {
+            Some(Token::Ident(ident)) if KEYWORDS.contains(&ident.as_str()) => {
+                return Err(Unsupported::new(format!("'{ident}' declaration")))
+            }
+            Some(Token::Ident(ident)) if ident != "_" => (ident, false),
+            Some(Token::Str(fragments)) => match fragments.as_slice() {
+                [Fragment::Text(text)] => (text.clone(), true),
+                _ => return Err(Unsupported::new("interpolated label")),
+            },
+            _ => return Err(Unsupported::new("embedding or pattern constraint")),
+        };
+        if label.starts_with('#') {
+            return Err(Unsupported::new("definition"));
+        }
+        if !self.eat_punct(":") {
+            return Err(Unsupported::new("optional field, alias or embedding"));
+        }
+
+        // `a: b: 1` declares the struct `a: {b: 1}`
+        let nested = matches!(self.peek(), Some(Token::Ident(_) | Token::Str(_)))
+            && matches!(self.tokens.get(self.pos + 1), Some(Token::Punct(":")));
+        let value = if nested {
+            Expr::Struct(StructLit {
+                fields: vec![self.field()?],
+            })
+        } else {
+            self.expr()?
+        };
+
+        while let Some(Token::Attribute(name)) = self.peek() {
+            if name == "tag" {
+                return Err(Unsupported::new("@tag attribute"));
+            }
+            self.pos += 1;
+        }
+        Ok(Field {
+            label,
+            quoted,
+            value,
+        })
+    }
+
+    fn expr(&mut self) -> Eval<Expr> {
+        let mut alternatives = Vec::new();
+        loop {
+            let default = self.eat_punct("*");
+            alternatives.push((self.conjunction()?, default));
+            if !self.eat_punct("|") {
+                break;
+            }
+        }
+
+        if alternatives.len() == 1 {
+            let (expr, default) = alternatives.remove(0);
+            if default {
+                return Err(Unsupported::new("default outside a disjunction"));
+            }
+            return Ok(expr);
+        }
+        Ok(Expr::Or(alternatives))
+    }
+
+    fn conjunction(&mut self) -> Eval<Expr> {
+        let mut operands = vec![self.sum()?];
+        while self.eat_punct("&") {
+            operands.push(self.sum()?);
+        }
+        if operands.len() == 1 {
+            return Ok(operands.remove(0));
+        }
+        Ok(Expr::And(operands))
+    }
+
+    fn sum(&mut self) -> Eval<Expr> {
+        let mut expr = self.product()?;
+        loop {
+            let op = if self.eat_punct("+") {
+                BinaryOp::Add
+            } else if self.eat_punct("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Eval<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat_punct("*") {
+            expr = Expr::Binary(BinaryOp::Mul, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Eval<Expr> {
+        let op = match self.peek() {
+            Some(Token::Punct("-")) => UnaryOp::Neg,
+            Some(Token::Punct("<")) => UnaryOp::Lt,
+            Some(Token::Punct("<=")) => UnaryOp::Le,
+            Some(Token::Punct(">")) => UnaryOp::Gt,
+            Some(Token::Punct(">=")) => UnaryOp::Ge,
+            Some(Token::Punct("!=")) => UnaryOp::Ne,
+            Some(Token::Punct("=~")) => UnaryOp::Match,
+            Some(Token::Punct("!~")) => UnaryOp::NotMatch,
+            _ => return self.primary(),
+        };
+        self.pos += 1;
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Eval<Expr> {
+        let mut expr = self.operand()?;
+        while self.eat_punct(".") {
+            match self.next() {
+                Some(Token::Ident(label)) if !label.starts_with('#') => {
+                    expr = Expr::Select(Box::new(expr), label);
+                }
+                _ => return Err(Unsupported::new("selector")),
+            }
+        }
+        if self.at_punct("(") || self.at_punct("[") {
+            return Err(Unsupported::new("call or index expression"));
+        }
+        Ok(expr)
+    }
+
+    fn operand(&mut self) -> Eval<Expr> {
+        match self.next() {
+            Some(Token::Int(i)) => Ok(Expr::Int(i)),
+            Some(Token::Float(f)) => Ok(Expr::Float(f)),
+            Some(Token::Str(fragments)) => fragments
+                .into_iter()
+                .map(|fragment| match fragment {
+                    Fragment::Text(text) => Ok(Part::Text(text)),
+                    Fragment::Interpolation(tokens) => {
+                        let mut parser = Parser { tokens, pos: 0 };
+                        let expr = parser.expr()?;
+                        parser.skip_commas();
+                        if parser.peek().is_some() {
+                            return Err(Unsupported::new("interpolation"));
+                        }
+                        Ok(Part::Interpolation(expr))
+                    }
+                })
+                .collect::<Eval<Vec<_>>>()
+                .map(Expr::Str),
+            Some(Token::Ident(ident)) => Ok(match ident.as_str() {
+                "null" => Expr::Null,
+                "true" => Expr::Bool(true),
+                "false" => Expr::Bool(false),
+                _ if KEYWORDS.contains(&ident.as_str()) => {
+                    return Err(Unsupported::new(format!("'{ident}' expression")))
+                }
+                _ => Expr::Ident(ident),
+            }),
+            Some(Token::Punct("(")) => {
+                let expr = self.expr()?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Some(Token::Punct("{")) => Ok(Expr::Struct(StructLit {
+                fields: self.fields(Some("}"))?,
+            })),
+            Some(Token::Punct("[")) => {
+                let mut elements = Vec::new();
+                loop {
+                    self.skip_commas();
+                    if self.eat_punct("]") {
+                        return Ok(Expr::List(elements));
+                    }
+                    elements.push(self.expr()?);
+                    if self.peek() != Some(&Token::Comma) && !self.at_punct("]") {
+                        return Err(Unsupported::new("list"));
+                    }
+                }
+            }
+            _ => Err(Unsupported::new("expression")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_labels_declare_structs() {
+        let file = parse_file(
+            "package cuenv\n\ntasks: build: command: \"make\"\n",
+            "cuenv",
+        )
+        .unwrap();
+        let Expr::Struct(tasks) = &file.fields[0].value else {
+            panic!("expected a struct, got {:?}", file.fields[0].value);
+        };
+        assert_eq!(tasks.fields[0].label, "build");
+    }
+
+    #[test]
+    fn test_constructs_outside_the_subset_are_rejected() {
+        for source in [
+            "package cuenv\nimport \"strings\"\n",
+            "package cuenv\na?: string\n",
+            "package cuenv\n#A: {}\n",
+            "package cuenv\na: strings.Join([], \",\")\n",
+            "package cuenv\na: [...string]\n",
+            "package cuenv\nenv: { for k, v in x { } }\n",
+            "package cuenv\na: string @tag(a)\n",
+            "@if(ci)\npackage cuenv\n",
+        ] {
+            assert!(parse_file(source, "cuenv").is_err(), "accepted {source:?}");
+        }
+        assert!(parse_file("package other\n", "cuenv").is_err());
+    }
+}
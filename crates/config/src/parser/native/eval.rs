@@ -0,0 +1,436 @@
+//! Evaluation of parsed files
+//!
+//! Every field of the configuration is a vertex, identified by its path,
+//! whose value is the unification of the expressions declared for it. The
+//! expressions are gathered from every struct literal declaring the field,
+//! including literals brought in by references, so fields that refer to
+//! their siblings are evaluated against the struct they end up in, as in
+//! CUE.
+
+use super::ast::{BinaryOp, Expr, Part, StructLit, UnaryOp};
+use super::value::{
+    disjunction, kind, push_alternative, unify, Comparison, Constraint, Struct, Value,
+};
+use super::{Eval, Unsupported};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Label {
+    Field(String),
+    /// A struct literal evaluated on its own, such as a list element
+    Anonymous(usize),
+}
+
+type Path = Vec<Label>;
+
+/// The struct literals identifiers are resolved in, innermost first
+struct Frame<'a> {
+    literals: Vec<&'a StructLit>,
+    /// The vertex the literals declare the fields of
+    path: Path,
+    parent: Option<Rc<Frame<'a>>>,
+}
+
+#[derive(Clone)]
+enum Conjunct<'a> {
+    Expr(&'a Expr, Rc<Frame<'a>>),
+    Value(Value),
+}
+
+impl Conjunct<'_> {
+    fn literal(&self) -> Option<&StructLit> {
+        match self {
+            Conjunct::Expr(Expr::Struct(literal), _) => Some(literal),
+            _ => None,
+        }
+    }
+}
+
+/// Where the value of a referenced vertex comes from
+enum Lookup<'a> {
+    Conjuncts(Rc<Vec<Conjunct<'a>>>),
+    /// A field of a vertex whose value is not only struct literals
+    Value(Value),
+}
+
+pub struct Evaluator<'a> {
+    root: Rc<Frame<'a>>,
+    conjuncts: HashMap<Path, Rc<Vec<Conjunct<'a>>>>,
+    values: HashMap<Path, Value>,
+    /// Vertices being gathered or evaluated, to detect cycles
+    gathering: HashSet<Path>,
+    evaluating: HashSet<Path>,
+    anonymous: Vec<Conjunct<'a>>,
+    /// Anonymous vertices whose fields refer to each other
+    sealed: HashSet<usize>,
+}
+
+impl<'a> Evaluator<'a> {
+    /// An evaluator for the files of a package
+    pub fn new(files: Vec<&'a StructLit>) -> Self {
+        Self {
+            root: Rc::new(Frame {
+                literals: files,
+                path: Vec::new(),
+                parent: None,
+            }),
+            conjuncts: HashMap::new(),
+            values: HashMap::new(),
+            gathering: HashSet::new(),
+            evaluating: HashSet::new(),
+            anonymous: Vec::new(),
+            sealed: HashSet::new(),
+        }
+    }
+
+    /// The value of the package
+    pub fn evaluate(&mut self) -> Eval<Value> {
+        let labels: BTreeSet<String> = self
+            .root
+            .literals
+            .iter()
+            .flat_map(|literal| literal.fields.iter().map(|field| field.label.clone()))
+            .collect();
+
+        let mut fields = std::collections::BTreeMap::new();
+        for label in labels {
+            let value = self.value(&[Label::Field(label.clone())])?;
+            fields.insert(label, value);
+        }
+        Ok(Value::Struct(Struct {
+            fields,
+            sealed: false,
+        }))
+    }
+
+    /// The expressions declared for a vertex, with references expanded
+    fn conjuncts(&mut self, path: &[Label]) -> Eval<Rc<Vec<Conjunct<'a>>>> {
+        if let Some(conjuncts) = self.conjuncts.get(path) {
+            return Ok(conjuncts.clone());
+        }
+        if !self.gathering.insert(path.to_vec()) {
+            return Err(Unsupported::new("reference cycle"));
+        }
+
+        let mut conjuncts = Vec::new();
+        match path.split_last() {
+            Some((Label::Anonymous(id), [])) => conjuncts.push(self.anonymous[*id].clone()),
+            Some((Label::Field(label), [])) => {
+                let root = self.root.clone();
+                for literal in root.literals.clone() {
+                    for field in literal.fields.iter().filter(|f| f.label == *label) {
+                        self.expand(&field.value, &root, &mut conjuncts)?;
+                    }
+                }
+            }
+            Some((Label::Field(label), parent)) => {
+                for conjunct in self.conjuncts(parent)?.iter() {
+                    let Conjunct::Expr(expr, env) = conjunct else {
+                        continue;
+                    };
+                    let expr: &'a Expr = expr;
+                    let Expr::Struct(literal) = expr else {
+                        continue;
+                    };
+                    let frame = Rc::new(Frame {
+                        literals: vec![literal],
+                        path: parent.to_vec(),
+                        parent: Some(env.clone()),
+                    });
+                    for field in literal.fields.iter().filter(|f| f.label == *label) {
+                        self.expand(&field.value, &frame, &mut conjuncts)?;
+                    }
+                }
+            }
+            _ => return Err(Unsupported::new("invalid path")),
+        }
+
+        self.gathering.remove(path);
+        let conjuncts = Rc::new(conjuncts);
+        self.conjuncts.insert(path.to_vec(), conjuncts.clone());
+        Ok(conjuncts)
+    }
+
+    /// Add an expression to the conjuncts of a vertex, splitting
+    /// unifications and replacing references by what they refer to
+    fn expand(
+        &mut self,
+        expr: &'a Expr,
+        frame: &Rc<Frame<'a>>,
+        conjuncts: &mut Vec<Conjunct<'a>>,
+    ) -> Eval<()> {
+        match expr {
+            Expr::And(operands) => {
+                for operand in operands {
+                    self.expand(operand, frame, conjuncts)?;
+                }
+            }
+            Expr::Ident(_) | Expr::Select(..) => match self.resolve(expr, frame)? {
+                Some(path) => match self.lookup(&path)? {
+                    Lookup::Conjuncts(referenced) => conjuncts.extend(referenced.iter().cloned()),
+                    Lookup::Value(value) => conjuncts.push(Conjunct::Value(value)),
+                },
+                None => conjuncts.push(Conjunct::Expr(expr, frame.clone())),
+            },
+            _ => conjuncts.push(Conjunct::Expr(expr, frame.clone())),
+        }
+        Ok(())
+    }
+
+    /// The vertex an identifier or selector refers to
+    fn resolve(&mut self, expr: &Expr, frame: &Rc<Frame<'a>>) -> Eval<Option<Path>> {
+        match expr {
+            Expr::Ident(name) => {
+                let mut current = Some(frame.clone());
+                while let Some(frame) = current {
+                    let mut fields = frame
+                        .literals
+                        .iter()
+                        .flat_map(|literal| &literal.fields)
+                        .filter(|field| field.label == *name);
+                    if let Some(field) = fields.next() {
+                        if field.quoted {
+                            return Err(Unsupported::new("reference to a quoted label"));
+                        }
+                        if let Some(Label::Anonymous(id)) = frame.path.first() {
+                            self.sealed.insert(*id);
+                        }
+                        let mut path = frame.path.clone();
+                        path.push(Label::Field(name.clone()));
+                        return Ok(Some(path));
+                    }
+                    current = frame.parent.clone();
+                }
+                Ok(None)
+            }
+            Expr::Select(base, label) => Ok(self.resolve(base, frame)?.map(|mut path| {
+                path.push(Label::Field(label.clone()));
+                path
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// How to evaluate a referenced vertex. The conjuncts of a vertex only
+    /// hold what its ancestors' struct literals declare, so below an
+    /// ancestor with other values the field is selected from its value.
+    fn lookup(&mut self, path: &[Label]) -> Eval<Lookup<'a>> {
+        for depth in 1..path.len() {
+            let ancestor = &path[..depth];
+            if self
+                .conjuncts(ancestor)?
+                .iter()
+                .all(|c| c.literal().is_some())
+            {
+                continue;
+            }
+            let mut value = self.value(ancestor)?;
+            for label in &path[depth..] {
+                let Label::Field(label) = label else {
+                    return Err(Unsupported::new("invalid path"));
+                };
+                value = value.field(label)?;
+            }
+            return Ok(Lookup::Value(value));
+        }
+        Ok(Lookup::Conjuncts(self.conjuncts(path)?))
+    }
+
+    /// The value of a vertex
+    fn value(&mut self, path: &[Label]) -> Eval<Value> {
+        if let Some(value) = self.values.get(path) {
+            return Ok(value.clone());
+        }
+        if !self.evaluating.insert(path.to_vec()) {
+            return Err(Unsupported::new("structural cycle"));
+        }
+
+        let conjuncts = self.conjuncts(path)?;
+        let labels: BTreeSet<String> = conjuncts
+            .iter()
+            .filter_map(Conjunct::literal)
+            .flat_map(|literal| literal.fields.iter().map(|field| field.label.clone()))
+            .collect();
+
+        let mut value = Value::Top;
+        if conjuncts.iter().any(|c| c.literal().is_some()) {
+            let mut fields = std::collections::BTreeMap::new();
+            for label in labels {
+                let mut child = path.to_vec();
+                child.push(Label::Field(label.clone()));
+                fields.insert(label, self.value(&child)?);
+            }
+            value = Value::Struct(Struct {
+                fields,
+                sealed: false,
+            });
+        }
+        for conjunct in conjuncts.iter().filter(|c| c.literal().is_none()) {
+            let operand = match conjunct {
+                Conjunct::Value(value) => value.clone(),
+                Conjunct::Expr(expr, frame) => self.eval(expr, frame)?,
+            };
+            value = unify(&value, &operand)?;
+        }
+        if value == Value::Bottom {
+            return Err(Unsupported::new("conflicting values"));
+        }
+
+        self.evaluating.remove(path);
+        self.values.insert(path.to_vec(), value.clone());
+        Ok(value)
+    }
+
+    fn eval(&mut self, expr: &'a Expr, frame: &Rc<Frame<'a>>) -> Eval<Value> {
+        Ok(match expr {
+            Expr::Null => Value::Null,
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Int(i) => Value::Int(*i),
+            Expr::Float(f) => Value::Float(*f),
+            Expr::Value(value) => value.clone(),
+            Expr::Str(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        Part::Text(s) => text.push_str(s),
+                        Part::Interpolation(expr) => match self.eval(expr, frame)?.scalar()? {
+                            Some(Value::String(s)) => text.push_str(s),
+                            Some(Value::Int(i)) => text.push_str(&i.to_string()),
+                            Some(Value::Bool(b)) => text.push_str(&b.to_string()),
+                            Some(_) => return Err(Unsupported::new("interpolated value")),
+                            None => return Ok(Value::Incomplete),
+                        },
+                    }
+                }
+                Value::String(text)
+            }
+            Expr::Ident(_) | Expr::Select(..) => match self.resolve(expr, frame)? {
+                Some(path) => match self.lookup(&path)? {
+                    Lookup::Conjuncts(_) => self.value(&path)?,
+                    Lookup::Value(value) => value,
+                },
+                None => match expr {
+                    Expr::Ident(name) => builtin(name)?,
+                    Expr::Select(base, label) => self.eval(base, frame)?.field(label)?,
+                    _ => unreachable!(),
+                },
+            },
+            Expr::Struct(_) => {
+                let id = self.anonymous.len();
+                self.anonymous.push(Conjunct::Expr(expr, frame.clone()));
+                let mut value = self.value(&[Label::Anonymous(id)])?;
+                if self.sealed.contains(&id) {
+                    value.seal();
+                }
+                value
+            }
+            Expr::List(elements) => Value::List(
+                elements
+                    .iter()
+                    .map(|element| self.eval(element, frame))
+                    .collect::<Eval<_>>()?,
+            ),
+            Expr::Unary(op, operand) => match self.eval(operand, frame)?.scalar()? {
+                Some(operand) => unary(*op, operand)?,
+                None => Value::Incomplete,
+            },
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left, frame)?;
+                let right = self.eval(right, frame)?;
+                match (left.scalar()?, right.scalar()?) {
+                    (Some(left), Some(right)) => binary(*op, left, right)?,
+                    _ => Value::Incomplete,
+                }
+            }
+            Expr::And(operands) => {
+                let mut value = Value::Top;
+                for operand in operands {
+                    value = unify(&value, &self.eval(operand, frame)?)?;
+                }
+                value
+            }
+            Expr::Or(alternatives) => {
+                let mut values = Vec::new();
+                for (alternative, marked) in alternatives {
+                    match self.eval(alternative, frame)? {
+                        Value::Disjunction(nested) => {
+                            if nested.iter().any(|(_, default)| *default) {
+                                return Err(Unsupported::new("nested defaults"));
+                            }
+                            for (value, _) in nested {
+                                push_alternative(&mut values, value, *marked);
+                            }
+                        }
+                        value => push_alternative(&mut values, value, *marked),
+                    }
+                }
+                disjunction(values)
+            }
+        })
+    }
+}
+
+/// Predeclared identifiers
+fn builtin(name: &str) -> Eval<Value> {
+    let kinds = match name {
+        "_" => return Ok(Value::Top),
+        "string" => kind::STRING,
+        "int" => kind::INT,
+        "float" => kind::FLOAT,
+        "number" => kind::NUMBER,
+        "bool" => kind::BOOL,
+        _ => return Err(Unsupported::new(format!("reference '{name}'"))),
+    };
+    Ok(Value::Constraint(vec![Constraint::Kind(kinds)]))
+}
+
+fn unary(op: UnaryOp, operand: &Value) -> Eval<Value> {
+    let comparison = match op {
+        UnaryOp::Neg => {
+            return match operand {
+                Value::Int(i) => i
+                    .checked_neg()
+                    .map(Value::Int)
+                    .ok_or_else(|| Unsupported::new("overflow")),
+                Value::Float(f) => Ok(Value::Float(-f)),
+                _ => Err(Unsupported::new("negation")),
+            }
+        }
+        UnaryOp::Match | UnaryOp::NotMatch => {
+            let Value::String(pattern) = operand else {
+                return Err(Unsupported::new("regular expression"));
+            };
+            regex::Regex::new(pattern).map_err(|_| Unsupported::new("regular expression"))?;
+            return Ok(Value::Constraint(vec![Constraint::Regex {
+                pattern: pattern.clone(),
+                negate: op == UnaryOp::NotMatch,
+            }]));
+        }
+        UnaryOp::Lt => Comparison::Lt,
+        UnaryOp::Le => Comparison::Le,
+        UnaryOp::Gt => Comparison::Gt,
+        UnaryOp::Ge => Comparison::Ge,
+        UnaryOp::Ne => Comparison::Ne,
+    };
+    if !matches!(operand, Value::Int(_) | Value::Float(_) | Value::String(_)) {
+        return Err(Unsupported::new("bound"));
+    }
+    Ok(Value::Constraint(vec![Constraint::Bound(
+        comparison,
+        Box::new(operand.clone()),
+    )]))
+}
+
+fn binary(op: BinaryOp, left: &Value, right: &Value) -> Eval<Value> {
+    let result = match (op, left, right) {
+        (BinaryOp::Add, Value::String(a), Value::String(b)) => {
+            Some(Value::String(format!("{a}{b}")))
+        }
+        (BinaryOp::Add, Value::Int(a), Value::Int(b)) => a.checked_add(*b).map(Value::Int),
+        (BinaryOp::Sub, Value::Int(a), Value::Int(b)) => a.checked_sub(*b).map(Value::Int),
+        (BinaryOp::Mul, Value::Int(a), Value::Int(b)) => a.checked_mul(*b).map(Value::Int),
+        _ => None,
+    };
+    result.ok_or_else(|| Unsupported::new("arithmetic"))
+}
@@ -0,0 +1,421 @@
+//! The fields the cuenv schema allows
+//!
+//! The CUE runtime validates configurations against `#Cuenv`, so that a
+//! misspelled field such as `dependecies:` fails instead of being ignored.
+//! Packages evaluated natively are checked here against the definitions of
+//! the embedded schema, and one with a field the schema does not declare is
+//! left to the CUE runtime, which reports it with its position. Only which
+//! fields exist is checked, not the types of their values.
+
+use super::lexer::{tokenize, Fragment, Token};
+use super::{Eval, Unsupported};
+use crate::schema::SCHEMA_FILES;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// What the schema declares about the fields of a value
+#[derive(Debug)]
+enum Type {
+    /// A value whose fields are not constrained, such as a string
+    Any,
+    /// A definition, by name
+    Ref(String),
+    Struct(StructType),
+    List(Box<Type>),
+    Or(Vec<Type>),
+}
+
+#[derive(Debug, Default)]
+struct StructType {
+    fields: HashMap<String, Type>,
+    /// Pattern constraints, `None` matching every label
+    patterns: Vec<(Option<Regex>, Type)>,
+    /// Embedded values, which declare the fields not declared here
+    embeds: Vec<Type>,
+    /// Whether `...` allows any other field
+    open: bool,
+}
+
+/// The definitions of the schema, by name
+struct Schema(HashMap<String, Type>);
+
+static SCHEMA: OnceLock<Result<Schema, String>> = OnceLock::new();
+
+/// Check that the fields of a configuration are declared by `#Cuenv`.
+/// Like the CUE runtime, other fields are allowed at the top level.
+pub fn check(config: &Value) -> Eval<()> {
+    let schema = SCHEMA
+        .get_or_init(|| Schema::parse().map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| Unsupported::new(format!("cuenv schema: {e}")))?;
+    let Some(Type::Struct(cuenv)) = schema.0.get("#Cuenv") else {
+        return Err(Unsupported::new("cuenv schema without #Cuenv"));
+    };
+    let Value::Object(fields) = config else {
+        return Ok(());
+    };
+
+    for (label, value) in fields {
+        if let Some(ty) = cuenv.fields.get(label) {
+            if !schema.allows(ty, value) {
+                return Err(Unsupported::new(format!(
+                    "'{label}' does not match the cuenv schema"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Schema {
+    fn parse() -> Eval<Self> {
+        let mut definitions = HashMap::new();
+        for (name, source) in SCHEMA_FILES {
+            let mut parser = Parser {
+                tokens: tokenize(source)?,
+                pos: 0,
+            };
+            parser
+                .definitions(&mut definitions)
+                .map_err(|e| Unsupported::new(format!("{name}: {e}")))?;
+        }
+        Ok(Self(definitions))
+    }
+
+    fn allows(&self, ty: &Type, value: &Value) -> bool {
+        match (ty, value) {
+            (Type::Any, _) => true,
+            // A definition the schema lacks constrains nothing here
+            (Type::Ref(name), _) => self.0.get(name).map_or(true, |ty| self.allows(ty, value)),
+            (Type::Or(alternatives), _) => alternatives.iter().any(|ty| self.allows(ty, value)),
+            (Type::List(element), Value::Array(items)) => {
+                items.iter().all(|item| self.allows(element, item))
+            }
+            (Type::Struct(ty), Value::Object(fields)) => self.allows_fields(ty, fields),
+            _ => false,
+        }
+    }
+
+    fn allows_fields(&self, ty: &StructType, fields: &Map<String, Value>) -> bool {
+        let mut embedded = Map::new();
+        for (label, value) in fields {
+            // Pattern constraints are left out for declared fields, as for
+            // `mode` among the tasks of a group
+            let types: Vec<&Type> = match ty.fields.get(label) {
+                Some(declared) => vec![declared],
+                None => ty
+                    .patterns
+                    .iter()
+                    .filter(|(pattern, _)| pattern.as_ref().map_or(true, |p| p.is_match(label)))
+                    .map(|(_, ty)| ty)
+                    .collect(),
+            };
+            if !types.is_empty() {
+                if !types.iter().all(|ty| self.allows(ty, value)) {
+                    return false;
+                }
+            } else if !ty.embeds.is_empty() {
+                embedded.insert(label.clone(), value.clone());
+            } else if !ty.open {
+                return false;
+            }
+        }
+
+        // The fields not declared by the struct itself must be declared by
+        // every value it embeds
+        let embedded = Value::Object(embedded);
+        ty.open || ty.embeds.iter().all(|embed| self.allows(embed, &embedded))
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn at_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.at_punct(punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Eval<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(Unsupported::new(format!("expected '{punct}'")))
+        }
+    }
+
+    fn skip_commas(&mut self) {
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+        }
+    }
+
+    /// A declaration must end its line, or the struct it is in
+    fn end_declaration(&self) -> Eval<()> {
+        if self.peek() == Some(&Token::Comma) || self.at_punct("}") {
+            Ok(())
+        } else {
+            Err(Unsupported::new("declaration"))
+        }
+    }
+
+    /// The definitions of a schema file, `#Name: type`
+    fn definitions(&mut self, definitions: &mut HashMap<String, Type>) -> Eval<()> {
+        if !matches!(self.next(), Some(Token::Ident(package)) if package == "package") {
+            return Err(Unsupported::new("file without a package clause"));
+        }
+        self.next();
+        loop {
+            self.skip_commas();
+            let name = match self.next() {
+                None => return Ok(()),
+                Some(Token::Ident(name)) if name.starts_with('#') => name,
+                _ => return Err(Unsupported::new("declaration other than a definition")),
+            };
+            self.expect_punct(":")?;
+            definitions.insert(name, self.value()?);
+            if self.peek().is_some() {
+                self.end_declaration()?;
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Eval<Type> {
+        let mut alternatives = Vec::new();
+        loop {
+            self.eat_punct("*");
+            alternatives.push(self.conjunction()?);
+            if !self.eat_punct("|") {
+                break;
+            }
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.remove(0));
+        }
+        Ok(Type::Or(alternatives))
+    }
+
+    /// The first operand of a conjunction that declares fields stands for
+    /// it, the others being bounds such as `int & >0`
+    fn conjunction(&mut self) -> Eval<Type> {
+        let mut ty = self.operand()?;
+        while self.eat_punct("&") {
+            let operand = self.operand()?;
+            if matches!(ty, Type::Any) {
+                ty = operand;
+            }
+        }
+        Ok(ty)
+    }
+
+    fn operand(&mut self) -> Eval<Type> {
+        match self.next() {
+            Some(Token::Ident(name)) if name.starts_with('#') => Ok(Type::Ref(name)),
+            Some(Token::Ident(_) | Token::Str(_) | Token::Int(_) | Token::Float(_)) => {
+                Ok(Type::Any)
+            }
+            Some(Token::Punct("<" | "<=" | ">" | ">=" | "!=" | "=~" | "!~" | "-")) => {
+                self.operand()?;
+                Ok(Type::Any)
+            }
+            Some(Token::Punct("(")) => {
+                let ty = self.expr()?;
+                self.expect_punct(")")?;
+                Ok(ty)
+            }
+            Some(Token::Punct("{")) => self.struct_type().map(Type::Struct),
+            Some(Token::Punct("[")) => self.list_type(),
+            _ => Err(Unsupported::new("expression")),
+        }
+    }
+
+    /// A list, constraining its elements when it is `[...type]`
+    fn list_type(&mut self) -> Eval<Type> {
+        let mut element = Type::Any;
+        loop {
+            self.skip_commas();
+            if self.eat_punct("]") {
+                return Ok(Type::List(Box::new(element)));
+            }
+            if self.eat_punct("...") {
+                if !self.at_punct("]") {
+                    element = self.expr()?;
+                }
+            } else {
+                self.expr()?;
+            }
+        }
+    }
+
+    fn struct_type(&mut self) -> Eval<StructType> {
+        let mut ty = StructType::default();
+        loop {
+            self.skip_commas();
+            if self.eat_punct("}") {
+                return Ok(ty);
+            }
+
+            if self.eat_punct("...") {
+                ty.open = true;
+            } else if !self.declaration(&mut ty)? {
+                ty.embeds.push(self.expr()?);
+            }
+            self.end_declaration()?;
+        }
+    }
+
+    /// A field or pattern constraint of `ty`, or `false` when the next
+    /// tokens declare neither
+    fn declaration(&mut self, ty: &mut StructType) -> Eval<bool> {
+        if self.at_pattern() {
+            self.pos += 1;
+            let pattern = self.pattern()?;
+            self.expect_punct("]")?;
+            self.expect_punct(":")?;
+            ty.patterns.push((pattern, self.value()?));
+        } else if let Some(label) = self.label() {
+            // Optional and required fields are declared all the same
+            if !self.eat_punct("?") {
+                self.eat_punct("!");
+            }
+            self.expect_punct(":")?;
+            ty.fields.insert(label, self.value()?);
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// The value of a field, `a: [string]: b` and `a: b: c` declaring
+    /// structs of their own
+    fn value(&mut self) -> Eval<Type> {
+        let mut ty = StructType::default();
+        if self.declaration(&mut ty)? {
+            return Ok(Type::Struct(ty));
+        }
+        self.expr()
+    }
+
+    /// Whether the next tokens start a pattern constraint, `[...]:`
+    fn at_pattern(&self) -> bool {
+        if !self.at_punct("[") {
+            return false;
+        }
+        let mut depth = 0;
+        for (i, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token {
+                Token::Punct("[") => depth += 1,
+                Token::Punct("]") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return matches!(
+                            self.tokens.get(self.pos + i + 1),
+                            Some(Token::Punct(":"))
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// The label of a field, when the next tokens declare one
+    fn label(&mut self) -> Option<String> {
+        if !matches!(
+            self.tokens.get(self.pos + 1),
+            Some(Token::Punct(":" | "?" | "!"))
+        ) {
+            return None;
+        }
+        let label = match self.peek()? {
+            Token::Ident(label) if !label.starts_with('#') => label.clone(),
+            Token::Str(fragments) => match fragments.as_slice() {
+                [Fragment::Text(label)] => label.clone(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(label)
+    }
+
+    /// The labels a pattern constraint applies to, `None` for all of them
+    fn pattern(&mut self) -> Eval<Option<Regex>> {
+        match self.next() {
+            Some(Token::Ident(ident)) if ident == "string" => Ok(None),
+            Some(Token::Punct("=~")) => match self.next() {
+                Some(Token::Str(fragments)) => match fragments.as_slice() {
+                    [Fragment::Text(pattern)] => Regex::new(pattern)
+                        .map(Some)
+                        .map_err(|e| Unsupported::new(e.to_string())),
+                    _ => Err(Unsupported::new("pattern")),
+                },
+                _ => Err(Unsupported::new("pattern")),
+            },
+            _ => Err(Unsupported::new("pattern constraint")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_parses() {
+        let schema = Schema::parse().unwrap();
+        assert!(schema.0.contains_key("#Cuenv"));
+        assert!(schema.0.contains_key("#Task"));
+    }
+
+    #[test]
+    fn test_declared_fields_are_allowed() {
+        let config = json!({
+            "env": { "HOST": "localhost", "environment": { "ci": { "HOST": "ci" } } },
+            "tasks": {
+                "build": { "command": "make", "dependencies": ["lint"] },
+                "ci": { "mode": "sequential", "lint": { "script": "cargo clippy" } },
+            },
+            "hooks": { "onEnter": [{ "command": "nix", "extra": true }] },
+            // Fields outside the schema are allowed at the top level
+            "services": { "web": { "name": "web" } },
+        });
+        assert!(check(&config).is_ok());
+    }
+
+    #[test]
+    fn test_undeclared_fields_are_rejected() {
+        for config in [
+            json!({ "tasks": { "build": { "command": "make", "dependecies": ["lint"] } } }),
+            json!({ "tasks": { "ci": { "lint": { "comand": "cargo clippy" } } } }),
+            json!({ "env": { "lowercase": "x" } }),
+            json!({ "config": { "outputFormt": "tui" } }),
+        ] {
+            assert!(check(&config).is_err(), "accepted {config}");
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Tokens of the CUE subset
+//!
+//! Commas are inserted at the end of lines the way CUE inserts them, so the
+//! parser only has to handle explicit separators.
+
+use super::{Eval, Unsupported};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Str(Vec<Fragment>),
+    Int(i64),
+    Float(f64),
+    /// A field attribute such as `@capability(aws)`, by name
+    Attribute(String),
+    Punct(&'static str),
+    /// An explicit comma, or one inserted at the end of a line
+    Comma,
+}
+
+/// A piece of a string literal
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fragment {
+    Text(String),
+    /// The tokens of an interpolated `\(expression)`
+    Interpolation(Vec<Token>),
+}
+
+const PUNCTUATION: &[&str] = &[
+    "...", "<=", ">=", "!=", "=~", "!~", "==", "&&", "||", "{", "}", "[", "]", "(", ")", ":", "|",
+    "&", "*", "+", "-", "/", ".", "?", "!", "<", ">", "=",
+];
+
+/// Split a source file into tokens
+pub fn tokenize(source: &str) -> Eval<Vec<Token>> {
+    Lexer {
+        chars: source.chars().collect(),
+        pos: 0,
+    }
+    .tokens(false)
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    /// Tokens up to the end of the source, or up to the parenthesis closing
+    /// an interpolation
+    fn tokens(&mut self, interpolation: bool) -> Eval<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let Some(c) = self.peek(0) else {
+                if interpolation {
+                    return Err(Unsupported::new("unterminated interpolation"));
+                }
+                break;
+            };
+
+            match c {
+                ' ' | '\t' | '\r' => self.pos += 1,
+                '\n' => {
+                    self.pos += 1;
+                    if ends_line(tokens.last()) {
+                        tokens.push(Token::Comma);
+                    }
+                }
+                '/' if self.peek(1) == Some('/') => {
+                    while self.peek(0).is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                '"' => tokens.push(self.string()?),
+                '0'..='9' => tokens.push(self.number()?),
+                '@' => tokens.push(self.attribute()?),
+                '#' if self
+                    .peek(1)
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_') =>
+                {
+                    // Definitions, as `#Task`, are idents of their own
+                    self.pos += 1;
+                    tokens.push(Token::Ident(format!("#{}", self.ident())));
+                }
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let ident = self.ident();
+                    if ident == "_" && self.starts_with("|_") {
+                        return Err(Unsupported::new("bottom literal"));
+                    }
+                    tokens.push(Token::Ident(ident));
+                }
+                ',' => {
+                    self.pos += 1;
+                    tokens.push(Token::Comma);
+                }
+                '(' if interpolation => {
+                    self.pos += 1;
+                    depth += 1;
+                    tokens.push(Token::Punct("("));
+                }
+                ')' if interpolation && depth == 0 => {
+                    self.pos += 1;
+                    return Ok(tokens);
+                }
+                ')' if interpolation => {
+                    self.pos += 1;
+                    depth -= 1;
+                    tokens.push(Token::Punct(")"));
+                }
+                _ => {
+                    let Some(punct) = PUNCTUATION.iter().find(|p| self.starts_with(p)) else {
+                        return Err(Unsupported::new(format!("unsupported character '{c}'")));
+                    };
+                    self.pos += punct.len();
+                    tokens.push(Token::Punct(punct));
+                }
+            }
+        }
+
+        if ends_line(tokens.last()) {
+            tokens.push(Token::Comma);
+        }
+        Ok(tokens)
+    }
+
+    fn ident(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek(0)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Decimal integers and floats without exponents or separators
+    fn number(&mut self) -> Eval<Token> {
+        let start = self.pos;
+        while self.peek(0).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let mut float = false;
+        if self.peek(0) == Some('.') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+            float = true;
+            self.pos += 1;
+            while self.peek(0).is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        if self
+            .peek(0)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            return Err(Unsupported::new("number literal"));
+        }
+
+        let text: String = self.chars[start..self.pos].iter().collect();
+        if float {
+            return text
+                .parse()
+                .map(Token::Float)
+                .map_err(|_| Unsupported::new("number literal"));
+        }
+        if text.len() > 1 && text.starts_with('0') {
+            return Err(Unsupported::new("number literal"));
+        }
+        text.parse()
+            .map(Token::Int)
+            .map_err(|_| Unsupported::new("number literal"))
+    }
+
+    /// An attribute, skipping its arguments
+    fn attribute(&mut self) -> Eval<Token> {
+        self.pos += 1;
+        let name = self.ident();
+        if name.is_empty() || self.peek(0) != Some('(') {
+            return Err(Unsupported::new("attribute"));
+        }
+
+        let mut depth = 0;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(Token::Attribute(name));
+                    }
+                }
+                '\n' => break,
+                _ => {}
+            }
+        }
+        Err(Unsupported::new("unterminated attribute"))
+    }
+
+    fn string(&mut self) -> Eval<Token> {
+        if self.starts_with("\"\"\"") {
+            return self.multiline_string();
+        }
+
+        self.pos += 1;
+        let mut fragments = Vec::new();
+        let mut text = String::new();
+        loop {
+            match self.peek(0) {
+                None | Some('\n') => return Err(Unsupported::new("unterminated string")),
+                Some('"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') => self.escape(&mut text, &mut fragments)?,
+                Some(c) => {
+                    self.pos += 1;
+                    text.push(c);
+                }
+            }
+        }
+        Ok(Token::Str(finish(fragments, text)))
+    }
+
+    /// A `"""` string, with the indentation of its closing delimiter
+    /// removed from every line
+    fn multiline_string(&mut self) -> Eval<Token> {
+        self.pos += 3;
+        if self.peek(0) != Some('\n') {
+            return Err(Unsupported::new("multi-line string"));
+        }
+        let indent = self.closing_indent()?;
+
+        let mut fragments = Vec::new();
+        let mut text = String::new();
+        let mut line_start = true;
+        loop {
+            if line_start {
+                line_start = false;
+                if self.starts_with(&format!("{indent}\"\"\"")) {
+                    self.pos += indent.chars().count() + 3;
+                    // The newline before the closing delimiter is not content
+                    text.pop();
+                    break;
+                }
+                if self.starts_with(&indent) {
+                    self.pos += indent.chars().count();
+                } else if self.blank_line() {
+                    while self.peek(0).is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                } else {
+                    return Err(Unsupported::new("multi-line string indentation"));
+                }
+            }
+
+            match self.peek(0) {
+                None => return Err(Unsupported::new("unterminated string")),
+                Some('\n') => {
+                    self.pos += 1;
+                    text.push('\n');
+                    line_start = true;
+                }
+                Some('\\') => self.escape(&mut text, &mut fragments)?,
+                Some(c) => {
+                    self.pos += 1;
+                    text.push(c);
+                }
+            }
+        }
+
+        // The opening line break is not content either
+        match fragments.first_mut() {
+            Some(Fragment::Text(first)) if first.starts_with('\n') => {
+                first.remove(0);
+            }
+            None if text.starts_with('\n') => {
+                text.remove(0);
+            }
+            _ => return Err(Unsupported::new("multi-line string")),
+        }
+        Ok(Token::Str(finish(fragments, text)))
+    }
+
+    /// The whitespace before the `"""` closing the string at the position
+    fn closing_indent(&self) -> Eval<String> {
+        let rest: String = self.chars[self.pos..].iter().collect();
+        rest.lines()
+            .skip(1)
+            .find(|line| line.trim_start().starts_with("\"\"\""))
+            .map(|line| line[..line.len() - line.trim_start().len()].to_string())
+            .ok_or_else(|| Unsupported::new("unterminated string"))
+    }
+
+    /// Whether only whitespace remains on the current line
+    fn blank_line(&self) -> bool {
+        self.chars[self.pos..]
+            .iter()
+            .take_while(|c| **c != '\n')
+            .all(|c| c.is_whitespace())
+    }
+
+    fn escape(&mut self, text: &mut String, fragments: &mut Vec<Fragment>) -> Eval<()> {
+        self.pos += 1;
+        let Some(c) = self.peek(0) else {
+            return Err(Unsupported::new("unterminated string"));
+        };
+        self.pos += 1;
+        match c {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            'r' => text.push('\r'),
+            '"' | '\\' | '/' => text.push(c),
+            'u' => {
+                let hex: String = (0..4).filter_map(|i| self.peek(i)).collect();
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 4)
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| Unsupported::new("unicode escape"))?;
+                self.pos += 4;
+                text.push(c);
+            }
+            '(' => {
+                if !text.is_empty() {
+                    fragments.push(Fragment::Text(std::mem::take(text)));
+                }
+                fragments.push(Fragment::Interpolation(self.tokens(true)?));
+            }
+            _ => return Err(Unsupported::new(format!("escape sequence '\\{c}'"))),
+        }
+        Ok(())
+    }
+}
+
+fn finish(mut fragments: Vec<Fragment>, text: String) -> Vec<Fragment> {
+    if !text.is_empty() || fragments.is_empty() {
+        fragments.push(Fragment::Text(text));
+    }
+    fragments
+}
+
+/// Whether a newline after the token ends the declaration
+fn ends_line(token: Option<&Token>) -> bool {
+    match token {
+        Some(Token::Ident(_) | Token::Str(_) | Token::Int(_) | Token::Float(_)) => true,
+        Some(Token::Attribute(_)) => true,
+        Some(Token::Punct(p)) => matches!(*p, ")" | "]" | "}"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commas_are_inserted_at_line_ends() {
+        let tokens = tokenize("a: 1\nb: \"x\" // comment\n").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("a".to_string()),
+                Token::Punct(":"),
+                Token::Int(1),
+                Token::Comma,
+                Token::Ident("b".to_string()),
+                Token::Punct(":"),
+                Token::Str(vec![Fragment::Text("x".to_string())]),
+                Token::Comma,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interpolations_are_tokenized() {
+        let tokens = tokenize(r#""http://\(host):\(port + 1)""#).unwrap();
+        let Token::Str(fragments) = &tokens[0] else {
+            panic!("expected a string, got {tokens:?}");
+        };
+        assert_eq!(fragments.len(), 4);
+        assert_eq!(
+            fragments[1],
+            Fragment::Interpolation(vec![Token::Ident("host".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_multiline_strings_lose_their_indentation() {
+        let tokens = tokenize("s: \"\"\"\n\t\techo a\n\n\t\t  echo b\n\t\t\"\"\"\n").unwrap();
+        assert_eq!(
+            tokens[2],
+            Token::Str(vec![Fragment::Text("echo a\n\n  echo b".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_definitions_are_idents() {
+        let tokens = tokenize("#Def: {}").unwrap();
+        assert_eq!(tokens[0], Token::Ident("#Def".to_string()));
+    }
+
+    #[test]
+    fn test_unsupported_syntax_is_rejected() {
+        assert!(tokenize("a: # b").is_err());
+        assert!(tokenize("a: 0x10").is_err());
+        assert!(tokenize("a: _|_").is_err());
+        assert!(tokenize("a: 'bytes'").is_err());
+    }
+}
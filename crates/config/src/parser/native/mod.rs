@@ -0,0 +1,277 @@
+//! A native evaluator for the subset of CUE most configurations use
+//!
+//! Calling into the Go CUE runtime dominates the latency of the shell hook.
+//! Packages that only merge structs, interpolate strings, and use defaults,
+//! basic types and bounds are evaluated here instead. Anything else, such as
+//! imports, definitions, comprehensions or `@tag()` attributes, leaves the
+//! package to the full evaluator, and so do conflicts and incomplete values,
+//! which the CUE runtime reports with their positions. So are packages with
+//! fields the cuenv schema does not declare.
+
+mod ast;
+mod eval;
+mod fields;
+mod lexer;
+mod value;
+
+use super::system::SystemInfo;
+use crate::package::package_files;
+use ast::{Expr, Field, StructLit};
+use cuenv_core::constants::{CUENV_EVALUATOR_VAR, ENV_LOCAL_CUE_FILENAME};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// `CUENV_EVALUATOR` value that always uses the full evaluator
+pub const FULL_EVALUATOR: &str = "cue";
+
+/// Why a package is left to the full evaluator
+#[derive(Debug)]
+pub struct Unsupported(String);
+
+impl Unsupported {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self(reason.into())
+    }
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub type Eval<T> = std::result::Result<T, Unsupported>;
+
+/// Whether packages may be evaluated natively, unless `CUENV_EVALUATOR`
+/// asks for the full evaluator
+pub fn enabled() -> bool {
+    std::env::var(CUENV_EVALUATOR_VAR).map_or(true, |value| value != FULL_EVALUATOR)
+}
+
+/// Evaluate a package to what the bridge would have returned, or `None`
+/// when it is outside the supported subset
+pub fn evaluate(dir: &Path, package_name: &str, system: &SystemInfo) -> Option<serde_json::Value> {
+    match evaluate_package(dir, package_name, system) {
+        Ok(value) => Some(value),
+        Err(reason) => {
+            log::debug!(
+                "Evaluating {} with the CUE runtime: {reason}",
+                dir.display()
+            );
+            None
+        }
+    }
+}
+
+fn evaluate_package(
+    dir: &Path,
+    package_name: &str,
+    system: &SystemInfo,
+) -> Eval<serde_json::Value> {
+    let files = package_files(dir, package_name);
+    if files.is_empty() {
+        return Err(Unsupported::new("no package files"));
+    }
+    let system = system_field(system)?;
+
+    // Like the bridge, env.local.cue is evaluated on its own and merged over
+    // the package
+    let shared = evaluate_files(&files, package_name, &system)?;
+    let local = dir.join(ENV_LOCAL_CUE_FILENAME);
+    let value = if local.is_file() {
+        merge_local(shared, evaluate_files(&[local], package_name, &system)?)
+    } else {
+        shared
+    };
+    fields::check(&value)?;
+    Ok(value)
+}
+
+fn evaluate_files(
+    files: &[PathBuf],
+    package_name: &str,
+    system: &StructLit,
+) -> Eval<serde_json::Value> {
+    let mut parsed = Vec::with_capacity(files.len());
+    for file in files {
+        let source = std::fs::read_to_string(file)
+            .map_err(|e| Unsupported::new(format!("{}: {e}", file.display())))?;
+        parsed.push(ast::parse_file(&source, package_name)?);
+    }
+
+    let mut literals: Vec<&StructLit> = parsed.iter().collect();
+    literals.push(system);
+    value::manifest(&eval::Evaluator::new(literals).evaluate()?)
+}
+
+/// Evaluate a file of `package_name` on its own, without the system facts,
+/// for files that are not a configuration, such as cuenv.workspace.cue
+pub fn evaluate_file(path: &Path, package_name: &str) -> Eval<serde_json::Value> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| Unsupported::new(format!("{}: {e}", path.display())))?;
+    let file = ast::parse_file(&source, package_name)?;
+    value::manifest(&eval::Evaluator::new(vec![&file]).evaluate()?)
+}
+
+/// The `_cuenv` field the bridge adds to every package
+fn system_field(system: &SystemInfo) -> Eval<StructLit> {
+    let json = serde_json::to_value(system).map_err(|e| Unsupported::new(e.to_string()))?;
+    Ok(StructLit {
+        fields: vec![Field {
+            label: "_cuenv".to_string(),
+            quoted: false,
+            value: Expr::Value(value::Value::from_json(&json)),
+        }],
+    })
+}
+
+/// Overlay the values of env.local.cue onto the shared configuration.
+/// Structs are merged field by field; any other local value replaces the
+/// shared one.
+fn merge_local(shared: serde_json::Value, local: serde_json::Value) -> serde_json::Value {
+    match (shared, local) {
+        (serde_json::Value::Object(mut shared), serde_json::Value::Object(local)) => {
+            for (key, value) in local {
+                let merged = match shared.remove(&key) {
+                    Some(existing) => merge_local(existing, value),
+                    None => value,
+                };
+                shared.insert(key, merged);
+            }
+            serde_json::Value::Object(shared)
+        }
+        (_, local) => local,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn system() -> SystemInfo {
+        SystemInfo {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            hostname: "build-01".to_string(),
+            username: "ci".to_string(),
+            cpu_count: 4,
+            git_branch: "main".to_string(),
+        }
+    }
+
+    fn eval(files: &[(&str, &str)]) -> Option<serde_json::Value> {
+        let temp = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            fs::write(temp.path().join(name), content).unwrap();
+        }
+        evaluate(temp.path(), "cuenv", &system())
+    }
+
+    #[test]
+    fn test_structs_merge_across_files() {
+        let value = eval(&[
+            (
+                "env.cue",
+                "package cuenv\n\nenv: {\n\tHOST: \"localhost\"\n\tURL: \"http://\\(HOST):\\(env.PORT)\"\n}\n",
+            ),
+            (
+                "ports.cue",
+                "package cuenv\n\nenv: PORT: 8080\nenv: PORT: int & >1024\n",
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            value,
+            json!({ "env": { "HOST": "localhost", "PORT": 8080, "URL": "http://localhost:8080" } })
+        );
+    }
+
+    #[test]
+    fn test_defaults_and_system_information() {
+        let value = eval(&[(
+            "env.cue",
+            r#"package cuenv
+
+_jobs: _cuenv.cpuCount * 2
+
+env: {
+	LOG_LEVEL: *"info" | "debug" | "warn"
+	JOBS:      "\(_jobs)"
+	BRANCH:    _cuenv.gitBranch
+	REPLICAS:  *1 | int
+	REPLICAS:  >0
+}
+
+tasks: build: {
+	command: "make"
+	args: ["-j", env.JOBS]
+}
+"#,
+        )])
+        .unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "env": { "LOG_LEVEL": "info", "JOBS": "8", "BRANCH": "main", "REPLICAS": 1 },
+                "tasks": { "build": { "command": "make", "args": ["-j", "8"] } }
+            })
+        );
+    }
+
+    #[test]
+    fn test_references_resolve_in_the_unified_struct() {
+        let value = eval(&[(
+            "env.cue",
+            r#"package cuenv
+
+_service: {
+	name: string
+	url:  "https://\(name).example.com"
+}
+
+services: web: _service
+services: web: name: "web"
+"#,
+        )])
+        .unwrap();
+        assert_eq!(
+            value,
+            json!({ "services": { "web": { "name": "web", "url": "https://web.example.com" } } })
+        );
+    }
+
+    #[test]
+    fn test_local_values_override_shared_ones() {
+        let value = eval(&[
+            (
+                "env.cue",
+                "package cuenv\n\nenv: {\n\tA: \"shared\"\n\tB: \"shared\"\n}\n",
+            ),
+            ("env.local.cue", "package cuenv\n\nenv: A: \"local\"\n"),
+        ])
+        .unwrap();
+
+        assert_eq!(value, json!({ "env": { "A": "local", "B": "shared" } }));
+    }
+
+    #[test]
+    fn test_unsupported_packages_fall_back() {
+        for content in [
+            "package cuenv\n\nimport \"strings\"\n\nenv: A: strings.ToUpper(\"a\")\n",
+            "package cuenv\n\nenv: A: \"a\"\nenv: A: \"b\"\n",
+            "package cuenv\n\nenv: A: string\n",
+            "package cuenv\n\n_host: string\nenv: A: \"http://\\(_host)\"\n",
+            "package cuenv\n\nenv: A: \"a\" | \"b\"\n",
+            "package cuenv\n\nenv: A: B\n",
+            "package cuenv\n\na: b\nb: a\n",
+            // Left to the CUE runtime to report with its position
+            "package cuenv\n\ntasks: build: comand: \"make\"\n",
+        ] {
+            assert_eq!(eval(&[("env.cue", content)]), None, "evaluated {content:?}");
+        }
+    }
+}
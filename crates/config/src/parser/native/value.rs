@@ -0,0 +1,430 @@
+//! Values of the CUE subset and their unification
+
+use super::{Eval, Unsupported};
+use std::collections::BTreeMap;
+
+/// Kinds of values, as a bit set
+pub mod kind {
+    pub const STRING: u8 = 1;
+    pub const INT: u8 = 2;
+    pub const FLOAT: u8 = 4;
+    pub const BOOL: u8 = 8;
+    pub const NULL: u8 = 16;
+    pub const NUMBER: u8 = INT | FLOAT;
+    pub const ANY: u8 = STRING | NUMBER | BOOL | NULL;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `_`, which unifies with anything
+    Top,
+    /// The result of a conflict
+    Bottom,
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+    Struct(Struct),
+    /// A type or bound that is not a value yet, like `string` or `>0`
+    Constraint(Vec<Constraint>),
+    /// Alternatives, each marked when it is a default
+    Disjunction(Vec<(Value, bool)>),
+    /// The result of an operation on values that are not concrete yet, like
+    /// `"\(name)"` while `name` is only a `string`
+    Incomplete,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Struct {
+    pub fields: BTreeMap<String, Value>,
+    /// Set when fields refer to each other within a struct evaluated on its
+    /// own. CUE would re-evaluate those references against the fields of a
+    /// unified struct, which this evaluator does not do.
+    pub sealed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constraint {
+    Kind(u8),
+    /// A comparison with a number or string, like `>0`
+    Bound(Comparison, Box<Value>),
+    Regex {
+        pattern: String,
+        negate: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Ne,
+}
+
+impl Value {
+    /// The value from JSON, such as the system information
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Self::Null,
+            serde_json::Value::Bool(b) => Self::Bool(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Self::Int(i),
+                None => Self::Float(n.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(s) => Self::String(s.clone()),
+            serde_json::Value::Array(items) => {
+                Self::List(items.iter().map(Self::from_json).collect())
+            }
+            serde_json::Value::Object(map) => Self::Struct(Struct {
+                fields: map
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Self::from_json(value)))
+                    .collect(),
+                sealed: false,
+            }),
+        }
+    }
+
+    /// The value a disjunction defaults to, or the value itself
+    pub fn resolve_default(&self) -> Eval<&Value> {
+        let Value::Disjunction(alternatives) = self else {
+            return Ok(self);
+        };
+        let mut defaults = alternatives.iter().filter(|(_, marked)| *marked);
+        match (defaults.next(), defaults.next()) {
+            (Some((value, _)), None) => Ok(value),
+            _ => Err(Unsupported::new("disjunction without a single default")),
+        }
+    }
+
+    /// The scalar a value stands for, as used by operators and
+    /// interpolation, or `None` while it is not concrete
+    pub fn scalar(&self) -> Eval<Option<&Value>> {
+        let value = match self.resolve_default() {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        };
+        match value {
+            Value::Null | Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::String(_) => {
+                Ok(Some(value))
+            }
+            Value::Top | Value::Constraint(_) | Value::Incomplete => Ok(None),
+            _ => Err(Unsupported::new("non-scalar operand")),
+        }
+    }
+
+    /// A field of a struct
+    pub fn field(&self, label: &str) -> Eval<Value> {
+        match self.resolve_default()? {
+            Value::Incomplete => Ok(Value::Incomplete),
+            Value::Struct(s) => s
+                .fields
+                .get(label)
+                .cloned()
+                .ok_or_else(|| Unsupported::new(format!("undefined field '{label}'"))),
+            _ => Err(Unsupported::new(format!(
+                "selecting '{label}' from a non-struct"
+            ))),
+        }
+    }
+
+    /// Mark every struct within the value as sealed
+    pub fn seal(&mut self) {
+        match self {
+            Value::Struct(s) => {
+                s.sealed = true;
+                s.fields.values_mut().for_each(Value::seal);
+            }
+            Value::List(items) => items.iter_mut().for_each(Value::seal),
+            Value::Disjunction(alternatives) => {
+                alternatives.iter_mut().for_each(|(value, _)| value.seal())
+            }
+            _ => {}
+        }
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Value::Null => kind::NULL,
+            Value::Bool(_) => kind::BOOL,
+            Value::Int(_) => kind::INT,
+            Value::Float(_) => kind::FLOAT,
+            Value::String(_) => kind::STRING,
+            _ => 0,
+        }
+    }
+}
+
+impl Constraint {
+    /// The kinds of values the constraint admits
+    fn kinds(&self) -> u8 {
+        match self {
+            Constraint::Kind(kinds) => *kinds,
+            Constraint::Bound(Comparison::Ne, _) => kind::ANY,
+            Constraint::Bound(_, bound) if bound.kind() == kind::STRING => kind::STRING,
+            Constraint::Bound(..) => kind::NUMBER,
+            Constraint::Regex { .. } => kind::STRING,
+        }
+    }
+
+    /// Whether a concrete value satisfies the constraint
+    fn admits(&self, value: &Value) -> Eval<bool> {
+        if value.kind() & self.kinds() == 0 {
+            return Ok(false);
+        }
+        match self {
+            Constraint::Kind(_) => Ok(true),
+            Constraint::Bound(Comparison::Ne, bound) => {
+                if value.kind() != bound.kind() {
+                    return Err(Unsupported::new("'!=' between different kinds"));
+                }
+                Ok(value != bound.as_ref())
+            }
+            Constraint::Bound(comparison, bound) => {
+                let ordering = match (value, bound.as_ref()) {
+                    (Value::String(a), Value::String(b)) => a.cmp(b),
+                    (Value::Int(a), Value::Int(b)) => a.cmp(b),
+                    (a, b) => {
+                        let (Some(a), Some(b)) = (as_f64(a), as_f64(b)) else {
+                            return Ok(false);
+                        };
+                        a.partial_cmp(&b)
+                            .ok_or_else(|| Unsupported::new("comparison"))?
+                    }
+                };
+                Ok(match comparison {
+                    Comparison::Lt => ordering.is_lt(),
+                    Comparison::Le => ordering.is_le(),
+                    Comparison::Gt => ordering.is_gt(),
+                    Comparison::Ge => ordering.is_ge(),
+                    Comparison::Ne => ordering.is_ne(),
+                })
+            }
+            Constraint::Regex { pattern, negate } => {
+                let Value::String(s) = value else {
+                    return Ok(false);
+                };
+                let regex = regex::Regex::new(pattern)
+                    .map_err(|_| Unsupported::new("regular expression"))?;
+                Ok(regex.is_match(s) != *negate)
+            }
+        }
+    }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// Unify two values, which is `Bottom` when they conflict
+pub fn unify(a: &Value, b: &Value) -> Eval<Value> {
+    Ok(match (a, b) {
+        (Value::Top, other) | (other, Value::Top) => other.clone(),
+        (Value::Bottom, _) | (_, Value::Bottom) => Value::Bottom,
+        (Value::Incomplete, _) | (_, Value::Incomplete) => Value::Incomplete,
+        (Value::Disjunction(left), Value::Disjunction(right)) => disjoin(left, right)?,
+        (Value::Disjunction(alternatives), other) | (other, Value::Disjunction(alternatives)) => {
+            disjoin(alternatives, &[(other.clone(), false)])?
+        }
+        (Value::Struct(left), Value::Struct(right)) => unify_structs(left, right)?,
+        (Value::List(left), Value::List(right)) => {
+            if left.len() != right.len() {
+                return Ok(Value::Bottom);
+            }
+            let mut items = Vec::with_capacity(left.len());
+            for (l, r) in left.iter().zip(right) {
+                let item = unify(l, r)?;
+                if item == Value::Bottom {
+                    return Ok(Value::Bottom);
+                }
+                items.push(item);
+            }
+            Value::List(items)
+        }
+        (Value::Constraint(left), Value::Constraint(right)) => {
+            let constraints: Vec<Constraint> = left.iter().chain(right).cloned().collect();
+            let kinds = constraints
+                .iter()
+                .fold(kind::ANY, |kinds, constraint| kinds & constraint.kinds());
+            if kinds == 0 {
+                Value::Bottom
+            } else {
+                Value::Constraint(constraints)
+            }
+        }
+        (Value::Constraint(constraints), other) | (other, Value::Constraint(constraints)) => {
+            for constraint in constraints {
+                if !constraint.admits(other)? {
+                    return Ok(Value::Bottom);
+                }
+            }
+            other.clone()
+        }
+        (left, right) => {
+            if left == right {
+                left.clone()
+            } else {
+                Value::Bottom
+            }
+        }
+    })
+}
+
+fn unify_structs(left: &Struct, right: &Struct) -> Eval<Value> {
+    if (left.sealed && !right.fields.is_empty()) || (right.sealed && !left.fields.is_empty()) {
+        return Err(Unsupported::new(
+            "unification with a struct whose fields refer to each other",
+        ));
+    }
+
+    let mut fields = left.fields.clone();
+    for (label, value) in &right.fields {
+        let unified = match fields.get(label) {
+            Some(existing) => unify(existing, value)?,
+            None => value.clone(),
+        };
+        if unified == Value::Bottom {
+            return Ok(Value::Bottom);
+        }
+        fields.insert(label.clone(), unified);
+    }
+    Ok(Value::Struct(Struct {
+        fields,
+        sealed: left.sealed || right.sealed,
+    }))
+}
+
+/// Unify every pair of alternatives. A result is a default when both of its
+/// alternatives are, counting every alternative of a side without defaults
+/// as one.
+fn disjoin(left: &[(Value, bool)], right: &[(Value, bool)]) -> Eval<Value> {
+    let left_defaults = left.iter().any(|(_, marked)| *marked);
+    let right_defaults = right.iter().any(|(_, marked)| *marked);
+
+    let mut alternatives = Vec::new();
+    for (l, l_marked) in left {
+        for (r, r_marked) in right {
+            let value = unify(l, r)?;
+            let marked = (*l_marked || !left_defaults) && (*r_marked || !right_defaults);
+            push_alternative(&mut alternatives, value, marked);
+        }
+    }
+    Ok(disjunction(alternatives))
+}
+
+/// Add an alternative, dropping conflicts and merging duplicates
+pub fn push_alternative(alternatives: &mut Vec<(Value, bool)>, value: Value, marked: bool) {
+    if value == Value::Bottom {
+        return;
+    }
+    match alternatives
+        .iter_mut()
+        .find(|(existing, _)| *existing == value)
+    {
+        Some((_, existing_marked)) => *existing_marked |= marked,
+        None => alternatives.push((value, marked)),
+    }
+}
+
+/// The value of a set of alternatives
+pub fn disjunction(mut alternatives: Vec<(Value, bool)>) -> Value {
+    // Alternatives that are all defaults are the same as none being one
+    if alternatives.iter().all(|(_, marked)| *marked) {
+        alternatives
+            .iter_mut()
+            .for_each(|(_, marked)| *marked = false);
+    }
+    match alternatives.len() {
+        0 => Value::Bottom,
+        1 => alternatives.remove(0).0,
+        _ => Value::Disjunction(alternatives),
+    }
+}
+
+/// The JSON a value exports to, leaving out hidden fields
+pub fn manifest(value: &Value) -> Eval<serde_json::Value> {
+    Ok(match value.resolve_default()? {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Int(i) => serde_json::Value::from(*i),
+        // Whole floats export like the Go runtime encodes them, without a
+        // fractional part
+        Value::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => serde_json::Value::from(*f as i64),
+        Value::Float(f) => serde_json::Number::from_f64(*f)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| Unsupported::new("non-finite number"))?,
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::List(items) => {
+            serde_json::Value::Array(items.iter().map(manifest).collect::<Eval<_>>()?)
+        }
+        Value::Struct(s) => serde_json::Value::Object(
+            s.fields
+                .iter()
+                .filter(|(label, _)| !label.starts_with('_'))
+                .map(|(label, value)| Ok((label.clone(), manifest(value)?)))
+                .collect::<Eval<_>>()?,
+        ),
+        Value::Top | Value::Constraint(_) | Value::Disjunction(_) | Value::Incomplete => {
+            return Err(Unsupported::new("incomplete value"))
+        }
+        Value::Bottom => return Err(Unsupported::new("conflicting values")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string() -> Value {
+        Value::Constraint(vec![Constraint::Kind(kind::STRING)])
+    }
+
+    #[test]
+    fn test_defaults_survive_unification_with_their_type() {
+        let port = Value::Disjunction(vec![
+            (Value::String("8080".to_string()), true),
+            (string(), false),
+        ]);
+
+        let unified = unify(&port, &string()).unwrap();
+        assert_eq!(manifest(&unified).unwrap(), serde_json::json!("8080"));
+
+        let unified = unify(&port, &Value::String("9090".to_string())).unwrap();
+        assert_eq!(unified, Value::String("9090".to_string()));
+    }
+
+    #[test]
+    fn test_conflicts_are_bottom() {
+        assert_eq!(
+            unify(&Value::Int(1), &Value::Float(1.0)).unwrap(),
+            Value::Bottom
+        );
+        let positive = Value::Constraint(vec![Constraint::Bound(
+            Comparison::Gt,
+            Box::new(Value::Int(0)),
+        )]);
+        assert_eq!(unify(&positive, &Value::Int(-1)).unwrap(), Value::Bottom);
+        assert_eq!(unify(&positive, &string()).unwrap(), Value::Bottom);
+    }
+
+    #[test]
+    fn test_sealed_structs_are_not_unified() {
+        let mut sealed = Value::from_json(&serde_json::json!({ "a": 1 }));
+        sealed.seal();
+        let other = Value::from_json(&serde_json::json!({ "a": 1 }));
+
+        assert!(unify(&sealed, &other).is_err());
+        assert_eq!(
+            unify(&sealed, &Value::Struct(Struct::default())).unwrap(),
+            sealed
+        );
+    }
+}
@@ -1,18 +1,28 @@
 //! Processing logic for CUE parse results
 
-use crate::parser::ffi::CueParser;
 use crate::parser::types::{
-    CommandConfig, ConfigSettings, CueParseResult, Hook, HookValue, HooksConfig, TaskConfig,
-    TaskNode, VariableMetadata,
+    is_typed, serialize_value, CommandConfig, CommandValue, ConfigSettings, CueParseResult,
+    DevenvConfig, EnvOverlays, Hook, HookValue, HooksConfig, ListModifier, LocalStoreRef,
+    NixConfig, NotifyConfig, SensitiveValue, ShellProfile, TaskConfig, TaskNode, TaskShell,
+    TerraformValue, VariableConstraint, VariableMetadata, DEFAULT_LIST_SEPARATOR,
 };
 use cuenv_core::errors::Result;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Separates the named environments stacked in `base+gpu+staging`
+pub const ENVIRONMENT_SEPARATOR: char = '+';
+
 #[derive(Default)]
 pub struct ParseOptions {
+    /// A named environment, or several joined with `+` to apply in order
     pub environment: Option<String>,
     pub capabilities: Vec<String>,
+    /// Values for `@tag()` attributes, each as `key=value`
+    pub tags: Vec<String>,
+    /// Always evaluate with the CUE runtime, which checks the configuration
+    /// against the schema
+    pub full_evaluation: bool,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -24,6 +34,42 @@ pub struct ParseResult {
     pub task_nodes: HashMap<String, TaskNode>, // Preserve task structure
     pub hooks: HashMap<String, Vec<Hook>>,
     pub config: Option<ConfigSettings>,
+    /// Whether this package is marked `root: true`, stopping hierarchical lookup
+    #[serde(default)]
+    pub root: bool,
+    /// Validation rules declared under `constraints`, keyed by variable name
+    #[serde(default)]
+    pub constraints: HashMap<String, VariableConstraint>,
+    /// Path-like variables declared as `{prepend, append}` instead of a string
+    #[serde(default)]
+    pub list_variables: HashMap<String, ListModifier>,
+    /// Variables whose value is the output of a `fromCommand`
+    #[serde(default)]
+    pub command_variables: HashMap<String, CommandValue>,
+    /// Variables whose value is an output of a Terraform or OpenTofu module
+    #[serde(default)]
+    pub terraform_variables: HashMap<String, TerraformValue>,
+    /// Platform and host overlays, applied when the environment is loaded
+    #[serde(default)]
+    pub overlays: EnvOverlays,
+    /// The selected environment that last overrode each variable
+    #[serde(default)]
+    pub environment_sources: HashMap<String, String>,
+    /// Original CUE values of variables declared as ints, bools, lists or structs
+    #[serde(default)]
+    pub typed_values: HashMap<String, serde_json::Value>,
+    /// Names of the environments the package declares, sorted
+    #[serde(default)]
+    pub environments: Vec<String>,
+    /// Notifications sent when a task run finishes
+    #[serde(default)]
+    pub notify: Option<NotifyConfig>,
+    /// Nix development shell the environment is layered on
+    #[serde(default)]
+    pub nix: Option<NixConfig>,
+    /// devenv.sh environment the environment is layered on
+    #[serde(default)]
+    pub devenv: Option<DevenvConfig>,
 }
 
 /// Builds the final parse result from CUE data
@@ -31,9 +77,65 @@ pub fn build_parse_result(
     mut cue_result: CueParseResult,
     options: &ParseOptions,
 ) -> Result<ParseResult> {
-    let final_vars = build_filtered_variables(&cue_result, options);
+    let mut final_vars = build_filtered_variables(&cue_result, options);
+    let typed_values = build_typed_values(&cue_result, options);
+    let list_variables = build_structured_variables(&cue_result, options);
+    let command_variables: HashMap<String, CommandValue> =
+        build_structured_variables(&cue_result, options);
+    for value in command_variables.values() {
+        value.ttl_duration()?;
+    }
+    let terraform_variables = build_structured_variables(&cue_result, options);
+
+    // Sensitive values are plain variables whose metadata asks for redaction
+    let sensitive: HashMap<String, SensitiveValue> =
+        build_structured_variables(&cue_result, options);
+    for (name, sensitive) in sensitive {
+        cue_result
+            .metadata
+            .entry(name.clone())
+            .or_default()
+            .sensitive = sensitive.sensitive;
+        final_vars.insert(name, sensitive.value);
+    }
+
+    // Local store secrets resolve like any other secret reference
+    let local_store: HashMap<String, LocalStoreRef> =
+        build_structured_variables(&cue_result, options);
+    for (name, store_ref) in local_store {
+        cue_result
+            .metadata
+            .entry(name.clone())
+            .or_default()
+            .sensitive = true;
+        final_vars.insert(name, store_ref.to_reference());
+    }
+
+    let overlays = EnvOverlays {
+        platform: process_overlays(&cue_result.platforms, &cue_result, options),
+        hosts: process_overlays(&cue_result.hosts, &cue_result, options),
+    };
+
+    let mut environment_sources = HashMap::new();
+    for (env_name, env_vars) in selected_environments(&cue_result, options) {
+        for key in env_vars.keys() {
+            if should_include_variable(key, &cue_result.metadata, &options.capabilities) {
+                environment_sources.insert(key.clone(), env_name.to_string());
+            }
+        }
+    }
+
+    let mut environments: Vec<String> = cue_result.environments.keys().cloned().collect();
+    environments.sort();
+
     let hooks = extract_hooks(cue_result.hooks);
-    let (tasks, task_nodes) = process_tasks_with_structure(cue_result.tasks);
+    let (mut tasks, mut task_nodes) = process_tasks_with_structure(cue_result.tasks);
+    for task in tasks.values_mut() {
+        resolve_shell_profile(task, &cue_result.shells);
+    }
+    for node in task_nodes.values_mut() {
+        resolve_node_shell_profiles(node, &cue_result.shells);
+    }
 
     // Validate config if present
     if let Some(ref config) = cue_result.config {
@@ -50,6 +152,18 @@ pub fn build_parse_result(
         task_nodes,
         hooks,
         config: cue_result.config,
+        root: cue_result.root,
+        constraints: cue_result.constraints,
+        list_variables,
+        command_variables,
+        terraform_variables,
+        overlays,
+        environment_sources,
+        typed_values,
+        environments,
+        notify: cue_result.notify,
+        nix: cue_result.nix,
+        devenv: cue_result.devenv,
     })
 }
 
@@ -73,17 +187,38 @@ fn should_include_variable(
     }
 }
 
+/// Whether a value is one of the structured declarations handled separately
+fn is_special_value(value: &serde_json::Value) -> bool {
+    parse_structured::<ListModifier>(value).is_some()
+        || parse_structured::<CommandValue>(value).is_some()
+        || parse_structured::<TerraformValue>(value).is_some()
+        || parse_structured::<SensitiveValue>(value).is_some()
+        || parse_structured::<LocalStoreRef>(value).is_some()
+}
+
+/// The separator configured for joining list values, by the project or the
+/// user
+fn list_separator(cue_result: &CueParseResult) -> String {
+    ConfigSettings::effective(cue_result.config.as_ref())
+        .list_separator
+        .unwrap_or_else(|| DEFAULT_LIST_SEPARATOR.to_string())
+}
+
 /// Processes variables from JSON values to strings
 fn process_variables(
     variables: &HashMap<String, serde_json::Value>,
-    metadata: &HashMap<String, VariableMetadata>,
+    cue_result: &CueParseResult,
     capabilities: &[String],
 ) -> HashMap<String, String> {
+    let separator = list_separator(cue_result);
     let mut result = HashMap::with_capacity(variables.len());
 
     for (key, val) in variables {
-        if should_include_variable(key, metadata, capabilities) {
-            if let Some(str_val) = CueParser::value_to_string(val) {
+        if is_special_value(val) {
+            continue;
+        }
+        if should_include_variable(key, &cue_result.metadata, capabilities) {
+            if let Some(str_val) = serialize_value(val, &separator) {
                 result.insert(key.clone(), str_val);
             }
         }
@@ -98,24 +233,133 @@ fn build_filtered_variables(
     options: &ParseOptions,
 ) -> HashMap<String, String> {
     // Start with base variables
-    let mut final_vars = process_variables(
+    let mut final_vars =
+        process_variables(&cue_result.variables, cue_result, &options.capabilities);
+
+    // Apply environment-specific overrides, later environments winning
+    for (_, env_vars) in selected_environments(cue_result, options) {
+        let env_overrides = process_variables(env_vars, cue_result, &options.capabilities);
+        final_vars.extend(env_overrides);
+    }
+
+    final_vars
+}
+
+/// Keeps the original value of every non-string variable for typed access
+fn build_typed_values(
+    cue_result: &CueParseResult,
+    options: &ParseOptions,
+) -> HashMap<String, serde_json::Value> {
+    let mut typed = HashMap::new();
+    let layers = std::iter::once(&cue_result.variables).chain(
+        selected_environments(cue_result, options)
+            .into_iter()
+            .map(|(_, vars)| vars),
+    );
+
+    for variables in layers {
+        for (key, val) in variables {
+            if !should_include_variable(key, &cue_result.metadata, &options.capabilities) {
+                continue;
+            }
+            if is_typed(val) && !is_special_value(val) {
+                typed.insert(key.clone(), val.clone());
+            } else {
+                typed.remove(key);
+            }
+        }
+    }
+
+    typed
+}
+
+/// The environments named by `options.environment`, in the order they apply
+fn selected_environments<'a>(
+    cue_result: &'a CueParseResult,
+    options: &'a ParseOptions,
+) -> Vec<(&'a str, &'a HashMap<String, serde_json::Value>)> {
+    let Some(spec) = &options.environment else {
+        return Vec::new();
+    };
+
+    spec.split(ENVIRONMENT_SEPARATOR)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| match cue_result.environments.get(name) {
+            Some(env_vars) => Some((name, env_vars)),
+            None => {
+                log::debug!("Environment {name} is not declared in this package");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Processes overlay variables, keyed by platform or host pattern
+fn process_overlays(
+    overlays: &HashMap<String, HashMap<String, serde_json::Value>>,
+    cue_result: &CueParseResult,
+    options: &ParseOptions,
+) -> HashMap<String, HashMap<String, String>> {
+    overlays
+        .iter()
+        .map(|(key, vars)| {
+            (
+                key.clone(),
+                process_variables(vars, cue_result, &options.capabilities),
+            )
+        })
+        .collect()
+}
+
+/// Parses an object such as `{prepend, append}` declared in place of a string value
+fn parse_structured<T: DeserializeOwned>(value: &serde_json::Value) -> Option<T> {
+    if value.is_object() {
+        serde_json::from_value(value.clone()).ok()
+    } else {
+        None
+    }
+}
+
+/// Extracts structured values allowed by the capability filter
+fn process_structured_variables<T: DeserializeOwned>(
+    variables: &HashMap<String, serde_json::Value>,
+    metadata: &HashMap<String, VariableMetadata>,
+    capabilities: &[String],
+) -> HashMap<String, T> {
+    variables
+        .iter()
+        .filter(|(key, _)| should_include_variable(key, metadata, capabilities))
+        .filter_map(|(key, val)| parse_structured(val).map(|parsed| (key.clone(), parsed)))
+        .collect()
+}
+
+/// Builds structured variables of one kind with environment overrides
+fn build_structured_variables<T: DeserializeOwned>(
+    cue_result: &CueParseResult,
+    options: &ParseOptions,
+) -> HashMap<String, T> {
+    let mut structured = process_structured_variables(
         &cue_result.variables,
         &cue_result.metadata,
         &options.capabilities,
     );
 
-    // Apply environment-specific overrides
-    if let Some(env_name) = &options.environment {
-        if let Some(env_vars) = cue_result.environments.get(env_name) {
-            let env_overrides =
-                process_variables(env_vars, &cue_result.metadata, &options.capabilities);
-
-            // Merge environment overrides into base variables
-            final_vars.extend(env_overrides);
-        }
+    for (_, env_vars) in selected_environments(cue_result, options) {
+        // An override of a different kind replaces the declaration entirely
+        structured.retain(|key, _| {
+            env_vars
+                .get(key)
+                .is_none_or(|val| parse_structured::<T>(val).is_some())
+        });
+        structured.extend(process_structured_variables(
+            env_vars,
+            &cue_result.metadata,
+            &options.capabilities,
+        ));
     }
 
-    final_vars
+    structured
 }
 
 /// Extracts hooks from the configuration
@@ -168,6 +412,26 @@ fn process_tasks_with_structure(
     (flat_tasks, task_nodes)
 }
 
+/// Replace the profile name in the `shell` of a task with the profile
+fn resolve_shell_profile(task: &mut TaskConfig, profiles: &HashMap<String, ShellProfile>) {
+    if let Some(shell @ TaskShell::Name(_)) = &task.shell {
+        if profiles.contains_key(shell.program()) {
+            task.shell = Some(TaskShell::Profile(shell.resolve(profiles)));
+        }
+    }
+}
+
+fn resolve_node_shell_profiles(node: &mut TaskNode, profiles: &HashMap<String, ShellProfile>) {
+    match node {
+        TaskNode::Task(config) => resolve_shell_profile(config, profiles),
+        TaskNode::Group { tasks, .. } => {
+            for node in tasks.values_mut() {
+                resolve_node_shell_profiles(node, profiles);
+            }
+        }
+    }
+}
+
 /// Recursively flattens a task node hierarchy
 fn flatten_task_node(
     name: &str,
@@ -209,9 +473,10 @@ mod tests {
             "AWS_KEY".to_string(),
             VariableMetadata {
                 capability: Some("aws".to_string()),
+                ..Default::default()
             },
         );
-        metadata.insert("DB_URL".to_string(), VariableMetadata { capability: None });
+        metadata.insert("DB_URL".to_string(), VariableMetadata::default());
 
         // Variable with no metadata should always be included
         assert!(should_include_variable("UNKNOWN", &metadata, &[]));
@@ -242,4 +507,133 @@ mod tests {
             &["gcp".to_string()]
         )); // Non-matching capability
     }
+
+    #[test]
+    fn test_sensitive_values_are_marked_in_metadata() {
+        let cue_result: CueParseResult = serde_json::from_value(serde_json::json!({
+            "variables": {
+                "API_TOKEN": { "value": "tok_123456", "sensitive": true },
+                "LOG_LEVEL": "info"
+            },
+            "metadata": {},
+            "environments": {},
+            "commands": {}
+        }))
+        .unwrap();
+
+        let result = build_parse_result(cue_result, &ParseOptions::default()).unwrap();
+
+        assert_eq!(result.variables["API_TOKEN"], "tok_123456");
+        assert!(result.metadata["API_TOKEN"].sensitive);
+        assert!(!result.metadata.contains_key("LOG_LEVEL"));
+    }
+
+    #[test]
+    fn test_local_store_values_become_secret_references() {
+        let cue_result: CueParseResult = serde_json::from_value(serde_json::json!({
+            "variables": {
+                "MY_TOKEN": { "fromLocalStore": "MY_TOKEN" }
+            },
+            "metadata": {},
+            "environments": {},
+            "commands": {}
+        }))
+        .unwrap();
+
+        let result = build_parse_result(cue_result, &ParseOptions::default()).unwrap();
+
+        assert!(result.variables["MY_TOKEN"].starts_with("cuenv-resolver://"));
+        assert!(result.metadata["MY_TOKEN"].sensitive);
+    }
+
+    #[test]
+    fn test_typed_values_are_serialized_and_kept() {
+        let cue_result: CueParseResult = serde_json::from_value(serde_json::json!({
+            "variables": {
+                "PORT": 8080,
+                "DEBUG": true,
+                "HOSTS": ["a.example.com", "b.example.com"],
+                "DATABASE": { "host": "localhost", "port": 5432 },
+                "NAME": "app"
+            },
+            "metadata": {},
+            "environments": {},
+            "commands": {},
+            "config": { "listSeparator": "," }
+        }))
+        .unwrap();
+
+        let result = build_parse_result(cue_result, &ParseOptions::default()).unwrap();
+
+        assert_eq!(result.variables["PORT"], "8080");
+        assert_eq!(result.variables["DEBUG"], "true");
+        assert_eq!(result.variables["HOSTS"], "a.example.com,b.example.com");
+        assert_eq!(
+            result.variables["DATABASE"],
+            r#"{"host":"localhost","port":5432}"#
+        );
+        assert_eq!(result.typed_values["PORT"], serde_json::json!(8080));
+        assert!(!result.typed_values.contains_key("NAME"));
+    }
+
+    #[test]
+    fn test_stacked_environments_apply_in_order() {
+        let cue_result: CueParseResult = serde_json::from_value(serde_json::json!({
+            "variables": { "REPLICAS": "1", "DEVICE": "cpu", "LOG_LEVEL": "debug" },
+            "metadata": {},
+            "environments": {
+                "base": { "LOG_LEVEL": "info", "REPLICAS": "2" },
+                "gpu": { "DEVICE": "cuda" },
+                "staging": { "REPLICAS": "3" }
+            },
+            "commands": {}
+        }))
+        .unwrap();
+
+        let options = ParseOptions {
+            environment: Some("base+gpu+staging".to_string()),
+            ..Default::default()
+        };
+        let result = build_parse_result(cue_result, &options).unwrap();
+
+        assert_eq!(result.variables["REPLICAS"], "3");
+        assert_eq!(result.variables["DEVICE"], "cuda");
+        assert_eq!(result.variables["LOG_LEVEL"], "info");
+        assert_eq!(result.environment_sources["REPLICAS"], "staging");
+        assert_eq!(result.environment_sources["DEVICE"], "gpu");
+        assert_eq!(result.environment_sources["LOG_LEVEL"], "base");
+    }
+
+    #[test]
+    fn test_task_shells_name_profiles() {
+        let cue_result: CueParseResult = serde_json::from_value(serde_json::json!({
+            "variables": {},
+            "metadata": {},
+            "environments": {},
+            "commands": {},
+            "shells": {
+                "bash-strict": { "program": "bash", "args": ["-euo", "pipefail", "-c"] }
+            },
+            "tasks": {
+                "build": { "command": "make", "shell": "bash-strict" },
+                "lint": { "command": "make lint", "shell": "zsh" },
+                "ci": {
+                    "test": { "command": "make test", "shell": "bash-strict" }
+                }
+            }
+        }))
+        .unwrap();
+
+        let result = build_parse_result(cue_result, &ParseOptions::default()).unwrap();
+
+        let shell = result.tasks["build"].shell.as_ref().unwrap();
+        assert_eq!(shell.program(), "bash");
+        assert_eq!(shell.args(), ["-euo", "pipefail", "-c"]);
+        let shell = result.tasks["ci.test"].shell.as_ref().unwrap();
+        assert_eq!(shell.program(), "bash");
+        assert_eq!(
+            result.tasks["lint"].shell,
+            Some(TaskShell::Name("zsh".to_string()))
+        );
+    }
 }
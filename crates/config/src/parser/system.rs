@@ -0,0 +1,126 @@
+//! Facts about the machine, available to CUE as `_cuenv`
+//!
+//! Configurations can compute values from them, like
+//! `JOBS: "\(_cuenv.cpuCount * 2)"`, without running a command. The struct
+//! is a hidden field of the package, so it is never exported itself.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+    /// Operating system as reported by Rust, e.g. `linux` or `macos`
+    pub os: String,
+    /// CPU architecture as reported by Rust, e.g. `x86_64` or `aarch64`
+    pub arch: String,
+    pub hostname: String,
+    pub username: String,
+    pub cpu_count: usize,
+    /// Branch checked out in the repository containing the directory, empty
+    /// outside a repository or on a detached HEAD
+    pub git_branch: String,
+}
+
+impl SystemInfo {
+    /// Describe the current machine, and the repository containing `dir`
+    pub fn current(dir: &Path) -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            hostname: hostname::get()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            username: whoami::username(),
+            cpu_count: std::thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(1),
+            git_branch: git_branch(dir).unwrap_or_default(),
+        }
+    }
+}
+
+/// The branch HEAD points at, read from the repository without running git
+fn git_branch(dir: &Path) -> Option<String> {
+    let git_dir = dir.ancestors().find_map(|ancestor| git_dir(ancestor))?;
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(str::to_string)
+}
+
+/// The git directory of a repository rooted at `dir`, following the `.git`
+/// file of worktrees and submodules
+fn git_dir(dir: &Path) -> Option<PathBuf> {
+    let dot_git = dir.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+
+    let content = std::fs::read_to_string(&dot_git).ok()?;
+    let target = PathBuf::from(content.trim().strip_prefix("gitdir:")?.trim());
+    Some(if target.is_absolute() {
+        target
+    } else {
+        dir.join(target)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_git_branch_is_read_from_head() {
+        let temp = tempfile::tempdir().unwrap();
+        let nested = temp.path().join("services").join("api");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+
+        fs::write(
+            temp.path().join(".git").join("HEAD"),
+            "ref: refs/heads/feature/x\n",
+        )
+        .unwrap();
+        assert_eq!(git_branch(&nested).as_deref(), Some("feature/x"));
+
+        // A detached HEAD has no branch
+        fs::write(temp.path().join(".git").join("HEAD"), "0123456789abcdef\n").unwrap();
+        assert_eq!(git_branch(&nested), None);
+    }
+
+    #[test]
+    fn test_worktrees_follow_the_git_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp
+            .path()
+            .join("main")
+            .join(".git")
+            .join("worktrees")
+            .join("wt");
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/release\n").unwrap();
+
+        let worktree = temp.path().join("wt");
+        fs::create_dir(&worktree).unwrap();
+        fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", git_dir.display()),
+        )
+        .unwrap();
+
+        assert_eq!(git_branch(&worktree).as_deref(), Some("release"));
+    }
+
+    #[test]
+    fn test_current_describes_this_machine() {
+        let info = SystemInfo::current(Path::new("/"));
+        assert_eq!(info.os, std::env::consts::OS);
+        assert!(info.cpu_count >= 1);
+        assert_eq!(
+            serde_json::to_value(&info).unwrap()["cpuCount"],
+            serde_json::json!(info.cpu_count)
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! CUE tag injection
+//!
+//! Tags set fields marked with `@tag(name)` at load time, as with
+//! `cue export -t name=value`, so one env.cue can be parameterized by
+//! region or cluster. They are passed with `cuenv --tag name=value` or
+//! through `CUENV_TAGS`, which holds whitespace-separated `name=value` pairs.
+
+use cuenv_core::constants::CUENV_TAGS_VAR;
+
+/// Check that a tag has the `name=value` form CUE expects
+pub fn parse_tag(tag: &str) -> Result<String, String> {
+    let Some((name, _)) = tag.split_once('=') else {
+        return Err(format!("Invalid tag '{tag}': expected name=value"));
+    };
+    if name.is_empty() {
+        return Err(format!("Invalid tag '{tag}': the name is empty"));
+    }
+    if tag.chars().any(char::is_whitespace) {
+        return Err(format!(
+            "Invalid tag '{tag}': tags cannot contain whitespace"
+        ));
+    }
+    Ok(tag.to_string())
+}
+
+/// The tags set through `CUENV_TAGS`
+pub fn tags_from_env() -> Vec<String> {
+    std::env::var(CUENV_TAGS_VAR)
+        .map(|tags| split_tags(&tags))
+        .unwrap_or_default()
+}
+
+/// Join tags into a `CUENV_TAGS` value
+pub fn join_tags(tags: &[String]) -> String {
+    tags.join(" ")
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split_whitespace().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(parse_tag("region=eu-west-1").unwrap(), "region=eu-west-1");
+        assert_eq!(parse_tag("debug=").unwrap(), "debug=");
+        assert!(parse_tag("region").is_err());
+        assert!(parse_tag("=eu").is_err());
+        assert!(parse_tag("name=two words").is_err());
+    }
+
+    #[test]
+    fn test_tags_round_trip_through_the_variable() {
+        let tags = vec!["region=eu".to_string(), "cluster=prod".to_string()];
+
+        assert_eq!(split_tags(&join_tags(&tags)), tags);
+        assert_eq!(split_tags("  region=eu\n cluster=prod "), tags);
+    }
+}
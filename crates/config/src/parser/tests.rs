@@ -192,6 +192,7 @@ fn test_parse_with_environments() {
     let options = ParseOptions {
         environment: Some("production".to_string()),
         capabilities: Vec::new(),
+        ..Default::default()
     };
     let result =
         CueParser::eval_package_with_options(temp_dir.path(), DEFAULT_PACKAGE_NAME, &options)
@@ -207,6 +208,7 @@ fn test_parse_with_environments() {
     let options = ParseOptions {
         environment: Some("staging".to_string()),
         capabilities: Vec::new(),
+        ..Default::default()
     };
     let result =
         CueParser::eval_package_with_options(temp_dir.path(), DEFAULT_PACKAGE_NAME, &options)
@@ -247,6 +249,7 @@ fn test_parse_with_capabilities() {
     let options = ParseOptions {
         environment: None,
         capabilities: vec!["aws".to_string()],
+        ..Default::default()
     };
     let result =
         CueParser::eval_package_with_options(temp_dir.path(), DEFAULT_PACKAGE_NAME, &options)
@@ -257,6 +260,7 @@ fn test_parse_with_capabilities() {
     let options = ParseOptions {
         environment: None,
         capabilities: vec!["gcp".to_string()],
+        ..Default::default()
     };
     let result =
         CueParser::eval_package_with_options(temp_dir.path(), DEFAULT_PACKAGE_NAME, &options)
@@ -345,6 +349,7 @@ fn test_parse_with_env_and_capabilities() {
     let options = ParseOptions {
         environment: Some("production".to_string()),
         capabilities: vec!["aws".to_string()],
+        ..Default::default()
     };
     let result =
         CueParser::eval_package_with_options(temp_dir.path(), DEFAULT_PACKAGE_NAME, &options)
@@ -657,6 +662,7 @@ fn test_parse_hooks_with_environments() {
     let options = ParseOptions {
         environment: Some("production".to_string()),
         capabilities: Vec::new(),
+        ..Default::default()
     };
     let result =
         CueParser::eval_package_with_options(temp_dir.path(), DEFAULT_PACKAGE_NAME, &options)
@@ -904,3 +910,144 @@ fn test_parse_nested_tasks() {
         Some("Apply code formatting changes")
     );
 }
+
+#[test]
+fn test_yaml_configuration_without_a_cue_package() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("cuenv.yaml"),
+        r#"env:
+  DATABASE_URL: postgresql://localhost/mydb
+  PORT: 8080
+tasks:
+  build:
+    description: Build the project
+    command: cargo build
+"#,
+    )
+    .unwrap();
+
+    let result = CueParser::eval_package_with_options(
+        temp_dir.path(),
+        DEFAULT_PACKAGE_NAME,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        result.variables.get("DATABASE_URL").map(String::as_str),
+        Some("postgresql://localhost/mydb")
+    );
+    assert_eq!(
+        result.variables.get("PORT").map(String::as_str),
+        Some("8080")
+    );
+    assert_eq!(
+        result.tasks.get("build").unwrap().command.as_deref(),
+        Some("cargo build")
+    );
+}
+
+#[test]
+fn test_system_information_is_available_as_cuenv() {
+    let temp_dir = create_test_env(
+        r#"package cuenv
+
+env: {
+    JOBS: _cuenv.cpuCount * 2
+    PLATFORM: "\(_cuenv.os)-\(_cuenv.arch)"
+}"#,
+    );
+
+    let result = CueParser::eval_package_with_options(
+        temp_dir.path(),
+        DEFAULT_PACKAGE_NAME,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+
+    let system = SystemInfo::current(temp_dir.path());
+    assert_eq!(
+        result.variables.get("JOBS"),
+        Some(&(system.cpu_count * 2).to_string())
+    );
+    assert_eq!(
+        result.variables.get("PLATFORM"),
+        Some(&format!("{}-{}", system.os, system.arch))
+    );
+}
+
+#[test]
+#[serial]
+fn test_parse_named_task_outputs() {
+    let content = r#"
+    package cuenv
+
+    tasks: {
+        build: {
+            command: "cargo build --release"
+            outputs: {
+                binary: { path: "target/release/app" }
+                docs: { path: "target/doc", type: "directory" }
+            }
+        }
+        package: {
+            command: "tar czf app.tgz ${outputs.build.binary}"
+            dependencies: ["build"]
+            outputs: ["app.tgz"]
+        }
+    }
+    "#;
+    let temp_dir = create_test_env(content);
+    let result = CueParser::eval_package_with_options(
+        temp_dir.path(),
+        DEFAULT_PACKAGE_NAME,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+
+    let outputs = result.tasks["build"].outputs.as_ref().unwrap();
+    assert_eq!(outputs.path_of("binary"), Some("target/release/app"));
+    assert_eq!(
+        outputs.artifacts()["docs"].artifact_type,
+        crate::ArtifactType::Directory
+    );
+    assert_eq!(
+        result.tasks["package"].outputs.as_ref().unwrap().paths(),
+        vec!["app.tgz"]
+    );
+}
+
+#[test]
+#[serial]
+fn test_parse_notify() {
+    let content = r#"
+    package cuenv
+
+    notify: {
+        onFailure: {
+            command: "notify-send 'Build failed'"
+            webhook: "https://hooks.slack.com/services/T000/B000/XXXX"
+        }
+    }
+    "#;
+    let temp_dir = create_test_env(content);
+    let result = CueParser::eval_package_with_options(
+        temp_dir.path(),
+        DEFAULT_PACKAGE_NAME,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+
+    let notify = result.notify.unwrap();
+    let on_failure = notify.on_failure.unwrap();
+    assert_eq!(
+        on_failure.command.as_deref(),
+        Some("notify-send 'Build failed'")
+    );
+    assert_eq!(
+        on_failure.webhook.as_deref(),
+        Some("https://hooks.slack.com/services/T000/B000/XXXX")
+    );
+    assert!(notify.on_success.is_none());
+}
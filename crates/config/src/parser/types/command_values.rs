@@ -0,0 +1,120 @@
+//! Command-derived variable types
+
+use cuenv_core::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A variable whose value is the output of a command
+///
+/// Declared in env.cue as `GIT_SHA: { fromCommand: "git rev-parse --short HEAD" }`.
+/// The output is cached until `ttl` expires or one of `inputs` changes;
+/// without either, the command runs every time the environment loads.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CommandValue {
+    #[serde(rename = "fromCommand")]
+    pub from_command: String,
+
+    /// How long the output stays valid, e.g. `"30s"`, `"10m"`, `"1h"`, `"1d"`
+    #[serde(default)]
+    pub ttl: Option<String>,
+
+    /// Files whose contents invalidate the cached output when they change
+    #[serde(default)]
+    pub inputs: Vec<String>,
+
+    /// Directory of the env.cue that declared the value
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+}
+
+impl CommandValue {
+    /// Record the directory the command runs in and `inputs` resolve against
+    pub fn declared_in(self, dir: &Path) -> Self {
+        Self {
+            dir: Some(dir.to_path_buf()),
+            ..self
+        }
+    }
+
+    /// Parse `ttl` into a duration
+    pub fn ttl_duration(&self) -> Result<Option<Duration>> {
+        let Some(ttl) = self.ttl.as_deref() else {
+            return Ok(None);
+        };
+
+        let ttl = ttl.trim();
+        let split = ttl.find(|c: char| !c.is_ascii_digit()).unwrap_or(ttl.len());
+        let (amount, unit) = ttl.split_at(split);
+        let seconds = match unit {
+            "" | "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => 0,
+        };
+
+        match amount.parse::<u64>() {
+            Ok(amount) if seconds > 0 => Ok(Some(Duration::from_secs(amount * seconds))),
+            _ => Err(Error::configuration(format!(
+                "invalid ttl {ttl:?} for fromCommand {:?}: expected a number followed by s, m, h or d",
+                self.from_command
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_ttl(ttl: &str) -> CommandValue {
+        CommandValue {
+            from_command: "date".to_string(),
+            ttl: Some(ttl.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ttl_units() {
+        assert_eq!(
+            with_ttl("45").ttl_duration().unwrap(),
+            Some(Duration::from_secs(45))
+        );
+        assert_eq!(
+            with_ttl("10m").ttl_duration().unwrap(),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            with_ttl("2h").ttl_duration().unwrap(),
+            Some(Duration::from_secs(7200))
+        );
+        assert_eq!(CommandValue::default().ttl_duration().unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_ttl() {
+        assert!(with_ttl("soon").ttl_duration().is_err());
+        assert!(with_ttl("5w").ttl_duration().is_err());
+        assert!(with_ttl("m").ttl_duration().is_err());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let value: CommandValue = serde_json::from_value(serde_json::json!({
+            "fromCommand": "git rev-parse --short HEAD",
+            "inputs": [".git/HEAD"]
+        }))
+        .unwrap();
+        assert_eq!(value.from_command, "git rev-parse --short HEAD");
+        assert_eq!(value.inputs, vec![".git/HEAD".to_string()]);
+
+        // Path lists are not command values
+        assert!(
+            serde_json::from_value::<CommandValue>(serde_json::json!({"prepend": ["./bin"]}))
+                .is_err()
+        );
+    }
+}
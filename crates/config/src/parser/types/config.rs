@@ -1,3 +1,4 @@
+use crate::workspace::CacheSettings;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
@@ -22,6 +23,28 @@ pub struct ConfigSettings {
 
     #[serde(rename = "defaultCapabilities")]
     pub default_capabilities: Option<Vec<String>>,
+
+    #[serde(rename = "lazySecrets")]
+    pub lazy_secrets: Option<bool>,
+
+    /// Separator used to join list values, defaulting to `:`
+    #[serde(rename = "listSeparator")]
+    pub list_separator: Option<String>,
+
+    /// Run the one task or group a name is a prefix of, as `dep` for `deploy`
+    #[serde(rename = "taskPrefixMatch")]
+    pub task_prefix_match: Option<bool>,
+
+    /// Expose package.json scripts as `npm:<script>` tasks, on by default
+    #[serde(rename = "npmScripts")]
+    pub npm_scripts: Option<bool>,
+
+    /// Put the versions pinned for asdf and mise in front of PATH, on by default
+    #[serde(rename = "toolVersions")]
+    pub tool_versions: Option<bool>,
+
+    /// Remote cache and retention of this package, over those of its workspace
+    pub cache: Option<CacheSettings>,
 }
 
 impl ConfigSettings {
@@ -50,6 +73,10 @@ impl ConfigSettings {
             }
         }
 
+        if let Some(ref cache) = self.cache {
+            cache.validate()?;
+        }
+
         Ok(())
     }
 }
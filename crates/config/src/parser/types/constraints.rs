@@ -0,0 +1,19 @@
+//! Environment variable constraint types
+
+use serde::{Deserialize, Serialize};
+
+/// Validation rules for a single environment variable
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VariableConstraint {
+    /// The variable must be set
+    #[serde(default)]
+    pub required: Option<bool>,
+
+    /// Regular expression the value must match
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    /// The value must be one of these
+    #[serde(default, rename = "oneOf")]
+    pub one_of: Option<Vec<String>>,
+}
@@ -0,0 +1,50 @@
+//! Path-like list variable types
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Entries to add to a path-like variable such as `PATH`
+///
+/// Declared in env.cue as `PATH: { prepend: ["./bin"], append: [...] }`.
+/// Entries are added around the value the variable already has.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ListModifier {
+    #[serde(default)]
+    pub prepend: Vec<String>,
+
+    #[serde(default)]
+    pub append: Vec<String>,
+
+    /// Separator between entries, defaulting to the platform path separator
+    #[serde(default)]
+    pub separator: Option<String>,
+}
+
+impl ListModifier {
+    /// Resolve relative entries (`./bin`, `../tools`) against `dir`
+    pub fn resolved_against(self, dir: &Path) -> Self {
+        let resolve = |entries: Vec<String>| {
+            entries
+                .into_iter()
+                .map(|entry| {
+                    if entry == "." || entry.starts_with("./") || entry.starts_with("../") {
+                        dir.join(&entry)
+                            .components()
+                            .collect::<PathBuf>()
+                            .display()
+                            .to_string()
+                    } else {
+                        entry
+                    }
+                })
+                .collect()
+        };
+
+        Self {
+            prepend: resolve(self.prepend),
+            append: resolve(self.append),
+            separator: self.separator,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! References to the encrypted local secret store
+
+use cuenv_core::constants::CUENV_RESOLVER_PREFIX;
+use serde::{Deserialize, Serialize};
+
+/// A secret kept in the user's local store instead of the repository
+///
+/// Declared in env.cue as `MY_TOKEN: { fromLocalStore: "MY_TOKEN" }` and set
+/// with `cuenv secret set MY_TOKEN`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LocalStoreRef {
+    #[serde(rename = "fromLocalStore")]
+    pub from_local_store: String,
+}
+
+impl LocalStoreRef {
+    /// The resolver reference that looks the secret up when it is needed
+    pub fn to_reference(&self) -> String {
+        format!(
+            "{CUENV_RESOLVER_PREFIX}{}",
+            serde_json::json!({ "fromLocalStore": self.from_local_store })
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_is_a_resolver_value() {
+        let store_ref: LocalStoreRef =
+            serde_json::from_value(serde_json::json!({ "fromLocalStore": "MY_TOKEN" })).unwrap();
+
+        assert_eq!(
+            store_ref.to_reference(),
+            r#"cuenv-resolver://{"fromLocalStore":"MY_TOKEN"}"#
+        );
+    }
+}
@@ -4,26 +4,54 @@
 //! parsed CUE configurations.
 
 mod cache;
+mod command_values;
 mod commands;
 mod config;
+mod constraints;
 mod hooks;
+mod lists;
+mod local_store;
+mod nix;
+mod notify;
+mod overlays;
 mod raw;
 mod result;
 mod security;
+mod sensitive;
+mod shells;
 mod tasks;
+mod terraform;
+mod typed;
 
 pub use cache::{CacheEnvConfig, TaskCacheConfig};
+pub use command_values::CommandValue;
 pub use commands::CommandConfig;
 pub use config::ConfigSettings;
+pub use constraints::VariableConstraint;
 pub use hooks::{Hook, HookConfig, HookConstraint, HookType, HookValue};
+pub use lists::ListModifier;
+pub use local_store::LocalStoreRef;
+pub use nix::{DevenvConfig, NixConfig};
+pub use notify::{Notification, NotifyConfig};
+pub use overlays::EnvOverlays;
 pub(crate) use raw::RawCueResult;
 pub(crate) use result::{CueParseResult, HooksConfig};
 pub use security::SecurityConfig;
-pub use tasks::{TaskConfig, TaskGroupMode, TaskNode};
+pub use sensitive::SensitiveValue;
+pub use shells::{ShellProfile, TaskShell};
+pub use tasks::{
+    ArtifactType, OutputArtifact, RestartPolicy, ServiceConfig, TaskConfig, TaskGroupMode,
+    TaskNode, TaskOutputs,
+};
+pub use terraform::{TerraformOutput, TerraformValue};
+pub use typed::{is_typed, serialize_value, DEFAULT_LIST_SEPARATOR};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VariableMetadata {
     pub capability: Option<String>,
+    /// Whether the value is redacted from output
+    #[serde(default)]
+    pub sensitive: bool,
 }
@@ -0,0 +1,144 @@
+//! Nix and devenv development shell types
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A Nix development shell whose variables the environment is layered on
+///
+/// Declared in env.cue as `nix: { flake: ".#devShell" }`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NixConfig {
+    /// Flake reference of the shell, relative to the env.cue declaring it
+    #[serde(default = "default_flake")]
+    pub flake: String,
+
+    /// Directory of the env.cue that declared the shell
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+}
+
+fn default_flake() -> String {
+    ".".to_string()
+}
+
+impl Default for NixConfig {
+    fn default() -> Self {
+        Self {
+            flake: default_flake(),
+            dir: None,
+        }
+    }
+}
+
+impl NixConfig {
+    /// Record the directory `nix` runs in and relative flakes resolve against
+    pub fn declared_in(self, dir: &Path) -> Self {
+        Self {
+            dir: Some(dir.to_path_buf()),
+            ..self
+        }
+    }
+
+    /// The local directory holding the flake, or `None` for a remote flake
+    /// such as `github:owner/repo`
+    pub fn flake_dir(&self) -> Option<PathBuf> {
+        let dir = self.dir.as_deref().unwrap_or(Path::new("."));
+        let path = self.flake.split('#').next().unwrap_or_default();
+        let path = path.strip_prefix("path:").unwrap_or(path);
+        if path.is_empty() {
+            Some(dir.to_path_buf())
+        } else if path.starts_with('.') || path.starts_with('/') {
+            Some(dir.join(path))
+        } else {
+            None
+        }
+    }
+}
+
+/// A devenv.sh environment the environment is layered on
+///
+/// Declared in env.cue as `devenv: {}`, for a devenv.nix next to it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DevenvConfig {
+    /// Directory of devenv.nix, relative to the env.cue declaring it
+    #[serde(default = "default_root")]
+    pub root: String,
+
+    /// Directory of the env.cue that declared the environment
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+}
+
+fn default_root() -> String {
+    ".".to_string()
+}
+
+impl Default for DevenvConfig {
+    fn default() -> Self {
+        Self {
+            root: default_root(),
+            dir: None,
+        }
+    }
+}
+
+impl DevenvConfig {
+    /// Record the directory `root` resolves against
+    pub fn declared_in(self, dir: &Path) -> Self {
+        Self {
+            dir: Some(dir.to_path_buf()),
+            ..self
+        }
+    }
+
+    /// The directory holding devenv.nix, where `devenv` runs
+    pub fn root_dir(&self) -> PathBuf {
+        self.dir
+            .as_deref()
+            .unwrap_or(Path::new("."))
+            .join(&self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flake(flake: &str) -> NixConfig {
+        NixConfig {
+            flake: flake.to_string(),
+            ..Default::default()
+        }
+        .declared_in(Path::new("/project"))
+    }
+
+    #[test]
+    fn test_flake_dir() {
+        assert_eq!(
+            flake(".#devShell").flake_dir(),
+            Some(PathBuf::from("/project/."))
+        );
+        assert_eq!(flake("#ci").flake_dir(), Some(PathBuf::from("/project")));
+        assert_eq!(
+            flake("path:./nix#dev").flake_dir(),
+            Some(PathBuf::from("/project/./nix"))
+        );
+        assert_eq!(
+            flake("/shared/flake").flake_dir(),
+            Some(PathBuf::from("/shared/flake"))
+        );
+        assert_eq!(flake("github:owner/repo#dev").flake_dir(), None);
+    }
+
+    #[test]
+    fn test_devenv_root_dir() {
+        let devenv: DevenvConfig = serde_json::from_str(r#"{"root": "dev"}"#).unwrap();
+        assert_eq!(
+            devenv.declared_in(Path::new("/project")).root_dir(),
+            PathBuf::from("/project/dev")
+        );
+        assert!(serde_json::from_str::<DevenvConfig>(r#"{"profile": "ci"}"#).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+//! Notification types for finished task runs
+
+use serde::{Deserialize, Serialize};
+
+/// Notifications sent when a task run finishes, by its outcome
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NotifyConfig {
+    /// Sent when a task of the run failed
+    #[serde(default, rename = "onFailure")]
+    pub on_failure: Option<Notification>,
+
+    /// Sent when every task of the run succeeded
+    #[serde(default, rename = "onSuccess")]
+    pub on_success: Option<Notification>,
+
+    /// Sent after every run, whatever its outcome
+    #[serde(default, rename = "onComplete")]
+    pub on_complete: Option<Notification>,
+}
+
+/// Where a notification goes; both may be given
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Notification {
+    /// Shell command run with the JSON payload on stdin
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// URL the JSON payload is POSTed to
+    #[serde(default)]
+    pub webhook: Option<String>,
+}
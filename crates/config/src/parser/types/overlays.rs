@@ -0,0 +1,32 @@
+//! Platform and host specific overlay types
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Variables that only apply on some machines
+///
+/// Declared inside `env` as `platform: { darwin: {...}, "linux-arm64": {...} }`
+/// and `hosts: { "ci-*": {...} }`. Which overlays apply is decided when the
+/// environment is loaded, based on the current OS, architecture and hostname.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EnvOverlays {
+    /// Overlays keyed by OS, architecture, or both joined with `-`
+    #[serde(default)]
+    pub platform: HashMap<String, HashMap<String, String>>,
+
+    /// Overlays keyed by hostname glob pattern
+    #[serde(default)]
+    pub hosts: HashMap<String, HashMap<String, String>>,
+}
+
+impl EnvOverlays {
+    /// Merge `other` over these overlays, variable by variable
+    pub fn extend(&mut self, other: EnvOverlays) {
+        for (key, vars) in other.platform {
+            self.platform.entry(key).or_default().extend(vars);
+        }
+        for (pattern, vars) in other.hosts {
+            self.hosts.entry(pattern).or_default().extend(vars);
+        }
+    }
+}
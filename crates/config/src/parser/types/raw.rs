@@ -1,6 +1,8 @@
 //! Raw types for direct CUE JSON deserialization
 
-use super::ConfigSettings;
+use super::{
+    ConfigSettings, DevenvConfig, NixConfig, NotifyConfig, ShellProfile, VariableConstraint,
+};
 use serde::Deserialize;
 use std::collections::HashMap;
 
@@ -17,6 +19,18 @@ pub(crate) struct RawCueResult {
     pub capabilities: HashMap<String, RawCapability>,
     #[serde(default)]
     pub config: Option<ConfigSettings>,
+    #[serde(default)]
+    pub root: bool,
+    #[serde(default)]
+    pub constraints: HashMap<String, VariableConstraint>,
+    #[serde(default)]
+    pub notify: Option<NotifyConfig>,
+    #[serde(default)]
+    pub nix: Option<NixConfig>,
+    #[serde(default)]
+    pub devenv: Option<DevenvConfig>,
+    #[serde(default)]
+    pub shells: HashMap<String, ShellProfile>,
     // Catch-all for other fields including sayHello at top level
     #[serde(flatten)]
     pub _other: HashMap<String, serde_json::Value>,
@@ -27,6 +41,10 @@ pub(crate) struct RawEnv {
     #[serde(default)]
     pub environment: HashMap<String, HashMap<String, serde_json::Value>>,
     #[serde(default)]
+    pub platform: HashMap<String, HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    pub hosts: HashMap<String, HashMap<String, serde_json::Value>>,
+    #[serde(default)]
     pub capabilities: HashMap<String, RawCapability>,
     #[serde(flatten)]
     pub variables: HashMap<String, serde_json::Value>,
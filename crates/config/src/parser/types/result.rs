@@ -1,6 +1,9 @@
 //! Result types for CUE parsing
 
-use super::{CommandConfig, ConfigSettings, HookValue, VariableMetadata};
+use super::{
+    CommandConfig, ConfigSettings, DevenvConfig, HookValue, NixConfig, NotifyConfig, ShellProfile,
+    VariableConstraint, VariableMetadata,
+};
 use serde::Deserialize;
 use std::collections::HashMap;
 
@@ -9,11 +12,27 @@ pub(crate) struct CueParseResult {
     pub variables: HashMap<String, serde_json::Value>,
     pub metadata: HashMap<String, VariableMetadata>,
     pub environments: HashMap<String, HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    pub platforms: HashMap<String, HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    pub hosts: HashMap<String, HashMap<String, serde_json::Value>>,
     pub commands: HashMap<String, CommandConfig>,
     #[serde(default)]
     pub tasks: HashMap<String, serde_json::Value>,
     pub hooks: Option<HooksConfig>,
     pub config: Option<ConfigSettings>,
+    #[serde(default)]
+    pub root: bool,
+    #[serde(default)]
+    pub constraints: HashMap<String, VariableConstraint>,
+    #[serde(default)]
+    pub notify: Option<NotifyConfig>,
+    #[serde(default)]
+    pub nix: Option<NixConfig>,
+    #[serde(default)]
+    pub devenv: Option<DevenvConfig>,
+    #[serde(default)]
+    pub shells: HashMap<String, ShellProfile>,
 }
 
 #[derive(Debug, Deserialize)]
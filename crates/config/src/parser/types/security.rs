@@ -19,4 +19,14 @@ pub struct SecurityConfig {
     /// Automatically infer disk restrictions from task inputs/outputs
     #[serde(rename = "inferFromInputsOutputs")]
     pub infer_from_inputs_outputs: Option<bool>,
+    /// Seccomp profile: "default", "strict" or the path of a JSON profile
+    pub seccomp: Option<String>,
+    /// Fail the task when its output contains a secret, after redacting it
+    #[serde(rename = "failOnSecretLeak")]
+    pub fail_on_secret_leak: Option<bool>,
+    /// User to run the task as, by name or numeric ID
+    pub user: Option<String>,
+    /// Run the task without Linux capabilities
+    #[serde(rename = "dropCapabilities")]
+    pub drop_capabilities: Option<bool>,
 }
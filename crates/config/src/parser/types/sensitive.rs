@@ -0,0 +1,15 @@
+//! Sensitive variable types
+
+use serde::{Deserialize, Serialize};
+
+/// A value to be redacted from output
+///
+/// Declared in env.cue as `API_TOKEN: { value: "...", sensitive: true }`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SensitiveValue {
+    pub value: String,
+
+    #[serde(default)]
+    pub sensitive: bool,
+}
@@ -0,0 +1,111 @@
+//! Shell profile types
+
+use cuenv_core::default_shell_args;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A shell and the arguments it runs a task's command or script with
+///
+/// Declared in env.cue as
+/// `shells: { "bash-strict": { program: "bash", args: ["-euo", "pipefail", "-c"] } }`
+/// for tasks to name in `shell`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ShellProfile {
+    pub program: String,
+
+    /// Arguments before the command or script, the last one taking it;
+    /// `-e -c` for POSIX shells and `-c` for others when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<String>>,
+}
+
+impl ShellProfile {
+    /// The arguments the program is run with
+    pub fn args(&self) -> Vec<String> {
+        self.args
+            .clone()
+            .unwrap_or_else(|| default_shell_args(&self.program))
+    }
+}
+
+/// The `shell` of a task: a shell program or profile name, or a profile
+/// of its own
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum TaskShell {
+    Name(String),
+    Profile(ShellProfile),
+}
+
+impl TaskShell {
+    /// The profile named by the shell, or the program it names run with
+    /// its default arguments
+    pub fn resolve(&self, profiles: &HashMap<String, ShellProfile>) -> ShellProfile {
+        match self {
+            TaskShell::Name(name) => profiles.get(name).cloned().unwrap_or(ShellProfile {
+                program: name.clone(),
+                args: None,
+            }),
+            TaskShell::Profile(profile) => profile.clone(),
+        }
+    }
+
+    /// The shell program run
+    pub fn program(&self) -> &str {
+        match self {
+            TaskShell::Name(program) => program,
+            TaskShell::Profile(profile) => &profile.program,
+        }
+    }
+
+    /// The arguments the program is run with
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            TaskShell::Name(program) => default_shell_args(program),
+            TaskShell::Profile(profile) => profile.args(),
+        }
+    }
+}
+
+impl From<&str> for TaskShell {
+    fn from(program: &str) -> Self {
+        TaskShell::Name(program.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_named_profile() {
+        let profiles = HashMap::from([(
+            "bash-strict".to_string(),
+            ShellProfile {
+                program: "bash".to_string(),
+                args: Some(vec!["-euo".into(), "pipefail".into(), "-c".into()]),
+            },
+        )]);
+
+        let profile = TaskShell::from("bash-strict").resolve(&profiles);
+        assert_eq!(profile.program, "bash");
+        assert_eq!(profile.args(), ["-euo", "pipefail", "-c"]);
+
+        let profile = TaskShell::from("zsh").resolve(&profiles);
+        assert_eq!(profile.program, "zsh");
+        assert_eq!(profile.args(), ["-e", "-c"]);
+    }
+
+    #[test]
+    fn test_deserialize_task_shell() {
+        let shell: TaskShell = serde_json::from_str(r#""fish""#).unwrap();
+        assert_eq!(shell.program(), "fish");
+        assert_eq!(shell.args(), ["-c"]);
+
+        let shell: TaskShell =
+            serde_json::from_str(r#"{"program": "sh", "args": ["-c"]}"#).unwrap();
+        assert_eq!(shell.program(), "sh");
+        assert_eq!(shell.args(), ["-c"]);
+    }
+}
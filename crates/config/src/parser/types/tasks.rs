@@ -1,13 +1,15 @@
 //! Task configuration types
 
-use super::{CacheEnvConfig, SecurityConfig, TaskCacheConfig};
+use super::{CacheEnvConfig, SecurityConfig, TaskCacheConfig, TaskShell};
+use cuenv_core::DependencyKind;
 use serde::{de::MapAccess, de::Visitor, Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 /// Task group execution mode
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum TaskGroupMode {
     /// Execute tasks based on dependency graph (DAG)
     Workflow,
@@ -16,15 +18,10 @@ pub enum TaskGroupMode {
     /// Execute all tasks simultaneously
     Parallel,
     /// Simple collection of tasks (default)
+    #[default]
     Group,
 }
 
-impl Default for TaskGroupMode {
-    fn default() -> Self {
-        Self::Group
-    }
-}
-
 /// A task node that can be either a single task or a group of tasks
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum TaskNode {
@@ -67,6 +64,8 @@ impl<'de> Deserialize<'de> for TaskNode {
                     "command",
                     "script",
                     "dependencies",
+                    "orderOnlyDependencies",
+                    "weakDependencies",
                     "workingDir",
                     "shell",
                     "inputs",
@@ -76,6 +75,10 @@ impl<'de> Deserialize<'de> for TaskNode {
                     "cacheKey",
                     "cache_env",
                     "timeout",
+                    "maxOutputBytes",
+                    "tags",
+                    "owners",
+                    "service",
                     "args",
                 ];
 
@@ -127,11 +130,28 @@ pub struct TaskConfig {
     pub command: Option<String>,
     pub script: Option<String>,
     pub dependencies: Option<Vec<String>>,
+    /// Tasks run before this one without it building on their results, so
+    /// that they do not keep it from being taken from the cache
+    #[serde(
+        rename = "orderOnlyDependencies",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub order_only_dependencies: Option<Vec<String>>,
+    /// Tasks run before this one only when they run anyway
+    #[serde(
+        rename = "weakDependencies",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub weak_dependencies: Option<Vec<String>>,
     #[serde(rename = "workingDir")]
     pub working_dir: Option<String>,
-    pub shell: Option<String>,
+    /// Shell program or profile of `shells` running the task, or a profile
+    /// of its own
+    pub shell: Option<TaskShell>,
     pub inputs: Option<Vec<String>>,
-    pub outputs: Option<Vec<String>>,
+    pub outputs: Option<TaskOutputs>,
     pub security: Option<SecurityConfig>,
     /// Cache configuration for this task (simple boolean or advanced config)
     #[serde(default, deserialize_with = "deserialize_cache_config")]
@@ -144,6 +164,120 @@ pub struct TaskConfig {
     pub cache_env: Option<CacheEnvConfig>,
     /// Timeout for task execution in seconds
     pub timeout: Option<u32>,
+    /// Bytes of stdout and of stderr each kept, as their head and tail
+    #[serde(
+        rename = "maxOutputBytes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_output_bytes: Option<u64>,
+    /// Labels to select tasks by in `cuenv task list --tagged`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Teams or people to ask about the task, named when it fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owners: Option<Vec<String>>,
+    /// Marks a long-running task that `cuenv service install` can run as a
+    /// systemd user service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<ServiceConfig>,
+}
+
+impl TaskConfig {
+    /// The dependencies of every kind, as named in the configuration
+    pub fn dependencies_by_kind(&self) -> impl Iterator<Item = (&String, DependencyKind)> {
+        [
+            (&self.dependencies, DependencyKind::Required),
+            (&self.order_only_dependencies, DependencyKind::OrderOnly),
+            (&self.weak_dependencies, DependencyKind::Weak),
+        ]
+        .into_iter()
+        .flat_map(|(names, kind)| names.iter().flatten().map(move |name| (name, kind)))
+    }
+}
+
+/// How a service task is kept running
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// Restarts allowed within five minutes before giving up
+    pub retries: Option<u32>,
+    /// Seconds to wait before restarting
+    pub delay: Option<u32>,
+}
+
+/// When a service is restarted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Always,
+    #[default]
+    OnFailure,
+    No,
+}
+
+/// The files a task produces: a list of paths, or named artifacts that
+/// downstream tasks reference as `${outputs.<task>.<name>}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TaskOutputs {
+    Paths(Vec<String>),
+    Named(BTreeMap<String, OutputArtifact>),
+}
+
+/// A named artifact produced by a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputArtifact {
+    /// Path relative to the task's working directory
+    pub path: String,
+    #[serde(rename = "type", default)]
+    pub artifact_type: ArtifactType,
+}
+
+/// What kind of filesystem entry an artifact is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactType {
+    #[default]
+    File,
+    Directory,
+}
+
+impl TaskOutputs {
+    /// The paths of all outputs
+    pub fn paths(&self) -> Vec<String> {
+        match self {
+            Self::Paths(paths) => paths.clone(),
+            Self::Named(artifacts) => artifacts.values().map(|a| a.path.clone()).collect(),
+        }
+    }
+
+    /// The named artifacts, empty for a plain list of paths
+    pub fn artifacts(&self) -> BTreeMap<String, OutputArtifact> {
+        match self {
+            Self::Paths(_) => BTreeMap::new(),
+            Self::Named(artifacts) => artifacts.clone(),
+        }
+    }
+
+    /// The path of an output, looked up by artifact name or by path
+    pub fn path_of(&self, output: &str) -> Option<&str> {
+        match self {
+            Self::Paths(paths) => paths
+                .iter()
+                .find(|path| *path == output)
+                .map(String::as_str),
+            Self::Named(artifacts) => {
+                artifacts.get(output).map(|a| a.path.as_str()).or_else(|| {
+                    artifacts
+                        .values()
+                        .find(|a| a.path == output)
+                        .map(|a| a.path.as_str())
+                })
+            }
+        }
+    }
 }
 
 /// Custom deserializer for cache configuration to support both simple and advanced forms
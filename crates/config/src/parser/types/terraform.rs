@@ -0,0 +1,81 @@
+//! Terraform and OpenTofu output variable types
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A variable whose value is an output of a Terraform or OpenTofu root
+/// module
+///
+/// Declared in env.cue as
+/// `DB_HOST: { fromTerraform: { dir: "infra/", output: "db_endpoint" } }`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TerraformValue {
+    #[serde(rename = "fromTerraform")]
+    pub from_terraform: TerraformOutput,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TerraformOutput {
+    /// Directory of the root module, relative to the env.cue declaring it
+    #[serde(default = "default_dir")]
+    pub dir: String,
+
+    /// Name of the output
+    pub output: String,
+
+    /// Program reading the outputs, `terraform` or `tofu`; the one
+    /// installed by default
+    #[serde(default)]
+    pub binary: Option<String>,
+
+    /// Directory of the env.cue that declared the value
+    #[serde(skip)]
+    pub base: Option<PathBuf>,
+}
+
+fn default_dir() -> String {
+    ".".to_string()
+}
+
+impl TerraformValue {
+    /// Record the directory `dir` is relative to
+    pub fn declared_in(mut self, dir: &Path) -> Self {
+        self.from_terraform.base = Some(dir.to_path_buf());
+        self
+    }
+
+    /// The directory of the root module
+    pub fn module_dir(&self) -> PathBuf {
+        self.from_terraform
+            .base
+            .as_deref()
+            .unwrap_or(Path::new("."))
+            .join(&self.from_terraform.dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let value: TerraformValue = serde_json::from_value(serde_json::json!({
+            "fromTerraform": { "dir": "infra/", "output": "db_endpoint" }
+        }))
+        .unwrap();
+        assert_eq!(value.from_terraform.output, "db_endpoint");
+        assert_eq!(
+            value.declared_in(Path::new("/project")).module_dir(),
+            Path::new("/project/infra/")
+        );
+
+        // Command values are not Terraform values
+        assert!(serde_json::from_value::<TerraformValue>(
+            serde_json::json!({"fromCommand": "date"})
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,72 @@
+//! Typed (non-string) environment values
+
+use serde_json::Value;
+
+/// Separator used to join list values when `config.listSeparator` is unset
+pub const DEFAULT_LIST_SEPARATOR: &str = ":";
+
+/// Serialize a CUE value into the string exported to the environment
+///
+/// Numbers and bools use their literal form, lists are joined with
+/// `list_separator` and structs are encoded as JSON. Nested lists and
+/// structs inside a list are encoded as JSON too. Returns `None` for null.
+pub fn serialize_value(value: &Value, list_separator: &str) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::Array(_) | Value::Object(_) => Some(item.to_string()),
+                    scalar => serialize_value(scalar, list_separator),
+                })
+                .collect::<Vec<_>>()
+                .join(list_separator),
+        ),
+        Value::Object(_) => Some(value.to_string()),
+    }
+}
+
+/// Whether a value needs its original form kept for typed access
+pub fn is_typed(value: &Value) -> bool {
+    !matches!(value, Value::String(_) | Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_scalars_use_their_literal_form() {
+        assert_eq!(serialize_value(&json!(8080), ":").unwrap(), "8080");
+        assert_eq!(serialize_value(&json!(0.5), ":").unwrap(), "0.5");
+        assert_eq!(serialize_value(&json!(true), ":").unwrap(), "true");
+        assert_eq!(serialize_value(&json!(null), ":"), None);
+    }
+
+    #[test]
+    fn test_lists_are_joined_with_the_separator() {
+        let value = json!(["a", 1, false]);
+
+        assert_eq!(serialize_value(&value, ":").unwrap(), "a:1:false");
+        assert_eq!(serialize_value(&value, ",").unwrap(), "a,1,false");
+    }
+
+    #[test]
+    fn test_structs_are_encoded_as_json() {
+        let value = json!({ "host": "localhost", "port": 5432 });
+
+        assert_eq!(
+            serialize_value(&value, ":").unwrap(),
+            r#"{"host":"localhost","port":5432}"#
+        );
+        assert_eq!(
+            serialize_value(&json!([{ "a": 1 }, "b"]), ",").unwrap(),
+            r#"{"a":1},b"#
+        );
+    }
+}
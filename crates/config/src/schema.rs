@@ -0,0 +1,58 @@
+//! The cuenv CUE schema, embedded in the binary
+//!
+//! env.cue files can `import "github.com/rawkode/cuenv/schema"` without
+//! vendoring the package, and every configuration is validated against
+//! `#Cuenv` when it is loaded so unknown fields such as a misspelled
+//! `dependecies:` fail instead of being ignored: by the CUE runtime, or for
+//! packages the native evaluator handles, by checking their fields against
+//! the definitions of the schema and leaving those that fail it to the CUE
+//! runtime. A module that vendors its own copy under `cue.mod/pkg` keeps
+//! using that copy for imports.
+
+use std::collections::HashMap;
+
+/// Import path the schema package is provided under
+pub const SCHEMA_IMPORT_PATH: &str = "github.com/rawkode/cuenv/schema";
+
+/// The files of the schema package, by file name
+pub const SCHEMA_FILES: &[(&str, &str)] = &[
+    (
+        "capabilities.cue",
+        include_str!("../../../schema/capabilities.cue"),
+    ),
+    ("config.cue", include_str!("../../../schema/config.cue")),
+    ("cuenv.cue", include_str!("../../../schema/cuenv.cue")),
+    ("env.cue", include_str!("../../../schema/env.cue")),
+    ("gcp.cue", include_str!("../../../schema/gcp.cue")),
+    ("hooks.cue", include_str!("../../../schema/hooks.cue")),
+    ("nix.cue", include_str!("../../../schema/nix.cue")),
+    ("notify.cue", include_str!("../../../schema/notify.cue")),
+    (
+        "onepassword.cue",
+        include_str!("../../../schema/onepassword.cue"),
+    ),
+    ("secrets.cue", include_str!("../../../schema/secrets.cue")),
+    ("security.cue", include_str!("../../../schema/security.cue")),
+    ("tasks.cue", include_str!("../../../schema/tasks.cue")),
+];
+
+/// The schema files as a map, as passed to the CUE bridge
+pub fn schema_files() -> HashMap<&'static str, &'static str> {
+    SCHEMA_FILES.iter().copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_files_share_one_package() {
+        for (name, content) in SCHEMA_FILES {
+            assert!(
+                content.trim_start().starts_with("package schema"),
+                "{name} is not in the schema package"
+            );
+        }
+        assert!(schema_files()["cuenv.cue"].contains("#Cuenv"));
+    }
+}
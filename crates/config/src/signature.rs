@@ -0,0 +1,310 @@
+//! Verification of signed configurations
+//!
+//! Deploy hosts can refuse task definitions the release pipeline did not
+//! sign. With `CUENV_REQUIRE_SIGNED=1`, a directory is only evaluated when
+//! every file of its configuration, including `env.local.cue` and the files
+//! of the packages it imports from its CUE module, has a valid detached
+//! signature next to it:
+//!
+//! - `env.cue.minisig`, a minisign signature by one of the public keys in
+//!   `CUENV_MINISIGN_KEYS`, given inline or as `.pub` files
+//! - `env.cue.sigstore.json`, a sigstore bundle whose certificate was issued
+//!   by `CUENV_SIGSTORE_ISSUER` to `CUENV_SIGSTORE_IDENTITY`, checked with
+//!   `cosign verify-blob`
+
+use crate::cache::read_sources;
+use crate::hierarchy::local_override;
+use crate::package::config_files;
+use cuenv_core::constants::{
+    CUENV_MINISIGN_KEYS_VAR, CUENV_REQUIRE_SIGNED_VAR, CUENV_SIGSTORE_IDENTITY_VAR,
+    CUENV_SIGSTORE_ISSUER_VAR,
+};
+use cuenv_core::{Error, Result};
+use minisign_verify::{PublicKey, Signature};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Extension of minisign signatures, appended to the signed file's name
+pub const MINISIGN_EXTENSION: &str = "minisig";
+/// Extension of sigstore bundles, appended to the signed file's name
+pub const SIGSTORE_BUNDLE_EXTENSION: &str = "sigstore.json";
+
+/// Separates the keys listed in `CUENV_MINISIGN_KEYS`
+const KEY_SEPARATOR: char = ',';
+
+/// Whether `CUENV_REQUIRE_SIGNED` asks for signed configurations
+pub fn required() -> bool {
+    std::env::var(CUENV_REQUIRE_SIGNED_VAR).is_ok_and(|value| value == "1" || value == "true")
+}
+
+/// Check the signatures of `dir`'s configuration when they are required
+pub fn verify_if_required(dir: &Path, package_name: &str) -> Result<()> {
+    if !required() {
+        return Ok(());
+    }
+    TrustedKeys::from_env()?.verify_dir(dir, package_name)
+}
+
+/// The certificate a sigstore bundle must carry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigstoreIdentity {
+    /// The signer, such as the workflow URL of the release pipeline
+    pub identity: String,
+    /// The OIDC issuer that vouched for the signer
+    pub issuer: String,
+}
+
+/// The signers whose configurations are trusted
+#[derive(Default)]
+pub struct TrustedKeys {
+    minisign: Vec<PublicKey>,
+    sigstore: Option<SigstoreIdentity>,
+}
+
+impl TrustedKeys {
+    /// The signers configured in the environment. Requiring signatures
+    /// without trusting anyone is an error.
+    pub fn from_env() -> Result<Self> {
+        let mut keys = Self::default();
+        if let Ok(value) = std::env::var(CUENV_MINISIGN_KEYS_VAR) {
+            for key in value.split(KEY_SEPARATOR).map(str::trim) {
+                if !key.is_empty() {
+                    keys.minisign.push(parse_minisign_key(key)?);
+                }
+            }
+        }
+        if let (Ok(identity), Ok(issuer)) = (
+            std::env::var(CUENV_SIGSTORE_IDENTITY_VAR),
+            std::env::var(CUENV_SIGSTORE_ISSUER_VAR),
+        ) {
+            keys.sigstore = Some(SigstoreIdentity { identity, issuer });
+        }
+
+        if keys.minisign.is_empty() && keys.sigstore.is_none() {
+            return Err(Error::security(format!(
+                "{CUENV_REQUIRE_SIGNED_VAR} is set but no signer is trusted: set \
+                 {CUENV_MINISIGN_KEYS_VAR}, or {CUENV_SIGSTORE_IDENTITY_VAR} and \
+                 {CUENV_SIGSTORE_ISSUER_VAR}"
+            )));
+        }
+        Ok(keys)
+    }
+
+    /// Trust signatures by a minisign public key
+    pub fn with_minisign_key(mut self, key: PublicKey) -> Self {
+        self.minisign.push(key);
+        self
+    }
+
+    /// Trust sigstore bundles issued to `identity`
+    pub fn with_sigstore_identity(mut self, identity: SigstoreIdentity) -> Self {
+        self.sigstore = Some(identity);
+        self
+    }
+
+    /// Check every file configuring `dir`, and those of the packages it
+    /// imports from its CUE module, such as under `cue.mod/pkg`
+    pub fn verify_dir(&self, dir: &Path, package_name: &str) -> Result<()> {
+        for file in signed_files(dir, package_name) {
+            self.verify_file(&file)?;
+        }
+        Ok(())
+    }
+
+    /// Check the detached signature of `file`
+    pub fn verify_file(&self, file: &Path) -> Result<()> {
+        let minisig = signature_path(file, MINISIGN_EXTENSION);
+        if !self.minisign.is_empty() && minisig.is_file() {
+            return self.verify_minisign(file, &minisig);
+        }
+
+        let bundle = signature_path(file, SIGSTORE_BUNDLE_EXTENSION);
+        if let Some(identity) = &self.sigstore {
+            if bundle.is_file() {
+                return verify_sigstore(file, &bundle, identity);
+            }
+        }
+
+        Err(Error::security(format!(
+            "{} is not signed: {CUENV_REQUIRE_SIGNED_VAR} requires a trusted {} next to it",
+            file.display(),
+            match (self.minisign.is_empty(), self.sigstore.is_none()) {
+                (false, true) => format!("{}", minisig.display()),
+                (true, false) => format!("{}", bundle.display()),
+                _ => format!("{} or {}", minisig.display(), bundle.display()),
+            }
+        )))
+    }
+
+    fn verify_minisign(&self, file: &Path, minisig: &Path) -> Result<()> {
+        let content = read(file)?;
+        let signature = Signature::decode(&String::from_utf8_lossy(&read(minisig)?))
+            .map_err(|e| invalid_signature(file, e))?;
+
+        let mut error = None;
+        for key in &self.minisign {
+            match key.verify(&content, &signature, false) {
+                Ok(()) => return Ok(()),
+                Err(e) => error = Some(e),
+            }
+        }
+        Err(invalid_signature(
+            file,
+            error.map_or_else(|| "no trusted key".to_string(), |e| e.to_string()),
+        ))
+    }
+}
+
+/// The files of `dir`'s configuration that must be signed, followed by those
+/// of the packages it imports, as the evaluation reads them
+fn signed_files(dir: &Path, package_name: &str) -> Vec<PathBuf> {
+    let mut files = config_files(dir, package_name);
+    files.extend(local_override(dir));
+    read_sources(dir, package_name, |file, content| {
+        if content.is_some() && !files.contains(&file) {
+            files.push(file);
+        }
+    });
+    files
+}
+
+/// A key given inline in base64 or as the path of a `.pub` file
+fn parse_minisign_key(key: &str) -> Result<PublicKey> {
+    let parsed = if Path::new(key).is_file() {
+        PublicKey::from_file(key)
+    } else {
+        PublicKey::from_base64(key)
+    };
+    parsed.map_err(|e| {
+        Error::configuration(format!(
+            "Invalid minisign key '{key}' in {CUENV_MINISIGN_KEYS_VAR}: {e}"
+        ))
+    })
+}
+
+/// `file` with `extension` appended, as in `env.cue.minisig`
+fn signature_path(file: &Path, extension: &str) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+fn verify_sigstore(file: &Path, bundle: &Path, identity: &SigstoreIdentity) -> Result<()> {
+    let output = Command::new("cosign")
+        .arg("verify-blob")
+        .arg("--bundle")
+        .arg(bundle)
+        .args(["--certificate-identity", &identity.identity])
+        .args(["--certificate-oidc-issuer", &identity.issuer])
+        .arg(file)
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::security(
+                "Verifying sigstore bundles needs cosign, which is not installed".to_string(),
+            ),
+            _ => Error::security(format!("Failed to run cosign: {e}")),
+        })?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(invalid_signature(
+            file,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        ))
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| Error::file_system(path, "read", e))
+}
+
+fn invalid_signature(file: &Path, reason: impl std::fmt::Display) -> Error {
+    Error::security(format!(
+        "Refusing to load {}: its signature is not valid: {reason}",
+        file.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_core::constants::ENV_LOCAL_CUE_FILENAME;
+
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    /// A signature of `test` by [`PUBLIC_KEY`]
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1633700835\tfile:test\tprehashed
+wLMDjy9FLAuxZ3q4NlEvkgtyhrr0gtTu6KC4KBJdITbbOeAi1zBIYo0v4iTgt8jJpIidRJnp94ABQkJAgAooBQ==
+";
+
+    fn trusted() -> TrustedKeys {
+        TrustedKeys::default().with_minisign_key(PublicKey::from_base64(PUBLIC_KEY).unwrap())
+    }
+
+    #[test]
+    fn test_verify_minisign() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("cuenv.json");
+        std::fs::write(&file, "test").unwrap();
+
+        let error = trusted().verify_file(&file).unwrap_err();
+        assert!(error.to_string().contains("is not signed"), "{error}");
+
+        std::fs::write(signature_path(&file, MINISIGN_EXTENSION), SIGNATURE).unwrap();
+        trusted().verify_file(&file).unwrap();
+        trusted().verify_dir(dir.path(), "cuenv").unwrap();
+
+        std::fs::write(&file, "tampered").unwrap();
+        let error = trusted().verify_file(&file).unwrap_err();
+        assert!(error.to_string().contains("not valid"), "{error}");
+    }
+
+    #[test]
+    fn test_local_override_needs_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("cuenv.json");
+        std::fs::write(&file, "test").unwrap();
+        std::fs::write(signature_path(&file, MINISIGN_EXTENSION), SIGNATURE).unwrap();
+        std::fs::write(dir.path().join(ENV_LOCAL_CUE_FILENAME), "package cuenv\n").unwrap();
+
+        let error = trusted().verify_dir(dir.path(), "cuenv").unwrap_err();
+        assert!(
+            error.to_string().contains(ENV_LOCAL_CUE_FILENAME),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_imported_packages_need_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("cue.mod/pkg/example.com/shared")).unwrap();
+        std::fs::write(
+            dir.path().join("cue.mod/module.cue"),
+            "module: \"example.com/app\"\n",
+        )
+        .unwrap();
+        let shared = dir.path().join("cue.mod/pkg/example.com/shared/tasks.cue");
+        std::fs::write(&shared, "package shared\n").unwrap();
+        std::fs::write(
+            dir.path().join("env.cue"),
+            "package cuenv\n\nimport \"example.com/shared\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            signed_files(dir.path(), "cuenv"),
+            [dir.path().join("env.cue"), shared]
+        );
+    }
+
+    #[test]
+    fn test_signature_path() {
+        assert_eq!(
+            signature_path(Path::new("/app/env.cue"), SIGSTORE_BUNDLE_EXTENSION),
+            Path::new("/app/env.cue.sigstore.json")
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! The per-user configuration file, `~/.config/cuenv/config.toml`
+//!
+//! It holds the defaults of one user across projects: when to color output
+//! and in which theme, how many tasks run at once, directories trusted without `cuenv allow`,
+//! cache limits, and defaults for the settings a project declares under
+//! `config:` in env.cue. The file is read once per process, and a project's
+//! own settings always take precedence over it.
+//!
+//! ```toml
+//! color = "always"
+//! theme = "bright"
+//! jobs = 4
+//! trusted = ["~/src/work"]
+//!
+//! [cache]
+//! max_size = "5GB"
+//!
+//! [defaults]
+//! outputFormat = "simple"
+//! taskPrefixMatch = true
+//! ```
+
+use crate::ConfigSettings;
+use cuenv_core::style::{self, Theme};
+use cuenv_core::{Error, Result};
+use cuenv_utils::xdg::XdgPaths;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// The name of the file in the cuenv configuration directory
+pub const USER_CONFIG_FILE: &str = "config.toml";
+
+/// When output is colored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// When writing to a terminal and `NO_COLOR` is not set, or when
+    /// `CLICOLOR_FORCE` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether colors are forced on or off, rather than left to the
+    /// environment and the terminal
+    pub fn forced(self) -> Option<bool> {
+        match self {
+            Self::Auto => None,
+            Self::Always => Some(true),
+            Self::Never => Some(false),
+        }
+    }
+}
+
+/// The `[cache]` table
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserCacheConfig {
+    pub enabled: Option<bool>,
+    /// Size the cache is pruned to, in bytes; written as a number of bytes
+    /// or a size such as `"5GB"`
+    #[serde(deserialize_with = "deserialize_size")]
+    pub max_size: Option<u64>,
+    /// Endpoint of a remote cache to share task results through
+    pub remote: Option<String>,
+}
+
+/// The settings of `config.toml`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserConfig {
+    pub color: ColorChoice,
+    /// The colors output is drawn in
+    pub theme: Theme,
+    /// Most tasks run at once; unlimited when unset
+    pub jobs: Option<usize>,
+    /// Directories whose configuration loads without `cuenv allow`, along
+    /// with their subdirectories
+    pub trusted: Vec<PathBuf>,
+    pub cache: UserCacheConfig,
+    /// Defaults for the settings of `config:` in env.cue
+    pub defaults: ConfigSettings,
+}
+
+impl UserConfig {
+    /// Where the file is read from
+    pub fn path() -> PathBuf {
+        XdgPaths::config_dir().join(USER_CONFIG_FILE)
+    }
+
+    /// Read the file, or the defaults when there is none
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| Error::configuration(format!("Invalid {}: {e}", path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::file_system(path, "read user configuration", e)),
+        }
+    }
+
+    /// The configuration of this process, read on first use
+    ///
+    /// A file that cannot be read is reported once and ignored, so that a
+    /// mistake in it does not stop every command.
+    pub fn get() -> &'static Self {
+        static USER_CONFIG: OnceLock<UserConfig> = OnceLock::new();
+        USER_CONFIG.get_or_init(|| {
+            Self::load().unwrap_or_else(|e| {
+                log::warn!("{e}; using the default user configuration");
+                Self::default()
+            })
+        })
+    }
+
+    fn parse(content: &str) -> std::result::Result<Self, String> {
+        let config: Self = toml::from_str(content).map_err(|e| e.to_string())?;
+        if config.jobs == Some(0) {
+            return Err("jobs must be at least 1".to_string());
+        }
+        // A relative directory would be trusted wherever cuenv runs
+        for trusted in &config.trusted {
+            if !trusted.is_absolute() && !trusted.starts_with("~") {
+                return Err(format!(
+                    "trusted directory '{}' must be an absolute path or start with ~",
+                    trusted.display()
+                ));
+            }
+        }
+        config.defaults.validate()?;
+        Ok(config)
+    }
+
+    /// Style output as configured, for [`style::stdout`] and
+    /// [`style::stderr`]
+    pub fn init_style(&self) {
+        style::init(self.color.forced(), self.theme);
+    }
+
+    /// Whether `dir` is one of the trusted directories or inside one
+    ///
+    /// A directory under `~` is not trusted when the home directory is
+    /// unknown.
+    pub fn is_trusted(&self, dir: &Path) -> bool {
+        self.trusted
+            .iter()
+            .map(|trusted| expand_home(trusted))
+            .filter(|trusted| trusted.is_absolute())
+            .any(|trusted| {
+                let trusted = trusted.canonicalize().unwrap_or(trusted);
+                dir.starts_with(trusted)
+            })
+    }
+}
+
+impl ConfigSettings {
+    /// These settings, with those they leave unset taken from `defaults`
+    pub fn layered_over(&self, defaults: &ConfigSettings) -> ConfigSettings {
+        ConfigSettings {
+            output_format: self
+                .output_format
+                .clone()
+                .or_else(|| defaults.output_format.clone()),
+            cache_mode: self
+                .cache_mode
+                .clone()
+                .or_else(|| defaults.cache_mode.clone()),
+            cache_enabled: self.cache_enabled.or(defaults.cache_enabled),
+            audit_mode: self.audit_mode.or(defaults.audit_mode),
+            trace_output: self.trace_output.or(defaults.trace_output),
+            default_environment: self
+                .default_environment
+                .clone()
+                .or_else(|| defaults.default_environment.clone()),
+            default_capabilities: self
+                .default_capabilities
+                .clone()
+                .or_else(|| defaults.default_capabilities.clone()),
+            lazy_secrets: self.lazy_secrets.or(defaults.lazy_secrets),
+            list_separator: self
+                .list_separator
+                .clone()
+                .or_else(|| defaults.list_separator.clone()),
+            task_prefix_match: self.task_prefix_match.or(defaults.task_prefix_match),
+            npm_scripts: self.npm_scripts.or(defaults.npm_scripts),
+            tool_versions: self.tool_versions.or(defaults.tool_versions),
+            cache: match (&self.cache, &defaults.cache) {
+                (Some(cache), Some(defaults)) => Some(cache.layered_over(defaults)),
+                (cache, defaults) => cache.clone().or_else(|| defaults.clone()),
+            },
+        }
+    }
+
+    /// The settings of a project, layered over those of the user
+    pub fn effective(project: Option<&ConfigSettings>) -> ConfigSettings {
+        let defaults = &UserConfig::get().defaults;
+        match project {
+            Some(project) => project.layered_over(defaults),
+            None => defaults.clone(),
+        }
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A size written as a number of bytes or as a string such as `"5GB"`
+pub(crate) fn deserialize_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Parse a size such as `500MB` or `2GB`; a number alone is bytes
+pub fn parse_size(size: &str) -> std::result::Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size '{size}', expected e.g. 500MB or 2GB"))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1u64,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "Invalid size unit '{unit}', expected B, KB, MB, GB or TB"
+            ))
+        }
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse an age such as `30m`, `12h`, `7d` or `2w`; a number alone is seconds
+pub fn parse_age(age: &str) -> std::result::Result<Duration, String> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (number, unit) = age.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid age '{age}', expected e.g. 12h or 7d"))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Invalid age unit '{unit}', expected s, m, h, d or w"
+            ))
+        }
+    };
+    Ok(Duration::from_secs(number * seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = UserConfig::parse(
+            r#"
+            color = "never"
+            theme = "mono"
+            jobs = 4
+            trusted = ["/src/work"]
+
+            [cache]
+            max_size = "5GB"
+            remote = "grpc://cache.example.com:9092"
+
+            [defaults]
+            outputFormat = "simple"
+            taskPrefixMatch = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.color, ColorChoice::Never);
+        assert_eq!(config.theme, Theme::Mono);
+        assert_eq!(config.jobs, Some(4));
+        assert!(config.is_trusted(Path::new("/src/work/app")));
+        assert!(!config.is_trusted(Path::new("/src/other")));
+        assert_eq!(config.cache.max_size, Some(5 << 30));
+        assert_eq!(config.defaults.output_format.as_deref(), Some("simple"));
+        assert_eq!(config.defaults.task_prefix_match, Some(true));
+        assert_eq!(config.color.forced(), Some(false));
+
+        assert_eq!(UserConfig::parse("").unwrap(), UserConfig::default());
+        assert!(UserConfig::parse("jobs = 0").is_err());
+        assert!(UserConfig::parse("trusted = [\"\"]").is_err());
+        assert!(UserConfig::parse("trusted = [\"src/work\"]").is_err());
+        assert!(UserConfig::parse("trusted = [\"~/src/work\"]").is_ok());
+        assert!(UserConfig::parse("colour = \"never\"").is_err());
+        assert!(UserConfig::parse("theme = \"solarized\"").is_err());
+        assert!(UserConfig::parse("[defaults]\noutputFormat = \"fancy\"").is_err());
+    }
+
+    #[test]
+    fn test_layered_over() {
+        let project = ConfigSettings {
+            output_format: Some("tui".to_string()),
+            ..Default::default()
+        };
+        let defaults = ConfigSettings {
+            output_format: Some("simple".to_string()),
+            task_prefix_match: Some(true),
+            ..Default::default()
+        };
+
+        let settings = project.layered_over(&defaults);
+        assert_eq!(settings.output_format.as_deref(), Some("tui"));
+        assert_eq!(settings.task_prefix_match, Some(true));
+        assert_eq!(settings.cache_mode, None);
+    }
+}
@@ -0,0 +1,198 @@
+//! The workspace of a monorepo
+//!
+//! A `cuenv.workspace.cue` at the root of a repository declares which of
+//! its directories are packages, as globs relative to the root:
+//!
+//! ```cue
+//! package workspace
+//!
+//! packages: ["apps/*", "libs/*"]
+//! exclude: ["apps/legacy"]
+//!
+//! cache: {
+//!     remote: "grpc://cache.example.com:9092"
+//!     maxSize: "20GB"
+//!     maxAge: "14d"
+//! }
+//! ```
+//!
+//! The file is a package of its own, so it is not part of the root
+//! directory's configuration, and it is evaluated without the CUE runtime,
+//! so it only holds plain values. Its `cache` settings apply to every
+//! package, unless a package sets them under `config: cache:` itself.
+
+use crate::parser::native;
+use crate::user::{deserialize_size, parse_age, UserConfig};
+use cuenv_core::constants::{WORKSPACE_FILENAME, WORKSPACE_PACKAGE_NAME};
+use cuenv_core::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The packages a workspace declares
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Globs of the package directories, relative to the root
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// Globs of directories left out even when `packages` matches them
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Cache settings of every package
+    #[serde(default)]
+    pub cache: CacheSettings,
+}
+
+/// Where task results are shared and how long they are kept, declared
+/// under `cache` of a workspace or `config: cache` of a package
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct CacheSettings {
+    /// Endpoint of a remote cache to share task results through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// Size the cache is pruned to, in bytes or as a size such as `"5GB"`
+    #[serde(
+        deserialize_with = "deserialize_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_size: Option<u64>,
+    /// Age of the results pruned by default, such as `"7d"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+}
+
+impl CacheSettings {
+    /// The cache settings of the package in `dir`: those of `package`, over
+    /// those of the workspace `dir` is in, over the `[cache]` table of the
+    /// user configuration
+    pub fn resolve(dir: &Path, package: Option<&CacheSettings>) -> CacheSettings {
+        let user = &UserConfig::get().cache;
+        let mut settings = CacheSettings {
+            remote: user.remote.clone(),
+            max_size: user.max_size,
+            max_age: None,
+        };
+        if let Some(root) = WorkspaceConfig::find_root(dir) {
+            match WorkspaceConfig::load(&root) {
+                Ok(workspace) => settings = workspace.cache.layered_over(&settings),
+                Err(e) => log::warn!("{e}; ignoring its cache settings"),
+            }
+        }
+        match package {
+            Some(package) => package.layered_over(&settings),
+            None => settings,
+        }
+    }
+
+    /// These settings, with those they leave unset taken from `defaults`
+    pub fn layered_over(&self, defaults: &CacheSettings) -> CacheSettings {
+        CacheSettings {
+            remote: self.remote.clone().or_else(|| defaults.remote.clone()),
+            max_size: self.max_size.or(defaults.max_size),
+            max_age: self.max_age.clone().or_else(|| defaults.max_age.clone()),
+        }
+    }
+
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(ref age) = self.max_age {
+            parse_age(age).map_err(|e| format!("Invalid cache maxAge: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl WorkspaceConfig {
+    /// The root of the workspace `dir` is in: the closest directory with a
+    /// cuenv.workspace.cue
+    pub fn find_root(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .find(|dir| dir.join(WORKSPACE_FILENAME).is_file())
+            .map(Path::to_path_buf)
+    }
+
+    /// Read the cuenv.workspace.cue of `root`
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(WORKSPACE_FILENAME);
+        let value = native::evaluate_file(&path, WORKSPACE_PACKAGE_NAME).map_err(|reason| {
+            Error::configuration(format!(
+                "{}: {reason}; it must be `package {WORKSPACE_PACKAGE_NAME}` and only hold plain values",
+                path.display()
+            ))
+        })?;
+        let workspace: Self = serde_json::from_value(value)
+            .map_err(|e| Error::configuration(format!("{}: {e}", path.display())))?;
+        workspace
+            .cache
+            .validate()
+            .map_err(|e| Error::configuration(format!("{}: {e}", path.display())))?;
+        Ok(workspace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load() {
+        let root = TempDir::new().unwrap();
+        std::fs::write(
+            root.path().join(WORKSPACE_FILENAME),
+            "package workspace\n\npackages: [\"apps/*\", \"libs/*\"]\nexclude: [\"apps/legacy\"]\ncache: maxSize: \"2GB\"\n",
+        )
+        .unwrap();
+        let nested = root.path().join("apps").join("web");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(
+            WorkspaceConfig::find_root(&nested).as_deref(),
+            Some(root.path())
+        );
+        assert_eq!(
+            WorkspaceConfig::load(root.path()).unwrap(),
+            WorkspaceConfig {
+                packages: vec!["apps/*".to_string(), "libs/*".to_string()],
+                exclude: vec!["apps/legacy".to_string()],
+                cache: CacheSettings {
+                    max_size: Some(2 << 30),
+                    ..Default::default()
+                },
+            }
+        );
+
+        std::fs::write(
+            root.path().join(WORKSPACE_FILENAME),
+            "package cuenv\n\npackages: [\"apps/*\"]\n",
+        )
+        .unwrap();
+        let message = WorkspaceConfig::load(root.path()).unwrap_err().to_string();
+        assert!(message.contains("package workspace"), "{message}");
+    }
+
+    #[test]
+    fn test_cache_layered_over() {
+        let workspace = CacheSettings {
+            remote: Some("grpc://cache.example.com:9092".to_string()),
+            max_size: Some(20 << 30),
+            max_age: Some("14d".to_string()),
+        };
+        let package = CacheSettings {
+            max_age: Some("2d".to_string()),
+            ..Default::default()
+        };
+
+        let settings = package.layered_over(&workspace);
+        assert_eq!(settings.remote, workspace.remote);
+        assert_eq!(settings.max_size, Some(20 << 30));
+        assert_eq!(settings.max_age.as_deref(), Some("2d"));
+
+        assert!(settings.validate().is_ok());
+        let invalid = CacheSettings {
+            max_age: Some("2y".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -1,8 +1,11 @@
 /// Constants used throughout the cuenv codebase
 // CUE package constants
 pub const ENV_CUE_FILENAME: &str = "env.cue";
+pub const ENV_LOCAL_CUE_FILENAME: &str = "env.local.cue";
 pub const CUENV_PACKAGE_VAR: &str = "CUENV_PACKAGE";
 pub const DEFAULT_PACKAGE_NAME: &str = "cuenv";
+pub const WORKSPACE_FILENAME: &str = "cuenv.workspace.cue";
+pub const WORKSPACE_PACKAGE_NAME: &str = "workspace";
 
 // Resolver prefix
 pub const CUENV_RESOLVER_PREFIX: &str = "cuenv-resolver://";
@@ -11,6 +14,20 @@ pub const CUENV_RESOLVER_PREFIX: &str = "cuenv-resolver://";
 pub const CUENV_ENV_VAR: &str = "CUENV_ENV";
 pub const CUENV_CAPABILITIES_VAR: &str = "CUENV_CAPABILITIES";
 pub const CUENV_LOG_VAR: &str = "CUENV_LOG";
+pub const CUENV_LOG_FORMAT_VAR: &str = "CUENV_LOG_FORMAT";
+pub const CUENV_RUN_ID_VAR: &str = "CUENV_RUN_ID";
+pub const CUENV_TAGS_VAR: &str = "CUENV_TAGS";
+pub const CUENV_EVAL_CACHE_VAR: &str = "CUENV_EVAL_CACHE";
+pub const CUENV_FILE_VAR: &str = "CUENV_FILE";
+pub const CUENV_EVALUATOR_VAR: &str = "CUENV_EVALUATOR";
+pub const CUENV_TMUX_VAR: &str = "CUENV_TMUX";
+pub const CUENV_OSC_VAR: &str = "CUENV_OSC";
+pub const CUENV_INSECURE_ALLOW_VAR: &str = "CUENV_INSECURE_ALLOW";
+pub const CUENV_REQUIRE_SIGNED_VAR: &str = "CUENV_REQUIRE_SIGNED";
+pub const CUENV_MINISIGN_KEYS_VAR: &str = "CUENV_MINISIGN_KEYS";
+pub const CUENV_SIGSTORE_IDENTITY_VAR: &str = "CUENV_SIGSTORE_IDENTITY";
+pub const CUENV_SIGSTORE_ISSUER_VAR: &str = "CUENV_SIGSTORE_ISSUER";
+pub const CUENV_CHANGED_FILES_VAR: &str = "CUENV_CHANGED_FILES";
 
 // Default shell
 pub const DEFAULT_SHELL: &str = "bash";
@@ -18,6 +35,23 @@ pub const DEFAULT_SHELL: &str = "bash";
 // System paths to filter in audit mode
 pub const AUDIT_IGNORED_PATH_PREFIXES: &[&str] = &["/proc/", "/sys/", "/dev/", "/tmp/"];
 
+// System paths sandboxed tasks can always read and execute, so that the
+// shell and the tools it runs still start
+pub const SANDBOX_READ_ONLY_PATHS: &[&str] = &[
+    "/bin",
+    "/sbin",
+    "/usr",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/etc",
+    "/proc",
+    "/nix/store",
+];
+
+// System paths sandboxed tasks can always write, for /dev/null and the terminal
+pub const SANDBOX_READ_WRITE_PATHS: &[&str] = &["/dev"];
+
 // Common system files
 pub const LD_SO_CACHE: &str = "/etc/ld.so.cache";
 
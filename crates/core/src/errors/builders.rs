@@ -1,5 +1,6 @@
 //! Builder methods for creating errors with context
 
+use super::diagnostic::Diagnostic;
 use super::types::Error;
 use std::path::PathBuf;
 
@@ -11,6 +12,22 @@ impl Error {
         Error::CueParse {
             path: path.into(),
             message: message.into(),
+            diagnostics: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Create a CUE parse error pointing at positions in the CUE files
+    #[must_use]
+    pub fn cue_parse_with_diagnostics(
+        path: impl Into<PathBuf>,
+        message: impl Into<String>,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Self {
+        Error::CueParse {
+            path: path.into(),
+            message: message.into(),
+            diagnostics,
             source: None,
         }
     }
@@ -25,6 +42,7 @@ impl Error {
         Error::CueParse {
             path: path.into(),
             message: message.into(),
+            diagnostics: Vec::new(),
             source: Some(source.into()),
         }
     }
@@ -72,6 +90,31 @@ impl Error {
         }
     }
 
+    /// Create a usage error
+    #[must_use]
+    pub fn usage(message: impl Into<String>) -> Self {
+        Error::Usage {
+            message: message.into(),
+        }
+    }
+
+    /// Create a cache error
+    #[must_use]
+    pub fn cache(message: impl Into<String>) -> Self {
+        Error::Cache {
+            message: message.into(),
+        }
+    }
+
+    /// Create an error for a task that exited with `exit_code`
+    #[must_use]
+    pub fn task_failed(task: impl Into<String>, exit_code: i32) -> Self {
+        Error::TaskFailed {
+            task: task.into(),
+            exit_code,
+        }
+    }
+
     /// Create a shell expansion error
     #[must_use]
     pub fn shell_expansion(value: impl Into<String>, message: impl Into<String>) -> Self {
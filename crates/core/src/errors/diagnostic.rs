@@ -0,0 +1,147 @@
+//! Source positions attached to CUE evaluation errors
+
+use super::types::Error;
+use crate::style::{Painter, Role};
+use std::fmt::{self, Write};
+use std::path::PathBuf;
+
+/// A problem at a position in a CUE file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub file: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+    /// The text of the line the position is on
+    pub snippet: Option<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic whose snippet is read from `file`, if it can be
+    pub fn from_source(
+        message: impl Into<String>,
+        file: impl Into<PathBuf>,
+        line: usize,
+        column: usize,
+    ) -> Self {
+        let file = file.into();
+        let snippet = std::fs::read_to_string(&file).ok().and_then(|content| {
+            content
+                .lines()
+                .nth(line.checked_sub(1)?)
+                .map(|text| text.trim_end().to_string())
+        });
+
+        Self {
+            message: message.into(),
+            file,
+            line,
+            column,
+            snippet,
+        }
+    }
+}
+
+impl Error {
+    /// The source positions of the first CUE parse error in the error chain
+    ///
+    /// Evaluation errors are usually wrapped with context about what was
+    /// being loaded, so the diagnostics may sit on an inner error.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        let mut current: Option<&(dyn std::error::Error + 'static)> = Some(self);
+        while let Some(error) = current {
+            if let Some(Error::CueParse { diagnostics, .. }) = error.downcast_ref::<Error>() {
+                if !diagnostics.is_empty() {
+                    return diagnostics;
+                }
+            }
+            current = error.source();
+        }
+        &[]
+    }
+}
+
+impl Diagnostic {
+    /// Renders like a compiler error, underlining the column in the snippet
+    pub fn render(&self, painter: &Painter) -> String {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let bar = painter.paint(Role::Accent, "|");
+
+        let mut out = format!(
+            "{}: {}\n{gutter}{} {}:{}:{}",
+            painter.bold(Role::Failure, "error"),
+            self.message,
+            painter.paint(Role::Accent, "-->"),
+            self.file.display(),
+            self.line,
+            self.column
+        );
+
+        if let Some(snippet) = &self.snippet {
+            // Tabs are kept so the marker lines up with the source
+            let indent: String = snippet
+                .chars()
+                .take(self.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let _ = write!(
+                out,
+                "\n{gutter} {bar}\n{} {bar} {snippet}\n{gutter} {bar} {indent}{}",
+                painter.paint(Role::Accent, &line_number),
+                painter.paint(Role::Failure, "^")
+            );
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&Painter::plain()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_annotated_snippet() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("env.cue");
+        std::fs::write(&file, "package cuenv\n\nenv: {\n\tPORT: \"80\" & 80\n}\n").unwrap();
+
+        let diagnostic = Diagnostic::from_source("conflicting values", &file, 4, 8);
+        assert_eq!(diagnostic.snippet.as_deref(), Some("\tPORT: \"80\" & 80"));
+        assert_eq!(
+            diagnostic.to_string(),
+            format!(
+                "error: conflicting values\n --> {}:4:8\n  |\n4 | \tPORT: \"80\" & 80\n  | \t      ^",
+                file.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_are_found_on_wrapped_errors() {
+        let diagnostic = Diagnostic::from_source("oops", "/nonexistent/env.cue", 1, 1);
+        let inner = Error::cue_parse_with_diagnostics("/project", "oops", vec![diagnostic]);
+        let outer = Error::cue_parse_with_source("/project", "Failed to evaluate", inner);
+
+        assert_eq!(outer.diagnostics().len(), 1);
+        assert!(Error::configuration("unrelated").diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_missing_file_renders_position_only() {
+        let diagnostic = Diagnostic::from_source("oops", "/nonexistent/env.cue", 12, 3);
+        assert_eq!(diagnostic.snippet, None);
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: oops\n  --> /nonexistent/env.cue:12:3"
+        );
+    }
+}
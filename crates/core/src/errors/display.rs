@@ -52,6 +52,13 @@ impl fmt::Display for Error {
             Error::Configuration { message } => {
                 write!(f, "configuration error: {message}")
             }
+            Error::Usage { message } => f.write_str(message),
+            Error::Cache { message } => {
+                write!(f, "cache error: {message}")
+            }
+            Error::TaskFailed { task, exit_code } => {
+                write!(f, "task '{task}' failed with exit code {exit_code}")
+            }
             Error::ShellExpansion { value, message } => {
                 write!(f, "failed to expand shell value '{value}': {message}")
             }
@@ -0,0 +1,117 @@
+//! Classes of errors and the exit codes cuenv exits with
+//!
+//! Exit codes are a contract scripts and CI pipelines can branch on. A task
+//! or command that fails passes its own exit code through, while cuenv's own
+//! errors use the codes of BSD's `sysexits.h`, which tasks rarely exit with.
+
+use super::types::Error;
+use serde::Serialize;
+
+/// Exit codes of cuenv
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// A check reported problems, as `cuenv vet` or `cuenv fmt --check` do,
+    /// or a failed command did not report an exit code
+    pub const FAILURE: i32 = 1;
+    /// A command was used wrongly, such as with a task that does not exist
+    pub const USAGE: i32 = 64;
+    /// cuenv failed on its own, such as on an unreadable file
+    pub const INTERNAL: i32 = 70;
+    /// The cache failed
+    pub const CACHE: i32 = 75;
+    /// Access was refused, such as to a directory that is not allowed
+    pub const PERMISSION: i32 = 77;
+    /// The configuration is invalid or cannot be evaluated
+    pub const CONFIGURATION: i32 = 78;
+}
+
+/// The class of failure an error belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Usage,
+    Configuration,
+    Permission,
+    Cache,
+    /// A task, hook or other command cuenv ran failed
+    Command,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Usage => "usage",
+            Self::Configuration => "configuration",
+            Self::Permission => "permission",
+            Self::Cache => "cache",
+            Self::Command => "command",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+impl Error {
+    /// The class of failure of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Usage { .. } => ErrorKind::Usage,
+            Error::CueParse { .. }
+            | Error::Configuration { .. }
+            | Error::Environment { .. }
+            | Error::ShellExpansion { .. }
+            | Error::SecretResolution { .. } => ErrorKind::Configuration,
+            Error::PermissionDenied { .. } | Error::Security { .. } => ErrorKind::Permission,
+            Error::Cache { .. } => ErrorKind::Cache,
+            Error::TaskFailed { .. } | Error::CommandExecution { .. } => ErrorKind::Command,
+            Error::FileSystem { .. }
+            | Error::Json { .. }
+            | Error::Ffi { .. }
+            | Error::Unsupported { .. }
+            | Error::Network { .. }
+            | Error::Timeout { .. } => ErrorKind::Internal,
+        }
+    }
+
+    /// A stable name of this error, such as `cue_parse` or `task_failed`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::CueParse { .. } => "cue_parse",
+            Error::Environment { .. } => "environment",
+            Error::SecretResolution { .. } => "secret_resolution",
+            Error::CommandExecution { .. } => "command_execution",
+            Error::Configuration { .. } => "configuration",
+            Error::Usage { .. } => "usage",
+            Error::Cache { .. } => "cache",
+            Error::TaskFailed { .. } => "task_failed",
+            Error::ShellExpansion { .. } => "shell_expansion",
+            Error::FileSystem { .. } => "file_system",
+            Error::Json { .. } => "json",
+            Error::Ffi { .. } => "ffi",
+            Error::PermissionDenied { .. } => "permission_denied",
+            Error::Unsupported { .. } => "unsupported",
+            Error::Security { .. } => "security",
+            Error::Network { .. } => "network",
+            Error::Timeout { .. } => "timeout",
+        }
+    }
+
+    /// The code cuenv exits with on this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::TaskFailed { exit_code, .. } if *exit_code != 0 => *exit_code,
+            Error::CommandExecution {
+                exit_code: Some(exit_code),
+                ..
+            } if *exit_code != 0 => *exit_code,
+            _ => match self.kind() {
+                ErrorKind::Usage => exit_code::USAGE,
+                ErrorKind::Configuration => exit_code::CONFIGURATION,
+                ErrorKind::Permission => exit_code::PERMISSION,
+                ErrorKind::Cache => exit_code::CACHE,
+                ErrorKind::Command => exit_code::FAILURE,
+                ErrorKind::Internal => exit_code::INTERNAL,
+            },
+        }
+    }
+}
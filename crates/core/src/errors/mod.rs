@@ -2,9 +2,14 @@
 
 mod builders;
 mod conversions;
+mod diagnostic;
 mod display;
 mod extensions;
+mod kind;
+mod report;
 mod types;
 
+pub use diagnostic::Diagnostic;
 pub use extensions::*;
+pub use kind::{exit_code, ErrorKind};
 pub use types::{Error, Result};
@@ -0,0 +1,147 @@
+//! Errors as JSON, for tools that run cuenv
+
+use super::types::Error;
+use serde_json::{json, Map, Value};
+
+impl Error {
+    /// The error with its class, exit code and the fields describing it
+    ///
+    /// ```json
+    /// {
+    ///   "kind": "configuration",
+    ///   "code": "cue_parse",
+    ///   "exit_code": 78,
+    ///   "message": "failed to parse CUE file 'env.cue': ...",
+    ///   "context": { "path": "env.cue" },
+    ///   "causes": [],
+    ///   "diagnostics": [{ "message": "...", "file": "env.cue", "line": 4, "column": 8 }]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let mut causes = Vec::new();
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            causes.push(Value::String(error.to_string()));
+            source = error.source();
+        }
+
+        let diagnostics: Vec<Value> = self
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "message": diagnostic.message,
+                    "file": diagnostic.file.display().to_string(),
+                    "line": diagnostic.line,
+                    "column": diagnostic.column,
+                })
+            })
+            .collect();
+
+        json!({
+            "kind": self.kind(),
+            "code": self.code(),
+            "exit_code": self.exit_code(),
+            "message": self.to_string(),
+            "context": self.context(),
+            "causes": causes,
+            "diagnostics": diagnostics,
+        })
+    }
+
+    /// The fields of the error other than its message
+    fn context(&self) -> Map<String, Value> {
+        let context = match self {
+            Error::CueParse { path, .. } => json!({ "path": path.display().to_string() }),
+            Error::Environment { variable, .. } => json!({ "variable": variable }),
+            Error::SecretResolution { reference, .. } => json!({ "reference": reference }),
+            Error::CommandExecution {
+                command,
+                args,
+                exit_code,
+                ..
+            } => json!({ "command": command, "args": args, "exit_code": exit_code }),
+            Error::TaskFailed { task, exit_code } => {
+                json!({ "task": task, "exit_code": exit_code })
+            }
+            Error::ShellExpansion { value, .. } => json!({ "value": value }),
+            Error::FileSystem {
+                path, operation, ..
+            } => json!({ "path": path.display().to_string(), "operation": operation }),
+            Error::Ffi { operation, .. } | Error::PermissionDenied { operation, .. } => {
+                json!({ "operation": operation })
+            }
+            Error::Unsupported { feature, .. } => json!({ "feature": feature }),
+            Error::Network { endpoint, .. } => json!({ "endpoint": endpoint }),
+            Error::Timeout {
+                operation,
+                duration,
+            } => json!({
+                "operation": operation,
+                "duration_ms": duration.as_millis() as u64,
+            }),
+            Error::Configuration { .. }
+            | Error::Usage { .. }
+            | Error::Cache { .. }
+            | Error::Json { .. }
+            | Error::Security { .. } => json!({}),
+        };
+        match context {
+            Value::Object(context) => context,
+            _ => Map::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::exit_code;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(Error::usage("no such task").exit_code(), exit_code::USAGE);
+        assert_eq!(
+            Error::configuration("invalid").exit_code(),
+            exit_code::CONFIGURATION
+        );
+        assert_eq!(Error::cache("corrupt").exit_code(), exit_code::CACHE);
+        assert_eq!(Error::task_failed("build", 3).exit_code(), 3);
+        assert_eq!(
+            Error::command_execution("hook", vec![], "killed", None).exit_code(),
+            exit_code::FAILURE
+        );
+        assert_eq!(
+            Error::file_system("/tmp", "read", std::io::ErrorKind::NotFound.into()).exit_code(),
+            exit_code::INTERNAL
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = Error::task_failed("build", 2).to_json();
+        assert_eq!(
+            json,
+            json!({
+                "kind": "command",
+                "code": "task_failed",
+                "exit_code": 2,
+                "message": "task 'build' failed with exit code 2",
+                "context": { "task": "build", "exit_code": 2 },
+                "causes": [],
+                "diagnostics": [],
+            })
+        );
+
+        let inner = Error::configuration("missing field");
+        let outer = Error::cue_parse_with_source("/project/env.cue", "Failed to evaluate", inner);
+        let json = outer.to_json();
+        assert_eq!(json["kind"], "configuration");
+        assert_eq!(json["exit_code"], exit_code::CONFIGURATION);
+        assert_eq!(json["context"]["path"], "/project/env.cue");
+        assert_eq!(
+            json["causes"],
+            json!(["configuration error: missing field"])
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Core error type definitions
 
+use super::diagnostic::Diagnostic;
 use std::path::PathBuf;
 
 /// Result type alias for cuenv operations
@@ -12,6 +13,8 @@ pub enum Error {
     CueParse {
         path: PathBuf,
         message: String,
+        /// Positions in the CUE files the error refers to
+        diagnostics: Vec<Diagnostic>,
         #[source]
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
@@ -38,6 +41,15 @@ pub enum Error {
     /// Configuration errors
     Configuration { message: String },
 
+    /// A command was used wrongly, such as with a task that does not exist
+    Usage { message: String },
+
+    /// Cache errors
+    Cache { message: String },
+
+    /// A task exited with a non-zero status
+    TaskFailed { task: String, exit_code: i32 },
+
     /// Shell expansion errors
     ShellExpansion { value: String, message: String },
 
@@ -3,8 +3,8 @@
 use crate::events::{
     CacheEvent, EnhancedEvent, EventSubscriber, PipelineEvent, SystemEvent, TaskEvent,
 };
+use crate::style::{self, Painter, Role};
 use async_trait::async_trait;
-use std::io::{self, IsTerminal};
 use tracing::debug;
 
 /// Console subscriber for terminal output
@@ -40,7 +40,7 @@ impl ConsoleSubscriber {
     /// Create a new console subscriber with default settings
     pub fn new() -> Self {
         Self {
-            use_colors: io::stderr().is_terminal(),
+            use_colors: style::stderr().enabled(),
             verbosity: ConsoleVerbosity::Normal,
             writer: ConsoleWriter::Stderr,
         }
@@ -97,7 +97,7 @@ impl ConsoleSubscriber {
                     self.verbosity,
                     ConsoleVerbosity::Verbose | ConsoleVerbosity::Debug
                 ) {
-                    Some(self.colorize(&format!("▶ Starting task '{task_name}'"), "blue"))
+                    Some(self.colorize(&format!("▶ Starting task '{task_name}'"), Role::Task))
                 } else {
                     None
                 }
@@ -108,11 +108,14 @@ impl ConsoleSubscriber {
                 ..
             } => Some(self.colorize(
                 &format!("✅ Task '{task_name}' completed in {duration_ms}ms"),
-                "green",
+                Role::Success,
             )),
             TaskEvent::TaskFailed {
                 task_name, error, ..
-            } => Some(self.colorize(&format!("❌ Task '{task_name}' failed: {error}"), "red")),
+            } => Some(self.colorize(
+                &format!("❌ Task '{task_name}' failed: {error}"),
+                Role::Failure,
+            )),
             TaskEvent::TaskProgress {
                 task_name, message, ..
             } => {
@@ -120,7 +123,7 @@ impl ConsoleSubscriber {
                     self.verbosity,
                     ConsoleVerbosity::Verbose | ConsoleVerbosity::Debug
                 ) {
-                    Some(self.colorize(&format!("⏳ {task_name}: {message}"), "yellow"))
+                    Some(self.colorize(&format!("⏳ {task_name}: {message}"), Role::Warning))
                 } else {
                     None
                 }
@@ -132,7 +135,10 @@ impl ConsoleSubscriber {
                     self.verbosity,
                     ConsoleVerbosity::Verbose | ConsoleVerbosity::Debug
                 ) {
-                    Some(self.colorize(&format!("⏭ Task '{task_name}' skipped: {reason}"), "cyan"))
+                    Some(self.colorize(
+                        &format!("⏭ Task '{task_name}' skipped: {reason}"),
+                        Role::Accent,
+                    ))
                 } else {
                     None
                 }
@@ -148,7 +154,16 @@ impl ConsoleSubscriber {
             }
             TaskEvent::TaskError {
                 task_name, error, ..
-            } => Some(self.colorize(&format!("🚨 {task_name}: {error}"), "red")),
+            } => Some(self.colorize(&format!("🚨 {task_name}: {error}"), Role::Failure)),
+            TaskEvent::TaskOutputTruncated {
+                task_name,
+                stream,
+                dropped_bytes,
+                ..
+            } => Some(self.colorize(
+                &format!("✂ Task '{task_name}' left {dropped_bytes} bytes of its {stream} out"),
+                Role::Warning,
+            )),
         }
     }
 
@@ -161,7 +176,7 @@ impl ConsoleSubscriber {
                 &format!(
                     "🚀 Starting pipeline: {total_tasks} tasks across {total_levels} levels"
                 ),
-                "blue",
+                Role::Heading,
             )),
             PipelineEvent::LevelStarted {
                 level,
@@ -173,7 +188,7 @@ impl ConsoleSubscriber {
                 ) {
                     Some(self.colorize(
                         &format!("📊 Level {level}: {tasks_in_level} tasks"),
-                        "cyan",
+                        Role::Accent,
                     ))
                 } else {
                     None
@@ -189,7 +204,7 @@ impl ConsoleSubscriber {
                         &format!(
                             "📊 Level {level} completed: {successful_tasks} successful, {failed_tasks} failed"
                         ),
-                        "yellow",
+                        Role::Warning,
                     ))
                 } else if matches!(
                     self.verbosity,
@@ -199,7 +214,7 @@ impl ConsoleSubscriber {
                         &format!(
                             "📊 Level {level} completed: {successful_tasks} tasks successful"
                         ),
-                        "green",
+                        Role::Success,
                     ))
                 } else {
                     None
@@ -213,7 +228,11 @@ impl ConsoleSubscriber {
                 &format!(
                     "🏁 Pipeline completed in {total_duration_ms}ms: {successful_tasks} successful, {failed_tasks} failed"
                 ),
-                if *failed_tasks > 0 { "red" } else { "green" },
+                if *failed_tasks > 0 {
+                    Role::Failure
+                } else {
+                    Role::Success
+                },
             )),
         }
     }
@@ -228,17 +247,17 @@ impl ConsoleSubscriber {
 
         match event {
             CacheEvent::CacheHit { key } => {
-                Some(self.colorize(&format!("💾 Cache hit: {key}"), "green"))
+                Some(self.colorize(&format!("💾 Cache hit: {key}"), Role::Success))
             }
             CacheEvent::CacheMiss { key } => {
-                Some(self.colorize(&format!("💿 Cache miss: {key}"), "yellow"))
+                Some(self.colorize(&format!("💿 Cache miss: {key}"), Role::Warning))
             }
             CacheEvent::CacheWrite { key, size_bytes } => Some(self.colorize(
                 &format!("💾 Cache write: {key} ({size_bytes} bytes)"),
-                "cyan",
+                Role::Accent,
             )),
             CacheEvent::CacheEvict { key, reason } => {
-                Some(self.colorize(&format!("🗑 Cache evict: {key} ({reason})"), "red"))
+                Some(self.colorize(&format!("🗑 Cache evict: {key} ({reason})"), Role::Failure))
             }
         }
     }
@@ -292,23 +311,8 @@ impl ConsoleSubscriber {
     }
 
     /// Apply color to text if colors are enabled
-    fn colorize(&self, text: &str, color: &str) -> String {
-        if !self.use_colors {
-            return text.to_string();
-        }
-
-        let color_code = match color {
-            "red" => "\x1b[31m",
-            "green" => "\x1b[32m",
-            "yellow" => "\x1b[33m",
-            "blue" => "\x1b[34m",
-            "magenta" => "\x1b[35m",
-            "cyan" => "\x1b[36m",
-            "white" => "\x1b[37m",
-            _ => "\x1b[0m",
-        };
-
-        format!("{color_code}{text}\x1b[0m")
+    fn colorize(&self, text: &str, role: Role) -> String {
+        Painter::new(self.use_colors, style::theme()).paint(role, text)
     }
 
     /// Write output to the configured destination
@@ -448,11 +452,11 @@ mod tests {
 
         let text = "test text";
 
-        let colored = color_subscriber.colorize(text, "red");
+        let colored = color_subscriber.colorize(text, Role::Failure);
         assert!(colored.contains("\x1b[31m"));
         assert!(colored.contains("\x1b[0m"));
 
-        let uncolored = no_color_subscriber.colorize(text, "red");
+        let uncolored = no_color_subscriber.colorize(text, Role::Failure);
         assert_eq!(uncolored, text);
     }
 }
@@ -31,5 +31,9 @@ pub async fn format_event(
         }
     }
 
-    serde_json::to_string(&json_obj).map_err(|e| JsonLogError::SerializationError(e.to_string()))
+    let line = serde_json::to_string(&json_obj)
+        .map_err(|e| JsonLogError::SerializationError(e.to_string()))?;
+
+    // Events can carry task output and error messages that quote secrets
+    Ok(crate::masking::mask(&line).into_owned())
 }
@@ -17,7 +17,7 @@ impl JsonLogSubscriber {
         let cached_size = self.cached_file_size.load(Ordering::Relaxed);
 
         let should_check_size =
-            write_count % self.config.size_check_interval == 0 || cached_size > max_size;
+            write_count.is_multiple_of(self.config.size_check_interval) || cached_size > max_size;
 
         if should_check_size {
             // Check actual file size
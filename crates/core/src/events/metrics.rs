@@ -165,7 +165,7 @@ impl MetricsSubscriber {
                 }
             })
             .collect();
-        failed_tasks.sort_by(|a, b| b.1.cmp(&a.1));
+        failed_tasks.sort_by_key(|b| std::cmp::Reverse(b.1));
         failed_tasks.truncate(10); // Top 10
 
         MetricsSummary {
@@ -37,6 +37,15 @@ pub enum TaskEvent {
         task_id: String,
         error: String,
     },
+    /// A task printed more than its `maxOutputBytes` to a stream, and the
+    /// middle of it was left out
+    TaskOutputTruncated {
+        task_name: String,
+        task_id: String,
+        /// `stdout` or `stderr`
+        stream: String,
+        dropped_bytes: u64,
+    },
     /// Task skipped due to cache or conditions
     TaskSkipped {
         task_name: String,
@@ -13,6 +13,8 @@
 //!   the type level.
 //! - **`constants`**: A collection of shared, static constants such as environment
 //!   variable names and file paths.
+//! - **`masking`**: Redaction of secret values in output, logs and errors.
+//! - **`style`**: Colors and themes of terminal output.
 
 // The `mod` statements declare the sub-modules within the `core` module.
 // The `pub` keyword makes them accessible from other parts of the crate that
@@ -20,6 +22,8 @@
 pub mod constants;
 pub mod errors;
 pub mod events;
+pub mod masking;
+pub mod style;
 pub mod types;
 
 // The `pub use` statements re-export the most important items from the sub-modules
@@ -28,7 +32,7 @@ pub mod types;
 // for the core domain.
 pub use self::{
     constants::*,
-    errors::{Error, Result, ResultExt},
+    errors::{Diagnostic, Error, ErrorKind, Result, ResultExt},
     events::{
         emit_global_event, emit_global_event_with_metadata, global_event_bus, global_event_emitter,
         initialize_global_events, publish_global_event, register_global_subscriber, CacheEvent,
@@ -0,0 +1,215 @@
+//! Terminal styling of cuenv's output
+//!
+//! Output is styled by the role of the text, such as a failure or a task
+//! name, and the theme maps each role to the escape codes that draw it.
+//! Whether a stream is colored is decided once per process, like the rest of
+//! the user's configuration: `color = "always"` or `"never"` in config.toml
+//! wins, then `NO_COLOR` turns colors off and `CLICOLOR_FORCE` turns them on
+//! even when the output is not a terminal. Otherwise terminals are colored.
+//!
+//! ```
+//! use cuenv_core::style::{Painter, Role, Theme};
+//!
+//! let painter = Painter::new(true, Theme::Default);
+//! assert_eq!(painter.paint(Role::Failure, "failed"), "\x1b[31mfailed\x1b[0m");
+//! assert_eq!(Painter::plain().paint(Role::Failure, "failed"), "failed");
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// What a piece of styled text is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Headers and names that stand out
+    Heading,
+    /// Hints, durations and other secondary text
+    Muted,
+    /// Tags, commands and other highlights
+    Accent,
+    Success,
+    Warning,
+    Failure,
+    /// The name of a task in front of what is said about it
+    Task,
+}
+
+/// The colors roles are drawn in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// The standard colors of the terminal
+    #[default]
+    Default,
+    /// High-intensity colors, for dark backgrounds
+    Bright,
+    /// No colors, only bold, dim and underlined text
+    Mono,
+}
+
+impl Theme {
+    /// The SGR parameters drawing `role`
+    fn codes(self, role: Role) -> &'static str {
+        match (self, role) {
+            (Self::Default, Role::Heading) => "1",
+            (Self::Default, Role::Muted) => "90",
+            (Self::Default, Role::Accent) => "36",
+            (Self::Default, Role::Success) => "32",
+            (Self::Default, Role::Warning) => "33",
+            (Self::Default, Role::Failure) => "31",
+            (Self::Default, Role::Task) => "35",
+            (Self::Bright, Role::Heading) => "1;97",
+            (Self::Bright, Role::Muted) => "37",
+            (Self::Bright, Role::Accent) => "96",
+            (Self::Bright, Role::Success) => "92",
+            (Self::Bright, Role::Warning) => "93",
+            (Self::Bright, Role::Failure) => "91",
+            (Self::Bright, Role::Task) => "95",
+            (Self::Mono, Role::Muted) => "2",
+            (Self::Mono, Role::Accent) => "4",
+            (Self::Mono, Role::Failure) => "1;4",
+            (Self::Mono, Role::Heading | Role::Success | Role::Warning | Role::Task) => "1",
+        }
+    }
+}
+
+impl std::str::FromStr for Theme {
+    type Err = String;
+
+    fn from_str(theme: &str) -> Result<Self, Self::Err> {
+        match theme {
+            "default" => Ok(Self::Default),
+            "bright" => Ok(Self::Bright),
+            "mono" => Ok(Self::Mono),
+            _ => Err(format!(
+                "Unknown theme '{theme}', expected default, bright or mono"
+            )),
+        }
+    }
+}
+
+/// Styles text for one stream, or leaves it plain when colors are off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Painter {
+    enabled: bool,
+    theme: Theme,
+}
+
+impl Painter {
+    pub fn new(enabled: bool, theme: Theme) -> Self {
+        Self { enabled, theme }
+    }
+
+    /// A painter leaving all text plain
+    pub fn plain() -> Self {
+        Self::new(false, Theme::Default)
+    }
+
+    /// Whether text is styled
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// `text` drawn as `role`
+    pub fn paint(&self, role: Role, text: impl Display) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{text}\x1b[0m", self.theme.codes(role))
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// `text` drawn as `role`, in bold
+    pub fn bold(&self, role: Role, text: impl Display) -> String {
+        if self.enabled {
+            format!("\x1b[1;{}m{text}\x1b[0m", self.theme.codes(role))
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// The color choice and theme of the user, set once at startup
+struct Settings {
+    color: Option<bool>,
+    theme: Theme,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Select how output is styled: `color` forces colors on or off, or leaves
+/// them to the environment and the terminal when `None`
+pub fn init(color: Option<bool>, theme: Theme) {
+    let _ = SETTINGS.set(Settings { color, theme });
+}
+
+/// Whether to color output going to a terminal when `is_terminal`
+pub fn color_enabled(color: Option<bool>, is_terminal: bool) -> bool {
+    if let Some(color) = color {
+        return color;
+    }
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    if std::env::var_os("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0") {
+        return true;
+    }
+    is_terminal && std::env::var_os("TERM").is_none_or(|term| term != "dumb")
+}
+
+/// The theme selected with [`init`]
+pub fn theme() -> Theme {
+    SETTINGS
+        .get()
+        .map_or(Theme::Default, |settings| settings.theme)
+}
+
+/// The painter for `is_terminal` with the settings of [`init`]
+fn painter(is_terminal: bool) -> Painter {
+    let color = SETTINGS.get().and_then(|settings| settings.color);
+    Painter::new(color_enabled(color, is_terminal), theme())
+}
+
+/// The painter for text written to stdout
+pub fn stdout() -> Painter {
+    painter(std::io::stdout().is_terminal())
+}
+
+/// The painter for text written to stderr
+pub fn stderr() -> Painter {
+    painter(std::io::stderr().is_terminal())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        let painter = Painter::new(true, Theme::Bright);
+        assert_eq!(painter.paint(Role::Success, "ok"), "\x1b[92mok\x1b[0m");
+        assert_eq!(painter.bold(Role::Accent, "ci"), "\x1b[1;96mci\x1b[0m");
+        assert_eq!(
+            Painter::new(true, Theme::Mono).paint(Role::Muted, "0.2s"),
+            "\x1b[2m0.2s\x1b[0m"
+        );
+        assert_eq!(
+            Painter::new(false, Theme::Bright).bold(Role::Task, "build"),
+            "build"
+        );
+    }
+
+    #[test]
+    fn test_color_enabled() {
+        assert!(color_enabled(Some(true), false));
+        assert!(!color_enabled(Some(false), true));
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        assert_eq!("mono".parse(), Ok(Theme::Mono));
+        assert!("solarized".parse::<Theme>().is_err());
+    }
+}
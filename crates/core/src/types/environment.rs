@@ -79,7 +79,7 @@ impl EnvironmentVariables {
 
     /// Get an iterator over the variables
     #[must_use]
-    pub fn iter(&self) -> std::collections::hash_map::Iter<String, String> {
+    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, String, String> {
         self.0.iter()
     }
 
@@ -1,12 +1,26 @@
 //! Task-related types for execution pipeline management
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
 
 /// Default task timeout in seconds (1 hour)
 pub const DEFAULT_TASK_TIMEOUT_SECS: u64 = 3600;
 
+/// The arguments a shell runs a task's command or script with when its
+/// profile gives none
+///
+/// POSIX shells stop at the first failing command, so that a failure in
+/// the middle of a script does not pass unnoticed.
+pub fn default_shell_args(shell: &str) -> Vec<String> {
+    let args: &[&str] = match shell {
+        "sh" | "bash" | "zsh" => &["-e", "-c"],
+        _ => &["-c"],
+    };
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
 /// Task execution mode - either command or script
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TaskExecutionMode {
@@ -16,6 +30,22 @@ pub enum TaskExecutionMode {
     Script { content: String },
 }
 
+/// How a task depends on another
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencyKind {
+    /// Planned with the task and run before it, which builds on its results:
+    /// the task is only taken from the cache along with it
+    #[default]
+    Required,
+    /// Planned with the task and run before it, without the task building
+    /// on its results
+    OrderOnly,
+    /// Run before the task when planned for another reason, and otherwise
+    /// left out
+    Weak,
+}
+
 /// Dependency reference with package information (for future cross-package support)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResolvedDependency {
@@ -25,6 +55,8 @@ pub struct ResolvedDependency {
     pub package: Option<String>,
     /// Full qualified name (package:task or just task)
     pub qualified_name: String,
+    #[serde(default)]
+    pub kind: DependencyKind,
 }
 
 impl ResolvedDependency {
@@ -34,6 +66,7 @@ impl ResolvedDependency {
             qualified_name: name.clone(),
             name,
             package: None,
+            kind: DependencyKind::Required,
         }
     }
 
@@ -44,12 +77,19 @@ impl ResolvedDependency {
             name,
             package: Some(package),
             qualified_name,
+            kind: DependencyKind::Required,
         }
     }
+
+    /// The same dependency of another kind
+    pub fn with_kind(mut self, kind: DependencyKind) -> Self {
+        self.kind = kind;
+        self
+    }
 }
 
 /// Validated security configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TaskSecurity {
     /// Restrict disk access
     pub restrict_disk: bool,
@@ -61,6 +101,18 @@ pub struct TaskSecurity {
     pub write_only_paths: Vec<PathBuf>,
     /// Allowed network hosts (for fine-grained control)
     pub allowed_hosts: Vec<String>,
+    /// Seccomp profile: a builtin name or the absolute path of a JSON profile
+    #[serde(default)]
+    pub seccomp: Option<String>,
+    /// Fail the task when its output contains a secret
+    #[serde(default)]
+    pub fail_on_secret_leak: bool,
+    /// User to run the task as, by name or numeric ID
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Run the task without Linux capabilities
+    #[serde(default)]
+    pub drop_capabilities: bool,
 }
 
 /// Resolved cache configuration
@@ -101,16 +153,25 @@ pub struct TaskDefinition {
     pub working_directory: PathBuf,
     /// Shell to use for execution
     pub shell: String,
+    /// Arguments of the shell, the last taking the command or script
+    #[serde(default)]
+    pub shell_args: Vec<String>,
     /// Input files/patterns
     pub inputs: Vec<String>,
     /// Output files/patterns  
     pub outputs: Vec<String>,
+    /// Named output artifacts, resolved to absolute paths
+    #[serde(default)]
+    pub artifacts: HashMap<String, PathBuf>,
     /// Security configuration
     pub security: Option<TaskSecurity>,
     /// Cache configuration
     pub cache: TaskCache,
     /// Timeout for execution
     pub timeout: Duration,
+    /// Bytes of each output stream kept, as its head and tail
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
 }
 
 impl TaskDefinition {
@@ -127,11 +188,14 @@ impl TaskDefinition {
             dependencies: Vec::new(),
             working_directory,
             shell: "sh".to_string(),
+            shell_args: default_shell_args("sh"),
             inputs: Vec::new(),
             outputs: Vec::new(),
+            artifacts: HashMap::new(),
             security: None,
             cache: TaskCache::default(),
             timeout: Duration::from_secs(DEFAULT_TASK_TIMEOUT_SECS),
+            max_output_bytes: None,
         }
     }
 
@@ -153,10 +217,27 @@ impl TaskDefinition {
         matches!(self.execution_mode, TaskExecutionMode::Script { .. })
     }
 
-    /// Get the names of all dependencies
+    /// Get the names of the dependencies whose results the task builds on
     pub fn dependency_names(&self) -> Vec<String> {
+        self.dependency_names_where(|kind| kind == DependencyKind::Required)
+    }
+
+    /// The names of the dependencies planned along with the task, required
+    /// and order-only ones
+    pub fn planned_dependency_names(&self) -> Vec<String> {
+        self.dependency_names_where(|kind| kind != DependencyKind::Weak)
+    }
+
+    /// The names of the weak dependencies, run before the task only when
+    /// they are planned anyway
+    pub fn weak_dependency_names(&self) -> Vec<String> {
+        self.dependency_names_where(|kind| kind == DependencyKind::Weak)
+    }
+
+    fn dependency_names_where(&self, matches: impl Fn(DependencyKind) -> bool) -> Vec<String> {
         self.dependencies
             .iter()
+            .filter(|dep| matches(dep.kind))
             .map(|dep| dep.name.clone())
             .collect()
     }
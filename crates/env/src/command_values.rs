@@ -0,0 +1,230 @@
+//! Variables computed from the output of a command
+//!
+//! A value declared as `GIT_SHA: { fromCommand: "git rev-parse --short HEAD" }`
+//! runs the command in the directory of its env.cue and exports the output
+//! with trailing newlines removed. With a `ttl` or `inputs`, the output is
+//! cached on disk so slow commands such as `nix eval` do not run on every
+//! directory change.
+
+use crate::value_cache;
+use cuenv_config::CommandValue;
+use cuenv_core::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize)]
+struct CachedOutput {
+    /// Seconds since the Unix epoch when the command ran
+    created_at: u64,
+    inputs_hash: String,
+    output: String,
+}
+
+/// On-disk cache of command outputs
+pub struct CommandCache {
+    dir: PathBuf,
+}
+
+impl CommandCache {
+    /// Create a cache storing its entries in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cache in the user's cache directory
+    pub fn user_cache() -> Self {
+        Self::new(value_cache::user_cache_dir("commands"))
+    }
+
+    /// Produce the value of `name`, running its command unless a fresh output is cached
+    pub fn evaluate(&self, name: &str, value: &CommandValue) -> Result<String> {
+        let dir = value.dir.as_deref().unwrap_or(Path::new("."));
+        let ttl = value.ttl_duration()?;
+
+        // Without a ttl or inputs there is nothing to decide freshness with
+        if ttl.is_none() && value.inputs.is_empty() {
+            return run(name, value, dir);
+        }
+
+        let inputs_hash = hash_inputs(dir, &value.inputs);
+        let entry = self.entry_path(dir, &value.from_command);
+        let now = now_secs();
+
+        if let Some(cached) = value_cache::read_entry::<CachedOutput>(&entry) {
+            let within_ttl =
+                ttl.is_none_or(|ttl| now.saturating_sub(cached.created_at) < ttl.as_secs());
+            if within_ttl && cached.inputs_hash == inputs_hash {
+                tracing::debug!("Using cached output of fromCommand for {name}");
+                return Ok(cached.output);
+            }
+        }
+
+        let output = run(name, value, dir)?;
+        let cached = CachedOutput {
+            created_at: now,
+            inputs_hash,
+            output,
+        };
+        if let Err(e) = value_cache::write_entry(&entry, &cached) {
+            tracing::warn!("Failed to cache output of fromCommand for {name}: {e}");
+        }
+
+        Ok(cached.output)
+    }
+
+    fn entry_path(&self, dir: &Path, command: &str) -> PathBuf {
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut hasher = Sha256::new();
+        hasher.update(canonical.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(command.as_bytes());
+        self.dir.join(format!("{:x}.json", hasher.finalize()))
+    }
+}
+
+fn run(name: &str, value: &CommandValue, dir: &Path) -> Result<String> {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(&value.from_command);
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&value.from_command);
+        command
+    };
+
+    let output = command.current_dir(dir).output().map_err(|e| {
+        Error::environment(
+            name,
+            format!("failed to run fromCommand {:?}: {e}", value.from_command),
+        )
+    })?;
+
+    if !output.status.success() {
+        return Err(Error::environment(
+            name,
+            format!(
+                "fromCommand {:?} failed with {}: {}",
+                value.from_command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\n', '\r'])
+        .to_string())
+}
+
+fn hash_inputs(dir: &Path, inputs: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for input in inputs {
+        hasher.update(input.as_bytes());
+        match std::fs::read(dir.join(input)) {
+            Ok(content) => {
+                hasher.update([1]);
+                hasher.update(&content);
+            }
+            // A missing input is a state of its own, so creating it invalidates the cache
+            Err(_) => hasher.update([0]),
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A command printing how many times it has run in `dir`
+    fn counting(dir: &Path) -> CommandValue {
+        CommandValue {
+            from_command: "echo run >> runs && wc -l < runs | tr -d ' '".to_string(),
+            ..Default::default()
+        }
+        .declared_in(dir)
+    }
+
+    #[test]
+    fn test_output_is_trimmed() {
+        let project = TempDir::new().unwrap();
+        let cache = CommandCache::new(project.path().join("cache"));
+        let value = CommandValue {
+            from_command: "printf 'abc123\\n\\n'".to_string(),
+            ..Default::default()
+        }
+        .declared_in(project.path());
+
+        assert_eq!(cache.evaluate("GIT_SHA", &value).unwrap(), "abc123");
+    }
+
+    #[test]
+    fn test_runs_every_time_without_ttl_or_inputs() {
+        let project = TempDir::new().unwrap();
+        let cache = CommandCache::new(project.path().join("cache"));
+        let value = counting(project.path());
+
+        assert_eq!(cache.evaluate("COUNT", &value).unwrap(), "1");
+        assert_eq!(cache.evaluate("COUNT", &value).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_ttl_reuses_cached_output() {
+        let project = TempDir::new().unwrap();
+        let cache = CommandCache::new(project.path().join("cache"));
+        let value = CommandValue {
+            ttl: Some("1h".to_string()),
+            ..counting(project.path())
+        };
+
+        assert_eq!(cache.evaluate("COUNT", &value).unwrap(), "1");
+        assert_eq!(cache.evaluate("COUNT", &value).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_changed_input_invalidates_cache() {
+        let project = TempDir::new().unwrap();
+        let cache = CommandCache::new(project.path().join("cache"));
+        std::fs::write(project.path().join("flake.lock"), "v1").unwrap();
+        let value = CommandValue {
+            inputs: vec!["flake.lock".to_string()],
+            ..counting(project.path())
+        };
+
+        assert_eq!(cache.evaluate("COUNT", &value).unwrap(), "1");
+        assert_eq!(cache.evaluate("COUNT", &value).unwrap(), "1");
+
+        std::fs::write(project.path().join("flake.lock"), "v2").unwrap();
+        assert_eq!(cache.evaluate("COUNT", &value).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_failing_command_names_variable() {
+        let project = TempDir::new().unwrap();
+        let cache = CommandCache::new(project.path().join("cache"));
+        let value = CommandValue {
+            from_command: "echo broken >&2; exit 3".to_string(),
+            ..Default::default()
+        }
+        .declared_in(project.path());
+
+        let message = cache.evaluate("BROKEN", &value).unwrap_err().to_string();
+        assert!(message.contains("BROKEN"), "{message}");
+        assert!(message.contains("broken"), "{message}");
+    }
+}
@@ -0,0 +1,147 @@
+//! Blocking client used by the shell hook and `cuenv task`
+
+use super::protocol::{Request, Response, TaskRun};
+use cuenv_config::{signature, HierarchicalParseResult, ParseOptions};
+use cuenv_core::{Error, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+/// Long enough for a cold evaluation of a large hierarchy
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Send one request to the daemon listening on `socket`
+pub fn request(socket: &Path, request: &Request) -> Result<Response> {
+    let mut responses = send(socket, request, Some(READ_TIMEOUT))?;
+    read_response(&mut responses)
+}
+
+/// Ask the daemon listening on `socket` to run a task, passing each line it
+/// prints to `on_output` with whether it went to stderr, and return the
+/// task it ran with its exit code
+///
+/// `None` when the daemon did not start the task, which is then left to
+/// run in process, like evaluations the daemon fails.
+pub fn run_task(
+    socket: &Path,
+    run: TaskRun,
+    mut on_output: impl FnMut(&str, bool),
+) -> Option<Result<(String, i32)>> {
+    if !socket.exists() {
+        return None;
+    }
+    // Tasks may print nothing for longer than any timeout
+    let mut responses = send(socket, &Request::RunTask(run), None)
+        .map_err(|e| tracing::debug!("cuenv daemon unavailable: {e}"))
+        .ok()?;
+    let task = match read_response(&mut responses) {
+        Ok(Response::Started { task }) => task,
+        Ok(Response::Error { message }) => {
+            tracing::debug!("cuenv daemon did not run the task: {message}");
+            return None;
+        }
+        _ => return None,
+    };
+
+    loop {
+        match read_response(&mut responses) {
+            Ok(Response::Output { line, stderr }) => on_output(&line, stderr),
+            Ok(Response::Finished { exit_code }) => return Some(Ok((task, exit_code))),
+            Ok(Response::Error { message }) => return Some(Err(Error::configuration(message))),
+            Ok(other) => {
+                return Some(Err(Error::configuration(format!(
+                    "Unexpected daemon response: {other:?}"
+                ))))
+            }
+            Err(e) => return Some(Err(e)),
+        }
+    }
+}
+
+/// Connect to `socket` and send `request`, returning the responses to read
+fn send(
+    socket: &Path,
+    request: &Request,
+    timeout: Option<Duration>,
+) -> Result<BufReader<UnixStream>> {
+    let mut stream = UnixStream::connect(socket).map_err(io_error)?;
+    stream.set_read_timeout(timeout).map_err(io_error)?;
+
+    let mut line = serde_json::to_string(request).map_err(|e| Error::Json {
+        message: "failed to serialize daemon request".to_string(),
+        source: e,
+    })?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).map_err(io_error)?;
+    Ok(BufReader::new(stream))
+}
+
+fn read_response(responses: &mut BufReader<UnixStream>) -> Result<Response> {
+    let mut response = String::new();
+    if responses.read_line(&mut response).map_err(io_error)? == 0 {
+        return Err(Error::configuration("cuenv daemon closed the connection"));
+    }
+    serde_json::from_str(&response).map_err(|e| Error::Json {
+        message: "invalid daemon response".to_string(),
+        source: e,
+    })
+}
+
+/// Check the signatures of every layer of `result` when this process
+/// requires them
+fn verify_signatures(dir: &Path, package: &str, result: &HierarchicalParseResult) -> Result<()> {
+    for layer in result
+        .files
+        .iter()
+        .filter_map(|file| file.parent())
+        .chain([dir])
+    {
+        signature::verify_if_required(layer, package)?;
+    }
+    Ok(())
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::configuration(format!("cuenv daemon: {e}"))
+}
+
+/// The daemon's evaluation, or `None` to evaluate in process
+///
+/// Evaluation errors are not taken from the daemon, so that evaluating
+/// again in process reports them exactly as without a daemon.
+pub(super) fn evaluate(
+    socket: &Path,
+    dir: &Path,
+    package: &str,
+    options: &ParseOptions,
+) -> Option<HierarchicalParseResult> {
+    if !socket.exists() {
+        return None;
+    }
+
+    let evaluate = Request::Evaluate {
+        dir: dir.to_path_buf(),
+        package: package.to_string(),
+        environment: options.environment.clone(),
+        capabilities: options.capabilities.clone(),
+        tags: options.tags.clone(),
+    };
+    match request(socket, &evaluate) {
+        Ok(Response::Evaluated { result, cached }) => {
+            // The daemon checks signatures as its own environment asks, which
+            // may not require them as this one does
+            if let Err(e) = verify_signatures(dir, package, &result) {
+                tracing::debug!("Evaluating in process, as the daemon's evaluation failed: {e}");
+                return None;
+            }
+            tracing::debug!(dir = %dir.display(), cached, "Evaluated by the cuenv daemon");
+            Some(*result)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            tracing::debug!("cuenv daemon unavailable: {e}");
+            None
+        }
+    }
+}
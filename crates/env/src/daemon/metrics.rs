@@ -0,0 +1,232 @@
+//! Prometheus metrics of the daemon
+//!
+//! Shared build machines keep the daemon running for days, so it counts
+//! what it serves and the tasks cuenv runs next to it: evaluations served
+//! from its cache against those it had to evaluate, how long evaluating an
+//! environment takes, how long tasks take and how many are running. With
+//! `--metrics <addr>` they are served in the Prometheus text format on
+//! `http://<addr>/metrics`.
+
+use cuenv_core::{Error, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Buckets of task durations, in seconds, from quick checks to long builds
+const TASK_DURATION_BUCKETS: &[f64] = &[
+    0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0,
+];
+
+/// What an evaluation request was answered with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvaluationResult {
+    /// Served from the cache
+    Hit,
+    /// Evaluated for the request
+    Miss,
+    /// The evaluation failed
+    Error,
+}
+
+impl EvaluationResult {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Miss => "miss",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Why an environment was evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvaluationTrigger {
+    /// A client asked for it
+    Request,
+    /// One of its files changed
+    Watch,
+}
+
+impl EvaluationTrigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Watch => "watch",
+        }
+    }
+}
+
+/// The metrics of a running daemon
+pub struct DaemonMetrics {
+    registry: Registry,
+    evaluations: IntCounterVec,
+    env_load_seconds: HistogramVec,
+    cached_environments: IntGauge,
+    task_duration_seconds: HistogramVec,
+    active_tasks: IntGauge,
+}
+
+impl DaemonMetrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        let metric_error =
+            |e: prometheus::Error| Error::configuration(format!("Failed to create metric: {e}"));
+
+        let evaluations = IntCounterVec::new(
+            Opts::new(
+                "cuenv_daemon_evaluations_total",
+                "Evaluation requests by whether the cache answered them",
+            ),
+            &["result"],
+        )
+        .map_err(metric_error)?;
+        let env_load_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "cuenv_daemon_env_load_seconds",
+                "Time taken to evaluate an environment",
+            ),
+            &["trigger"],
+        )
+        .map_err(metric_error)?;
+        let cached_environments = IntGauge::new(
+            "cuenv_daemon_cached_environments",
+            "Evaluated environments held warm",
+        )
+        .map_err(metric_error)?;
+        let task_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("cuenv_task_duration_seconds", "Time taken by tasks")
+                .buckets(TASK_DURATION_BUCKETS.to_vec()),
+            &["task", "result"],
+        )
+        .map_err(metric_error)?;
+        let active_tasks =
+            IntGauge::new("cuenv_tasks_active", "Tasks running").map_err(metric_error)?;
+
+        registry
+            .register(Box::new(evaluations.clone()))
+            .and_then(|()| registry.register(Box::new(env_load_seconds.clone())))
+            .and_then(|()| registry.register(Box::new(cached_environments.clone())))
+            .and_then(|()| registry.register(Box::new(task_duration_seconds.clone())))
+            .and_then(|()| registry.register(Box::new(active_tasks.clone())))
+            .map_err(metric_error)?;
+
+        Ok(Self {
+            registry,
+            evaluations,
+            env_load_seconds,
+            cached_environments,
+            task_duration_seconds,
+            active_tasks,
+        })
+    }
+
+    pub fn record_evaluation(&self, result: EvaluationResult) {
+        self.evaluations.with_label_values(&[result.as_str()]).inc();
+    }
+
+    pub fn record_env_load(&self, trigger: EvaluationTrigger, duration: Duration) {
+        self.env_load_seconds
+            .with_label_values(&[trigger.as_str()])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn set_cached_environments(&self, count: usize) {
+        self.cached_environments.set(count as i64);
+    }
+
+    pub fn task_started(&self) {
+        self.active_tasks.inc();
+    }
+
+    pub fn task_finished(&self, task: &str, duration: Duration, success: bool) {
+        self.active_tasks.dec();
+        self.task_duration_seconds
+            .with_label_values(&[task, if success { "success" } else { "failure" }])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// The metrics in the Prometheus text format
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::warn!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Serve the metrics on `http://<addr>/metrics` until the daemon stops
+pub async fn serve(metrics: &Arc<DaemonMetrics>, addr: SocketAddr) -> Result<()> {
+    let metrics = Arc::clone(metrics);
+    let make_svc = make_service_fn(move |_: &hyper::server::conn::AddrStream| {
+        let metrics = Arc::clone(&metrics);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let metrics = Arc::clone(&metrics);
+                async move { Ok::<_, Infallible>(respond(&metrics, &request)) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .map_err(|e| Error::configuration(format!("Failed to bind metrics to {addr}: {e}")))?
+        .serve(make_svc);
+    tracing::info!(%addr, "Serving metrics on /metrics");
+    server
+        .await
+        .map_err(|e| Error::configuration(format!("Metrics server failed: {e}")))
+}
+
+fn respond(metrics: &DaemonMetrics, request: &Request<Body>) -> Response<Body> {
+    let (status, content_type, body) = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => (
+            StatusCode::OK,
+            TextEncoder::new().format_type().to_string(),
+            metrics.encode(),
+        ),
+        _ => (
+            StatusCode::NOT_FOUND,
+            "text/plain".to_string(),
+            "Not found\n".to_string(),
+        ),
+    };
+    Response::builder()
+        .status(status)
+        .header("Content-Type", content_type)
+        .body(Body::from(body))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let metrics = DaemonMetrics::new().unwrap();
+        metrics.record_evaluation(EvaluationResult::Hit);
+        metrics.record_evaluation(EvaluationResult::Hit);
+        metrics.record_evaluation(EvaluationResult::Miss);
+        metrics.record_env_load(EvaluationTrigger::Request, Duration::from_millis(40));
+        metrics.set_cached_environments(1);
+        metrics.task_started();
+        metrics.task_started();
+        metrics.task_finished("build", Duration::from_secs(3), true);
+
+        let text = metrics.encode();
+        assert!(text.contains(r#"cuenv_daemon_evaluations_total{result="hit"} 2"#));
+        assert!(text.contains(r#"cuenv_daemon_evaluations_total{result="miss"} 1"#));
+        assert!(text.contains(r#"cuenv_daemon_env_load_seconds_count{trigger="request"} 1"#));
+        assert!(text.contains("cuenv_daemon_cached_environments 1"));
+        assert!(text.contains("cuenv_tasks_active 1"));
+        assert!(text.contains(
+            r#"cuenv_task_duration_seconds_bucket{result="success",task="build",le="5"} 1"#
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+//! Background daemon that keeps evaluated environments warm
+//!
+//! Evaluating the CUE hierarchy is most of the time the shell hook spends
+//! when entering a directory. `cuenv daemon start` runs a server on a unix
+//! socket that caches every evaluation it is asked for and re-evaluates it
+//! in the background when one of its files changes, so the hook only pays
+//! for a socket round trip. Without a running daemon, environments are
+//! evaluated in process as before.
+//!
+//! Started with `--tasks`, the daemon also runs tasks for `cuenv task`, like
+//! a build server: the evaluated environments and the hashes of input files
+//! stay in its memory between runs, so a repeated run skips loading and
+//! hashing what did not change.
+
+use cuenv_config::{eval_hierarchy, HierarchicalParseResult, ParseOptions};
+use cuenv_core::Result;
+use cuenv_utils::xdg::XdgPaths;
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+mod client;
+#[cfg(unix)]
+mod metrics;
+mod protocol;
+#[cfg(unix)]
+mod server;
+#[cfg(unix)]
+mod tasks;
+
+#[cfg(unix)]
+pub use client::{request, run_task};
+pub use protocol::{DaemonStatus, Request, Response, TaskRun};
+#[cfg(unix)]
+pub use server::serve;
+#[cfg(unix)]
+pub use tasks::{TaskOutput, TaskRunner};
+
+/// The socket the daemon listens on
+pub fn socket_path() -> PathBuf {
+    XdgPaths::state_dir().join("daemon.sock")
+}
+
+/// Tell the running daemon, if any, that a task started or finished, for
+/// its metrics
+pub fn report_task(request: &Request) {
+    #[cfg(unix)]
+    {
+        let socket = socket_path();
+        if socket.exists() {
+            if let Err(e) = client::request(&socket, request) {
+                tracing::debug!("cuenv daemon unavailable: {e}");
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = request;
+}
+
+/// Evaluate the hierarchy for `dir`, from the daemon's cache when it runs
+pub fn evaluate(
+    dir: &Path,
+    package: &str,
+    options: &ParseOptions,
+) -> Result<HierarchicalParseResult> {
+    #[cfg(unix)]
+    {
+        if let Some(result) = server::evaluate_in_process(dir, package, options) {
+            return result;
+        }
+        if let Some(result) = client::evaluate(&socket_path(), dir, package, options) {
+            return Ok(result);
+        }
+    }
+
+    eval_hierarchy(dir, package, options)
+}
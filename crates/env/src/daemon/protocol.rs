@@ -0,0 +1,98 @@
+//! Messages exchanged with the daemon, one JSON object per line
+
+use cuenv_config::HierarchicalParseResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Request {
+    /// Evaluate the hierarchy for a directory
+    Evaluate {
+        dir: PathBuf,
+        package: String,
+        environment: Option<String>,
+        capabilities: Vec<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+    /// Run a task, answered with its output and then how it finished
+    RunTask(TaskRun),
+    /// A task started, for the metrics
+    TaskStarted {
+        task: String,
+    },
+    /// A task finished, for the metrics
+    TaskFinished {
+        task: String,
+        duration_ms: u64,
+        success: bool,
+    },
+    Status,
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Response {
+    Evaluated {
+        result: Box<HierarchicalParseResult>,
+        /// Whether the result was served from the cache
+        cached: bool,
+    },
+    /// The task of a [`Request::RunTask`] was found and is about to run;
+    /// an error after this one is the run's own
+    Started {
+        /// The task run, as the arguments name it
+        task: String,
+    },
+    /// A line the task printed
+    Output {
+        line: String,
+        #[serde(default)]
+        stderr: bool,
+    },
+    /// The task of a [`Request::RunTask`] ran
+    Finished {
+        exit_code: i32,
+    },
+    Status(DaemonStatus),
+    Recorded,
+    Stopping,
+    Error {
+        message: String,
+    },
+}
+
+/// What a running daemon reports about itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    /// Number of evaluated environments held warm
+    pub cached: usize,
+    pub uptime_secs: u64,
+    /// Where the metrics are served, if anywhere
+    #[serde(default)]
+    pub metrics_addr: Option<SocketAddr>,
+    /// Whether the daemon runs tasks for `cuenv task`
+    #[serde(default)]
+    pub runs_tasks: bool,
+}
+
+/// A task a client asks the daemon to run, as `cuenv task <task> <args>`
+/// would in `dir`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRun {
+    pub dir: PathBuf,
+    pub task: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// The environment of the client, which the task runs with instead of
+    /// the daemon's
+    pub env: HashMap<String, String>,
+}
@@ -0,0 +1,651 @@
+//! The daemon's socket server and background re-evaluation
+//!
+//! The directories of the files of every cached environment are watched
+//! with notify, so a change wakes the re-evaluation at once. The files are
+//! also checked every [`WATCH_INTERVAL`], for file systems notify cannot
+//! watch.
+
+use super::metrics::{self, DaemonMetrics, EvaluationResult, EvaluationTrigger};
+use super::protocol::{DaemonStatus, Request, Response, TaskRun};
+use super::tasks::{TaskOutput, TaskRunner};
+use crate::manager::environment::watched_files;
+use cuenv_config::{eval_hierarchy, file_names, HierarchicalParseResult, ParseOptions};
+use cuenv_core::{Error, Result};
+use cuenv_utils::FileTimes;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, Notify};
+
+/// How often cached environments are checked for changed files that no
+/// notification came for
+const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to wait after a notification for the rest of a burst of
+/// changes, such as a checkout, before re-evaluating
+const SETTLE_DELAY: Duration = Duration::from_millis(50);
+
+/// The cache of the daemon serving in this process, which the evaluations
+/// of the tasks it runs use directly instead of through the socket
+static LOCAL: Mutex<Option<Arc<Cache>>> = Mutex::new(None);
+
+/// Everything an evaluation depends on besides the files themselves
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EvalKey {
+    dir: PathBuf,
+    package: String,
+    environment: Option<String>,
+    capabilities: Vec<String>,
+    tags: Vec<String>,
+}
+
+impl EvalKey {
+    fn new(dir: &Path, package: &str, options: &ParseOptions) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            package: package.to_string(),
+            environment: options.environment.clone(),
+            capabilities: options.capabilities.clone(),
+            tags: options.tags.clone(),
+        }
+    }
+
+    fn evaluate(&self) -> Result<Entry> {
+        let options = ParseOptions {
+            environment: self.environment.clone(),
+            capabilities: self.capabilities.clone(),
+            tags: self.tags.clone(),
+            ..Default::default()
+        };
+        let result = eval_hierarchy(&self.dir, &self.package, &options)?;
+
+        // Also watch for a configuration file appearing in a directory above,
+        // which would add a layer to the hierarchy
+        let mut watches = FileTimes::new();
+        for file in watched_files(&result.files) {
+            watches.watch(file);
+        }
+        let file_names = file_names();
+        for dir in self.dir.ancestors() {
+            for name in &file_names {
+                watches.watch(dir.join(name));
+            }
+        }
+        // The package.json scripts of the hierarchy are tasks too
+        for dir in result.files.iter().filter_map(|file| file.parent()) {
+            watches.watch(dir.join("package.json"));
+        }
+
+        Ok(Entry {
+            result: Arc::new(result),
+            watches,
+        })
+    }
+}
+
+struct Entry {
+    result: Arc<HierarchicalParseResult>,
+    watches: FileTimes,
+}
+
+/// The directories of cached files, watched without their subdirectories
+struct DirWatcher {
+    watcher: Option<RecommendedWatcher>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl DirWatcher {
+    /// A watcher waking `changed` whenever a watched directory changes
+    fn new(changed: Arc<Notify>) -> Self {
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // Evaluating reads the files, which must not wake it again
+            if event.is_ok_and(|event| !matches!(event.kind, EventKind::Access(_))) {
+                changed.notify_one();
+            }
+        });
+        let watcher = match watcher {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                tracing::warn!(error = %e, "File watching unavailable, polling instead");
+                None
+            }
+        };
+        Self {
+            watcher,
+            dirs: HashSet::new(),
+        }
+    }
+
+    /// Also watch the directories of `watches` that exist
+    fn watch(&mut self, watches: &FileTimes) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        for dir in watches.paths().filter_map(Path::parent) {
+            if self.dirs.contains(dir) || !dir.is_dir() {
+                continue;
+            }
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    self.dirs.insert(dir.to_path_buf());
+                }
+                Err(e) => tracing::debug!(dir = %dir.display(), error = %e, "Failed to watch"),
+            }
+        }
+    }
+}
+
+struct Cache {
+    entries: Mutex<HashMap<EvalKey, Entry>>,
+    metrics: Arc<DaemonMetrics>,
+    watcher: Mutex<DirWatcher>,
+    /// Woken when a watched directory changed
+    changed: Arc<Notify>,
+}
+
+impl Cache {
+    fn new() -> Result<Self> {
+        let changed = Arc::new(Notify::new());
+        Ok(Self {
+            entries: Mutex::default(),
+            metrics: Arc::new(DaemonMetrics::new()?),
+            watcher: Mutex::new(DirWatcher::new(Arc::clone(&changed))),
+            changed,
+        })
+    }
+
+    /// The cached result for `key`, unless one of its files changed
+    fn fresh(&self, key: &EvalKey) -> Option<Arc<HierarchicalParseResult>> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|entry| !entry.watches.has_changed())
+            .map(|entry| Arc::clone(&entry.result))
+    }
+
+    fn stale_keys(&self) -> Vec<EvalKey> {
+        self.entries
+            .lock()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|(_, entry)| entry.watches.has_changed())
+                    .map(|(key, _)| key.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn insert(&self, key: EvalKey, entry: Entry) {
+        if let Ok(mut watcher) = self.watcher.lock() {
+            watcher.watch(&entry.watches);
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, entry);
+            self.metrics.set_cached_environments(entries.len());
+        }
+    }
+
+    fn remove(&self, key: &EvalKey) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+            self.metrics.set_cached_environments(entries.len());
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+
+    /// Evaluate `key` off the async runtime and cache the result
+    async fn evaluate(
+        &self,
+        key: EvalKey,
+        trigger: EvaluationTrigger,
+    ) -> Result<Arc<HierarchicalParseResult>> {
+        let evaluating = key.clone();
+        let started = Instant::now();
+        let entry = tokio::task::spawn_blocking(move || evaluating.evaluate())
+            .await
+            .map_err(|e| Error::configuration(format!("Evaluation task failed: {e}")))?;
+        self.metrics.record_env_load(trigger, started.elapsed());
+        self.store(key, entry)
+    }
+
+    /// Cache the evaluation of `key`, or forget it when evaluating failed
+    fn store(&self, key: EvalKey, entry: Result<Entry>) -> Result<Arc<HierarchicalParseResult>> {
+        match entry {
+            Ok(entry) => {
+                let result = Arc::clone(&entry.result);
+                self.insert(key, entry);
+                Ok(result)
+            }
+            Err(e) => {
+                self.remove(&key);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Evaluate with the cache of the daemon serving in this process, or `None`
+/// in any other process
+pub(super) fn evaluate_in_process(
+    dir: &Path,
+    package: &str,
+    options: &ParseOptions,
+) -> Option<Result<HierarchicalParseResult>> {
+    let cache = LOCAL.lock().ok()?.clone()?;
+    let key = EvalKey::new(dir, package, options);
+    if let Some(result) = cache.fresh(&key) {
+        cache.metrics.record_evaluation(EvaluationResult::Hit);
+        return Some(Ok((*result).clone()));
+    }
+
+    let started = Instant::now();
+    let entry = key.evaluate();
+    cache
+        .metrics
+        .record_env_load(EvaluationTrigger::Request, started.elapsed());
+    let result = cache.store(key, entry);
+    cache.metrics.record_evaluation(match result {
+        Ok(_) => EvaluationResult::Miss,
+        Err(_) => EvaluationResult::Error,
+    });
+    Some(result.map(|result| (*result).clone()))
+}
+
+/// The tasks clients may ask the daemon to run
+struct Tasks {
+    runner: Option<Arc<dyn TaskRunner>>,
+}
+
+/// Serve evaluations on `socket` until a client asks the daemon to stop,
+/// and its metrics on `metrics_addr` if given
+///
+/// With a `runner`, the daemon also runs tasks for `cuenv task`.
+pub async fn serve(
+    socket: &Path,
+    metrics_addr: Option<SocketAddr>,
+    runner: Option<Arc<dyn TaskRunner>>,
+) -> Result<()> {
+    if super::client::request(socket, &Request::Status).is_ok() {
+        return Err(Error::configuration(format!(
+            "A cuenv daemon is already listening on {}",
+            socket.display()
+        )));
+    }
+    if socket.exists() {
+        std::fs::remove_file(socket)
+            .map_err(|e| Error::file_system(socket, "remove stale daemon socket", e))?;
+    }
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| Error::file_system(parent, "create daemon socket directory", e))?;
+    }
+
+    let listener = UnixListener::bind(socket).map_err(|e| {
+        Error::configuration(format!(
+            "Failed to bind to socket {}: {e}",
+            socket.display()
+        ))
+    })?;
+    // Evaluations include secret references, so only this user may connect
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| Error::file_system(socket, "restrict daemon socket", e))?;
+    }
+
+    let cache = Arc::new(Cache::new()?);
+    if let Ok(mut local) = LOCAL.lock() {
+        *local = Some(Arc::clone(&cache));
+    }
+    let tasks = Arc::new(Tasks { runner });
+    let shutdown = Arc::new(Notify::new());
+    let started = Instant::now();
+    tokio::spawn(keep_warm(Arc::clone(&cache)));
+    if let Some(addr) = metrics_addr {
+        let cache = Arc::clone(&cache);
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(&cache.metrics, addr).await {
+                tracing::error!(error = %e, "Metrics endpoint stopped");
+            }
+        });
+    }
+    tracing::info!(socket = %socket.display(), "cuenv daemon started");
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let cache = Arc::clone(&cache);
+                    let tasks = Arc::clone(&tasks);
+                    let shutdown = Arc::clone(&shutdown);
+                    tokio::spawn(async move {
+                        let client = Client {
+                            cache: &cache,
+                            tasks: &tasks,
+                            shutdown: &shutdown,
+                            started,
+                            metrics_addr,
+                        };
+                        if let Err(e) = client.handle(stream).await {
+                            tracing::warn!(error = %e, "Daemon client connection error");
+                        }
+                    });
+                }
+                Err(e) => tracing::error!(error = %e, "Failed to accept connection"),
+            },
+            _ = shutdown.notified() => break,
+        }
+    }
+
+    if let Ok(mut local) = LOCAL.lock() {
+        *local = None;
+    }
+    let _ = std::fs::remove_file(socket);
+    tracing::info!("cuenv daemon stopped");
+    Ok(())
+}
+
+/// Re-evaluate cached environments as soon as their files change
+async fn keep_warm(cache: Arc<Cache>) {
+    loop {
+        tokio::select! {
+            _ = cache.changed.notified() => tokio::time::sleep(SETTLE_DELAY).await,
+            _ = tokio::time::sleep(WATCH_INTERVAL) => {}
+        }
+        for key in cache.stale_keys() {
+            tracing::debug!(dir = %key.dir.display(), "Re-evaluating changed environment");
+            if let Err(e) = cache.evaluate(key, EvaluationTrigger::Watch).await {
+                tracing::debug!(error = %e, "Re-evaluation failed, dropped from the cache");
+            }
+        }
+    }
+}
+
+/// A connection and what its requests are served from
+struct Client<'a> {
+    cache: &'a Cache,
+    tasks: &'a Arc<Tasks>,
+    shutdown: &'a Notify,
+    started: Instant,
+    metrics_addr: Option<SocketAddr>,
+}
+
+impl Client<'_> {
+    async fn handle(&self, stream: UnixStream) -> Result<()> {
+        let (read_half, mut write_half) = stream.into_split();
+        let mut reader = BufReader::new(read_half);
+        let mut line = String::new();
+
+        while reader
+            .read_line(&mut line)
+            .await
+            .map_err(|e| Error::configuration(format!("Failed to read from client: {e}")))?
+            > 0
+        {
+            let request = serde_json::from_str::<Request>(line.trim());
+            let stopping = matches!(request, Ok(Request::Shutdown));
+            let response = match request {
+                Ok(Request::RunTask(run)) => self.run_task(run, &mut write_half).await?,
+                Ok(request) => self.respond(request).await,
+                Err(e) => Response::Error {
+                    message: format!("Invalid request: {e}"),
+                },
+            };
+            write_response(&mut write_half, &response).await?;
+
+            if stopping {
+                self.shutdown.notify_one();
+                break;
+            }
+            line.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Run the task of `run`, writing its output as it prints it, and
+    /// return how it finished
+    async fn run_task(&self, run: TaskRun, write_half: &mut OwnedWriteHalf) -> Result<Response> {
+        let Some(runner) = self.tasks.runner.clone() else {
+            return Ok(Response::Error {
+                message: "This cuenv daemon does not run tasks, start it with --tasks".to_string(),
+            });
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let running = tokio::spawn(async move { runner.run(run, TaskOutput(sender)).await });
+
+        // The channel closes once the run is over
+        while let Some(response) = receiver.recv().await {
+            if let Err(e) = write_response(write_half, &response).await {
+                running.abort();
+                return Err(e);
+            }
+        }
+        Ok(match running.await {
+            Ok(Ok(exit_code)) => Response::Finished { exit_code },
+            Ok(Err(e)) => Response::Error {
+                message: e.to_string(),
+            },
+            Err(e) => Response::Error {
+                message: format!("Task run failed: {e}"),
+            },
+        })
+    }
+
+    async fn respond(&self, request: Request) -> Response {
+        let cache = self.cache;
+        match request {
+            Request::Evaluate {
+                dir,
+                package,
+                environment,
+                capabilities,
+                tags,
+            } => {
+                let key = EvalKey {
+                    dir,
+                    package,
+                    environment,
+                    capabilities,
+                    tags,
+                };
+                if let Some(result) = cache.fresh(&key) {
+                    cache.metrics.record_evaluation(EvaluationResult::Hit);
+                    return Response::Evaluated {
+                        result: Box::new((*result).clone()),
+                        cached: true,
+                    };
+                }
+                match cache.evaluate(key, EvaluationTrigger::Request).await {
+                    Ok(result) => {
+                        cache.metrics.record_evaluation(EvaluationResult::Miss);
+                        Response::Evaluated {
+                            result: Box::new((*result).clone()),
+                            cached: false,
+                        }
+                    }
+                    Err(e) => {
+                        cache.metrics.record_evaluation(EvaluationResult::Error);
+                        Response::Error {
+                            message: e.to_string(),
+                        }
+                    }
+                }
+            }
+            Request::RunTask(_) => Response::Error {
+                message: "Task runs are answered as they run".to_string(),
+            },
+            Request::TaskStarted { .. } => {
+                cache.metrics.task_started();
+                Response::Recorded
+            }
+            Request::TaskFinished {
+                task,
+                duration_ms,
+                success,
+            } => {
+                cache
+                    .metrics
+                    .task_finished(&task, Duration::from_millis(duration_ms), success);
+                Response::Recorded
+            }
+            Request::Status => Response::Status(DaemonStatus {
+                pid: std::process::id(),
+                cached: cache.len(),
+                uptime_secs: self.started.elapsed().as_secs(),
+                metrics_addr: self.metrics_addr,
+                runs_tasks: self.tasks.runner.is_some(),
+            }),
+            Request::Shutdown => Response::Stopping,
+        }
+    }
+}
+
+async fn write_response(write_half: &mut OwnedWriteHalf, response: &Response) -> Result<()> {
+    let mut json = serde_json::to_string(response).map_err(|e| Error::Json {
+        message: "failed to serialize daemon response".to_string(),
+        source: e,
+    })?;
+    json.push('\n');
+    write_half
+        .write_all(json.as_bytes())
+        .await
+        .map_err(|e| Error::configuration(format!("Failed to write response: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::client::{request, run_task};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    /// Prints its arguments and the variable `GREETING` of the client
+    struct Echo;
+
+    impl TaskRunner for Echo {
+        fn run(
+            &self,
+            run: TaskRun,
+            output: TaskOutput,
+        ) -> Pin<Box<dyn Future<Output = Result<i32>> + Send + '_>> {
+            Box::pin(async move {
+                if run.task != "echo" {
+                    return Err(Error::configuration(format!(
+                        "Task '{}' not found",
+                        run.task
+                    )));
+                }
+                output.started("echo");
+                output.line(&run.args.join(" "), false);
+                let greeting = run.env.get("GREETING").cloned().unwrap_or_default();
+                output.line(&greeting, true);
+                Ok(3)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_and_shutdown() {
+        let temp = tempfile::tempdir().unwrap();
+        let socket = temp.path().join("daemon.sock");
+
+        let server = tokio::spawn({
+            let socket = socket.clone();
+            async move { serve(&socket, None, None).await }
+        });
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let status = {
+            let socket = socket.clone();
+            tokio::task::spawn_blocking(move || request(&socket, &Request::Status))
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        match status {
+            Response::Status(status) => {
+                assert_eq!(status.pid, std::process::id());
+                assert_eq!(status.cached, 0);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+
+        let stopping = {
+            let socket = socket.clone();
+            tokio::task::spawn_blocking(move || request(&socket, &Request::Shutdown))
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        assert!(matches!(stopping, Response::Stopping));
+        server.await.unwrap().unwrap();
+        assert!(!socket.exists());
+    }
+
+    #[tokio::test]
+    async fn test_task_runs_with_the_client_environment() {
+        let temp = tempfile::tempdir().unwrap();
+        let socket = temp.path().join("daemon.sock");
+
+        let server = tokio::spawn({
+            let socket = socket.clone();
+            async move { serve(&socket, None, Some(Arc::new(Echo))).await }
+        });
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let env = HashMap::from([("GREETING".to_string(), "hello".to_string())]);
+        let run = |task: &str| TaskRun {
+            dir: std::env::current_dir().unwrap(),
+            task: task.to_string(),
+            args: vec!["a".to_string(), "b".to_string()],
+            environment: None,
+            capabilities: Vec::new(),
+            env: env.clone(),
+        };
+        let (socket_path, echo, missing) = (socket.clone(), run("echo"), run("missing"));
+        let (lines, finished, fell_back) = tokio::task::spawn_blocking(move || {
+            let mut lines = Vec::new();
+            let finished = run_task(&socket_path, echo, |line, stderr| {
+                lines.push((line.to_string(), stderr));
+            });
+            let fell_back = run_task(&socket_path, missing, |_, _| {}).is_none();
+            (lines, finished, fell_back)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(finished.unwrap().unwrap(), ("echo".to_string(), 3));
+        assert_eq!(
+            lines,
+            [("a b".to_string(), false), ("hello".to_string(), true)]
+        );
+        assert!(fell_back);
+        assert!(std::env::var("GREETING").is_err());
+
+        let socket_path = socket.clone();
+        tokio::task::spawn_blocking(move || request(&socket_path, &Request::Shutdown))
+            .await
+            .unwrap()
+            .unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,52 @@
+//! Task runs the daemon serves for `cuenv task`
+//!
+//! Running tasks takes the executor, which is built on top of this crate,
+//! so the command line hands the daemon a [`TaskRunner`]. A run carries the
+//! environment and directory of the client that asked for it, which the
+//! runner evaluates the configuration over and gives the task, leaving those
+//! of the daemon untouched: runs and evaluations for other clients go on
+//! meanwhile.
+
+use super::protocol::{Response, TaskRun};
+use cuenv_core::Result;
+use std::future::Future;
+use std::pin::Pin;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Runs the tasks clients ask the daemon for
+pub trait TaskRunner: Send + Sync {
+    /// Run `run` and return its exit code, sending what it prints to
+    /// `output`
+    ///
+    /// The task runs in the environment and directory of `run`, which must
+    /// not be applied to the daemon's process.
+    ///
+    /// Errors before [`TaskOutput::started`] make the client run the task
+    /// itself, so that they are reported exactly as without a daemon.
+    fn run(
+        &self,
+        run: TaskRun,
+        output: TaskOutput,
+    ) -> Pin<Box<dyn Future<Output = Result<i32>> + Send + '_>>;
+}
+
+/// Where a task run reports to the client that asked for it
+#[derive(Clone)]
+pub struct TaskOutput(pub(super) UnboundedSender<Response>);
+
+impl TaskOutput {
+    /// `task` was found and is about to run
+    pub fn started(&self, task: &str) {
+        let _ = self.0.send(Response::Started {
+            task: task.to_string(),
+        });
+    }
+
+    /// The task printed `line`
+    pub fn line(&self, line: &str, stderr: bool) {
+        let _ = self.0.send(Response::Output {
+            line: line.to_string(),
+            stderr,
+        });
+    }
+}
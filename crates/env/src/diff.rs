@@ -1,3 +1,4 @@
+use crate::path_list;
 use anyhow::Result;
 use cuenv_utils::sync::env::SyncEnv;
 use serde::{Deserialize, Serialize};
@@ -22,6 +23,7 @@ const IGNORED_VARS: &[&str] = &[
     "CUENV_FILE",
     "CUENV_WATCHES",
     "CUENV_DIFF",
+    "CUENV_STATE",
 ];
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -30,12 +32,50 @@ pub struct EnvDiff {
     pub prev: HashMap<String, String>,
     /// Environment variables after the change
     pub next: HashMap<String, String>,
+    /// Path-like variables cuenv extended, mapped to their entry separator
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub lists: HashMap<String, String>,
 }
 
 impl EnvDiff {
     /// Create a new environment diff
     pub fn new(prev: HashMap<String, String>, next: HashMap<String, String>) -> Self {
-        Self { prev, next }
+        Self {
+            prev,
+            next,
+            lists: HashMap::new(),
+        }
+    }
+
+    /// Record path-like variables whose entries were added rather than replaced
+    pub fn with_lists(mut self, lists: HashMap<String, String>) -> Self {
+        self.lists = lists;
+        self
+    }
+
+    /// Keep only the variables this diff touches
+    ///
+    /// The result still records the exact prior value of every variable it
+    /// changed, which is all that is needed to undo it, without carrying a
+    /// copy of the unrelated environment.
+    pub fn compact(self) -> Self {
+        let touched: HashSet<String> = self
+            .added_or_changed()
+            .into_keys()
+            .chain(self.removed())
+            .map(str::to_string)
+            .collect();
+        let keep = |vars: HashMap<String, String>| -> HashMap<String, String> {
+            vars.into_iter()
+                .filter(|(key, _)| touched.contains(key))
+                .collect()
+        };
+
+        Self {
+            prev: keep(self.prev),
+            next: keep(self.next),
+            lists: self.lists,
+        }
     }
 
     /// Create a diff from the current environment to a new environment
@@ -106,9 +146,36 @@ impl EnvDiff {
         Self {
             prev: self.next.clone(),
             next: self.prev.clone(),
+            lists: HashMap::new(),
         }
     }
 
+    /// Undo this diff in the current environment
+    ///
+    /// Like applying the reverse diff, except that path-like variables only
+    /// lose the entries cuenv added, keeping anything added since.
+    pub fn restore(&self) -> Result<()> {
+        let mut reverse = self.reverse();
+
+        for (key, sep) in &self.lists {
+            let (Some(next), Some(current)) = (self.next.get(key), SyncEnv::var(key)?) else {
+                continue;
+            };
+            let added = path_list::added_entries(self.prev.get(key).map(String::as_str), next, sep);
+            let restored = path_list::remove_entries(&current, &added, sep);
+
+            // Diff against the live value so apply() writes the restored list
+            reverse.prev.insert(key.clone(), current);
+            if restored.is_empty() && !self.prev.contains_key(key) {
+                reverse.next.remove(key);
+            } else {
+                reverse.next.insert(key.clone(), restored);
+            }
+        }
+
+        reverse.apply()
+    }
+
     /// Check if this diff is empty (no changes)
     pub fn is_empty(&self) -> bool {
         self.added_or_changed().is_empty() && self.removed().is_empty()
@@ -117,13 +184,61 @@ impl EnvDiff {
     /// Merge another diff into this one
     /// The resulting diff represents going from self.prev to other.next
     pub fn merge(&self, other: &Self) -> Self {
+        let mut lists = self.lists.clone();
+        lists.extend(other.lists.clone());
         Self {
             prev: self.prev.clone(),
             next: other.next.clone(),
+            lists,
         }
     }
 }
 
+/// Counts of variables that differ between two loads of the same environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChangeSummary {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+}
+
+impl ChangeSummary {
+    /// Compare the variables set by a previous load with those set by the current one
+    pub fn between(previous: &EnvDiff, current: &EnvDiff) -> Self {
+        let before = previous.added_or_changed();
+        let after = current.added_or_changed();
+
+        let added = after
+            .keys()
+            .filter(|key| !before.contains_key(*key))
+            .count();
+        let changed = after
+            .iter()
+            .filter(|(key, value)| before.get(*key).is_some_and(|old| old != *value))
+            .count();
+        let removed = before
+            .keys()
+            .filter(|key| !after.contains_key(*key))
+            .count();
+
+        Self {
+            added,
+            changed,
+            removed,
+        }
+    }
+}
+
+impl std::fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} vars, ~{} changed", self.added, self.changed)?;
+        if self.removed > 0 {
+            write!(f, ", -{} removed", self.removed)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,6 +313,49 @@ mod tests {
         assert_eq!(reversed.next, prev);
     }
 
+    #[test]
+    fn test_restore_keeps_entries_added_while_loaded() {
+        let key = "CUENV_TEST_RESTORE_PATH";
+        let prev = HashMap::from([(key.to_string(), "/usr/bin:/bin".to_string())]);
+        let next = HashMap::from([(key.to_string(), "/project/bin:/usr/bin:/bin".to_string())]);
+        let diff = EnvDiff::new(prev, next)
+            .with_lists(HashMap::from([(key.to_string(), ":".to_string())]));
+
+        SyncEnv::set_var(key, "/home/user/.local/bin:/project/bin:/usr/bin:/bin").unwrap();
+        diff.restore().unwrap();
+
+        assert_eq!(
+            SyncEnv::var(key).unwrap().as_deref(),
+            Some("/home/user/.local/bin:/usr/bin:/bin")
+        );
+        SyncEnv::remove_var(key).unwrap();
+    }
+
+    #[test]
+    fn test_compact_keeps_prior_values() {
+        let prev = HashMap::from([
+            ("HOME".to_string(), "/home/user".to_string()),
+            ("LOG_LEVEL".to_string(), "warn".to_string()),
+        ]);
+        let mut next = prev.clone();
+        next.insert("LOG_LEVEL".to_string(), "debug".to_string());
+        next.insert("API_URL".to_string(), "http://localhost".to_string());
+
+        let diff = EnvDiff::new(prev, next).compact();
+
+        assert_eq!(
+            diff.prev,
+            HashMap::from([("LOG_LEVEL".to_string(), "warn".to_string())])
+        );
+        assert_eq!(diff.next.len(), 2);
+        assert!(!diff.next.contains_key("HOME"));
+
+        // Undoing restores the overridden value and drops the added one
+        let reversed = diff.reverse();
+        assert_eq!(reversed.added_or_changed().get("LOG_LEVEL"), Some(&"warn"));
+        assert!(reversed.removed().contains("API_URL"));
+    }
+
     #[test]
     fn test_is_empty() {
         let env = HashMap::new();
@@ -212,4 +370,32 @@ mod tests {
         let diff = EnvDiff::new(env1, env2);
         assert!(!diff.is_empty());
     }
+
+    #[test]
+    fn test_change_summary_between_loads() {
+        let base: HashMap<String, String> =
+            HashMap::from([("HOME".to_string(), "/home/user".to_string())]);
+        let with = |pairs: &[(&str, &str)]| {
+            let mut next = base.clone();
+            next.extend(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+            EnvDiff::new(base.clone(), next)
+        };
+
+        let previous = with(&[("A", "1"), ("B", "2"), ("C", "3")]);
+        let current = with(&[("A", "1"), ("B", "changed"), ("D", "4"), ("E", "5")]);
+
+        let summary = ChangeSummary::between(&previous, &current);
+        assert_eq!(
+            summary,
+            ChangeSummary {
+                added: 2,
+                changed: 1,
+                removed: 1
+            }
+        );
+        assert_eq!(summary.to_string(), "+2 vars, ~1 changed, -1 removed");
+
+        let unchanged = ChangeSummary::between(&previous, &previous);
+        assert_eq!(unchanged.to_string(), "+0 vars, ~0 changed");
+    }
 }
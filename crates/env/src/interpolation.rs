@@ -0,0 +1,259 @@
+//! Variable interpolation between environment variables
+//!
+//! Values may reference other variables of the same environment using the
+//! `${NAME}` syntax (e.g. `DATABASE_URL: "postgres://${DB_HOST}:${DB_PORT}/app"`).
+//! References are resolved in dependency order and reference cycles are
+//! reported as errors. References to names that are not part of the
+//! environment, as well as a variable referencing itself (`PATH: "./bin:${PATH}"`),
+//! are left untouched so they can later be expanded against the process
+//! environment. An escaped reference (`\${NAME}`) is never interpolated.
+//!
+//! Secrets are resolved on their own, from the whole value of their
+//! variable, once they are needed, so a reference to a secret inside
+//! another value is rejected rather than exporting its unresolved reference.
+
+use crate::manager::secrets::is_secret_reference;
+use cuenv_core::{Error, Result};
+use std::collections::HashMap;
+
+/// Resolution state of a variable during the depth-first traversal
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VisitState {
+    InProgress,
+    Done,
+}
+
+/// Resolve `${NAME}` references between the given variables
+pub fn interpolate_variables(
+    variables: &HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    let mut resolved = HashMap::with_capacity(variables.len());
+    let mut states = HashMap::with_capacity(variables.len());
+    let mut stack = Vec::new();
+
+    // Sort the keys so that error reporting is deterministic
+    let mut names: Vec<&String> = variables.keys().collect();
+    names.sort();
+
+    for name in names {
+        resolve_variable(name, variables, &mut resolved, &mut states, &mut stack)?;
+    }
+
+    Ok(resolved)
+}
+
+/// Return the names referenced through `${NAME}` in a value, in order of appearance
+pub fn references(value: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                if is_valid_name(name) && !is_escaped(rest, start) {
+                    names.push(name);
+                }
+                rest = &after[end + 1..];
+            }
+            None => break,
+        }
+    }
+
+    names
+}
+
+fn resolve_variable(
+    name: &str,
+    variables: &HashMap<String, String>,
+    resolved: &mut HashMap<String, String>,
+    states: &mut HashMap<String, VisitState>,
+    stack: &mut Vec<String>,
+) -> Result<()> {
+    match states.get(name) {
+        Some(VisitState::Done) => return Ok(()),
+        Some(VisitState::InProgress) => {
+            let cycle_start = stack.iter().position(|n| n == name).unwrap_or(0);
+            let mut cycle: Vec<&str> = stack[cycle_start..].iter().map(String::as_str).collect();
+            cycle.push(name);
+            return Err(Error::environment(
+                name,
+                format!("circular variable reference: {}", cycle.join(" -> ")),
+            ));
+        }
+        None => {}
+    }
+
+    let Some(value) = variables.get(name) else {
+        return Ok(());
+    };
+
+    states.insert(name.to_string(), VisitState::InProgress);
+    stack.push(name.to_string());
+
+    for reference in references(value) {
+        // A self-reference points at the outer value, not at this definition
+        if reference == name {
+            continue;
+        }
+        if variables
+            .get(reference)
+            .is_some_and(|value| is_secret_reference(value))
+        {
+            return Err(Error::environment(
+                name,
+                format!(
+                    "references the secret {reference}, which cannot be part of another value; \
+                     have its resolver return the whole value instead"
+                ),
+            ));
+        }
+        if variables.contains_key(reference) {
+            resolve_variable(reference, variables, resolved, states, stack)?;
+        }
+    }
+
+    let expanded = substitute(value, resolved);
+
+    stack.pop();
+    states.insert(name.to_string(), VisitState::Done);
+    resolved.insert(name.to_string(), expanded);
+
+    Ok(())
+}
+
+/// Replace every `${NAME}` whose name has already been resolved
+fn substitute(value: &str, resolved: &HashMap<String, String>) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                match resolved.get(name) {
+                    Some(replacement) if !is_escaped(rest, start) => output.push_str(replacement),
+                    // Unknown and escaped names are kept verbatim for later shell expansion
+                    _ => output.push_str(&rest[start..start + end + 3]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn is_escaped(value: &str, dollar_pos: usize) -> bool {
+    value[..dollar_pos].ends_with('\\')
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_interpolates_between_variables() {
+        let input = vars(&[
+            ("DB_HOST", "localhost"),
+            ("DB_PORT", "5432"),
+            ("DATABASE_URL", "postgres://${DB_HOST}:${DB_PORT}/app"),
+        ]);
+
+        let result = interpolate_variables(&input).unwrap();
+        assert_eq!(result["DATABASE_URL"], "postgres://localhost:5432/app");
+        assert_eq!(result["DB_HOST"], "localhost");
+    }
+
+    #[test]
+    fn test_resolves_transitive_references() {
+        let input = vars(&[("A", "${B}/a"), ("B", "${C}/b"), ("C", "/root")]);
+
+        let result = interpolate_variables(&input).unwrap();
+        assert_eq!(result["A"], "/root/b/a");
+        assert_eq!(result["B"], "/root/b");
+    }
+
+    #[test]
+    fn test_unknown_references_are_preserved() {
+        let input = vars(&[("DATA", "${HOME}/data"), ("BROKEN", "${UNCLOSED")]);
+
+        let result = interpolate_variables(&input).unwrap();
+        assert_eq!(result["DATA"], "${HOME}/data");
+        assert_eq!(result["BROKEN"], "${UNCLOSED");
+    }
+
+    #[test]
+    fn test_detects_cycles() {
+        let input = vars(&[("A", "${B}"), ("B", "${C}"), ("C", "${A}")]);
+
+        let err = interpolate_variables(&input).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("circular variable reference"), "{message}");
+        assert!(message.contains("A -> B -> C -> A"), "{message}");
+    }
+
+    #[test]
+    fn test_rejects_references_to_secrets() {
+        let token = r#"cuenv-resolver://{"fromLocalStore":"TOKEN"}"#;
+        let input = vars(&[("TOKEN", token), ("AUTH", "Bearer ${TOKEN}")]);
+
+        let message = interpolate_variables(&input).unwrap_err().to_string();
+        assert!(message.contains("AUTH"), "{message}");
+        assert!(message.contains("references the secret TOKEN"), "{message}");
+
+        // The secret itself, and escaped references to it, are left alone
+        let input = vars(&[("TOKEN", token), ("HINT", "Set \\${TOKEN}")]);
+        let result = interpolate_variables(&input).unwrap();
+        assert_eq!(result["TOKEN"], token);
+        assert_eq!(result["HINT"], "Set \\${TOKEN}");
+    }
+
+    #[test]
+    fn test_self_reference_refers_to_outer_value() {
+        let input = vars(&[("BIN", "/opt/bin"), ("PATH", "${BIN}:${PATH}")]);
+
+        let result = interpolate_variables(&input).unwrap();
+        assert_eq!(result["PATH"], "/opt/bin:${PATH}");
+    }
+
+    #[test]
+    fn test_escaped_references_are_not_interpolated() {
+        let input = vars(&[("NAME", "app"), ("TEMPLATE", "User: \\${NAME}")]);
+
+        let result = interpolate_variables(&input).unwrap();
+        assert_eq!(result["TEMPLATE"], "User: \\${NAME}");
+    }
+
+    #[test]
+    fn test_references() {
+        assert_eq!(references("${A}-${B_2}-${1X}-$C"), vec!["A", "B_2"]);
+        assert!(references("no refs").is_empty());
+    }
+}
@@ -4,15 +4,41 @@
 //! and caching of environment state.
 
 pub mod cache;
+pub mod command_values;
+pub mod daemon;
 pub mod diff;
+pub mod interpolation;
 pub mod manager;
+pub mod nix;
+pub mod overlays;
+pub mod path_list;
+pub mod policy;
+pub mod provenance;
+pub mod selection;
+pub mod snapshot;
+pub mod snapshots;
 pub mod source_parser;
 pub mod state;
+pub mod terraform;
+pub mod tool_versions;
+pub mod validation;
+mod value_cache;
 pub mod watcher;
 
 pub use cache::*;
+pub use command_values::CommandCache;
 pub use diff::*;
+pub use interpolation::interpolate_variables;
 pub use manager::{EnvManager, TaskSource};
+pub use nix::DevShellCache;
+pub use policy::EnvPolicy;
+pub use provenance::{LoadedVariable, VariableSource};
+pub use selection::EnvironmentSelection;
+pub use snapshot::EnvSnapshot;
+pub use snapshots::{SavedSnapshot, SavedSnapshots};
 pub use source_parser::*;
 pub use state::StateManager;
+pub use terraform::TerraformCache;
+pub use tool_versions::Installs;
+pub use validation::validate_variables;
 pub use watcher::*;
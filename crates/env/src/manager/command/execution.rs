@@ -1,12 +1,12 @@
 use cuenv_core::{Error, Result};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::io::{self, BufReader};
 use std::process::{Command, Stdio};
-use std::sync::{Arc, RwLock};
 
 use super::output::wait_for_output_threads;
 use crate::manager::secrets::resolve_secret;
-use crate::manager::stubs::{OutputFilter, Platform};
+use crate::manager::stubs::Platform;
+use cuenv_core::masking::MaskingWriter;
 
 /// Setup environment variables for command execution
 pub fn setup_command_environment(
@@ -70,6 +70,33 @@ pub fn execute_command(
 }
 
 /// Execute command directly inheriting stdio (for exec command)
+///
+/// The command replaces the cuenv process, so it gets signals and terminal
+/// input as if the shell had started it. This only returns when the command
+/// could not be started.
+#[cfg(unix)]
+pub fn execute_command_direct(
+    command: &str,
+    args: &[String],
+    final_env: HashMap<String, String>,
+) -> Result<i32> {
+    use std::os::unix::process::CommandExt;
+
+    let error = Command::new(command)
+        .args(args)
+        .env_clear()
+        .envs(&final_env)
+        .exec();
+    Err(Error::command_execution(
+        command,
+        args.to_vec(),
+        format!("Failed to execute command: {error}"),
+        None,
+    ))
+}
+
+/// Execute command directly inheriting stdio (for exec command)
+#[cfg(not(unix))]
 pub fn execute_command_direct(
     command: &str,
     args: &[String],
@@ -134,10 +161,7 @@ fn execute_command_with_options(
         };
         Ok(status.code().unwrap_or(1))
     } else {
-        // For regular commands: handle output filtering
-        let secret_set = HashSet::new();
-        let secrets = Arc::new(RwLock::new(secret_set));
-
+        // For regular commands: mask secrets in the output
         // Set up filtered output streams
         let stdout = match child.stdout.take() {
             Some(s) => s,
@@ -162,17 +186,14 @@ fn execute_command_with_options(
             }
         };
 
-        let stdout_secrets = Arc::clone(&secrets);
-        let stderr_secrets = Arc::clone(&secrets);
-
         // Spawn threads to handle output filtering
         let stdout_thread = std::thread::spawn(move || {
-            let mut filter = OutputFilter::new(io::stdout(), stdout_secrets);
+            let mut filter = MaskingWriter::new(io::stdout());
             io::copy(&mut BufReader::new(stdout), &mut filter)
         });
 
         let stderr_thread = std::thread::spawn(move || {
-            let mut filter = OutputFilter::new(io::stderr(), stderr_secrets);
+            let mut filter = MaskingWriter::new(io::stderr());
             io::copy(&mut BufReader::new(stderr), &mut filter)
         });
 
@@ -69,14 +69,8 @@ pub fn run_command_with_restrictions(
     };
 
     // Handle output streams
-    use std::collections::HashSet;
+    use cuenv_core::masking::MaskingWriter;
     use std::io::{self, BufReader};
-    use std::sync::{Arc, RwLock};
-
-    use crate::manager::stubs::OutputFilter;
-
-    let secret_set = HashSet::new();
-    let secrets = Arc::new(RwLock::new(secret_set));
 
     let stdout = match child.stdout.take() {
         Some(s) => s,
@@ -101,17 +95,14 @@ pub fn run_command_with_restrictions(
         }
     };
 
-    let stdout_secrets = Arc::clone(&secrets);
-    let stderr_secrets = Arc::clone(&secrets);
-
-    // Spawn threads to handle output filtering
+    // Spawn threads that mask secrets in the output
     let stdout_thread = std::thread::spawn(move || {
-        let mut filter = OutputFilter::new(io::stdout(), stdout_secrets);
+        let mut filter = MaskingWriter::new(io::stdout());
         io::copy(&mut BufReader::new(stdout), &mut filter)
     });
 
     let stderr_thread = std::thread::spawn(move || {
-        let mut filter = OutputFilter::new(io::stderr(), stderr_secrets);
+        let mut filter = MaskingWriter::new(io::stderr());
         io::copy(&mut BufReader::new(stderr), &mut filter)
     });
 
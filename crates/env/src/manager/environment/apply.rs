@@ -1,35 +1,27 @@
-use cuenv_config::ParseOptions;
-use cuenv_core::{Error, Result};
-use cuenv_utils::sync::env::SyncEnv;
-use cuenv_utils::FileTimes;
+use cuenv_core::{Error, Result, ENV_LOCAL_CUE_FILENAME};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::PathBuf;
 
-use crate::diff::EnvDiff;
-use crate::state::StateManager;
+use crate::tool_versions::TOOL_VERSION_FILES;
 
-/// Apply merged environment variables (sourced + CUE)
-pub async fn apply_merged_environment(
-    dir: &Path,
+/// Expand shell references in merged variables (sourced + CUE) against
+/// `env` rather than the environment of this process
+pub fn expand_variables(
     variables: HashMap<String, String>,
-    _options: &ParseOptions,
     has_sourced_env: bool,
-    original_env: &HashMap<String, String>,
-    cue_vars: &mut HashMap<String, String>,
-) -> Result<()> {
-    // Build the new environment
-    let mut new_env = original_env.clone();
-    cue_vars.clear();
-
+    env: &HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    let mut expanded = HashMap::with_capacity(variables.len());
     for (key, value) in variables {
         // Skip shell expansion for nix-sourced variables that contain unexpandable references
         // These will be expanded by the shell when the command runs
         let final_value = if has_sourced_env && value.contains("$NIX_BUILD_TOP") {
             // Don't expand nix-specific variables, they'll be set by the shell
-            value.clone()
+            value
         } else {
             // Try to expand other variables
-            match shellexpand::full(&value) {
+            let lookup = |name: &str| Ok::<_, std::convert::Infallible>(env.get(name));
+            match shellexpand::full_with_context(&value, || env.get("HOME"), lookup) {
                 Ok(expanded) => expanded.to_string(),
                 Err(e) => {
                     // If expansion fails and it's a nix variable, just use it as-is
@@ -47,43 +39,41 @@ pub async fn apply_merged_environment(
                 }
             }
         };
-
-        tracing::debug!("Setting {key}={final_value}");
-        new_env.insert(key.clone(), final_value.clone());
-        cue_vars.insert(key.clone(), final_value.clone());
-        SyncEnv::set_var(key, final_value).map_err(|e| Error::Configuration {
-            message: format!("Failed to set environment variable: {e}"),
-        })?;
-    }
-
-    // Create environment diff
-    let diff = EnvDiff::new(original_env.clone(), new_env);
-
-    // Create file watches
-    let mut watches = FileTimes::new();
-    let env_cue = dir.join("env.cue");
-    if env_cue.exists() {
-        watches.watch(&env_cue);
+        expanded.insert(key, final_value);
     }
+    Ok(expanded)
+}
 
-    // Save state with all required parameters
-    let environment = SyncEnv::var("CUENV_ENV")
-        .map_err(|e| Error::Configuration {
-            message: format!("Failed to get CUENV_ENV: {e}"),
-        })?
-        .or_else(|| Some("default".to_string()));
-
-    let capabilities = Vec::new(); // TODO: get actual capabilities from context
-
-    StateManager::load(
-        dir,
-        &env_cue,
-        environment.as_deref(),
-        &capabilities,
-        &diff,
-        &watches,
-    )
-    .await?;
+/// All `.cue` files in the directories of the given env.cue files
+///
+/// A CUE package spans every `.cue` file in its directory, so any of them
+/// may change the evaluated environment. A missing env.local.cue is watched
+/// too, so creating one reloads the environment, and so are the files
+/// pinning tool versions next to them.
+pub(crate) fn watched_files(config_files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = config_files
+        .iter()
+        .filter_map(|file| file.parent())
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cue"))
+        .chain(config_files.iter().cloned())
+        .chain(
+            config_files
+                .iter()
+                .filter_map(|file| file.parent())
+                .map(|dir| dir.join(ENV_LOCAL_CUE_FILENAME)),
+        )
+        .chain(
+            config_files
+                .iter()
+                .filter_map(|file| file.parent())
+                .flat_map(|dir| TOOL_VERSION_FILES.iter().map(|name| dir.join(name))),
+        )
+        .collect();
 
-    Ok(())
+    files.sort();
+    files.dedup();
+    files
 }
@@ -1,17 +1,30 @@
 use cuenv_config::{
-    CommandConfig, CueParser, Hook, HookConfig, HookType, ParseOptions, TaskConfig, TaskNode,
-    VariableMetadata,
+    tags_from_env, CommandConfig, ConfigSettings, Hook, HookConfig, HookType, ParseOptions,
+    TaskConfig, TaskNode, VariableMetadata,
 };
 use cuenv_core::{
-    constants::{CUENV_PACKAGE_VAR, DEFAULT_PACKAGE_NAME},
-    Error, Result,
+    constants::{CUENV_ENV_VAR, CUENV_PACKAGE_VAR, DEFAULT_PACKAGE_NAME},
+    masking, Error, Result,
 };
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-use super::apply::apply_merged_environment;
+use super::apply::expand_variables;
 use super::hooks::process_all_hooks;
 use super::supervisor::SupervisorMode;
+use crate::command_values::CommandCache;
+use crate::daemon;
+use crate::interpolation::interpolate_variables;
+use crate::manager::secrets::{defer_secrets, is_secret_reference};
+use crate::nix::{self, DevShellCache};
+use crate::overlays::{self, HostInfo};
+use crate::path_list;
+use crate::policy::EnvPolicy;
+use crate::selection::EnvironmentSelection;
+use crate::snapshot::EnvSnapshot;
+use crate::terraform::TerraformCache;
+use crate::tool_versions::{self, Installs, TOOL_VERSIONS_VAR};
+use crate::validation::validate_variables;
 
 /// Context for loading environment with all the mutable maps
 pub struct LoadEnvironmentContext<'a> {
@@ -22,10 +35,19 @@ pub struct LoadEnvironmentContext<'a> {
     pub cue_vars: &'a mut HashMap<String, String>,
     pub cue_vars_metadata: &'a mut HashMap<String, VariableMetadata>,
     pub sourced_env: &'a mut HashMap<String, String>,
+    pub variable_sources: &'a mut HashMap<String, PathBuf>,
+    pub environment_sources: &'a mut HashMap<String, String>,
+    pub deferred_secrets: &'a mut HashMap<String, String>,
+    /// Variables marked sensitive or set by a secret resolver
+    pub secret_variables: &'a mut HashSet<String>,
+    pub typed_values: &'a mut HashMap<String, serde_json::Value>,
+    pub granted_capabilities: &'a mut Vec<String>,
+    pub policy: &'a EnvPolicy,
 }
 
-/// Load environment with given options
-pub async fn load_env_with_options(
+/// Evaluate the environment of `dir` over `original_env`, without changing
+/// the environment of this process
+pub async fn evaluate_env(
     dir: &Path,
     environment: Option<String>,
     mut capabilities: Vec<String>,
@@ -33,18 +55,29 @@ pub async fn load_env_with_options(
     original_env: &HashMap<String, String>,
     context: &mut LoadEnvironmentContext<'_>,
     mode: SupervisorMode,
-) -> Result<()> {
+) -> Result<EnvSnapshot> {
     // Get the package name from environment or use default
-    let package_name =
-        std::env::var(CUENV_PACKAGE_VAR).unwrap_or_else(|_| DEFAULT_PACKAGE_NAME.to_string());
+    let package_name = original_env
+        .get(CUENV_PACKAGE_VAR)
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_PACKAGE_NAME.to_string());
+
+    // Without an explicit environment, fall back to CUENV_ENV and then to
+    // the environments chosen for this directory with `cuenv env use`
+    let environment = environment
+        .or_else(|| original_env.get(CUENV_ENV_VAR).cloned())
+        .or_else(|| EnvironmentSelection::user().get(dir));
 
     // First pass: load package to get command mappings
+    let tags = tags_from_env();
     let temp_options = ParseOptions {
         environment: environment.clone(),
         capabilities: Vec::new(), // Empty for now to get all commands
+        tags: tags.clone(),
+        ..Default::default()
     };
 
-    let parse_result = CueParser::eval_package_with_options(dir, &package_name, &temp_options)?;
+    let parse_result = daemon::evaluate(dir, &package_name, &temp_options)?.result;
     context.commands.extend(parse_result.commands.clone());
     context.tasks.extend(parse_result.tasks.clone());
     context.task_nodes.extend(parse_result.task_nodes.clone());
@@ -59,17 +92,20 @@ pub async fn load_env_with_options(
     let options = ParseOptions {
         environment,
         capabilities,
+        tags,
+        ..Default::default()
     };
 
     tracing::info!(
         path = %dir.display(),
         environment = ?options.environment,
         capabilities = ?options.capabilities,
+        tags = ?options.tags,
         "Loading CUE package"
     );
 
     // First, parse CUE package to get hooks and initial environment
-    let parse_result = match CueParser::eval_package_with_options(dir, &package_name, &options) {
+    let hierarchy = match daemon::evaluate(dir, &package_name, &options) {
         Ok(result) => result,
         Err(e) => {
             return Err(Error::cue_parse_with_source(
@@ -80,37 +116,192 @@ pub async fn load_env_with_options(
         }
     };
 
+    tracing::debug!(
+        files = ?hierarchy.files,
+        local_files = ?hierarchy.local_files,
+        "Merged env.cue files"
+    );
+    *context.variable_sources = hierarchy.variable_sources;
+    *context.environment_sources = hierarchy.result.environment_sources.clone();
+    *context.typed_values = hierarchy.result.typed_values.clone();
+    let parse_result = hierarchy.result;
+
     // Store commands, tasks and hooks
     context.commands.extend(parse_result.commands.clone());
     context.tasks.extend(parse_result.tasks.clone());
     context.task_nodes.extend(parse_result.task_nodes.clone());
     convert_hooks_to_config(&parse_result.hooks, context.hooks);
 
+    // Variables of the devenv and Nix shells come first, so hooks and
+    // env.cue override them
+    let mut sourced_env_vars = HashMap::new();
+    if parse_result.devenv.is_some() || parse_result.nix.is_some() {
+        let cache = DevShellCache::user_cache();
+        let mut shells = Vec::new();
+        if let Some(devenv) = &parse_result.devenv {
+            shells.push(cache.evaluate_devenv(devenv)?);
+        }
+        if let Some(nix) = &parse_result.nix {
+            shells.push(cache.evaluate(nix)?);
+        }
+        // Each shell's search paths go in front of those of the one below
+        let mut current = original_env.clone();
+        for mut variables in shells {
+            nix::merge_search_paths(&mut variables, &current);
+            current.extend(variables.clone());
+            sourced_env_vars.extend(variables);
+        }
+    }
+
+    // The versions pinned for asdf and mise go in front of PATH, so the
+    // version manager's own shell hook running after cuenv's changes nothing
+    let settings = ConfigSettings::effective(parse_result.config.as_ref());
+    if settings.tool_versions != Some(false) {
+        let tools = Installs::user().resolve(&tool_versions::pinned_tools(dir));
+        for missing in &tools.missing {
+            tracing::warn!("{missing} is pinned but not installed; run `mise install`");
+        }
+        if !tools.path.is_empty() {
+            let current = sourced_env_vars
+                .get("PATH")
+                .or_else(|| original_env.get("PATH"))
+                .cloned();
+            let dirs: Vec<String> = tools
+                .path
+                .iter()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .collect();
+            if let Some(path) = crate::merge_xdg_data_dirs(current, Some(dirs.join(":"))) {
+                sourced_env_vars.insert("PATH".to_string(), path);
+            }
+            sourced_env_vars.insert(TOOL_VERSIONS_VAR.to_string(), tools.versions);
+        }
+    }
+
     // Process all hooks using the new supervisor-based model
-    let sourced_env_vars = process_all_hooks(dir, &parse_result.hooks, mode).await?;
+    sourced_env_vars.extend(process_all_hooks(dir, &parse_result.hooks, mode).await?);
 
     // Store the sourced environment
     let has_sourced_env = !sourced_env_vars.is_empty();
     *context.sourced_env = sourced_env_vars.clone();
+    context.policy.filter(context.sourced_env);
 
     // Merge CUE variables with sourced variables (CUE takes precedence)
     let mut merged_variables = sourced_env_vars;
     merged_variables.extend(parse_result.variables);
 
+    // Apply the platform and host overlays that match this machine
+    merged_variables.extend(overlays::select(
+        &parse_result.overlays,
+        &HostInfo::current(),
+    ));
+
+    // Run fromCommand values, reusing cached output while it is still fresh
+    if !parse_result.command_variables.is_empty() {
+        let cache = CommandCache::user_cache();
+        for (name, value) in &parse_result.command_variables {
+            merged_variables.insert(name.clone(), cache.evaluate(name, value)?);
+        }
+    }
+
+    // Read fromTerraform values, reusing outputs cached for the current state
+    if !parse_result.terraform_variables.is_empty() {
+        let mut cache = TerraformCache::user_cache();
+        for (name, value) in &parse_result.terraform_variables {
+            merged_variables.insert(name.clone(), cache.evaluate(name, value)?);
+        }
+    }
+
+    // Resolve ${VAR} references between variables before shell expansion
+    let mut merged_variables = interpolate_variables(&merged_variables)?;
+
+    // Extend path-like variables around their current value instead of replacing it
+    let mut lists = HashMap::new();
+    for (name, modifier) in &parse_result.list_variables {
+        let base = merged_variables
+            .get(name)
+            .or_else(|| original_env.get(name))
+            .cloned();
+        let value = path_list::compose(base.as_deref(), modifier);
+        merged_variables.insert(name.clone(), value);
+        lists.insert(name.clone(), path_list::separator(modifier).to_string());
+    }
+
+    // Enforce declared constraints before anything is exported
+    validate_variables(&merged_variables, &parse_result.constraints, original_env)?;
+
+    // The machine's policy overrides whatever the configuration declares
+    for name in context.policy.filter(&mut merged_variables) {
+        tracing::warn!("Not setting {name}: the machine's environment policy forbids it");
+        lists.remove(&name);
+    }
+
+    // Variables marked sensitive and secret references, counted for the prompt
+    *context.secret_variables = merged_variables
+        .iter()
+        .filter(|(name, value)| {
+            parse_result
+                .metadata
+                .get(*name)
+                .is_some_and(|metadata| metadata.sensitive)
+                || is_secret_reference(value)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    let secrets = context.secret_variables.len();
+
+    // With lazy secrets, export sentinels and only resolve once a process starts
+    let lazy_secrets = parse_result
+        .config
+        .as_ref()
+        .and_then(|config| config.lazy_secrets)
+        .unwrap_or(false);
+    *context.deferred_secrets = if lazy_secrets {
+        defer_secrets(&mut merged_variables)
+    } else {
+        HashMap::new()
+    };
+    *context.granted_capabilities = options.capabilities.clone();
+
+    // Redact values marked sensitive from everything cuenv prints
+    for (name, metadata) in &parse_result.metadata {
+        if let Some(value) = merged_variables.get(name).filter(|_| metadata.sensitive) {
+            masking::register_secret(value.clone());
+        }
+    }
+
     // Store variable metadata
     context.cue_vars_metadata.clear();
     context.cue_vars_metadata.extend(parse_result.metadata);
 
-    // Apply the merged environment
-    apply_merged_environment(
-        dir,
-        merged_variables,
-        &options,
-        has_sourced_env,
-        original_env,
-        context.cue_vars,
-    )
-    .await
+    // Expand shell references against the environment loaded over, without
+    // the variables the policy keeps from child processes
+    let denied: HashSet<String> = original_env
+        .keys()
+        .filter(|name| context.policy.denies(name))
+        .cloned()
+        .collect();
+    let expansion_env: HashMap<String, String> = original_env
+        .iter()
+        .filter(|(name, _)| !denied.contains(*name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    let variables = expand_variables(merged_variables, has_sourced_env, &expansion_env)?;
+    *context.cue_vars = variables.clone();
+
+    Ok(EnvSnapshot {
+        dir: dir.to_path_buf(),
+        config_files: hierarchy.files,
+        environment: options.environment,
+        secrets,
+        base: original_env.clone(),
+        denied,
+        variables,
+        sourced: context.sourced_env.keys().cloned().collect(),
+        files: context.variable_sources.clone(),
+        environments: context.environment_sources.clone(),
+        lists,
+    })
 }
 
 fn convert_hooks_to_config(
@@ -6,8 +6,9 @@ pub mod preload;
 pub mod supervisor;
 mod unload;
 
+pub(crate) use apply::watched_files;
 pub use hooks::execute_on_enter_hooks;
-pub use loading::{load_env_with_options, LoadEnvironmentContext};
+pub use loading::{evaluate_env, LoadEnvironmentContext};
 pub use preload::PreloadHookManager;
 pub use supervisor::SupervisorMode;
 pub use unload::unload_env;
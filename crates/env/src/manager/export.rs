@@ -2,59 +2,40 @@ use cuenv_core::{Error, Result};
 use cuenv_utils::sync::env::SyncEnv;
 use std::collections::HashMap;
 
-use super::stubs::{Platform, Shell};
+use crate::diff::EnvDiff;
+use crate::policy::EnvPolicy;
 
-/// Export environment changes for a specific shell
-pub fn export_for_shell(original_env: &HashMap<String, String>, shell: &str) -> Result<String> {
-    let current_env: HashMap<String, String> = SyncEnv::vars()
+/// The changes from `original_env` to the current environment
+pub fn changes(original_env: &HashMap<String, String>, policy: &EnvPolicy) -> Result<EnvDiff> {
+    Ok(EnvDiff::new(
+        original_env.clone(),
+        current_env(original_env, policy)?,
+    ))
+}
+
+/// The current environment, with the variables the policy removed from
+/// this process as the shell still has them
+fn current_env(
+    original_env: &HashMap<String, String>,
+    policy: &EnvPolicy,
+) -> Result<HashMap<String, String>> {
+    let mut current_env: HashMap<String, String> = SyncEnv::vars()
         .map_err(|e| Error::Configuration {
             message: format!("Failed to get environment variables: {e}"),
         })?
         .into_iter()
         .collect();
-    let mut output = String::new();
-
-    // Parse shell type
-    let shell_type = match shell.parse::<Shell>() {
-        Ok(st) => st,
-        Err(_) => {
-            return Err(Error::unsupported(
-                "shell",
-                format!("Unsupported shell: {shell}"),
-            ));
-        }
-    };
-
-    // Get export format for the shell
-    let format = Platform::get_export_format(shell_type);
-
-    // Export new or changed variables
-    for (key, value) in &current_env {
-        if !original_env.contains_key(key as &str) || original_env.get(key as &str) != Some(value) {
-            output.push_str(&format.format_export(key, value));
-            output.push('\n');
+    for (key, value) in original_env {
+        if policy.denies(key) {
+            current_env.insert(key.clone(), value.clone());
         }
     }
-
-    // Unset removed variables
-    for key in original_env.keys() {
-        if !current_env.contains_key(key) {
-            output.push_str(&format.format_unset(key));
-            output.push('\n');
-        }
-    }
-
-    Ok(output)
+    Ok(current_env)
 }
 
 /// Print environment diff to stdout/stderr
-pub fn print_env_diff(original_env: &HashMap<String, String>) -> Result<()> {
-    let current_env: HashMap<String, String> = SyncEnv::vars()
-        .map_err(|e| Error::Configuration {
-            message: format!("Failed to get environment variables: {e}"),
-        })?
-        .into_iter()
-        .collect();
+pub fn print_env_diff(original_env: &HashMap<String, String>, policy: &EnvPolicy) -> Result<()> {
+    let current_env = current_env(original_env, policy)?;
 
     // Emit structured events for environment changes while maintaining user output
     let is_tty = std::io::IsTerminal::is_terminal(&std::io::stderr());
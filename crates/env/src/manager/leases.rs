@@ -0,0 +1,113 @@
+//! Expiring secrets resolved in this session
+//!
+//! Resolvers for short-lived credentials, such as AWS STS sessions or Vault
+//! leases, can report when their value expires. The reference of such a
+//! secret is kept so it can be resolved again shortly before it expires,
+//! instead of the later tasks of a long pipeline starting with credentials
+//! that are no longer valid.
+
+use super::secrets::resolve_secret_with_expiry;
+use chrono::{DateTime, Utc};
+use cuenv_core::{Error, Result};
+use cuenv_utils::sync::env::SyncEnv;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How long before expiry a secret is refreshed
+pub const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+struct Lease {
+    reference: String,
+    expires_at: DateTime<Utc>,
+}
+
+static LEASES: Lazy<Mutex<HashMap<String, Lease>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Remember that the secret in `name` expires at `expires_at`
+pub(crate) fn track(name: &str, reference: &str, expires_at: DateTime<Utc>) {
+    LEASES.lock().insert(
+        name.to_string(),
+        Lease {
+            reference: reference.to_string(),
+            expires_at,
+        },
+    );
+}
+
+/// Resolve again every secret that expires within `margin`
+///
+/// The new values replace the old ones in the process environment, so
+/// processes started afterwards inherit them. Returns the refreshed names.
+pub fn refresh_expiring_secrets(margin: Duration) -> Result<Vec<String>> {
+    let deadline = Utc::now() + chrono::Duration::from_std(margin).unwrap_or_default();
+    let due: Vec<(String, String)> = LEASES
+        .lock()
+        .iter()
+        .filter(|(_, lease)| lease.expires_at <= deadline)
+        .map(|(name, lease)| (name.clone(), lease.reference.clone()))
+        .collect();
+
+    let mut refreshed = Vec::with_capacity(due.len());
+    for (name, reference) in due {
+        let resolved = resolve_secret_with_expiry(&reference)?;
+        SyncEnv::set_var(&name, &resolved.value).map_err(|e| Error::Configuration {
+            message: format!("Failed to set environment variable: {e}"),
+        })?;
+
+        match resolved.expires_at {
+            Some(expires_at) => track(&name, &reference, expires_at),
+            None => {
+                LEASES.lock().remove(&name);
+            }
+        }
+        tracing::info!(variable = %name, "Refreshed expiring secret");
+        refreshed.push(name);
+    }
+
+    Ok(refreshed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(output: &str) -> String {
+        format!(
+            "cuenv-resolver://{}",
+            serde_json::json!({ "cmd": "echo", "args": [output] })
+        )
+    }
+
+    #[test]
+    fn test_refreshes_secret_close_to_expiry() {
+        let later = Utc::now() + chrono::Duration::hours(1);
+        let output = format!(
+            r#"{{"value":"fresh-token","expiresAt":"{}"}}"#,
+            later.to_rfc3339()
+        );
+        track(
+            "CUENV_TEST_LEASE_EXPIRING",
+            &reference(&output),
+            Utc::now() + chrono::Duration::seconds(30),
+        );
+        track(
+            "CUENV_TEST_LEASE_VALID",
+            &reference("unused"),
+            Utc::now() + chrono::Duration::hours(2),
+        );
+
+        let refreshed = refresh_expiring_secrets(REFRESH_MARGIN).unwrap();
+
+        assert!(refreshed.contains(&"CUENV_TEST_LEASE_EXPIRING".to_string()));
+        assert!(!refreshed.contains(&"CUENV_TEST_LEASE_VALID".to_string()));
+        assert_eq!(
+            std::env::var("CUENV_TEST_LEASE_EXPIRING").unwrap(),
+            "fresh-token"
+        );
+        // The new expiry is tracked, so it is not refreshed again right away
+        let lease_expiry = LEASES.lock()["CUENV_TEST_LEASE_EXPIRING"].expires_at;
+        assert_eq!(lease_expiry.timestamp(), later.timestamp());
+    }
+}
@@ -1,17 +1,25 @@
 use cuenv_config::{CommandConfig, HookConfig, TaskConfig, TaskNode};
 use cuenv_core::{Error, Result};
 use cuenv_utils::sync::env::SyncEnv;
-use std::collections::HashMap;
-use std::path::Path;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::diff::EnvDiff;
+use crate::policy::EnvPolicy;
+use crate::provenance::{LoadedVariable, VariableSource};
+use crate::snapshot::EnvSnapshot;
 
 mod command;
 pub mod environment;
 mod export;
 mod hooks;
-mod secrets;
+mod leases;
+pub(crate) mod secrets;
 pub mod stubs;
 mod task;
 
+pub use leases::{refresh_expiring_secrets, REFRESH_MARGIN};
 pub use stubs::{AccessRestrictions, Shell};
 pub use task::TaskSource;
 
@@ -27,6 +35,13 @@ pub struct EnvManager {
     tasks: HashMap<String, TaskConfig>,
     task_nodes: HashMap<String, TaskNode>, // Preserve task structure
     hooks: HashMap<String, HookConfig>,
+    variable_sources: HashMap<String, PathBuf>, // env.cue file each variable came from
+    environment_sources: HashMap<String, String>, // Named environment that overrode a variable
+    deferred_secrets: HashMap<String, String>,  // Secret references awaiting lazy resolution
+    secret_variables: HashSet<String>,          // Variables marked sensitive or set by a resolver
+    typed_values: HashMap<String, serde_json::Value>, // Original CUE values of non-string variables
+    granted_capabilities: Vec<String>,
+    policy: EnvPolicy, // Machine-wide policy on which variables reach child processes
 }
 
 impl EnvManager {
@@ -40,6 +55,13 @@ impl EnvManager {
             tasks: HashMap::with_capacity(20),
             task_nodes: HashMap::with_capacity(20),
             hooks: HashMap::with_capacity(4),
+            variable_sources: HashMap::with_capacity(50),
+            environment_sources: HashMap::new(),
+            deferred_secrets: HashMap::new(),
+            secret_variables: HashSet::new(),
+            typed_values: HashMap::new(),
+            granted_capabilities: Vec::new(),
+            policy: EnvPolicy::default(),
         }
     }
 }
@@ -51,11 +73,17 @@ impl Default for EnvManager {
 }
 
 impl EnvManager {
+    /// Load the configuration of `dir`, discovered by the file names in
+    /// `CUENV_FILE` when set
     pub async fn load_env(&mut self, dir: &Path) -> Result<()> {
         self.load_env_with_options(dir, None, Vec::new(), None, SupervisorMode::Foreground)
             .await
     }
 
+    /// Load the configuration of `dir` into the environment of this process
+    ///
+    /// Evaluates it like [`EnvManager::evaluate`] over the current
+    /// environment, then applies the snapshot.
     pub async fn load_env_with_options(
         &mut self,
         dir: &Path,
@@ -64,8 +92,53 @@ impl EnvManager {
         command: Option<&str>,
         mode: SupervisorMode,
     ) -> Result<()> {
+        self.policy = EnvPolicy::machine()?;
         self.save_original_env()?;
+        // Hooks, commands and tasks all start from this process's environment
+        self.policy.remove_denied_from_process()?;
+
+        let snapshot = self
+            .evaluate_loaded(dir, environment, capabilities, command, mode)
+            .await?;
+        snapshot.apply().await?;
+
+        // Execute remaining onEnter hooks after environment variables are set
+        environment::execute_on_enter_hooks(&self.hooks)?;
+        Ok(())
+    }
+
+    /// Evaluate the configuration of `dir` over the environment `base`,
+    /// without changing the environment of this process
+    ///
+    /// The manager keeps the commands, tasks and hooks of the configuration,
+    /// and runs commands in the returned snapshot; apply it with
+    /// [`EnvSnapshot::apply`] for this process and what it starts to inherit
+    /// the variables. Hooks still run as children of this process.
+    pub async fn evaluate(
+        &mut self,
+        dir: &Path,
+        base: HashMap<String, String>,
+        environment: Option<String>,
+        capabilities: Vec<String>,
+        command: Option<&str>,
+        mode: SupervisorMode,
+    ) -> Result<EnvSnapshot> {
+        self.policy = EnvPolicy::machine()?;
+        self.original_env = base;
+        self.evaluate_loaded(dir, environment, capabilities, command, mode)
+            .await
+    }
 
+    /// Evaluate the configuration of `dir` over the saved original
+    /// environment
+    async fn evaluate_loaded(
+        &mut self,
+        dir: &Path,
+        environment: Option<String>,
+        capabilities: Vec<String>,
+        command: Option<&str>,
+        mode: SupervisorMode,
+    ) -> Result<EnvSnapshot> {
         let mut context = environment::LoadEnvironmentContext {
             commands: &mut self.commands,
             tasks: &mut self.tasks,
@@ -74,9 +147,16 @@ impl EnvManager {
             cue_vars: &mut self.cue_vars,
             cue_vars_metadata: &mut self.cue_vars_metadata,
             sourced_env: &mut self.sourced_env,
+            variable_sources: &mut self.variable_sources,
+            environment_sources: &mut self.environment_sources,
+            deferred_secrets: &mut self.deferred_secrets,
+            secret_variables: &mut self.secret_variables,
+            typed_values: &mut self.typed_values,
+            granted_capabilities: &mut self.granted_capabilities,
+            policy: &self.policy,
         };
 
-        environment::load_env_with_options(
+        environment::evaluate_env(
             dir,
             environment,
             capabilities,
@@ -85,14 +165,15 @@ impl EnvManager {
             &mut context,
             mode,
         )
-        .await?;
-
-        // Execute remaining onEnter hooks after environment variables are set
-        environment::execute_on_enter_hooks(&self.hooks)?;
-        Ok(())
+        .await
     }
 
     pub fn unload_env(&mut self) -> Result<()> {
+        self.variable_sources.clear();
+        self.environment_sources.clear();
+        self.deferred_secrets.clear();
+        self.secret_variables.clear();
+        self.typed_values.clear();
         environment::unload_env(
             &self.original_env,
             &self.hooks,
@@ -111,12 +192,48 @@ impl EnvManager {
         Ok(())
     }
 
+    /// Resolve secrets deferred by `lazySecrets` before starting a process
+    ///
+    /// Only secrets allowed by the capabilities the environment was loaded
+    /// with are resolved; they replace their sentinels in the process
+    /// environment so spawned tasks and commands inherit the real values.
+    pub fn resolve_deferred_secrets(&mut self) -> Result<()> {
+        for (name, value) in self.resolve_deferred_secret_values()? {
+            SyncEnv::set_var(&name, &value).map_err(|e| Error::Configuration {
+                message: format!("Failed to set environment variable: {e}"),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Resolve secrets deferred by `lazySecrets` like
+    /// [`Self::resolve_deferred_secrets`], returning them instead of setting
+    /// them in the process environment, for an environment evaluated with
+    /// [`Self::evaluate`]
+    pub fn resolve_deferred_secret_values(&mut self) -> Result<HashMap<String, String>> {
+        if self.deferred_secrets.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let resolved = secrets::resolve_deferred_secrets(
+            &self.deferred_secrets,
+            &self.cue_vars_metadata,
+            &self.granted_capabilities,
+        )?;
+        for (name, value) in &resolved {
+            self.deferred_secrets.remove(name);
+            self.cue_vars.insert(name.clone(), value.clone());
+        }
+        Ok(resolved)
+    }
+
     pub fn print_env_diff(&self) -> Result<()> {
-        export::print_env_diff(&self.original_env)
+        export::print_env_diff(&self.original_env, &self.policy)
     }
 
-    pub fn export_for_shell(&self, shell: &str) -> Result<String> {
-        export::export_for_shell(&self.original_env, shell)
+    /// The changes loading the environment made, for the shell to apply
+    pub fn changes(&self) -> Result<EnvDiff> {
+        export::changes(&self.original_env, &self.policy)
     }
 
     pub fn run_command(&self, command: &str, args: &[String]) -> Result<i32> {
@@ -184,6 +301,68 @@ impl EnvManager {
         &self.cue_vars
     }
 
+    /// Whether the loaded variable `name` holds a secret: a value marked
+    /// sensitive, or one a resolver produces, resolved or not
+    pub fn is_secret(&self, name: &str) -> bool {
+        self.secret_variables.contains(name)
+    }
+
+    /// Get a loaded variable deserialized into `T`
+    ///
+    /// Variables declared as ints, bools, lists or structs deserialize from
+    /// their original CUE value; string variables are read as JSON when
+    /// possible and as a plain string otherwise. Returns `None` when the
+    /// variable is not set by the loaded environment.
+    pub fn get_typed<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>> {
+        let value = match (self.typed_values.get(name), self.cue_vars.get(name)) {
+            (Some(typed), _) => typed.clone(),
+            (None, Some(raw)) => {
+                serde_json::from_str(raw).unwrap_or_else(|_| serde_json::Value::String(raw.clone()))
+            }
+            (None, None) => return Ok(None),
+        };
+
+        serde_json::from_value(value).map(Some).map_err(|e| {
+            Error::environment(name, format!("Cannot read value as requested type: {e}"))
+        })
+    }
+
+    /// Get the env.cue file that contributed each loaded variable
+    pub fn get_variable_sources(&self) -> &HashMap<String, PathBuf> {
+        &self.variable_sources
+    }
+
+    /// All variables set by the loaded environment, sorted by name
+    ///
+    /// Variables from env.cue take precedence over those sourced by hooks,
+    /// matching how they are applied.
+    pub fn loaded_variables(&self) -> Vec<LoadedVariable> {
+        let mut variables: HashMap<&String, (&String, Option<VariableSource>)> = self
+            .sourced_env
+            .iter()
+            .map(|(name, value)| (name, (value, Some(VariableSource::Hook))))
+            .collect();
+        for (name, value) in &self.cue_vars {
+            let source = self.variable_sources.get(name).map(|file| {
+                let environment = self.environment_sources.get(name).map(String::as_str);
+                VariableSource::locate(file, name, environment)
+            });
+            variables.insert(name, (value, source));
+        }
+
+        let mut loaded: Vec<LoadedVariable> = variables
+            .into_iter()
+            .map(|(name, (value, source))| LoadedVariable {
+                name: name.clone(),
+                value: value.clone(),
+                source,
+                changed: self.original_env.get(name) != Some(value),
+            })
+            .collect();
+        loaded.sort_by(|a, b| a.name.cmp(&b.name));
+        loaded
+    }
+
     /// Get the capabilities for a specific command
     pub fn get_command_capabilities(&self, command: &str) -> Vec<String> {
         // Extract the base command from the full command string
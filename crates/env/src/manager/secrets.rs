@@ -1,43 +1,172 @@
-use cuenv_core::{Error, Result};
+use super::leases;
+use chrono::{DateTime, Utc};
+use cuenv_config::VariableMetadata;
+use cuenv_core::{masking, Error, Result};
+use cuenv_security::SecretStore;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Prefix of values produced by secret resolvers in env.cue
+const RESOLVER_PREFIX: &str = "cuenv-resolver://";
+
+/// Prefix of the placeholder exported for secrets whose resolution is deferred
+pub const SECRET_SENTINEL_PREFIX: &str = "cuenv-secret://";
 
 #[derive(Debug, Deserialize, Serialize)]
-struct ResolverConfig {
-    cmd: String,
-    args: Vec<String>,
+#[serde(untagged)]
+enum ResolverConfig {
+    /// A command whose output is the secret
+    Command { cmd: String, args: Vec<String> },
+    /// A value kept in the encrypted local store
+    LocalStore {
+        #[serde(rename = "fromLocalStore")]
+        from_local_store: String,
+    },
+}
+
+/// The value produced by a resolver
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedSecret {
+    pub value: String,
+    /// When the value stops being valid, if the resolver reported it
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Resolver output that carries an expiry, e.g. `{"value": "...", "expiresAt": "2025-01-01T12:00:00Z"}`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ExpiringOutput {
+    value: String,
+    expires_at: DateTime<Utc>,
 }
 
 /// Resolve secret values that may contain special resolver references
 pub fn resolve_secret(value: &str) -> Result<String> {
-    if let Some(json_str) = value.strip_prefix("cuenv-resolver://") {
-        if let Ok(config) = serde_json::from_str::<ResolverConfig>(json_str) {
-            // Execute the resolver command
-            let output = std::process::Command::new(&config.cmd)
-                .args(&config.args)
-                .output()
-                .map_err(|e| {
-                    Error::configuration(format!(
-                        "Failed to execute resolver command '{}': {}",
-                        config.cmd, e
-                    ))
-                })?;
-
-            if output.status.success() {
-                let result = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                Ok(result)
-            } else {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                Err(Error::configuration(format!(
-                    "Resolver command '{}' failed: {}",
-                    config.cmd, stderr
-                )))
-            }
-        } else {
-            // If it's not valid JSON, just return the original value
-            Ok(value.to_string())
+    resolve_secret_with_expiry(value).map(|resolved| resolved.value)
+}
+
+/// Resolve a secret, keeping the expiry reported by its resolver
+pub fn resolve_secret_with_expiry(value: &str) -> Result<ResolvedSecret> {
+    let unresolved = || ResolvedSecret {
+        value: value.to_string(),
+        expires_at: None,
+    };
+    let Some(json_str) = value.strip_prefix(RESOLVER_PREFIX) else {
+        // Not a resolver reference, return as-is
+        return Ok(unresolved());
+    };
+    let Ok(config) = serde_json::from_str::<ResolverConfig>(json_str) else {
+        // If it's not valid JSON, just return the original value
+        return Ok(unresolved());
+    };
+
+    let resolved = match config {
+        ResolverConfig::Command { cmd, args } => {
+            parse_resolver_output(&run_resolver_command(&cmd, &args)?)
         }
+        ResolverConfig::LocalStore { from_local_store } => ResolvedSecret {
+            value: SecretStore::user().get(&from_local_store)?.ok_or_else(|| {
+                Error::configuration(format!(
+                    "Secret '{from_local_store}' is not in the local store. \
+                     Set it with: cuenv secret set {from_local_store}"
+                ))
+            })?,
+            expires_at: None,
+        },
+    };
+    masking::register_secret(resolved.value.clone());
+    Ok(resolved)
+}
+
+/// Interpret resolver output, which is either the value itself or a JSON
+/// object with the value and its expiry
+fn parse_resolver_output(output: &str) -> ResolvedSecret {
+    match serde_json::from_str::<ExpiringOutput>(output) {
+        Ok(expiring) => ResolvedSecret {
+            value: expiring.value,
+            expires_at: Some(expiring.expires_at),
+        },
+        Err(_) => ResolvedSecret {
+            value: output.to_string(),
+            expires_at: None,
+        },
+    }
+}
+
+fn run_resolver_command(cmd: &str, args: &[String]) -> Result<String> {
+    // Execute the resolver command
+    let output = std::process::Command::new(cmd)
+        .args(args)
+        .output()
+        .map_err(|e| {
+            Error::configuration(format!("Failed to execute resolver command '{cmd}': {e}"))
+        })?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     } else {
-        // Not a resolver reference, return as-is
-        Ok(value.to_string())
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(Error::configuration(format!(
+            "Resolver command '{cmd}' failed: {stderr}"
+        )))
     }
 }
+
+/// Whether a value is a secret resolver reference
+pub fn is_secret_reference(value: &str) -> bool {
+    value.starts_with(RESOLVER_PREFIX)
+}
+
+/// The placeholder exported in place of a deferred secret
+pub fn secret_sentinel(name: &str) -> String {
+    format!("{SECRET_SENTINEL_PREFIX}{name}")
+}
+
+/// Replace secret references with sentinels, returning the deferred references
+pub fn defer_secrets(variables: &mut HashMap<String, String>) -> HashMap<String, String> {
+    variables
+        .iter_mut()
+        .filter(|(_, value)| is_secret_reference(value))
+        .map(|(name, value)| {
+            (
+                name.clone(),
+                std::mem::replace(value, secret_sentinel(name)),
+            )
+        })
+        .collect()
+}
+
+/// Resolve deferred secrets that the granted capabilities allow
+///
+/// Secrets tagged with a capability that wasn't granted stay unresolved and
+/// are left out of the returned map, so the process only sees their sentinel.
+/// Secrets that expire are tracked so they can be refreshed later.
+pub fn resolve_deferred_secrets(
+    deferred: &HashMap<String, String>,
+    metadata: &HashMap<String, VariableMetadata>,
+    granted_capabilities: &[String],
+) -> Result<HashMap<String, String>> {
+    deferred
+        .iter()
+        .filter(
+            |(name, _)| match metadata.get(*name).and_then(|m| m.capability.as_ref()) {
+                Some(capability) if !granted_capabilities.contains(capability) => {
+                    tracing::debug!(
+                        variable = %name,
+                        capability = %capability,
+                        "Not resolving secret without granted capability"
+                    );
+                    false
+                }
+                _ => true,
+            },
+        )
+        .map(|(name, reference)| {
+            let resolved = resolve_secret_with_expiry(reference)?;
+            if let Some(expires_at) = resolved.expires_at {
+                leases::track(name, reference, expires_at);
+            }
+            Ok((name.clone(), resolved.value))
+        })
+        .collect()
+}
@@ -62,35 +62,7 @@ impl StateManager {
 
 pub struct Platform;
 
-pub struct ExportFormat {
-    shell: Shell,
-}
-
-impl ExportFormat {
-    pub fn new(shell: Shell) -> Self {
-        Self { shell }
-    }
-
-    pub fn format_export(&self, key: &str, value: &str) -> String {
-        match self.shell {
-            Shell::Fish => format!("set -x {key} \"{value}\""),
-            _ => format!("export {key}=\"{value}\""),
-        }
-    }
-
-    pub fn format_unset(&self, key: &str) -> String {
-        match self.shell {
-            Shell::Fish => format!("set -e {key}"),
-            _ => format!("unset {key}"),
-        }
-    }
-}
-
 impl Platform {
-    pub fn get_export_format(shell: Shell) -> ExportFormat {
-        ExportFormat::new(shell)
-    }
-
     pub fn setup_environment(_env: &mut HashMap<String, String>) {
         // Stub
     }
@@ -99,26 +71,3 @@ impl Platform {
         "HOME"
     }
 }
-
-pub struct OutputFilter<W> {
-    writer: W,
-}
-
-impl<W: std::io::Write> OutputFilter<W> {
-    pub fn new(
-        writer: W,
-        _secrets: std::sync::Arc<std::sync::RwLock<std::collections::HashSet<String>>>,
-    ) -> Self {
-        Self { writer }
-    }
-}
-
-impl<W: std::io::Write> std::io::Write for OutputFilter<W> {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.writer.write(buf)
-    }
-
-    fn flush(&mut self) -> std::io::Result<()> {
-        self.writer.flush()
-    }
-}
@@ -1,5 +1,8 @@
-use crate::manager::AccessRestrictions;
+use crate::manager::secrets::{defer_secrets, resolve_deferred_secrets, secret_sentinel};
+use crate::manager::{AccessRestrictions, EnvManager};
 use crate::source_parser::parse_shell_exports;
+use cuenv_config::VariableMetadata;
+use std::collections::HashMap;
 
 #[test]
 fn test_access_restrictions_creation_and_methods() {
@@ -55,3 +58,69 @@ export 123INVALID=value
     assert!(!vars.contains_key(""));
     assert!(!vars.contains_key("123INVALID"));
 }
+
+#[test]
+fn test_defer_secrets_replaces_references_with_sentinels() {
+    let reference = r#"cuenv-resolver://{"cmd":"echo","args":["s3cret"]}"#;
+    let mut vars = HashMap::from([
+        ("API_KEY".to_string(), reference.to_string()),
+        ("PORT".to_string(), "8080".to_string()),
+    ]);
+
+    let deferred = defer_secrets(&mut vars);
+
+    assert_eq!(vars["API_KEY"], secret_sentinel("API_KEY"));
+    assert_eq!(vars["PORT"], "8080");
+    assert_eq!(deferred.len(), 1);
+    assert_eq!(deferred["API_KEY"], reference);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resolve_deferred_secrets_respects_capabilities() {
+    let deferred = HashMap::from([
+        (
+            "API_KEY".to_string(),
+            r#"cuenv-resolver://{"cmd":"echo","args":["api"]}"#.to_string(),
+        ),
+        (
+            "AWS_SECRET".to_string(),
+            r#"cuenv-resolver://{"cmd":"echo","args":["aws"]}"#.to_string(),
+        ),
+    ]);
+    let metadata = HashMap::from([(
+        "AWS_SECRET".to_string(),
+        VariableMetadata {
+            capability: Some("aws".to_string()),
+            ..Default::default()
+        },
+    )]);
+
+    let resolved = resolve_deferred_secrets(&deferred, &metadata, &[]).unwrap();
+    assert_eq!(resolved.get("API_KEY"), Some(&"api".to_string()));
+    assert!(!resolved.contains_key("AWS_SECRET"));
+
+    let resolved = resolve_deferred_secrets(&deferred, &metadata, &["aws".to_string()]).unwrap();
+    assert_eq!(resolved.get("AWS_SECRET"), Some(&"aws".to_string()));
+}
+
+#[test]
+fn test_get_typed_reads_original_and_string_values() {
+    let mut manager = EnvManager::new();
+    manager.cue_vars = HashMap::from([
+        ("HOSTS".to_string(), "a:b".to_string()),
+        ("PORT".to_string(), "8080".to_string()),
+        ("NAME".to_string(), "app".to_string()),
+    ]);
+    manager.typed_values = HashMap::from([("HOSTS".to_string(), serde_json::json!(["a", "b"]))]);
+
+    let hosts: Option<Vec<String>> = manager.get_typed("HOSTS").unwrap();
+    assert_eq!(hosts, Some(vec!["a".to_string(), "b".to_string()]));
+    assert_eq!(manager.get_typed::<u16>("PORT").unwrap(), Some(8080));
+    assert_eq!(
+        manager.get_typed::<String>("NAME").unwrap(),
+        Some("app".to_string())
+    );
+    assert_eq!(manager.get_typed::<u16>("MISSING").unwrap(), None);
+    assert!(manager.get_typed::<u16>("NAME").is_err());
+}
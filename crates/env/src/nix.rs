@@ -0,0 +1,343 @@
+//! Variables of Nix and devenv development shells
+//!
+//! With `nix: { flake: ".#devShell" }` in env.cue, the environment is
+//! layered on the variables `nix print-dev-env --json` reports for the
+//! shell, like direnv with nix-direnv. With `devenv: {}`, it is layered on
+//! those of `devenv print-dev-env --json` for the devenv.nix next to it.
+//! Evaluating a shell takes seconds, so the variables are cached until the
+//! files pinning the shell change, and the last variables are kept when the
+//! shell fails to evaluate.
+
+use crate::value_cache;
+use cuenv_config::{DevenvConfig, NixConfig};
+use cuenv_core::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Variables of the build environment that `nix develop` leaves out of the
+/// shell as well
+const IGNORED_VARIABLES: &[&str] = &[
+    "BASHOPTS",
+    "HOME",
+    "NIX_BUILD_TOP",
+    "NIX_ENFORCE_PURITY",
+    "NIX_LOG_FD",
+    "NIX_REMOTE",
+    "PPID",
+    "SHELL",
+    "SHELLOPTS",
+    "SSL_CERT_FILE",
+    "NIX_SSL_CERT_FILE",
+    "TEMP",
+    "TEMPDIR",
+    "TERM",
+    "TMP",
+    "TMPDIR",
+    "TZ",
+    "UID",
+];
+
+/// Search paths the shell's entries are put in front of instead of replacing
+const SEARCH_PATHS: &[&str] = &["PATH", "XDG_DATA_DIRS"];
+
+#[derive(Serialize, Deserialize)]
+struct CachedShell {
+    inputs_hash: String,
+    variables: HashMap<String, String>,
+}
+
+/// Output of `print-dev-env --json`
+#[derive(Deserialize)]
+struct DevEnv {
+    variables: HashMap<String, DevEnvVariable>,
+}
+
+#[derive(Deserialize)]
+struct DevEnvVariable {
+    #[serde(rename = "type")]
+    kind: String,
+    value: serde_json::Value,
+}
+
+/// Files of a devenv project that change its environment
+const DEVENV_INPUTS: &[&str] = &["devenv.nix", "devenv.lock", "devenv.yaml"];
+
+/// A development shell and how it is evaluated
+struct Shell<'a> {
+    /// What the shell is called in messages
+    name: &'a str,
+    program: &'static str,
+    args: Vec<String>,
+    /// Directory the program runs in
+    dir: &'a Path,
+    /// Files whose contents invalidate the cached variables
+    inputs: Vec<PathBuf>,
+}
+
+/// On-disk cache of development shell variables
+pub struct DevShellCache {
+    dir: PathBuf,
+}
+
+impl DevShellCache {
+    /// Create a cache storing its entries in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cache in the user's cache directory
+    pub fn user_cache() -> Self {
+        Self::new(value_cache::user_cache_dir("nix"))
+    }
+
+    /// The exported variables of the flake's shell, evaluating it unless
+    /// the flake is unchanged since it was cached
+    pub fn evaluate(&self, nix: &NixConfig) -> Result<HashMap<String, String>> {
+        // A remote flake is only pinned by its reference
+        let inputs = nix
+            .flake_dir()
+            .map(|dir| vec![dir.join("flake.lock"), dir.join("flake.nix")])
+            .unwrap_or_default();
+        self.load(&Shell {
+            name: &nix.flake,
+            program: "nix",
+            args: vec![
+                "print-dev-env".to_string(),
+                "--json".to_string(),
+                nix.flake.clone(),
+            ],
+            dir: nix.dir.as_deref().unwrap_or(Path::new(".")),
+            inputs,
+        })
+    }
+
+    /// The exported variables of the devenv environment, evaluating it
+    /// unless devenv.nix, devenv.lock and devenv.yaml are unchanged since it
+    /// was cached
+    pub fn evaluate_devenv(&self, devenv: &DevenvConfig) -> Result<HashMap<String, String>> {
+        let root = devenv.root_dir();
+        self.load(&Shell {
+            name: "devenv",
+            program: "devenv",
+            args: vec!["print-dev-env".to_string(), "--json".to_string()],
+            dir: &root,
+            inputs: DEVENV_INPUTS.iter().map(|file| root.join(file)).collect(),
+        })
+    }
+
+    fn load(&self, shell: &Shell) -> Result<HashMap<String, String>> {
+        let inputs_hash = hash_inputs(shell);
+        let entry = self.entry_path(shell);
+        let cached = value_cache::read_entry::<CachedShell>(&entry);
+
+        if let Some(cached) = &cached {
+            if cached.inputs_hash == inputs_hash {
+                tracing::debug!("Using cached shell {}", shell.name);
+                return Ok(cached.variables.clone());
+            }
+        }
+
+        let variables = match run(shell) {
+            Ok(variables) => variables,
+            Err(e) => match cached {
+                Some(cached) => {
+                    tracing::warn!("{e}; using the previous variables of {}", shell.name);
+                    return Ok(cached.variables);
+                }
+                None => return Err(e),
+            },
+        };
+
+        let cached = CachedShell {
+            inputs_hash,
+            variables,
+        };
+        if let Err(e) = value_cache::write_entry(&entry, &cached) {
+            tracing::warn!("Failed to cache shell {}: {e}", shell.name);
+        }
+        Ok(cached.variables)
+    }
+
+    fn entry_path(&self, shell: &Shell) -> PathBuf {
+        let dir = shell.dir;
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut hasher = Sha256::new();
+        hasher.update(canonical.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(shell.program.as_bytes());
+        for arg in &shell.args {
+            hasher.update([0]);
+            hasher.update(arg.as_bytes());
+        }
+        self.dir.join(format!("{:x}.json", hasher.finalize()))
+    }
+}
+
+/// Put the shell's entries of search paths in front of those of `current`
+pub fn merge_search_paths(
+    variables: &mut HashMap<String, String>,
+    current: &HashMap<String, String>,
+) {
+    for name in SEARCH_PATHS {
+        if let Some(value) = variables.remove(*name) {
+            if let Some(merged) =
+                crate::merge_xdg_data_dirs(current.get(*name).cloned(), Some(value))
+            {
+                variables.insert(name.to_string(), merged);
+            }
+        }
+    }
+}
+
+fn run(shell: &Shell) -> Result<HashMap<String, String>> {
+    tracing::info!("Evaluating shell {}", shell.name);
+    let output = Command::new(shell.program)
+        .args(&shell.args)
+        .current_dir(shell.dir)
+        .output()
+        .map_err(|e| {
+            Error::command_execution(
+                shell.program,
+                shell.args.clone(),
+                format!("failed to run {}: {e}", shell.program),
+                None,
+            )
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::command_execution(
+            shell.program,
+            shell.args.clone(),
+            format!(
+                "failed to evaluate the shell {}: {}",
+                shell.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            output.status.code(),
+        ));
+    }
+
+    parse_dev_env(&output.stdout)
+}
+
+/// The exported string variables of `print-dev-env --json`, whose output
+/// is the same for nix and devenv
+fn parse_dev_env(json: &[u8]) -> Result<HashMap<String, String>> {
+    let dev_env: DevEnv = serde_json::from_slice(json).map_err(|e| Error::Json {
+        message: "failed to parse the output of nix print-dev-env".to_string(),
+        source: e,
+    })?;
+
+    // Shell variables, arrays and functions only exist inside bash
+    let variables = dev_env
+        .variables
+        .into_iter()
+        .filter(|(name, _)| !IGNORED_VARIABLES.contains(&name.as_str()))
+        .filter_map(
+            |(name, variable)| match (variable.kind.as_str(), variable.value) {
+                ("exported", serde_json::Value::String(value)) => Some((name, value)),
+                _ => None,
+            },
+        )
+        .collect();
+    Ok(crate::filter_environment(variables))
+}
+
+/// Hash of how the shell is evaluated and of the files pinning it
+fn hash_inputs(shell: &Shell) -> String {
+    let mut hasher = Sha256::new();
+    for arg in &shell.args {
+        hasher.update(arg.as_bytes());
+        hasher.update([0]);
+    }
+    for input in &shell.inputs {
+        match std::fs::read(input) {
+            Ok(content) => {
+                hasher.update([1]);
+                hasher.update(&content);
+            }
+            // A missing input is a state of its own, so creating it invalidates the cache
+            Err(_) => hasher.update([0]),
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_dev_env() {
+        let json = br#"{
+            "bashFunctions": {},
+            "variables": {
+                "PATH": { "type": "exported", "value": "/nix/store/abc-cargo/bin" },
+                "RUST_SRC_PATH": { "type": "exported", "value": "/nix/store/def-rust-src" },
+                "HOME": { "type": "exported", "value": "/homeless-shelter" },
+                "NIX_BUILD_TOP": { "type": "exported", "value": "/tmp/nix" },
+                "buildInputs": { "type": "var", "value": "" },
+                "pkgs": { "type": "array", "value": ["a", "b"] }
+            }
+        }"#;
+
+        let variables = parse_dev_env(json).unwrap();
+        assert_eq!(variables.len(), 2);
+        assert_eq!(variables["PATH"], "/nix/store/abc-cargo/bin");
+        assert_eq!(variables["RUST_SRC_PATH"], "/nix/store/def-rust-src");
+    }
+
+    #[test]
+    fn test_merge_search_paths() {
+        let mut variables = HashMap::from([
+            ("PATH".to_string(), "/nix/store/abc/bin".to_string()),
+            ("CC".to_string(), "gcc".to_string()),
+        ]);
+        let current = HashMap::from([("PATH".to_string(), "/usr/bin:/bin".to_string())]);
+
+        merge_search_paths(&mut variables, &current);
+        assert_eq!(variables["PATH"], "/nix/store/abc/bin:/usr/bin:/bin");
+        assert_eq!(variables["CC"], "gcc");
+    }
+
+    #[test]
+    fn test_cache_is_keyed_on_the_lock_file() {
+        let project = TempDir::new().unwrap();
+        std::fs::write(project.path().join("devenv.lock"), "{}").unwrap();
+        let devenv = DevenvConfig::default().declared_in(project.path());
+        let root = devenv.root_dir();
+        let shell = Shell {
+            name: "devenv",
+            // Evaluating the shell fails, so only the cache can answer
+            program: "/nonexistent/devenv",
+            args: vec!["print-dev-env".to_string()],
+            dir: &root,
+            inputs: vec![root.join("devenv.lock")],
+        };
+
+        let cache = DevShellCache::new(project.path().join("cache"));
+        assert!(cache.load(&shell).is_err());
+
+        let variables = HashMap::from([("CC".to_string(), "clang".to_string())]);
+        value_cache::write_entry(
+            &cache.entry_path(&shell),
+            &CachedShell {
+                inputs_hash: hash_inputs(&shell),
+                variables: variables.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(cache.load(&shell).unwrap(), variables);
+
+        // A changed lock file is evaluated again, and the previous variables
+        // are kept when that fails
+        let before = hash_inputs(&shell);
+        std::fs::write(project.path().join("devenv.lock"), "{\"version\": 7}").unwrap();
+        assert_ne!(hash_inputs(&shell), before);
+        assert_eq!(cache.load(&shell).unwrap(), variables);
+    }
+}
@@ -0,0 +1,175 @@
+//! Selection of platform and host overlays
+//!
+//! An env.cue can declare variables that only apply on some machines:
+//!
+//! ```cue
+//! env: {
+//!     platform: {
+//!         darwin: { OPENSSL_DIR: "/opt/homebrew/opt/openssl" }
+//!         "linux-arm64": { JAVA_HOME: "/usr/lib/jvm/java-17-arm64" }
+//!     }
+//!     hosts: {
+//!         "ci-*": { CI_CACHE: "/mnt/cache" }
+//!     }
+//! }
+//! ```
+//!
+//! Platform overlays apply first, from the least to the most specific key,
+//! followed by host overlays, so a variable set for a host wins over one set
+//! for its platform.
+
+use cuenv_config::EnvOverlays;
+use globset::Glob;
+use std::collections::HashMap;
+
+/// The machine the environment is loaded on
+#[derive(Debug, Clone)]
+pub struct HostInfo {
+    /// Operating system as reported by Rust, e.g. `linux` or `macos`
+    pub os: String,
+    /// CPU architecture as reported by Rust, e.g. `x86_64` or `aarch64`
+    pub arch: String,
+    pub hostname: String,
+}
+
+impl HostInfo {
+    /// Describe the current machine
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            hostname: hostname::get()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Whether every `-` separated part of `key` names this OS or architecture
+    fn matches_platform(&self, key: &str) -> bool {
+        key.split('-').all(|part| {
+            let part = part.to_ascii_lowercase();
+            os_aliases(&self.os).contains(&part.as_str())
+                || arch_aliases(&self.arch).contains(&part.as_str())
+        })
+    }
+}
+
+/// Names accepted for an OS, so `darwin` works as well as Rust's `macos`
+fn os_aliases(os: &str) -> Vec<&str> {
+    match os {
+        "macos" => vec!["macos", "darwin"],
+        other => vec![other],
+    }
+}
+
+/// Names accepted for an architecture, covering the Go and Node spellings
+fn arch_aliases(arch: &str) -> Vec<&str> {
+    match arch {
+        "x86_64" => vec!["x86_64", "amd64", "x64"],
+        "aarch64" => vec!["aarch64", "arm64"],
+        other => vec![other],
+    }
+}
+
+/// The variables contributed by the overlays that apply to `host`
+pub fn select(overlays: &EnvOverlays, host: &HostInfo) -> HashMap<String, String> {
+    let mut platforms: Vec<&String> = overlays
+        .platform
+        .keys()
+        .filter(|key| host.matches_platform(key))
+        .collect();
+    // `darwin-arm64` is more specific than `darwin`, so it applies later
+    platforms.sort_by_key(|key| (key.split('-').count(), key.as_str()));
+
+    let mut hosts: Vec<&String> = overlays
+        .hosts
+        .keys()
+        .filter(|pattern| match Glob::new(pattern) {
+            Ok(glob) => glob.compile_matcher().is_match(&host.hostname),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid host pattern {pattern:?}: {e}");
+                false
+            }
+        })
+        .collect();
+    // An exact hostname is more specific than any pattern
+    hosts.sort_by_key(|pattern| (*pattern == &host.hostname, pattern.len(), pattern.as_str()));
+
+    let mut selected = HashMap::new();
+    for key in platforms {
+        selected.extend(overlays.platform[key].clone());
+    }
+    for pattern in hosts {
+        selected.extend(overlays.hosts[pattern].clone());
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(os: &str, arch: &str, hostname: &str) -> HostInfo {
+        HostInfo {
+            os: os.to_string(),
+            arch: arch.to_string(),
+            hostname: hostname.to_string(),
+        }
+    }
+
+    fn overlay(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_platform_aliases() {
+        let mac = host("macos", "aarch64", "laptop");
+        assert!(mac.matches_platform("darwin"));
+        assert!(mac.matches_platform("macos"));
+        assert!(mac.matches_platform("darwin-arm64"));
+        assert!(!mac.matches_platform("linux"));
+        assert!(!mac.matches_platform("darwin-x86_64"));
+    }
+
+    #[test]
+    fn test_more_specific_platform_wins() {
+        let overlays = EnvOverlays {
+            platform: HashMap::from([
+                (
+                    "linux".to_string(),
+                    overlay(&[("JAVA_HOME", "/usr/lib/jvm")]),
+                ),
+                (
+                    "linux-arm64".to_string(),
+                    overlay(&[("JAVA_HOME", "/usr/lib/jvm-arm64")]),
+                ),
+                ("darwin".to_string(), overlay(&[("BREW", "1")])),
+            ]),
+            ..Default::default()
+        };
+
+        let selected = select(&overlays, &host("linux", "aarch64", "box"));
+        assert_eq!(selected["JAVA_HOME"], "/usr/lib/jvm-arm64");
+        assert!(!selected.contains_key("BREW"));
+    }
+
+    #[test]
+    fn test_host_overlays_apply_after_platform() {
+        let overlays = EnvOverlays {
+            platform: HashMap::from([("linux".to_string(), overlay(&[("CACHE", "/tmp")]))]),
+            hosts: HashMap::from([
+                ("ci-*".to_string(), overlay(&[("CACHE", "/mnt/cache")])),
+                ("dev-*".to_string(), overlay(&[("CACHE", "/home/cache")])),
+            ]),
+        };
+
+        let selected = select(&overlays, &host("linux", "x86_64", "ci-runner-3"));
+        assert_eq!(selected["CACHE"], "/mnt/cache");
+
+        let selected = select(&overlays, &host("linux", "x86_64", "laptop"));
+        assert_eq!(selected["CACHE"], "/tmp");
+    }
+}
@@ -0,0 +1,112 @@
+//! Prepending and appending entries to path-like variables
+//!
+//! A variable declared as `PATH: { prepend: ["./bin"], append: ["/opt/tools"] }`
+//! keeps the value it already had and gains the listed entries around it,
+//! without duplicates. When the environment is unloaded only the entries
+//! cuenv added are removed, so changes made in the meantime survive.
+
+use cuenv_config::ListModifier;
+
+/// The platform's default separator for path lists
+#[cfg(windows)]
+pub const DEFAULT_SEPARATOR: &str = ";";
+#[cfg(not(windows))]
+pub const DEFAULT_SEPARATOR: &str = ":";
+
+/// The separator a modifier uses
+pub fn separator(modifier: &ListModifier) -> &str {
+    modifier.separator.as_deref().unwrap_or(DEFAULT_SEPARATOR)
+}
+
+/// Apply a modifier to the current value of a variable
+pub fn compose(base: Option<&str>, modifier: &ListModifier) -> String {
+    let sep = separator(modifier);
+    let existing = base.map(|value| split(value, sep)).unwrap_or_default();
+
+    let mut entries: Vec<&str> = Vec::new();
+    for entry in modifier
+        .prepend
+        .iter()
+        .map(String::as_str)
+        .chain(
+            existing
+                .into_iter()
+                .filter(|e| !modifier.append.iter().any(|a| a == e)),
+        )
+        .chain(modifier.append.iter().map(String::as_str))
+    {
+        if !entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+
+    entries.join(sep)
+}
+
+/// Entries present in `next` that were not part of `prev`
+pub fn added_entries<'a>(prev: Option<&str>, next: &'a str, sep: &str) -> Vec<&'a str> {
+    let previous = prev.map(|value| split(value, sep)).unwrap_or_default();
+    split(next, sep)
+        .into_iter()
+        .filter(|entry| !previous.contains(entry))
+        .collect()
+}
+
+/// Remove the given entries from a list value, keeping everything else in order
+pub fn remove_entries(value: &str, entries: &[&str], sep: &str) -> String {
+    split(value, sep)
+        .into_iter()
+        .filter(|entry| !entries.contains(entry))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn split<'a>(value: &'a str, sep: &str) -> Vec<&'a str> {
+    value.split(sep).filter(|entry| !entry.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifier(prepend: &[&str], append: &[&str]) -> ListModifier {
+        ListModifier {
+            prepend: prepend.iter().map(|s| s.to_string()).collect(),
+            append: append.iter().map(|s| s.to_string()).collect(),
+            separator: Some(":".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_compose_prepends_and_appends() {
+        let result = compose(
+            Some("/usr/bin:/bin"),
+            &modifier(&["/opt/bin"], &["/opt/tools"]),
+        );
+        assert_eq!(result, "/opt/bin:/usr/bin:/bin:/opt/tools");
+    }
+
+    #[test]
+    fn test_compose_deduplicates() {
+        let result = compose(
+            Some("/usr/bin:/opt/bin:/bin"),
+            &modifier(&["/opt/bin", "/opt/bin"], &["/bin"]),
+        );
+        assert_eq!(result, "/opt/bin:/usr/bin:/bin");
+    }
+
+    #[test]
+    fn test_unload_removes_only_added_entries() {
+        let prev = "/usr/bin:/bin";
+        let next = "/project/bin:/usr/bin:/bin";
+        let added = added_entries(Some(prev), next, ":");
+        assert_eq!(added, vec!["/project/bin"]);
+
+        // The user added an entry of their own while the environment was loaded
+        let current = "/home/user/.local/bin:/project/bin:/usr/bin:/bin";
+        assert_eq!(
+            remove_entries(current, &added, ":"),
+            "/home/user/.local/bin:/usr/bin:/bin"
+        );
+    }
+}
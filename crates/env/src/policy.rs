@@ -0,0 +1,193 @@
+//! Machine-wide policy on environment variables
+//!
+//! Build agents can keep variables away from everything cuenv starts,
+//! whatever the env.cue files they check out declare. The policy lives in
+//! `/etc/cuenv/env-policy.json` (`%ProgramData%\cuenv\env-policy.json` on
+//! Windows):
+//!
+//! ```json
+//! {
+//!     "deny": ["LD_PRELOAD", "LD_LIBRARY_PATH", "AWS_*"],
+//!     "allow": ["APP_*", "DATABASE_URL", "PATH"]
+//! }
+//! ```
+//!
+//! Denied variables are never set by env.cue or hooks, and are removed from
+//! the environment that hooks, commands and tasks inherit. With `allow`,
+//! env.cue and hooks may only set the listed variables. Patterns are globs
+//! and `deny` wins over `allow`. The shell's own values are left alone.
+
+use cuenv_core::{Error, Result};
+use cuenv_utils::sync::env::SyncEnv;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The file name of the policy in the machine's cuenv directory
+pub const POLICY_FILE_NAME: &str = "env-policy.json";
+
+/// The contents of a policy file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    deny: Vec<String>,
+    allow: Option<Vec<String>>,
+}
+
+/// Which variables may reach the environment
+#[derive(Debug, Clone, Default)]
+pub struct EnvPolicy {
+    deny: Option<GlobSet>,
+    allow: Option<GlobSet>,
+}
+
+impl EnvPolicy {
+    /// The policy file of this machine
+    pub fn path() -> PathBuf {
+        if cfg!(windows) {
+            std::env::var_os("ProgramData")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+                .join("cuenv")
+                .join(POLICY_FILE_NAME)
+        } else {
+            Path::new("/etc/cuenv").join(POLICY_FILE_NAME)
+        }
+    }
+
+    /// The policy of this machine, allowing everything when it has none
+    pub fn machine() -> Result<Self> {
+        Self::from_file(&Self::path())
+    }
+
+    /// Read a policy file. A missing file allows everything; a file that
+    /// cannot be read or parsed is an error rather than no policy.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content).map_err(|e| {
+                Error::configuration(format!(
+                    "Invalid environment policy {}: {e}",
+                    path.display()
+                ))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::file_system(path, "read environment policy", e)),
+        }
+    }
+
+    /// Parse the JSON of a policy file
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: PolicyFile = serde_json::from_str(content).map_err(|e| Error::Json {
+            message: "expected {\"deny\": [...], \"allow\": [...]}".to_string(),
+            source: e,
+        })?;
+        Ok(Self {
+            deny: (!file.deny.is_empty())
+                .then(|| glob_set(&file.deny))
+                .transpose()?,
+            allow: file.allow.as_deref().map(glob_set).transpose()?,
+        })
+    }
+
+    /// Whether the policy keeps `name` away from child processes
+    pub fn denies(&self, name: &str) -> bool {
+        self.deny.as_ref().is_some_and(|deny| deny.is_match(name))
+    }
+
+    /// Whether env.cue and hooks may set `name`
+    pub fn allows(&self, name: &str) -> bool {
+        !self.denies(name) && self.allow.as_ref().is_none_or(|allow| allow.is_match(name))
+    }
+
+    /// Remove the variables env.cue and hooks may not set, returning their
+    /// names, sorted
+    pub fn filter(&self, variables: &mut HashMap<String, String>) -> Vec<String> {
+        let mut removed: Vec<String> = variables
+            .keys()
+            .filter(|name| !self.allows(name))
+            .cloned()
+            .collect();
+        removed.sort();
+        for name in &removed {
+            variables.remove(name);
+        }
+        removed
+    }
+
+    /// Remove denied variables from this process, so that nothing it starts
+    /// inherits them
+    pub fn remove_denied_from_process(&self) -> Result<()> {
+        if self.deny.is_none() {
+            return Ok(());
+        }
+        let vars = SyncEnv::vars().map_err(|e| Error::Configuration {
+            message: format!("Failed to get environment variables: {e}"),
+        })?;
+        for (name, _) in vars.iter().filter(|(name, _)| self.denies(name)) {
+            SyncEnv::remove_var(name).map_err(|e| Error::Configuration {
+                message: format!("Failed to remove environment variable: {e}"),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| {
+            Error::configuration(format!("Invalid variable pattern '{pattern}': {e}"))
+        })?);
+    }
+    builder
+        .build()
+        .map_err(|e| Error::configuration(format!("Invalid variable patterns: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        let policy = EnvPolicy::parse(
+            r#"{"deny": ["LD_PRELOAD", "AWS_*"], "allow": ["APP_*", "AWS_REGION", "PATH"]}"#,
+        )
+        .unwrap();
+        assert!(policy.denies("LD_PRELOAD"));
+        assert!(policy.denies("AWS_SECRET_ACCESS_KEY"));
+        assert!(!policy.denies("HOME"));
+        assert!(policy.allows("APP_NAME"));
+        assert!(!policy.allows("AWS_REGION"));
+        assert!(!policy.allows("EDITOR"));
+
+        let mut variables = HashMap::from([
+            ("APP_NAME".to_string(), "demo".to_string()),
+            ("LD_PRELOAD".to_string(), "/tmp/evil.so".to_string()),
+            ("EDITOR".to_string(), "vi".to_string()),
+        ]);
+        assert_eq!(policy.filter(&mut variables), ["EDITOR", "LD_PRELOAD"]);
+        assert_eq!(variables.len(), 1);
+    }
+
+    #[test]
+    fn test_empty_policy_allows_everything() {
+        let policy = EnvPolicy::parse(r#"{"deny": ["AWS_*"]}"#).unwrap();
+        assert!(policy.allows("EDITOR"));
+
+        let policy = EnvPolicy::default();
+        assert!(policy.allows("LD_PRELOAD"));
+        assert!(!policy.denies("LD_PRELOAD"));
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        assert!(EnvPolicy::parse(r#"{"block": ["AWS_*"]}"#).is_err());
+        assert!(EnvPolicy::parse(r#"{"deny": ["AWS_[*"]}"#).is_err());
+
+        let missing = EnvPolicy::from_file(Path::new("/nonexistent/env-policy.json")).unwrap();
+        assert!(!missing.denies("LD_PRELOAD"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Where loaded variables come from
+//!
+//! The hierarchy records which env.cue file provided each variable. The line
+//! is looked up only when it is asked for, by searching the file for the
+//! field's declaration. A field in the directory's env.local.cue takes
+//! precedence, since that file is merged over env.cue. When one of the
+//! selected environments overrode the variable, the search starts at that
+//! environment's declaration.
+
+use cuenv_core::ENV_LOCAL_CUE_FILENAME;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The origin of a loaded variable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum VariableSource {
+    /// Declared in a CUE file
+    File {
+        path: PathBuf,
+        line: Option<usize>,
+        /// The named environment the value came from, if it was overridden
+        #[serde(skip_serializing_if = "Option::is_none")]
+        environment: Option<String>,
+    },
+    /// Exported by a hook that sources an environment, such as nix or devenv
+    Hook,
+}
+
+impl VariableSource {
+    /// Find the declaration of `name` for a variable provided by `env_cue`,
+    /// overridden by the named `environment` if given
+    pub fn locate(env_cue: &Path, name: &str, environment: Option<&str>) -> Self {
+        let local = env_cue
+            .parent()
+            .map(|dir| dir.join(ENV_LOCAL_CUE_FILENAME))
+            .and_then(|local| Some((declaration_line(&local, name, environment)?, local)));
+
+        let (path, line) = match local {
+            Some((line, path)) => (path, Some(line)),
+            None => (
+                env_cue.to_path_buf(),
+                declaration_line(env_cue, name, environment),
+            ),
+        };
+        VariableSource::File {
+            path,
+            line,
+            environment: environment.map(str::to_string),
+        }
+    }
+}
+
+impl fmt::Display for VariableSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableSource::File {
+                path,
+                line,
+                environment,
+            } => {
+                write!(f, "{}", path.display())?;
+                if let Some(line) = line {
+                    write!(f, ":{line}")?;
+                }
+                if let Some(environment) = environment {
+                    write!(f, " ({environment})")?;
+                }
+                Ok(())
+            }
+            VariableSource::Hook => write!(f, "hook"),
+        }
+    }
+}
+
+/// A variable set by the loaded environment
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedVariable {
+    pub name: String,
+    pub value: String,
+    /// Where the value came from, if known
+    pub source: Option<VariableSource>,
+    /// Whether the value differs from the environment before loading
+    pub changed: bool,
+}
+
+/// The 1-based line declaring the field `name`, quoted or not, searching
+/// after the declaration of `within` when given
+fn declaration_line(file: &Path, name: &str, within: Option<&str>) -> Option<usize> {
+    let content = std::fs::read_to_string(file).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    let start = match within {
+        Some(section) => lines.iter().position(|line| declares(line, section))?,
+        None => 0,
+    };
+    lines[start..]
+        .iter()
+        .position(|line| declares(line, name))
+        .map(|index| start + index + 1)
+}
+
+/// Whether `line` starts the field `name`
+fn declares(line: &str, name: &str) -> bool {
+    let line = line.trim_start();
+    let rest = line
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_prefix(name))
+        .and_then(|rest| rest.strip_prefix('"'))
+        .or_else(|| line.strip_prefix(name));
+    rest.is_some_and(|rest| rest.trim_start().starts_with(':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locates_declaration_line() {
+        let temp = tempfile::tempdir().unwrap();
+        let env_cue = temp.path().join("env.cue");
+        std::fs::write(
+            &env_cue,
+            "package cuenv\n\nenv: {\n    DATABASE_URL_OLD: \"a\"\n    DATABASE_URL: \"b\"\n    \"QUOTED\": \"c\"\n}\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            VariableSource::locate(&env_cue, "DATABASE_URL", None).to_string(),
+            format!("{}:5", env_cue.display())
+        );
+        assert_eq!(
+            VariableSource::locate(&env_cue, "QUOTED", None),
+            VariableSource::File {
+                path: env_cue.clone(),
+                line: Some(6),
+                environment: None,
+            }
+        );
+        assert_eq!(
+            VariableSource::locate(&env_cue, "MISSING", None),
+            VariableSource::File {
+                path: env_cue.clone(),
+                line: None,
+                environment: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_local_override_takes_precedence() {
+        let temp = tempfile::tempdir().unwrap();
+        let env_cue = temp.path().join("env.cue");
+        let local = temp.path().join(ENV_LOCAL_CUE_FILENAME);
+        std::fs::write(&env_cue, "env: {\n    DB_HOST: \"db\"\n}\n").unwrap();
+        std::fs::write(&local, "package cuenv\nenv: DB_HOST: \"localhost\"\n").unwrap();
+
+        // Only fields written on their own line are found
+        assert_eq!(
+            VariableSource::locate(&env_cue, "DB_HOST", None),
+            VariableSource::File {
+                path: env_cue.clone(),
+                line: Some(2),
+                environment: None,
+            }
+        );
+
+        std::fs::write(
+            &local,
+            "package cuenv\nenv: {\n    DB_HOST: \"localhost\"\n}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            VariableSource::locate(&env_cue, "DB_HOST", None),
+            VariableSource::File {
+                path: local,
+                line: Some(3),
+                environment: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_environment_override_is_located() {
+        let temp = tempfile::tempdir().unwrap();
+        let env_cue = temp.path().join("env.cue");
+        std::fs::write(
+            &env_cue,
+            "env: {\n    REPLICAS: \"1\"\n    environment: {\n        gpu: {\n            DEVICE: \"cuda\"\n        }\n        staging: {\n            REPLICAS: \"3\"\n        }\n    }\n}\n",
+        )
+        .unwrap();
+
+        let source = VariableSource::locate(&env_cue, "REPLICAS", Some("staging"));
+        assert_eq!(
+            source.to_string(),
+            format!("{}:8 (staging)", env_cue.display())
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Environments chosen with `cuenv env use`
+//!
+//! The selection is remembered per directory in the user's state directory,
+//! so entering the directory again loads the same stack of environments
+//! without setting `CUENV_ENV` or passing `--env` each time.
+
+use cuenv_core::{Error, Result};
+use cuenv_utils::atomic_file::write_atomic_string;
+use cuenv_utils::xdg::XdgPaths;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Environment specs such as `base+gpu+staging`, keyed by directory
+#[derive(Debug, Clone)]
+pub struct EnvironmentSelection {
+    file: PathBuf,
+}
+
+impl EnvironmentSelection {
+    /// A selection stored in `file`
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self { file: file.into() }
+    }
+
+    /// The selection in the user's cuenv state directory
+    pub fn user() -> Self {
+        Self::new(XdgPaths::state_dir().join("environments.json"))
+    }
+
+    /// The environment spec selected for `dir`
+    pub fn get(&self, dir: &Path) -> Option<String> {
+        self.read().ok()?.remove(&key(dir))
+    }
+
+    /// Select `spec` for `dir`, replacing any previous selection
+    pub fn set(&self, dir: &Path, spec: &str) -> Result<()> {
+        let mut selections = self.read()?;
+        selections.insert(key(dir), spec.to_string());
+        self.write(&selections)
+    }
+
+    /// Forget the selection for `dir`, returning whether there was one
+    pub fn clear(&self, dir: &Path) -> Result<bool> {
+        let mut selections = self.read()?;
+        let removed = selections.remove(&key(dir)).is_some();
+        if removed {
+            self.write(&selections)?;
+        }
+        Ok(removed)
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        let content = match std::fs::read_to_string(&self.file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => {
+                return Err(Error::file_system(
+                    &self.file,
+                    "read environment selection",
+                    e,
+                ))
+            }
+        };
+        serde_json::from_str(&content).map_err(|e| Error::Json {
+            message: format!("invalid environment selection {}", self.file.display()),
+            source: e,
+        })
+    }
+
+    fn write(&self, selections: &BTreeMap<String, String>) -> Result<()> {
+        let json = serde_json::to_string_pretty(selections).map_err(|e| Error::Json {
+            message: "failed to serialize environment selection".to_string(),
+            source: e,
+        })?;
+        write_atomic_string(&self.file, &json)
+    }
+}
+
+/// Directories are compared by their canonical path when it exists
+fn key(dir: &Path) -> String {
+    dir.canonicalize()
+        .unwrap_or_else(|_| dir.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_is_per_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        let selection = EnvironmentSelection::new(temp.path().join("environments.json"));
+        let project = temp.path().join("project");
+        let other = temp.path().join("other");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+
+        assert_eq!(selection.get(&project), None);
+        selection.set(&project, "base+gpu").unwrap();
+        assert_eq!(selection.get(&project).as_deref(), Some("base+gpu"));
+        assert_eq!(selection.get(&other), None);
+
+        assert!(selection.clear(&project).unwrap());
+        assert!(!selection.clear(&project).unwrap());
+        assert_eq!(selection.get(&project), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! A loaded environment, separate from the process it may be applied to
+//!
+//! Evaluating a configuration yields an [`EnvSnapshot`]: the variables it
+//! sets over a base environment, and where each came from. Nothing changes
+//! the environment of the process until [`EnvSnapshot::apply`], so several
+//! directories can be evaluated side by side, and a child process can be
+//! given [`EnvSnapshot::to_env`] instead of inheriting from this one.
+
+use cuenv_core::{Error, Result};
+use cuenv_utils::sync::env::SyncEnv;
+use cuenv_utils::FileTimes;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::diff::EnvDiff;
+use crate::manager::environment::watched_files;
+use crate::provenance::{LoadedVariable, VariableSource};
+use crate::state::StateManager;
+
+/// The variables a configuration sets over a base environment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvSnapshot {
+    pub(crate) dir: PathBuf,
+    /// Every env.cue that contributed, from the root down
+    pub(crate) config_files: Vec<PathBuf>,
+    pub(crate) environment: Option<String>,
+    /// How many of the variables are secrets
+    pub(crate) secrets: usize,
+    /// The environment the configuration was evaluated over
+    pub(crate) base: HashMap<String, String>,
+    /// Variables of the base the machine's policy keeps from child processes
+    pub(crate) denied: HashSet<String>,
+    pub(crate) variables: HashMap<String, String>,
+    /// Variables exported by hooks sourcing an environment
+    pub(crate) sourced: HashSet<String>,
+    /// The env.cue file each variable came from
+    pub(crate) files: HashMap<String, PathBuf>,
+    /// The named environment that overrode a variable
+    pub(crate) environments: HashMap<String, String>,
+    /// Path-like variables extended, mapped to their entry separator
+    pub(crate) lists: HashMap<String, String>,
+}
+
+impl EnvSnapshot {
+    /// The directory whose configuration was evaluated
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The named environment that was selected, if any
+    pub fn environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    /// The variables the configuration sets
+    pub fn variables(&self) -> &HashMap<String, String> {
+        &self.variables
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// Where the variable `name` came from, if the configuration sets it
+    pub fn source(&self, name: &str) -> Option<VariableSource> {
+        match self.files.get(name) {
+            Some(file) => {
+                let environment = self.environments.get(name).map(String::as_str);
+                Some(VariableSource::locate(file, name, environment))
+            }
+            None if self.sourced.contains(name) => Some(VariableSource::Hook),
+            None => None,
+        }
+    }
+
+    /// The variables the configuration sets with their sources, sorted by
+    /// name
+    pub fn loaded_variables(&self) -> Vec<LoadedVariable> {
+        let mut loaded: Vec<LoadedVariable> = self
+            .variables
+            .iter()
+            .map(|(name, value)| LoadedVariable {
+                name: name.clone(),
+                value: value.clone(),
+                source: self.source(name),
+                changed: self.base.get(name) != Some(value),
+            })
+            .collect();
+        loaded.sort_by(|a, b| a.name.cmp(&b.name));
+        loaded
+    }
+
+    /// The complete environment of a process started in this snapshot: the
+    /// base without the variables the policy denies, and those the
+    /// configuration sets
+    pub fn to_env(&self) -> HashMap<String, String> {
+        let mut env: HashMap<String, String> = self
+            .base
+            .iter()
+            .filter(|(name, _)| !self.denied.contains(*name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        env.extend(self.variables.clone());
+        env
+    }
+
+    /// The snapshot over the environment `base` instead, as when the
+    /// same variables are applied to another shell
+    pub fn rebase(mut self, base: HashMap<String, String>) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// What applying the snapshot changes, compared with the base
+    pub fn diff(&self) -> EnvDiff {
+        let mut next = self.base.clone();
+        next.extend(self.variables.clone());
+        EnvDiff::new(self.base.clone(), next)
+            .compact()
+            .with_lists(self.lists.clone())
+    }
+
+    /// Set the variables in the environment of this process, and record
+    /// them as the loaded state so that unloading restores the base
+    pub async fn apply(&self) -> Result<()> {
+        for name in &self.denied {
+            SyncEnv::remove_var(name).map_err(|e| Error::Configuration {
+                message: format!("Failed to remove environment variable: {e}"),
+            })?;
+        }
+        for (key, value) in &self.variables {
+            tracing::debug!("Setting {key}={value}");
+            SyncEnv::set_var(key, value).map_err(|e| Error::Configuration {
+                message: format!("Failed to set environment variable: {e}"),
+            })?;
+        }
+
+        // Watch every CUE file that contributed, so edits to imported files reload too
+        let mut watches = FileTimes::new();
+        for file in watched_files(&self.config_files) {
+            watches.watch(file);
+        }
+
+        let environment = self.environment.as_deref().unwrap_or("default");
+        let capabilities = Vec::new(); // TODO: get actual capabilities from context
+
+        StateManager::load(
+            &self.dir,
+            &self.dir.join("env.cue"),
+            Some(environment),
+            &capabilities,
+            self.secrets,
+            &self.diff(),
+            &watches,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_environment() {
+        let temp = tempfile::tempdir().unwrap();
+        let env_cue = temp.path().join("env.cue");
+        std::fs::write(&env_cue, "env: {\n    DATABASE_URL: \"db\"\n}\n").unwrap();
+        let snapshot = EnvSnapshot {
+            dir: temp.path().to_path_buf(),
+            config_files: vec![env_cue.clone()],
+            environment: None,
+            secrets: 0,
+            base: HashMap::from([
+                ("HOME".to_string(), "/home/user".to_string()),
+                ("AWS_SECRET".to_string(), "hunter2".to_string()),
+                ("PATH".to_string(), "/usr/bin".to_string()),
+            ]),
+            denied: HashSet::from(["AWS_SECRET".to_string()]),
+            variables: HashMap::from([
+                ("DATABASE_URL".to_string(), "db".to_string()),
+                ("IN_NIX_SHELL".to_string(), "impure".to_string()),
+                ("PATH".to_string(), "/usr/bin".to_string()),
+            ]),
+            sourced: HashSet::from(["IN_NIX_SHELL".to_string(), "PATH".to_string()]),
+            files: HashMap::from([("DATABASE_URL".to_string(), env_cue.clone())]),
+            environments: HashMap::new(),
+            lists: HashMap::new(),
+        };
+
+        let loaded = snapshot.loaded_variables();
+        let names: Vec<&str> = loaded.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["DATABASE_URL", "IN_NIX_SHELL", "PATH"]);
+        assert_eq!(
+            loaded[0].source.as_ref().map(ToString::to_string),
+            Some(format!("{}:2", env_cue.display()))
+        );
+        assert_eq!(loaded[1].source, Some(VariableSource::Hook));
+        assert!(!loaded[2].changed);
+
+        let env = snapshot.to_env();
+        assert_eq!(env.get("DATABASE_URL").map(String::as_str), Some("db"));
+        assert_eq!(env.get("HOME").map(String::as_str), Some("/home/user"));
+        assert!(!env.contains_key("AWS_SECRET"));
+
+        let diff = snapshot.diff();
+        let mut changed: Vec<&str> = diff.added_or_changed().into_keys().collect();
+        changed.sort();
+        assert_eq!(changed, ["DATABASE_URL", "IN_NIX_SHELL"]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Environments saved with `cuenv env snapshot save`
+//!
+//! A saved snapshot is the resolved environment of a directory, stored by
+//! name in the user's state directory so that it can be applied again after
+//! a change to the configuration, its profiles or its resolvers went wrong.
+//! Resolved secrets are part of it, so the files and their directory are
+//! only readable by the user. Only the variables the configuration sets are
+//! kept: restoring applies them over the environment of the shell at that
+//! time.
+
+use crate::snapshot::EnvSnapshot;
+use chrono::{DateTime, Utc};
+use cuenv_core::{Error, Result};
+use cuenv_utils::atomic_file::write_private;
+use cuenv_utils::xdg::XdgPaths;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A snapshot as it is stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSnapshot {
+    pub name: String,
+    pub saved_at: DateTime<Utc>,
+    pub snapshot: EnvSnapshot,
+}
+
+/// Snapshots stored by name in a directory
+#[derive(Debug, Clone)]
+pub struct SavedSnapshots {
+    dir: PathBuf,
+}
+
+impl SavedSnapshots {
+    /// Snapshots stored in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The snapshots in the user's cuenv state directory
+    pub fn user() -> Self {
+        Self::new(XdgPaths::state_dir().join("snapshots"))
+    }
+
+    /// Save `snapshot` as `name`, replacing a snapshot of that name
+    pub fn save(&self, name: &str, snapshot: &EnvSnapshot) -> Result<PathBuf> {
+        let path = self.path(name)?;
+        let mut snapshot = snapshot.clone();
+        // The rest of the base is the shell's, not the configuration's
+        snapshot.base.retain(|variable, _| {
+            snapshot.variables.contains_key(variable) || snapshot.denied.contains(variable)
+        });
+        let saved = SavedSnapshot {
+            name: name.to_string(),
+            saved_at: Utc::now(),
+            snapshot,
+        };
+        let json = serde_json::to_string_pretty(&saved).map_err(|e| Error::Json {
+            message: "failed to serialize environment snapshot".to_string(),
+            source: e,
+        })?;
+
+        write_private(&path, json.as_bytes())?;
+        Ok(path)
+    }
+
+    /// The snapshot saved as `name`
+    pub fn load(&self, name: &str) -> Result<SavedSnapshot> {
+        let path = self.path(name)?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::configuration(format!(
+                    "No snapshot named '{name}'; run 'cuenv env snapshot list' to see the saved ones"
+                )))
+            }
+            Err(e) => return Err(Error::file_system(&path, "read environment snapshot", e)),
+        };
+        read_snapshot(&path, &content)
+    }
+
+    /// The saved snapshots, newest first
+    pub fn list(&self) -> Result<Vec<SavedSnapshot>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::file_system(&self.dir, "read snapshot directory", e)),
+        };
+        let mut snapshots: Vec<SavedSnapshot> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(&path).ok()?;
+                read_snapshot(&path, &content)
+                    .inspect_err(|e| tracing::warn!("Skipping snapshot: {e}"))
+                    .ok()
+            })
+            .collect();
+        snapshots.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+        Ok(snapshots)
+    }
+
+    /// The file of the snapshot `name`, which names a file of its own
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(Error::configuration(format!(
+                "Invalid snapshot name {name:?}, expected letters, digits, '-', '_' or '.' such as pre-upgrade"
+            )));
+        }
+        Ok(self.dir.join(format!("{name}.json")))
+    }
+}
+
+fn read_snapshot(path: &Path, content: &str) -> Result<SavedSnapshot> {
+    serde_json::from_str(content).map_err(|e| Error::Json {
+        message: format!("invalid environment snapshot {}", path.display()),
+        source: e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    fn snapshot(dir: &Path) -> EnvSnapshot {
+        EnvSnapshot {
+            dir: dir.to_path_buf(),
+            config_files: vec![dir.join("env.cue")],
+            environment: Some("dev".to_string()),
+            secrets: 0,
+            base: HashMap::from([
+                ("HOME".to_string(), "/home/user".to_string()),
+                ("DATABASE_URL".to_string(), "old".to_string()),
+            ]),
+            denied: HashSet::new(),
+            variables: HashMap::from([("DATABASE_URL".to_string(), "db".to_string())]),
+            sourced: HashSet::new(),
+            files: HashMap::new(),
+            environments: HashMap::new(),
+            lists: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_snapshots_are_saved_by_name() {
+        let temp = tempfile::tempdir().unwrap();
+        let snapshots = SavedSnapshots::new(temp.path().join("snapshots"));
+        let snapshot = snapshot(temp.path());
+
+        assert!(snapshots.load("pre-upgrade").is_err());
+        snapshots.save("pre-upgrade", &snapshot).unwrap();
+        assert!(snapshots.save("../escape", &snapshot).is_err());
+
+        let saved = snapshots.load("pre-upgrade").unwrap();
+        assert_eq!(saved.name, "pre-upgrade");
+        assert_eq!(saved.snapshot.get("DATABASE_URL"), Some("db"));
+        assert_eq!(saved.snapshot.environment(), Some("dev"));
+        // Only the base of the variables the configuration sets is kept
+        assert!(!saved.snapshot.base.contains_key("HOME"));
+        assert_eq!(snapshots.list().unwrap().len(), 1);
+
+        let rebased = saved.snapshot.rebase(HashMap::from([(
+            "HOME".to_string(),
+            "/home/other".to_string(),
+        )]));
+        assert_eq!(
+            rebased.to_env().get("HOME").map(String::as_str),
+            Some("/home/other")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_snapshots_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("state").join("snapshots");
+        let snapshots = SavedSnapshots::new(&dir);
+        let path = snapshots.save("secrets", &snapshot(temp.path())).unwrap();
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(mode(&temp.path().join("state")), 0o700);
+    }
+}
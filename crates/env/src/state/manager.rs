@@ -21,6 +21,9 @@ pub struct CuenvState {
     pub environment: Option<String>,
     /// The capabilities that were loaded
     pub capabilities: Vec<String>,
+    /// How many of the loaded variables are secrets
+    #[serde(default)]
+    pub secrets: usize,
 }
 
 /// Represents a snapshot of environment variables for rollback
@@ -153,6 +156,21 @@ impl StateManager {
         ]
     }
 
+    /// State variables with their current values, `None` when unset
+    ///
+    /// The shell hook runs as a child process, so it must export these to the
+    /// shell for the next invocation to know what is loaded and what to watch.
+    pub fn exported_vars() -> Vec<(String, Option<String>)> {
+        let _guard = STATE_LOCK.read().ok();
+        Self::state_var_names()
+            .into_iter()
+            .map(|name| {
+                let value = SyncEnv::var(&name).unwrap_or_default();
+                (name, value)
+            })
+            .collect()
+    }
+
     /// Check if an environment is currently loaded
     pub fn is_loaded() -> bool {
         let _guard = STATE_LOCK.read().ok();
@@ -207,6 +225,7 @@ impl StateManager {
         file: &Path,
         environment: Option<&str>,
         capabilities: &[String],
+        secrets: usize,
     ) -> Result<()> {
         // Log environment state change
         if let Some(logger) = audit_logger() {
@@ -230,6 +249,7 @@ impl StateManager {
             file: file.to_path_buf(),
             environment: environment.map(str::to_string),
             capabilities: capabilities.to_vec(),
+            secrets,
         };
 
         Self::encode_and_store(
@@ -273,6 +293,7 @@ impl StateManager {
         file: &Path,
         environment: Option<&str>,
         capabilities: &[String],
+        secrets: usize,
         diff: &EnvDiff,
         watches: &FileTimes,
     ) -> Result<()> {
@@ -280,7 +301,15 @@ impl StateManager {
         let mut transaction = StateTransaction::new(&Self::state_var_names())?;
 
         // Store all state components (this includes async logging)
-        Self::store_state(&mut transaction, dir, file, environment, capabilities).await?;
+        Self::store_state(
+            &mut transaction,
+            dir,
+            file,
+            environment,
+            capabilities,
+            secrets,
+        )
+        .await?;
         Self::store_metadata(&mut transaction, diff, watches)?;
 
         // Now acquire the lock and commit
@@ -343,6 +372,17 @@ impl StateManager {
         Ok(())
     }
 
+    /// Undo the loaded environment and clear the state
+    ///
+    /// Variables cuenv overrode get back their exact prior values and
+    /// variables it added are removed, as recorded in the stored diff.
+    pub async fn restore_and_unload() -> Result<()> {
+        if let Some(diff) = Self::get_diff()? {
+            diff.restore().context("Failed to restore environment")?;
+        }
+        Self::unload().await
+    }
+
     /// Get the current state
     pub fn get_state() -> Result<Option<CuenvState>> {
         // Don't acquire lock here to avoid deadlock when called from within locked methods
@@ -464,6 +504,7 @@ mod tests {
             &file,
             Some("dev"),
             &["cap1".to_string()],
+            0,
             &diff,
             &watches,
         )
@@ -502,7 +543,7 @@ mod tests {
 
         // Create a transaction and don't commit
         {
-            let mut transaction = StateTransaction::new(&[test_key.clone()]).unwrap();
+            let mut transaction = StateTransaction::new(std::slice::from_ref(&test_key)).unwrap();
             transaction.set_var(&test_key, "modified");
 
             // Apply changes
@@ -538,7 +579,7 @@ mod tests {
 
         // Create a transaction and commit it
         {
-            let mut transaction = StateTransaction::new(&[test_key.clone()]).unwrap();
+            let mut transaction = StateTransaction::new(std::slice::from_ref(&test_key)).unwrap();
             transaction.set_var(&test_key, "committed");
             transaction.commit().unwrap();
         }
@@ -618,6 +659,7 @@ mod tests {
             &file,
             Some("dev"),
             &["cap1".to_string()],
+            0,
             &diff,
             &watches,
         )
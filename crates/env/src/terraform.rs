@@ -0,0 +1,298 @@
+//! Variables read from the outputs of Terraform and OpenTofu modules
+//!
+//! A value declared as
+//! `DB_HOST: { fromTerraform: { dir: "infra/", output: "db_endpoint" } }`
+//! is the output of `terraform output -json` in the module's directory.
+//! The outputs of a module are cached until its local state changes, so
+//! they are only read again after an apply. With remote state there is no
+//! local state to tell a change by, and the outputs are read every time the
+//! environment loads. Sensitive outputs are masked like secrets.
+
+use crate::value_cache;
+use cuenv_config::TerraformValue;
+use cuenv_core::{masking, Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Programs reading the outputs, in the order they are looked for
+const BINARIES: &[&str] = &["terraform", "tofu"];
+
+/// Files of a module selecting its workspace and backend, which change
+/// which state the outputs come from
+const SELECTION_FILES: &[&str] = &[".terraform/environment", ".terraform/terraform.tfstate"];
+
+#[derive(Serialize, Deserialize)]
+struct CachedOutputs {
+    state_hash: String,
+    outputs: HashMap<String, Output>,
+}
+
+/// An output as `output -json` reports it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Output {
+    value: serde_json::Value,
+    #[serde(default)]
+    sensitive: bool,
+}
+
+/// On-disk cache of module outputs
+pub struct TerraformCache {
+    dir: PathBuf,
+    /// Outputs read during this load, by module directory and binary
+    loaded: HashMap<(PathBuf, String), HashMap<String, Output>>,
+}
+
+impl TerraformCache {
+    /// Create a cache storing its entries in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            loaded: HashMap::new(),
+        }
+    }
+
+    /// The cache in the user's cache directory
+    pub fn user_cache() -> Self {
+        Self::new(value_cache::user_cache_dir("terraform"))
+    }
+
+    /// Produce the value of `name` from its module's outputs, reading them
+    /// unless they are cached for the current state
+    pub fn evaluate(&mut self, name: &str, value: &TerraformValue) -> Result<String> {
+        let dir = value.module_dir();
+        let binary = value
+            .from_terraform
+            .binary
+            .clone()
+            .unwrap_or_else(default_binary);
+        let key = (dir.clone(), binary.clone());
+        if !self.loaded.contains_key(&key) {
+            let outputs = self.outputs(name, &dir, &binary)?;
+            self.loaded.insert(key.clone(), outputs);
+        }
+
+        let output_name = &value.from_terraform.output;
+        let Some(output) = self.loaded[&key].get(output_name) else {
+            let mut names: Vec<&String> = self.loaded[&key].keys().collect();
+            names.sort();
+            return Err(Error::environment(
+                name,
+                format!(
+                    "{} has no output {output_name:?}; its outputs are {names:?}",
+                    dir.display()
+                ),
+            ));
+        };
+        let value = match &output.value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        if output.sensitive {
+            masking::register_secret(value.clone());
+        }
+        Ok(value)
+    }
+
+    fn outputs(&self, name: &str, dir: &Path, binary: &str) -> Result<HashMap<String, Output>> {
+        let state_hash = hash_state(dir);
+        let entry = self.entry_path(dir, binary);
+
+        if let Some(state_hash) = &state_hash {
+            if let Some(cached) = value_cache::read_entry::<CachedOutputs>(&entry) {
+                if &cached.state_hash == state_hash {
+                    tracing::debug!("Using cached outputs of {}", dir.display());
+                    return Ok(cached.outputs);
+                }
+            }
+        }
+
+        let outputs = run(name, dir, binary)?;
+        if let Some(state_hash) = state_hash {
+            let cached = CachedOutputs {
+                state_hash,
+                outputs: outputs.clone(),
+            };
+            if let Err(e) = value_cache::write_entry(&entry, &cached) {
+                tracing::warn!("Failed to cache outputs of {}: {e}", dir.display());
+            }
+        }
+        Ok(outputs)
+    }
+
+    fn entry_path(&self, dir: &Path, binary: &str) -> PathBuf {
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut hasher = Sha256::new();
+        hasher.update(canonical.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(binary.as_bytes());
+        self.dir.join(format!("{:x}.json", hasher.finalize()))
+    }
+}
+
+/// `terraform` when it is installed, else `tofu` when that is
+fn default_binary() -> String {
+    BINARIES
+        .iter()
+        .find(|binary| which::which(binary).is_ok())
+        .unwrap_or(&BINARIES[0])
+        .to_string()
+}
+
+fn run(name: &str, dir: &Path, binary: &str) -> Result<HashMap<String, Output>> {
+    tracing::info!("Reading the outputs of {}", dir.display());
+    let output = Command::new(binary)
+        .args(["output", "-json"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| {
+            Error::environment(
+                name,
+                format!("failed to run {binary} output in {}: {e}", dir.display()),
+            )
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::environment(
+            name,
+            format!(
+                "{binary} output in {} failed with {}: {}",
+                dir.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(|e| {
+        Error::environment(
+            name,
+            format!("could not read the output of {binary} output: {e}"),
+        )
+    })
+}
+
+/// A hash of the module's local state, or `None` when the state is remote
+fn hash_state(dir: &Path) -> Option<String> {
+    let workspace = std::fs::read_to_string(dir.join(".terraform/environment")).ok();
+    let state = match workspace.as_deref().map(str::trim) {
+        Some(workspace) if workspace != "default" => dir
+            .join("terraform.tfstate.d")
+            .join(workspace)
+            .join("terraform.tfstate"),
+        _ => dir.join("terraform.tfstate"),
+    };
+    let content = std::fs::read(&state).ok()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&content);
+    for file in SELECTION_FILES {
+        hasher.update(file.as_bytes());
+        match std::fs::read(dir.join(file)) {
+            Ok(content) => {
+                hasher.update([1]);
+                hasher.update(&content);
+            }
+            Err(_) => hasher.update([0]),
+        }
+    }
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use cuenv_config::TerraformOutput;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// A module whose `fake-terraform` counts its runs and reports the
+    /// number as the output `runs`
+    fn module() -> TempDir {
+        let module = TempDir::new().unwrap();
+        let binary = module.path().join("fake-terraform");
+        std::fs::write(
+            &binary,
+            "#!/bin/sh\necho run >> runs\nprintf '{\"runs\": {\"value\": %s}, \"url\": {\"value\": \"db:5432\", \"sensitive\": true}}' $(wc -l < runs)\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        module
+    }
+
+    fn value(module: &Path, output: &str) -> TerraformValue {
+        TerraformValue {
+            from_terraform: TerraformOutput {
+                output: output.to_string(),
+                dir: ".".to_string(),
+                binary: Some(module.join("fake-terraform").to_string_lossy().into_owned()),
+                base: None,
+            },
+        }
+        .declared_in(module)
+    }
+
+    #[test]
+    fn test_outputs_are_cached_on_state() {
+        let module = module();
+        let cache_dir = module.path().join("cache");
+        std::fs::write(module.path().join("terraform.tfstate"), "v1").unwrap();
+        let runs = value(module.path(), "runs");
+
+        assert_eq!(
+            TerraformCache::new(&cache_dir)
+                .evaluate("RUNS", &runs)
+                .unwrap(),
+            "1"
+        );
+        assert_eq!(
+            TerraformCache::new(&cache_dir)
+                .evaluate("RUNS", &runs)
+                .unwrap(),
+            "1"
+        );
+
+        std::fs::write(module.path().join("terraform.tfstate"), "v2").unwrap();
+        let mut cache = TerraformCache::new(&cache_dir);
+        assert_eq!(cache.evaluate("RUNS", &runs).unwrap(), "2");
+        // Outputs of the same module are read once per load
+        let url = value(module.path(), "url");
+        assert_eq!(cache.evaluate("DB_URL", &url).unwrap(), "db:5432");
+        assert_eq!(cache.evaluate("RUNS", &runs).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_remote_state_is_read_every_time() {
+        let module = module();
+        let cache_dir = module.path().join("cache");
+        let runs = value(module.path(), "runs");
+
+        assert_eq!(
+            TerraformCache::new(&cache_dir)
+                .evaluate("RUNS", &runs)
+                .unwrap(),
+            "1"
+        );
+        assert_eq!(
+            TerraformCache::new(&cache_dir)
+                .evaluate("RUNS", &runs)
+                .unwrap(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn test_missing_output_names_variable() {
+        let module = module();
+        let mut cache = TerraformCache::new(module.path().join("cache"));
+
+        let message = cache
+            .evaluate("DB_HOST", &value(module.path(), "db_endpoint"))
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("DB_HOST"), "{message}");
+        assert!(message.contains("\"runs\""), "{message}");
+    }
+}
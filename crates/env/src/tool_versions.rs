@@ -0,0 +1,319 @@
+//! Runtime versions pinned for asdf and mise
+//!
+//! The versions of the `.tool-versions` and `mise.toml` files in a directory
+//! and above it are resolved to the directories mise or asdf installed them
+//! in, whose `bin` directories go in front of PATH when the environment
+//! loads. The pinned versions then run whether the version manager's shell
+//! hook ran before cuenv's or after it. The versions are exported as
+//! `CUENV_TOOL_VERSIONS`, which is part of the task cache keys.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Variable listing the resolved versions, as `node@20.11.1 python@3.12.1`
+pub const TOOL_VERSIONS_VAR: &str = "CUENV_TOOL_VERSIONS";
+
+/// Files pinning tool versions, from the one taking precedence in a
+/// directory
+pub const TOOL_VERSION_FILES: &[&str] = &[
+    ".mise.local.toml",
+    "mise.local.toml",
+    ".mise.toml",
+    "mise.toml",
+    ".tool-versions",
+];
+
+/// Names asdf plugins have where mise uses another
+const ALIASES: &[(&str, &str)] = &[("nodejs", "node"), ("golang", "go")];
+
+/// Directories of an install holding its executables, other than the
+/// install directory itself
+const BIN_DIRS: &[&str] = &["bin", "go/bin"];
+
+/// A tool and the versions pinned for it, the preferred one first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolVersion {
+    pub tool: String,
+    pub versions: Vec<String>,
+}
+
+/// The tools pinned for `dir`, each by the file closest to it, sorted by
+/// name
+pub fn pinned_tools(dir: &Path) -> Vec<ToolVersion> {
+    let mut tools: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for dir in dir.ancestors() {
+        for name in TOOL_VERSION_FILES {
+            let path = dir.join(name);
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let pinned = if *name == ".tool-versions" {
+                parse_tool_versions(&content)
+            } else {
+                match parse_mise_toml(&content) {
+                    Ok(pinned) => pinned,
+                    Err(e) => {
+                        tracing::warn!("Ignoring the tools of {}: {e}", path.display());
+                        continue;
+                    }
+                }
+            };
+            for tool in pinned {
+                tools.entry(tool.tool).or_insert(tool.versions);
+            }
+        }
+    }
+    tools
+        .into_iter()
+        .map(|(tool, versions)| ToolVersion { tool, versions })
+        .collect()
+}
+
+/// The lines of a `.tool-versions` file, `<tool> <version>...`
+fn parse_tool_versions(content: &str) -> Vec<ToolVersion> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let tool = words.next()?.to_string();
+            let versions: Vec<String> = words.map(str::to_string).collect();
+            (!versions.is_empty()).then_some(ToolVersion { tool, versions })
+        })
+        .collect()
+}
+
+/// The `[tools]` of a mise.toml, whose versions are a string, a list of
+/// them or a table with a `version`
+fn parse_mise_toml(content: &str) -> Result<Vec<ToolVersion>, toml::de::Error> {
+    let config: toml::Table = toml::from_str(content)?;
+    let Some(tools) = config.get("tools").and_then(toml::Value::as_table) else {
+        return Ok(Vec::new());
+    };
+    Ok(tools
+        .iter()
+        .filter_map(|(tool, value)| {
+            let version = |value: &toml::Value| match value {
+                toml::Value::String(version) => Some(version.clone()),
+                toml::Value::Table(table) => table
+                    .get("version")
+                    .and_then(toml::Value::as_str)
+                    .map(str::to_string),
+                _ => None,
+            };
+            let versions: Vec<String> = match value {
+                toml::Value::Array(values) => values.iter().filter_map(version).collect(),
+                value => version(value).into_iter().collect(),
+            };
+            (!versions.is_empty()).then(|| ToolVersion {
+                tool: tool.clone(),
+                versions,
+            })
+        })
+        .collect())
+}
+
+/// The tools pinned for a directory, as they are installed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ResolvedTools {
+    /// Directories to put in front of PATH, in order
+    pub path: Vec<PathBuf>,
+    /// The installed versions, as `tool@version` separated by spaces
+    pub versions: String,
+    /// The `tool@version`s that are not installed
+    pub missing: Vec<String>,
+}
+
+/// Where mise and asdf install tools
+pub struct Installs {
+    roots: Vec<PathBuf>,
+}
+
+impl Installs {
+    /// Installs in the `installs` directories `roots`, in order
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        Self { roots }
+    }
+
+    /// The installs of mise and then of asdf for the current user
+    pub fn user() -> Self {
+        let home = dirs::home_dir().unwrap_or_default();
+        let var = |name: &str| std::env::var_os(name).map(PathBuf::from);
+        let mise = var("MISE_DATA_DIR").unwrap_or_else(|| {
+            var("XDG_DATA_HOME")
+                .unwrap_or_else(|| home.join(".local/share"))
+                .join("mise")
+        });
+        let asdf = var("ASDF_DATA_DIR").unwrap_or_else(|| home.join(".asdf"));
+        Self::new(vec![mise.join("installs"), asdf.join("installs")])
+    }
+
+    /// The installed version a pinned version resolves to and its
+    /// directory: the version itself, or the highest one it is a prefix of
+    pub fn find(&self, tool: &str, version: &str) -> Option<(String, PathBuf)> {
+        if let Some(dir) = version.strip_prefix("path:") {
+            return Some((version.to_string(), PathBuf::from(dir)));
+        }
+        let alias = ALIASES
+            .iter()
+            .find(|(from, to)| *from == tool || *to == tool)
+            .map(|(from, to)| if *from == tool { *to } else { *from });
+        let names = std::iter::once(tool).chain(alias);
+
+        for root in &self.roots {
+            for name in names.clone() {
+                // mise installs `npm:prettier` as `npm-prettier`
+                let tool_dir = root.join(name.replace([':', '/'], "-"));
+                let exact = tool_dir.join(version);
+                if exact.is_dir() {
+                    // mise links `20` to the `20.11.1` it installed
+                    let resolved = std::fs::canonicalize(&exact)
+                        .ok()
+                        .and_then(|dir| Some(dir.file_name()?.to_string_lossy().into_owned()))
+                        .unwrap_or_else(|| version.to_string());
+                    return Some((resolved, exact));
+                }
+
+                let Ok(entries) = std::fs::read_dir(&tool_dir) else {
+                    continue;
+                };
+                let highest = entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .filter(|installed| {
+                        version == "latest" || installed.starts_with(&format!("{version}."))
+                    })
+                    .max_by(|a, b| version_key(a).cmp(&version_key(b)));
+                if let Some(installed) = highest {
+                    let dir = tool_dir.join(&installed);
+                    return Some((installed, dir));
+                }
+            }
+        }
+        None
+    }
+
+    /// The pinned tools with the directories of their executables
+    pub fn resolve(&self, tools: &[ToolVersion]) -> ResolvedTools {
+        let mut resolved = ResolvedTools::default();
+        let mut versions = Vec::new();
+        for tool in tools {
+            for version in &tool.versions {
+                // Left to whatever is on PATH already
+                if version == "system" {
+                    continue;
+                }
+                let Some((installed, dir)) = self.find(&tool.tool, version) else {
+                    resolved.missing.push(format!("{}@{version}", tool.tool));
+                    continue;
+                };
+                let bins: Vec<PathBuf> = BIN_DIRS
+                    .iter()
+                    .map(|bin| dir.join(bin))
+                    .filter(|bin| bin.is_dir())
+                    .collect();
+                if bins.is_empty() {
+                    resolved.path.push(dir);
+                } else {
+                    resolved.path.extend(bins);
+                }
+                versions.push(format!("{}@{installed}", tool.tool));
+            }
+        }
+        resolved.versions = versions.join(" ");
+        resolved
+    }
+}
+
+/// A version's parts for ordering, numbers by their value
+fn version_key(version: &str) -> Vec<Result<u64, String>> {
+    version
+        .split(['.', '-', '+'])
+        .map(|part| part.parse().map_err(|_| part.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pinned_tools() {
+        let root = TempDir::new().unwrap();
+        let app = root.path().join("app");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(
+            root.path().join(".tool-versions"),
+            "# runtimes\nnodejs 18.19.0\npython 3.12.1 2.7.18 # legacy\nterraform\n",
+        )
+        .unwrap();
+        std::fs::write(
+            app.join("mise.toml"),
+            "[tools]\nnodejs = \"20\"\ngo = [\"1.22\", \"1.21\"]\nrust = { version = \"1.77\" }\n",
+        )
+        .unwrap();
+        std::fs::write(app.join(".tool-versions"), "go 1.20.0\n").unwrap();
+
+        let tools = pinned_tools(&app);
+        let pinned: Vec<(&str, Vec<&str>)> = tools
+            .iter()
+            .map(|tool| {
+                let versions = tool.versions.iter().map(String::as_str).collect();
+                (tool.tool.as_str(), versions)
+            })
+            .collect();
+        assert_eq!(
+            pinned,
+            [
+                ("go", vec!["1.22", "1.21"]),
+                ("nodejs", vec!["20"]),
+                ("python", vec!["3.12.1", "2.7.18"]),
+                ("rust", vec!["1.77"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let installs = TempDir::new().unwrap();
+        let mise = installs.path().join("mise");
+        let asdf = installs.path().join("asdf");
+        for dir in [
+            mise.join("node/20.9.0/bin"),
+            mise.join("node/20.11.1/bin"),
+            mise.join("node/21.0.0/bin"),
+            asdf.join("golang/1.22.1/go/bin"),
+            mise.join("npm-prettier/3.2.5"),
+        ] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let installs = Installs::new(vec![mise.clone(), asdf.clone()]);
+
+        let tool = |tool: &str, version: &str| ToolVersion {
+            tool: tool.to_string(),
+            versions: vec![version.to_string()],
+        };
+        let resolved = installs.resolve(&[
+            tool("go", "1.22.1"),
+            tool("nodejs", "20"),
+            tool("npm:prettier", "latest"),
+            tool("python", "3.12.1"),
+            tool("ruby", "system"),
+        ]);
+        assert_eq!(
+            resolved.path,
+            [
+                asdf.join("golang/1.22.1/go/bin"),
+                mise.join("node/20.11.1/bin"),
+                mise.join("npm-prettier/3.2.5"),
+            ]
+        );
+        assert_eq!(
+            resolved.versions,
+            "go@1.22.1 nodejs@20.11.1 npm:prettier@3.2.5"
+        );
+        assert_eq!(resolved.missing, ["python@3.12.1"]);
+    }
+}
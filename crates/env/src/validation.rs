@@ -0,0 +1,230 @@
+//! Validation of environment variables against declared constraints
+//!
+//! Constraints are declared next to the environment in env.cue:
+//!
+//! ```cue
+//! constraints: {
+//!     DATABASE_URL: { required: true, pattern: "^postgres://" }
+//!     LOG_LEVEL: oneOf: ["debug", "info", "warn", "error"]
+//! }
+//! ```
+//!
+//! Validation runs while the environment is loaded, so a misconfigured
+//! variable is reported by name before any task or command starts.
+
+use crate::manager::secrets::is_secret_reference;
+use cuenv_config::VariableConstraint;
+use cuenv_core::{Error, Result};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Check every constrained variable, failing on the first violation
+///
+/// Variables missing from `variables` are looked up in `fallback`, so a
+/// required variable may also be provided by the surrounding environment.
+/// Unresolved secret references only have to satisfy `required`, since
+/// their value is not known until they are resolved.
+pub fn validate_variables(
+    variables: &HashMap<String, String>,
+    constraints: &HashMap<String, VariableConstraint>,
+    fallback: &HashMap<String, String>,
+) -> Result<()> {
+    // Sort the names so the reported violation is deterministic
+    let mut names: Vec<&String> = constraints.keys().collect();
+    names.sort();
+
+    for name in names {
+        let constraint = &constraints[name];
+        let value = variables.get(name).or_else(|| fallback.get(name));
+        check_constraint(name, value.map(String::as_str), constraint)?;
+    }
+
+    Ok(())
+}
+
+fn check_constraint(
+    name: &str,
+    value: Option<&str>,
+    constraint: &VariableConstraint,
+) -> Result<()> {
+    let Some(value) = value else {
+        return if constraint.required.unwrap_or(false) {
+            Err(Error::environment(
+                name,
+                "required variable is not set (constraint: required: true)",
+            ))
+        } else {
+            Ok(())
+        };
+    };
+
+    if is_secret_reference(value) {
+        return Ok(());
+    }
+
+    if let Some(pattern) = &constraint.pattern {
+        let regex = Regex::new(pattern).map_err(|e| {
+            Error::environment(name, format!("invalid constraint pattern {pattern:?}: {e}"))
+        })?;
+        if !regex.is_match(value) {
+            return Err(Error::environment(
+                name,
+                format!("value does not match constraint pattern: {pattern:?}"),
+            ));
+        }
+    }
+
+    if let Some(allowed) = &constraint.one_of {
+        if !allowed.iter().any(|candidate| candidate == value) {
+            return Err(Error::environment(
+                name,
+                format!(
+                    "value {value:?} is not allowed by constraint oneOf: [{}]",
+                    allowed
+                        .iter()
+                        .map(|candidate| format!("{candidate:?}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn constraint(
+        name: &str,
+        constraint: VariableConstraint,
+    ) -> HashMap<String, VariableConstraint> {
+        HashMap::from([(name.to_string(), constraint)])
+    }
+
+    #[test]
+    fn test_required_variable_missing() {
+        let constraints = constraint(
+            "DATABASE_URL",
+            VariableConstraint {
+                required: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let err = validate_variables(&HashMap::new(), &constraints, &HashMap::new()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("DATABASE_URL"), "{message}");
+        assert!(message.contains("required"), "{message}");
+    }
+
+    #[test]
+    fn test_required_variable_from_fallback() {
+        let constraints = constraint(
+            "HOME",
+            VariableConstraint {
+                required: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let fallback = vars(&[("HOME", "/home/user")]);
+        assert!(validate_variables(&HashMap::new(), &constraints, &fallback).is_ok());
+    }
+
+    #[test]
+    fn test_pattern_constraint() {
+        let constraints = constraint(
+            "DATABASE_URL",
+            VariableConstraint {
+                pattern: Some("^postgres://".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let valid = vars(&[("DATABASE_URL", "postgres://localhost/app")]);
+        assert!(validate_variables(&valid, &constraints, &HashMap::new()).is_ok());
+
+        let invalid = vars(&[("DATABASE_URL", "mysql://localhost/app")]);
+        let message = validate_variables(&invalid, &constraints, &HashMap::new())
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("DATABASE_URL"), "{message}");
+        assert!(message.contains("^postgres://"), "{message}");
+    }
+
+    #[test]
+    fn test_one_of_constraint() {
+        let constraints = constraint(
+            "LOG_LEVEL",
+            VariableConstraint {
+                one_of: Some(vec!["debug".to_string(), "info".to_string()]),
+                ..Default::default()
+            },
+        );
+
+        let valid = vars(&[("LOG_LEVEL", "info")]);
+        assert!(validate_variables(&valid, &constraints, &HashMap::new()).is_ok());
+
+        let invalid = vars(&[("LOG_LEVEL", "verbose")]);
+        let message = validate_variables(&invalid, &constraints, &HashMap::new())
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("oneOf"), "{message}");
+        assert!(message.contains("\"verbose\""), "{message}");
+    }
+
+    #[test]
+    fn test_optional_missing_variable_is_valid() {
+        let constraints = constraint(
+            "OPTIONAL",
+            VariableConstraint {
+                pattern: Some("^x".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert!(validate_variables(&HashMap::new(), &constraints, &HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn test_secret_references_skip_value_checks() {
+        let constraints = constraint(
+            "API_KEY",
+            VariableConstraint {
+                required: Some(true),
+                pattern: Some("^sk_".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let variables = vars(&[("API_KEY", r#"cuenv-resolver://{"cmd":"op","args":[]}"#)]);
+        assert!(validate_variables(&variables, &constraints, &HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_reported() {
+        let constraints = constraint(
+            "NAME",
+            VariableConstraint {
+                pattern: Some("(".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let variables = vars(&[("NAME", "value")]);
+        let message = validate_variables(&variables, &constraints, &HashMap::new())
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("invalid constraint pattern"), "{message}");
+    }
+}
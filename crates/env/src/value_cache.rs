@@ -0,0 +1,55 @@
+//! Entries of the on-disk caches of computed values
+//!
+//! The outputs of `fromCommand` and `fromTerraform` values and the variables
+//! of Nix shells are cached under the user's cache directory, one JSON file
+//! an entry. They can hold credentials, so entries are written readable by
+//! the user only, replacing any entry written before atomically.
+
+use cuenv_core::Result;
+use cuenv_utils::atomic_file::write_private;
+use cuenv_utils::xdg::XdgPaths;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// The directory of the cache `name` in the user's cache directory
+pub(crate) fn user_cache_dir(name: &str) -> PathBuf {
+    XdgPaths::cache_dir().join(name)
+}
+
+/// The entry at `path`, unless it is missing or unreadable
+pub(crate) fn read_entry<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = std::fs::read(path).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Write `entry` to `path`, readable by the current user only
+pub(crate) fn write_entry<T: Serialize>(path: &Path, entry: &T) -> Result<()> {
+    write_private(path, &serde_json::to_vec(entry)?)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_entries_are_private() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cache").join("entry.json");
+        // An entry written before with the default mode
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "{}").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_entry(&path, &vec!["secret".to_string()]).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(
+            read_entry::<Vec<String>>(&path).unwrap(),
+            ["secret".to_string()]
+        );
+    }
+}
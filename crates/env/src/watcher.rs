@@ -1,3 +1,4 @@
+use cuenv_config::UserConfig;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
@@ -152,10 +153,9 @@ pub fn default_watch_files(project_dir: &Path) -> Vec<PathBuf> {
     }
 
     // Add home config files
+    files.push(UserConfig::path());
     if let Ok(home) = std::env::var("HOME") {
-        let home_path = Path::new(&home);
-        files.push(home_path.join(".config/cuenv/config.cue"));
-        files.push(home_path.join(".cuenvrc"));
+        files.push(Path::new(&home).join(".cuenvrc"));
     }
 
     // Filter to only existing files
@@ -4,9 +4,11 @@
 //! - Hook execution with constraints
 //! - Nix integration
 //! - Environment setup and teardown
+//! - Notifications when a task run finishes
 
 pub mod manager;
 pub mod nix_executor;
+pub mod notify;
 
 pub use manager::*;
 pub use nix_executor::*;
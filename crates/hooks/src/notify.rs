@@ -0,0 +1,226 @@
+//! Notifications when a task run finishes
+//!
+//! `notify.onFailure`, `notify.onSuccess` and `notify.onComplete` in env.cue
+//! run a command with a JSON description of the run on stdin, POST it to a
+//! webhook, or both. The payload has a `text` line, so Slack and similar
+//! incoming webhooks accept it as it is. A notification that cannot be sent
+//! is a warning; it never changes the outcome of the run.
+
+use anyhow::{anyhow, Result};
+use cuenv_config::{Notification, NotifyConfig};
+use cuenv_task::RunSummary;
+use cuenv_utils::tracing::{message, Level};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+/// How long a webhook may take to answer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What notifications say about a finished run
+#[derive(Debug, Clone, Serialize)]
+pub struct RunOutcome {
+    /// The task or group that was run
+    pub task: String,
+    pub directory: PathBuf,
+    pub run_id: String,
+    /// The exit code, unless the run failed before its tasks finished
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// The tasks of the run, if they ran in this process
+    pub summary: Option<RunSummary>,
+}
+
+impl RunOutcome {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// One line for chat messages and desktop notifications
+    pub fn text(&self) -> String {
+        let seconds = self.duration_ms as f64 / 1000.0;
+        match self.exit_code {
+            Some(0) => format!("✓ cuenv task '{}' succeeded in {seconds:.1}s", self.task),
+            Some(code) => format!(
+                "✗ cuenv task '{}' failed with exit code {code} after {seconds:.1}s",
+                self.task
+            ),
+            None => format!("✗ cuenv task '{}' failed after {seconds:.1}s", self.task),
+        }
+    }
+}
+
+/// The JSON a notification sends
+#[derive(Serialize)]
+struct Payload<'a> {
+    /// `success` or `failure`
+    event: &'static str,
+    success: bool,
+    text: String,
+    #[serde(flatten)]
+    outcome: &'a RunOutcome,
+}
+
+/// Send the notifications `config` asks for after `outcome`
+pub async fn send(config: &NotifyConfig, outcome: &RunOutcome) {
+    let on_outcome = if outcome.success() {
+        &config.on_success
+    } else {
+        &config.on_failure
+    };
+    let notifications = [on_outcome.as_ref(), config.on_complete.as_ref()];
+    if notifications.iter().all(Option::is_none) {
+        return;
+    }
+
+    let payload = match serde_json::to_string(&Payload {
+        event: if outcome.success() {
+            "success"
+        } else {
+            "failure"
+        },
+        success: outcome.success(),
+        text: outcome.text(),
+        outcome,
+    }) {
+        Ok(payload) => payload,
+        Err(e) => {
+            message(
+                Level::WARN,
+                &format!("Warning: Failed to serialize notification: {e}"),
+            );
+            return;
+        }
+    };
+
+    for notification in notifications.into_iter().flatten() {
+        if let Err(e) = deliver(notification, &payload).await {
+            message(
+                Level::WARN,
+                &format!("Warning: Failed to send notification: {e}"),
+            );
+        }
+    }
+}
+
+async fn deliver(notification: &Notification, payload: &str) -> Result<()> {
+    if let Some(command) = &notification.command {
+        run_command(command, payload).await?;
+    }
+    if let Some(webhook) = &notification.webhook {
+        post_webhook(webhook, payload).await?;
+    }
+    Ok(())
+}
+
+/// Run `command` in the shell with the payload on stdin
+async fn run_command(command: &str, payload: &str) -> Result<()> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = tokio::process::Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run '{command}': {e}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Commands that do not read the payload close stdin early
+        let _ = stdin.write_all(payload.as_bytes()).await;
+    }
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| anyhow!("Failed to run '{command}': {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("'{command}' exited with {status}"));
+    }
+    Ok(())
+}
+
+async fn post_webhook(webhook: &str, payload: &str) -> Result<()> {
+    let url = Url::parse(webhook).map_err(|e| anyhow!("Invalid webhook URL: {e}"))?;
+    let response = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .user_agent(concat!("cuenv/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| anyhow!("Failed to create HTTP client: {e}"))?
+        .post(url.as_str())
+        .header("Content-Type", "application/json")
+        .body(payload.to_string())
+        .send()
+        .await
+        .map_err(|e| anyhow!("Webhook request failed: {e}"))?;
+
+    if !response.status().is_success() {
+        // Report the host only; webhook URLs carry their credentials
+        let host = url.host_str().unwrap_or_default();
+        return Err(anyhow!(
+            "Webhook on {host} answered with status {}",
+            response.status()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(exit_code: Option<i32>) -> RunOutcome {
+        RunOutcome {
+            task: "ci".to_string(),
+            directory: PathBuf::from("/repo"),
+            run_id: "run-1".to_string(),
+            exit_code,
+            duration_ms: 12_300,
+            summary: None,
+        }
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            outcome(Some(0)).text(),
+            "✓ cuenv task 'ci' succeeded in 12.3s"
+        );
+        assert_eq!(
+            outcome(Some(2)).text(),
+            "✗ cuenv task 'ci' failed with exit code 2 after 12.3s"
+        );
+        assert!(!outcome(None).success());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_receives_payload() {
+        let temp = tempfile::tempdir().unwrap();
+        let received = temp.path().join("payload.json");
+        let config = NotifyConfig {
+            on_failure: Some(Notification {
+                command: Some(format!("cat > '{}'", received.display())),
+                webhook: None,
+            }),
+            on_success: Some(Notification {
+                command: Some("exit 1".to_string()),
+                webhook: None,
+            }),
+            on_complete: None,
+        };
+
+        send(&config, &outcome(Some(2))).await;
+
+        let payload: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&received).unwrap()).unwrap();
+        assert_eq!(payload["event"], "failure");
+        assert_eq!(payload["task"], "ci");
+        assert_eq!(payload["exit_code"], 2);
+        assert_eq!(payload["run_id"], "run-1");
+        assert!(payload["text"].as_str().unwrap().contains("exit code 2"));
+    }
+}
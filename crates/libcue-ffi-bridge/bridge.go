@@ -6,24 +6,79 @@ package main
 import "C"
 import (
 	"encoding/json"
+	stderrors "errors"
 	"fmt"
 	"os"
+	"path/filepath"
+	"regexp"
+	"sort"
+	"strings"
 	"unsafe"
 
+	"cuelang.org/go/cue"
 	"cuelang.org/go/cue/build"
 	"cuelang.org/go/cue/cuecontext"
+	cueerrors "cuelang.org/go/cue/errors"
+	"cuelang.org/go/cue/format"
 	"cuelang.org/go/cue/load"
+	"cuelang.org/go/cue/parser"
 )
 
+// localFileName is the optional, untracked override file merged over the package
+const localFileName = "env.local.cue"
+
+// systemFileName is the file, only present in the overlay, that provides the
+// facts about the machine as the hidden _cuenv field of the package
+const systemFileName = "cuenv_system.cue"
+
+// tagPattern matches the name in a @tag(name) or @tag(name,type=int) attribute
+var tagPattern = regexp.MustCompile(`@tag\(\s*([A-Za-z_][A-Za-z0-9_]*)`)
+
+// missingImportPatterns match the loader's errors for imports it cannot resolve
+var missingImportPatterns = []*regexp.Regexp{
+	regexp.MustCompile(`cannot find package "([^"]+)"`),
+	regexp.MustCompile(`package "([^"]+)" not found`),
+}
+
 //export cue_free_string
 func cue_free_string(s *C.char) {
 	C.free(unsafe.Pointer(s))
 }
 
+// schemaImportPath is the import path the embedded schema is provided under
+const schemaImportPath = "github.com/rawkode/cuenv/schema"
+
+// evalOptions configure an evaluation beyond the directory and package
+type evalOptions struct {
+	// Schema holds the files of the cuenv schema package by name. When set,
+	// the package can be imported without vendoring it and the configuration
+	// is validated against #Cuenv.
+	Schema map[string]string `json:"schema"`
+	// Tags set fields marked with @tag(), each given as "name=value"
+	Tags []string `json:"tags"`
+	// System holds facts about the machine, such as os and cpuCount, that
+	// the configuration can reference as _cuenv
+	System map[string]interface{} `json:"system"`
+}
+
 //export cue_eval_package
 func cue_eval_package(dirPath *C.char, packageName *C.char) *C.char {
+	return evalPackage(C.GoString(dirPath), C.GoString(packageName), evalOptions{})
+}
+
+//export cue_eval_package_with_options
+func cue_eval_package_with_options(dirPath *C.char, packageName *C.char, optionsJSON *C.char) *C.char {
+	var opts evalOptions
+	if err := json.Unmarshal([]byte(C.GoString(optionsJSON)), &opts); err != nil {
+		errMsg := map[string]string{"error": fmt.Sprintf("Invalid evaluation options: %v", err)}
+		errBytes, _ := json.Marshal(errMsg)
+		return C.CString(string(errBytes))
+	}
+	return evalPackage(C.GoString(dirPath), C.GoString(packageName), opts)
+}
+
+func evalPackage(goDir string, goPackageName string, opts evalOptions) (result *C.char) {
 	// Add recover to catch any panics
-	var result *C.char
 	defer func() {
 		if r := recover(); r != nil {
 			errMsg := map[string]string{"error": fmt.Sprintf("Internal error: %v", r)}
@@ -32,9 +87,6 @@ func cue_eval_package(dirPath *C.char, packageName *C.char) *C.char {
 		}
 	}()
 
-	goDir := C.GoString(dirPath)
-	goPackageName := C.GoString(packageName)
-
 	// Validate inputs
 	if goDir == "" {
 		errMsg := map[string]string{"error": "Directory path cannot be empty"}
@@ -70,56 +122,365 @@ func cue_eval_package(dirPath *C.char, packageName *C.char) *C.char {
 	// Create CUE context
 	ctx := cuecontext.New()
 
+	schema, err := compileSchema(ctx, opts.Schema)
+	if err != nil {
+		result = errorResponse(fmt.Sprintf("Failed to compile the cuenv schema: %v", err), err, goDir)
+		return result
+	}
+
 	// Load the specific CUE package by name
 	// This matches the behavior of "cue export .:package-name"
-	var instances []*build.Instance
+	// env.local.cue is left out of the package and evaluated on its own,
+	// so its values override the shared ones instead of conflicting
 	packagePath := ".:" + goPackageName
-	instances = load.Instances([]string{packagePath}, nil)
+	localPath, _ := filepath.Abs(localFileName)
+	_, statErr := os.Stat(localPath)
+	hasLocal := statErr == nil
 
-	if len(instances) == 0 {
-		errMsg := map[string]string{"error": "No CUE instances found"}
+	packageFiles, _ := filepath.Glob("*.cue")
+	for i, file := range packageFiles {
+		if file == localFileName {
+			packageFiles = append(packageFiles[:i], packageFiles[i+1:]...)
+			break
+		}
+	}
+
+	cfg := newLoadConfig(opts, packageFiles)
+	addSystemOverlay(cfg, goPackageName, opts)
+	if hasLocal {
+		cfg.Overlay[localPath] = load.FromString("package " + goPackageName + "\n")
+	}
+
+	data, err := decodeInstances(ctx, load.Instances([]string{packagePath}, cfg), schema)
+	if err != nil {
+		result = errorResponse(explainLoadError(err, goDir).Error(), err, goDir)
+		return result
+	}
+
+	if hasLocal {
+		localFiles := []string{localFileName}
+		localCfg := newLoadConfig(opts, localFiles)
+		if addSystemOverlay(localCfg, goPackageName, opts) {
+			localFiles = append(localFiles, systemFileName)
+		}
+		local, err := decodeInstances(ctx, load.Instances(localFiles, localCfg), schema)
+		if err != nil {
+			message := fmt.Sprintf("%s: %v", localFileName, explainLoadError(err, goDir))
+			result = errorResponse(message, err, goDir)
+			return result
+		}
+		data = mergeLocal(data, local)
+	}
+
+	// Convert to JSON
+	jsonBytes, err := json.Marshal(data)
+	if err != nil {
+		errMsg := map[string]string{"error": err.Error()}
 		errBytes, _ := json.Marshal(errMsg)
 		result = C.CString(string(errBytes))
 		return result
 	}
 
+	result = C.CString(string(jsonBytes))
+	return result
+}
+
+// newLoadConfig prepares loading files from the current directory with the
+// tags they declare, providing the schema package as if it were vendored
+// unless the module has its own copy
+func newLoadConfig(opts evalOptions, files []string) *load.Config {
+	cfg := &load.Config{Overlay: map[string]load.Source{}, Tags: declaredTags(opts.Tags, files)}
+	schemaFiles := opts.Schema
+	if len(schemaFiles) == 0 {
+		return cfg
+	}
+
+	dir, err := os.Getwd()
+	if err != nil {
+		return cfg
+	}
+	root := findModuleRoot(dir)
+	if root == "" {
+		// Imports only resolve inside a module, so provide one
+		root = dir
+		cfg.ModuleRoot = dir
+		cfg.Overlay[filepath.Join(dir, "cue.mod", "module.cue")] = load.FromString("module: \"cuenv.local\"\n")
+	}
+
+	pkgDir := filepath.Join(root, "cue.mod", "pkg", filepath.FromSlash(schemaImportPath))
+	if _, err := os.Stat(pkgDir); err == nil {
+		return cfg
+	}
+	for name, content := range schemaFiles {
+		cfg.Overlay[filepath.Join(pkgDir, name)] = load.FromString(content)
+	}
+	return cfg
+}
+
+// addSystemOverlay provides opts.System as _cuenv to the package loaded from
+// the current directory, unless a file of that name exists. It reports
+// whether the overlay was added.
+func addSystemOverlay(cfg *load.Config, packageName string, opts evalOptions) bool {
+	if opts.System == nil {
+		return false
+	}
+	systemPath, err := filepath.Abs(systemFileName)
+	if err != nil {
+		return false
+	}
+	if _, err := os.Stat(systemPath); err == nil {
+		return false
+	}
+
+	systemJSON, err := json.Marshal(opts.System)
+	if err != nil {
+		return false
+	}
+	cfg.Overlay[systemPath] = load.FromString("package " + packageName + "\n\n_cuenv: " + string(systemJSON) + "\n")
+	return true
+}
+
+// declaredTags keeps the tags whose names appear in a @tag() attribute in
+// files. CUE rejects a tag no field is marked with, and a tag usually only
+// concerns some of the env.cue files in a hierarchy.
+func declaredTags(tags []string, files []string) []string {
+	if len(tags) == 0 {
+		return nil
+	}
+
+	declared := map[string]bool{}
+	for _, file := range files {
+		content, err := os.ReadFile(file)
+		if err != nil {
+			continue
+		}
+		for _, match := range tagPattern.FindAllStringSubmatch(string(content), -1) {
+			declared[match[1]] = true
+		}
+	}
+
+	var kept []string
+	for _, tag := range tags {
+		name, _, _ := strings.Cut(tag, "=")
+		if declared[name] {
+			kept = append(kept, tag)
+		}
+	}
+	return kept
+}
+
+// compileSchema builds the definition configurations are validated against:
+// #Cuenv, left open at the top level so only the fields cuenv reads are
+// checked. Returns an empty value when no schema was provided.
+func compileSchema(ctx *cue.Context, schemaFiles map[string]string) (cue.Value, error) {
+	if len(schemaFiles) == 0 {
+		return cue.Value{}, nil
+	}
+
+	names := make([]string, 0, len(schemaFiles))
+	for name := range schemaFiles {
+		names = append(names, name)
+	}
+	sort.Strings(names)
+
+	var src strings.Builder
+	src.WriteString("package schema\n")
+	for _, name := range names {
+		for _, line := range strings.Split(schemaFiles[name], "\n") {
+			if strings.HasPrefix(strings.TrimSpace(line), "package ") {
+				continue
+			}
+			src.WriteString(line)
+			src.WriteString("\n")
+		}
+	}
+	src.WriteString("\n#Document: {\n\t#Cuenv\n\t...\n}\n")
+
+	compiled := ctx.CompileString(src.String(), cue.Filename("cuenv-schema.cue"))
+	if compiled.Err() != nil {
+		return cue.Value{}, compiled.Err()
+	}
+	return compiled.LookupPath(cue.ParsePath("#Document")), nil
+}
+
+// decodeInstances builds the first loaded instance, validates it against the
+// schema when one exists and decodes it into plain Go values
+func decodeInstances(ctx *cue.Context, instances []*build.Instance, schema cue.Value) (interface{}, error) {
+	if len(instances) == 0 {
+		return nil, fmt.Errorf("No CUE instances found")
+	}
+
 	inst := instances[0]
 	if inst.Err != nil {
-		errMsg := map[string]string{"error": fmt.Sprintf("Failed to load CUE instance: %v", inst.Err)}
-		errBytes, _ := json.Marshal(errMsg)
-		result = C.CString(string(errBytes))
-		return result
+		return nil, fmt.Errorf("Failed to load CUE instance: %w", inst.Err)
 	}
 
 	// Build the CUE value
 	v := ctx.BuildInstance(inst)
 	if v.Err() != nil {
-		errMsg := map[string]string{"error": fmt.Sprintf("Failed to build CUE value: %v", v.Err())}
-		errBytes, _ := json.Marshal(errMsg)
-		result = C.CString(string(errBytes))
-		return result
+		return nil, fmt.Errorf("Failed to build CUE value: %w", v.Err())
+	}
+
+	// Fields the schema does not allow, such as a misspelled task property,
+	// fail the load instead of being silently ignored
+	if schema.Exists() {
+		if err := schema.Unify(v).Validate(); err != nil {
+			return nil, fmt.Errorf("Configuration does not match the cuenv schema: %w", err)
+		}
 	}
 
 	// Simply decode the entire CUE value as JSON
 	var data interface{}
 	if err := v.Decode(&data); err != nil {
-		errMsg := map[string]string{"error": fmt.Sprintf("Failed to decode CUE value: %v", err)}
-		errBytes, _ := json.Marshal(errMsg)
-		result = C.CString(string(errBytes))
-		return result
+		return nil, fmt.Errorf("Failed to decode CUE value: %w", err)
 	}
+	return data, nil
+}
 
-	// Convert to JSON
-	jsonBytes, err := json.Marshal(data)
+// diagnostic is one CUE error together with the position it refers to
+type diagnostic struct {
+	Message string `json:"message"`
+	File    string `json:"file"`
+	Line    int    `json:"line"`
+	Column  int    `json:"column"`
+}
+
+// errorResponse encodes an error for the caller, listing the positions of
+// the CUE errors behind it so they can be shown against the source
+func errorResponse(message string, err error, dir string) *C.char {
+	response := map[string]interface{}{"error": message}
+	if diagnostics := diagnosticsFor(err, dir); len(diagnostics) > 0 {
+		response["diagnostics"] = diagnostics
+	}
+	responseBytes, _ := json.Marshal(response)
+	return C.CString(string(responseBytes))
+}
+
+// diagnosticsFor lists the positioned CUE errors wrapped by err
+func diagnosticsFor(err error, dir string) []diagnostic {
+	var cueErr cueerrors.Error
+	if !stderrors.As(err, &cueErr) {
+		return nil
+	}
+
+	var diagnostics []diagnostic
+	for _, e := range cueerrors.Errors(cueErr) {
+		pos := e.Position()
+		if !pos.IsValid() {
+			continue
+		}
+		file := pos.Filename()
+		if !filepath.IsAbs(file) {
+			file = filepath.Join(dir, file)
+		}
+		format, args := e.Msg()
+		message := fmt.Sprintf(format, args...)
+		if path := e.Path(); len(path) > 0 {
+			message = strings.Join(path, ".") + ": " + message
+		}
+		diagnostics = append(diagnostics, diagnostic{
+			Message: message,
+			File:    file,
+			Line:    pos.Line(),
+			Column:  pos.Column(),
+		})
+	}
+	return diagnostics
+}
+
+// explainLoadError turns an unresolved import into instructions for
+// providing the package. Imports are resolved from the CUE module
+// containing the directory: vendored packages live in its cue.mod/pkg,
+// cue.mod/gen or cue.mod/usr directories.
+func explainLoadError(err error, dir string) error {
+	var importPath string
+	for _, pattern := range missingImportPatterns {
+		if match := pattern.FindStringSubmatch(err.Error()); match != nil {
+			importPath = match[1]
+			break
+		}
+	}
+	// Paths starting with "." name the local package, not an import
+	if importPath == "" || strings.HasPrefix(importPath, ".") {
+		return err
+	}
+
+	if absDir, absErr := filepath.Abs(dir); absErr == nil {
+		dir = absDir
+	}
+	root := findModuleRoot(dir)
+	if root == "" {
+		return fmt.Errorf("cannot import %q: %s is not inside a CUE module. Run 'cue mod init' at the repository root and vendor the package under cue.mod/pkg/%s", importPath, dir, importPath)
+	}
+	return fmt.Errorf("cannot import %q: the package is not in the CUE module at %s. Vendor it under %s", importPath, root, filepath.Join(root, "cue.mod", "pkg", filepath.FromSlash(importPath)))
+}
+
+// findModuleRoot returns the closest directory at or above dir containing
+// cue.mod, or "" if there is none
+func findModuleRoot(dir string) string {
+	for {
+		if info, err := os.Stat(filepath.Join(dir, "cue.mod")); err == nil && info.IsDir() {
+			return dir
+		}
+		parent := filepath.Dir(dir)
+		if parent == dir {
+			return ""
+		}
+		dir = parent
+	}
+}
+
+//export cue_format
+func cue_format(fileName *C.char, source *C.char) (result *C.char) {
+	defer func() {
+		if r := recover(); r != nil {
+			errMsg := map[string]string{"error": fmt.Sprintf("Internal error: %v", r)}
+			errBytes, _ := json.Marshal(errMsg)
+			result = C.CString(string(errBytes))
+		}
+	}()
+
+	goFileName := C.GoString(fileName)
+	formatted, err := formatSource(goFileName, C.GoString(source))
 	if err != nil {
-		errMsg := map[string]string{"error": err.Error()}
-		errBytes, _ := json.Marshal(errMsg)
-		result = C.CString(string(errBytes))
-		return result
+		return errorResponse(err.Error(), err, filepath.Dir(goFileName))
 	}
 
-	result = C.CString(string(jsonBytes))
-	return result
+	resultBytes, _ := json.Marshal(map[string]string{"formatted": formatted})
+	return C.CString(string(resultBytes))
+}
+
+// formatSource formats a CUE file the way `cue fmt` does, keeping comments
+func formatSource(fileName string, source string) (string, error) {
+	file, err := parser.ParseFile(fileName, source, parser.ParseComments)
+	if err != nil {
+		return "", err
+	}
+	formatted, err := format.Node(file, format.Simplify())
+	if err != nil {
+		return "", err
+	}
+	return string(formatted), nil
+}
+
+// mergeLocal overlays the values of env.local.cue onto the shared
+// configuration. Structs are merged field by field; any other local value
+// replaces the shared one.
+func mergeLocal(shared, local interface{}) interface{} {
+	sharedMap, sharedIsMap := shared.(map[string]interface{})
+	localMap, localIsMap := local.(map[string]interface{})
+	if !sharedIsMap || !localIsMap {
+		return local
+	}
+
+	for key, value := range localMap {
+		if existing, ok := sharedMap[key]; ok {
+			sharedMap[key] = mergeLocal(existing, value)
+		} else {
+			sharedMap[key] = value
+		}
+	}
+	return sharedMap
 }
 
 func main() {}
\ No newline at end of file
@@ -20,6 +20,12 @@ type TestCueData struct {
 	Env map[string]interface{} `json:"env"`
 }
 
+// errorResult is the response for a failed evaluation
+type errorResult struct {
+	Error       string       `json:"error"`
+	Diagnostics []diagnostic `json:"diagnostics"`
+}
+
 // Helper function to create a temporary directory with CUE files
 func createTestCueDir(t *testing.T, packageName string, content string) (string, func()) {
 	// Validate package name to prevent path traversal
@@ -179,14 +185,41 @@ env: {
 	result := callCueEvalPackage(tempDir, "cuenv")
 
 	// Should return error JSON
-	var errorResponse map[string]string
+	var errorResponse errorResult
 	if err := json.Unmarshal([]byte(result), &errorResponse); err != nil {
 		t.Fatalf("Failed to parse error JSON: %v\nResult: %s", err, result)
 	}
 
 	// Should contain some indication of CUE error
-	if !strings.Contains(errorResponse["error"], "Failed to") {
-		t.Errorf("Expected CUE parsing error, got: %s", errorResponse["error"])
+	if !strings.Contains(errorResponse.Error, "Failed to") {
+		t.Errorf("Expected CUE parsing error, got: %s", errorResponse.Error)
+	}
+}
+
+func TestCueEvalPackage_ErrorDiagnostics(t *testing.T) {
+	cueContent := `env: {
+	PORT: "80" & 80
+}`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+
+	result := callCueEvalPackage(tempDir, "cuenv")
+
+	var response errorResult
+	if err := json.Unmarshal([]byte(result), &response); err != nil {
+		t.Fatalf("Failed to parse error result: %v\nResult: %s", err, result)
+	}
+	if response.Error == "" || len(response.Diagnostics) == 0 {
+		t.Fatalf("Expected an error with diagnostics, got: %s", result)
+	}
+
+	// The conflict is on line 4: the package clause and a blank line come first
+	first := response.Diagnostics[0]
+	if filepath.Base(first.File) != "env.cue" || first.Line != 4 {
+		t.Errorf("Expected a position in env.cue on line 4, got %s:%d:%d", first.File, first.Line, first.Column)
+	}
+	if !strings.Contains(first.Message, "env.PORT") {
+		t.Errorf("Expected the message to name the field, got: %s", first.Message)
 	}
 }
 
@@ -198,13 +231,13 @@ func TestCueEvalPackage_WrongPackageName(t *testing.T) {
 	result := callCueEvalPackage(tempDir, "cuenv")
 
 	// Should return error JSON since package name doesn't match
-	var errorResponse map[string]string
+	var errorResponse errorResult
 	if err := json.Unmarshal([]byte(result), &errorResponse); err != nil {
 		t.Fatalf("Failed to parse error JSON: %v\nResult: %s", err, result)
 	}
 
 	// Should indicate that no instances were found or there was a loading error
-	errorMsg := errorResponse["error"]
+	errorMsg := errorResponse.Error
 	if !strings.Contains(errorMsg, "No CUE instances found") && !strings.Contains(errorMsg, "Failed to load CUE instance") {
 		t.Errorf("Expected package loading error, got: %s", errorMsg)
 	}
@@ -271,6 +304,277 @@ env: {
 	}
 }
 
+func TestCueEvalPackage_LocalOverride(t *testing.T) {
+	cueContent := `
+env: {
+	DB_HOST: "db.internal"
+	DB_PORT: "5432"
+}`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+
+	localContent := "package cuenv\n\nenv: {\n\tDB_HOST: \"localhost\"\n\tFEATURE_X: \"on\"\n}\n"
+	if err := os.WriteFile(filepath.Join(tempDir, "env.local.cue"), []byte(localContent), 0644); err != nil {
+		t.Fatalf("Failed to write env.local.cue: %v", err)
+	}
+
+	result := callCueEvalPackage(tempDir, "cuenv")
+
+	var data TestCueData
+	if err := json.Unmarshal([]byte(result), &data); err != nil {
+		t.Fatalf("Failed to parse JSON result: %v\nResult: %s", err, result)
+	}
+
+	// The local value overrides the shared one instead of conflicting with it
+	if data.Env["DB_HOST"] != "localhost" {
+		t.Errorf("Expected DB_HOST to be overridden to 'localhost', got %v", data.Env["DB_HOST"])
+	}
+	if data.Env["DB_PORT"] != "5432" {
+		t.Errorf("Expected DB_PORT to be kept as '5432', got %v", data.Env["DB_PORT"])
+	}
+	if data.Env["FEATURE_X"] != "on" {
+		t.Errorf("Expected FEATURE_X from env.local.cue, got %v", data.Env["FEATURE_X"])
+	}
+}
+
+// writeTestFile writes content to path below dir, creating parent directories
+func writeTestFile(t *testing.T, dir, path, content string) {
+	full := filepath.Join(dir, filepath.FromSlash(path))
+	if err := os.MkdirAll(filepath.Dir(full), 0755); err != nil {
+		t.Fatalf("Failed to create directory for %s: %v", path, err)
+	}
+	if err := os.WriteFile(full, []byte(content), 0644); err != nil {
+		t.Fatalf("Failed to write %s: %v", path, err)
+	}
+}
+
+func TestCueEvalPackage_VendoredImport(t *testing.T) {
+	cueContent := `
+import policies "github.com/ourorg/policies/env"
+
+env: policies.#Service & {
+	PORT: 8080
+}`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+
+	writeTestFile(t, tempDir, "cue.mod/module.cue", "module: \"example.com/app\"\n")
+	writeTestFile(t, tempDir, "cue.mod/pkg/github.com/ourorg/policies/env/service.cue",
+		"package env\n\n#Service: {\n\tPORT: int & >1024\n\tREGION: *\"eu-west-1\" | string\n}\n")
+
+	result := callCueEvalPackage(tempDir, "cuenv")
+
+	var data TestCueData
+	if err := json.Unmarshal([]byte(result), &data); err != nil {
+		t.Fatalf("Failed to parse JSON result: %v\nResult: %s", err, result)
+	}
+	if data.Env["REGION"] != "eu-west-1" {
+		t.Errorf("Expected REGION default from the vendored schema, got %v (result: %s)", data.Env["REGION"], result)
+	}
+}
+
+func TestCueEvalPackage_MissingImport(t *testing.T) {
+	cueContent := `
+import policies "github.com/ourorg/policies/env"
+
+env: policies.#Service`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+
+	var errResult errorResult
+	result := callCueEvalPackage(tempDir, "cuenv")
+	if err := json.Unmarshal([]byte(result), &errResult); err != nil {
+		t.Fatalf("Failed to parse error result: %v\nResult: %s", err, result)
+	}
+	if !strings.Contains(errResult.Error, "not inside a CUE module") {
+		t.Errorf("Expected a hint to create a module, got: %s", errResult.Error)
+	}
+
+	writeTestFile(t, tempDir, "cue.mod/module.cue", "module: \"example.com/app\"\n")
+	result = callCueEvalPackage(tempDir, "cuenv")
+	if err := json.Unmarshal([]byte(result), &errResult); err != nil {
+		t.Fatalf("Failed to parse error result: %v\nResult: %s", err, result)
+	}
+	expected := filepath.Join("cue.mod", "pkg", "github.com", "ourorg", "policies", "env")
+	if !strings.Contains(errResult.Error, expected) {
+		t.Errorf("Expected a hint to vendor under %s, got: %s", expected, errResult.Error)
+	}
+}
+
+// callCueEvalPackageWithOptions evaluates with the given options
+func callCueEvalPackageWithOptions(dirPath, packageName string, opts evalOptions) string {
+	options, _ := json.Marshal(opts)
+
+	cDirPath := C.CString(dirPath)
+	cPackageName := C.CString(packageName)
+	cOptions := C.CString(string(options))
+	defer C.free(unsafe.Pointer(cDirPath))
+	defer C.free(unsafe.Pointer(cPackageName))
+	defer C.free(unsafe.Pointer(cOptions))
+
+	result := cue_eval_package_with_options(cDirPath, cPackageName, cOptions)
+	defer cue_free_string(result)
+
+	return C.GoString(result)
+}
+
+// callCueEvalPackageWithSchema evaluates with the repository's schema files
+func callCueEvalPackageWithSchema(t *testing.T, dirPath, packageName string) string {
+	files, err := filepath.Glob(filepath.Join("..", "..", "schema", "*.cue"))
+	if err != nil || len(files) == 0 {
+		t.Fatalf("Failed to find schema files: %v", err)
+	}
+	schema := map[string]string{}
+	for _, file := range files {
+		content, err := os.ReadFile(file)
+		if err != nil {
+			t.Fatalf("Failed to read %s: %v", file, err)
+		}
+		schema[filepath.Base(file)] = string(content)
+	}
+	return callCueEvalPackageWithOptions(dirPath, packageName, evalOptions{Schema: schema})
+}
+
+func TestCueEvalPackage_SchemaImport(t *testing.T) {
+	cueContent := `
+import "github.com/rawkode/cuenv/schema"
+
+schema.#Cuenv
+
+env: PORT: "8080"
+tasks: build: command: "cargo build"`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+
+	result := callCueEvalPackageWithSchema(t, tempDir, "cuenv")
+
+	var data TestCueData
+	if err := json.Unmarshal([]byte(result), &data); err != nil {
+		t.Fatalf("Failed to parse JSON result: %v\nResult: %s", err, result)
+	}
+	if data.Env["PORT"] != "8080" {
+		t.Errorf("Expected PORT from a package importing the schema, got %v (result: %s)", data.Env["PORT"], result)
+	}
+}
+
+func TestCueEvalPackage_SchemaRejectsUnknownTaskFields(t *testing.T) {
+	cueContent := `
+tasks: build: {
+	command: "cargo build"
+	dependecies: ["lint"]
+}`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+
+	var errResult errorResult
+	result := callCueEvalPackageWithSchema(t, tempDir, "cuenv")
+	if err := json.Unmarshal([]byte(result), &errResult); err != nil {
+		t.Fatalf("Failed to parse error result: %v\nResult: %s", err, result)
+	}
+	if !strings.Contains(errResult.Error, "cuenv schema") {
+		t.Errorf("Expected the misspelled field to fail validation, got: %s", result)
+	}
+
+	// Without the schema the typo is not noticed
+	if strings.Contains(callCueEvalPackage(tempDir, "cuenv"), "error") {
+		t.Errorf("Expected evaluation without a schema to succeed")
+	}
+}
+
+func TestCueEvalPackage_Tags(t *testing.T) {
+	cueContent := `
+env: {
+	REGION: *"us-east-1" | string @tag(region)
+	CLUSTER: string | *"dev" @tag(cluster)
+}`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+
+	// Tags no field is marked with, e.g. for another env.cue in the hierarchy, are ignored
+	tags := []string{"region=eu-west-1", "zone=b"}
+	result := callCueEvalPackageWithOptions(tempDir, "cuenv", evalOptions{Tags: tags})
+
+	var data TestCueData
+	if err := json.Unmarshal([]byte(result), &data); err != nil {
+		t.Fatalf("Failed to parse JSON result: %v\nResult: %s", err, result)
+	}
+	if data.Env["REGION"] != "eu-west-1" {
+		t.Errorf("Expected REGION from the tag, got %v (result: %s)", data.Env["REGION"], result)
+	}
+	if data.Env["CLUSTER"] != "dev" {
+		t.Errorf("Expected CLUSTER to keep its default, got %v", data.Env["CLUSTER"])
+	}
+}
+
+func callCueFormat(fileName, source string) string {
+	cFileName := C.CString(fileName)
+	cSource := C.CString(source)
+	defer C.free(unsafe.Pointer(cFileName))
+	defer C.free(unsafe.Pointer(cSource))
+
+	result := cue_format(cFileName, cSource)
+	defer cue_free_string(result)
+
+	return C.GoString(result)
+}
+
+func TestCueFormat(t *testing.T) {
+	source := "package cuenv\n\n// Database\nenv: {\nDATABASE_URL:   \"postgres://localhost\"\n}\n"
+	result := callCueFormat("/project/env.cue", source)
+
+	var formatted struct {
+		Formatted string `json:"formatted"`
+	}
+	if err := json.Unmarshal([]byte(result), &formatted); err != nil {
+		t.Fatalf("Failed to parse JSON result: %v\nResult: %s", err, result)
+	}
+	expected := "package cuenv\n\n// Database\nenv: {\n\tDATABASE_URL: \"postgres://localhost\"\n}\n"
+	if formatted.Formatted != expected {
+		t.Errorf("Expected %q, got %q", expected, formatted.Formatted)
+	}
+
+	var failure errorResult
+	result = callCueFormat("/project/env.cue", "package cuenv\n\nenv: {\n")
+	if err := json.Unmarshal([]byte(result), &failure); err != nil {
+		t.Fatalf("Failed to parse JSON result: %v\nResult: %s", err, result)
+	}
+	if failure.Error == "" || len(failure.Diagnostics) == 0 {
+		t.Fatalf("Expected a positioned syntax error, got %s", result)
+	}
+	if failure.Diagnostics[0].File != "/project/env.cue" {
+		t.Errorf("Expected the error to point at env.cue, got %s", failure.Diagnostics[0].File)
+	}
+}
+
+func TestCueEvalPackage_SystemInfo(t *testing.T) {
+	cueContent := `
+env: {
+	JOBS: "\(_cuenv.cpuCount * 2)"
+	TARGET: "\(_cuenv.os)-\(_cuenv.arch)"
+}`
+	tempDir, cleanup := createTestCueDir(t, "cuenv", cueContent)
+	defer cleanup()
+	localContent := "package cuenv\n\nenv: BRANCH: _cuenv.gitBranch\n"
+	if err := os.WriteFile(filepath.Join(tempDir, localFileName), []byte(localContent), 0644); err != nil {
+		t.Fatalf("Failed to write %s: %v", localFileName, err)
+	}
+
+	system := map[string]interface{}{"os": "linux", "arch": "x86_64", "cpuCount": 4, "gitBranch": "main"}
+	result := callCueEvalPackageWithOptions(tempDir, "cuenv", evalOptions{System: system})
+
+	var data map[string]interface{}
+	if err := json.Unmarshal([]byte(result), &data); err != nil {
+		t.Fatalf("Failed to parse JSON result: %v\nResult: %s", err, result)
+	}
+	env, _ := data["env"].(map[string]interface{})
+	if env["JOBS"] != "8" || env["TARGET"] != "linux-x86_64" || env["BRANCH"] != "main" {
+		t.Errorf("Expected values computed from _cuenv, got %s", result)
+	}
+	if _, exported := data["_cuenv"]; exported {
+		t.Errorf("_cuenv should stay hidden, got %s", result)
+	}
+}
+
 func TestCueEvalPackage_MemoryManagement(t *testing.T) {
 	// Test that multiple calls don't leak memory or cause crashes
 	cueContent := `env: { TEST_VAR: "value" }`
@@ -1,16 +1,57 @@
-use cuenv_core::constants::{AUDIT_IGNORED_PATH_PREFIXES, AUDIT_LOG_PATH, LD_SO_CACHE};
+use crate::privileges::{self, TaskUser};
+use crate::seccomp::SeccompProfile;
+use cuenv_core::constants::{
+    AUDIT_IGNORED_PATH_PREFIXES, AUDIT_LOG_PATH, CUENV_INSECURE_ALLOW_VAR, LD_SO_CACHE,
+    SANDBOX_READ_ONLY_PATHS, SANDBOX_READ_WRITE_PATHS,
+};
 use cuenv_core::{Error, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
 use std::process::Command;
 
+/// Flags that open a file for writing
+const AUDIT_WRITE_OPEN_FLAGS: &[&str] = &["O_WRONLY", "O_RDWR", "O_CREAT", "O_TRUNC", "O_APPEND"];
+
+/// Syscalls that create, change or remove the files they name
+const AUDIT_WRITE_SYSCALLS: &[&str] = &[
+    "creat",
+    "mkdir",
+    "mkdirat",
+    "rename",
+    "renameat",
+    "renameat2",
+    "unlink",
+    "unlinkat",
+    "rmdir",
+    "link",
+    "linkat",
+    "symlink",
+    "symlinkat",
+    "truncate",
+    "chmod",
+    "fchmodat",
+    "chown",
+    "lchown",
+    "fchownat",
+];
+
 /// Report generated by audit mode showing actual access patterns
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AuditReport {
     /// Files that were accessed during execution
     pub accessed_files: Vec<String>,
-    /// Network connections that were attempted
+    /// Files that were only read
+    #[serde(default)]
+    pub read_files: Vec<String>,
+    /// Files that were created, written or removed
+    #[serde(default)]
+    pub written_files: Vec<String>,
+    /// Network connections that were attempted, as `address:port`
     pub network_connections: Vec<String>,
+    /// Syscalls the task's seccomp profile would have blocked
+    #[serde(default)]
+    pub blocked_syscalls: Vec<String>,
 }
 
 impl AuditReport {
@@ -19,12 +60,19 @@ impl AuditReport {
         println!("🔍 Audit Report:");
         println!("================");
 
-        if !self.accessed_files.is_empty() {
+        if !self.read_files.is_empty() {
+            println!("\n📖 Files Read ({} unique paths):", self.read_files.len());
+            for file in &self.read_files {
+                println!("  • {file}");
+            }
+        }
+
+        if !self.written_files.is_empty() {
             println!(
-                "\n📁 File Access ({} unique paths):",
-                self.accessed_files.len()
+                "\n✏️  Files Written ({} unique paths):",
+                self.written_files.len()
             );
-            for file in &self.accessed_files {
+            for file in &self.written_files {
                 println!("  • {file}");
             }
         }
@@ -39,24 +87,85 @@ impl AuditReport {
             }
         }
 
+        if !self.blocked_syscalls.is_empty() {
+            println!(
+                "\n🚫 Syscalls the seccomp profile would block ({}):",
+                self.blocked_syscalls.len()
+            );
+            for syscall in &self.blocked_syscalls {
+                println!("  • {syscall}");
+            }
+            println!("  Allow these syscalls in the seccomp profile, or choose a less strict one");
+        }
+
         if self.accessed_files.is_empty() && self.network_connections.is_empty() {
             println!("  No file or network access detected");
         }
+    }
 
-        println!("\n💡 Recommendations:");
-        if !self.accessed_files.is_empty() {
-            println!("  Add to security.readOnlyPaths or security.readWritePaths:");
-            for file in &self.accessed_files {
-                println!("    - \"{file}\"");
-            }
-        }
+    /// Globs matching the files under `dir` the command read, leaving out
+    /// those it wrote
+    pub fn inferred_inputs(&self, dir: &Path) -> Vec<String> {
+        let outputs = local_groups(&self.written_files, dir);
+        let mut inputs = local_groups(&self.read_files, dir);
+        inputs.retain(|group, _| !outputs.contains_key(group));
+        group_globs(&inputs)
+    }
 
-        if !self.network_connections.is_empty() {
-            println!("  Add to security.allowedHosts:");
-            for conn in &self.network_connections {
-                println!("    - \"{conn}\"");
-            }
-        }
+    /// Globs matching the files under `dir` the command created or wrote
+    pub fn inferred_outputs(&self, dir: &Path) -> Vec<String> {
+        group_globs(&local_groups(&self.written_files, dir))
+    }
+
+    /// Task configuration allowing what the audited command accessed, as
+    /// CUE fields. Files under `dir` become inputs and outputs, and are
+    /// allowed by the entry below `dir` holding them; files elsewhere are
+    /// listed as they are, except those sandboxed tasks can always reach.
+    /// Landlock limits connections by port, so hosts are suggested as ports.
+    pub fn suggested_config(&self, dir: &Path) -> String {
+        use std::collections::BTreeSet;
+
+        let external = |files: &[String], always: &[&str]| -> Vec<String> {
+            files
+                .iter()
+                .filter(|path| {
+                    let path = Path::new(path);
+                    !path.starts_with(dir) && !always.iter().any(|prefix| path.starts_with(prefix))
+                })
+                .cloned()
+                .collect()
+        };
+
+        let outputs = local_groups(&self.written_files, dir);
+        let mut inputs = local_groups(&self.read_files, dir);
+        inputs.retain(|group, _| !outputs.contains_key(group));
+        let read_only_paths: Vec<String> = inputs
+            .keys()
+            .map(|group| format!("./{group}"))
+            .chain(external(&self.read_files, SANDBOX_READ_ONLY_PATHS))
+            .collect();
+        let read_write_paths: Vec<String> = outputs
+            .keys()
+            .map(|group| format!("./{group}"))
+            .chain(external(&self.written_files, SANDBOX_READ_WRITE_PATHS))
+            .collect();
+        let ports: BTreeSet<u16> = self
+            .network_connections
+            .iter()
+            .filter_map(|conn| conn.rsplit_once(':')?.1.parse().ok())
+            .collect();
+        let ports: Vec<String> = ports.iter().map(u16::to_string).collect();
+
+        let mut config = String::new();
+        push_cue_list(&mut config, "", "inputs", group_globs(&inputs).iter());
+        push_cue_list(&mut config, "", "outputs", group_globs(&outputs).iter());
+        config.push_str("security: {\n\trestrictDisk: true\n");
+        push_cue_list(&mut config, "\t", "readOnlyPaths", read_only_paths.iter());
+        push_cue_list(&mut config, "\t", "readWritePaths", read_write_paths.iter());
+        config.push_str("\trestrictNetwork: true\n");
+        push_cue_list(&mut config, "\t", "allowedHosts", ports.iter());
+        config.push_str("}\n");
+        config
     }
 
     /// Export the audit report as JSON string
@@ -98,21 +207,46 @@ pub struct AccessRestrictions {
     pub allowed_hosts: Vec<String>,
     /// Audit mode - collect access information instead of restricting
     pub audit_mode: bool,
+    /// Seccomp profile limiting the syscalls the command can make
+    pub seccomp: Option<SeccompProfile>,
+    /// User the command runs as
+    pub user: Option<TaskUser>,
+    /// Run the command without Linux capabilities
+    pub drop_capabilities: bool,
 }
 
 impl AccessRestrictions {
     /// Check if Landlock is supported on the current system
     #[cfg(target_os = "linux")]
     pub fn is_landlock_supported() -> bool {
-        // For now, we'll check by trying to create a ruleset
-        use landlock::Ruleset;
-        Ruleset::default().create().is_ok()
+        landlock_enforces(true, false)
     }
 
     #[cfg(not(target_os = "linux"))]
     pub fn is_landlock_supported() -> bool {
         false
     }
+
+    /// Whether the running system can enforce the enabled restrictions
+    pub fn is_enforceable(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            // Seccomp filters need no kernel feature check: every kernel
+            // Landlock could run on supports them
+            !(self.restrict_disk || self.restrict_network)
+                || landlock_enforces(self.restrict_disk, self.restrict_network)
+        }
+
+        // Only switching users works elsewhere, and only on Unix
+        #[cfg(not(target_os = "linux"))]
+        {
+            cfg!(unix)
+                && !(self.restrict_disk
+                    || self.restrict_network
+                    || self.seccomp.is_some()
+                    || self.drop_capabilities)
+        }
+    }
     /// Create new restrictions configuration
     pub fn new(restrict_disk: bool, restrict_network: bool) -> Self {
         Self {
@@ -123,6 +257,9 @@ impl AccessRestrictions {
             deny_paths: Vec::new(),
             allowed_hosts: Vec::new(),
             audit_mode: false,
+            seccomp: None,
+            user: None,
+            drop_capabilities: false,
         }
     }
 
@@ -143,6 +280,9 @@ impl AccessRestrictions {
             deny_paths,
             allowed_hosts,
             audit_mode: false,
+            seccomp: None,
+            user: None,
+            drop_capabilities: false,
         }
     }
 
@@ -174,12 +314,18 @@ impl AccessRestrictions {
             ));
         }
 
-        // Use strace to monitor file and network access
+        // Use strace to monitor file and network access, and every syscall
+        // when there is a seccomp profile to check them against
+        let trace = if self.seccomp.is_some() {
+            "trace=all"
+        } else {
+            "trace=file,network"
+        };
         let mut strace_cmd = Command::new("strace");
         strace_cmd
             .arg("-f") // Follow forks
             .arg("-e") // Filter system calls
-            .arg("trace=file,network") // Monitor file and network calls
+            .arg(trace)
             .arg("-o") // Output to file
             .arg(AUDIT_LOG_PATH)
             .arg("--");
@@ -202,55 +348,81 @@ impl AccessRestrictions {
             strace_cmd.current_dir(current_dir);
         }
 
-        // Execute with strace
-        let output = strace_cmd.output().map_err(|e| {
+        // Execute with strace, letting the task's output through
+        let status = strace_cmd.status().map_err(|e| {
+            let reason = if e.kind() == std::io::ErrorKind::NotFound {
+                "Audit mode needs strace, which is not installed".to_string()
+            } else {
+                format!("Failed to run audit command: {e}")
+            };
             Error::command_execution(
                 "strace",
                 vec!["monitoring command".to_string()],
-                format!("Failed to run audit command: {e}"),
+                reason,
                 None,
             )
         })?;
 
-        // Parse the audit log
-        let audit_report = self.parse_audit_log(AUDIT_LOG_PATH)?;
+        // Parse the audit log. Relative paths are relative to the directory
+        // the command started in.
+        let cwd = match cmd.get_current_dir() {
+            Some(dir) => dir.to_path_buf(),
+            None => std::env::current_dir().unwrap_or_default(),
+        };
+        let log = std::fs::read_to_string(AUDIT_LOG_PATH).unwrap_or_default();
+        let audit_report = self.parse_audit_log(&log, &cwd);
 
         // Clean up the audit log
         let _ = std::fs::remove_file(AUDIT_LOG_PATH);
 
-        Ok((output.status.code().unwrap_or(1), audit_report))
+        Ok((status.code().unwrap_or(1), audit_report))
     }
 
     /// Parse strace output to generate audit report
-    fn parse_audit_log(&self, log_path: &str) -> Result<AuditReport> {
-        use std::collections::HashSet;
-        use std::fs;
-
-        let mut accessed_files = HashSet::new();
-        let mut network_connections = HashSet::new();
-
-        if let Ok(content) = fs::read_to_string(log_path) {
-            for line in content.lines() {
-                // Parse file access patterns
-                if line.contains("openat(") || line.contains("open(") {
-                    if let Some(path) = extract_file_path(line) {
-                        accessed_files.insert(path);
-                    }
+    fn parse_audit_log(&self, log: &str, cwd: &Path) -> AuditReport {
+        use std::collections::BTreeSet;
+
+        let mut read_files = BTreeSet::new();
+        let mut written_files = BTreeSet::new();
+        let mut network_connections = BTreeSet::new();
+        let mut blocked_syscalls = BTreeSet::new();
+
+        for line in log.lines() {
+            let Some(syscall) = extract_syscall_name(line) else {
+                continue;
+            };
+
+            match extract_file_access(syscall, line, cwd) {
+                Some((path, true)) => {
+                    written_files.insert(path);
                 }
+                Some((path, false)) => {
+                    read_files.insert(path);
+                }
+                None => {}
+            }
 
-                // Parse network connection patterns
-                if line.contains("connect(") || line.contains("bind(") {
-                    if let Some(addr) = extract_network_address(line) {
-                        network_connections.insert(addr);
-                    }
+            // Parse network connection patterns
+            if syscall == "connect" || syscall == "bind" {
+                if let Some(addr) = extract_network_address(line) {
+                    network_connections.insert(addr);
+                }
+            }
+
+            if let Some(profile) = &self.seccomp {
+                if profile.blocks(syscall) {
+                    blocked_syscalls.insert(syscall.to_string());
                 }
             }
         }
 
-        Ok(AuditReport {
-            accessed_files: accessed_files.into_iter().collect(),
+        AuditReport {
+            accessed_files: read_files.union(&written_files).cloned().collect(),
+            read_files: read_files.difference(&written_files).cloned().collect(),
+            written_files: written_files.into_iter().collect(),
             network_connections: network_connections.into_iter().collect(),
-        })
+            blocked_syscalls: blocked_syscalls.into_iter().collect(),
+        }
     }
 
     /// Add an allowed network host/CIDR
@@ -258,12 +430,18 @@ impl AccessRestrictions {
         self.allowed_hosts.push(host.into());
     }
 
-    /// Create AccessRestrictions from a SecurityConfig
+    /// Create AccessRestrictions from a SecurityConfig. Listing paths
+    /// restricts disk access to them unless `restrictDisk` is false.
     pub fn from_security_config(security: &cuenv_config::SecurityConfig) -> Self {
         use std::path::PathBuf;
 
+        let lists_paths = [&security.read_only_paths, &security.read_write_paths]
+            .into_iter()
+            .flatten()
+            .any(|paths| !paths.is_empty());
+
         Self {
-            restrict_disk: security.restrict_disk.unwrap_or(false),
+            restrict_disk: security.restrict_disk.unwrap_or(lists_paths),
             restrict_network: security.restrict_network.unwrap_or(false),
             read_only_paths: security
                 .read_only_paths
@@ -282,6 +460,11 @@ impl AccessRestrictions {
                 .unwrap_or_default(),
             allowed_hosts: security.allowed_hosts.as_ref().cloned().unwrap_or_default(),
             audit_mode: false,
+            // Reading the profile and looking up the user can fail, so they
+            // are loaded separately with SeccompProfile::load and TaskUser::lookup
+            seccomp: None,
+            user: None,
+            drop_capabilities: security.drop_capabilities.unwrap_or(false),
         }
     }
 
@@ -305,7 +488,7 @@ impl AccessRestrictions {
 
             // Add outputs as read-write paths
             if let Some(outputs) = &task_config.outputs {
-                for output in outputs {
+                for output in outputs.paths() {
                     restrictions.add_read_write_path(PathBuf::from(output));
                 }
             }
@@ -321,23 +504,76 @@ impl AccessRestrictions {
 
     /// Apply restrictions to a command before execution
     /// This is the main entry point for applying platform-specific restrictions
+    ///
+    /// Restrictions the system cannot enforce are an error, unless
+    /// `--insecure-allow` (`CUENV_INSECURE_ALLOW=1`) lets the command run
+    /// without them.
     pub fn apply_to_command(&self, cmd: &mut Command) -> Result<()> {
         if !self.has_any_restrictions() {
             return Ok(());
         }
-        // Apply platform-specific restrictions
-        #[cfg(target_os = "linux")]
-        self.apply_landlock_restrictions(cmd)?;
 
-        #[cfg(not(target_os = "linux"))]
-        self.apply_fallback_restrictions(cmd)?;
+        // Errors raised in the child only reach us as an errno, so support is
+        // checked here, where the message can still be clear
+        if !self.is_enforceable() {
+            if insecure_allowed() {
+                eprintln!(
+                    "Warning: running without the task's access restrictions, which this system cannot enforce"
+                );
+                return Ok(());
+            }
+            return Err(self.unenforceable_error());
+        }
+
+        // Privileges go first: Landlock and seccomp take the process's own
+        // and may block the calls that drop them
+        if self.user.is_some() || self.drop_capabilities {
+            privileges::apply_to_command(cmd, self.user.as_ref(), self.drop_capabilities)?;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if self.restrict_disk || self.restrict_network {
+                self.apply_landlock_restrictions(cmd)?;
+            }
+            if let Some(profile) = &self.seccomp {
+                profile.apply_to_command(cmd)?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Why the enabled restrictions cannot be enforced
+    #[cfg(target_os = "linux")]
+    fn unenforceable_error(&self) -> Error {
+        let requirement = if self.restrict_network && Self::is_landlock_supported() {
+            "restricting network access needs Landlock ABI 4 (Linux 6.7+)"
+        } else {
+            "Landlock needs Linux 5.13+ and must be enabled in the kernel"
+        };
+        Error::configuration(format!(
+            "The running kernel cannot enforce the task's access restrictions: {requirement}. \
+             Pass --insecure-allow to run it without them."
+        ))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn unenforceable_error(&self) -> Error {
+        Error::configuration(
+            "Access restrictions are only supported on Linux with Landlock (kernel 5.13+). \
+             Pass --insecure-allow to run the task without them."
+                .to_string(),
+        )
+    }
+
     /// Check if any restrictions are enabled
     pub fn has_any_restrictions(&self) -> bool {
-        self.restrict_disk || self.restrict_network
+        self.restrict_disk
+            || self.restrict_network
+            || self.seccomp.is_some()
+            || self.user.is_some()
+            || self.drop_capabilities
     }
 
     /// Apply Landlock-based restrictions on Linux
@@ -352,8 +588,16 @@ impl AccessRestrictions {
         // Clone the necessary data for the pre_exec closure
         let restrict_disk = self.restrict_disk;
         let restrict_network = self.restrict_network;
-        let read_only_paths = self.read_only_paths.clone();
-        let read_write_paths = self.read_write_paths.clone();
+        let read_only_paths: Vec<PathBuf> = SANDBOX_READ_ONLY_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .chain(self.read_only_paths.iter().cloned())
+            .collect();
+        let read_write_paths: Vec<PathBuf> = SANDBOX_READ_WRITE_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .chain(self.read_write_paths.iter().cloned())
+            .collect();
         let allowed_hosts = self.allowed_hosts.clone();
 
         // SAFETY: The pre_exec closure is only executed in the child process after fork()
@@ -472,50 +716,172 @@ impl AccessRestrictions {
 
         Ok(())
     }
+}
 
-    /// Apply fallback restrictions on non-Linux platforms
-    #[cfg(not(target_os = "linux"))]
-    fn apply_fallback_restrictions(&self, _cmd: &mut Command) -> Result<()> {
-        if self.has_any_restrictions() {
-            return Err(Error::configuration(
-                "Access restrictions are only supported on Linux with Landlock. Please use a Linux system with kernel 5.13+ for sandboxing support.".to_string()
-            ));
+/// Whether `--insecure-allow` lets tasks run without restrictions the system
+/// cannot enforce
+fn insecure_allowed() -> bool {
+    std::env::var(CUENV_INSECURE_ALLOW_VAR).is_ok_and(|value| value == "1" || value == "true")
+}
+
+/// Whether the running kernel enforces Landlock rules for the requested kinds
+/// of access. Creating a ruleset that requires them fails otherwise.
+#[cfg(target_os = "linux")]
+fn landlock_enforces(disk: bool, network: bool) -> bool {
+    use landlock::{
+        Access, AccessFs, AccessNet, CompatLevel, Compatible, Ruleset, RulesetAttr, RulesetError,
+        ABI,
+    };
+
+    let create = || -> std::result::Result<_, RulesetError> {
+        let mut ruleset = Ruleset::default().set_compatibility(CompatLevel::HardRequirement);
+        if disk {
+            ruleset = ruleset.handle_access(AccessFs::from_all(ABI::V1))?;
+        }
+        if network {
+            ruleset = ruleset.handle_access(AccessNet::BindTcp | AccessNet::ConnectTcp)?;
+        }
+        ruleset.create()
+    };
+    (disk || network) && create().is_ok()
+}
+
+/// The files under `dir`, grouped by the entry directly below `dir` that
+/// holds them. Each group lists the paths below its entry, relative to `dir`.
+fn local_groups(files: &[String], dir: &Path) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for file in files {
+        let Ok(relative) = Path::new(file).strip_prefix(dir) else {
+            continue;
+        };
+        let Some(first) = relative.components().next() else {
+            continue;
+        };
+        let nested = groups
+            .entry(first.as_os_str().to_string_lossy().into_owned())
+            .or_default();
+        if relative.components().count() > 1 {
+            nested.push(relative.to_path_buf());
         }
-        Ok(())
     }
+    groups
 }
 
-/// Extract file path from strace output line
-fn extract_file_path(line: &str) -> Option<String> {
+/// A glob per group: files directly below the directory as they are, and
+/// directories by their extension when all the files in them share one
+fn group_globs(groups: &BTreeMap<String, Vec<PathBuf>>) -> Vec<String> {
+    groups
+        .iter()
+        .map(|(group, nested)| {
+            let mut extensions = nested.iter().map(|path| path.extension());
+            match extensions.next() {
+                None => group.clone(),
+                Some(Some(extension)) if extensions.all(|other| other == Some(extension)) => {
+                    format!("{group}/**/*.{}", extension.to_string_lossy())
+                }
+                Some(_) => format!("{group}/**"),
+            }
+        })
+        .collect()
+}
+
+/// Append `name: [...]` to CUE `config`, unless there are no `values`
+fn push_cue_list<'a>(
+    config: &mut String,
+    indent: &str,
+    name: &str,
+    values: impl ExactSizeIterator<Item = &'a String>,
+) {
+    if values.len() == 0 {
+        return;
+    }
+    config.push_str(&format!("{indent}{name}: [\n"));
+    for value in values {
+        // JSON strings are CUE strings
+        let value = serde_json::to_string(value).unwrap_or_default();
+        config.push_str(&format!("{indent}\t{value},\n"));
+    }
+    config.push_str(&format!("{indent}]\n"));
+}
+
+/// The file a strace line accessed and whether it was written. Failed
+/// calls and the paths audits ignore are left out.
+fn extract_file_access(syscall: &str, line: &str, cwd: &Path) -> Option<(String, bool)> {
+    if line.contains(") = -1 ") {
+        return None;
+    }
+
     // Look for patterns like: openat(AT_FDCWD, "/path/to/file", O_RDONLY) = 3
-    if let Some(start) = line.find('"') {
-        if let Some(end) = line[start + 1..].find('"') {
-            let path = &line[start + 1..start + 1 + end];
-            // Filter out common system paths that aren't interesting for user restrictions
-            if !AUDIT_IGNORED_PATH_PREFIXES
+    let mut paths = line.split('"').skip(1).step_by(2);
+    let (path, written) = match syscall {
+        "open" | "openat" | "openat2" | "execve" => (
+            paths.next()?,
+            AUDIT_WRITE_OPEN_FLAGS
                 .iter()
-                .any(|prefix| path.starts_with(prefix))
-                && path != LD_SO_CACHE
-            {
-                return Some(path.to_string());
+                .any(|flag| line.contains(flag)),
+        ),
+        // Renames and links create their last path
+        _ if AUDIT_WRITE_SYSCALLS.contains(&syscall) => (paths.last()?, true),
+        _ => return None,
+    };
+
+    let path = normalize_path(&cwd.join(path));
+    let path = path.to_str()?;
+    // Filter out common system paths that aren't interesting for user restrictions
+    if AUDIT_IGNORED_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+        || path == LD_SO_CACHE
+    {
+        return None;
+    }
+    Some((path.to_string(), written))
+}
+
+/// Remove `.` and `..` components without touching the filesystem, where
+/// the paths may no longer exist
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
             }
+            component => normalized.push(component),
         }
     }
-    None
+    normalized
 }
 
 /// Extract network address from strace output line
 fn extract_network_address(line: &str) -> Option<String> {
     // Look for patterns like: connect(3, {sa_family=AF_INET, sin_port=htons(80), sin_addr=inet_addr("127.0.0.1")}, 16) = 0
-    if line.contains("AF_INET") {
-        if let Some(addr_start) = line.find("inet_addr(\"") {
-            if let Some(addr_end) = line[addr_start + 11..].find('"') {
-                let addr = &line[addr_start + 11..addr_start + 11 + addr_end];
-                return Some(addr.to_string());
-            }
-        }
+    // or: connect(3, {sa_family=AF_INET6, sin6_port=htons(443), ..., inet_pton(AF_INET6, "::1", &sin6_addr), ...}, 28) = 0
+    let port_start = line.find("port=htons(")? + "port=htons(".len();
+    let port = &line[port_start..port_start + line[port_start..].find(')')?];
+
+    if line.contains("AF_INET6") {
+        let addr = line.split('"').nth(1)?;
+        Some(format!("[{addr}]:{port}"))
+    } else if line.contains("AF_INET") {
+        let addr_start = line.find("inet_addr(\"")? + "inet_addr(\"".len();
+        let addr = &line[addr_start..addr_start + line[addr_start..].find('"')?];
+        Some(format!("{addr}:{port}"))
+    } else {
+        None
     }
-    None
+}
+
+/// Extract the syscall name from strace output line
+fn extract_syscall_name(line: &str) -> Option<&str> {
+    // Look for patterns like: 1234  uname({sysname="Linux", ...}) = 0
+    let call = line
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start();
+    let name = &call[..call.find('(')?];
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .then_some(name)
 }
 
 #[cfg(test)]
@@ -597,6 +963,17 @@ mod tests {
         // We can't easily test the actual Landlock functionality without kernel support
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_unenforceable_restrictions_fail() {
+        if AccessRestrictions::is_landlock_supported() {
+            return;
+        }
+        let restrictions = AccessRestrictions::new(true, false);
+        let result = restrictions.apply_to_command(&mut Command::new("true"));
+        assert!(result.unwrap_err().to_string().contains("--insecure-allow"));
+    }
+
     #[cfg(not(target_os = "linux"))]
     #[test]
     fn test_non_linux_restrictions_fail() {
@@ -619,7 +996,10 @@ mod tests {
                 "/tmp/test.txt".to_string(),
                 "/usr/lib/libssl.so".to_string(),
             ],
-            network_connections: vec!["127.0.0.1".to_string(), "192.168.1.1".to_string()],
+            read_files: vec!["/usr/lib/libssl.so".to_string()],
+            written_files: vec!["/tmp/test.txt".to_string()],
+            network_connections: vec!["127.0.0.1:80".to_string(), "192.168.1.1:443".to_string()],
+            blocked_syscalls: vec!["ptrace".to_string()],
         };
 
         // Test JSON serialization
@@ -637,17 +1017,22 @@ mod tests {
         // Test JSON deserialization
         let parsed_report = AuditReport::from_json(&json).unwrap();
         assert_eq!(parsed_report.accessed_files, report.accessed_files);
+        assert_eq!(parsed_report.written_files, report.written_files);
         assert_eq!(
             parsed_report.network_connections,
             report.network_connections
         );
+        assert_eq!(parsed_report.blocked_syscalls, report.blocked_syscalls);
     }
 
     #[test]
     fn test_empty_audit_report_json() {
         let report = AuditReport {
             accessed_files: vec![],
+            read_files: vec![],
+            written_files: vec![],
             network_connections: vec![],
+            blocked_syscalls: vec![],
         };
 
         let json = report.to_json().unwrap();
@@ -656,4 +1041,124 @@ mod tests {
         assert!(parsed_report.accessed_files.is_empty());
         assert!(parsed_report.network_connections.is_empty());
     }
+
+    #[test]
+    fn test_extract_syscall_name() {
+        assert_eq!(
+            extract_syscall_name(r#"4021  uname({sysname="Linux", ...}) = 0"#),
+            Some("uname")
+        );
+        assert_eq!(
+            extract_syscall_name(r#"openat(AT_FDCWD, "/tmp", O_RDONLY) = 3"#),
+            Some("openat")
+        );
+        assert_eq!(
+            extract_syscall_name("4021  <... read resumed>\"\", 1) = 0"),
+            None
+        );
+        assert_eq!(extract_syscall_name("4021  +++ exited with 0 +++"), None);
+    }
+
+    const AUDIT_LOG: &str = r#"4021  execve("/bin/sh", ["sh", "-c", "make"], 0x7ffd /* 20 vars */) = 0
+4021  openat(AT_FDCWD, "/etc/ld.so.cache", O_RDONLY|O_CLOEXEC) = 3
+4021  openat(AT_FDCWD, "src/main.c", O_RDONLY) = 3
+4021  openat(AT_FDCWD, "./Makefile", O_RDONLY) = 3
+4021  openat(AT_FDCWD, "missing.h", O_RDONLY) = -1 ENOENT (No such file or directory)
+4022  openat(AT_FDCWD, "build/main.o.tmp", O_WRONLY|O_CREAT|O_TRUNC, 0666 <unfinished ...>
+4022  rename("build/main.o.tmp", "build/main.o") = 0
+4021  openat(AT_FDCWD, "/home/dev/.config/tool.toml", O_RDONLY) = 4
+4021  mkdir("/home/dev/.cache/tool", 0777) = 0
+4021  connect(5, {sa_family=AF_INET, sin_port=htons(443), sin_addr=inet_addr("140.82.112.3")}, 16) = -1 EINPROGRESS (Operation now in progress)
+4021  connect(6, {sa_family=AF_INET6, sin6_port=htons(53), sin6_flowinfo=htonl(0), inet_pton(AF_INET6, "::1", &sin6_addr), sin6_scope_id=0}, 28) = 0
+4021  +++ exited with 0 +++
+"#;
+
+    #[test]
+    fn test_parse_audit_log() {
+        let report =
+            AccessRestrictions::default().parse_audit_log(AUDIT_LOG, Path::new("/home/dev/app"));
+
+        assert_eq!(
+            report.read_files,
+            [
+                "/bin/sh",
+                "/home/dev/.config/tool.toml",
+                "/home/dev/app/Makefile",
+                "/home/dev/app/src/main.c",
+            ]
+        );
+        assert_eq!(
+            report.written_files,
+            [
+                "/home/dev/.cache/tool",
+                "/home/dev/app/build/main.o",
+                "/home/dev/app/build/main.o.tmp",
+            ]
+        );
+        assert_eq!(report.accessed_files.len(), 7);
+        assert_eq!(report.network_connections, ["140.82.112.3:443", "[::1]:53"]);
+        assert!(report.blocked_syscalls.is_empty());
+    }
+
+    #[test]
+    fn test_inferred_inputs_and_outputs() {
+        let dir = Path::new("/home/dev/app");
+        let report = AuditReport {
+            read_files: vec![
+                "/home/dev/app/README.md".to_string(),
+                "/home/dev/app/src/lib.rs".to_string(),
+                "/home/dev/app/src/bin/main.rs".to_string(),
+                "/home/dev/app/assets/logo.svg".to_string(),
+                "/home/dev/app/assets/fonts/mono.woff2".to_string(),
+                "/home/dev/app/dist/stale.js".to_string(),
+                "/home/dev/.npmrc".to_string(),
+            ],
+            written_files: vec![
+                "/home/dev/app/dist".to_string(),
+                "/home/dev/app/dist/app.js".to_string(),
+            ],
+            ..AuditReport::default()
+        };
+
+        assert_eq!(
+            report.inferred_inputs(dir),
+            ["README.md", "assets/**", "src/**/*.rs"]
+        );
+        assert_eq!(report.inferred_outputs(dir), ["dist/**/*.js"]);
+    }
+
+    #[test]
+    fn test_suggested_config() {
+        let report =
+            AccessRestrictions::default().parse_audit_log(AUDIT_LOG, Path::new("/home/dev/app"));
+
+        assert_eq!(
+            report.suggested_config(Path::new("/home/dev/app")),
+            r#"inputs: [
+	"Makefile",
+	"src/**/*.c",
+]
+outputs: [
+	"build/**",
+]
+security: {
+	restrictDisk: true
+	readOnlyPaths: [
+		"./Makefile",
+		"./src",
+		"/home/dev/.config/tool.toml",
+	]
+	readWritePaths: [
+		"./build",
+		"/home/dev/.cache/tool",
+	]
+	restrictNetwork: true
+	allowedHosts: [
+		"53",
+		"443",
+	]
+}
+"#
+        );
+    }
 }
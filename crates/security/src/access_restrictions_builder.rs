@@ -1,4 +1,6 @@
 use crate::access_restrictions::AccessRestrictions;
+use crate::privileges::TaskUser;
+use crate::seccomp::SeccompProfile;
 use std::path::PathBuf;
 
 /// Builder for creating AccessRestrictions with a fluent API
@@ -11,6 +13,9 @@ pub struct AccessRestrictionsBuilder {
     deny_paths: Vec<PathBuf>,
     allowed_hosts: Vec<String>,
     audit_mode: bool,
+    seccomp: Option<SeccompProfile>,
+    user: Option<TaskUser>,
+    drop_capabilities: bool,
 }
 
 impl AccessRestrictionsBuilder {
@@ -104,6 +109,24 @@ impl AccessRestrictionsBuilder {
         self
     }
 
+    /// Filter syscalls with a seccomp profile
+    pub fn seccomp(mut self, profile: SeccompProfile) -> Self {
+        self.seccomp = Some(profile);
+        self
+    }
+
+    /// Run the command as another user
+    pub fn user(mut self, user: TaskUser) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// Run the command without Linux capabilities
+    pub fn drop_capabilities(mut self, drop: bool) -> Self {
+        self.drop_capabilities = drop;
+        self
+    }
+
     /// Build the AccessRestrictions
     pub fn build(self) -> AccessRestrictions {
         let mut restrictions = AccessRestrictions::with_allowlists(
@@ -118,6 +141,9 @@ impl AccessRestrictionsBuilder {
         if self.audit_mode {
             restrictions.enable_audit_mode();
         }
+        restrictions.seccomp = self.seccomp;
+        restrictions.user = self.user;
+        restrictions.drop_capabilities = self.drop_capabilities;
 
         restrictions
     }
@@ -5,13 +5,22 @@
 //! - Audit logging
 //! - File system access controls
 //! - Network access controls
+//! - Seccomp syscall filtering
+//! - Running tasks as another user without capabilities
+//! - An encrypted store for per-user secrets
 
 pub mod access_restrictions;
 pub mod access_restrictions_builder;
 pub mod audit;
+pub mod privileges;
+pub mod seccomp;
+pub mod secret_store;
 pub mod validator;
 
 pub use access_restrictions::*;
 pub use access_restrictions_builder::*;
 pub use audit::*;
+pub use privileges::TaskUser;
+pub use seccomp::SeccompProfile;
+pub use secret_store::SecretStore;
 pub use validator::SecurityValidator;
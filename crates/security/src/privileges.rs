@@ -0,0 +1,333 @@
+//! Running tasks with fewer privileges
+//!
+//! A task's `security.user` runs its process as another user: the groups, the
+//! group and the user ID are switched right before exec, which takes root. With
+//! `security.dropCapabilities` the process also loses its Linux capabilities,
+//! including those a later exec could gain back, so a task run by a root
+//! provisioning script is no more privileged than an ordinary process.
+
+use cuenv_core::{Error, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The account a task runs as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskUser {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    /// Supplementary groups, including `gid`
+    pub groups: Vec<u32>,
+    pub home: PathBuf,
+}
+
+impl TaskUser {
+    /// Look up a user by name or numeric ID
+    #[cfg(unix)]
+    pub fn lookup(user: &str) -> Result<Self> {
+        let passwd = match user.parse::<u32>() {
+            Ok(uid) => passwd_entry(|pwd, buf, len, result| unsafe {
+                libc::getpwuid_r(uid, pwd, buf, len, result)
+            }),
+            Err(_) => {
+                let name = std::ffi::CString::new(user).map_err(|_| unknown_user(user))?;
+                passwd_entry(|pwd, buf, len, result| unsafe {
+                    libc::getpwnam_r(name.as_ptr(), pwd, buf, len, result)
+                })
+            }
+        }
+        .map_err(|e| Error::configuration(format!("Failed to look up user '{user}': {e}")))?;
+
+        let (name, uid, gid, home) = passwd.ok_or_else(|| unknown_user(user))?;
+        let groups = supplementary_groups(&name, gid);
+        Ok(Self {
+            name,
+            uid,
+            gid,
+            groups,
+            home,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn lookup(_user: &str) -> Result<Self> {
+        Err(Error::configuration(
+            "Running tasks as another user is only supported on Unix".to_string(),
+        ))
+    }
+}
+
+#[cfg(unix)]
+fn unknown_user(user: &str) -> Error {
+    Error::configuration(format!("Task user '{user}' does not exist"))
+}
+
+/// Name, user ID, group ID and home directory of a passwd entry, growing
+/// the buffer `lookup` fills until the entry fits
+#[cfg(unix)]
+fn passwd_entry(
+    lookup: impl Fn(
+        *mut libc::passwd,
+        *mut libc::c_char,
+        libc::size_t,
+        *mut *mut libc::passwd,
+    ) -> libc::c_int,
+) -> std::io::Result<Option<(String, u32, u32, PathBuf)>> {
+    use std::ffi::CStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        // SAFETY: passwd is plain data that the lookup fills in, pointing
+        // into buf, which outlives the reads below
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        match lookup(&mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) {
+            0 if result.is_null() => return Ok(None),
+            0 => {
+                // SAFETY: on success the strings are NUL-terminated and in buf
+                let (name, home) =
+                    unsafe { (CStr::from_ptr(pwd.pw_name), CStr::from_ptr(pwd.pw_dir)) };
+                return Ok(Some((
+                    name.to_string_lossy().into_owned(),
+                    pwd.pw_uid,
+                    pwd.pw_gid,
+                    PathBuf::from(std::ffi::OsStr::from_bytes(home.to_bytes())),
+                )));
+            }
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            // Some systems report a missing entry as an error
+            libc::ENOENT | libc::ESRCH | libc::EBADF | libc::EPERM => return Ok(None),
+            errno => return Err(std::io::Error::from_raw_os_error(errno)),
+        }
+    }
+}
+
+/// The groups `name` belongs to according to the group database
+#[cfg(target_os = "linux")]
+fn supplementary_groups(name: &str, gid: u32) -> Vec<u32> {
+    let Ok(name) = std::ffi::CString::new(name) else {
+        return vec![gid];
+    };
+    let mut groups = vec![0; 64];
+    loop {
+        let mut count = groups.len() as libc::c_int;
+        // SAFETY: count is the capacity of groups, which the call fills in
+        let found =
+            unsafe { libc::getgrouplist(name.as_ptr(), gid, groups.as_mut_ptr(), &mut count) };
+        if found >= 0 {
+            groups.truncate(count as usize);
+            return groups;
+        }
+        // count now holds the number of groups
+        groups.resize((count as usize).max(groups.len() * 2), 0);
+    }
+}
+
+/// Elsewhere the task only keeps its primary group
+#[cfg(all(unix, not(target_os = "linux")))]
+fn supplementary_groups(_name: &str, gid: u32) -> Vec<u32> {
+    vec![gid]
+}
+
+/// Run the command as `user` and without capabilities when
+/// `drop_capabilities` is set
+///
+/// Switching to another user needs root; switching to the current user
+/// changes nothing. `HOME`, `USER` and `LOGNAME` are set for the user.
+#[cfg(unix)]
+pub fn apply_to_command(
+    cmd: &mut Command,
+    user: Option<&TaskUser>,
+    drop_capabilities: bool,
+) -> Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: reading the IDs of our own process has no side effects
+    let (euid, uid) = unsafe { (libc::geteuid(), libc::getuid()) };
+    let switch = match user {
+        Some(user) if user.uid == uid && user.uid == euid => None,
+        Some(user) if euid != 0 => {
+            return Err(Error::configuration(format!(
+                "Running a task as user '{}' needs cuenv to run as root",
+                user.name
+            )));
+        }
+        Some(user) => Some((user.uid, user.gid, user.groups.clone())),
+        None => None,
+    };
+    if let Some(user) = user {
+        cmd.env("HOME", &user.home)
+            .env("USER", &user.name)
+            .env("LOGNAME", &user.name);
+    }
+    if switch.is_none() && !drop_capabilities {
+        return Ok(());
+    }
+    if drop_capabilities && !cfg!(target_os = "linux") {
+        return Err(Error::configuration(
+            "Dropping capabilities is only supported on Linux".to_string(),
+        ));
+    }
+
+    // SAFETY: The closure runs in the child between fork() and exec(). It
+    // only makes prctl(), capset() and set*id() calls with data prepared
+    // here, and touches no state shared with the parent.
+    unsafe {
+        cmd.pre_exec(move || {
+            // The bounding set can only shrink while we still hold
+            // CAP_SETPCAP, so before the user switch
+            #[cfg(target_os = "linux")]
+            if drop_capabilities {
+                drop_bounding_capabilities();
+            }
+
+            if let Some((uid, gid, groups)) = &switch {
+                check(libc::setgroups(groups.len() as _, groups.as_ptr()))?;
+                check(libc::setgid(*gid))?;
+                check(libc::setuid(*uid))?;
+            }
+
+            #[cfg(target_os = "linux")]
+            if drop_capabilities {
+                clear_capabilities()?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn apply_to_command(
+    _cmd: &mut Command,
+    _user: Option<&TaskUser>,
+    _drop_capabilities: bool,
+) -> Result<()> {
+    Err(Error::configuration(
+        "Running tasks with fewer privileges is only supported on Unix".to_string(),
+    ))
+}
+
+#[cfg(unix)]
+fn check(result: libc::c_int) -> std::io::Result<()> {
+    if result == -1 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Remove every capability from the bounding set, so that no exec can gain
+/// one. Without CAP_SETPCAP this fails, and `no_new_privs` covers it.
+#[cfg(target_os = "linux")]
+fn drop_bounding_capabilities() {
+    // The kernel rejects capabilities it does not know with EINVAL
+    for capability in 0..64 {
+        // SAFETY: prctl with integer arguments only
+        if unsafe { libc::prctl(libc::PR_CAPBSET_DROP, capability, 0, 0, 0) } == -1
+            && std::io::Error::last_os_error().raw_os_error() == Some(libc::EINVAL)
+        {
+            break;
+        }
+    }
+}
+
+/// Empty the ambient, effective, permitted and inheritable capabilities and
+/// keep setuid and file capabilities from granting new ones
+#[cfg(target_os = "linux")]
+fn clear_capabilities() -> std::io::Result<()> {
+    #[repr(C)]
+    struct Header {
+        version: u32,
+        pid: libc::c_int,
+    }
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Data {
+        effective: u32,
+        permitted: u32,
+        inheritable: u32,
+    }
+    const CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+    let mut header = Header {
+        version: CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+    let data = [Data {
+        effective: 0,
+        permitted: 0,
+        inheritable: 0,
+    }; 2];
+
+    // SAFETY: prctl with integer arguments, and capset with a header and
+    // the two data entries version 3 expects
+    unsafe {
+        check(libc::prctl(
+            libc::PR_CAP_AMBIENT,
+            libc::PR_CAP_AMBIENT_CLEAR_ALL,
+            0,
+            0,
+            0,
+        ))?;
+        check(libc::syscall(libc::SYS_capset, &mut header, data.as_ptr()) as libc::c_int)?;
+        check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let root = TaskUser::lookup("root").unwrap();
+        assert_eq!(root.uid, 0);
+        assert!(root.groups.contains(&root.gid));
+        assert_eq!(TaskUser::lookup("0").unwrap().name, root.name);
+
+        let error = TaskUser::lookup("cuenv-no-such-user").unwrap_err();
+        assert!(error.to_string().contains("does not exist"), "{error}");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_drop_capabilities() {
+        let mut cmd = Command::new("grep");
+        cmd.args(["-E", "^(CapEff|CapBnd|NoNewPrivs)", "/proc/self/status"]);
+        apply_to_command(&mut cmd, None, true).unwrap();
+        let output = cmd.output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+
+        let status = String::from_utf8_lossy(&output.stdout);
+        assert!(status.contains("CapEff:\t0000000000000000"), "{status}");
+        assert!(status.contains("NoNewPrivs:\t1"), "{status}");
+        // SAFETY: no side effects
+        if unsafe { libc::geteuid() } == 0 {
+            assert!(status.contains("CapBnd:\t0000000000000000"), "{status}");
+        }
+    }
+
+    #[test]
+    fn test_switch_user() {
+        let Ok(nobody) = TaskUser::lookup("nobody") else {
+            return;
+        };
+        let mut cmd = Command::new("id");
+        cmd.arg("-u");
+        let result = apply_to_command(&mut cmd, Some(&nobody), false);
+
+        // SAFETY: no side effects
+        if unsafe { libc::geteuid() } != 0 {
+            assert!(result.is_err());
+            return;
+        }
+        result.unwrap();
+        let output = cmd.output().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            nobody.uid.to_string()
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Seccomp profiles for tasks
+//!
+//! A task's `security.seccomp` names the profile applied to its process right
+//! before exec. `default` blocks syscalls build scripts have no business
+//! making, such as `mount`, `ptrace` or `kexec_load`; `strict` only allows
+//! those that shells, compilers and common tools need. Anything else is the
+//! path of a JSON profile, either `{"allow": [...]}` or `{"deny": [...]}`.
+//! Blocked syscalls fail with `EPERM`.
+
+use cuenv_core::{Error, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+
+/// Profile names that are not paths
+pub const BUILTIN_PROFILES: &[&str] = &["default", "strict"];
+
+/// Syscalls the `default` profile blocks: changes to the system, other
+/// processes and kernel state
+const DEFAULT_DENIED: &[&str] = &[
+    "acct",
+    "add_key",
+    "bpf",
+    "clock_adjtime",
+    "clock_settime",
+    "delete_module",
+    "finit_module",
+    "fsconfig",
+    "fsmount",
+    "fsopen",
+    "fspick",
+    "init_module",
+    "kexec_file_load",
+    "kexec_load",
+    "keyctl",
+    "lookup_dcookie",
+    "mount",
+    "mount_setattr",
+    "move_mount",
+    "name_to_handle_at",
+    "nfsservctl",
+    "open_by_handle_at",
+    "open_tree",
+    "perf_event_open",
+    "pivot_root",
+    "process_vm_readv",
+    "process_vm_writev",
+    "ptrace",
+    "quotactl",
+    "reboot",
+    "request_key",
+    "setns",
+    "settimeofday",
+    "swapoff",
+    "swapon",
+    "syslog",
+    "umount2",
+    "unshare",
+    "userfaultfd",
+    "vhangup",
+];
+
+/// Syscalls only x86_64 has that the `default` profile blocks as well
+const DEFAULT_DENIED_X86_64: &[&str] = &[
+    "_sysctl",
+    "create_module",
+    "get_kernel_syms",
+    "ioperm",
+    "iopl",
+    "query_module",
+    "sysfs",
+    "uselib",
+    "ustat",
+];
+
+/// Syscalls the `strict` profile allows: files, memory, processes,
+/// signals, time, polling and sockets
+const STRICT_ALLOWED: &[&str] = &[
+    // Files
+    "read",
+    "write",
+    "readv",
+    "writev",
+    "pread64",
+    "pwrite64",
+    "preadv",
+    "pwritev",
+    "preadv2",
+    "pwritev2",
+    "openat",
+    "openat2",
+    "close",
+    "close_range",
+    "lseek",
+    "fstat",
+    "newfstatat",
+    "statx",
+    "fstatfs",
+    "statfs",
+    "readlinkat",
+    "faccessat",
+    "faccessat2",
+    "getdents64",
+    "getcwd",
+    "chdir",
+    "fchdir",
+    "mkdirat",
+    "unlinkat",
+    "renameat2",
+    "linkat",
+    "symlinkat",
+    "fchmod",
+    "fchmodat",
+    "fchown",
+    "fchownat",
+    "umask",
+    "truncate",
+    "ftruncate",
+    "fallocate",
+    "fsync",
+    "fdatasync",
+    "sync_file_range",
+    "flock",
+    "fcntl",
+    "dup",
+    "dup3",
+    "pipe2",
+    "ioctl",
+    "utimensat",
+    "copy_file_range",
+    "sendfile",
+    "splice",
+    "tee",
+    "inotify_init1",
+    "inotify_add_watch",
+    "inotify_rm_watch",
+    // Memory
+    "mmap",
+    "munmap",
+    "mprotect",
+    "mremap",
+    "madvise",
+    "brk",
+    "mlock",
+    "munlock",
+    "msync",
+    "membarrier",
+    "memfd_create",
+    // Processes and signals
+    "clone",
+    "clone3",
+    "execve",
+    "execveat",
+    "exit",
+    "exit_group",
+    "wait4",
+    "waitid",
+    "kill",
+    "tgkill",
+    "tkill",
+    "rt_sigaction",
+    "rt_sigprocmask",
+    "rt_sigreturn",
+    "rt_sigsuspend",
+    "rt_sigtimedwait",
+    "sigaltstack",
+    "set_tid_address",
+    "set_robust_list",
+    "get_robust_list",
+    "rseq",
+    "futex",
+    "getpid",
+    "getppid",
+    "gettid",
+    "getuid",
+    "geteuid",
+    "getgid",
+    "getegid",
+    "getgroups",
+    "getresuid",
+    "getresgid",
+    "getpgid",
+    "setpgid",
+    "getsid",
+    "setsid",
+    "prlimit64",
+    "getrlimit",
+    "setrlimit",
+    "getrusage",
+    "sysinfo",
+    "times",
+    "uname",
+    "prctl",
+    "sched_yield",
+    "sched_getaffinity",
+    "sched_setaffinity",
+    "sched_getparam",
+    "sched_getscheduler",
+    "getpriority",
+    "setpriority",
+    "getcpu",
+    "getrandom",
+    // Time
+    "clock_gettime",
+    "clock_getres",
+    "clock_nanosleep",
+    "nanosleep",
+    "gettimeofday",
+    // Polling
+    "ppoll",
+    "pselect6",
+    "epoll_create1",
+    "epoll_ctl",
+    "epoll_pwait",
+    "epoll_pwait2",
+    "eventfd2",
+    "timerfd_create",
+    "timerfd_settime",
+    "timerfd_gettime",
+    "signalfd4",
+    // Sockets
+    "socket",
+    "socketpair",
+    "connect",
+    "bind",
+    "listen",
+    "accept",
+    "accept4",
+    "getsockname",
+    "getpeername",
+    "sendto",
+    "recvfrom",
+    "sendmsg",
+    "recvmsg",
+    "sendmmsg",
+    "recvmmsg",
+    "shutdown",
+    "setsockopt",
+    "getsockopt",
+];
+
+/// The older variants x86_64 still has of syscalls in [`STRICT_ALLOWED`]
+const STRICT_ALLOWED_X86_64: &[&str] = &[
+    "access",
+    "alarm",
+    "arch_prctl",
+    "chmod",
+    "chown",
+    "creat",
+    "dup2",
+    "epoll_create",
+    "epoll_wait",
+    "eventfd",
+    "fork",
+    "getdents",
+    "getpgrp",
+    "inotify_init",
+    "lchown",
+    "link",
+    "lstat",
+    "mkdir",
+    "open",
+    "pipe",
+    "poll",
+    "readlink",
+    "rename",
+    "renameat",
+    "rmdir",
+    "select",
+    "signalfd",
+    "stat",
+    "symlink",
+    "time",
+    "unlink",
+    "utimes",
+    "vfork",
+];
+
+/// The syscalls a task may make
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeccompProfile {
+    /// Everything but the listed syscalls is allowed
+    Deny(BTreeSet<String>),
+    /// Only the listed syscalls are allowed
+    Allow(BTreeSet<String>),
+}
+
+/// The contents of a profile file
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+enum ProfileFile {
+    Allow(Vec<String>),
+    Deny(Vec<String>),
+}
+
+impl SeccompProfile {
+    /// The profile `security.seccomp` names: `default`, `strict` or the path
+    /// of a profile file
+    pub fn load(name: &str) -> Result<Self> {
+        match name {
+            "default" => Ok(Self::Deny(builtin(DEFAULT_DENIED, DEFAULT_DENIED_X86_64))),
+            "strict" => Ok(Self::Allow(builtin(STRICT_ALLOWED, STRICT_ALLOWED_X86_64))),
+            path => Self::from_file(Path::new(path)),
+        }
+    }
+
+    /// Read a JSON profile, `{"allow": [...]}` or `{"deny": [...]}`
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            Error::configuration(format!(
+                "Failed to read seccomp profile {}: {e}",
+                path.display()
+            ))
+        })?;
+        let file: ProfileFile = serde_json::from_str(&content).map_err(|e| {
+            Error::configuration(format!(
+                "Invalid seccomp profile {}: expected {{\"allow\": [...]}} or {{\"deny\": [...]}}: {e}",
+                path.display()
+            ))
+        })?;
+
+        Ok(match file {
+            ProfileFile::Allow(names) => Self::Allow(names.into_iter().collect()),
+            ProfileFile::Deny(names) => Self::Deny(names.into_iter().collect()),
+        })
+    }
+
+    /// Whether the profile blocks a syscall
+    pub fn blocks(&self, syscall: &str) -> bool {
+        match self {
+            Self::Deny(names) => names.contains(syscall),
+            Self::Allow(names) => !names.contains(syscall),
+        }
+    }
+
+    /// Compile the profile into a BPF program for the running architecture.
+    /// Unknown syscall names are an error.
+    #[cfg(target_os = "linux")]
+    pub fn compile(&self) -> Result<seccompiler::BpfProgram> {
+        use seccompiler::TargetArch;
+        use serde_json::json;
+
+        let arch = TargetArch::try_from(std::env::consts::ARCH).map_err(|_| {
+            Error::configuration(format!(
+                "Seccomp profiles are not supported on {}",
+                std::env::consts::ARCH
+            ))
+        })?;
+
+        let blocked = json!({ "errno": libc::EPERM });
+        let (names, mismatch_action, match_action) = match self {
+            Self::Deny(names) => (names, json!("allow"), blocked),
+            Self::Allow(names) => (names, blocked, json!("allow")),
+        };
+        let filter = json!({
+            "task": {
+                "mismatch_action": mismatch_action,
+                "match_action": match_action,
+                "filter": names
+                    .iter()
+                    .map(|name| json!({ "syscall": name }))
+                    .collect::<Vec<_>>(),
+            }
+        });
+
+        seccompiler::compile_from_json(filter.to_string().as_bytes(), arch)
+            .map_err(|e| Error::configuration(format!("Invalid seccomp profile: {e}")))?
+            .remove("task")
+            .ok_or_else(|| Error::configuration("Invalid seccomp profile".to_string()))
+    }
+
+    /// Apply the profile to the command's process right before exec
+    #[cfg(target_os = "linux")]
+    pub fn apply_to_command(&self, cmd: &mut Command) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+
+        // Compiled here, so that mistakes in the profile are reported rather
+        // than failing in the child
+        let program = self.compile()?;
+
+        // SAFETY: The closure runs in the child between fork() and exec(). It
+        // only installs the already compiled filter with prctl() and
+        // seccomp(), and touches no state shared with the parent.
+        unsafe {
+            cmd.pre_exec(move || {
+                seccompiler::apply_filter(&program).map_err(|e| {
+                    std::io::Error::other(format!("Failed to apply seccomp profile: {e}"))
+                })
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply_to_command(&self, _cmd: &mut Command) -> Result<()> {
+        Err(Error::configuration(
+            "Seccomp profiles are only supported on Linux".to_string(),
+        ))
+    }
+}
+
+/// A builtin list, with the syscalls that only exist on x86_64 when running
+/// there. Names the architecture does not know cannot be compiled.
+fn builtin(names: &[&str], x86_64_names: &[&str]) -> BTreeSet<String> {
+    let x86_64_names = if cfg!(target_arch = "x86_64") {
+        x86_64_names
+    } else {
+        &[]
+    };
+    names
+        .iter()
+        .chain(x86_64_names)
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_builtin_profiles() {
+        let default = SeccompProfile::load("default").unwrap();
+        assert!(default.blocks("ptrace"));
+        assert!(default.blocks("mount"));
+        assert!(!default.blocks("openat"));
+
+        let strict = SeccompProfile::load("strict").unwrap();
+        assert!(!strict.blocks("execve"));
+        assert!(!strict.blocks("openat"));
+        assert!(strict.blocks("ptrace"));
+        assert!(strict.blocks("bpf"));
+    }
+
+    #[test]
+    fn test_profile_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"{{"deny": ["chmod", "fchmodat"]}}"#).unwrap();
+        let profile = SeccompProfile::from_file(file.path()).unwrap();
+        assert!(profile.blocks("fchmodat"));
+        assert!(!profile.blocks("openat"));
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"{{"allow": ["read"], "deny": ["write"]}}"#).unwrap();
+        let error = SeccompProfile::from_file(file.path()).unwrap_err();
+        assert!(error.to_string().contains("expected"), "{error}");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_compile() {
+        for name in BUILTIN_PROFILES {
+            let profile = SeccompProfile::load(name).unwrap();
+            assert!(!profile.compile().unwrap().is_empty(), "{name}");
+        }
+
+        let unknown = SeccompProfile::Deny(BTreeSet::from(["not_a_syscall".to_string()]));
+        assert!(unknown.compile().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_blocked_syscalls_fail() {
+        let profile = SeccompProfile::Deny(BTreeSet::from(["uname".to_string()]));
+        let mut cmd = Command::new("uname");
+        profile.apply_to_command(&mut cmd).unwrap();
+        assert!(!cmd.output().unwrap().status.success());
+
+        // Without the profile the same command works
+        assert!(Command::new("uname").output().unwrap().status.success());
+    }
+}
@@ -0,0 +1,212 @@
+//! Encrypted per-user secret store
+//!
+//! `cuenv secret set NAME` keeps a value in the user's config directory,
+//! encrypted with [age](https://age-encryption.org) to a key generated for
+//! this machine. An env.cue refers to it with `{ fromLocalStore: "NAME" }`,
+//! so developer-specific secrets stay out of the repository and out of
+//! plaintext files.
+//!
+//! Layout of the store directory:
+//!
+//! ```text
+//! secrets/
+//!   machine.key      age identity, readable only by the user
+//!   NAME.age         one encrypted file per secret
+//! ```
+
+use age::secrecy::ExposeSecret;
+use age::x25519::Identity;
+use cuenv_core::{Error, Result};
+use cuenv_utils::atomic_file::write_private;
+use cuenv_utils::xdg::XdgPaths;
+use std::fs;
+use std::path::PathBuf;
+
+const KEY_FILE: &str = "machine.key";
+const SECRET_EXTENSION: &str = "age";
+
+/// Secrets stored encrypted on this machine
+#[derive(Debug, Clone)]
+pub struct SecretStore {
+    dir: PathBuf,
+}
+
+impl SecretStore {
+    /// A store rooted at `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The store in the user's cuenv config directory
+    pub fn user() -> Self {
+        Self::new(XdgPaths::config_dir().join("secrets"))
+    }
+
+    /// Encrypt and store `value` under `name`, replacing any previous value
+    pub fn set(&self, name: &str, value: &str) -> Result<()> {
+        validate_name(name)?;
+        let identity = self.machine_identity_or_generate()?;
+        let encrypted = age::encrypt(&identity.to_public(), value.as_bytes())
+            .map_err(|e| Error::configuration(format!("Failed to encrypt secret {name}: {e}")))?;
+
+        write_private(&self.secret_path(name), &encrypted)
+    }
+
+    /// Decrypt the value stored under `name`
+    pub fn get(&self, name: &str) -> Result<Option<String>> {
+        validate_name(name)?;
+        let path = self.secret_path(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let encrypted = fs::read(&path).map_err(|e| Error::file_system(&path, "read secret", e))?;
+        let identity = self.machine_identity()?.ok_or_else(|| {
+            Error::configuration(format!(
+                "Cannot decrypt secret {name}: the machine key in {} is missing",
+                self.dir.display()
+            ))
+        })?;
+        let decrypted = age::decrypt(&identity, &encrypted).map_err(|e| {
+            Error::configuration(format!(
+                "Failed to decrypt secret {name}: {e}. Was it stored on another machine?"
+            ))
+        })?;
+
+        String::from_utf8(decrypted)
+            .map(Some)
+            .map_err(|_| Error::configuration(format!("Secret {name} is not valid UTF-8")))
+    }
+
+    /// Delete the value stored under `name`, returning whether it existed
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        validate_name(name)?;
+        let path = self.secret_path(name);
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        fs::remove_file(&path).map_err(|e| Error::file_system(&path, "remove secret", e))?;
+        Ok(true)
+    }
+
+    /// Names of all stored secrets, sorted
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::file_system(&self.dir, "read secret store", e)),
+        };
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == SECRET_EXTENSION))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn secret_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.{SECRET_EXTENSION}"))
+    }
+
+    /// This machine's key, if one was generated
+    fn machine_identity(&self) -> Result<Option<Identity>> {
+        let path = self.dir.join(KEY_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let key = fs::read_to_string(&path)
+            .map_err(|e| Error::file_system(&path, "read machine key", e))?;
+        key.trim().parse().map(Some).map_err(|e| {
+            Error::configuration(format!("Invalid machine key {}: {e}", path.display()))
+        })
+    }
+
+    /// This machine's key, generated on first use
+    fn machine_identity_or_generate(&self) -> Result<Identity> {
+        if let Some(identity) = self.machine_identity()? {
+            return Ok(identity);
+        }
+
+        let identity = Identity::generate();
+        write_private(
+            &self.dir.join(KEY_FILE),
+            identity.to_string().expose_secret().as_bytes(),
+        )?;
+        Ok(identity)
+    }
+}
+
+/// Secret names become file names, so keep them to environment variable characters
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+        && !name.starts_with('.');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::configuration(format!(
+            "Invalid secret name {name:?}: use letters, digits, '_', '-' and '.'"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = SecretStore::new(temp.path());
+
+        assert_eq!(store.get("MY_TOKEN").unwrap(), None);
+        store.set("MY_TOKEN", "s3cr3t-value").unwrap();
+        assert_eq!(
+            store.get("MY_TOKEN").unwrap().as_deref(),
+            Some("s3cr3t-value")
+        );
+
+        // The value is not stored in plaintext
+        let stored = fs::read(temp.path().join("MY_TOKEN.age")).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("s3cr3t-value"));
+
+        assert_eq!(store.list().unwrap(), vec!["MY_TOKEN".to_string()]);
+        assert!(store.remove("MY_TOKEN").unwrap());
+        assert!(!store.remove("MY_TOKEN").unwrap());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_other_machine_key_cannot_decrypt() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = SecretStore::new(temp.path());
+        store.set("MY_TOKEN", "s3cr3t-value").unwrap();
+
+        fs::remove_file(temp.path().join(KEY_FILE)).unwrap();
+        assert!(store.get("MY_TOKEN").is_err());
+
+        // A new key is generated, which cannot read the old secret
+        store.set("OTHER", "another-value").unwrap();
+        assert!(store.get("MY_TOKEN").is_err());
+        assert_eq!(
+            store.get("OTHER").unwrap().as_deref(),
+            Some("another-value")
+        );
+    }
+
+    #[test]
+    fn test_rejects_path_like_names() {
+        let store = SecretStore::new("/nonexistent");
+        assert!(store.get("../etc/passwd").is_err());
+        assert!(store.get(".hidden").is_err());
+        assert!(store.get("").is_err());
+    }
+}
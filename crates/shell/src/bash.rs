@@ -1,4 +1,4 @@
-use super::{escape_bash_like, Shell};
+use super::{quote, Shell};
 
 pub struct BashShell;
 
@@ -7,7 +7,7 @@ impl Shell for BashShell {
         r#"_cuenv_hook() {
   local previous_exit_status=$?
   trap -- '' SIGINT
-  eval "$(cuenv hook bash)"
+  eval "$(cuenv shell hook bash)"
   trap - SIGINT
   return $previous_exit_status
 }
@@ -18,7 +18,14 @@ if [[ ";${PROMPT_COMMAND[*]:-};" != *";_cuenv_hook;"* ]]; then
   else
     PROMPT_COMMAND="_cuenv_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
   fi
-fi"#
+fi
+
+# Status segment for PS1/PROMPT, empty outside a loaded directory
+cuenv_prompt() {
+  if [[ -n "${CUENV_DIR:-}" ]]; then
+    command cuenv prompt "$@"
+  fi
+}"#
         .to_string()
     }
 
@@ -28,12 +35,18 @@ fi"#
         format!("export {key}={}", self.escape(value))
     }
 
+    #[cfg(unix)]
+    fn export_os(&self, key: &str, value: &std::ffi::OsStr) -> String {
+        use std::os::unix::ffi::OsStrExt;
+        format!("export {key}={}", quote::posix_bytes(value.as_bytes()))
+    }
+
     fn unset(&self, key: &str) -> String {
         format!("unset {key}")
     }
 
     fn escape(&self, s: &str) -> String {
-        escape_bash_like(s)
+        quote::posix(s)
     }
 }
 
@@ -49,6 +62,18 @@ mod tests {
         assert_eq!(shell.export("FOO", "it's"), "export FOO='it'\"'\"'s'");
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_bash_export_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let shell = BashShell;
+        let value = std::ffi::OsStr::from_bytes(b"caf\xe9 'x'");
+        assert_eq!(
+            shell.export_os("FOO", value),
+            r"export FOO=$'caf\xe9 \'x\''"
+        );
+    }
+
     #[test]
     fn test_bash_unset() {
         let shell = BashShell;
@@ -62,5 +87,6 @@ mod tests {
         assert!(hook.contains("_cuenv_hook"));
         assert!(hook.contains("PROMPT_COMMAND"));
         assert!(hook.contains("declare -a"));
+        assert!(hook.contains("cuenv_prompt()"));
     }
 }
@@ -1,37 +1,101 @@
-use super::Shell;
+use super::{quote, Shell};
 
 pub struct CmdShell;
 
 impl Shell for CmdShell {
+    /// A Clink script, as cmd.exe itself has no prompt hook or functions.
+    /// Clink runs Lua inside the cmd process, so the `set` statements the
+    /// hook prints are parsed and applied with `os.setenv`.
     fn hook(&self) -> String {
-        // CMD doesn't support automatic hooks, provide manual function
-        r#":: cuenv hook for cmd.exe
-:: Call _cuenv_hook manually when changing directories
-doskey _cuenv_hook=FOR /F "tokens=*" %i IN ('cuenv hook cmd') DO %i"#
+        r#"-- cuenv hook for cmd.exe, loaded by Clink (https://chrisant996.github.io/clink)
+-- Save it in a Clink scripts directory:
+--   cuenv shell init cmd > "%LOCALAPPDATA%\clink\cuenv.lua"
+-- Without Clink, apply the environment of the current directory manually:
+--   for /f "delims=" %i in ('cuenv shell hook cmd') do @%i
+
+-- The variable a `set` statement assigns, undoing cmd's quoting: `^`
+-- escapes a character outside quotes, and set drops the first `"` and
+-- everything from the last one
+local function cuenv_parse_set(line)
+    local arg = line:match("^set (.*)$")
+    if not arg then
+        return nil
+    end
+    local chars, quoted, escaped = {}, false, false
+    for c in arg:gmatch(".") do
+        if escaped then
+            table.insert(chars, c)
+            escaped = false
+        elseif c == "^" and not quoted then
+            escaped = true
+        else
+            if c == '"' then
+                quoted = not quoted
+            end
+            table.insert(chars, c)
+        end
+    end
+    arg = table.concat(chars)
+    if arg:sub(1, 1) == '"' then
+        arg = arg:match('^"(.*)"') or arg:sub(2)
+    end
+    return arg:match("^([^=]+)=(.*)$")
+end
+
+local cuenv_hook = clink.promptfilter(1)
+
+function cuenv_hook:filter(prompt)
+    local output = io.popen("cuenv shell hook cmd")
+    if not output then
+        return nil
+    end
+    for line in output:lines() do
+        local name, value = cuenv_parse_set((line:gsub("\r$", "")))
+        if name and value == "" then
+            os.unsetenv(name)
+        elseif name then
+            os.setenv(name, value)
+        end
+    end
+    output:close()
+    return nil
+end
+
+-- Status segment for custom prompts, empty outside a loaded directory
+function cuenv_prompt(...)
+    if not os.getenv("CUENV_DIR") then
+        return ""
+    end
+    local output = io.popen("cuenv prompt " .. table.concat({ ... }, " "))
+    if not output then
+        return ""
+    end
+    local segment = output:read("*l") or ""
+    output:close()
+    return segment
+end"#
             .to_string()
     }
 
+    fn hook_export(&self, key: &str, value: &str) -> String {
+        format!("os.setenv({}, {})", quote::lua(key), quote::lua(value))
+    }
+
     fn export(&self, key: &str, value: &str) -> String {
-        format!("set {key}={}", self.escape(value))
+        format!("set {}", self.escape(&format!("{key}={value}")))
     }
 
     fn unset(&self, key: &str) -> String {
-        format!("set {key}=")
+        format!("set {}", self.escape(&format!("{key}=")))
     }
 
+    fn accepts_name(&self, key: &str) -> bool {
+        !key.is_empty() && !key.contains(['=', '"', '%', '\r', '\n'])
+    }
+
+    /// Quote the `name=value` argument of `set`
     fn escape(&self, s: &str) -> String {
-        // CMD has limited escaping capabilities
-        // Just handle basic cases
-        if s.contains(' ')
-            || s.contains('&')
-            || s.contains('|')
-            || s.contains('>')
-            || s.contains('<')
-        {
-            format!("\"{}\"", s.replace('"', "\"\""))
-        } else {
-            s.to_string()
-        }
+        quote::cmd(s)
     }
 }
 
@@ -43,7 +107,11 @@ mod tests {
     fn test_cmd_export() {
         let shell = CmdShell;
         assert_eq!(shell.export("FOO", "bar"), "set FOO=bar");
-        assert_eq!(shell.export("FOO", "bar baz"), "set FOO=\"bar baz\"");
+        assert_eq!(shell.export("FOO", "bar baz"), "set \"FOO=bar baz\"");
+        assert_eq!(
+            shell.export("Path", r"C:\Program Files (x86)"),
+            r#"set "Path=C:\Program Files (x86)""#
+        );
     }
 
     #[test]
@@ -60,11 +128,21 @@ mod tests {
         assert_eq!(shell.escape("hello&world"), "\"hello&world\"");
     }
 
+    #[test]
+    fn test_cmd_hook_export() {
+        let shell = CmdShell;
+        assert_eq!(
+            shell.hook_export("CUENV_FILE", r"env.cue:C:\cfg\app.cue"),
+            r#"os.setenv("CUENV_FILE", "env.cue:C:\\cfg\\app.cue")"#
+        );
+    }
+
     #[test]
     fn test_cmd_hook() {
         let shell = CmdShell;
         let hook = shell.hook();
-        assert!(hook.contains("_cuenv_hook"));
-        assert!(hook.contains("doskey"));
+        assert!(hook.contains("clink.promptfilter"));
+        assert!(hook.contains("cuenv shell hook cmd"));
+        assert!(hook.contains("function cuenv_prompt"));
     }
 }
@@ -1,4 +1,4 @@
-use super::Shell;
+use super::{quote, Shell};
 
 pub struct ElvishShell;
 
@@ -8,7 +8,7 @@ impl Shell for ElvishShell {
   try {
     $@args
   } finally {
-    eval (cuenv hook elvish | slurp)
+    eval (cuenv shell hook elvish | slurp)
   }
 }"#
         .to_string()
@@ -23,24 +23,7 @@ impl Shell for ElvishShell {
     }
 
     fn escape(&self, s: &str) -> String {
-        // Elvish uses single quotes for literal strings
-        if s.is_empty() {
-            return "''".to_string();
-        }
-
-        let mut result = String::with_capacity(s.len() + 2);
-        result.push('\'');
-
-        for c in s.chars() {
-            if c == '\'' {
-                result.push_str("''");
-            } else {
-                result.push(c);
-            }
-        }
-
-        result.push('\'');
-        result
+        quote::elvish(s)
     }
 }
 
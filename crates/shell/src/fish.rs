@@ -1,10 +1,10 @@
-use super::Shell;
+use super::{quote, Shell};
 
 pub struct FishShell;
 
 impl Shell for FishShell {
     fn hook(&self) -> String {
-        r#"function _cuenv_hook --on-variable PWD --description 'cuenv hook'
+        r#"function _cuenv_hook --on-event fish_prompt --description 'cuenv hook'
   set -l prev_status $status
   cuenv shell hook fish | source
   if test $prev_status -ne 0
@@ -13,7 +13,14 @@ impl Shell for FishShell {
 end
 
 # Trigger the hook for the initial directory
-_cuenv_hook"#
+_cuenv_hook
+
+# Status segment for fish_prompt, empty outside a loaded directory
+function cuenv_prompt --description 'cuenv status for the prompt'
+  if set -q CUENV_DIR
+    command cuenv prompt $argv
+  end
+end"#
             .to_string()
     }
 
@@ -21,58 +28,18 @@ _cuenv_hook"#
         format!("set -gx {key} {}", self.escape(value))
     }
 
+    #[cfg(unix)]
+    fn export_os(&self, key: &str, value: &std::ffi::OsStr) -> String {
+        use std::os::unix::ffi::OsStrExt;
+        format!("set -gx {key} {}", quote::fish_bytes(value.as_bytes()))
+    }
+
     fn unset(&self, key: &str) -> String {
         format!("set -e {key}")
     }
 
     fn escape(&self, s: &str) -> String {
-        if s.is_empty() {
-            return "''".to_string();
-        }
-
-        let needs_quotes = s.chars().any(|c| {
-            matches!(
-                c,
-                ' ' | '\t'
-                    | '\n'
-                    | '\r'
-                    | '$'
-                    | '&'
-                    | '|'
-                    | '<'
-                    | '>'
-                    | '('
-                    | ')'
-                    | '{'
-                    | '}'
-                    | '['
-                    | ']'
-                    | '*'
-                    | '?'
-                    | ';'
-                    | '"'
-                    | '\''
-                    | '\\'
-            )
-        });
-
-        if !needs_quotes {
-            return s.to_string();
-        }
-
-        let mut result = String::with_capacity(s.len() + 2);
-        result.push('\'');
-
-        for c in s.chars() {
-            match c {
-                '\'' => result.push_str("\'\\\'\'"),
-                '\\' => result.push_str("\\\\"),
-                _ => result.push(c),
-            }
-        }
-
-        result.push('\'');
-        result
+        quote::fish(s)
     }
 }
 
@@ -108,6 +75,7 @@ mod tests {
         let shell = FishShell;
         let hook = shell.hook();
         assert!(hook.contains("_cuenv_hook"));
-        assert!(hook.contains("--on-variable PWD"));
+        assert!(hook.contains("--on-event fish_prompt"));
+        assert!(hook.contains("function cuenv_prompt"));
     }
 }
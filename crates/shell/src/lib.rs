@@ -9,9 +9,12 @@ pub mod elvish;
 pub mod fish;
 pub mod mod_shell;
 pub mod murex;
+pub mod osc;
 pub mod pwsh;
+pub mod quote;
 pub mod shell_hook;
 pub mod tcsh;
+pub mod tmux;
 pub mod zsh;
 
 pub use bash::*;
@@ -24,3 +27,11 @@ pub use pwsh::*;
 pub use shell_hook::*;
 pub use tcsh::*;
 pub use zsh::*;
+
+/// Whether an opt-in variable such as `CUENV_TMUX` is switched on
+pub(crate) fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
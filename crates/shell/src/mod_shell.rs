@@ -1,7 +1,9 @@
+use cuenv_env::EnvDiff;
 use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::path::Path;
 
-use crate::{bash, cmd, elvish, fish, murex, pwsh, tcsh, zsh};
+use crate::{bash, cmd, elvish, fish, murex, pwsh, quote, tcsh, zsh};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ShellType {
@@ -19,17 +21,64 @@ pub enum ShellType {
 pub trait Shell {
     fn hook(&self) -> String;
 
+    /// A statement of the hook script setting a variable, such as
+    /// `CUENV_FILE` during `cuenv shell init`. Hooks are written in the
+    /// shell's own language, except where the shell needs a plugin.
+    fn hook_export(&self, key: &str, value: &str) -> String {
+        self.export(key, value)
+    }
+
     fn export(&self, key: &str, value: &str) -> String;
 
+    /// Export a value that may not be Unicode. Shells that cannot write
+    /// arbitrary bytes get the value with invalid sequences replaced.
+    fn export_os(&self, key: &str, value: &OsStr) -> String {
+        self.export(key, &value.to_string_lossy())
+    }
+
     fn unset(&self, key: &str) -> String;
 
+    /// Whether the shell can assign a variable of this name. Names that
+    /// would need quoting are never emitted, as they could inject commands.
+    fn accepts_name(&self, key: &str) -> bool {
+        quote::is_name(key)
+    }
+
     fn dump(&self, env: &HashMap<String, String>) -> String {
-        env.iter()
+        let mut env: Vec<_> = env.iter().filter(|(k, _)| self.accepts_name(k)).collect();
+        env.sort();
+        env.into_iter()
             .map(|(k, v)| self.export(k, v))
             .collect::<Vec<_>>()
             .join("\n")
     }
 
+    /// The statements that apply `diff`, one per line: removed variables
+    /// are unset and the others exported
+    fn apply(&self, diff: &EnvDiff) -> String {
+        let mut removed: Vec<&str> = diff.removed().into_iter().collect();
+        removed.sort_unstable();
+        let mut changed: Vec<(&str, &str)> = diff.added_or_changed().into_iter().collect();
+        changed.sort_unstable();
+
+        let mut output = String::new();
+        for key in removed {
+            if self.accepts_name(key) {
+                output.push_str(&self.unset(key));
+                output.push('\n');
+            }
+        }
+        for (key, value) in changed {
+            if self.accepts_name(key) {
+                output.push_str(&self.export(key, value));
+                output.push('\n');
+            } else {
+                log::warn!("Not exporting {key}: the name cannot be assigned in this shell");
+            }
+        }
+        output
+    }
+
     fn escape(&self, s: &str) -> String;
 }
 
@@ -41,6 +90,11 @@ impl ShellType {
             .unwrap_or(arg0);
 
         let shell_name = shell_name.strip_prefix('-').unwrap_or(shell_name);
+        // Windows executables, like pwsh.exe or powershell.exe
+        let shell_name = shell_name
+            .to_ascii_lowercase()
+            .strip_suffix(".exe")
+            .map_or(shell_name, |stem| &shell_name[..stem.len()]);
 
         Self::from_name(shell_name)
     }
@@ -88,32 +142,6 @@ impl ShellType {
     }
 }
 
-pub fn escape_bash_like(s: &str) -> String {
-    if s.is_empty() {
-        return "''".to_string();
-    }
-
-    if s.chars()
-        .all(|c| c.is_alphanumeric() || c == '_' || c == '=' || c == '/' || c == '.')
-    {
-        return s.to_string();
-    }
-
-    let mut result = String::with_capacity(s.len() + 10);
-    result.push('\'');
-
-    for c in s.chars() {
-        if c == '\'' {
-            result.push_str("'\"'\"'");
-        } else {
-            result.push(c);
-        }
-    }
-
-    result.push('\'');
-    result
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,6 +155,18 @@ mod tests {
         assert_eq!(ShellType::detect_from_arg("-zsh"), ShellType::Zsh);
         assert_eq!(ShellType::detect_from_arg("fish"), ShellType::Fish);
         assert_eq!(ShellType::detect_from_arg("pwsh"), ShellType::PowerShell);
+        assert_eq!(
+            ShellType::detect_from_arg("/usr/local/bin/pwsh"),
+            ShellType::PowerShell
+        );
+        assert_eq!(
+            ShellType::detect_from_arg("pwsh.exe"),
+            ShellType::PowerShell
+        );
+        assert_eq!(
+            ShellType::detect_from_arg("powershell.EXE"),
+            ShellType::PowerShell
+        );
         assert_eq!(
             ShellType::detect_from_arg("unknown"),
             ShellType::Unknown("unknown".to_string())
@@ -134,11 +174,26 @@ mod tests {
     }
 
     #[test]
-    fn test_escape_bash_like() {
-        assert_eq!(escape_bash_like(""), "''");
-        assert_eq!(escape_bash_like("hello"), "hello");
-        assert_eq!(escape_bash_like("hello world"), "'hello world'");
-        assert_eq!(escape_bash_like("it's"), "'it'\"'\"'s'");
-        assert_eq!(escape_bash_like("$HOME"), "'$HOME'");
+    fn test_apply_diff() {
+        let prev = HashMap::from([
+            ("OLD".to_string(), "1".to_string()),
+            ("KEEP".to_string(), "same".to_string()),
+        ]);
+        let next = HashMap::from([
+            ("KEEP".to_string(), "same".to_string()),
+            ("B".to_string(), "it's".to_string()),
+            ("A".to_string(), "$HOME".to_string()),
+            ("X;rm -rf ~".to_string(), "x".to_string()),
+        ]);
+        let diff = EnvDiff::new(prev, next);
+
+        assert_eq!(
+            bash::BashShell.apply(&diff),
+            "unset OLD\nexport A='$HOME'\nexport B='it'\"'\"'s'\n"
+        );
+        assert_eq!(
+            fish::FishShell.apply(&diff),
+            "set -e OLD\nset -gx A '$HOME'\nset -gx B 'it'\\''s'\n"
+        );
     }
 }
@@ -1,11 +1,11 @@
-use super::Shell;
+use super::{quote, Shell};
 
 pub struct MurexShell;
 
 impl Shell for MurexShell {
     fn hook(&self) -> String {
         r#"event onPrompt cuenv {
-    cuenv hook murex -> source
+    cuenv shell hook murex -> source
 }"#
         .to_string()
     }
@@ -19,23 +19,7 @@ impl Shell for MurexShell {
     }
 
     fn escape(&self, s: &str) -> String {
-        // Murex uses JSON-style string escaping
-        let mut result = String::with_capacity(s.len() + 2);
-        result.push('"');
-
-        for c in s.chars() {
-            match c {
-                '"' => result.push_str("\\\""),
-                '\\' => result.push_str("\\\\"),
-                '\n' => result.push_str("\\n"),
-                '\r' => result.push_str("\\r"),
-                '\t' => result.push_str("\\t"),
-                _ => result.push(c),
-            }
-        }
-
-        result.push('"');
-        result
+        quote::murex(s)
     }
 }
 
@@ -46,8 +30,8 @@ mod tests {
     #[test]
     fn test_murex_export() {
         let shell = MurexShell;
-        assert_eq!(shell.export("FOO", "bar"), r#"export FOO = "bar""#);
-        assert_eq!(shell.export("FOO", "bar baz"), r#"export FOO = "bar baz""#);
+        assert_eq!(shell.export("FOO", "bar"), "export FOO = 'bar'");
+        assert_eq!(shell.export("FOO", "bar baz"), "export FOO = 'bar baz'");
     }
 
     #[test]
@@ -59,8 +43,9 @@ mod tests {
     #[test]
     fn test_murex_escape() {
         let shell = MurexShell;
-        assert_eq!(shell.escape("hello"), r#""hello""#);
-        assert_eq!(shell.escape(r#"hello "world""#), r#""hello \"world\"""#);
-        assert_eq!(shell.escape("line1\nline2"), r#""line1\nline2""#);
+        assert_eq!(shell.escape("hello"), "'hello'");
+        assert_eq!(shell.escape(r#"hello "world""#), r#"'hello "world"'"#);
+        assert_eq!(shell.escape("line1\nline2"), "'line1\nline2'");
+        assert_eq!(shell.escape(r#"it's "$HOME""#), r#""it's \"\$HOME\"""#);
     }
 }
@@ -0,0 +1,90 @@
+//! Terminal escape sequences announcing the directory and the loaded environment
+//!
+//! With `CUENV_OSC=1` the hook writes, on every prompt, OSC 7 with the working
+//! directory and OSC 1337 `SetUserVar` sequences with the loaded project and
+//! environment. WezTerm, kitty and iTerm2 read these, so tab titles and status
+//! bars can show which environment a pane has loaded.
+
+use crate::is_truthy;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use cuenv_core::constants::CUENV_OSC_VAR;
+use std::path::Path;
+
+/// The user variable holding the name of the loaded directory
+pub const PROJECT_VAR: &str = "cuenv_project";
+/// The user variable holding the selected environments
+pub const ENVIRONMENT_VAR: &str = "cuenv_environment";
+
+/// Whether `CUENV_OSC` asks for the sequences
+pub fn enabled() -> bool {
+    std::env::var(CUENV_OSC_VAR).is_ok_and(|value| is_truthy(&value))
+}
+
+/// The sequences for a prompt in `dir`. The user variables are emptied
+/// outside a loaded directory, so terminals stop showing the last project.
+pub fn sequences(dir: &Path, project: Option<&str>, environment: Option<&str>) -> String {
+    let host = hostname::get()
+        .map(|host| host.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    [
+        cwd(&host, dir),
+        user_var(PROJECT_VAR, project.unwrap_or_default()),
+        user_var(ENVIRONMENT_VAR, environment.unwrap_or_default()),
+    ]
+    .concat()
+}
+
+/// OSC 7, the working directory as a `file://` URL
+pub fn cwd(host: &str, dir: &Path) -> String {
+    format!("\x1b]7;file://{host}{}\x1b\\", url_path(dir))
+}
+
+/// OSC 1337 `SetUserVar`, which takes the value in base64
+pub fn user_var(name: &str, value: &str) -> String {
+    format!("\x1b]1337;SetUserVar={name}={}\x07", STANDARD.encode(value))
+}
+
+/// The percent-encoded path of a `file://` URL
+fn url_path(dir: &Path) -> String {
+    let mut path = String::new();
+    // Drive letters become `/C:/...`
+    if cfg!(windows) {
+        path.push('/');
+    }
+    for &byte in dir.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'\\' if cfg!(windows) => path.push('/'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                path.push(byte as char)
+            }
+            _ => path.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cwd() {
+        assert_eq!(
+            cwd("devbox", Path::new("/home/dev/my project/ü")),
+            "\x1b]7;file://devbox/home/dev/my%20project/%C3%BC\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_user_var() {
+        assert_eq!(
+            user_var(ENVIRONMENT_VAR, "staging"),
+            "\x1b]1337;SetUserVar=cuenv_environment=c3RhZ2luZw==\x07"
+        );
+        assert_eq!(
+            user_var(PROJECT_VAR, ""),
+            "\x1b]1337;SetUserVar=cuenv_project=\x07"
+        );
+    }
+}
@@ -1,41 +1,63 @@
-use super::Shell;
+use super::{quote, Shell};
 
 pub struct PwshShell;
 
 impl Shell for PwshShell {
     fn hook(&self) -> String {
-        r#"$Global:_cuenvOriginalPrompt = $function:prompt
-function global:prompt {
-    $null = & cuenv hook pwsh | Out-String | Invoke-Expression
-    & $Global:_cuenvOriginalPrompt
+        r#"if (-not (Test-Path variable:global:_cuenvOriginalPrompt)) {
+    $Global:_cuenvOriginalPrompt = $function:prompt
+    function global:prompt {
+        $previousExitCode = $Global:LASTEXITCODE
+        $null = & cuenv shell hook pwsh | Out-String | Invoke-Expression
+        $Global:LASTEXITCODE = $previousExitCode
+        & $Global:_cuenvOriginalPrompt
+    }
+}
+
+# Status segment for custom prompts, empty outside a loaded directory
+function global:cuenv_prompt {
+    if ($env:CUENV_DIR) { & cuenv prompt @args }
 }"#
         .to_string()
     }
 
     fn export(&self, key: &str, value: &str) -> String {
-        format!(r#"$env:{} = {}"#, key, self.escape(value))
+        format!("{} = {}", env_variable(key), self.escape(value))
     }
 
     fn unset(&self, key: &str) -> String {
-        format!(r#"Remove-Item -Path Env:\{key} -ErrorAction SilentlyContinue"#)
+        format!(
+            "Remove-Item -LiteralPath {} -ErrorAction SilentlyContinue",
+            self.escape(&format!("Env:{key}"))
+        )
+    }
+
+    fn accepts_name(&self, key: &str) -> bool {
+        !key.is_empty() && !key.contains('=')
     }
 
+    /// Quote a value as a single-quoted string, in which nothing is expanded
     fn escape(&self, s: &str) -> String {
-        let mut result = String::with_capacity(s.len() + 10);
-        result.push('"');
-
-        for c in s.chars() {
-            match c {
-                '"' => result.push_str("`\""),
-                '`' => result.push_str("``"),
-                '$' => result.push_str("`$"),
-                _ => result.push(c),
-            }
-        }
+        quote::pwsh(s)
+    }
+}
+
+/// The variable expression for an environment variable. Names such as
+/// `ProgramFiles(x86)` need the braced form.
+fn env_variable(key: &str) -> String {
+    if key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return format!("$env:{key}");
+    }
 
-        result.push('"');
-        result
+    let mut result = String::from("${env:");
+    for c in key.chars() {
+        if matches!(c, '{' | '}' | '`') {
+            result.push('`');
+        }
+        result.push(c);
     }
+    result.push('}');
+    result
 }
 
 #[cfg(test)]
@@ -45,9 +67,13 @@ mod tests {
     #[test]
     fn test_pwsh_export() {
         let shell = PwshShell;
-        assert_eq!(shell.export("FOO", "bar"), r#"$env:FOO = "bar""#);
-        assert_eq!(shell.export("FOO", "bar baz"), r#"$env:FOO = "bar baz""#);
-        assert_eq!(shell.export("FOO", "$HOME"), r#"$env:FOO = "`$HOME""#);
+        assert_eq!(shell.export("FOO", "bar"), "$env:FOO = 'bar'");
+        assert_eq!(shell.export("FOO", "bar baz"), "$env:FOO = 'bar baz'");
+        assert_eq!(shell.export("FOO", "$HOME"), "$env:FOO = '$HOME'");
+        assert_eq!(
+            shell.export("ProgramFiles(x86)", r"C:\Program Files (x86)"),
+            r"${env:ProgramFiles(x86)} = 'C:\Program Files (x86)'"
+        );
     }
 
     #[test]
@@ -55,25 +81,60 @@ mod tests {
         let shell = PwshShell;
         assert_eq!(
             shell.unset("FOO"),
-            r#"Remove-Item -Path Env:\FOO -ErrorAction SilentlyContinue"#
+            "Remove-Item -LiteralPath 'Env:FOO' -ErrorAction SilentlyContinue"
+        );
+        assert_eq!(
+            shell.unset("A[1]"),
+            "Remove-Item -LiteralPath 'Env:A[1]' -ErrorAction SilentlyContinue"
         );
     }
 
     #[test]
     fn test_pwsh_escape() {
         let shell = PwshShell;
-        assert_eq!(shell.escape("hello"), r#""hello""#);
-        assert_eq!(shell.escape("hello world"), r#""hello world""#);
-        assert_eq!(shell.escape(r#"hello "world""#), r#""hello `"world`"""#);
-        assert_eq!(shell.escape("$HOME"), r#""`$HOME""#);
-        assert_eq!(shell.escape("back`tick"), r#""back``tick""#);
+        assert_eq!(shell.escape(""), "''");
+        assert_eq!(shell.escape("hello world"), "'hello world'");
+        assert_eq!(shell.escape(r#"hello "world""#), r#"'hello "world"'"#);
+        assert_eq!(shell.escape("it's"), "'it''s'");
+        assert_eq!(shell.escape("it\u{2019}s"), "'it\u{2019}\u{2019}s'");
+        assert_eq!(shell.escape("$HOME `n"), "'$HOME `n'");
+        assert_eq!(shell.escape("a\nb"), "'a\nb'");
     }
 
     #[test]
     fn test_pwsh_hook() {
         let shell = PwshShell;
         let hook = shell.hook();
-        assert!(hook.contains("_cuenvOriginalPrompt"));
+        assert!(hook.contains("Test-Path variable:global:_cuenvOriginalPrompt"));
         assert!(hook.contains("function global:prompt"));
+        assert!(hook.contains("cuenv shell hook pwsh"));
+        assert!(hook.contains("LASTEXITCODE"));
+        assert!(hook.contains("function global:cuenv_prompt"));
+    }
+
+    /// Runs the generated commands in PowerShell when it is installed, on
+    /// Windows as well as on Linux and macOS
+    #[test]
+    fn test_pwsh_round_trip() {
+        let Ok(pwsh) = which::which("pwsh").or_else(|_| which::which("powershell")) else {
+            return;
+        };
+        let shell = PwshShell;
+        let value = "it's \"$HOME\" `n back`tick\nsecond line";
+        let script = [
+            shell.export("CUENV_PWSH_TEST", value),
+            shell.export("CUENV_PWSH_REMOVED", "x"),
+            shell.unset("CUENV_PWSH_REMOVED"),
+            "[Console]::Out.Write($env:CUENV_PWSH_TEST + '|' + [string]$env:CUENV_PWSH_REMOVED)"
+                .to_string(),
+        ]
+        .join("\n");
+
+        let output = std::process::Command::new(pwsh)
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), format!("{value}|"));
     }
 }
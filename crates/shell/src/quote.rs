@@ -0,0 +1,450 @@
+//! Quoting of the values in emitted statements
+//!
+//! The hook output is evaluated by the user's shell, so every value must read
+//! back as exactly the string that was exported, whatever it contains:
+//! quotes, `$`, backslashes, newlines or other control characters. Each
+//! shell's `escape` is one of the functions here.
+
+/// Whether a value reads back unchanged without any quoting in the shells
+/// that use [`posix`], [`tcsh`] or [`fish`]. A leading `=` is left out, as
+/// zsh would expand it to the path of a command.
+pub fn is_plain(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with('=')
+        && s.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '_' | '-' | '.' | '/' | ':' | ',' | '+' | '@' | '=')
+        })
+}
+
+/// Whether a variable name can be assigned in every shell without quoting
+pub fn is_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A single-quoted string for bash, zsh and other POSIX shells, in which
+/// everything but `'` is literal
+pub fn posix(s: &str) -> String {
+    if is_plain(s) {
+        return s.to_string();
+    }
+
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('\'');
+    for c in s.chars() {
+        if c == '\'' {
+            result.push_str("'\"'\"'");
+        } else {
+            result.push(c);
+        }
+    }
+    result.push('\'');
+    result
+}
+
+/// A value that may not be UTF-8 for bash and zsh. Bytes that are not
+/// printable ASCII are written as `\xHH` in an ANSI-C quoted string.
+pub fn posix_bytes(bytes: &[u8]) -> String {
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return posix(s);
+    }
+
+    let mut result = String::with_capacity(bytes.len() * 2 + 3);
+    result.push_str("$'");
+    for &byte in bytes {
+        match byte {
+            b'\'' => result.push_str("\\'"),
+            b'\\' => result.push_str("\\\\"),
+            b' '..=b'~' => result.push(char::from(byte)),
+            _ => result.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+    result.push('\'');
+    result
+}
+
+/// A single-quoted string for fish, in which only `\` and `'` are escaped
+pub fn fish(s: &str) -> String {
+    if is_plain(s) {
+        return s.to_string();
+    }
+
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => result.push_str("'\\''"),
+            '\\' => result.push_str("\\\\"),
+            _ => result.push(c),
+        }
+    }
+    result.push('\'');
+    result
+}
+
+/// A value that may not be UTF-8 for fish, which reads `\XHH` as the raw
+/// byte
+pub fn fish_bytes(bytes: &[u8]) -> String {
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return fish(s);
+    }
+
+    bytes
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_alphanumeric() {
+                char::from(byte).to_string()
+            } else {
+                format!("\\X{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// A single-quoted string for tcsh, where history substitution still
+/// applies to `!` and a newline has to be escaped
+pub fn tcsh(s: &str) -> String {
+    if is_plain(s) {
+        return s.to_string();
+    }
+
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => result.push_str("'\\''"),
+            '!' => result.push_str("\\!"),
+            '\n' => result.push_str("\\\n"),
+            _ => result.push(c),
+        }
+    }
+    result.push('\'');
+    result
+}
+
+/// A single-quoted string for elvish, where `'` is doubled
+pub fn elvish(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// A string for murex. Single quotes cannot contain a `'`, so such values
+/// are double-quoted with variables and arrays escaped.
+pub fn murex(s: &str) -> String {
+    if !s.contains('\'') {
+        return format!("'{s}'");
+    }
+
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '$' => result.push_str("\\$"),
+            '@' => result.push_str("\\@"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// A single-quoted string for PowerShell, in which nothing is expanded
+pub fn pwsh(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('\'');
+    for c in s.chars() {
+        // PowerShell treats the typographic single quotes like `'`, and any
+        // of them is escaped by doubling it
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            result.push(c);
+        }
+        result.push(c);
+    }
+    result.push('\'');
+    result
+}
+
+/// The argument of cmd's `set`, which drops the first `"` and everything
+/// from the last one. Special characters that a `"` in the value leaves
+/// unquoted are escaped with `^`. cmd cannot represent line breaks, which
+/// would start a new command, so they become spaces.
+pub fn cmd(s: &str) -> String {
+    if is_plain(s) {
+        return s.to_string();
+    }
+
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    let mut quoted = true;
+    for c in s.chars() {
+        match c {
+            '\r' | '\n' => result.push(' '),
+            '"' => {
+                quoted = !quoted;
+                result.push(c);
+            }
+            '^' | '&' | '|' | '<' | '>' | '(' | ')' if !quoted => {
+                result.push('^');
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// A double-quoted Lua string, for the Clink script that hooks cmd
+pub fn lua(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '"' => result.push_str("\\\""),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            // Three digits, so that a following digit is not read as part
+            // of the escape
+            c if c.is_ascii_control() => result.push_str(&format!("\\{:03}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// Characters shells give a meaning to, mixed with ordinary ones
+    const ALPHABET: &[char] = &[
+        'a', 'Z', '0', ' ', '\t', '\n', '\r', '\'', '"', '`', '$', '\\', '!', '%', '^', '&', '|',
+        ';', '<', '>', '(', ')', '{', '}', '[', ']', '*', '?', '~', '#', '=', '@', '-', ',',
+        '\u{1b}', '\u{7f}', 'é', '\u{2019}', '€',
+    ];
+
+    /// Deterministic pseudo-random strings over `ALPHABET`
+    fn samples(count: usize) -> Vec<String> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..count)
+            .map(|_| {
+                let len = (next() % 16) as usize;
+                (0..len)
+                    .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Run `script` and return what it printed, or `None` without the shell
+    fn run(shell: &str, args: &[&str], script: &str) -> Option<Vec<u8>> {
+        let path = which::which(shell).ok()?;
+        let output = Command::new(path).args(args).arg(script).output().unwrap();
+        assert!(output.status.success(), "{shell} failed: {output:?}");
+        Some(output.stdout)
+    }
+
+    /// Assign every sample in one script and print them back, each followed
+    /// by `terminator` and a NUL byte, which no value can contain
+    fn round_trip(
+        shell: &str,
+        args: &[&str],
+        quote: fn(&str) -> String,
+        assign: impl Fn(usize, String) -> String,
+        print: impl Fn(usize) -> String,
+        terminator: &str,
+    ) {
+        let values = samples(200);
+        let mut script = String::new();
+        for (i, value) in values.iter().enumerate() {
+            script.push_str(&assign(i, quote(value)));
+            script.push('\n');
+        }
+        for i in 0..values.len() {
+            script.push_str(&print(i));
+            script.push('\n');
+        }
+
+        let Some(stdout) = run(shell, args, &script) else {
+            return;
+        };
+        let printed: Vec<String> = String::from_utf8(stdout)
+            .unwrap()
+            .split_terminator('\0')
+            .map(|printed| {
+                printed
+                    .strip_suffix(terminator)
+                    .unwrap_or(printed)
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(printed, values, "{shell} read back different values");
+    }
+
+    #[test]
+    fn test_plain_values_stay_unquoted() {
+        assert!(is_plain("/usr/local/bin:/usr/bin"));
+        assert!(is_plain("a=b"));
+        assert!(!is_plain(""));
+        assert!(!is_plain("=ls"));
+        assert!(!is_plain("~"));
+        assert!(!is_plain("é"));
+        assert_eq!(posix("/usr/bin"), "/usr/bin");
+        assert_eq!(posix(""), "''");
+        assert_eq!(posix("hello world"), "'hello world'");
+        assert_eq!(posix("it's"), "'it'\"'\"'s'");
+        assert_eq!(posix("$HOME"), "'$HOME'");
+        assert_eq!(posix("a\nb"), "'a\nb'");
+    }
+
+    #[test]
+    fn test_names() {
+        assert!(is_name("PATH"));
+        assert!(is_name("_cuenv_1"));
+        assert!(!is_name(""));
+        assert!(!is_name("1A"));
+        assert!(!is_name("A-B"));
+        assert!(!is_name("A;rm"));
+        assert!(!is_name("ProgramFiles(x86)"));
+    }
+
+    #[test]
+    fn test_quoted_values_are_closed() {
+        // Whatever the value, the quoting neither ends early nor leaves a
+        // string open: removing the escaped quotes leaves balanced ones
+        for value in samples(500) {
+            let quoted = posix(&value).replace("'\"'\"'", "");
+            assert_eq!(quoted.matches('\'').count() % 2, 0, "{value:?}");
+
+            let quoted = elvish(&value).replace("''", "");
+            assert!(
+                quoted.is_empty() || quoted.matches('\'').count() == 2,
+                "{value:?}"
+            );
+
+            let quoted = pwsh(&value);
+            assert!(
+                quoted.starts_with('\'') && quoted.ends_with('\''),
+                "{value:?}"
+            );
+
+            assert!(!cmd(&value).contains(['\n', '\r']), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(posix_bytes(b"it's"), "'it'\"'\"'s'");
+        assert_eq!(posix_bytes(b"a\xffb'\\"), r"$'a\xffb\'\\'");
+        assert_eq!(fish_bytes(b"a\xff b"), r"a\XFF\X20b");
+    }
+
+    #[test]
+    fn test_cmd() {
+        assert_eq!(cmd("FOO=bar"), "FOO=bar");
+        assert_eq!(cmd("FOO=a & b"), "\"FOO=a & b\"");
+        assert_eq!(cmd("FOO=a\"&b"), "\"FOO=a\"^&b\"");
+        assert_eq!(cmd("FOO=a\"b\"&c"), "\"FOO=a\"b\"&c\"");
+        assert_eq!(cmd("FOO=a\nb"), "\"FOO=a b\"");
+    }
+
+    #[test]
+    fn test_murex() {
+        assert_eq!(murex("$HOME"), "'$HOME'");
+        assert_eq!(murex("it's $HOME"), r#""it's \$HOME""#);
+    }
+
+    #[test]
+    fn test_lua() {
+        assert_eq!(lua("a.cue"), "\"a.cue\"");
+        assert_eq!(lua(r#"C:\dir "x""#), r#""C:\\dir \"x\"""#);
+        assert_eq!(lua("a\nb\u{1b}1"), r#""a\nb\0271""#);
+    }
+
+    #[test]
+    fn test_bash_round_trip() {
+        round_trip(
+            "bash",
+            &["--norc", "-c"],
+            posix,
+            |i, value| format!("export V{i}={value}"),
+            |i| format!("printf '%s\\0' \"$V{i}\""),
+            "",
+        );
+    }
+
+    #[test]
+    fn test_sh_round_trip() {
+        round_trip(
+            "dash",
+            &["-c"],
+            posix,
+            |i, value| format!("export V{i}={value}"),
+            |i| format!("printf '%s\\0' \"$V{i}\""),
+            "",
+        );
+    }
+
+    #[test]
+    fn test_zsh_round_trip() {
+        round_trip(
+            "zsh",
+            &["-f", "-c"],
+            posix,
+            |i, value| format!("export V{i}={value}"),
+            |i| format!("printf '%s\\0' \"$V{i}\""),
+            "",
+        );
+    }
+
+    #[test]
+    fn test_fish_round_trip() {
+        round_trip(
+            "fish",
+            &["--no-config", "-c"],
+            fish,
+            |i, value| format!("set -gx V{i} {value}"),
+            |i| format!("printf '%s\\0' \"$V{i}\""),
+            "",
+        );
+    }
+
+    #[test]
+    fn test_tcsh_round_trip() {
+        round_trip(
+            "tcsh",
+            &["-f", "-c"],
+            tcsh,
+            |i, value| format!("setenv V{i} {value}"),
+            // printenv ends every value with a newline
+            |i| format!("printenv V{i}; printf '\\0'"),
+            "\n",
+        );
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let value = b"\xff'\\ \x01\xc3";
+        let script = format!("export V={}; printf '%s' \"$V\"", posix_bytes(value));
+        if let Some(stdout) = run("bash", &["--norc", "-c"], &script) {
+            assert_eq!(stdout, value);
+        }
+    }
+}
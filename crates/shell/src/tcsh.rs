@@ -1,10 +1,10 @@
-use super::{escape_bash_like, Shell};
+use super::{quote, Shell};
 
 pub struct TcshShell;
 
 impl Shell for TcshShell {
     fn hook(&self) -> String {
-        r#"alias precmd 'eval `cuenv hook tcsh`'"#.to_string()
+        r#"alias precmd 'eval `cuenv shell hook tcsh`'"#.to_string()
     }
 
     fn export(&self, key: &str, value: &str) -> String {
@@ -16,7 +16,7 @@ impl Shell for TcshShell {
     }
 
     fn escape(&self, s: &str) -> String {
-        escape_bash_like(s)
+        quote::tcsh(s)
     }
 }
 
@@ -29,6 +29,10 @@ mod tests {
         let shell = TcshShell;
         assert_eq!(shell.export("FOO", "bar"), "setenv FOO bar");
         assert_eq!(shell.export("FOO", "bar baz"), "setenv FOO 'bar baz'");
+        assert_eq!(
+            shell.export("FOO", "hi!\nthere"),
+            "setenv FOO 'hi\\!\\\nthere'"
+        );
     }
 
     #[test]
@@ -0,0 +1,139 @@
+//! Propagation of loaded environments to tmux
+//!
+//! A shell only passes its environment to its own children, so panes and
+//! windows opened later start from the tmux session environment instead.
+//! With `CUENV_TMUX=1` the hook mirrors every change it makes into the
+//! session with `tmux set-environment`.
+
+use crate::is_truthy;
+use cuenv_core::constants::CUENV_TMUX_VAR;
+use cuenv_env::EnvDiff;
+use std::process::Command;
+
+/// Whether changes should reach the tmux session: `CUENV_TMUX` is enabled
+/// and the shell runs inside tmux
+pub fn enabled() -> bool {
+    inside_tmux() && std::env::var(CUENV_TMUX_VAR).is_ok_and(|value| is_truthy(&value))
+}
+
+/// Whether the process runs inside a tmux client
+pub fn inside_tmux() -> bool {
+    std::env::var_os("TMUX").is_some_and(|value| !value.is_empty())
+}
+
+/// A variable to set in the session, or to unset when the value is `None`
+pub type Change = (String, Option<String>);
+
+/// The changes that apply `diff`. Removed variables are unset in the
+/// session, so new panes fall back to the global environment.
+pub fn changes(diff: &EnvDiff) -> Vec<Change> {
+    let mut removed: Vec<&str> = diff.removed().into_iter().collect();
+    removed.sort_unstable();
+    let mut changed: Vec<(&str, &str)> = diff.added_or_changed().into_iter().collect();
+    changed.sort_unstable();
+
+    removed
+        .into_iter()
+        .map(|key| (key.to_string(), None))
+        .chain(
+            changed
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), Some(value.to_string()))),
+        )
+        .collect()
+}
+
+/// The arguments of a single tmux invocation making `changes`
+pub fn set_environment_args(changes: &[Change]) -> Vec<String> {
+    let mut args = Vec::new();
+    for (key, value) in changes {
+        // tmux runs commands separated by a lone `;` in order
+        if !args.is_empty() {
+            args.push(";".to_string());
+        }
+        args.push("set-environment".to_string());
+        match value {
+            Some(value) => {
+                args.push(escape_separator(key));
+                args.push(escape_separator(value));
+            }
+            None => {
+                args.push("-u".to_string());
+                args.push(escape_separator(key));
+            }
+        }
+    }
+    args
+}
+
+/// tmux ends a command at an argument ending in `;` unless the `;` is
+/// escaped with a backslash
+fn escape_separator(arg: &str) -> String {
+    match arg.strip_suffix(';') {
+        Some(rest) => format!("{rest}\\;"),
+        None => arg.to_string(),
+    }
+}
+
+/// Make `changes` in the environment of the current tmux session
+pub fn propagate(changes: &[Change]) -> std::io::Result<()> {
+    let args = set_environment_args(changes);
+    if args.is_empty() {
+        return Ok(());
+    }
+
+    let output = Command::new("tmux").args(&args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "tmux set-environment failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_set_environment_args() {
+        let prev = HashMap::from([
+            ("OLD".to_string(), "1".to_string()),
+            ("KEEP".to_string(), "same".to_string()),
+        ]);
+        let next = HashMap::from([
+            ("KEEP".to_string(), "same".to_string()),
+            ("B".to_string(), "two words".to_string()),
+            ("A".to_string(), "a;b;".to_string()),
+        ]);
+
+        assert_eq!(
+            set_environment_args(&changes(&EnvDiff::new(prev, next))),
+            [
+                "set-environment",
+                "-u",
+                "OLD",
+                ";",
+                "set-environment",
+                "A",
+                "a;b\\;",
+                ";",
+                "set-environment",
+                "B",
+                "two words",
+            ]
+        );
+        assert!(set_environment_args(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_truthy_values() {
+        assert!(is_truthy("1"));
+        assert!(is_truthy("TRUE"));
+        assert!(!is_truthy("0"));
+        assert!(!is_truthy(""));
+    }
+}
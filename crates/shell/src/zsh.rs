@@ -1,4 +1,4 @@
-use super::{escape_bash_like, Shell};
+use super::{quote, Shell};
 
 pub struct ZshShell;
 
@@ -6,13 +6,20 @@ impl Shell for ZshShell {
     fn hook(&self) -> String {
         r#"_cuenv_hook() {
   trap -- '' SIGINT
-  eval "$(cuenv hook zsh)"
+  eval "$(cuenv shell hook zsh)"
   trap - SIGINT
 }
 typeset -ag precmd_functions
 if [[ ${precmd_functions[(ie)_cuenv_hook]} -gt ${#precmd_functions} ]]; then
   precmd_functions+=(_cuenv_hook)
-fi"#
+fi
+
+# Status segment for PS1/PROMPT, empty outside a loaded directory
+cuenv_prompt() {
+  if [[ -n "${CUENV_DIR:-}" ]]; then
+    command cuenv prompt "$@"
+  fi
+}"#
         .to_string()
     }
 
@@ -20,12 +27,18 @@ fi"#
         format!("export {}={}", key, self.escape(value))
     }
 
+    #[cfg(unix)]
+    fn export_os(&self, key: &str, value: &std::ffi::OsStr) -> String {
+        use std::os::unix::ffi::OsStrExt;
+        format!("export {key}={}", quote::posix_bytes(value.as_bytes()))
+    }
+
     fn unset(&self, key: &str) -> String {
         format!("unset {key}")
     }
 
     fn escape(&self, s: &str) -> String {
-        escape_bash_like(s)
+        quote::posix(s)
     }
 }
 
@@ -52,5 +65,6 @@ mod tests {
         let hook = shell.hook();
         assert!(hook.contains("_cuenv_hook"));
         assert!(hook.contains("precmd_functions"));
+        assert!(hook.contains("cuenv_prompt()"));
     }
 }
@@ -5,8 +5,8 @@
 
 use cuenv_config::TaskConfig;
 use cuenv_core::{
-    Error, ResolvedDependency, Result, TaskCache, TaskDefinition, TaskExecutionMode, TaskSecurity,
-    DEFAULT_TASK_TIMEOUT_SECS,
+    default_shell_args, Error, ResolvedDependency, Result, TaskCache, TaskDefinition,
+    TaskExecutionMode, TaskSecurity, DEFAULT_TASK_TIMEOUT_SECS,
 };
 use std::path::PathBuf;
 use std::time::Duration;
@@ -32,15 +32,36 @@ pub fn config_to_definition(config: TaskConfig) -> Result<TaskDefinition> {
         execution_mode,
         dependencies,
         working_directory: PathBuf::from(config.working_dir.unwrap_or_else(|| ".".to_string())),
-        shell: config.shell.unwrap_or_else(|| "sh".to_string()),
+        shell: config
+            .shell
+            .as_ref()
+            .map_or_else(|| "sh".to_string(), |shell| shell.program().to_string()),
+        shell_args: config
+            .shell
+            .as_ref()
+            .map_or_else(|| default_shell_args("sh"), |shell| shell.args()),
         inputs: config.inputs.unwrap_or_default(),
-        outputs: config.outputs.unwrap_or_default(),
+        outputs: config
+            .outputs
+            .as_ref()
+            .map(|outputs| outputs.paths())
+            .unwrap_or_default(),
+        // Relative to the working directory until it is resolved
+        artifacts: config
+            .outputs
+            .as_ref()
+            .map(|outputs| outputs.artifacts())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, artifact)| (name, PathBuf::from(artifact.path)))
+            .collect(),
         security,
         cache,
         timeout: config
             .timeout
             .map(|t| Duration::from_secs(t as u64))
             .unwrap_or_else(|| Duration::from_secs(DEFAULT_TASK_TIMEOUT_SECS)),
+        max_output_bytes: config.max_output_bytes,
     };
 
     Ok(definition)
@@ -67,36 +88,44 @@ fn create_execution_mode(config: &TaskConfig) -> Result<TaskExecutionMode> {
 /// Convert task dependencies to resolved dependencies
 fn convert_dependencies(config: &TaskConfig) -> Vec<ResolvedDependency> {
     config
-        .dependencies
-        .as_ref()
-        .unwrap_or(&Vec::new())
-        .iter()
-        .map(|dep| ResolvedDependency::new(dep.clone()))
+        .dependencies_by_kind()
+        .map(|(dep, kind)| ResolvedDependency::new(dep.clone()).with_kind(kind))
         .collect()
 }
 
 /// Convert security configuration to TaskSecurity
+///
+/// Listing paths restricts disk access to them, unless `restrictDisk` is
+/// explicitly false.
 fn convert_security_config(config: &TaskConfig) -> Option<TaskSecurity> {
-    config.security.as_ref().map(|sec| TaskSecurity {
-        restrict_disk: sec.restrict_disk.unwrap_or(false),
-        restrict_network: sec.restrict_network.unwrap_or(false),
-        read_only_paths: sec
-            .read_only_paths
-            .as_ref()
-            .unwrap_or(&Vec::new())
-            .iter()
-            .map(PathBuf::from)
-            .collect(),
-        write_only_paths: Vec::new(), // TODO: Add when TaskConfig supports it
-        allowed_hosts: sec.allowed_hosts.as_ref().unwrap_or(&Vec::new()).clone(),
+    let paths = |paths: &Option<Vec<String>>| -> Vec<PathBuf> {
+        paths.iter().flatten().map(PathBuf::from).collect()
+    };
+
+    config.security.as_ref().map(|sec| {
+        let read_only_paths = paths(&sec.read_only_paths);
+        let write_only_paths = paths(&sec.read_write_paths);
+        TaskSecurity {
+            restrict_disk: sec
+                .restrict_disk
+                .unwrap_or(!read_only_paths.is_empty() || !write_only_paths.is_empty()),
+            restrict_network: sec.restrict_network.unwrap_or(false),
+            read_only_paths,
+            write_only_paths,
+            allowed_hosts: sec.allowed_hosts.as_ref().unwrap_or(&Vec::new()).clone(),
+            seccomp: sec.seccomp.clone(),
+            fail_on_secret_leak: sec.fail_on_secret_leak.unwrap_or(false),
+            user: sec.user.clone(),
+            drop_capabilities: sec.drop_capabilities.unwrap_or(false),
+        }
     })
 }
 
 /// Convert cache configuration to TaskCache
 fn convert_cache_config(config: &TaskConfig) -> TaskCache {
     match &config.cache {
-        Some(_cache_config) => TaskCache {
-            enabled: true, // If cache config is present, enable it
+        Some(cache_config) => TaskCache {
+            enabled: cache_config.enabled(),
             key: config.cache_key.clone(),
             env_filter: None, // TODO: Convert from cache_config if needed
         },
@@ -145,8 +174,10 @@ mod tests {
             command: Some("echo hello".to_string()),
             script: None,
             dependencies: None,
+            order_only_dependencies: None,
+            weak_dependencies: None,
             working_dir: None,
-            shell: Some("sh".to_string()),
+            shell: Some("sh".into()),
             inputs: None,
             outputs: None,
             security: None,
@@ -154,6 +185,10 @@ mod tests {
             cache_key: None,
             cache_env: None,
             timeout: Some(30),
+            max_output_bytes: None,
+            tags: None,
+            owners: None,
+            service: None,
         }
     }
 
@@ -228,6 +263,10 @@ mod tests {
             deny_paths: None,
             allowed_hosts: Some(vec!["example.com".to_string()]),
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         });
 
         let definition = config_to_definition(config).unwrap();
@@ -239,16 +278,47 @@ mod tests {
         assert_eq!(security.allowed_hosts, vec!["example.com"]);
     }
 
+    #[test]
+    fn test_security_paths_restrict_disk() {
+        let mut config = create_basic_task_config();
+        config.security = Some(SecurityConfig {
+            restrict_disk: None,
+            restrict_network: None,
+            read_only_paths: Some(vec!["./src".to_string()]),
+            read_write_paths: Some(vec!["./dist".to_string()]),
+            deny_paths: None,
+            allowed_hosts: None,
+            infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
+        });
+
+        let definition = config_to_definition(config.clone()).unwrap();
+        let security = definition.security.as_ref().unwrap();
+        assert!(security.restrict_disk);
+        assert_eq!(security.read_only_paths, vec![PathBuf::from("./src")]);
+        assert_eq!(security.write_only_paths, vec![PathBuf::from("./dist")]);
+
+        config.security.as_mut().unwrap().restrict_disk = Some(false);
+        let definition = config_to_definition(config).unwrap();
+        assert!(!definition.security.unwrap().restrict_disk);
+    }
+
     #[test]
     fn test_cache_config_conversion() {
         let mut config = create_basic_task_config();
         config.cache = Some(TaskCacheConfig::Simple(true));
         config.cache_key = Some("custom-key".to_string());
 
-        let definition = config_to_definition(config).unwrap();
+        let definition = config_to_definition(config.clone()).unwrap();
 
         assert!(definition.cache.enabled);
         assert_eq!(definition.cache.key, Some("custom-key".to_string()));
+
+        config.cache = Some(TaskCacheConfig::Simple(false));
+        assert!(!config_to_definition(config).unwrap().cache.enabled);
     }
 
     #[test]
@@ -258,6 +328,8 @@ mod tests {
             command: Some("echo test".to_string()),
             script: None,
             dependencies: None,
+            order_only_dependencies: None,
+            weak_dependencies: None,
             working_dir: None,
             shell: None,
             inputs: None,
@@ -267,12 +339,17 @@ mod tests {
             cache_key: None,
             cache_env: None,
             timeout: None,
+            max_output_bytes: None,
+            tags: None,
+            owners: None,
+            service: None,
         };
 
         let definition = config_to_definition(config).unwrap();
 
         assert!(definition.description.is_none());
         assert_eq!(definition.shell, "sh");
+        assert_eq!(definition.shell_args, ["-e", "-c"]);
         assert_eq!(definition.working_directory, PathBuf::from("."));
         assert_eq!(
             definition.timeout,
@@ -20,53 +20,53 @@ pub fn create_dependency_cache() -> DependencyValidationCache {
 }
 
 /// Resolve task dependencies and update the build context
+///
+/// The dependency graph holds dependencies of every kind, so that ordering
+/// ones cannot form cycles either.
 pub fn resolve_dependencies(context: &mut BuildContext) -> Result<()> {
     for (task_name, config) in &context.task_configs {
         let mut resolved_deps = Vec::new();
         let mut dep_names = Vec::new();
 
-        if let Some(dependencies) = &config.dependencies {
-            for dep_name in dependencies {
-                // For now, all dependencies are local (no cross-package support)
-                // Future enhancement would parse "package:task" format
-                let resolved_dep = if dep_name.contains(':') {
-                    // Cross-package dependency (future feature)
-                    let parts: Vec<&str> = dep_name.splitn(2, ':').collect();
-                    if parts.len() != 2 || parts[0].is_empty() || parts[1].is_empty() {
-                        return Err(Error::configuration(format!(
-                            "Invalid cross-package dependency '{dep_name}' in task '{task_name}': format should be 'package:task'"
-                        )));
-                    }
-                    ResolvedDependency::with_package(parts[1].to_string(), parts[0].to_string())
-                } else {
-                    // Local dependency - check if it's a task or task group
-                    if context.task_configs.contains_key(dep_name) {
-                        // It's an individual task
-                        ResolvedDependency::new(dep_name.clone())
-                    } else if context.task_nodes.contains_key(dep_name) {
-                        // It's a task group - expand it to all its tasks
-                        let group_tasks =
-                            expand_task_group_dependency(dep_name, &context.task_nodes)?;
-                        for group_task in &group_tasks {
-                            if !context.task_configs.contains_key(group_task) {
-                                return Err(Error::configuration(format!(
-                                    "Task '{group_task}' from group '{dep_name}' not found in flattened tasks"
-                                )));
-                            }
-                            resolved_deps.push(ResolvedDependency::new(group_task.clone()));
-                            dep_names.push(group_task.clone());
+        for (dep_name, kind) in config.dependencies_by_kind() {
+            // A local task wins over the "package:task" reading of its
+            // name, so `npm:build` stays the package.json script
+            let resolved_dep = if context.task_configs.contains_key(dep_name) {
+                ResolvedDependency::new(dep_name.clone())
+            } else if dep_name.contains(':') {
+                // Cross-package dependency (future feature)
+                let parts: Vec<&str> = dep_name.splitn(2, ':').collect();
+                if parts.len() != 2 || parts[0].is_empty() || parts[1].is_empty() {
+                    return Err(Error::configuration(format!(
+                        "Invalid cross-package dependency '{dep_name}' in task '{task_name}': format should be 'package:task'"
+                    )));
+                }
+                ResolvedDependency::with_package(parts[1].to_string(), parts[0].to_string())
+            } else {
+                // Local dependency - check if it's a task group
+                if context.task_nodes.contains_key(dep_name) {
+                    // It's a task group - expand it to all its tasks
+                    let group_tasks = expand_task_group_dependency(dep_name, &context.task_nodes)?;
+                    for group_task in &group_tasks {
+                        if !context.task_configs.contains_key(group_task) {
+                            return Err(Error::configuration(format!(
+                                "Task '{group_task}' from group '{dep_name}' not found in flattened tasks"
+                            )));
                         }
-                        continue; // Skip the normal processing since we handled multiple dependencies
-                    } else {
-                        return Err(Error::configuration(format!(
-                            "Dependency '{dep_name}' of task '{task_name}' not found (neither task nor task group)"
-                        )));
+                        resolved_deps
+                            .push(ResolvedDependency::new(group_task.clone()).with_kind(kind));
+                        dep_names.push(group_task.clone());
                     }
-                };
+                    continue; // Skip the normal processing since we handled multiple dependencies
+                } else {
+                    return Err(Error::configuration(format!(
+                        "Dependency '{dep_name}' of task '{task_name}' not found (neither task nor task group)"
+                    )));
+                }
+            };
 
-                resolved_deps.push(resolved_dep);
-                dep_names.push(dep_name.clone());
-            }
+            resolved_deps.push(resolved_dep.with_kind(kind));
+            dep_names.push(dep_name.clone());
         }
 
         // Update task definition with resolved dependencies
@@ -286,8 +286,10 @@ mod tests {
             command: Some("echo hello".to_string()),
             script: None,
             dependencies: deps.map(|d| d.iter().map(|s| s.to_string()).collect()),
+            order_only_dependencies: None,
+            weak_dependencies: None,
             working_dir: None,
-            shell: Some("sh".to_string()),
+            shell: Some("sh".into()),
             inputs: None,
             outputs: None,
             security: None,
@@ -295,6 +297,10 @@ mod tests {
             cache_key: None,
             cache_env: None,
             timeout: Some(30),
+            max_output_bytes: None,
+            tags: None,
+            owners: None,
+            service: None,
         }
     }
 
@@ -308,11 +314,14 @@ mod tests {
             dependencies: Vec::new(),
             working_directory: std::path::PathBuf::from("."),
             shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
             inputs: Vec::new(),
             outputs: Vec::new(),
+            artifacts: HashMap::new(),
             security: None,
             cache: cuenv_core::TaskCache::default(),
             timeout: std::time::Duration::from_secs(30),
+            max_output_bytes: None,
         }
     }
 
@@ -347,6 +356,38 @@ mod tests {
         assert_eq!(build_def.dependencies[0].name, "test");
     }
 
+    #[test]
+    fn test_resolve_dependency_kinds() {
+        let mut context = BuildContext {
+            task_configs: HashMap::new(),
+            task_nodes: HashMap::new(),
+            task_definitions: HashMap::new(),
+            dependency_graph: HashMap::new(),
+        };
+        let mut build = create_test_config(Some(vec!["codegen"]));
+        build.order_only_dependencies = Some(vec!["lint".to_string()]);
+        build.weak_dependencies = Some(vec!["docs".to_string()]);
+        for (name, config) in [
+            ("codegen", create_test_config(None)),
+            ("lint", create_test_config(None)),
+            ("docs", create_test_config(None)),
+            ("build", build),
+        ] {
+            context.task_configs.insert(name.to_string(), config);
+            context
+                .task_definitions
+                .insert(name.to_string(), create_test_definition(name));
+        }
+
+        resolve_dependencies(&mut context).unwrap();
+
+        let build_def = &context.task_definitions["build"];
+        assert_eq!(build_def.dependency_names(), ["codegen"]);
+        assert_eq!(build_def.planned_dependency_names(), ["codegen", "lint"]);
+        assert_eq!(build_def.weak_dependency_names(), ["docs"]);
+        assert_eq!(context.dependency_graph["build"].len(), 3);
+    }
+
     #[test]
     fn test_resolve_missing_dependency() {
         let mut context = BuildContext {
@@ -9,19 +9,36 @@ use std::path::{Path, PathBuf};
 
 use super::BuildContext;
 
-/// Expand environment variables in task execution content and working directories
+/// Prefix of references to another task's named outputs,
+/// `${outputs.<task>.<name>}`
+pub const OUTPUT_REFERENCE_PREFIX: &str = "outputs.";
+
+/// Expand environment variables and output references in task execution
+/// content
+///
+/// Output references expand to resolved artifact paths, so this runs after
+/// [`resolve_artifacts`].
 pub fn expand_environment_variables(
     context: &mut BuildContext,
     global_env: &HashMap<String, String>,
 ) -> Result<()> {
+    let mut variables = global_env.clone();
+    variables.extend(output_references(context));
+
     for definition in context.task_definitions.values_mut() {
+        check_output_references(
+            &definition.name,
+            definition.get_execution_content(),
+            &variables,
+        )?;
+
         // Expand environment variables in execution content
         match &mut definition.execution_mode {
             TaskExecutionMode::Command { command } => {
-                *command = expand_env_vars(command, global_env)?;
+                *command = expand_env_vars(command, &variables)?;
             }
             TaskExecutionMode::Script { content } => {
-                *content = expand_env_vars(content, global_env)?;
+                *content = expand_env_vars(content, &variables)?;
             }
         }
     }
@@ -29,6 +46,55 @@ pub fn expand_environment_variables(
     Ok(())
 }
 
+/// Resolve named output artifacts against their task's working directory
+pub fn resolve_artifacts(context: &mut BuildContext) {
+    for definition in context.task_definitions.values_mut() {
+        let working_directory = definition.working_directory.clone();
+        for path in definition.artifacts.values_mut() {
+            *path = working_directory.join(&*path);
+        }
+    }
+}
+
+/// The values of output references: every task's artifact paths
+fn output_references(context: &BuildContext) -> HashMap<String, String> {
+    context
+        .task_definitions
+        .iter()
+        .flat_map(|(task, definition)| {
+            definition.artifacts.iter().map(move |(name, path)| {
+                (
+                    format!("{OUTPUT_REFERENCE_PREFIX}{task}.{name}"),
+                    path.to_string_lossy().to_string(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Fail on output references to artifacts no task declares, which would
+/// otherwise expand to nothing
+fn check_output_references(
+    task_name: &str,
+    content: &str,
+    variables: &HashMap<String, String>,
+) -> Result<()> {
+    let pattern = format!("${{{OUTPUT_REFERENCE_PREFIX}");
+    for (pos, _) in content.match_indices(&pattern) {
+        let reference = &content[pos + 2..];
+        let Some(end) = reference.find('}') else {
+            continue;
+        };
+        let reference = &reference[..end];
+        if !variables.contains_key(reference) {
+            return Err(cuenv_core::Error::configuration(format!(
+                "Task '{task_name}' references undeclared output '{reference}'"
+            )));
+        }
+    }
+    Ok(())
+}
+
 /// Resolve working directories to absolute paths with environment variable expansion
 pub fn resolve_working_directories(
     context: &mut BuildContext,
@@ -102,11 +168,14 @@ mod tests {
             dependencies: Vec::new(),
             working_directory: PathBuf::from(working_dir),
             shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
             inputs: Vec::new(),
             outputs: Vec::new(),
+            artifacts: HashMap::new(),
             security: None,
             cache: cuenv_core::TaskCache::default(),
             timeout: Duration::from_secs(30),
+            max_output_bytes: None,
         }
     }
 
@@ -200,6 +269,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_output_references_expand_to_artifact_paths() {
+        let mut context = BuildContext {
+            task_configs: HashMap::new(),
+            task_nodes: HashMap::new(),
+            task_definitions: HashMap::new(),
+            dependency_graph: HashMap::new(),
+        };
+
+        let mut build = create_test_definition("build", "cargo build", "/project");
+        build
+            .artifacts
+            .insert("binary".to_string(), PathBuf::from("target/release/app"));
+        context.task_definitions.insert("build".to_string(), build);
+        context.task_definitions.insert(
+            "package".to_string(),
+            create_test_definition("package", "tar czf app.tgz ${outputs.build.binary}", "/"),
+        );
+
+        resolve_artifacts(&mut context);
+        expand_environment_variables(&mut context, &HashMap::new()).unwrap();
+        assert_eq!(
+            context.task_definitions["package"].get_execution_content(),
+            "tar czf app.tgz /project/target/release/app"
+        );
+
+        context.task_definitions.insert(
+            "deploy".to_string(),
+            create_test_definition("deploy", "scp ${outputs.build.docs} host:", "/"),
+        );
+        let error = expand_environment_variables(&mut context, &HashMap::new()).unwrap_err();
+        assert!(error.to_string().contains("outputs.build.docs"));
+    }
+
     #[test]
     fn test_resolve_working_directories_with_env() {
         use tempfile::TempDir;
@@ -101,15 +101,16 @@ impl TaskBuilder {
         // Step 4: Validate dependency graph for cycles
         dependency::validate_dependencies(&context, &self.dependency_cache)?;
 
-        // Step 5: Expand environment variables
-        env_expansion::expand_environment_variables(&mut context, &self.global_env)?;
-
-        // Step 6: Resolve working directories
+        // Step 5: Resolve working directories and the artifacts inside them
         env_expansion::resolve_working_directories(
             &mut context,
             &self.workspace_root,
             &self.global_env,
         )?;
+        env_expansion::resolve_artifacts(&mut context);
+
+        // Step 6: Expand environment variables and output references
+        env_expansion::expand_environment_variables(&mut context, &self.global_env)?;
 
         // Step 7: Validate security configurations
         security::validate_security_configs(&mut context, &self.workspace_root)?;
@@ -141,8 +142,10 @@ mod tests {
             command: Some(command.to_string()),
             script: None,
             dependencies: None,
+            order_only_dependencies: None,
+            weak_dependencies: None,
             working_dir: None,
-            shell: Some("sh".to_string()),
+            shell: Some("sh".into()),
             inputs: None,
             outputs: None,
             security: None,
@@ -150,6 +153,10 @@ mod tests {
             cache_key: None,
             cache_env: None,
             timeout: Some(30),
+            max_output_bytes: None,
+            tags: None,
+            owners: None,
+            service: None,
         }
     }
 
@@ -295,6 +302,10 @@ mod tests {
             deny_paths: None,
             allowed_hosts: Some(vec!["example.com".to_string()]),
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         });
 
         configs.insert("test".to_string(), config);
@@ -316,7 +327,7 @@ mod tests {
 
         let mut configs = HashMap::new();
         let mut config = create_test_config("echo hello");
-        config.shell = Some("evil_shell".to_string());
+        config.shell = Some("evil_shell".into());
 
         configs.insert("test".to_string(), config);
 
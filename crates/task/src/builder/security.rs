@@ -4,6 +4,7 @@
 //! security paths are properly resolved and validated for task execution.
 
 use cuenv_core::{Error, Result, TaskSecurity};
+use cuenv_security::seccomp::BUILTIN_PROFILES;
 use std::path::{Path, PathBuf};
 
 use super::BuildContext;
@@ -71,6 +72,16 @@ pub fn resolve_security_paths(
     resolve_paths(&mut security.read_only_paths)?;
     resolve_paths(&mut security.write_only_paths)?;
 
+    // Profile files are relative to the workspace, like the paths above
+    if let Some(profile) = &mut security.seccomp {
+        if !BUILTIN_PROFILES.contains(&profile.as_str()) && Path::new(profile).is_relative() {
+            *profile = workspace_root
+                .join(&*profile)
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+
     Ok(())
 }
 
@@ -145,11 +156,14 @@ mod tests {
             dependencies: Vec::new(),
             working_directory: PathBuf::from("."),
             shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
             inputs: Vec::new(),
             outputs: Vec::new(),
+            artifacts: std::collections::HashMap::new(),
             security,
             cache: cuenv_core::TaskCache::default(),
             timeout: Duration::from_secs(30),
+            max_output_bytes: None,
         }
     }
 
@@ -161,6 +175,10 @@ mod tests {
             read_only_paths: Vec::new(),
             write_only_paths: Vec::new(),
             allowed_hosts: vec!["example.com".to_string(), "api.test.com".to_string()],
+            seccomp: None,
+            fail_on_secret_leak: false,
+            user: None,
+            drop_capabilities: false,
         };
 
         let result = validate_security_hosts("test_task", &security);
@@ -175,6 +193,10 @@ mod tests {
             read_only_paths: Vec::new(),
             write_only_paths: Vec::new(),
             allowed_hosts: vec!["".to_string()],
+            seccomp: None,
+            fail_on_secret_leak: false,
+            user: None,
+            drop_capabilities: false,
         };
 
         let result = validate_security_hosts("test_task", &security);
@@ -190,6 +212,10 @@ mod tests {
             read_only_paths: Vec::new(),
             write_only_paths: Vec::new(),
             allowed_hosts: vec!["invalid host.com".to_string()],
+            seccomp: None,
+            fail_on_secret_leak: false,
+            user: None,
+            drop_capabilities: false,
         };
 
         let result = validate_security_hosts("test_task", &security);
@@ -215,6 +241,10 @@ mod tests {
             read_only_paths: vec![PathBuf::from("readonly")],
             write_only_paths: Vec::new(),
             allowed_hosts: Vec::new(),
+            seccomp: Some("seccomp.json".to_string()),
+            fail_on_secret_leak: false,
+            user: None,
+            drop_capabilities: false,
         };
 
         let result = resolve_security_paths("test_task", &mut security, &workspace_root);
@@ -226,6 +256,15 @@ mod tests {
             security.read_only_paths[0],
             test_dir.canonicalize().unwrap()
         );
+        assert_eq!(
+            security.seccomp.as_deref().map(PathBuf::from),
+            Some(workspace_root.join("seccomp.json"))
+        );
+
+        // Builtin profiles are names, not paths
+        security.seccomp = Some("strict".to_string());
+        resolve_security_paths("test_task", &mut security, &workspace_root).unwrap();
+        assert_eq!(security.seccomp.as_deref(), Some("strict"));
     }
 
     #[test]
@@ -239,6 +278,10 @@ mod tests {
             read_only_paths: vec![PathBuf::from("/etc/passwd")],
             write_only_paths: Vec::new(),
             allowed_hosts: Vec::new(),
+            seccomp: None,
+            fail_on_secret_leak: false,
+            user: None,
+            drop_capabilities: false,
         };
 
         let result = resolve_security_paths("test_task", &mut security, &workspace_root);
@@ -262,6 +305,10 @@ mod tests {
             read_only_paths: vec![PathBuf::from("secure")],
             write_only_paths: Vec::new(),
             allowed_hosts: vec!["example.com".to_string()],
+            seccomp: None,
+            fail_on_secret_leak: false,
+            user: None,
+            drop_capabilities: false,
         };
 
         let mut context = BuildContext {
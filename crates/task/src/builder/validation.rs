@@ -22,7 +22,12 @@ pub fn validate_task_configs(task_configs: &HashMap<String, TaskConfig>) -> Resu
 
         // Validate shell
         if let Some(shell) = &config.shell {
-            validate_shell(shell)?;
+            validate_shell(shell.program())?;
+            if shell.args().is_empty() {
+                return Err(Error::configuration(format!(
+                    "Task '{name}' shell needs args, the last taking the command, as in [\"-c\"]"
+                )));
+            }
         }
 
         // Validate timeout
@@ -33,6 +38,12 @@ pub fn validate_task_configs(task_configs: &HashMap<String, TaskConfig>) -> Resu
                 )));
             }
         }
+
+        if config.max_output_bytes == Some(0) {
+            return Err(Error::configuration(format!(
+                "Task '{name}' maxOutputBytes must be greater than 0"
+            )));
+        }
     }
 
     Ok(())
@@ -57,7 +68,7 @@ pub fn validate_shell(shell: &str) -> Result<()> {
 
     if !ALLOWED_SHELLS.contains(&shell) {
         return Err(Error::configuration(format!(
-            "Shell '{}' is not allowed. Allowed shells: {}",
+            "Shell '{}' is not allowed. Allowed shells: {}, or a profile of `shells`",
             shell,
             ALLOWED_SHELLS.join(", ")
         )));
@@ -78,7 +89,7 @@ pub fn validate_shell(shell: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cuenv_config::TaskConfig;
+    use cuenv_config::{ShellProfile, TaskConfig, TaskShell};
 
     fn create_test_config(command: Option<&str>, script: Option<&str>) -> TaskConfig {
         TaskConfig {
@@ -86,8 +97,10 @@ mod tests {
             command: command.map(|s| s.to_string()),
             script: script.map(|s| s.to_string()),
             dependencies: None,
+            order_only_dependencies: None,
+            weak_dependencies: None,
             working_dir: None,
-            shell: Some("sh".to_string()),
+            shell: Some("sh".into()),
             inputs: None,
             outputs: None,
             security: None,
@@ -95,6 +108,10 @@ mod tests {
             cache_key: None,
             cache_env: None,
             timeout: Some(30),
+            max_output_bytes: None,
+            tags: None,
+            owners: None,
+            service: None,
         }
     }
 
@@ -160,6 +177,20 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_shell_profile_without_args() {
+        let mut configs = HashMap::new();
+        let mut config = create_test_config(Some("echo hello"), None);
+        config.shell = Some(TaskShell::Profile(ShellProfile {
+            program: "bash".to_string(),
+            args: Some(Vec::new()),
+        }));
+        configs.insert("test".to_string(), config);
+
+        let result = validate_task_configs(&configs);
+        assert!(result.unwrap_err().to_string().contains("shell needs args"));
+    }
+
     #[test]
     fn test_zero_timeout() {
         let mut configs = HashMap::new();
@@ -174,4 +205,18 @@ mod tests {
             .to_string()
             .contains("must be greater than 0"));
     }
+
+    #[test]
+    fn test_zero_max_output_bytes() {
+        let mut configs = HashMap::new();
+        let mut config = create_test_config(Some("echo hello"), None);
+        config.max_output_bytes = Some(0);
+        configs.insert("test".to_string(), config);
+
+        let result = validate_task_configs(&configs);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("maxOutputBytes must be greater than 0"));
+    }
 }
@@ -0,0 +1,327 @@
+//! A stable API for running cuenv tasks from other Rust programs
+//!
+//! Loading an environment, planning the tasks to run and running them are
+//! three steps, each with its own type, so a program embedding cuenv needs
+//! neither the CLI nor the executor's internals:
+//!
+//! ```no_run
+//! use cuenv_task::{Environment, Runner, TaskGraph};
+//!
+//! # async fn deploy() -> cuenv_core::Result<()> {
+//! let environment = Environment::load("services/api").await?;
+//! let graph = TaskGraph::plan(environment, &["deploy"]).await?;
+//! let outcome = Runner::new().with_jobs(4).execute(graph).await?;
+//! assert!(outcome.success());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! These types follow semantic versioning: fields are private or marked
+//! non-exhaustive and options are set through methods, so all of them can
+//! grow without breaking callers. Everything else the crate exports may
+//! change in any release.
+//!
+//! With the `unstable` feature, progress is reported to a [`TaskObserver`]
+//! given to `Runner::with_observer` and the [`RunSummary`] of a run is
+//! returned by `RunOutcome::summary`. Both types belong to the executor and
+//! may change in any release, like it.
+
+#[cfg(feature = "unstable")]
+use crate::executor::RunSummary;
+use crate::executor::{TaskExecutor, TaskObserver};
+use cuenv_core::{Error, Result};
+use cuenv_env::manager::environment::SupervisorMode;
+use cuenv_env::manager::EnvManager;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How an environment is loaded
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    environment: Option<String>,
+    capabilities: Vec<String>,
+    base_env: Option<HashMap<String, String>>,
+}
+
+impl LoadOptions {
+    /// Apply the named environment of the configuration, like `cuenv -e`
+    pub fn environment(mut self, name: impl Into<String>) -> Self {
+        self.environment = Some(name.into());
+        self
+    }
+
+    /// Grant a capability, like `cuenv -c`
+    pub fn capability(mut self, capability: impl Into<String>) -> Self {
+        self.capabilities.push(capability.into());
+        self
+    }
+
+    /// Evaluate the configuration over `env` instead of the environment of
+    /// the process, as for a client the process runs tasks for
+    pub fn base_env(mut self, env: HashMap<String, String>) -> Self {
+        self.base_env = Some(env);
+        self
+    }
+}
+
+/// The loaded configuration of a directory: its variables and tasks
+///
+/// Loading runs the hooks of the configuration and evaluates its variables
+/// over the environment of the process, without changing it: the tasks
+/// planned from it are given the variables instead.
+pub struct Environment {
+    dir: PathBuf,
+    manager: EnvManager,
+    /// The complete environment the tasks run in
+    env: HashMap<String, String>,
+}
+
+impl Environment {
+    /// Load the configuration of `path`
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::load_with_options(path, LoadOptions::default()).await
+    }
+
+    /// Load the configuration of `path` with a named environment or
+    /// capabilities
+    pub async fn load_with_options(path: impl AsRef<Path>, options: LoadOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let dir = path
+            .canonicalize()
+            .map_err(|e| Error::file_system(path, "resolve directory", e))?;
+        let base = options
+            .base_env
+            .unwrap_or_else(|| std::env::vars().collect());
+        let mut manager = EnvManager::new();
+        let snapshot = manager
+            .evaluate(
+                &dir,
+                base,
+                options.environment,
+                options.capabilities,
+                None,
+                SupervisorMode::Synchronous,
+            )
+            .await?;
+        let mut env = snapshot.to_env();
+        // Tasks may read any secret their capabilities allow
+        env.extend(manager.resolve_deferred_secret_values()?);
+        Ok(Self { dir, manager, env })
+    }
+
+    /// The directory the configuration was loaded from
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The variables the configuration sets
+    pub fn variables(&self) -> &HashMap<String, String> {
+        self.manager.get_cue_vars()
+    }
+
+    /// The names of the tasks of the configuration, sorted
+    pub fn task_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.manager.get_tasks().keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// The tasks to run and their dependencies, in the order they run
+pub struct TaskGraph {
+    levels: Vec<Vec<String>>,
+    executor: TaskExecutor,
+}
+
+impl TaskGraph {
+    /// Plan running `tasks` of `environment` and every task they depend on
+    pub async fn plan<S: AsRef<str>>(environment: Environment, tasks: &[S]) -> Result<Self> {
+        let executor = TaskExecutor::builder(environment.manager, environment.dir)
+            .with_env(environment.env)
+            .build()
+            .await?;
+        Self::from_executor(executor, tasks)
+    }
+
+    fn from_executor<S: AsRef<str>>(executor: TaskExecutor, tasks: &[S]) -> Result<Self> {
+        let tasks: Vec<String> = tasks.iter().map(|task| task.as_ref().to_string()).collect();
+        if tasks.is_empty() {
+            return Err(Error::configuration("No task to plan"));
+        }
+        let plan = executor.build_execution_plan(&tasks)?;
+        let mut levels = plan.levels;
+        for level in &mut levels {
+            level.sort();
+        }
+        Ok(Self { levels, executor })
+    }
+
+    /// The tasks by level: those of a level only depend on tasks of the
+    /// levels before it
+    pub fn levels(&self) -> &[Vec<String>] {
+        &self.levels
+    }
+
+    /// Every task of the graph, in an order they can run in
+    pub fn tasks(&self) -> impl Iterator<Item = &str> {
+        self.levels.iter().flatten().map(String::as_str)
+    }
+}
+
+/// Runs the tasks of a [`TaskGraph`]
+#[derive(Clone, Default)]
+pub struct Runner {
+    jobs: Option<usize>,
+    args: Vec<String>,
+    observers: Option<Vec<Arc<dyn TaskObserver>>>,
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run at most `jobs` tasks at once, instead of the `jobs` of the user
+    /// configuration
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs.max(1));
+        self
+    }
+
+    /// Pass `args` to the tasks planned, like `cuenv task <task> <args>`
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Tell `observer` when tasks start, print and finish. The output of
+    /// tasks then only reaches the observers, instead of being printed.
+    ///
+    /// [`TaskObserver`] is not covered by semantic versioning.
+    #[cfg(feature = "unstable")]
+    pub fn with_observer(mut self, observer: Arc<dyn TaskObserver>) -> Self {
+        self.observers.get_or_insert_with(Vec::new).push(observer);
+        self
+    }
+
+    /// Run the tasks of `graph`; once a task failed, no other task starts
+    ///
+    /// A failing task is part of the outcome, an error is returned when the
+    /// tasks could not be run at all.
+    pub async fn execute(&self, graph: TaskGraph) -> Result<RunOutcome> {
+        let mut executor = graph.executor;
+        if let Some(jobs) = self.jobs {
+            executor = executor.with_jobs(jobs);
+        }
+        if let Some(observers) = &self.observers {
+            executor = executor.with_observers(observers.clone());
+        }
+        let tasks: Vec<String> = graph.levels.into_iter().flatten().collect();
+        let result = executor
+            .execute_tasks_with_dependencies(&tasks, &self.args, false)
+            .await;
+        let summary = executor.run_summary();
+        let failed: Vec<(String, Option<i32>)> = summary
+            .iter()
+            .flat_map(|summary| &summary.tasks)
+            .filter(|task| task.exit_code != Some(0))
+            .map(|task| (task.name.clone(), task.exit_code))
+            .collect();
+        let exit_code = match result {
+            Ok(exit_code) => exit_code,
+            Err(_) if !failed.is_empty() => failed[0].1.unwrap_or(1),
+            Err(e) => return Err(e),
+        };
+        Ok(RunOutcome {
+            exit_code,
+            failed: failed.into_iter().map(|(name, _)| name).collect(),
+            #[cfg(feature = "unstable")]
+            summary,
+        })
+    }
+}
+
+/// How a run went
+#[derive(Debug, Clone)]
+pub struct RunOutcome {
+    exit_code: i32,
+    failed: Vec<String>,
+    #[cfg(feature = "unstable")]
+    summary: Option<RunSummary>,
+}
+
+impl RunOutcome {
+    /// Whether every task succeeded
+    pub fn success(&self) -> bool {
+        self.exit_code == 0 && self.failed.is_empty()
+    }
+
+    /// The tasks that failed
+    pub fn failed(&self) -> &[String] {
+        &self.failed
+    }
+
+    /// The exit code of a task that failed, or 0
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    /// How long each task took and whether its result came from the cache,
+    /// once any task ran
+    ///
+    /// [`RunSummary`] is not covered by semantic versioning.
+    #[cfg(feature = "unstable")]
+    pub fn summary(&self) -> Option<&RunSummary> {
+        self.summary.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_config::TaskConfig;
+    use tempfile::TempDir;
+
+    fn task(dependencies: &[&str]) -> TaskConfig {
+        TaskConfig {
+            command: Some("true".to_string()),
+            dependencies: Some(dependencies.iter().map(|d| d.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_levels() {
+        let dir = TempDir::new().unwrap();
+        let mut manager = EnvManager::new();
+        manager.set_tasks_for_testing(
+            HashMap::from([
+                ("lint".to_string(), task(&[])),
+                ("build".to_string(), task(&[])),
+                ("test".to_string(), task(&["build", "lint"])),
+                ("docs".to_string(), task(&[])),
+            ]),
+            HashMap::new(),
+            HashMap::new(),
+        );
+        let executor = TaskExecutor::new_with_config(
+            manager,
+            dir.path().to_path_buf(),
+            cuenv_cache::CacheConfig {
+                base_dir: dir.path().join(".cache"),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let graph = TaskGraph::from_executor(executor, &["test"]).unwrap();
+        assert_eq!(graph.levels(), [vec!["build", "lint"], vec!["test"]]);
+        assert_eq!(graph.tasks().collect::<Vec<_>>(), ["build", "lint", "test"]);
+    }
+}
@@ -4,22 +4,40 @@ mod cache;
 mod context;
 mod dependency;
 pub mod execution;
+mod explain;
 mod graph;
+mod info;
 mod management;
+mod manifest;
+mod observer;
 mod plan;
 mod runner;
 mod strategies;
+mod summary;
+mod task_log;
+mod terminal;
 
+pub use api::InferredTaskIo;
+pub use builder::{OutputMode, RunnerKind, TaskExecutorBuilder};
 pub use context::TaskExecutionContext;
+pub use explain::{CacheKeyComponents, ExpandedGlob, TaskExplanation};
+pub use graph::topological_sort;
+pub use info::TaskInfo;
+pub use manifest::RunManifest;
+pub use observer::{ConsoleObserver, LogObserver, OutputStream, TaskObserver, TaskOutcome};
 pub use plan::TaskExecutionPlan;
+pub use summary::{CacheStatus, RunSummary, TaskSummary};
+pub use task_log::{FailedTask, FailureExcerpt};
 
 use crate::{MonorepoTaskRegistry, TaskBuilder};
 use cuenv_cache::config::CacheConfiguration;
 use cuenv_cache::{concurrent::action::ActionCache, CacheManager};
 use cuenv_env::manager::EnvManager;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
 
 /// Main task executor that handles dependency resolution and execution
 #[derive(Clone)]
@@ -28,13 +46,27 @@ pub struct TaskExecutor {
     pub(crate) working_dir: PathBuf,
     pub(crate) cache_manager: Arc<CacheManager>,
     pub(crate) action_cache: Arc<ActionCache>,
-    pub(crate) cache_config: CacheConfiguration,
+    pub(crate) cache_config: Arc<CacheConfiguration>,
     /// Task builder for Phase 3 architecture
     pub(crate) task_builder: TaskBuilder,
     /// Optional registry for cross-package task execution in monorepos
     pub(crate) monorepo_registry: Option<Arc<MonorepoTaskRegistry>>,
     /// Track executed tasks to avoid re-execution in cross-package scenarios
     pub(crate) executed_tasks: Arc<Mutex<HashSet<String>>>,
+    /// Timings of the tasks run, for the summary of the run
+    pub(crate) run_recorder: Arc<Mutex<summary::RunRecorder>>,
+    /// Limits how many tasks run at once to `jobs` of the user configuration
+    pub(crate) job_slots: Option<Arc<Semaphore>>,
+    /// Told when tasks start, print and finish
+    pub(crate) observers: observer::Observers,
+    /// Capture output for every run, not only those asking for it
+    pub(crate) output_mode: OutputMode,
+    /// Audit every run, not only those asking for it
+    pub(crate) runner: RunnerKind,
+    /// Stops the run when cancelled
+    pub(crate) cancellation: CancellationToken,
+    /// The environment tasks run in, instead of that of this process
+    pub(crate) task_env: Option<Arc<HashMap<String, String>>>,
 }
 
 #[cfg(test)]
@@ -63,6 +95,7 @@ mod executor_tests {
         let options = ParseOptions {
             environment: None,
             capabilities: Vec::new(),
+            ..Default::default()
         };
 
         // Change to the temp dir for CUE evaluation
@@ -112,6 +145,7 @@ tasks: {
             inline_threshold: 4096,
             env_filter: Default::default(),
             task_env_filters: std::collections::HashMap::new(),
+            remote: None,
         };
         let executor =
             TaskExecutor::new_with_config(manager, temp_dir.path().to_path_buf(), cache_config)
@@ -152,6 +186,7 @@ tasks: {
             inline_threshold: 4096,
             env_filter: Default::default(),
             task_env_filters: std::collections::HashMap::new(),
+            remote: None,
         };
         let executor =
             TaskExecutor::new_with_config(manager, temp_dir.path().to_path_buf(), cache_config)
@@ -193,6 +228,7 @@ tasks: {
             inline_threshold: 4096,
             env_filter: Default::default(),
             task_env_filters: std::collections::HashMap::new(),
+            remote: None,
         };
         let executor =
             TaskExecutor::new_with_config(manager, temp_dir.path().to_path_buf(), cache_config)
@@ -227,6 +263,7 @@ tasks: {
             inline_threshold: 4096,
             env_filter: Default::default(),
             task_env_filters: std::collections::HashMap::new(),
+            remote: None,
         };
         let executor =
             TaskExecutor::new_with_config(manager, temp_dir.path().to_path_buf(), cache_config)
@@ -259,6 +296,7 @@ tasks: {
             inline_threshold: 4096,
             env_filter: Default::default(),
             task_env_filters: std::collections::HashMap::new(),
+            remote: None,
         };
         let executor =
             TaskExecutor::new_with_config(manager, temp_dir.path().to_path_buf(), cache_config)
@@ -302,6 +340,7 @@ tasks: {
             inline_threshold: 4096,
             env_filter: Default::default(),
             task_env_filters: std::collections::HashMap::new(),
+            remote: None,
         };
         let executor =
             TaskExecutor::new_with_config(manager, temp_dir.path().to_path_buf(), cache_config)
@@ -320,4 +359,57 @@ tasks: {
         assert!(plan.levels[1].contains(&"test".to_string()));
         assert_eq!(plan.levels[2], vec!["deploy"]);
     }
+
+    #[tokio::test]
+    async fn test_cached_subtree_is_not_spawned() {
+        let tasks_cue = r#"package cuenv
+
+env: {}
+
+tasks: {
+    "build": {
+        command: "echo 'Building...'"
+        dependencies: ["compile"]
+        cache: true
+    }
+    "compile": {
+        command: "echo 'Compiling...'"
+        cache: true
+    }
+}"#;
+
+        let (manager, temp_dir) = create_test_env_manager_with_tasks(tasks_cue).await;
+        let cache_config = cuenv_cache::CacheConfig {
+            base_dir: temp_dir.path().join(".cache"),
+            max_size: 1024 * 1024, // 1MB for tests
+            mode: cuenv_cache::CacheMode::ReadWrite,
+            inline_threshold: 4096,
+            env_filter: Default::default(),
+            task_env_filters: std::collections::HashMap::new(),
+            remote: None,
+        };
+        let executor =
+            TaskExecutor::new_with_config(manager, temp_dir.path().to_path_buf(), cache_config)
+                .await
+                .unwrap();
+
+        assert_eq!(executor.execute_task("build", &[]).await.unwrap(), 0);
+        assert_eq!(executor.execute_task("build", &[]).await.unwrap(), 0);
+
+        let summary = executor.run_summary().unwrap();
+        let caches: Vec<(&str, CacheStatus)> = summary
+            .tasks
+            .iter()
+            .map(|task| (task.name.as_str(), task.cache))
+            .collect();
+        assert_eq!(
+            caches,
+            [
+                ("compile", CacheStatus::Miss),
+                ("build", CacheStatus::Miss),
+                ("compile", CacheStatus::Cached),
+                ("build", CacheStatus::Cached),
+            ]
+        );
+    }
 }
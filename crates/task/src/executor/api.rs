@@ -1,6 +1,17 @@
 use super::TaskExecutor;
 use cuenv_core::{Error, Result};
 
+/// Inputs and outputs inferred from a traced run of a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InferredTaskIo {
+    /// The exit code of the task
+    pub exit_code: i32,
+    /// Globs matching the files the task read
+    pub inputs: Vec<String>,
+    /// Globs matching the files the task wrote
+    pub outputs: Vec<String>,
+}
+
 impl TaskExecutor {
     /// Execute a single task by name
     pub async fn execute_task(&self, task_name: &str, args: &[String]) -> Result<i32> {
@@ -42,6 +53,11 @@ impl TaskExecutor {
         Ok(order)
     }
 
+    /// The summary of the tasks this executor has run, once it ran any
+    pub fn run_summary(&self) -> Option<super::RunSummary> {
+        self.run_recorder.lock().ok()?.summary()
+    }
+
     /// Check if a task has been executed (for testing)
     pub fn is_executed(&self, task_name: &str) -> bool {
         self.executed_tasks
@@ -61,6 +77,37 @@ impl TaskExecutor {
             .await
     }
 
+    /// Run a task's dependencies, then trace the task itself to infer the
+    /// inputs and outputs it uses, relative to its working directory
+    pub async fn infer_task_io(&self, task_name: &str, args: &[String]) -> Result<InferredTaskIo> {
+        let plan = self.build_execution_plan(&[task_name.to_string()])?;
+        let definition = plan.tasks.get(task_name).ok_or_else(|| {
+            Error::configuration(format!("Task '{task_name}' not found in execution plan"))
+        })?;
+
+        // Dependencies run as usual, so the task finds what they produce
+        let dependencies: Vec<String> = plan
+            .levels
+            .iter()
+            .flatten()
+            .filter(|name| *name != task_name)
+            .cloned()
+            .collect();
+        if !dependencies.is_empty() {
+            self.execute_tasks_with_dependencies(&dependencies, &[], false)
+                .await?;
+        }
+
+        let (exit_code, report) =
+            super::runner::audit_single_task(definition, args, self.task_env.as_deref())?;
+        let dir = &definition.working_directory;
+        Ok(InferredTaskIo {
+            exit_code,
+            inputs: report.inferred_inputs(dir),
+            outputs: report.inferred_outputs(dir),
+        })
+    }
+
     /// Execute multiple tasks with their dependencies and output capture
     pub async fn execute_tasks_with_capture(
         &self,
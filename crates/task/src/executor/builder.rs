@@ -1,71 +1,199 @@
-use super::{cache, TaskExecutor};
+use super::observer::Observers;
+use super::{cache, TaskExecutor, TaskObserver};
 use crate::{MonorepoTaskRegistry, TaskBuilder};
-use cuenv_cache::config::CacheConfiguration;
-use cuenv_cache::CacheManager;
+use cuenv_cache::config::{CacheConfigLoader, CacheConfiguration};
+use cuenv_cache::{CacheManager, CacheMode};
+use cuenv_config::{CacheSettings, UserConfig};
 use cuenv_core::Result;
 use cuenv_env::manager::EnvManager;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
 
-impl TaskExecutor {
-    /// Create a new task executor
-    pub async fn new(env_manager: EnvManager, working_dir: PathBuf) -> Result<Self> {
-        // TODO: Add CacheConfigLoader when moved to workspace
-        let cache_config = CacheConfiguration::default();
-        let cache_config_struct = cache::create_cache_config_struct(&cache_config)?;
-        let mut cache_manager = CacheManager::new(cache_config_struct).await?;
-
-        // Apply task-specific cache environment configurations
-        let tasks = env_manager.get_tasks();
-        cache_manager.apply_task_configs(tasks)?;
+/// Where the output of tasks goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// Printed as the tasks write it
+    #[default]
+    Stream,
+    /// Only passed to the observers, for a spinner or the TUI to show; tasks
+    /// cannot read from the terminal
+    Capture,
+}
 
-        let cache_manager = Arc::new(cache_manager);
-        let action_cache = cache_manager.action_cache();
+/// How the commands of tasks are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunnerKind {
+    /// As processes, within the restrictions of their `security`
+    #[default]
+    Process,
+    /// As processes traced for the files and hosts they access, which are
+    /// reported after each task
+    Audit,
+}
 
-        // Create TaskBuilder with current working directory and environment
-        let task_builder = TaskBuilder::new(working_dir.clone());
+/// Options of a [`TaskExecutor`]
+///
+/// Options left unset take their defaults from the user configuration and
+/// the cache configuration, like [`TaskExecutor::new`] does.
+pub struct TaskExecutorBuilder {
+    env_manager: EnvManager,
+    working_dir: Option<PathBuf>,
+    registry: Option<MonorepoTaskRegistry>,
+    max_jobs: Option<usize>,
+    cache_mode: Option<CacheMode>,
+    output_mode: OutputMode,
+    cancellation: CancellationToken,
+    runner: RunnerKind,
+    observers: Observers,
+    task_env: Option<HashMap<String, String>>,
+}
 
-        Ok(Self {
+impl TaskExecutorBuilder {
+    /// An executor for the tasks of `env_manager`, run in `working_dir`
+    pub fn new(env_manager: EnvManager, working_dir: PathBuf) -> Self {
+        Self {
             env_manager,
-            working_dir,
-            cache_manager,
-            action_cache,
-            cache_config,
-            task_builder,
-            monorepo_registry: None,
-            executed_tasks: Arc::new(Mutex::new(HashSet::new())),
-        })
+            working_dir: Some(working_dir),
+            registry: None,
+            max_jobs: None,
+            cache_mode: None,
+            output_mode: OutputMode::default(),
+            cancellation: CancellationToken::new(),
+            runner: RunnerKind::default(),
+            observers: Observers::default(),
+            task_env: None,
+        }
     }
 
-    /// Create a new task executor with monorepo registry for cross-package execution
-    pub async fn new_with_registry(registry: MonorepoTaskRegistry) -> Result<Self> {
-        // Create a minimal env manager for the registry-based executor
-        let env_manager = EnvManager::new();
-        let working_dir = std::env::current_dir()?;
+    /// An executor for the tasks of every package of `registry`, each run in
+    /// its package
+    pub fn with_registry(registry: MonorepoTaskRegistry) -> Self {
+        Self {
+            working_dir: None,
+            registry: Some(registry),
+            ..Self::new(EnvManager::new(), PathBuf::new())
+        }
+    }
+
+    /// Run at most `jobs` tasks at once, instead of the `jobs` of the user
+    /// configuration
+    pub fn with_max_jobs(mut self, jobs: usize) -> Self {
+        self.max_jobs = Some(jobs.max(1));
+        self
+    }
+
+    /// Use the cache in `mode`, instead of the mode of the cache
+    /// configuration and `CUENV_CACHE`
+    pub fn with_cache_mode(mut self, mode: CacheMode) -> Self {
+        self.cache_mode = Some(mode);
+        self
+    }
+
+    pub fn with_output_mode(mut self, mode: OutputMode) -> Self {
+        self.output_mode = mode;
+        self
+    }
+
+    /// Stop the run when `token` is cancelled: running tasks are killed and
+    /// no other task starts
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    pub fn with_runner(mut self, runner: RunnerKind) -> Self {
+        self.runner = runner;
+        self
+    }
 
-        // TODO: Add CacheConfigLoader when moved to workspace
-        let cache_config = CacheConfiguration::default();
-        let cache_config_struct = cache::create_cache_config_struct(&cache_config)?;
-        let cache_manager = CacheManager::new(cache_config_struct).await?;
+    /// Also tell `observer` when tasks start, print and finish
+    pub fn with_observer(mut self, observer: Arc<dyn TaskObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Tell only `observers` when tasks start, print and finish, instead of
+    /// printing their output and logging their events
+    pub fn with_observers(mut self, observers: Vec<Arc<dyn TaskObserver>>) -> Self {
+        self.observers = Observers::new(observers);
+        self
+    }
+
+    /// Run the tasks in the environment `env`, as evaluated for a client,
+    /// instead of the environment of this process
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.task_env = Some(env);
+        self
+    }
+
+    pub async fn build(self) -> Result<TaskExecutor> {
+        let mut cache_config = CacheConfigLoader::load()?;
+        if let Some(mode) = self.cache_mode {
+            cache_config.global.mode = mode;
+        }
+        // Tasks of a registry run in their package; the working directory
+        // only resolves their relative paths
+        let working_dir = match self.working_dir {
+            Some(working_dir) => working_dir,
+            None => std::env::current_dir()?,
+        };
+
+        let mut cache_config_struct = cache::create_cache_config_struct(&cache_config)?;
+        cache_config_struct.remote = CacheSettings::resolve(&working_dir, None).remote;
+        let mut cache_manager = CacheManager::new(cache_config_struct).await?;
+
+        // Apply task-specific cache environment configurations
+        if self.registry.is_none() {
+            cache_manager.apply_task_configs(self.env_manager.get_tasks())?;
+        }
 
         let cache_manager = Arc::new(cache_manager);
         let action_cache = cache_manager.action_cache();
-
-        // Create TaskBuilder with current working directory
         let task_builder = TaskBuilder::new(working_dir.clone());
 
-        Ok(Self {
-            env_manager,
+        Ok(TaskExecutor {
+            env_manager: self.env_manager,
             working_dir,
             cache_manager,
             action_cache,
-            cache_config,
+            cache_config: Arc::new(cache_config),
             task_builder,
-            monorepo_registry: Some(Arc::new(registry)),
+            monorepo_registry: self.registry.map(Arc::new),
             executed_tasks: Arc::new(Mutex::new(HashSet::new())),
+            run_recorder: Arc::default(),
+            job_slots: match self.max_jobs {
+                Some(jobs) => Some(Arc::new(Semaphore::new(jobs))),
+                None => job_slots(),
+            },
+            observers: self.observers,
+            output_mode: self.output_mode,
+            runner: self.runner,
+            cancellation: self.cancellation,
+            task_env: self.task_env.map(Arc::new),
         })
     }
+}
+
+impl TaskExecutor {
+    /// Options of an executor for the tasks of `env_manager`
+    pub fn builder(env_manager: EnvManager, working_dir: PathBuf) -> TaskExecutorBuilder {
+        TaskExecutorBuilder::new(env_manager, working_dir)
+    }
+
+    /// Create a new task executor
+    pub async fn new(env_manager: EnvManager, working_dir: PathBuf) -> Result<Self> {
+        TaskExecutorBuilder::new(env_manager, working_dir)
+            .build()
+            .await
+    }
+
+    /// Create a new task executor with monorepo registry for cross-package execution
+    pub async fn new_with_registry(registry: MonorepoTaskRegistry) -> Result<Self> {
+        TaskExecutorBuilder::with_registry(registry).build().await
+    }
 
     /// Create a new task executor with custom cache config (for testing)
     #[cfg(test)]
@@ -92,10 +220,82 @@ impl TaskExecutor {
             working_dir,
             cache_manager,
             action_cache,
-            cache_config: cache_configuration,
+            cache_config: Arc::new(cache_configuration),
             task_builder,
             monorepo_registry: None,
             executed_tasks: Arc::new(Mutex::new(HashSet::new())),
+            run_recorder: Arc::default(),
+            job_slots: job_slots(),
+            observers: Observers::default(),
+            output_mode: OutputMode::default(),
+            runner: RunnerKind::default(),
+            cancellation: CancellationToken::new(),
+            task_env: None,
         })
     }
 }
+
+impl TaskExecutor {
+    /// Run at most `jobs` tasks at once, instead of the `jobs` of the user
+    /// configuration
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.job_slots = Some(Arc::new(Semaphore::new(jobs)));
+        self
+    }
+
+    /// Also tell `observer` when tasks start, print and finish
+    pub fn with_observer(mut self, observer: Arc<dyn TaskObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Tell only `observers` when tasks start, print and finish, instead of
+    /// printing their output and logging their events
+    pub fn with_observers(mut self, observers: Vec<Arc<dyn TaskObserver>>) -> Self {
+        self.observers = Observers::new(observers);
+        self
+    }
+
+    /// The environment tasks run in, which their cache keys are computed from
+    pub(crate) fn task_env(&self) -> HashMap<String, String> {
+        match &self.task_env {
+            Some(env) => env.as_ref().clone(),
+            None => std::env::vars().collect(),
+        }
+    }
+}
+
+/// Slots for the tasks that may run at once, when the user limits them
+fn job_slots() -> Option<Arc<Semaphore>> {
+    UserConfig::get()
+        .jobs
+        .map(|jobs| Arc::new(Semaphore::new(jobs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_options() {
+        let token = CancellationToken::new();
+        let builder = TaskExecutor::builder(EnvManager::new(), PathBuf::from("/repo"))
+            .with_max_jobs(0)
+            .with_cache_mode(CacheMode::Read)
+            .with_output_mode(OutputMode::Capture)
+            .with_runner(RunnerKind::Audit)
+            .with_cancellation_token(token.clone())
+            .with_observers(Vec::new());
+
+        assert_eq!(builder.max_jobs, Some(1));
+        assert_eq!(builder.cache_mode, Some(CacheMode::Read));
+        assert_eq!(builder.output_mode, OutputMode::Capture);
+        assert_eq!(builder.runner, RunnerKind::Audit);
+        assert!(!builder.observers.observe_output());
+        token.cancel();
+        assert!(builder.cancellation.is_cancelled());
+
+        let registry = TaskExecutorBuilder::with_registry(MonorepoTaskRegistry::new());
+        assert!(registry.working_dir.is_none());
+    }
+}
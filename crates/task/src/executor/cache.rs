@@ -1,87 +1,66 @@
 use super::context::TaskExecutionContext;
 use super::runner;
+use super::summary::CacheStatus;
 use cuenv_cache::config::{CacheConfig, CacheConfiguration};
+use cuenv_cache::CacheMode;
 use cuenv_core::{Result, TaskDefinition};
 
 /// Create cache config struct from configuration
 pub fn create_cache_config_struct(cache_config: &CacheConfiguration) -> Result<CacheConfig> {
-    // TODO: Fix when CacheConfig is properly exposed
-    let mut config = CacheConfig::default();
-
-    // Apply global configuration
-    if let Some(base_dir) = &cache_config.global.base_dir {
-        config.base_dir = base_dir.clone();
-    }
-
-    if let Some(max_size) = cache_config.global.max_size {
-        config.max_size = max_size;
-    }
-
-    if let Some(inline_threshold) = cache_config.global.inline_threshold {
-        config.inline_threshold = inline_threshold;
-    }
-
-    if let Some(env_filter) = &cache_config.global.env_filter {
-        config.env_filter = env_filter.clone();
-    }
-
-    config.mode = cache_config.global.mode;
+    Ok(CacheConfig::from(&cache_config.global))
+}
 
-    Ok(config)
+/// Whether the result of `task_definition` is looked up in and stored to
+/// the cache: the task enables caching and the configuration does not turn
+/// it off
+pub(crate) fn cache_enabled(
+    cache_config: &CacheConfiguration,
+    task_definition: &TaskDefinition,
+) -> bool {
+    cache_config.global.enabled
+        && cache_config.global.mode != CacheMode::Off
+        && task_definition.cache.enabled
 }
 
-/// Execute a single task with caching support
+/// Execute a single task with caching support, returning its exit code,
+/// where its result came from and the key it is cached under
 pub async fn execute_single_task_with_cache(
     ctx: &TaskExecutionContext<'_>,
     task_name: &str,
     task_definition: &TaskDefinition,
     args: &[String],
-) -> Result<i32> {
-    // Check if caching is enabled for this task using the new configuration system
-    // TODO: Add CacheConfigResolver when moved to workspace
-    let cache_enabled = false;
-    let _unused = (&ctx.cache_config.global, &task_definition.cache, task_name);
-
-    if !cache_enabled {
+) -> Result<(i32, CacheStatus, Option<String>)> {
+    if !cache_enabled(ctx.cache_config, task_definition) {
         // Execute without caching
         // TODO: Add tracing when moved to workspace
         // task_progress(task_name, None, "Executing task (cache disabled)");
-        return runner::execute_single_task(
-            task_name,
-            task_definition,
-            ctx.working_dir,
-            args,
-            ctx.audit_mode,
-            ctx.capture_output,
-        )
-        .await;
+        let exit_code = runner::execute_single_task(ctx, task_name, task_definition, args).await?;
+        return Ok((exit_code, CacheStatus::Disabled, None));
     }
 
     // Generate action digest using ActionCache
-    let env_vars = std::env::vars().collect();
+    let env_vars = match ctx.env {
+        Some(env) => env.clone(),
+        None => std::env::vars().collect(),
+    };
     let digest = ctx
         .action_cache
         .compute_digest(task_name, task_definition, ctx.working_dir, env_vars)
         .await?;
 
-    // Execute with ActionCache
+    // Execute with ActionCache; the task only runs on a cache miss
+    let ran = std::sync::atomic::AtomicBool::new(false);
     let result = ctx
         .action_cache
         .execute_action(&digest, || async {
+            ran.store(true, std::sync::atomic::Ordering::Relaxed);
             // TODO: Add tracing when moved to workspace
             // cache_event(task_name, false, "task_result");
             // TODO: Add tracing when moved to workspace
             // task_progress(task_name, Some(0), "Starting task execution");
 
-            let exit_code = runner::execute_single_task(
-                task_name,
-                task_definition,
-                ctx.working_dir,
-                args,
-                ctx.audit_mode,
-                ctx.capture_output,
-            )
-            .await?;
+            let exit_code =
+                runner::execute_single_task(ctx, task_name, task_definition, args).await?;
 
             // Create ActionResult for caching
             // TODO: Fix when ActionResult is properly exposed
@@ -90,6 +69,8 @@ pub async fn execute_single_task_with_cache(
                 stdout_hash: None, // Not captured in current implementation
                 stderr_hash: None, // Not captured in current implementation
                 output_files: std::collections::HashMap::new(),
+                artifacts: task_definition.artifacts.clone(),
+                executables: Vec::new(),
                 executed_at: std::time::SystemTime::now(),
                 duration_ms: 0, // Not tracked in current implementation
             })
@@ -109,5 +90,10 @@ pub async fn execute_single_task_with_cache(
         );
     }
 
-    Ok(result.exit_code)
+    let cache = if ran.load(std::sync::atomic::Ordering::Relaxed) {
+        CacheStatus::Miss
+    } else {
+        CacheStatus::Hit
+    };
+    Ok((result.exit_code, cache, Some(digest.hash)))
 }
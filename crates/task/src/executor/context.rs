@@ -1,6 +1,9 @@
+use super::observer::Observers;
 use cuenv_cache::concurrent::action::ActionCache;
 use cuenv_cache::config::CacheConfiguration;
+use std::collections::HashMap;
 use std::path::Path;
+use tokio_util::sync::CancellationToken;
 
 /// Context for task execution to reduce function parameter count
 pub struct TaskExecutionContext<'a> {
@@ -9,4 +12,9 @@ pub struct TaskExecutionContext<'a> {
     pub action_cache: &'a ActionCache,
     pub audit_mode: bool,
     pub capture_output: bool,
+    pub(crate) observers: &'a Observers,
+    /// Kills the task when cancelled
+    pub(crate) cancellation: &'a CancellationToken,
+    /// The environment the task runs in, instead of that of this process
+    pub(crate) env: Option<&'a HashMap<String, String>>,
 }
@@ -1,53 +1,203 @@
 use cuenv_core::{Error, Result, TaskDefinition};
 use std::collections::{HashMap, HashSet};
 
-/// Recursively collect task dependencies from task definitions (Phase 3)
-pub fn collect_dependencies_from_definitions(
-    task_name: &str,
-    all_tasks: &HashMap<String, TaskDefinition>,
-    task_dependencies: &mut HashMap<String, Vec<String>>,
-    visited: &mut HashSet<String>,
-    stack: &mut HashSet<String>,
-) -> Result<()> {
-    // Check for circular dependencies
-    if stack.contains(task_name) {
-        return Err(Error::configuration(format!(
-            "Circular dependency detected involving task '{task_name}'"
-        )));
-    }
+/// Collect the dependencies of `roots` and of every task they depend on
+///
+/// The graph is walked with an explicit stack rather than by recursion, so
+/// chains of thousands of tasks cannot overflow the stack.
+/// `dependencies_of` is called once per task and fails for unknown tasks.
+pub fn collect_dependencies<F>(
+    roots: &[String],
+    mut dependencies_of: F,
+) -> Result<HashMap<String, Vec<String>>>
+where
+    F: FnMut(&str) -> Result<Vec<String>>,
+{
+    let mut collected: HashMap<String, Vec<String>> = HashMap::new();
+    let mut on_stack: HashSet<String> = HashSet::new();
+    // Each task being visited, its dependencies and the next one to visit
+    let mut stack: Vec<(String, Vec<String>, usize)> = Vec::new();
+
+    for root in roots {
+        if collected.contains_key(root) {
+            continue;
+        }
+        stack.push((root.clone(), dependencies_of(root)?, 0));
+        on_stack.insert(root.clone());
+
+        while let Some((_, dependencies, next)) = stack.last_mut() {
+            let Some(dependency) = dependencies.get(*next) else {
+                if let Some((task, dependencies, _)) = stack.pop() {
+                    on_stack.remove(&task);
+                    collected.insert(task, dependencies);
+                }
+                continue;
+            };
+            *next += 1;
 
-    if visited.contains(task_name) {
-        return Ok(());
+            // Check for circular dependencies
+            if on_stack.contains(dependency) {
+                return Err(Error::configuration(format!(
+                    "Circular dependency detected involving task '{dependency}'"
+                )));
+            }
+            if collected.contains_key(dependency) {
+                continue;
+            }
+            let dependency = dependency.clone();
+            let dependencies = dependencies_of(&dependency)?;
+            on_stack.insert(dependency.clone());
+            stack.push((dependency, dependencies, 0));
+        }
     }
 
-    stack.insert(task_name.to_owned());
+    Ok(collected)
+}
 
-    let task_definition = all_tasks
-        .get(task_name)
-        .ok_or_else(|| Error::configuration(format!("Task '{task_name}' not found")))?;
+/// Order the collected tasks after those of their weak dependencies that
+/// were collected for other tasks
+///
+/// Weak dependencies are never collected themselves.
+/// `weak_dependencies_of` fails for unknown tasks.
+pub fn add_weak_dependencies<F>(
+    collected: &mut HashMap<String, Vec<String>>,
+    mut weak_dependencies_of: F,
+) -> Result<()>
+where
+    F: FnMut(&str) -> Result<Vec<String>>,
+{
+    let tasks: Vec<String> = collected.keys().cloned().collect();
+    for task in tasks {
+        let planned: Vec<String> = weak_dependencies_of(&task)?
+            .into_iter()
+            .filter(|dependency| collected.contains_key(dependency))
+            .collect();
+        if let Some(dependencies) = collected.get_mut(&task) {
+            for dependency in planned {
+                if !dependencies.contains(&dependency) {
+                    dependencies.push(dependency);
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
-    let dependencies = task_definition.dependency_names();
+/// Collect task dependencies from task definitions (Phase 3)
+///
+/// Required and order-only dependencies are collected, weak ones only order
+/// the tasks collected.
+pub fn collect_dependencies_from_definitions(
+    task_names: &[String],
+    all_tasks: &HashMap<String, TaskDefinition>,
+) -> Result<HashMap<String, Vec<String>>> {
+    let dependencies_of = |task_name: &str, weak: bool| -> Result<Vec<String>> {
+        let task_definition = all_tasks
+            .get(task_name)
+            .ok_or_else(|| Error::configuration(format!("Task '{task_name}' not found")))?;
 
-    // Validate and collect dependencies
-    for dep_name in &dependencies {
-        if !all_tasks.contains_key(dep_name) {
+        let dependencies = if weak {
+            task_definition.weak_dependency_names()
+        } else {
+            task_definition.planned_dependency_names()
+        };
+        if let Some(dep_name) = dependencies
+            .iter()
+            .find(|dep_name| !all_tasks.contains_key(*dep_name))
+        {
             return Err(Error::configuration(format!(
                 "Dependency '{dep_name}' of task '{task_name}' not found"
             )));
         }
+        Ok(dependencies)
+    };
+
+    let mut collected =
+        collect_dependencies(task_names, |task_name| dependencies_of(task_name, false))?;
+    add_weak_dependencies(&mut collected, |task_name| dependencies_of(task_name, true))?;
+    Ok(collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_core::{DependencyKind, ResolvedDependency, TaskExecutionMode};
+
+    #[test]
+    fn test_collects_long_chains() {
+        let dependencies_of = |task: &str| -> Result<Vec<String>> {
+            let index: usize = task[1..].parse().unwrap();
+            Ok(match index {
+                0 => Vec::new(),
+                _ => vec![format!("t{}", index - 1)],
+            })
+        };
 
-        collect_dependencies_from_definitions(
-            dep_name,
-            all_tasks,
-            task_dependencies,
-            visited,
-            stack,
-        )?;
+        let collected = collect_dependencies(&["t50000".to_string()], dependencies_of).unwrap();
+        assert_eq!(collected.len(), 50_001);
+        assert_eq!(collected["t1"], ["t0"]);
     }
 
-    task_dependencies.insert(task_name.to_owned(), dependencies);
-    visited.insert(task_name.to_owned());
-    stack.remove(task_name);
+    #[test]
+    fn test_detects_cycles() {
+        let dependencies_of = |task: &str| -> Result<Vec<String>> {
+            Ok(match task {
+                "build" => vec!["codegen".to_string()],
+                "codegen" => vec!["schema".to_string()],
+                _ => vec!["build".to_string()],
+            })
+        };
+        let error = collect_dependencies(&["build".to_string()], dependencies_of).unwrap_err();
+        assert!(error.to_string().contains("involving task 'build'"));
+    }
 
-    Ok(())
+    #[test]
+    fn test_dependency_kinds() {
+        let task = |name: &str, dependencies: &[(&str, DependencyKind)]| {
+            let mut definition = TaskDefinition::new(
+                name.to_string(),
+                TaskExecutionMode::Command {
+                    command: "true".to_string(),
+                },
+                ".".into(),
+            );
+            definition.dependencies = dependencies
+                .iter()
+                .map(|(name, kind)| ResolvedDependency::new(name.to_string()).with_kind(*kind))
+                .collect();
+            (name.to_string(), definition)
+        };
+        let all_tasks = HashMap::from([
+            task("codegen", &[]),
+            task("lint", &[]),
+            task("docs", &[]),
+            task(
+                "build",
+                &[
+                    ("codegen", DependencyKind::Required),
+                    ("lint", DependencyKind::OrderOnly),
+                    ("docs", DependencyKind::Weak),
+                ],
+            ),
+            task(
+                "release",
+                &[
+                    ("build", DependencyKind::Required),
+                    ("docs", DependencyKind::Required),
+                ],
+            ),
+        ]);
+
+        // Weak dependencies are left out unless planned for another task
+        let collected =
+            collect_dependencies_from_definitions(&["build".to_string()], &all_tasks).unwrap();
+        let mut planned: Vec<&str> = collected.keys().map(String::as_str).collect();
+        planned.sort_unstable();
+        assert_eq!(planned, ["build", "codegen", "lint"]);
+        assert_eq!(collected["build"], ["codegen", "lint"]);
+
+        let collected =
+            collect_dependencies_from_definitions(&["release".to_string()], &all_tasks).unwrap();
+        assert_eq!(collected["build"], ["codegen", "lint", "docs"]);
+    }
 }
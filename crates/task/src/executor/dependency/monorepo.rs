@@ -1,30 +1,15 @@
-use crate::MonorepoTaskRegistry;
+use crate::{MonorepoTaskRegistry, RegisteredTask};
 use cuenv_config::TaskConfig;
-use cuenv_core::{Error, Result};
-use std::collections::{HashMap, HashSet};
+use cuenv_core::{DependencyKind, Error, Result};
+use std::collections::HashMap;
 
-/// Recursively collect dependencies for monorepo tasks
-pub fn collect_monorepo_dependencies(
+/// The required and order-only dependencies of a monorepo task, resolving
+/// cross-package references, with its config added to `all_tasks`
+pub fn monorepo_dependencies(
     task_name: &str,
     registry: &MonorepoTaskRegistry,
     all_tasks: &mut HashMap<String, TaskConfig>,
-    task_dependencies: &mut HashMap<String, Vec<String>>,
-    visited: &mut HashSet<String>,
-    stack: &mut HashSet<String>,
-) -> Result<()> {
-    // Check for circular dependencies
-    if stack.contains(task_name) {
-        return Err(Error::configuration(format!(
-            "Circular dependency detected involving task '{task_name}'"
-        )));
-    }
-
-    if visited.contains(task_name) {
-        return Ok(());
-    }
-
-    stack.insert(task_name.to_owned());
-
+) -> Result<Vec<String>> {
     let task = registry
         .get_task(task_name)
         .ok_or_else(|| Error::configuration(format!("Task '{task_name}' not found")))?;
@@ -32,44 +17,59 @@ pub fn collect_monorepo_dependencies(
     // Add task config to all_tasks
     all_tasks.insert(task_name.to_owned(), task.config.clone());
 
-    let mut dependencies = Vec::new();
+    resolve_dependencies(task_name, task, registry, |kind| {
+        kind != DependencyKind::Weak
+    })
+}
 
-    // Process dependencies, resolving cross-package references
-    if let Some(ref deps) = task.config.dependencies {
-        for dep in deps {
-            // Check if this is a cross-package reference
-            let full_dep_name = if dep.contains(':') {
-                // Already a full cross-package reference
-                dep.clone()
-            } else {
-                // Local task reference, add package prefix
-                format!("{}:{}", task.package_name, dep)
-            };
+/// The weak dependencies of a monorepo task, resolving cross-package
+/// references
+pub fn monorepo_weak_dependencies(
+    task_name: &str,
+    registry: &MonorepoTaskRegistry,
+) -> Result<Vec<String>> {
+    let task = registry
+        .get_task(task_name)
+        .ok_or_else(|| Error::configuration(format!("Task '{task_name}' not found")))?;
+    resolve_dependencies(task_name, task, registry, |kind| {
+        kind == DependencyKind::Weak
+    })
+}
 
-            // Validate dependency exists
-            if registry.get_task(&full_dep_name).is_none() {
-                return Err(Error::configuration(format!(
-                    "Dependency '{full_dep_name}' of task '{task_name}' not found"
-                )));
-            }
+/// The full names of the dependencies of `task` of the kinds `matches`
+fn resolve_dependencies(
+    task_name: &str,
+    task: &RegisteredTask,
+    registry: &MonorepoTaskRegistry,
+    matches: impl Fn(DependencyKind) -> bool,
+) -> Result<Vec<String>> {
+    let mut dependencies = Vec::new();
 
-            dependencies.push(full_dep_name.clone());
+    // Process dependencies, resolving cross-package references
+    for (dep, _) in task
+        .config
+        .dependencies_by_kind()
+        .filter(|(_, kind)| matches(*kind))
+    {
+        // A task of the same package wins over a cross-package reading
+        // of a name such as `npm:build`
+        let local_dep_name = format!("{}:{}", task.package_name, dep);
+        let full_dep_name = if !dep.contains(':') || registry.get_task(&local_dep_name).is_some() {
+            local_dep_name
+        } else {
+            // Already a full cross-package reference
+            dep.clone()
+        };
 
-            // Recursively collect dependencies
-            collect_monorepo_dependencies(
-                &full_dep_name,
-                registry,
-                all_tasks,
-                task_dependencies,
-                visited,
-                stack,
-            )?;
+        // Validate dependency exists
+        if registry.get_task(&full_dep_name).is_none() {
+            return Err(Error::configuration(format!(
+                "Dependency '{full_dep_name}' of task '{task_name}' not found"
+            )));
         }
-    }
 
-    task_dependencies.insert(task_name.to_owned(), dependencies);
-    visited.insert(task_name.to_owned());
-    stack.remove(task_name);
+        dependencies.push(full_dep_name);
+    }
 
-    Ok(())
+    Ok(dependencies)
 }
@@ -3,11 +3,14 @@ use crate::executor::plan::TaskExecutionPlan;
 use crate::executor::TaskExecutor;
 use crate::MonorepoTaskRegistry;
 use cuenv_core::{Error, Result};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
 impl TaskExecutor {
     /// Build an execution plan with dependency resolution
     pub fn build_execution_plan(&self, task_names: &[String]) -> Result<TaskExecutionPlan> {
+        let _plan_span = tracing::debug_span!("plan", tasks = ?task_names).entered();
+
         // If we have a monorepo registry, use it for cross-package task resolution
         if let Some(ref registry) = self.monorepo_registry {
             return self.build_monorepo_execution_plan(task_names, registry);
@@ -27,39 +30,28 @@ impl TaskExecutor {
         }
 
         // Build task definitions using TaskBuilder with task nodes
-        let task_definitions = self
+        let mut task_definitions = self
             .task_builder
             .build_tasks_with_nodes(all_task_configs.clone(), all_task_nodes.clone())?;
 
         // Build dependency graph using task definitions
-        let mut task_dependencies = HashMap::with_capacity(task_definitions.len());
-        let mut visited = HashSet::with_capacity(task_definitions.len());
-        let mut stack = HashSet::new();
-
-        for task_name in task_names {
-            super::collector::collect_dependencies_from_definitions(
-                task_name,
-                &task_definitions,
-                &mut task_dependencies,
-                &mut visited,
-                &mut stack,
-            )?;
-        }
+        let task_dependencies =
+            super::collector::collect_dependencies_from_definitions(task_names, &task_definitions)?;
 
         // Topological sort to determine execution order
         let levels = graph::topological_sort(&task_dependencies)?;
 
-        // Build final execution plan
-        let mut plan_tasks = HashMap::with_capacity(task_dependencies.len());
-        for task_name in task_dependencies.keys() {
-            if let Some(definition) = task_definitions.get(task_name) {
-                plan_tasks.insert(task_name.clone(), definition.clone());
-            }
-        }
+        // The definitions of the tasks planned move into the plan
+        let plan_tasks: HashMap<_, _> = task_dependencies
+            .keys()
+            .filter_map(|task_name| task_definitions.remove_entry(task_name))
+            .collect();
 
         Ok(TaskExecutionPlan {
             levels,
             tasks: plan_tasks,
+            dependencies: task_dependencies,
+            cache_keys: BTreeMap::new(),
         })
     }
 
@@ -70,25 +62,15 @@ impl TaskExecutor {
         registry: &MonorepoTaskRegistry,
     ) -> Result<TaskExecutionPlan> {
         let mut all_tasks = HashMap::new();
-        let mut task_dependencies = HashMap::new();
-        let mut visited = HashSet::new();
-        let mut stack = HashSet::new();
 
         // Validate and collect tasks from registry
-        for task_name in task_names {
-            let _task = registry
-                .get_task(task_name)
-                .ok_or_else(|| Error::configuration(format!("Task '{task_name}' not found")))?;
-
-            super::monorepo::collect_monorepo_dependencies(
-                task_name,
-                registry,
-                &mut all_tasks,
-                &mut task_dependencies,
-                &mut visited,
-                &mut stack,
-            )?;
-        }
+        let mut task_dependencies =
+            super::collector::collect_dependencies(task_names, |task_name| {
+                super::monorepo::monorepo_dependencies(task_name, registry, &mut all_tasks)
+            })?;
+        super::collector::add_weak_dependencies(&mut task_dependencies, |task_name| {
+            super::monorepo::monorepo_weak_dependencies(task_name, registry)
+        })?;
 
         // Build task definitions using TaskBuilder
         let task_definitions = self.task_builder.build_tasks(all_tasks)?;
@@ -99,6 +81,40 @@ impl TaskExecutor {
         Ok(TaskExecutionPlan {
             levels,
             tasks: task_definitions,
+            dependencies: task_dependencies,
+            cache_keys: BTreeMap::new(),
         })
     }
+
+    /// Compute the cache key of every task of `plan` from its definition,
+    /// its input files as they are now and the environment of this process
+    pub async fn compute_cache_keys(&self, plan: &mut TaskExecutionPlan) -> Result<()> {
+        let mut cache_keys = BTreeMap::new();
+        for (task_name, definition) in &plan.tasks {
+            let digest = self
+                .action_cache
+                .compute_digest(
+                    task_name,
+                    definition,
+                    &self.task_working_dir(task_name),
+                    self.task_env(),
+                )
+                .await?;
+            cache_keys.insert(task_name.clone(), digest.hash);
+        }
+        plan.cache_keys = cache_keys;
+        Ok(())
+    }
+
+    /// The directory `task_name` runs in: its package for a task of the
+    /// monorepo registry
+    pub(crate) fn task_working_dir(&self, task_name: &str) -> PathBuf {
+        self.monorepo_registry
+            .as_ref()
+            .and_then(|registry| registry.get_task(task_name))
+            .map_or_else(
+                || self.working_dir.clone(),
+                |task| task.package_path.clone(),
+            )
+    }
 }
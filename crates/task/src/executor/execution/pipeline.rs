@@ -1,7 +1,15 @@
-use crate::executor::TaskExecutor;
-use cuenv_core::{Error, Result};
+use crate::executor::cache;
+use crate::executor::graph::Schedule;
+use crate::executor::observer::{Observers, TaskOutcome};
+use crate::executor::summary::{CacheStatus, TaskRecord};
+use crate::executor::task_log::{failure_report, FailureExcerpt, TaskLogs};
+use crate::executor::{OutputMode, RunnerKind, TaskExecutionPlan, TaskExecutor};
+use cuenv_core::{Error, Result, TaskDefinition};
+use cuenv_env::manager::{refresh_expiring_secrets, REFRESH_MARGIN};
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
-use tokio::task::JoinSet;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
 impl TaskExecutor {
     /// Internal method that supports output capture for TUI mode
@@ -14,6 +22,46 @@ impl TaskExecutor {
     ) -> Result<i32> {
         // Build execution plan
         let plan = self.build_execution_plan(task_names)?;
+        self.execute_plan_internal(&plan, args, audit_mode, capture_output)
+            .await
+    }
+
+    /// Run the tasks of `plan`, each as soon as the tasks it depends on
+    /// finished, such as a plan read with [`TaskExecutionPlan::from_json`]
+    pub async fn execute_plan(
+        &self,
+        plan: &TaskExecutionPlan,
+        args: &[String],
+        audit_mode: bool,
+    ) -> Result<i32> {
+        self.execute_plan_internal(plan, args, audit_mode, false)
+            .await
+    }
+
+    async fn execute_plan_internal(
+        &self,
+        plan: &TaskExecutionPlan,
+        args: &[String],
+        audit_mode: bool,
+        capture_output: bool,
+    ) -> Result<i32> {
+        let capture_output = capture_output || self.output_mode == OutputMode::Capture;
+        let audit_mode = audit_mode || self.runner == RunnerKind::Audit;
+        // Nothing may write to a terminal a spinner or the TUI draws on
+        let mut observers = if capture_output {
+            self.observers.without_terminal()
+        } else {
+            self.observers.clone()
+        };
+        let task_logs = Arc::new(TaskLogs::new(
+            &self.working_dir,
+            cuenv_utils::tracing::run_id(),
+        ));
+        observers.push(task_logs.clone());
+
+        if let Ok(mut recorder) = self.run_recorder.lock() {
+            recorder.begin(&plan.levels);
+        }
 
         // Create pipeline span for the entire execution
         // TODO: Add tracing when moved to workspace
@@ -21,92 +69,222 @@ impl TaskExecutor {
         let pipeline_guard = _pipeline_span.enter();
 
         tracing::info!(
-            requested_tasks = ?task_names,
             total_tasks = %plan.tasks.len(),
             levels = %plan.levels.len(),
             "Starting task execution pipeline"
         );
 
-        // Execute tasks level by level
-        for (level_idx, level) in plan.levels.iter().enumerate() {
-            // TODO: Add tracing when moved to workspace
-            let _level_span = tracing::info_span!("level", idx = level_idx, tasks = level.len());
-            let level_guard = _level_span.enter();
-
-            tracing::info!(
-                level = %level_idx,
-                tasks = ?level,
-                "Starting execution level"
-            );
-            let mut join_set = JoinSet::new();
-            let failed_tasks = Arc::new(Mutex::new(Vec::with_capacity(level.len())));
-
-            // Launch all tasks in this level concurrently
-            for task_name in level {
-                let task_definition = match plan.tasks.get(task_name) {
-                    Some(definition) => definition.clone(),
-                    None => {
-                        return Err(Error::configuration(format!(
-                            "Task '{task_name}' not found in execution plan"
-                        )));
-                    }
-                };
-
-                // Determine working directory based on whether this is a cross-package task
-                let working_dir = if let Some(ref registry) = self.monorepo_registry {
-                    // For cross-package tasks, get the package path from the registry
-                    if let Some(task) = registry.get_task(task_name) {
-                        task.package_path.clone()
-                    } else {
-                        self.working_dir.clone()
-                    }
-                } else {
-                    self.working_dir.clone()
-                };
-
-                super::task::spawn_task_execution(
-                    &mut join_set,
-                    super::task::TaskExecutionParams {
-                        task_name: task_name.clone(),
-                        task_definition,
-                        working_dir,
-                        task_args: args.to_vec(),
-                        failed_tasks: Arc::clone(&failed_tasks),
-                        action_cache: Arc::clone(&self.action_cache),
-                        _env_manager: self.env_manager.clone(),
-                        cache_config: self.cache_config.clone(),
-                        executed_tasks: Arc::clone(&self.executed_tasks),
-                        audit_mode,
-                        capture_output,
-                    },
+        // Tasks whose whole subtree is cached are never spawned
+        let cached = self.cached_tasks(plan, args).await;
+        if !cached.is_empty() {
+            tracing::info!(cached = %cached.len(), "Tasks satisfied from the cache");
+        }
+
+        // Tasks start as soon as the tasks they depend on finished. Each
+        // runs on the runtime, whose workers steal tasks from each other,
+        // and reports back when it is done.
+        let mut schedule = Schedule::new(plan)?;
+        let failed_tasks = Arc::new(Mutex::new(Vec::new()));
+        let (done_sender, mut done) = mpsc::unbounded_channel();
+        let mut ready: VecDeque<usize> = schedule.ready().into();
+        let mut running = 0;
+        let mut panicked = None;
+
+        loop {
+            // Nothing new starts once the run is cancelled or a task failed
+            let stopped = self.cancellation.is_cancelled()
+                || panicked.is_some()
+                || failed_tasks
+                    .lock()
+                    .map_or(true, |failed| !failed.is_empty());
+            if !stopped && !ready.is_empty() {
+                tracing::info!(
+                    tasks = ?ready.iter().map(|&task| schedule.name(task)).collect::<Vec<_>>(),
+                    "Starting ready tasks"
                 );
-            }
 
-            // Wait for all tasks in this level to complete
-            while let Some(result) = join_set.join_next().await {
-                if let Err(e) = result {
-                    return Err(Error::configuration(format!("Task execution failed: {e}")));
+                // Long pipelines can outlive short-lived credentials, so
+                // renew them before starting more tasks
+                if let Err(e) = refresh_expiring_secrets(REFRESH_MARGIN) {
+                    tracing::warn!("Failed to refresh expiring secrets: {e}");
                 }
-            }
 
-            // Check if any tasks failed
-            let failed = failed_tasks
-                .lock()
-                .map_err(|e| Error::configuration(format!("Failed to acquire lock: {e}")))?;
-            if !failed.is_empty() {
-                let failed_names: Vec<&str> =
-                    failed.iter().map(|(name, _)| name.as_str()).collect();
-                return Err(Error::configuration(format!(
-                    "Tasks failed: {}",
-                    failed_names.join(", ")
-                )));
+                while let Some(task) = ready.pop_front() {
+                    let task_name = schedule.name(task).to_string();
+                    let task_definition = schedule.definition(task);
+                    if let Some(cache_key) = cached.get(&task_name) {
+                        self.record_cached_task(
+                            &task_name,
+                            &task_definition,
+                            cache_key,
+                            &observers,
+                        );
+                        ready.extend(schedule.finish(task));
+                        continue;
+                    }
+
+                    // Cross-package tasks run in their package
+                    let working_dir = self.task_working_dir(&task_name);
+
+                    super::task::spawn_task_execution(
+                        super::task::TaskExecutionParams {
+                            owners: self.task_owners(&task_name),
+                            task_name,
+                            task_definition,
+                            working_dir,
+                            task_args: args.to_vec(),
+                            failed_tasks: Arc::clone(&failed_tasks),
+                            action_cache: Arc::clone(&self.action_cache),
+                            cache_config: Arc::clone(&self.cache_config),
+                            executed_tasks: Arc::clone(&self.executed_tasks),
+                            run_recorder: Arc::clone(&self.run_recorder),
+                            job_slots: self.job_slots.clone(),
+                            cancellation: self.cancellation.clone(),
+                            observers: observers.clone(),
+                            task_logs: Arc::clone(&task_logs),
+                            env: self.task_env.clone(),
+                            audit_mode,
+                            capture_output,
+                        },
+                        super::task::TaskDone::new(task, done_sender.clone()),
+                    );
+                    running += 1;
+                }
             }
 
-            drop(level_guard);
+            // Wait for the running tasks, also after a failure
+            if running == 0 {
+                break;
+            }
+            let Some((task, exit_code)) = done.recv().await else {
+                break;
+            };
+            running -= 1;
+            match exit_code {
+                Some(_) => ready.extend(schedule.finish(task)),
+                None => {
+                    panicked.get_or_insert_with(|| schedule.name(task).to_string());
+                }
+            }
         }
 
         drop(pipeline_guard);
+        if let Some(task_name) = panicked {
+            return Err(Error::configuration(format!(
+                "Task execution failed: task '{task_name}' panicked"
+            )));
+        }
+        // Tasks killed by a cancellation did not fail
+        if self.cancellation.is_cancelled() {
+            tracing::warn!("Task execution pipeline cancelled");
+            return Ok(130);
+        }
+        let failed = failed_tasks
+            .lock()
+            .map_err(|e| Error::configuration(format!("Failed to acquire lock: {e}")))?;
+        if !failed.is_empty() {
+            return Err(Error::configuration(failure_report(&failed)));
+        }
         tracing::info!("Task execution pipeline completed successfully");
         Ok(0)
     }
+
+    /// The tasks of `plan` whose results are in the cache along with the
+    /// results of all their required dependencies, so that none of them has
+    /// to run, with their cache keys
+    ///
+    /// Order-only and weak dependencies only order the tasks, and do not
+    /// keep them from being taken from the cache.
+    ///
+    /// Levels are looked up in order, so a task is only looked up once all
+    /// its dependencies were found. Nothing is satisfied for a run with
+    /// arguments, which are not part of the cache keys.
+    async fn cached_tasks(
+        &self,
+        plan: &TaskExecutionPlan,
+        args: &[String],
+    ) -> HashMap<String, String> {
+        let mut cached = HashMap::new();
+        if !args.is_empty() || !self.cache_config.global.mode.is_readable() {
+            return cached;
+        }
+
+        let env_vars = self.task_env();
+        for task_name in plan.levels.iter().flatten() {
+            let Some(definition) = plan.tasks.get(task_name) else {
+                continue;
+            };
+            if !cache::cache_enabled(&self.cache_config, definition)
+                || !definition
+                    .dependency_names()
+                    .iter()
+                    .all(|dependency| cached.contains_key(dependency))
+            {
+                continue;
+            }
+
+            let digest = match self
+                .action_cache
+                .compute_digest(
+                    task_name,
+                    definition,
+                    &self.task_working_dir(task_name),
+                    env_vars.clone(),
+                )
+                .await
+            {
+                Ok(digest) => digest,
+                Err(e) => {
+                    tracing::debug!(task = %task_name, "Failed to compute the cache key: {e}");
+                    continue;
+                }
+            };
+            if let Some(result) = self.action_cache.get_cached_result(&digest).await {
+                if result.exit_code == 0 {
+                    cached.insert(task_name.clone(), digest.hash);
+                }
+            }
+        }
+        cached
+    }
+
+    /// Report `task_name` as finished from the cache, without running it
+    fn record_cached_task(
+        &self,
+        task_name: &str,
+        task_definition: &TaskDefinition,
+        cache_key: &str,
+        observers: &Observers,
+    ) {
+        let owners = self.task_owners(task_name);
+        let now = Instant::now();
+        observers.task_started(task_name);
+        observers.cache_hit(task_name);
+        if let Ok(mut recorder) = self.run_recorder.lock() {
+            recorder.record(TaskRecord {
+                name: task_name.to_string(),
+                dependencies: task_definition.dependency_names(),
+                started_at: now,
+                finished_at: now,
+                cache: CacheStatus::Cached,
+                exit_code: Some(0),
+                owners: owners.clone(),
+                cache_key: Some(cache_key.to_string()),
+            });
+        }
+        if let Ok(mut executed) = self.executed_tasks.lock() {
+            executed.insert(task_name.to_string());
+        }
+        observers.task_finished(
+            task_name,
+            &TaskOutcome {
+                exit_code: Some(0),
+                error: None,
+                duration: Duration::ZERO,
+                cache: CacheStatus::Cached,
+                owners,
+                excerpt: FailureExcerpt::default(),
+            },
+        );
+    }
 }
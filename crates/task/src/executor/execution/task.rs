@@ -1,59 +1,137 @@
 use crate::executor::cache;
 use crate::executor::context::TaskExecutionContext;
+use crate::executor::graph::TaskRef;
+use crate::executor::observer::{Observers, TaskOutcome};
+use crate::executor::summary::{CacheStatus, RunRecorder, TaskRecord};
+use crate::executor::task_log::{FailedTask, FailureExcerpt, TaskLogs};
 use cuenv_cache::concurrent::action::ActionCache;
 use cuenv_cache::config::CacheConfiguration;
-use cuenv_core::TaskDefinition;
-use cuenv_env::manager::EnvManager;
-use std::collections::HashSet;
+use cuenv_env::daemon::{self, Request};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use tokio::task::JoinSet;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_util::sync::CancellationToken;
 use tracing::Instrument;
 
 /// Parameters for task execution
 pub struct TaskExecutionParams {
     pub task_name: String,
-    pub task_definition: TaskDefinition,
+    /// Teams or people to ask about the task when it fails
+    pub owners: Vec<String>,
+    pub task_definition: TaskRef,
     pub working_dir: PathBuf,
     pub task_args: Vec<String>,
-    pub failed_tasks: Arc<Mutex<Vec<(String, i32)>>>,
+    pub failed_tasks: Arc<Mutex<Vec<FailedTask>>>,
     pub action_cache: Arc<ActionCache>,
-    pub _env_manager: EnvManager,
-    pub cache_config: CacheConfiguration,
+    pub cache_config: Arc<CacheConfiguration>,
     pub executed_tasks: Arc<Mutex<HashSet<String>>>,
+    pub(crate) run_recorder: Arc<Mutex<RunRecorder>>,
+    pub(crate) job_slots: Option<Arc<Semaphore>>,
+    pub(crate) observers: Observers,
+    /// Logs the output of the task, for the report of its failure
+    pub(crate) task_logs: Arc<TaskLogs>,
+    pub(crate) cancellation: CancellationToken,
+    /// The environment the task runs in, instead of that of this process
+    pub(crate) env: Option<Arc<HashMap<String, String>>>,
     pub audit_mode: bool,
     pub capture_output: bool,
 }
 
-/// Spawn a task execution
-pub fn spawn_task_execution(join_set: &mut JoinSet<i32>, params: TaskExecutionParams) {
+/// Tells the scheduler that a task finished with its exit code, or without
+/// one when it panicked and was dropped before finishing
+pub struct TaskDone {
+    task: usize,
+    sender: Option<mpsc::UnboundedSender<(usize, Option<i32>)>>,
+}
+
+impl TaskDone {
+    pub fn new(task: usize, sender: mpsc::UnboundedSender<(usize, Option<i32>)>) -> Self {
+        Self {
+            task,
+            sender: Some(sender),
+        }
+    }
+
+    fn finish(mut self, exit_code: i32) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send((self.task, Some(exit_code)));
+        }
+    }
+}
+
+impl Drop for TaskDone {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send((self.task, None));
+        }
+    }
+}
+
+/// Spawn a task execution on the runtime, reporting to `done` once it
+/// finished
+pub fn spawn_task_execution(params: TaskExecutionParams, done: TaskDone) {
     // Create task span
     // TODO: Add tracing when moved to workspace
-    let task_span = tracing::info_span!("task", name = params.task_name.as_str());
+    let task_span = tracing::info_span!(
+        "task",
+        name = params.task_name.as_str(),
+        owners = params.owners.join(",").as_str()
+    );
 
-    join_set.spawn(async move { execute_single_task_async(params).await }.instrument(task_span));
+    tokio::spawn(
+        async move {
+            let exit_code = execute_single_task_async(params).await;
+            done.finish(exit_code);
+        }
+        .instrument(task_span),
+    );
 }
 
 async fn execute_single_task_async(params: TaskExecutionParams) -> i32 {
     let TaskExecutionParams {
         task_name,
+        owners,
         task_definition,
         working_dir,
         task_args,
         failed_tasks,
         action_cache,
-        _env_manager: _,
         cache_config,
         executed_tasks,
+        run_recorder,
+        job_slots,
+        observers,
+        task_logs,
+        cancellation,
+        env,
         audit_mode,
         capture_output,
     } = params;
 
+    // Wait for a free slot when the user limits how many tasks run at once
+    let _slot = match job_slots {
+        Some(slots) => tokio::select! {
+            slot = slots.acquire_owned() => slot.ok(),
+            _ = cancellation.cancelled() => None,
+        },
+        None => None,
+    };
+    // A task still waiting when the run is cancelled never starts
+    if cancellation.is_cancelled() {
+        return 130;
+    }
+
     let start_time = Instant::now();
 
     // Publish task started event
+    observers.task_started(&task_name);
     publish_task_started(&task_name).await;
+    report_to_daemon(Request::TaskStarted {
+        task: task_name.clone(),
+    })
+    .await;
 
     // Disabled: Detailed task configuration events (not essential for now)
     // if false {
@@ -66,15 +144,70 @@ async fn execute_single_task_async(params: TaskExecutionParams) -> i32 {
         action_cache: &action_cache,
         audit_mode,
         capture_output,
+        observers: &observers,
+        cancellation: &cancellation,
+        env: env.as_deref(),
+    };
+
+    let result =
+        cache::execute_single_task_with_cache(&ctx, &task_name, &task_definition, &task_args).await;
+
+    let (exit_code, cache, cache_key) = match &result {
+        Ok((status, cache, cache_key)) => (Some(*status), *cache, cache_key.clone()),
+        Err(_) => (None, CacheStatus::Disabled, None),
     };
+    if cache == CacheStatus::Hit {
+        observers.cache_hit(&task_name);
+    }
+    report_to_daemon(Request::TaskFinished {
+        task: task_name.clone(),
+        duration_ms: start_time.elapsed().as_millis() as u64,
+        success: exit_code == Some(0),
+    })
+    .await;
+    if let Ok(mut recorder) = run_recorder.lock() {
+        recorder.record(TaskRecord {
+            name: task_name.clone(),
+            dependencies: task_definition
+                .dependencies
+                .iter()
+                .map(|dependency| dependency.name.clone())
+                .collect(),
+            started_at: start_time,
+            finished_at: Instant::now(),
+            cache,
+            exit_code,
+            owners: owners.clone(),
+            cache_key,
+        });
+    }
 
-    match cache::execute_single_task_with_cache(&ctx, &task_name, &task_definition, &task_args)
-        .await
-    {
-        Ok(status) => {
-            handle_task_success(status, &task_name, start_time, failed_tasks, executed_tasks).await
+    let excerpt = task_logs.finish(&task_name, exit_code == Some(0));
+    observers.task_finished(
+        &task_name,
+        &TaskOutcome {
+            exit_code,
+            error: result.as_ref().err().map(ToString::to_string),
+            duration: start_time.elapsed(),
+            cache,
+            owners,
+            excerpt: excerpt.clone(),
+        },
+    );
+
+    match result {
+        Ok((status, _, _)) => {
+            handle_task_success(
+                status,
+                &task_name,
+                start_time,
+                excerpt,
+                failed_tasks,
+                executed_tasks,
+            )
+            .await
         }
-        Err(e) => handle_task_error(e, &task_name, start_time, failed_tasks).await,
+        Err(e) => handle_task_error(e, &task_name, start_time, excerpt, failed_tasks).await,
     }
 }
 
@@ -94,14 +227,19 @@ async fn handle_task_success(
     status: i32,
     task_name: &str,
     start_time: Instant,
-    failed_tasks: Arc<Mutex<Vec<(String, i32)>>>,
+    excerpt: FailureExcerpt,
+    failed_tasks: Arc<Mutex<Vec<FailedTask>>>,
     executed_tasks: Arc<Mutex<HashSet<String>>>,
 ) -> i32 {
     let duration_ms = start_time.elapsed().as_millis() as u64;
 
     if status != 0 {
         if let Ok(mut guard) = failed_tasks.lock() {
-            guard.push((task_name.to_string(), status));
+            guard.push(FailedTask {
+                name: task_name.to_string(),
+                exit_code: status,
+                excerpt,
+            });
         } else {
             tracing::error!("Failed to acquire lock for failed tasks tracking");
         }
@@ -134,12 +272,6 @@ async fn handle_task_success(
                 },
             ))
             .await;
-
-        tracing::info!(
-            task = task_name,
-            duration_ms = duration_ms,
-            "Task completed"
-        );
     }
 
     status
@@ -149,12 +281,17 @@ async fn handle_task_error(
     e: cuenv_core::Error,
     task_name: &str,
     start_time: Instant,
-    failed_tasks: Arc<Mutex<Vec<(String, i32)>>>,
+    excerpt: FailureExcerpt,
+    failed_tasks: Arc<Mutex<Vec<FailedTask>>>,
 ) -> i32 {
     let _duration_ms = start_time.elapsed().as_millis() as u64;
 
     if let Ok(mut guard) = failed_tasks.lock() {
-        guard.push((task_name.to_string(), -1));
+        guard.push(FailedTask {
+            name: task_name.to_string(),
+            exit_code: -1,
+            excerpt,
+        });
     } else {
         tracing::error!("Failed to acquire lock for failed tasks tracking");
     }
@@ -171,11 +308,11 @@ async fn handle_task_error(
         ))
         .await;
 
-    tracing::error!(
-        task_name = %task_name,
-        error = %e,
-        "Task execution failed"
-    );
-
     -1
 }
+
+/// Count the task in the metrics of a running daemon, without blocking the
+/// runtime on its socket
+async fn report_to_daemon(request: Request) {
+    let _ = tokio::task::spawn_blocking(move || daemon::report_task(&request)).await;
+}
@@ -0,0 +1,214 @@
+//! A task as it is resolved for running
+//!
+//! What runs can differ from what env.cue appears to say: CUE unifies a task
+//! with everything it embeds, `${VAR}` and output references are expanded,
+//! the variables a task gets depend on the capabilities of its command, and
+//! globs match whatever files exist. `cuenv task explain` shows each of these
+//! next to the cache key the task would be cached under.
+
+use super::TaskExecutor;
+use cuenv_cache::config::CacheConfigResolver;
+use cuenv_cache::expand_glob_pattern;
+use cuenv_config::TaskConfig;
+use cuenv_core::{DependencyKind, Error, Result, TaskExecutionMode};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A glob with the files it matches, relative to the task's working
+/// directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExpandedGlob {
+    pub pattern: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// What the cache key of a task is computed from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheKeyComponents {
+    /// Whether results of the task are cached
+    pub enabled: bool,
+    /// The `cacheKey` of the task, when it sets one
+    pub custom_key: Option<String>,
+    /// The key, changing whenever any of the components below does
+    pub digest: String,
+    /// Hash of the resolved task
+    pub config_hash: String,
+    /// Variables the key depends on
+    pub env: BTreeMap<String, String>,
+    /// Hashes of the input files
+    pub input_files: BTreeMap<String, String>,
+}
+
+/// A task resolved as it would run
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskExplanation {
+    pub name: String,
+    pub description: Option<String>,
+    /// The task as CUE evaluated it, merged with what it embeds
+    pub config: TaskConfig,
+    pub shell: String,
+    /// Arguments of the shell, the last taking the command or script
+    pub shell_args: Vec<String>,
+    /// The command or script that runs, with references expanded
+    pub command: Option<String>,
+    pub script: Option<String>,
+    pub working_directory: PathBuf,
+    pub timeout_secs: u64,
+    /// Variables of env.cue the task gets for the capabilities of its command
+    pub env: BTreeMap<String, String>,
+    pub inputs: Vec<ExpandedGlob>,
+    pub outputs: Vec<ExpandedGlob>,
+    /// Named outputs, resolved against the working directory
+    pub artifacts: BTreeMap<String, PathBuf>,
+    pub cache: CacheKeyComponents,
+    /// The tasks the task depends on directly, order-only and weak ones
+    /// marked as such
+    pub dependencies: Vec<String>,
+    /// Every task that runs before it, in the order they run
+    pub dependency_closure: Vec<String>,
+}
+
+impl TaskExecutor {
+    /// Resolve `task_name` as it would run, without running anything
+    pub async fn explain_task(&self, task_name: &str) -> Result<TaskExplanation> {
+        let config = self
+            .env_manager
+            .get_tasks()
+            .get(task_name)
+            .cloned()
+            .ok_or_else(|| Error::configuration(format!("Task '{task_name}' not found")))?;
+        let plan = self.build_execution_plan(&[task_name.to_string()])?;
+        let definition = plan.tasks.get(task_name).ok_or_else(|| {
+            Error::configuration(format!("Task '{task_name}' not found in execution plan"))
+        })?;
+
+        let digest = self
+            .action_cache
+            .compute_digest(task_name, definition, &self.working_dir, self.task_env())
+            .await?;
+
+        let (command, script) = match &definition.execution_mode {
+            TaskExecutionMode::Command { command } => (Some(command.clone()), None),
+            TaskExecutionMode::Script { content } => (None, Some(content.clone())),
+        };
+        let dir = &definition.working_directory;
+
+        Ok(TaskExplanation {
+            name: task_name.to_string(),
+            description: definition.description.clone(),
+            shell: definition.shell.clone(),
+            shell_args: definition.shell_args.clone(),
+            command,
+            script,
+            working_directory: dir.clone(),
+            timeout_secs: definition.timeout.as_secs(),
+            env: self.get_task_env_vars(task_name).into_iter().collect(),
+            inputs: expand_globs(&definition.inputs, dir)?,
+            outputs: expand_globs(&definition.outputs, dir)?,
+            artifacts: definition.artifacts.clone().into_iter().collect(),
+            cache: CacheKeyComponents {
+                enabled: CacheConfigResolver::should_cache_task(
+                    &self.cache_config.global,
+                    config.cache.as_ref(),
+                    task_name,
+                ),
+                custom_key: definition.cache.key.clone(),
+                digest: digest.hash,
+                config_hash: digest.components.config_hash,
+                env: digest.components.env_vars.into_iter().collect(),
+                input_files: digest.components.input_files.into_iter().collect(),
+            },
+            dependencies: definition
+                .dependencies
+                .iter()
+                .map(|dependency| match dependency.kind {
+                    DependencyKind::Required => dependency.qualified_name.clone(),
+                    DependencyKind::OrderOnly => {
+                        format!("{} (order-only)", dependency.qualified_name)
+                    }
+                    DependencyKind::Weak => format!("{} (weak)", dependency.qualified_name),
+                })
+                .collect(),
+            dependency_closure: dependency_closure(&plan.levels, task_name),
+            config,
+        })
+    }
+}
+
+/// The tasks of the levels of a plan other than `task_name`, in order
+fn dependency_closure(levels: &[Vec<String>], task_name: &str) -> Vec<String> {
+    levels
+        .iter()
+        .flat_map(|level| {
+            // Tasks of a level run in any order, so list them by name
+            let mut level: Vec<&String> = level.iter().collect();
+            level.sort();
+            level
+        })
+        .filter(|name| *name != task_name)
+        .cloned()
+        .collect()
+}
+
+/// Each of `patterns` with the files it matches below `dir`
+fn expand_globs(patterns: &[String], dir: &Path) -> Result<Vec<ExpandedGlob>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let mut files: Vec<PathBuf> = expand_glob_pattern(pattern, dir)?
+                .into_iter()
+                .map(|file| {
+                    file.strip_prefix(dir)
+                        .map(Path::to_path_buf)
+                        .unwrap_or(file)
+                })
+                .collect();
+            files.sort();
+            Ok(ExpandedGlob {
+                pattern: pattern.clone(),
+                files,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_dependency_closure() {
+        let levels = vec![
+            vec!["lint".to_string(), "fetch".to_string()],
+            vec!["build".to_string()],
+            vec!["test".to_string()],
+        ];
+        assert_eq!(
+            dependency_closure(&levels, "test"),
+            vec!["fetch", "lint", "build"]
+        );
+    }
+
+    #[test]
+    fn test_expand_globs() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+
+        let globs = expand_globs(
+            &["src/*.rs".to_string(), "missing.txt".to_string()],
+            dir.path(),
+        )
+        .unwrap();
+        assert_eq!(
+            globs[0].files,
+            vec![PathBuf::from("src/lib.rs"), PathBuf::from("src/main.rs")]
+        );
+        assert!(globs[1].files.is_empty());
+    }
+}
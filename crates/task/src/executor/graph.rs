@@ -1,69 +1,413 @@
-use cuenv_core::{Error, Result};
-use std::collections::{HashMap, VecDeque};
+use crate::executor::TaskExecutionPlan;
+use cuenv_core::{Error, Result, TaskDefinition};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A dependency graph with its tasks numbered
+///
+/// Sorting compares and copies indices rather than names, so graphs of tens
+/// of thousands of tasks sort in a few milliseconds and each name is only
+/// copied once, into the levels returned.
+pub struct IndexedGraph<'a> {
+    names: Vec<&'a str>,
+    /// The tasks depending on each task
+    dependents: Vec<Vec<usize>>,
+    /// How many dependencies of each task have not run yet
+    in_degree: Vec<usize>,
+}
+
+impl<'a> IndexedGraph<'a> {
+    /// Number the tasks of `dependencies`, and the tasks they depend on
+    pub fn new(dependencies: &'a HashMap<String, Vec<String>>) -> Self {
+        let mut graph = Self {
+            names: Vec::with_capacity(dependencies.len()),
+            dependents: Vec::with_capacity(dependencies.len()),
+            in_degree: Vec::with_capacity(dependencies.len()),
+        };
+        let mut indices: HashMap<&'a str, usize> = HashMap::with_capacity(dependencies.len());
+        for task in dependencies.keys() {
+            graph.index(&mut indices, task);
+        }
+        // The tasks were numbered in the order the map iterates them in
+        for (task, deps) in dependencies.values().enumerate() {
+            for dep in deps {
+                let dep = graph.index(&mut indices, dep);
+                graph.dependents[dep].push(task);
+                graph.in_degree[task] += 1;
+            }
+        }
+        graph
+    }
+
+    fn index(&mut self, indices: &mut HashMap<&'a str, usize>, name: &'a str) -> usize {
+        *indices.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.dependents.push(Vec::new());
+            self.in_degree.push(0);
+            self.names.len() - 1
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn name(&self, index: usize) -> &'a str {
+        self.names[index]
+    }
+
+    /// The tasks by level, each level sorted by name: the tasks of a level
+    /// only depend on tasks of the levels before it
+    pub fn levels(&self) -> Result<Vec<Vec<usize>>> {
+        let mut in_degree = self.in_degree.clone();
+        let mut level: Vec<usize> = (0..self.len()).filter(|&i| in_degree[i] == 0).collect();
+        let mut levels = Vec::new();
+        let mut processed = 0;
+
+        while !level.is_empty() {
+            level.sort_unstable_by_key(|&index| self.names[index]);
+            let mut next = Vec::new();
+            for &task in &level {
+                for &dependent in &self.dependents[task] {
+                    in_degree[dependent] -= 1;
+                    if in_degree[dependent] == 0 {
+                        next.push(dependent);
+                    }
+                }
+            }
+            processed += level.len();
+            levels.push(std::mem::replace(&mut level, next));
+        }
+
+        // Tasks left waiting depend on each other
+        if processed != self.len() {
+            let mut cycle: Vec<&str> = (0..self.len())
+                .filter(|&i| in_degree[i] > 0)
+                .map(|i| self.names[i])
+                .collect();
+            cycle.sort_unstable();
+            return Err(Error::configuration(format!(
+                "Circular dependency detected in task graph between: {}",
+                cycle.join(", ")
+            )));
+        }
+
+        Ok(levels)
+    }
+}
 
 /// Perform topological sort to determine execution levels
 pub fn topological_sort(dependencies: &HashMap<String, Vec<String>>) -> Result<Vec<Vec<String>>> {
-    let mut in_degree = HashMap::with_capacity(dependencies.len());
-    let mut graph = HashMap::with_capacity(dependencies.len());
-
-    // Initialize in-degree count and adjacency list
-    for (task, deps) in dependencies {
-        in_degree.entry(task.clone()).or_insert(0);
-        graph.entry(task.clone()).or_insert_with(Vec::new);
-
-        for dep in deps {
-            *in_degree.entry(dep.clone()).or_insert(0) += 0; // Ensure dep is in map
-            graph
-                .entry(dep.clone())
-                .or_insert_with(Vec::new)
-                .push(task.clone());
-            if let Some(degree) = in_degree.get_mut(task) {
-                *degree += 1;
-            } else {
-                return Err(Error::configuration(format!(
-                    "Task '{task}' not found in in-degree map"
-                )));
+    let graph = IndexedGraph::new(dependencies);
+    Ok(graph
+        .levels()?
+        .into_iter()
+        .map(|level| {
+            level
+                .into_iter()
+                .map(|index| graph.name(index).to_string())
+                .collect()
+        })
+        .collect())
+}
+
+/// The tasks of a plan as they become ready to run
+///
+/// A task is ready as soon as the tasks it depends on finished, rather than
+/// once the whole level before it did. The definitions move once into an
+/// arena that the running tasks share, instead of each task copying its own.
+pub struct Schedule {
+    names: Vec<String>,
+    definitions: Arc<[TaskDefinition]>,
+    /// The tasks depending on each task
+    dependents: Vec<Vec<usize>>,
+    /// How many dependencies of each task have not finished yet
+    waiting: Vec<usize>,
+    /// The levels a plan without its dependencies waits for instead
+    levels: Option<Levels>,
+}
+
+/// The levels of a plan, each waiting for the whole level before it
+struct Levels {
+    tasks: Vec<Vec<usize>>,
+    /// The level of each task
+    level_of: Vec<usize>,
+    /// How many tasks of each level have not finished yet
+    left: Vec<usize>,
+}
+
+impl Schedule {
+    /// Number the tasks of `plan` in the order of its levels
+    pub fn new(plan: &TaskExecutionPlan) -> Result<Self> {
+        let count = plan.tasks.len();
+        let mut names = Vec::with_capacity(count);
+        let mut definitions = Vec::with_capacity(count);
+        let mut level_tasks = Vec::with_capacity(plan.levels.len());
+        let mut indices: HashMap<&str, usize> = HashMap::with_capacity(count);
+        for level in &plan.levels {
+            let mut tasks = Vec::with_capacity(level.len());
+            for task_name in level {
+                let definition = plan.tasks.get(task_name).ok_or_else(|| {
+                    Error::configuration(format!("Task '{task_name}' not found in execution plan"))
+                })?;
+                indices.insert(task_name, names.len());
+                tasks.push(names.len());
+                names.push(task_name.clone());
+                definitions.push(definition.clone());
+            }
+            level_tasks.push(tasks);
+        }
+
+        let mut schedule = Self {
+            dependents: vec![Vec::new(); names.len()],
+            waiting: vec![0; names.len()],
+            levels: None,
+            names,
+            definitions: definitions.into(),
+        };
+
+        if plan.dependencies.is_empty() {
+            let mut level_of = vec![0; schedule.len()];
+            for (level, tasks) in level_tasks.iter().enumerate() {
+                for &task in tasks {
+                    level_of[task] = level;
+                    schedule.waiting[task] = usize::from(level > 0);
+                }
+            }
+            schedule.levels = Some(Levels {
+                left: level_tasks.iter().map(Vec::len).collect(),
+                tasks: level_tasks,
+                level_of,
+            });
+            return Ok(schedule);
+        }
+
+        for (task_name, dependencies) in &plan.dependencies {
+            let Some(&task) = indices.get(task_name.as_str()) else {
+                continue;
+            };
+            for dependency in dependencies {
+                let dependency = *indices.get(dependency.as_str()).ok_or_else(|| {
+                    Error::configuration(format!(
+                        "Execution plan runs '{task_name}' without running '{dependency}'"
+                    ))
+                })?;
+                schedule.dependents[dependency].push(task);
+                schedule.waiting[task] += 1;
             }
         }
+        Ok(schedule)
     }
 
-    let mut levels = Vec::with_capacity(dependencies.len() / 2); // Estimate
-    let mut queue: VecDeque<String> = in_degree
-        .iter()
-        .filter(|(_, &degree)| degree == 0)
-        .map(|(task, _)| task.clone())
-        .collect();
+    fn len(&self) -> usize {
+        self.names.len()
+    }
 
-    while !queue.is_empty() {
-        let current_level: Vec<String> = queue.drain(..).collect();
+    pub fn name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
 
-        if current_level.is_empty() {
-            break;
+    /// The definition of a task, to hand to the task running it
+    pub fn definition(&self, index: usize) -> TaskRef {
+        TaskRef {
+            definitions: Arc::clone(&self.definitions),
+            index,
         }
+    }
 
-        for task in &current_level {
-            if let Some(dependents) = graph.get(task) {
-                for dependent in dependents {
-                    if let Some(degree) = in_degree.get_mut(dependent) {
-                        *degree -= 1;
-                        if *degree == 0 {
-                            queue.push_back(dependent.clone());
+    /// The tasks that depend on nothing, sorted by name
+    pub fn ready(&self) -> Vec<usize> {
+        let mut ready: Vec<usize> = (0..self.len()).filter(|&i| self.waiting[i] == 0).collect();
+        ready.sort_unstable_by_key(|&index| &self.names[index]);
+        ready
+    }
+
+    /// Note that a task finished, and return the tasks this made ready,
+    /// sorted by name
+    pub fn finish(&mut self, index: usize) -> Vec<usize> {
+        let mut ready = Vec::new();
+        match &mut self.levels {
+            Some(levels) => {
+                let level = levels.level_of[index];
+                levels.left[level] -= 1;
+                if levels.left[level] == 0 {
+                    if let Some(next) = levels.tasks.get(level + 1) {
+                        for &task in next {
+                            self.waiting[task] -= 1;
+                            ready.push(task);
                         }
                     }
                 }
             }
+            None => {
+                for &dependent in &self.dependents[index] {
+                    self.waiting[dependent] -= 1;
+                    if self.waiting[dependent] == 0 {
+                        ready.push(dependent);
+                    }
+                }
+            }
+        }
+        ready.sort_unstable_by_key(|&index| &self.names[index]);
+        ready
+    }
+}
+
+/// The definition of a task of a [`Schedule`], shared with the other tasks
+#[derive(Clone)]
+pub struct TaskRef {
+    definitions: Arc<[TaskDefinition]>,
+    index: usize,
+}
+
+impl Deref for TaskRef {
+    type Target = TaskDefinition;
+
+    fn deref(&self) -> &TaskDefinition {
+        &self.definitions[self.index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_core::TaskExecutionMode;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    fn graph(edges: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        edges
+            .iter()
+            .map(|(task, deps)| {
+                (
+                    task.to_string(),
+                    deps.iter().map(|dep| dep.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_levels_are_sorted() {
+        let levels = topological_sort(&graph(&[
+            ("test", &["build", "lint"]),
+            ("lint", &[]),
+            ("build", &["codegen"]),
+            ("codegen", &[]),
+            ("docs", &[]),
+        ]))
+        .unwrap();
+        assert_eq!(
+            levels,
+            [vec!["codegen", "docs", "lint"], vec!["build"], vec!["test"]]
+        );
+    }
+
+    #[test]
+    fn test_cycle_names_its_tasks() {
+        let error = topological_sort(&graph(&[
+            ("a", &["b"]),
+            ("b", &["c"]),
+            ("c", &["a"]),
+            ("d", &[]),
+        ]))
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Circular dependency detected in task graph between: a, b, c"));
+    }
+
+    #[test]
+    fn test_large_graph() {
+        // A matrix of 100 packages with 200 tasks each, every task
+        // depending on the one before it and on the same task of the
+        // previous package
+        let mut dependencies = HashMap::new();
+        for package in 0..100 {
+            for task in 0..200 {
+                let mut deps = Vec::new();
+                if task > 0 {
+                    deps.push(format!("p{package}:t{}", task - 1));
+                }
+                if package > 0 {
+                    deps.push(format!("p{}:t{task}", package - 1));
+                }
+                dependencies.insert(format!("p{package}:t{task}"), deps);
+            }
         }
 
-        levels.push(current_level);
+        let started = Instant::now();
+        let levels = topological_sort(&dependencies).unwrap();
+        // Planning must not hold up running tasks, even in debug builds
+        assert!(
+            started.elapsed().as_millis() < 100,
+            "{:?}",
+            started.elapsed()
+        );
+        assert_eq!(levels.len(), 299);
+        assert_eq!(levels[0], ["p0:t0"]);
+        assert_eq!(levels.iter().map(Vec::len).sum::<usize>(), 20_000);
     }
 
-    // Check for remaining tasks (would indicate circular dependencies)
-    let processed_count: usize = levels.iter().map(|level| level.len()).sum();
-    if processed_count != dependencies.len() {
-        return Err(Error::configuration(
-            "Circular dependency detected in task graph".to_string(),
-        ));
+    fn plan(edges: &[(&str, &[&str])]) -> TaskExecutionPlan {
+        let dependencies = graph(edges);
+        TaskExecutionPlan {
+            levels: topological_sort(&dependencies).unwrap(),
+            tasks: dependencies
+                .keys()
+                .map(|name| {
+                    let mode = TaskExecutionMode::Command {
+                        command: format!("make {name}"),
+                    };
+                    let task = TaskDefinition::new(name.clone(), mode, PathBuf::from("/repo"));
+                    (name.clone(), task)
+                })
+                .collect(),
+            dependencies,
+            cache_keys: Default::default(),
+        }
     }
 
-    Ok(levels)
+    fn names(schedule: &Schedule, tasks: Vec<usize>) -> Vec<&str> {
+        tasks.into_iter().map(|task| schedule.name(task)).collect()
+    }
+
+    #[test]
+    fn test_schedule_starts_tasks_once_their_dependencies_finished() {
+        // `docs` only waits for `codegen`, not for the slower `lint`
+        let mut schedule = Schedule::new(&plan(&[
+            ("codegen", &[]),
+            ("lint", &[]),
+            ("docs", &["codegen"]),
+            ("test", &["docs", "lint"]),
+        ]))
+        .unwrap();
+        let ready = schedule.ready();
+        assert_eq!(names(&schedule, ready.clone()), ["codegen", "lint"]);
+
+        let docs = schedule.finish(ready[0]);
+        assert_eq!(names(&schedule, docs.clone()), ["docs"]);
+        assert_eq!(schedule.definition(docs[0]).name, "docs");
+        assert!(schedule.finish(docs[0]).is_empty());
+        let test = schedule.finish(ready[1]);
+        assert_eq!(names(&schedule, test), ["test"]);
+    }
+
+    #[test]
+    fn test_schedule_without_dependencies_runs_by_level() {
+        let mut plan = plan(&[("codegen", &[]), ("lint", &[]), ("docs", &["codegen"])]);
+        plan.dependencies.clear();
+        let mut schedule = Schedule::new(&plan).unwrap();
+        let ready = schedule.ready();
+        assert_eq!(names(&schedule, ready.clone()), ["codegen", "lint"]);
+
+        assert!(schedule.finish(ready[0]).is_empty());
+        let docs = schedule.finish(ready[1]);
+        assert_eq!(names(&schedule, docs), ["docs"]);
+    }
 }
@@ -0,0 +1,114 @@
+//! What is listed about each task
+//!
+//! `cuenv task list` and tools reading its JSON need more than a name and a
+//! description: the tags a task is selected by, who owns it, the tasks it
+//! depends on and whether its results are cached.
+
+use super::TaskExecutor;
+use cuenv_cache::config::{CacheConfigResolver, GlobalCacheConfig};
+use cuenv_config::TaskConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A task as it is listed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskInfo {
+    /// The full name, with the groups of the task separated by dots
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// Teams or people to ask about the task
+    pub owners: Vec<String>,
+    pub dependencies: Vec<String>,
+    /// Whether results of the task are cached
+    pub cached: bool,
+}
+
+impl TaskInfo {
+    pub fn new(name: &str, config: &TaskConfig, cache: &GlobalCacheConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            description: config.description.clone(),
+            tags: config.tags.clone().unwrap_or_default(),
+            owners: config.owners.clone().unwrap_or_default(),
+            dependencies: config.dependencies.clone().unwrap_or_default(),
+            cached: CacheConfigResolver::should_cache_task(cache, config.cache.as_ref(), name),
+        }
+    }
+
+    /// The tasks of `tasks`, sorted by name
+    pub fn from_tasks(tasks: &HashMap<String, TaskConfig>, cache: &GlobalCacheConfig) -> Vec<Self> {
+        let mut infos: Vec<Self> = tasks
+            .iter()
+            .map(|(name, config)| Self::new(name, config, cache))
+            .collect();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+}
+
+impl TaskExecutor {
+    /// The tasks this executor can run, sorted by name
+    pub fn task_info(&self) -> Vec<TaskInfo> {
+        TaskInfo::from_tasks(self.env_manager.get_tasks(), &self.cache_config.global)
+    }
+
+    /// The owners of `task_name`, of its package in a monorepo
+    pub(crate) fn task_owners(&self, task_name: &str) -> Vec<String> {
+        let owners = match &self.monorepo_registry {
+            Some(registry) => registry
+                .get_task(task_name)
+                .and_then(|task| task.config.owners.clone()),
+            None => self
+                .env_manager
+                .get_tasks()
+                .get(task_name)
+                .and_then(|config| config.owners.clone()),
+        };
+        owners.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_config::TaskCacheConfig;
+
+    #[test]
+    fn test_from_tasks() {
+        let tasks = HashMap::from([
+            (
+                "test".to_string(),
+                TaskConfig {
+                    description: Some("Run the tests".to_string()),
+                    dependencies: Some(vec!["build".to_string()]),
+                    tags: Some(vec!["ci".to_string()]),
+                    owners: Some(vec!["team-platform".to_string()]),
+                    ..Default::default()
+                },
+            ),
+            (
+                "build".to_string(),
+                TaskConfig {
+                    cache: Some(TaskCacheConfig::Simple(false)),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let infos = TaskInfo::from_tasks(&tasks, &GlobalCacheConfig::default());
+        assert_eq!(infos[0].name, "build");
+        assert!(!infos[0].cached);
+        assert_eq!(
+            infos[1],
+            TaskInfo {
+                name: "test".to_string(),
+                description: Some("Run the tests".to_string()),
+                tags: vec!["ci".to_string()],
+                owners: vec!["team-platform".to_string()],
+                dependencies: vec!["build".to_string()],
+                cached: true,
+            }
+        );
+    }
+}
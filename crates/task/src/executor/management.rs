@@ -1,8 +1,10 @@
 use super::strategies::{process_task_group, TaskGroupExecutionPlan};
 use super::TaskExecutor;
+use cuenv_cache::concurrent::artifacts::StoredArtifact;
 use cuenv_config::{TaskGroupMode, TaskNode};
-use cuenv_core::Result;
+use cuenv_core::{Error, Result};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 impl TaskExecutor {
@@ -64,6 +66,43 @@ impl TaskExecutor {
         Ok(())
     }
 
+    /// The artifact `name` of `task_name` stored in the cache, by the run
+    /// cached under `cache_key` or else by the newest successful one
+    pub fn cached_artifact(
+        &self,
+        task_name: &str,
+        name: &str,
+        cache_key: Option<&str>,
+    ) -> Result<StoredArtifact> {
+        let plan = self.build_execution_plan(&[task_name.to_string()])?;
+        let definition = plan
+            .tasks
+            .get(task_name)
+            .ok_or_else(|| Error::configuration(format!("Task '{task_name}' not found")))?;
+        let Some(path) = definition.artifacts.get(name) else {
+            let mut declared: Vec<&str> = definition.artifacts.keys().map(String::as_str).collect();
+            declared.sort_unstable();
+            return Err(Error::configuration(if declared.is_empty() {
+                format!("Task '{task_name}' declares no named artifacts")
+            } else {
+                format!(
+                    "Task '{task_name}' declares no artifact '{name}'; it declares {}",
+                    declared.join(", ")
+                )
+            }));
+        };
+        match cache_key {
+            Some(cache_key) => self.action_cache.artifact(cache_key, name),
+            None => self.action_cache.latest_artifact(task_name, name, path),
+        }
+    }
+
+    /// Copy a stored artifact into the directory `out`, returning the files
+    /// written
+    pub fn restore_artifact(&self, artifact: &StoredArtifact, out: &Path) -> Result<Vec<PathBuf>> {
+        self.action_cache.restore_artifact(artifact, out)
+    }
+
     /// Clean up stale cache entries
     pub fn cleanup_cache(&self, _max_age: Duration) -> Result<(usize, u64)> {
         self.cache_manager.cleanup_stale_entries()?;
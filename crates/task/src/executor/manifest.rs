@@ -0,0 +1,146 @@
+//! Manifests of runs, written to `.cuenv/runs/<run id>.json`
+//!
+//! A manifest records what one invocation of `cuenv task` planned, how each
+//! of its tasks ended, how long they took and the keys their results are
+//! cached under. It is the record other tools read a run back from, and
+//! one that can be archived as it is: the format only gains fields, and
+//! [`RunManifest::version`] changes when it cannot.
+
+use super::summary::RunSummary;
+use chrono::{DateTime, Utc};
+use cuenv_core::{Error, Result};
+use cuenv_utils::atomic_file::write_atomic_string;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Version of the manifest format written
+const FORMAT_VERSION: u32 = 1;
+
+/// What one run did, as written to its manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// Version of the format
+    pub version: u32,
+    /// Identifier of the run, shared with its logs and notifications
+    pub run_id: String,
+    /// Version of cuenv that ran it
+    pub cuenv_version: String,
+    /// What was asked to run: a task, a group or a selection of packages
+    pub target: String,
+    /// Directory of the configuration the tasks came from
+    pub working_dir: PathBuf,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// The exit code of the run, unless it failed to run at all
+    pub exit_code: Option<i32>,
+    #[serde(flatten)]
+    pub summary: RunSummary,
+}
+
+impl RunManifest {
+    /// The manifest of the run `run_id` of `target` in `working_dir`,
+    /// finishing now
+    pub fn new(
+        run_id: impl Into<String>,
+        target: impl Into<String>,
+        working_dir: impl Into<PathBuf>,
+        started_at: DateTime<Utc>,
+        exit_code: Option<i32>,
+        summary: RunSummary,
+    ) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            run_id: run_id.into(),
+            cuenv_version: env!("CARGO_PKG_VERSION").to_string(),
+            target: target.into(),
+            working_dir: working_dir.into(),
+            started_at,
+            finished_at: Utc::now(),
+            exit_code,
+            summary,
+        }
+    }
+
+    /// Directory the manifests of runs in `working_dir` are written to
+    pub fn dir(working_dir: &Path) -> PathBuf {
+        working_dir.join(".cuenv").join("runs")
+    }
+
+    /// Create the runs directory of `working_dir`, returning it
+    ///
+    /// `.cuenv` is ignored by git the first time it is created, since runs
+    /// are a local record rather than part of the project.
+    pub(crate) fn create_dir(working_dir: &Path) -> Result<PathBuf> {
+        let dir = Self::dir(working_dir);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| Error::file_system(&dir, "create runs directory", e))?;
+        let gitignore = working_dir.join(".cuenv").join(".gitignore");
+        if !gitignore.exists() {
+            std::fs::write(&gitignore, "*\n")
+                .map_err(|e| Error::file_system(&gitignore, "write", e))?;
+        }
+        Ok(dir)
+    }
+
+    /// Write the manifest to the runs directory of its working directory,
+    /// returning where
+    pub fn write(&self) -> Result<PathBuf> {
+        let dir = Self::create_dir(&self.working_dir)?;
+        let path = dir.join(format!("{}.json", self.run_id));
+        write_atomic_string(&path, &serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    /// Read the manifest at `path`
+    pub fn read(path: &Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| Error::file_system(path, "read", e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::summary::{CacheStatus, TaskSummary};
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let summary = RunSummary {
+            duration_ms: 1200,
+            plan: vec![vec!["build".to_string()], vec!["test".to_string()]],
+            tasks: vec![TaskSummary {
+                name: "build".to_string(),
+                duration_ms: 1000,
+                queue_wait_ms: 0,
+                cache: CacheStatus::Miss,
+                exit_code: Some(1),
+                owners: vec![],
+                cache_key: Some("abc123".to_string()),
+            }],
+            critical_path: vec!["build".to_string()],
+            critical_path_ms: 1000,
+        };
+        let manifest = RunManifest::new("run-1", "test", temp.path(), Utc::now(), Some(1), summary);
+
+        let path = manifest.write().unwrap();
+        assert_eq!(path, temp.path().join(".cuenv/runs/run-1.json"));
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join(".cuenv/.gitignore")).unwrap(),
+            "*\n"
+        );
+
+        let read = RunManifest::read(&path).unwrap();
+        assert_eq!(read.version, FORMAT_VERSION);
+        assert_eq!(read.run_id, "run-1");
+        assert_eq!(read.exit_code, Some(1));
+        assert_eq!(read.summary.plan.len(), 2);
+        assert_eq!(read.summary.tasks[0].cache_key.as_deref(), Some("abc123"));
+
+        // The summary's fields sit at the top level of the manifest
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["tasks"][0]["name"], "build");
+    }
+}
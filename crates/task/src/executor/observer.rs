@@ -0,0 +1,269 @@
+//! Callbacks on the lifecycle of the tasks an executor runs
+//!
+//! The console output of a run and its log events are observers like any
+//! other, so a program embedding cuenv can follow the progress of its tasks
+//! without parsing what they print.
+
+use super::summary::CacheStatus;
+use super::task_log::FailureExcerpt;
+use super::terminal;
+use cuenv_utils::tracing::{task_message, Level};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The stream a line of task output was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// How a task ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TaskOutcome {
+    /// The exit code of the task, `None` when it could not run
+    pub exit_code: Option<i32>,
+    /// Why the task could not run
+    pub error: Option<String>,
+    pub duration: Duration,
+    pub cache: CacheStatus,
+    /// Teams or people to ask about the task when it fails
+    pub owners: Vec<String>,
+    /// What the task printed last, when it failed
+    pub excerpt: FailureExcerpt,
+}
+
+impl TaskOutcome {
+    /// Whether the task ran and exited with 0
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Receives the lifecycle events of the tasks an executor runs
+///
+/// Tasks of a level run concurrently, so calls for different tasks
+/// interleave, and output lines are reported from the threads reading them.
+/// Every method does nothing by default.
+pub trait TaskObserver: Send + Sync {
+    /// Whether the observer needs the output of tasks, which is then read
+    /// line by line rather than left to the terminal
+    fn observes_output(&self) -> bool {
+        true
+    }
+
+    /// Whether the observer writes to the terminal, and so stays silent
+    /// while a spinner or the TUI draws on it
+    fn writes_to_terminal(&self) -> bool {
+        false
+    }
+
+    /// The task is about to run, its dependencies having succeeded
+    fn on_task_start(&self, _task: &str) {}
+
+    /// The task printed a line, with its secrets redacted
+    fn on_output_line(&self, _task: &str, _stream: OutputStream, _line: &str) {}
+
+    /// The result of the task was taken from the cache instead of running it
+    fn on_cache_hit(&self, _task: &str) {}
+
+    /// The task ended, or could not run
+    fn on_task_finish(&self, _task: &str, _outcome: &TaskOutcome) {}
+}
+
+/// The observers of an executor
+#[derive(Clone)]
+pub(crate) struct Observers(Vec<Arc<dyn TaskObserver>>);
+
+impl Default for Observers {
+    /// The console output and the log events of the command line
+    fn default() -> Self {
+        Self(vec![Arc::new(ConsoleObserver), Arc::new(LogObserver)])
+    }
+}
+
+impl Observers {
+    pub(crate) fn new(observers: Vec<Arc<dyn TaskObserver>>) -> Self {
+        Self(observers)
+    }
+
+    pub(crate) fn push(&mut self, observer: Arc<dyn TaskObserver>) {
+        self.0.push(observer);
+    }
+
+    /// These observers without those writing to the terminal, for runs whose
+    /// output is captured
+    pub(crate) fn without_terminal(&self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .filter(|observer| !observer.writes_to_terminal())
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Whether the output of tasks must be read for an observer
+    pub(crate) fn observe_output(&self) -> bool {
+        self.0.iter().any(|observer| observer.observes_output())
+    }
+
+    pub(crate) fn task_started(&self, task: &str) {
+        self.0
+            .iter()
+            .for_each(|observer| observer.on_task_start(task));
+    }
+
+    pub(crate) fn output_line(&self, task: &str, stream: OutputStream, line: &str) {
+        for observer in &self.0 {
+            observer.on_output_line(task, stream, line);
+        }
+    }
+
+    pub(crate) fn cache_hit(&self, task: &str) {
+        self.0
+            .iter()
+            .for_each(|observer| observer.on_cache_hit(task));
+    }
+
+    pub(crate) fn task_finished(&self, task: &str, outcome: &TaskOutcome) {
+        for observer in &self.0 {
+            observer.on_task_finish(task, outcome);
+        }
+    }
+}
+
+/// Prints the output of tasks, and whom to ask about a task as soon as it
+/// fails rather than only in the summary after the run
+pub struct ConsoleObserver;
+
+impl TaskObserver for ConsoleObserver {
+    /// A terminal shows the output of tasks as they write it
+    fn observes_output(&self) -> bool {
+        false
+    }
+
+    fn writes_to_terminal(&self) -> bool {
+        true
+    }
+
+    /// Lines are written in batches, see [`terminal`]
+    fn on_output_line(&self, _task: &str, stream: OutputStream, line: &str) {
+        terminal::write_line(stream, line);
+    }
+
+    fn on_task_finish(&self, task: &str, outcome: &TaskOutcome) {
+        // The output of the task comes before the news of its end
+        terminal::flush();
+        if !outcome.success() && !outcome.owners.is_empty() {
+            task_message(
+                Level::ERROR,
+                task,
+                &format!("✗ {task} failed; owners: {}", outcome.owners.join(", ")),
+            );
+        }
+    }
+}
+
+/// Logs the lifecycle of tasks as tracing events, which become the JSON
+/// event stream with `CUENV_LOG_FORMAT=json`
+///
+/// Each event has an `event` field: `task_started`, `cache_hit` or
+/// `task_finished`. A task that failed after cuenv read its output also
+/// has the last lines of its stderr in `stderr_tail`, and its log in `log`.
+pub struct LogObserver;
+
+impl TaskObserver for LogObserver {
+    fn observes_output(&self) -> bool {
+        false
+    }
+
+    fn on_task_start(&self, task: &str) {
+        tracing::info!(target: "cuenv", task, event = "task_started", "Task started");
+    }
+
+    fn on_cache_hit(&self, task: &str) {
+        tracing::info!(
+            target: "cuenv",
+            task,
+            event = "cache_hit",
+            "Task result taken from the cache"
+        );
+    }
+
+    fn on_task_finish(&self, task: &str, outcome: &TaskOutcome) {
+        let duration_ms = outcome.duration.as_millis() as u64;
+        match (&outcome.error, outcome.exit_code) {
+            (Some(error), _) => tracing::error!(
+                target: "cuenv",
+                task,
+                event = "task_finished",
+                duration_ms,
+                cache = %outcome.cache,
+                error = error.as_str(),
+                "Task execution failed"
+            ),
+            (None, Some(0)) => tracing::info!(
+                target: "cuenv",
+                task,
+                event = "task_finished",
+                duration_ms,
+                cache = %outcome.cache,
+                exit_code = 0,
+                "Task completed"
+            ),
+            (None, exit_code) => tracing::error!(
+                target: "cuenv",
+                task,
+                event = "task_finished",
+                duration_ms,
+                cache = %outcome.cache,
+                exit_code,
+                stderr_tail = outcome.excerpt.stderr_tail.join("\n").as_str(),
+                log = outcome.excerpt.log.as_ref().map(|log| log.display().to_string()).as_deref(),
+                "Task failed"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl TaskObserver for Recorder {
+        fn on_task_start(&self, task: &str) {
+            self.0.lock().unwrap().push(format!("start {task}"));
+        }
+
+        fn on_output_line(&self, task: &str, stream: OutputStream, line: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{task} {stream:?}: {line}"));
+        }
+    }
+
+    #[test]
+    fn test_without_terminal() {
+        let recorder = Arc::new(Recorder::default());
+        let mut observers = Observers::default();
+        assert!(!observers.observe_output());
+        observers.push(recorder.clone());
+        assert!(observers.observe_output());
+
+        let captured = observers.without_terminal();
+        captured.task_started("build");
+        captured.output_line("build", OutputStream::Stderr, "compiling");
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            ["start build", "build Stderr: compiling"]
+        );
+        assert_eq!(captured.0.len(), 2);
+    }
+}
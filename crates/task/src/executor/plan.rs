@@ -1,5 +1,11 @@
-use cuenv_core::TaskDefinition;
-use std::collections::HashMap;
+use cuenv_core::{Error, Result, TaskDefinition};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Version of the JSON a plan is written as, raised whenever reading an
+/// older plan would run something different
+const FORMAT_VERSION: u32 = 1;
 
 /// Represents a task execution plan with resolved dependencies
 #[derive(Debug, Clone)]
@@ -8,4 +14,232 @@ pub struct TaskExecutionPlan {
     pub levels: Vec<Vec<String>>,
     /// Built and validated task definitions
     pub tasks: HashMap<String, TaskDefinition>,
+    /// The planned tasks each task waits for, which it starts after as soon
+    /// as they finished; a plan without them runs each level after the
+    /// whole level before it
+    pub dependencies: HashMap<String, Vec<String>>,
+    /// The cache key of each task, once computed with
+    /// [`TaskExecutor::compute_cache_keys`](super::TaskExecutor::compute_cache_keys)
+    pub cache_keys: BTreeMap<String, String>,
+}
+
+/// A plan as it is written: every map ordered by name
+#[derive(Serialize, Deserialize)]
+struct PlanDocument {
+    version: u32,
+    levels: Vec<Vec<String>>,
+    tasks: BTreeMap<String, TaskDefinition>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    cache_keys: BTreeMap<String, String>,
+}
+
+impl TaskExecutionPlan {
+    /// The plan as JSON, to run it elsewhere with
+    /// [`TaskExecutor::execute_plan`](super::TaskExecutor::execute_plan) or
+    /// archive it
+    ///
+    /// Tasks, the tasks of each level and the fields of every object are
+    /// sorted by name, so the same plan always gives the same JSON. Paths
+    /// are absolute: a plan runs in a checkout at the same path.
+    pub fn to_json(&self) -> Result<String> {
+        let mut levels = self.levels.clone();
+        for level in &mut levels {
+            level.sort();
+        }
+        let document = PlanDocument {
+            version: FORMAT_VERSION,
+            levels,
+            tasks: self
+                .tasks
+                .iter()
+                .map(|(name, task)| (name.clone(), task.clone()))
+                .collect(),
+            dependencies: self
+                .dependencies
+                .iter()
+                .filter(|(_, dependencies)| !dependencies.is_empty())
+                .map(|(name, dependencies)| {
+                    let mut dependencies = dependencies.clone();
+                    dependencies.sort();
+                    (name.clone(), dependencies)
+                })
+                .collect(),
+            cache_keys: self.cache_keys.clone(),
+        };
+        let value = serde_json::to_value(&document).map_err(|e| Error::Json {
+            message: "Failed to serialize the execution plan".to_string(),
+            source: e,
+        })?;
+        serde_json::to_string_pretty(&sort_keys(value)).map_err(|e| Error::Json {
+            message: "Failed to serialize the execution plan".to_string(),
+            source: e,
+        })
+    }
+
+    /// Read a plan written by [`TaskExecutionPlan::to_json`]
+    pub fn from_json(json: &str) -> Result<Self> {
+        let document: PlanDocument = serde_json::from_str(json).map_err(|e| Error::Json {
+            message: "Invalid execution plan".to_string(),
+            source: e,
+        })?;
+        if document.version != FORMAT_VERSION {
+            return Err(Error::configuration(format!(
+                "Execution plan has format version {}, this cuenv reads version {FORMAT_VERSION}",
+                document.version
+            )));
+        }
+
+        // Every task runs at exactly one level
+        let mut planned = HashMap::new();
+        for (level, task) in document
+            .levels
+            .iter()
+            .enumerate()
+            .flat_map(|(level, tasks)| tasks.iter().map(move |task| (level, task)))
+        {
+            if !document.tasks.contains_key(task) {
+                return Err(Error::configuration(format!(
+                    "Execution plan runs '{task}' without defining it"
+                )));
+            }
+            if planned.insert(task, level).is_some() {
+                return Err(Error::configuration(format!(
+                    "Execution plan runs '{task}' more than once"
+                )));
+            }
+        }
+        if let Some(task) = document
+            .tasks
+            .keys()
+            .find(|task| !planned.contains_key(task))
+        {
+            return Err(Error::configuration(format!(
+                "Execution plan defines '{task}' without running it"
+            )));
+        }
+
+        // Tasks only wait for planned tasks of the levels before theirs
+        for (task, dependencies) in &document.dependencies {
+            let Some(&level) = planned.get(task) else {
+                return Err(Error::configuration(format!(
+                    "Execution plan orders '{task}' without running it"
+                )));
+            };
+            for dependency in dependencies {
+                if !planned
+                    .get(dependency)
+                    .is_some_and(|&before| before < level)
+                {
+                    return Err(Error::configuration(format!(
+                        "Execution plan runs '{task}' without running '{dependency}' before it"
+                    )));
+                }
+            }
+        }
+
+        Ok(Self {
+            levels: document.levels,
+            tasks: document.tasks.into_iter().collect(),
+            dependencies: document.dependencies.into_iter().collect(),
+            cache_keys: document.cache_keys,
+        })
+    }
+}
+
+/// `value` with the fields of every object sorted, whichever order the map
+/// of `serde_json` keeps them in
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<(String, Value)> = map.into_iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cuenv_core::TaskExecutionMode;
+    use std::path::PathBuf;
+
+    fn task(name: &str) -> TaskDefinition {
+        let mut task = TaskDefinition::new(
+            name.to_string(),
+            TaskExecutionMode::Command {
+                command: format!("make {name}"),
+            },
+            PathBuf::from("/repo"),
+        );
+        task.artifacts = HashMap::from([
+            ("report".to_string(), PathBuf::from("/repo/report.xml")),
+            ("binary".to_string(), PathBuf::from("/repo/bin/app")),
+        ]);
+        task
+    }
+
+    #[test]
+    fn test_json_round_trip_is_stable() {
+        let plan = TaskExecutionPlan {
+            levels: vec![
+                vec!["lint".to_string(), "build".to_string()],
+                vec!["test".to_string()],
+            ],
+            tasks: ["lint", "build", "test"]
+                .into_iter()
+                .map(|name| (name.to_string(), task(name)))
+                .collect(),
+            dependencies: HashMap::from([
+                (
+                    "test".to_string(),
+                    vec!["lint".to_string(), "build".to_string()],
+                ),
+                ("lint".to_string(), Vec::new()),
+            ]),
+            cache_keys: BTreeMap::from([("build".to_string(), "abc123".to_string())]),
+        };
+
+        let json = plan.to_json().unwrap();
+        let read = TaskExecutionPlan::from_json(&json).unwrap();
+        assert_eq!(read.levels, [vec!["build", "lint"], vec!["test"]]);
+        assert_eq!(read.tasks["test"].working_directory, PathBuf::from("/repo"));
+        assert_eq!(read.dependencies["test"], ["build", "lint"]);
+        assert_eq!(read.cache_keys, plan.cache_keys);
+        assert_eq!(read.to_json().unwrap(), json);
+        assert!(json.find("\"binary\"").unwrap() < json.find("\"report\"").unwrap());
+    }
+
+    #[test]
+    fn test_from_json_rejects_inconsistent_plans() {
+        let json = |levels: &str| {
+            format!(
+                "{{\"version\": 1, \"levels\": {levels}, \"tasks\": {{\"build\": {}}}}}",
+                serde_json::to_string(&task("build")).unwrap()
+            )
+        };
+        assert!(TaskExecutionPlan::from_json(&json("[[\"build\"]]")).is_ok());
+        assert!(TaskExecutionPlan::from_json(&json("[[\"build\"], [\"test\"]]")).is_err());
+        assert!(TaskExecutionPlan::from_json(&json("[[\"build\"], [\"build\"]]")).is_err());
+        assert!(TaskExecutionPlan::from_json(&json("[]")).is_err());
+        assert!(
+            TaskExecutionPlan::from_json(&json("[[\"build\"]]").replace(": 1,", ": 2,")).is_err()
+        );
+        // Tasks wait for tasks of earlier levels only
+        let dependencies = |dependencies: &str| {
+            json("[[\"build\"]]").replacen('{', &format!("{{\"dependencies\": {dependencies}, "), 1)
+        };
+        assert!(TaskExecutionPlan::from_json(&dependencies("{\"build\": []}")).is_ok());
+        assert!(TaskExecutionPlan::from_json(&dependencies("{\"build\": [\"build\"]}")).is_err());
+        assert!(TaskExecutionPlan::from_json(&dependencies("{\"build\": [\"lint\"]}")).is_err());
+    }
 }
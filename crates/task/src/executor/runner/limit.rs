@@ -0,0 +1,135 @@
+//! The output a task keeps under its `maxOutputBytes`
+//!
+//! Each stream of the task keeps the first half of the limit as it is
+//! printed and the last half once the task ends, with a line saying how
+//! much was left out between them. A runaway task then neither fills its
+//! logs nor floods the terminal, while its first error and its last words
+//! survive.
+
+use std::collections::VecDeque;
+
+/// The head and the tail of one stream of a task's output
+pub(super) struct HeadTail {
+    /// Bytes kept at either end
+    half: usize,
+    head_bytes: usize,
+    /// Whether the head is full, so that lines are held in the tail
+    holding: bool,
+    tail: VecDeque<String>,
+    tail_bytes: usize,
+    dropped_bytes: u64,
+}
+
+impl HeadTail {
+    pub(super) fn new(max_bytes: u64) -> Self {
+        Self {
+            half: usize::try_from(max_bytes / 2).unwrap_or(usize::MAX),
+            head_bytes: 0,
+            holding: false,
+            tail: VecDeque::new(),
+            tail_bytes: 0,
+            dropped_bytes: 0,
+        }
+    }
+
+    /// The longest line that can be kept; longer lines are left out
+    /// whole, so output past this length need not be held
+    pub(super) fn max_line(&self) -> usize {
+        // Counting the newline
+        self.half.saturating_sub(1)
+    }
+
+    /// Count `bytes` left out before they reached the limit, such as the
+    /// end of a line longer than [`HeadTail::max_line`]
+    pub(super) fn leave_out(&mut self, bytes: u64) {
+        self.dropped_bytes += bytes;
+    }
+
+    /// Take the next line, returning it while it belongs to the head and
+    /// can be passed on at once
+    pub(super) fn push(&mut self, line: String) -> Option<String> {
+        // Counting the newline
+        let size = line.len() + 1;
+        if !self.holding && self.head_bytes + size <= self.half {
+            self.head_bytes += size;
+            return Some(line);
+        }
+
+        self.holding = true;
+        self.tail_bytes += size;
+        self.tail.push_back(line);
+        while self.tail_bytes > self.half {
+            let Some(oldest) = self.tail.pop_front() else {
+                break;
+            };
+            self.tail_bytes -= oldest.len() + 1;
+            self.dropped_bytes += (oldest.len() + 1) as u64;
+        }
+        None
+    }
+
+    /// The lines held back, after a line about those left out if any were,
+    /// and how many bytes were left out
+    pub(super) fn finish(self) -> (Vec<String>, u64) {
+        let mut lines = Vec::with_capacity(self.tail.len() + 1);
+        if self.dropped_bytes > 0 {
+            lines.push(format!(
+                "[cuenv: {} bytes of output left out, over maxOutputBytes]",
+                self.dropped_bytes
+            ));
+        }
+        lines.extend(self.tail);
+        (lines, self.dropped_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(max_bytes: u64, count: usize) -> (Vec<String>, u64) {
+        let mut limit = HeadTail::new(max_bytes);
+        let mut lines: Vec<String> = (0..count)
+            .filter_map(|i| limit.push(format!("line {i:02}")))
+            .collect();
+        let (rest, dropped) = limit.finish();
+        lines.extend(rest);
+        (lines, dropped)
+    }
+
+    #[test]
+    fn test_output_under_the_limit_is_kept() {
+        // Eight bytes a line with its newline
+        let (lines, dropped) = run(64, 8);
+        assert_eq!(dropped, 0);
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[7], "line 07");
+    }
+
+    #[test]
+    fn test_head_and_tail_are_kept() {
+        let (lines, dropped) = run(32, 10);
+        assert_eq!(dropped, 6 * 8);
+        assert_eq!(
+            lines,
+            [
+                "line 00",
+                "line 01",
+                "[cuenv: 48 bytes of output left out, over maxOutputBytes]",
+                "line 08",
+                "line 09"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lines_longer_than_half_the_limit_are_left_out() {
+        let mut limit = HeadTail::new(16);
+        assert_eq!(limit.push("short".to_string()).as_deref(), Some("short"));
+        assert!(limit.push("x".repeat(100)).is_none());
+        assert!(limit.push("end".to_string()).is_none());
+        let (rest, dropped) = limit.finish();
+        assert_eq!(dropped, 101);
+        assert_eq!(rest.last().map(String::as_str), Some("end"));
+    }
+}
@@ -1,5 +1,6 @@
+mod limit;
 mod output;
 mod process;
 mod security;
 
-pub use process::execute_single_task;
+pub use process::{audit_single_task, execute_single_task};
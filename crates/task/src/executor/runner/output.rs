@@ -1,18 +1,58 @@
+use super::limit::HeadTail;
+use crate::executor::observer::{Observers, OutputStream};
+use cuenv_core::masking::{self, MaskingWriter, StreamMasker};
 use cuenv_core::{Error, Result};
 use cuenv_utils::cleanup::handler::ProcessGuard;
+use cuenv_utils::tracing::{task_message, Level};
+use std::io::{BufReader, Read, Write};
 use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Bytes of a task's output read from its pipe at once
+const PIPE_BUFFER: usize = 64 * 1024;
+
+/// How the output of a task is handled
+pub struct OutputOptions<'a> {
+    pub task_name: &'a str,
+    pub timeout: Duration,
+    /// Keep the output for the observers and the report of a failure,
+    /// instead of printing it
+    pub capture_output: bool,
+    /// Fail the task when a secret is found in its output
+    pub fail_on_secret_leak: bool,
+    /// Keep only the head and the tail of each stream past this size
+    pub max_output_bytes: Option<u64>,
+    pub observers: &'a Observers,
+    /// Kills the task when cancelled
+    pub cancellation: &'a CancellationToken,
+}
 
 /// Execute command with output handling
+///
+/// Secrets found in the output are redacted and reported, and fail the task
+/// when `fail_on_secret_leak` is set. Piped output reaches `observers` line
+/// by line. Past `max_output_bytes`, each stream keeps its head and its tail
+/// and the task is reported as truncated. The task is killed when
+/// `cancellation` is cancelled, and exits with 130 like one interrupted.
 pub async fn execute_with_output_handling(
     mut cmd: Command,
     shell: &str,
     script_content: String,
-    timeout: Duration,
-    task_name: &str,
-    capture_output: bool,
+    options: OutputOptions<'_>,
 ) -> Result<i32> {
+    let OutputOptions {
+        task_name,
+        timeout,
+        capture_output,
+        fail_on_secret_leak,
+        max_output_bytes,
+        observers,
+        cancellation,
+    } = options;
+
     // Spawn the process with timeout
     let mut child = cmd.spawn().map_err(|e| {
         Error::command_execution(
@@ -26,36 +66,89 @@ pub async fn execute_with_output_handling(
     // Handle output capturing if needed
     let (stdout_handle, stderr_handle, captured_output) = if capture_output {
         let output = Arc::new(Mutex::new(CapturedOutput::default()));
-        let task_name_clone = task_name.to_string();
-        let (stdout_h, stderr_h) =
-            handle_captured_output(&mut child, &task_name_clone, Arc::clone(&output));
+        let (stdout_h, stderr_h) = handle_captured_output(
+            &mut child,
+            task_name,
+            observers,
+            max_output_bytes,
+            Arc::clone(&output),
+        );
         (stdout_h, stderr_h, Some(output))
     } else {
-        (None, None, None)
+        let (stdout_h, stderr_h) =
+            stream_masked_output(&mut child, task_name, observers, max_output_bytes);
+        (stdout_h, stderr_h, None)
     };
 
     // Use ProcessGuard for automatic cleanup
+    let pid = child.id();
     let mut guard = ProcessGuard::new(child, timeout);
 
     // Wait for completion with timeout (use async version to avoid blocking the runtime)
-    let status = guard.wait_with_timeout_async().await.map_err(|e| {
-        Error::command_execution(
-            shell,
-            vec!["-c".to_string(), script_content.clone()],
-            e.to_string(),
-            None,
-        )
-    })?;
+    let status = tokio::select! {
+        status = guard.wait_with_timeout_async() => Some(status),
+        _ = cancellation.cancelled() => None,
+    };
+    let status = match status {
+        Some(status) => Some(status.map_err(|e| {
+            Error::command_execution(
+                shell,
+                vec!["-c".to_string(), script_content.clone()],
+                e.to_string(),
+                None,
+            )
+        })?),
+        None => {
+            tracing::warn!(task = task_name, "Task cancelled");
+            kill_process_group(pid);
+            None
+        }
+    };
 
     // Wait for output threads to complete
-    if let Some(handle) = stdout_handle {
-        let _ = handle.join();
+    let mut redactions = 0;
+    let mut truncated = Vec::new();
+    for (stream, handle) in [
+        (OutputStream::Stdout, stdout_handle),
+        (OutputStream::Stderr, stderr_handle),
+    ] {
+        if let Some(handle) = handle {
+            let (redacted, dropped_bytes) = handle.join().unwrap_or((0, 0));
+            redactions += redacted;
+            if dropped_bytes > 0 {
+                truncated.push((stream, dropped_bytes));
+            }
+        }
     }
-    if let Some(handle) = stderr_handle {
-        let _ = handle.join();
+    if let Some(output) = &captured_output {
+        if let Ok(captured) = output.lock() {
+            redactions += captured.redactions;
+        }
+    }
+
+    let exit_code = match status {
+        Some(status) => status.code().unwrap_or(1),
+        None => 130,
+    };
+
+    if redactions > 0 {
+        task_message(
+            Level::WARN,
+            task_name,
+            &format!(
+                "Warning: task '{task_name}' printed {redactions} secret(s), which were redacted"
+            ),
+        );
+        if fail_on_secret_leak {
+            return Err(Error::security(format!(
+                "Task '{task_name}' leaked {redactions} secret(s) in its output and failOnSecretLeak is set"
+            )));
+        }
     }
 
-    let exit_code = status.code().unwrap_or(1);
+    for (stream, dropped_bytes) in truncated {
+        report_truncation(task_name, stream, dropped_bytes).await;
+    }
 
     // If the task failed and we captured output, send it through the event system
     // This ensures TUI can display it properly without corrupting the terminal
@@ -108,57 +201,446 @@ pub async fn execute_with_output_handling(
     Ok(exit_code)
 }
 
+/// Warn that the middle of a stream of the task was left out, and publish
+/// it for the TUI and the event log
+async fn report_truncation(task_name: &str, stream: OutputStream, dropped_bytes: u64) {
+    let stream = match stream {
+        OutputStream::Stdout => "stdout",
+        OutputStream::Stderr => "stderr",
+    };
+    task_message(
+        Level::WARN,
+        task_name,
+        &format!(
+            "Warning: task '{task_name}' printed more than its maxOutputBytes; {dropped_bytes} bytes of its {stream} were left out"
+        ),
+    );
+    let _ = cuenv_core::events::global_event_bus()
+        .publish(cuenv_core::SystemEvent::Task(
+            cuenv_core::TaskEvent::TaskOutputTruncated {
+                task_name: task_name.to_string(),
+                task_id: task_name.to_string(),
+                stream: stream.to_string(),
+                dropped_bytes,
+            },
+        ))
+        .await;
+}
+
+/// Stop a cancelled task and the processes it started, which share its
+/// process group
+fn kill_process_group(pid: u32) {
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .args(["-TERM", "--", &format!("-{pid}")])
+        .status();
+    #[cfg(windows)]
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .status();
+}
+
 #[derive(Default)]
 struct CapturedOutput {
     stdout: Vec<String>,
     stderr: Vec<String>,
+    redactions: usize,
 }
 
+/// Reading threads return how many secrets they redacted and how many
+/// bytes of output they left out
+type OutputHandle = Option<std::thread::JoinHandle<(usize, u64)>>;
+
 fn handle_captured_output(
     child: &mut std::process::Child,
-    _task_name: &str,
+    task_name: &str,
+    observers: &Observers,
+    max_output_bytes: Option<u64>,
     captured_output: Arc<Mutex<CapturedOutput>>,
-) -> (
-    Option<std::thread::JoinHandle<()>>,
-    Option<std::thread::JoinHandle<()>>,
-) {
-    use std::io::{BufRead, BufReader};
-
-    // Take stdout and stderr from child
-    let stdout = child.stdout.take();
-    let stderr = child.stderr.take();
-
-    // Spawn thread to read stdout
-    let stdout_handle = stdout.map(|stdout| {
-        let output_clone = Arc::clone(&captured_output);
+) -> (OutputHandle, OutputHandle) {
+    let capture = |mut stream: Box<dyn Read + Send>, output: OutputStream| {
+        let captured_output = Arc::clone(&captured_output);
+        let (task_name, observers) = (task_name.to_string(), observers.clone());
         std::thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines().map_while(|result| result.ok()) {
+            let keep = |line: String| {
+                observers.output_line(&task_name, output, &line);
                 // Store for potential error display
-                if let Ok(mut output) = output_clone.lock() {
-                    output.stdout.push(line);
+                if let Ok(mut captured) = captured_output.lock() {
+                    match output {
+                        OutputStream::Stdout => captured.stdout.push(line),
+                        OutputStream::Stderr => captured.stderr.push(line),
+                    }
+                }
+            };
+            let mut limit = max_output_bytes.map(HeadTail::new);
+            let mut lines = LineSplitter::new(limit.as_ref());
+            let mut on_line = |line: &[u8], left_out: u64| {
+                let line = String::from_utf8_lossy(line);
+                let line = line.strip_suffix('\r').unwrap_or(&line);
+                let (line, redactions) = masking::mask_task_output(line);
+                if let Ok(mut captured) = captured_output.lock() {
+                    captured.redactions += redactions;
+                }
+                match &mut limit {
+                    Some(limit) => {
+                        limit.push(line).into_iter().for_each(&keep);
+                        limit.leave_out(left_out);
+                    }
+                    None => keep(line),
+                }
+            };
+            let mut buf = vec![0; PIPE_BUFFER];
+            loop {
+                match stream.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(read) => lines.split(&buf[..read], &mut on_line),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(_) => break,
                 }
-                // Note: Real-time event sending removed as it's not working reliably
-                // Events will be sent after task completion
             }
+            lines.finish(&mut on_line);
+            let (rest, dropped_bytes) = limit.map(HeadTail::finish).unwrap_or_default();
+            rest.into_iter().for_each(&keep);
+            // Redactions are counted in the shared output
+            (0, dropped_bytes)
         })
-    });
+    };
+    let stdout_handle = child
+        .stdout
+        .take()
+        .map(|stdout| capture(Box::new(stdout), OutputStream::Stdout));
+    let stderr_handle = child
+        .stderr
+        .take()
+        .map(|stderr| capture(Box::new(stderr), OutputStream::Stderr));
 
-    // Spawn thread to read stderr
-    let stderr_handle = stderr.map(|stderr| {
-        let output_clone = Arc::clone(&captured_output);
+    (stdout_handle, stderr_handle)
+}
+
+/// Pass piped output to the observers line by line, with secrets and
+/// tokens masked
+///
+/// Streams that were inherited rather than piped need no forwarding.
+fn stream_masked_output(
+    child: &mut std::process::Child,
+    task_name: &str,
+    observers: &Observers,
+    max_output_bytes: Option<u64>,
+) -> (OutputHandle, OutputHandle) {
+    let forward = |stream: Box<dyn Read + Send>, output: OutputStream| {
+        let dropped_bytes = Arc::new(AtomicU64::new(0));
+        let mut lines = LineWriter::new(task_name, output, observers.clone());
+        if let Some(max_bytes) = max_output_bytes {
+            lines = lines.with_limit(max_bytes, Arc::clone(&dropped_bytes));
+        }
         std::thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            for line in reader.lines().map_while(|result| result.ok()) {
-                // Store for potential error display
-                if let Ok(mut output) = output_clone.lock() {
-                    output.stderr.push(line);
-                }
-                // Note: Real-time event sending removed as it's not working reliably
-                // Events will be sent after task completion
-            }
+            // Chatty tasks are read in large chunks rather than as written
+            let mut stream = BufReader::with_capacity(PIPE_BUFFER, stream);
+            let mut writer =
+                MaskingWriter::with_masker(lines, StreamMasker::new().with_token_patterns());
+            let _ = std::io::copy(&mut stream, &mut writer);
+            // Finishing drops the line writer, which passes on the tail
+            let redactions = writer.finish().unwrap_or(0);
+            (redactions, dropped_bytes.load(Ordering::Relaxed))
         })
-    });
+    };
+    let stdout_handle = child
+        .stdout
+        .take()
+        .map(|stdout| forward(Box::new(stdout), OutputStream::Stdout));
+    let stderr_handle = child
+        .stderr
+        .take()
+        .map(|stderr| forward(Box::new(stderr), OutputStream::Stderr));
 
     (stdout_handle, stderr_handle)
 }
+
+/// Longest line passed on whole from output without a limit; longer lines
+/// are passed on in pieces of this size
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Splits output into lines, holding no more of a line that has not ended
+/// than can be passed on
+///
+/// Under a limit, the bytes of a line past what the limit keeps are left out
+/// as they arrive and only counted. Without one, a line longer than
+/// [`MAX_LINE_BYTES`] is passed on in pieces.
+struct LineSplitter {
+    pending: Vec<u8>,
+    max_line: usize,
+    /// Whether the bytes past `max_line` are left out, rather than passed
+    /// on as the next piece of the line
+    truncate: bool,
+    /// Bytes of the pending line left out
+    left_out: u64,
+}
+
+impl LineSplitter {
+    fn new(limit: Option<&HeadTail>) -> Self {
+        Self {
+            pending: Vec::new(),
+            // A byte more than the limit keeps, so that a line cut short is
+            // still left out whole
+            max_line: limit.map_or(MAX_LINE_BYTES, |limit| limit.max_line() + 1),
+            truncate: limit.is_some(),
+            left_out: 0,
+        }
+    }
+
+    /// Split the next bytes of output, calling `emit` with each line they
+    /// end and how many of its bytes were left out
+    ///
+    /// Only the new bytes are scanned for the end of a line.
+    fn split(&mut self, bytes: &[u8], emit: &mut impl FnMut(&[u8], u64)) {
+        let mut rest = bytes;
+        while let Some(end) = rest.iter().position(|byte| *byte == b'\n') {
+            self.hold(&rest[..end], emit);
+            emit(&self.pending, std::mem::take(&mut self.left_out));
+            self.pending.clear();
+            rest = &rest[end + 1..];
+        }
+        self.hold(rest, emit);
+    }
+
+    /// Pass on a last line without a newline
+    fn finish(&mut self, emit: &mut impl FnMut(&[u8], u64)) {
+        if !self.pending.is_empty() || self.left_out > 0 {
+            emit(&self.pending, std::mem::take(&mut self.left_out));
+            self.pending.clear();
+        }
+    }
+
+    /// Add bytes to the pending line, as many as fit
+    fn hold(&mut self, bytes: &[u8], emit: &mut impl FnMut(&[u8], u64)) {
+        let mut bytes = bytes;
+        loop {
+            let room = self.max_line.saturating_sub(self.pending.len());
+            if bytes.len() <= room {
+                self.pending.extend_from_slice(bytes);
+                return;
+            }
+            self.pending.extend_from_slice(&bytes[..room]);
+            if self.truncate {
+                self.left_out += (bytes.len() - room) as u64;
+                return;
+            }
+            emit(&self.pending, 0);
+            self.pending.clear();
+            bytes = &bytes[room..];
+        }
+    }
+}
+
+/// Splits masked output into lines for the observers; a last line without
+/// a newline, and the tail of limited output, are passed on when the writer
+/// is dropped
+struct LineWriter {
+    task_name: String,
+    stream: OutputStream,
+    observers: Observers,
+    lines: LineSplitter,
+    limit: Option<HeadTail>,
+    /// Bytes the limit left out, known once the writer is dropped
+    dropped_bytes: Arc<AtomicU64>,
+}
+
+impl LineWriter {
+    fn new(task_name: &str, stream: OutputStream, observers: Observers) -> Self {
+        Self {
+            task_name: task_name.to_string(),
+            stream,
+            observers,
+            lines: LineSplitter::new(None),
+            limit: None,
+            dropped_bytes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Keep only the head and the tail of more than `max_bytes` of output,
+    /// counting the bytes left out in `dropped_bytes`
+    fn with_limit(mut self, max_bytes: u64, dropped_bytes: Arc<AtomicU64>) -> Self {
+        let limit = HeadTail::new(max_bytes);
+        self.lines = LineSplitter::new(Some(&limit));
+        self.limit = Some(limit);
+        self.dropped_bytes = dropped_bytes;
+        self
+    }
+
+    /// Split `bytes` into lines and pass them on, or all that is pending
+    /// when `bytes` is `None`
+    fn pass_on(&mut self, bytes: Option<&[u8]>) {
+        let Self {
+            task_name,
+            stream,
+            observers,
+            lines,
+            limit,
+            ..
+        } = self;
+        let mut emit = |line: &[u8], left_out: u64| {
+            let line = String::from_utf8_lossy(line);
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            match limit {
+                Some(limit) => {
+                    if let Some(line) = limit.push(line.to_string()) {
+                        observers.output_line(task_name, *stream, &line);
+                    }
+                    limit.leave_out(left_out);
+                }
+                None => observers.output_line(task_name, *stream, line),
+            }
+        };
+        match bytes {
+            Some(bytes) => lines.split(bytes, &mut emit),
+            None => lines.finish(&mut emit),
+        }
+    }
+}
+
+impl Write for LineWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pass_on(Some(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LineWriter {
+    fn drop(&mut self) {
+        self.pass_on(None);
+        if let Some(limit) = self.limit.take() {
+            let (rest, dropped_bytes) = limit.finish();
+            for line in rest {
+                self.observers
+                    .output_line(&self.task_name, self.stream, &line);
+            }
+            self.dropped_bytes.store(dropped_bytes, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::observer::TaskObserver;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Lines(Mutex<Vec<String>>);
+
+    impl TaskObserver for Lines {
+        fn on_output_line(&self, task: &str, _stream: OutputStream, line: &str) {
+            self.0.lock().unwrap().push(format!("{task}: {line}"));
+        }
+    }
+
+    #[test]
+    fn test_line_writer() {
+        let lines = Arc::new(Lines::default());
+        {
+            let mut writer = LineWriter::new(
+                "build",
+                OutputStream::Stdout,
+                Observers::new(vec![lines.clone() as Arc<dyn TaskObserver>]),
+            );
+            writer.write_all(b"compiling a\r\ncompil").unwrap();
+            writer.write_all(b"ing b\n\ndone").unwrap();
+        }
+        assert_eq!(
+            *lines.0.lock().unwrap(),
+            [
+                "build: compiling a",
+                "build: compiling b",
+                "build: ",
+                "build: done"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_writer_with_limit() {
+        let lines = Arc::new(Lines::default());
+        let dropped_bytes = Arc::new(AtomicU64::new(0));
+        {
+            let mut writer = LineWriter::new(
+                "build",
+                OutputStream::Stdout,
+                Observers::new(vec![lines.clone() as Arc<dyn TaskObserver>]),
+            )
+            .with_limit(16, Arc::clone(&dropped_bytes));
+            for i in 0..5 {
+                writeln!(writer, "line {i}").unwrap();
+            }
+        }
+        assert_eq!(dropped_bytes.load(Ordering::Relaxed), 21);
+        assert_eq!(
+            *lines.0.lock().unwrap(),
+            [
+                "build: line 0",
+                "build: [cuenv: 21 bytes of output left out, over maxOutputBytes]",
+                "build: line 4"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_splitter_holds_at_most_a_line() {
+        let mut lines = Vec::new();
+        let mut emit = |line: &[u8], left_out: u64| {
+            lines.push((String::from_utf8_lossy(line).into_owned(), left_out));
+        };
+
+        // Without a limit, long lines are passed on in pieces
+        let mut splitter = LineSplitter::new(None);
+        splitter.split(&vec![b'a'; MAX_LINE_BYTES + 1], &mut emit);
+        assert!(splitter.pending.len() <= MAX_LINE_BYTES);
+        splitter.split(b"\nb", &mut emit);
+        splitter.finish(&mut emit);
+
+        // Under a limit, the bytes past what it keeps are only counted
+        let limit = HeadTail::new(16);
+        let mut splitter = LineSplitter::new(Some(&limit));
+        for _ in 0..100 {
+            splitter.split(b"xxxxxxxxxx", &mut emit);
+        }
+        assert_eq!(splitter.pending.len(), 8);
+        splitter.split(b"\nok\n", &mut emit);
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0].0.len(), MAX_LINE_BYTES);
+        assert_eq!(lines[1], ("a".to_string(), 0));
+        assert_eq!(lines[2], ("b".to_string(), 0));
+        assert_eq!(lines[3], ("x".repeat(8), 992));
+        assert_eq!(lines[4], ("ok".to_string(), 0));
+    }
+
+    #[test]
+    fn test_line_writer_leaves_out_long_lines_whole() {
+        let lines = Arc::new(Lines::default());
+        let dropped_bytes = Arc::new(AtomicU64::new(0));
+        {
+            let mut writer = LineWriter::new(
+                "build",
+                OutputStream::Stdout,
+                Observers::new(vec![lines.clone() as Arc<dyn TaskObserver>]),
+            )
+            .with_limit(16, Arc::clone(&dropped_bytes));
+            writer.write_all(b"start\n").unwrap();
+            writer.write_all(&[b'x'; 1000]).unwrap();
+            writer.write_all(b"\nend\n").unwrap();
+        }
+        assert_eq!(dropped_bytes.load(Ordering::Relaxed), 1001);
+        assert_eq!(
+            *lines.0.lock().unwrap(),
+            [
+                "build: start",
+                "build: [cuenv: 1001 bytes of output left out, over maxOutputBytes]",
+                "build: end"
+            ]
+        );
+    }
+}
@@ -1,17 +1,84 @@
-use cuenv_core::{Result, TaskDefinition, TaskExecutionMode};
-use std::collections::HashSet;
-use std::path::Path;
+use crate::executor::context::TaskExecutionContext;
+use cuenv_core::{Result, TaskDefinition, TaskExecutionMode, TaskSecurity};
+use cuenv_security::{AccessRestrictions, AuditReport};
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
 use std::process::{Command, Stdio};
 
 /// Execute a single task
 pub async fn execute_single_task(
+    ctx: &TaskExecutionContext<'_>,
     task_name: &str,
     task_definition: &TaskDefinition,
-    _working_dir: &Path,
     args: &[String],
-    audit_mode: bool,
-    capture_output: bool,
 ) -> Result<i32> {
+    let (audit_mode, capture_output) = (ctx.audit_mode, ctx.capture_output);
+    let (shell, script_content, mut cmd) = task_command(task_definition, args, ctx.env)?;
+    let fail_on_secret_leak = task_definition
+        .security
+        .as_ref()
+        .is_some_and(|security| security.fail_on_secret_leak);
+
+    configure_stdio(
+        &mut cmd,
+        capture_output,
+        fail_on_secret_leak
+            || task_definition.max_output_bytes.is_some()
+            || ctx.observers.observe_output(),
+    );
+    configure_platform_specific(&mut cmd);
+
+    // Apply security restrictions if configured. Audits trace every task,
+    // restricted or not.
+    let unrestricted = TaskSecurity::default();
+    let security = match &task_definition.security {
+        Some(security) => Some(security),
+        None => audit_mode.then_some(&unrestricted),
+    };
+    if let Some(security) = security {
+        if let Some(exit_code) =
+            super::security::apply_security_restrictions(&mut cmd, security, audit_mode)?
+        {
+            return Ok(exit_code);
+        }
+    }
+
+    // Execute with output handling
+    super::output::execute_with_output_handling(
+        cmd,
+        &shell,
+        script_content,
+        super::output::OutputOptions {
+            task_name,
+            timeout: task_definition.timeout,
+            capture_output,
+            fail_on_secret_leak,
+            max_output_bytes: task_definition.max_output_bytes,
+            observers: ctx.observers,
+            cancellation: ctx.cancellation,
+        },
+    )
+    .await
+}
+
+/// Run a task under tracing, without its restrictions, and report what it
+/// accessed
+pub fn audit_single_task(
+    task_definition: &TaskDefinition,
+    args: &[String],
+    env: Option<&HashMap<String, String>>,
+) -> Result<(i32, AuditReport)> {
+    let (_, _, mut cmd) = task_command(task_definition, args, env)?;
+    AccessRestrictions::default().run_with_audit(&mut cmd)
+}
+
+/// The shell, the script and the command running a task, in `env` instead
+/// of the environment of this process when given
+fn task_command(
+    task_definition: &TaskDefinition,
+    args: &[String],
+    env: Option<&HashMap<String, String>>,
+) -> Result<(String, String, Command)> {
     // Determine what to execute from TaskDefinition
     let (shell, script_content) = match &task_definition.execution_mode {
         TaskExecutionMode::Command { command } => {
@@ -33,31 +100,21 @@ pub async fn execute_single_task(
     let exec_dir = task_definition.working_directory.clone();
 
     // Configure command
+    // Definitions recorded before shells took arguments have none
+    let shell_args = if task_definition.shell_args.is_empty() {
+        cuenv_core::default_shell_args(&shell)
+    } else {
+        task_definition.shell_args.clone()
+    };
     let mut cmd = Command::new(&shell);
-    cmd.arg("-c").arg(&script_content).current_dir(&exec_dir);
-
-    configure_stdio(&mut cmd, capture_output);
-    configure_platform_specific(&mut cmd);
-
-    // Apply security restrictions if configured
-    if let Some(security) = &task_definition.security {
-        if let Some(exit_code) =
-            super::security::apply_security_restrictions(&mut cmd, security, audit_mode)?
-        {
-            return Ok(exit_code);
-        }
+    cmd.args(&shell_args)
+        .arg(&script_content)
+        .current_dir(&exec_dir);
+    if let Some(env) = env {
+        cmd.env_clear().envs(env);
     }
 
-    // Execute with output handling
-    super::output::execute_with_output_handling(
-        cmd,
-        &shell,
-        script_content,
-        task_definition.timeout,
-        task_name,
-        capture_output,
-    )
-    .await
+    Ok((shell, script_content, cmd))
 }
 
 fn validate_security(shell: &str, script_content: &str, args: &[String]) -> Result<()> {
@@ -75,12 +132,21 @@ fn validate_security(shell: &str, script_content: &str, args: &[String]) -> Resu
     Ok(())
 }
 
-fn configure_stdio(cmd: &mut Command, capture_output: bool) {
+/// Pipe the output of the task when it is captured, or must be read to be
+/// masked, limited or observed
+fn configure_stdio(cmd: &mut Command, capture_output: bool, read_output: bool) {
     if capture_output {
         // Capture output for TUI mode to prevent interference
         cmd.stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
+    } else if cuenv_core::masking::is_active() || read_output || !std::io::stdout().is_terminal() {
+        // Route output through the secret masker and the observers, keeping
+        // stdin interactive.
+        // Output going to logs rather than a terminal is always scanned.
+        cmd.stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
     } else {
         // Normal mode - inherit stdio
         cmd.stdin(Stdio::inherit())
@@ -19,7 +19,7 @@ pub fn apply_security_restrictions_with_format(
     audit_mode: bool,
     json_output: bool,
 ) -> Result<Option<i32>> {
-    use cuenv_security::AccessRestrictions;
+    use cuenv_security::{AccessRestrictions, SeccompProfile, TaskUser};
     let mut restrictions =
         AccessRestrictions::new(security.restrict_disk, security.restrict_network);
 
@@ -30,6 +30,13 @@ pub fn apply_security_restrictions_with_format(
     for path in &security.write_only_paths {
         restrictions.add_read_write_path(path);
     }
+    restrictions.seccomp = security
+        .seccomp
+        .as_deref()
+        .map(SeccompProfile::load)
+        .transpose()?;
+    restrictions.user = security.user.as_deref().map(TaskUser::lookup).transpose()?;
+    restrictions.drop_capabilities = security.drop_capabilities;
 
     if audit_mode {
         restrictions.enable_audit_mode();
@@ -42,12 +49,21 @@ pub fn apply_security_restrictions_with_format(
             match audit_report.to_json() {
                 Ok(json) => println!("{json}"),
                 Err(e) => {
-                    eprintln!("Warning: Failed to export audit report as JSON: {e}");
+                    cuenv_utils::tracing::message(
+                        cuenv_utils::tracing::Level::WARN,
+                        &format!("Warning: Failed to export audit report as JSON: {e}"),
+                    );
                     audit_report.print_summary();
                 }
             }
         } else {
             audit_report.print_summary();
+            if let Some(dir) = cmd.get_current_dir() {
+                println!("\n💡 Suggested task configuration:");
+                for line in audit_report.suggested_config(dir).lines() {
+                    println!("  {line}");
+                }
+            }
         }
 
         return Ok(Some(exit_code));
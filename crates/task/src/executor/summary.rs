@@ -0,0 +1,347 @@
+//! Summary of the tasks an executor ran
+//!
+//! Each task records when it started and finished, its exit code and
+//! whether its result came from the cache. A task is ready once its
+//! dependencies have finished, so the time from then until it started is
+//! the time it waited on the scheduler. The critical path is the chain of
+//! dependencies with the longest total duration: the run cannot finish
+//! sooner than it does, however many tasks run in parallel.
+
+use cuenv_core::style::{Painter, Role};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Where the result of a task came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheStatus {
+    /// The cached result was used and the task did not run
+    Hit,
+    /// The task and all its dependencies were found in the cache before
+    /// the run started, so it was never scheduled
+    Cached,
+    /// The task ran and its result was cached
+    Miss,
+    /// The task ran without caching
+    Disabled,
+}
+
+impl fmt::Display for CacheStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hit => "hit",
+            Self::Cached => "cached",
+            Self::Miss => "miss",
+            Self::Disabled => "disabled",
+        })
+    }
+}
+
+/// A task of a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSummary {
+    pub name: String,
+    pub duration_ms: u64,
+    /// Time from the end of the task's dependencies until it started
+    pub queue_wait_ms: u64,
+    pub cache: CacheStatus,
+    /// The exit code, unless the task could not be run
+    pub exit_code: Option<i32>,
+    /// Teams or people to ask about the task
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    /// The key the result of the task is cached under, when caching is
+    /// enabled for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_key: Option<String>,
+}
+
+impl TaskSummary {
+    /// Whom to ask about the task, when it failed and has owners
+    pub fn owner_note(&self) -> Option<String> {
+        (self.exit_code != Some(0) && !self.owners.is_empty())
+            .then(|| format!("owners: {}", self.owners.join(", ")))
+    }
+}
+
+/// The tasks of a run and its critical path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    /// Wall-clock time from the start of the run to the end of its last task
+    pub duration_ms: u64,
+    /// The tasks planned by level, including those that did not run after
+    /// a failure
+    #[serde(default)]
+    pub plan: Vec<Vec<String>>,
+    /// The tasks in the order they started
+    pub tasks: Vec<TaskSummary>,
+    /// The chain of dependencies taking longest, from the first task
+    pub critical_path: Vec<String>,
+    pub critical_path_ms: u64,
+}
+
+/// What a task recorded about its execution
+#[derive(Debug, Clone)]
+pub(crate) struct TaskRecord {
+    pub name: String,
+    pub dependencies: Vec<String>,
+    pub started_at: Instant,
+    pub finished_at: Instant,
+    pub cache: CacheStatus,
+    pub exit_code: Option<i32>,
+    pub owners: Vec<String>,
+    pub cache_key: Option<String>,
+}
+
+/// Collects the records of the tasks an executor runs
+#[derive(Debug, Default)]
+pub(crate) struct RunRecorder {
+    started_at: Option<Instant>,
+    plan: Vec<Vec<String>>,
+    tasks: Vec<TaskRecord>,
+}
+
+impl RunRecorder {
+    /// Note the start of a run of the tasks `levels`; later runs of the same
+    /// executor extend it
+    pub fn begin(&mut self, levels: &[Vec<String>]) {
+        self.started_at.get_or_insert_with(Instant::now);
+        self.plan.extend(levels.iter().cloned());
+    }
+
+    pub fn record(&mut self, task: TaskRecord) {
+        self.tasks.push(task);
+    }
+
+    /// The summary of the tasks recorded so far, if any
+    pub fn summary(&self) -> Option<RunSummary> {
+        let run_start = self.started_at?;
+        let mut tasks: Vec<&TaskRecord> = self.tasks.iter().collect();
+        if tasks.is_empty() {
+            return None;
+        }
+        tasks.sort_by_key(|task| task.started_at);
+        let by_name: HashMap<&str, &TaskRecord> = tasks
+            .iter()
+            .map(|task| (task.name.as_str(), *task))
+            .collect();
+        let dependencies = |task: &TaskRecord| -> Vec<&TaskRecord> {
+            task.dependencies
+                .iter()
+                .filter_map(|name| by_name.get(name.as_str()).copied())
+                .collect()
+        };
+
+        let summaries = tasks
+            .iter()
+            .map(|task| {
+                let ready_at = dependencies(task)
+                    .iter()
+                    .map(|dependency| dependency.finished_at)
+                    .max()
+                    .unwrap_or(run_start)
+                    .max(run_start);
+                TaskSummary {
+                    name: task.name.clone(),
+                    duration_ms: millis(
+                        task.finished_at.saturating_duration_since(task.started_at),
+                    ),
+                    queue_wait_ms: millis(task.started_at.saturating_duration_since(ready_at)),
+                    cache: task.cache,
+                    exit_code: task.exit_code,
+                    owners: task.owners.clone(),
+                    cache_key: task.cache_key.clone(),
+                }
+            })
+            .collect();
+
+        // Dependencies finish before their dependents start, so going
+        // through the tasks by their end sees every dependency first
+        let mut by_finish = tasks.clone();
+        by_finish.sort_by_key(|task| task.finished_at);
+        let mut longest: HashMap<&str, (Duration, Option<&str>)> = HashMap::new();
+        for task in &by_finish {
+            let duration = task.finished_at.saturating_duration_since(task.started_at);
+            let (before, previous) = dependencies(task)
+                .iter()
+                .filter_map(|dependency| {
+                    longest
+                        .get(dependency.name.as_str())
+                        .map(|(length, _)| (*length, Some(dependency.name.as_str())))
+                })
+                .max_by_key(|(length, _)| *length)
+                .unwrap_or((Duration::ZERO, None));
+            longest.insert(task.name.as_str(), (before + duration, previous));
+        }
+
+        let (mut last, critical_length) = longest
+            .iter()
+            .max_by_key(|(_, (length, _))| *length)
+            .map(|(name, (length, _))| (Some(*name), *length))
+            .unwrap_or((None, Duration::ZERO));
+        let mut critical_path = Vec::new();
+        while let Some(name) = last {
+            critical_path.push(name.to_string());
+            last = longest.get(name).and_then(|(_, previous)| *previous);
+        }
+        critical_path.reverse();
+
+        let run_end = tasks
+            .iter()
+            .map(|task| task.finished_at)
+            .max()
+            .unwrap_or(run_start);
+        Some(RunSummary {
+            duration_ms: millis(run_end.saturating_duration_since(run_start)),
+            plan: self.plan.clone(),
+            tasks: summaries,
+            critical_path,
+            critical_path_ms: millis(critical_length),
+        })
+    }
+}
+
+impl RunSummary {
+    /// A table of the tasks followed by the critical path
+    pub fn render(&self, painter: &Painter) -> String {
+        let width = self
+            .tasks
+            .iter()
+            .map(|task| task.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Task".len());
+
+        // Columns are padded before they are painted, as escape codes have
+        // no width on screen
+        let header = format!(
+            "{:<width$}  {:>9}  {:>9}  {:<8}  Status",
+            "Task", "Duration", "Wait", "Cache"
+        );
+        let mut out = format!("{}\n", painter.paint(Role::Heading, header));
+        for task in &self.tasks {
+            let mut status = match task.exit_code {
+                Some(0) => painter.paint(Role::Success, "ok"),
+                Some(code) => painter.paint(Role::Failure, format!("exit {code}")),
+                None => painter.paint(Role::Failure, "error"),
+            };
+            if let Some(note) = task.owner_note() {
+                status = format!("{status}  {}", painter.paint(Role::Muted, note));
+            }
+            let cache = format!("{:<8}", task.cache.to_string());
+            let cache = match task.cache {
+                CacheStatus::Hit | CacheStatus::Cached => painter.paint(Role::Success, cache),
+                CacheStatus::Miss | CacheStatus::Disabled => cache,
+            };
+            let _ = writeln!(
+                out,
+                "{:<width$}  {:>9}  {:>9}  {cache}  {status}",
+                task.name,
+                seconds(task.duration_ms),
+                seconds(task.queue_wait_ms),
+            );
+        }
+        let _ = write!(
+            out,
+            "{} ({} of {}): {}",
+            painter.paint(Role::Heading, "Critical path"),
+            seconds(self.critical_path_ms),
+            seconds(self.duration_ms),
+            self.critical_path.join(" → ")
+        );
+        out
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&Painter::plain()))
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+fn seconds(millis: u64) -> String {
+    format!("{:.2}s", millis as f64 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(
+        name: &str,
+        dependencies: &[&str],
+        start: Instant,
+        from_ms: u64,
+        to_ms: u64,
+    ) -> TaskRecord {
+        TaskRecord {
+            name: name.to_string(),
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+            started_at: start + Duration::from_millis(from_ms),
+            finished_at: start + Duration::from_millis(to_ms),
+            cache: CacheStatus::Disabled,
+            exit_code: Some(0),
+            owners: vec![],
+            cache_key: None,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut recorder = RunRecorder::default();
+        assert!(recorder.summary().is_none());
+        recorder.begin(&[
+            vec!["build".to_string(), "lint".to_string()],
+            vec!["e2e".to_string(), "test".to_string()],
+        ]);
+        let start = recorder.started_at.unwrap();
+
+        // lint and build run first; test needs build, and only starts once
+        // lint has finished too
+        recorder.record(task("lint", &[], start, 0, 300));
+        recorder.record(task("build", &[], start, 0, 200));
+        recorder.record(task("test", &["build"], start, 300, 1000));
+        recorder.record(TaskRecord {
+            exit_code: Some(1),
+            owners: vec!["team-web".to_string()],
+            ..task("e2e", &["build"], start, 300, 800)
+        });
+
+        let summary = recorder.summary().unwrap();
+        assert_eq!(summary.duration_ms, 1000);
+        assert_eq!(summary.critical_path, ["build", "test"]);
+        assert_eq!(summary.critical_path_ms, 900);
+        assert_eq!(summary.plan.len(), 2);
+
+        let test = summary
+            .tasks
+            .iter()
+            .find(|task| task.name == "test")
+            .unwrap();
+        assert_eq!(test.duration_ms, 700);
+        assert_eq!(test.queue_wait_ms, 100);
+
+        let table = summary.to_string();
+        assert!(
+            table.contains("test       0.70s      0.10s  disabled  ok"),
+            "{table}"
+        );
+        assert!(
+            table.contains("e2e        0.50s      0.10s  disabled  exit 1  owners: team-web"),
+            "{table}"
+        );
+        assert!(table.ends_with("Critical path (0.90s of 1.00s): build → test"));
+
+        let painted = summary.render(&Painter::new(true, cuenv_core::style::Theme::Default));
+        assert!(
+            painted.contains("test       0.70s      0.10s  disabled  \x1b[32mok\x1b[0m"),
+            "{painted}"
+        );
+    }
+}
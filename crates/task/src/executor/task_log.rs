@@ -0,0 +1,256 @@
+//! Logs of the output of the tasks of a run, kept for those that fail
+//!
+//! The output cuenv reads from a task is written to
+//! `.cuenv/runs/<run id>/<task>.log` as it arrives, and the log is removed
+//! once the task succeeds. The last lines of the stderr of a failed task go
+//! into the report of the failure and its `task_finished` event, so that a
+//! failure in CI can be understood from the end of the run alone. A task
+//! printing straight to a terminal is not logged: its output is on screen.
+
+use super::manifest::RunManifest;
+use super::observer::{OutputStream, TaskObserver};
+use cuenv_core::Error;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Lines of stderr kept for the report of a failed task
+const STDERR_EXCERPT_LINES: usize = 20;
+
+/// What a failed task printed last, when cuenv read its output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailureExcerpt {
+    /// The last lines of stderr, with secrets redacted
+    pub stderr_tail: Vec<String>,
+    /// The log of the whole output of the task
+    pub log: Option<PathBuf>,
+}
+
+/// A task that failed in a level of the run
+#[derive(Debug, Clone)]
+pub struct FailedTask {
+    pub name: String,
+    /// The exit code, or -1 when the task could not run
+    pub exit_code: i32,
+    pub excerpt: FailureExcerpt,
+}
+
+/// The error report of the failed tasks of a level: their names, then the
+/// last lines of stderr and the log of each
+pub(crate) fn failure_report(failed: &[FailedTask]) -> String {
+    let names: Vec<&str> = failed.iter().map(|task| task.name.as_str()).collect();
+    let mut report = format!("Tasks failed: {}", names.join(", "));
+    for task in failed {
+        let FailureExcerpt { stderr_tail, log } = &task.excerpt;
+        if stderr_tail.is_empty() && log.is_none() {
+            continue;
+        }
+        match task.exit_code {
+            -1 => {
+                let _ = write!(report, "\n\n{} could not finish", task.name);
+            }
+            code => {
+                let _ = write!(report, "\n\n{} exited with {code}", task.name);
+            }
+        }
+        if !stderr_tail.is_empty() {
+            report.push_str("; its stderr ended with:");
+            for line in stderr_tail {
+                let _ = write!(report, "\n    {line}");
+            }
+        }
+        if let Some(log) = log {
+            let _ = write!(report, "\n  full log: {}", log.display());
+        }
+    }
+    report
+}
+
+/// The output logs of the tasks of one run
+pub(crate) struct TaskLogs {
+    /// `.cuenv/runs/<run id>` of the working directory, created with the
+    /// first log
+    dir: PathBuf,
+    working_dir: PathBuf,
+    tasks: Mutex<HashMap<String, TaskLog>>,
+}
+
+#[derive(Default)]
+struct TaskLog {
+    /// `None` once the log could not be written
+    file: Option<BufWriter<File>>,
+    path: Option<PathBuf>,
+    stderr_tail: VecDeque<String>,
+}
+
+impl TaskLogs {
+    pub(crate) fn new(working_dir: &Path, run_id: &str) -> Self {
+        Self {
+            dir: RunManifest::dir(working_dir).join(run_id),
+            working_dir: working_dir.to_path_buf(),
+            tasks: Mutex::default(),
+        }
+    }
+
+    /// Open the log of `task`, warning when it cannot be written
+    fn open(&self, task: &str) -> Option<(BufWriter<File>, PathBuf)> {
+        let path = self.dir.join(format!("{}.log", file_name(task)));
+        let opened = RunManifest::create_dir(&self.working_dir).and_then(|_| {
+            std::fs::create_dir_all(&self.dir)
+                .and_then(|()| File::create(&path))
+                .map_err(|e| Error::file_system(&path, "create task log", e))
+        });
+        match opened {
+            Ok(file) => Some((BufWriter::new(file), path)),
+            Err(e) => {
+                tracing::warn!(task, "Could not log the output of the task: {e}");
+                None
+            }
+        }
+    }
+
+    /// Close the log of `task`, removing it when the task succeeded, and
+    /// return what it printed last when it failed
+    pub(crate) fn finish(&self, task: &str, success: bool) -> FailureExcerpt {
+        let Some(log) = self
+            .tasks
+            .lock()
+            .ok()
+            .and_then(|mut tasks| tasks.remove(task))
+        else {
+            return FailureExcerpt::default();
+        };
+        let mut path = log.path;
+        if let Some(mut file) = log.file {
+            if file.flush().is_err() {
+                path = None;
+            }
+        }
+        if success {
+            if let Some(path) = &path {
+                let _ = std::fs::remove_file(path);
+            }
+            return FailureExcerpt::default();
+        }
+        FailureExcerpt {
+            stderr_tail: log.stderr_tail.into(),
+            log: path,
+        }
+    }
+}
+
+impl TaskObserver for TaskLogs {
+    /// Only output read for other reasons is logged
+    fn observes_output(&self) -> bool {
+        false
+    }
+
+    fn on_output_line(&self, task: &str, stream: OutputStream, line: &str) {
+        let Ok(mut tasks) = self.tasks.lock() else {
+            return;
+        };
+        let log = tasks.entry(task.to_string()).or_insert_with(|| {
+            let (file, path) = self.open(task).unzip();
+            TaskLog {
+                file,
+                path,
+                ..TaskLog::default()
+            }
+        });
+        if let Some(file) = &mut log.file {
+            if writeln!(file, "{line}").is_err() {
+                log.file = None;
+                log.path = None;
+            }
+        }
+        if stream == OutputStream::Stderr {
+            if log.stderr_tail.len() == STDERR_EXCERPT_LINES {
+                log.stderr_tail.pop_front();
+            }
+            log.stderr_tail.push_back(line.to_string());
+        }
+    }
+}
+
+/// `task` as a file name, with the bytes of any character but ASCII
+/// letters, digits, `-`, `_` and `.` escaped as `%XX`, so that tasks such as
+/// `pkg:test` and `pkg/test` get logs of their own
+fn file_name(task: &str) -> String {
+    let mut name = String::with_capacity(task.len());
+    for byte in task.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.') {
+            name.push(byte as char);
+        } else {
+            let _ = write!(name, "%{byte:02X}");
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_logs() {
+        let temp = tempfile::tempdir().unwrap();
+        let logs = TaskLogs::new(temp.path(), "run-1");
+        for i in 0..30 {
+            logs.on_output_line("pkg:test", OutputStream::Stderr, &format!("error {i}"));
+        }
+        logs.on_output_line("pkg:test", OutputStream::Stdout, "done");
+        logs.on_output_line("build", OutputStream::Stdout, "built");
+
+        let excerpt = logs.finish("pkg:test", false);
+        assert_eq!(excerpt.stderr_tail.len(), STDERR_EXCERPT_LINES);
+        assert_eq!(excerpt.stderr_tail[0], "error 10");
+        let log = excerpt.log.unwrap();
+        assert_eq!(log, temp.path().join(".cuenv/runs/run-1/pkg%3Atest.log"));
+        let content = std::fs::read_to_string(&log).unwrap();
+        assert!(content.starts_with("error 0\n"));
+        assert!(content.ends_with("error 29\ndone\n"));
+
+        // The logs of tasks that succeed are removed
+        let build = temp.path().join(".cuenv/runs/run-1/build.log");
+        assert!(build.exists());
+        assert_eq!(logs.finish("build", true), FailureExcerpt::default());
+        assert!(!build.exists());
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("build"), "build");
+        assert_eq!(file_name("pkg:test"), "pkg%3Atest");
+        assert_eq!(file_name("pkg/test"), "pkg%2Ftest");
+        assert_eq!(file_name("pkg_test"), "pkg_test");
+        assert_eq!(file_name("100%"), "100%25");
+    }
+
+    #[test]
+    fn test_failure_report() {
+        let report = failure_report(&[
+            FailedTask {
+                name: "test".to_string(),
+                exit_code: 2,
+                excerpt: FailureExcerpt {
+                    stderr_tail: vec!["assertion failed".to_string()],
+                    log: Some(PathBuf::from("/repo/.cuenv/runs/run-1/test.log")),
+                },
+            },
+            FailedTask {
+                name: "lint".to_string(),
+                exit_code: 1,
+                excerpt: FailureExcerpt::default(),
+            },
+        ]);
+        assert_eq!(
+            report,
+            "Tasks failed: test, lint\n\n\
+             test exited with 2; its stderr ended with:\n    assertion failed\n  \
+             full log: /repo/.cuenv/runs/run-1/test.log"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Batched writes of task output to the terminal
+//!
+//! Chatty tasks print thousands of lines a second, and writing each on its
+//! own locks stdout and makes a system call per line. Lines are instead
+//! appended to a batch per stream, which a background thread writes out at
+//! most every [`FLUSH_INTERVAL`], or at once when it grows past
+//! [`MAX_BATCH`]. Within a batch, the lines of stdout are written before
+//! those of stderr.
+
+use super::observer::OutputStream;
+use std::io::Write;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+/// How often batched lines are written at most
+const FLUSH_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Bytes of a stream written without waiting for the next interval
+const MAX_BATCH: usize = 64 * 1024;
+
+/// Lines waiting to be written
+#[derive(Default)]
+struct Batch {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl Batch {
+    fn push(&mut self, stream: OutputStream, line: &str) {
+        let buffer = match stream {
+            OutputStream::Stdout => &mut self.stdout,
+            OutputStream::Stderr => &mut self.stderr,
+        };
+        buffer.extend_from_slice(line.as_bytes());
+        buffer.push(b'\n');
+    }
+
+    fn is_empty(&self) -> bool {
+        self.stdout.is_empty() && self.stderr.is_empty()
+    }
+
+    fn is_full(&self) -> bool {
+        self.stdout.len() >= MAX_BATCH || self.stderr.len() >= MAX_BATCH
+    }
+
+    /// Write the lines in one call per stream, leaving the batch empty
+    fn write_to(&mut self, stdout: &mut impl Write, stderr: &mut impl Write) {
+        if !self.stdout.is_empty() {
+            let _ = stdout.write_all(&self.stdout).and_then(|()| stdout.flush());
+            self.stdout.clear();
+        }
+        if !self.stderr.is_empty() {
+            let _ = stderr.write_all(&self.stderr).and_then(|()| stderr.flush());
+            self.stderr.clear();
+        }
+    }
+}
+
+/// The batch of this process, once a line was queued
+static TERMINAL: OnceLock<Terminal> = OnceLock::new();
+
+/// Lines waiting to be written, and the thread flushing them
+struct Terminal {
+    batch: Mutex<Batch>,
+    pending: Condvar,
+}
+
+impl Terminal {
+    fn get() -> &'static Terminal {
+        TERMINAL.get_or_init(|| {
+            let spawned = std::thread::Builder::new()
+                .name("cuenv-output".to_string())
+                .spawn(|| Terminal::get().flush_periodically());
+            if let Err(e) = spawned {
+                tracing::debug!("Failed to start the output thread: {e}");
+            }
+            Terminal {
+                batch: Mutex::default(),
+                pending: Condvar::new(),
+            }
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Batch> {
+        self.batch.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sleep until lines are waiting, then write them an interval later
+    fn flush_periodically(&self) {
+        loop {
+            {
+                let mut batch = self.lock();
+                while batch.is_empty() {
+                    batch = self.pending.wait(batch).unwrap_or_else(|e| e.into_inner());
+                }
+            }
+            std::thread::sleep(FLUSH_INTERVAL);
+            flush();
+        }
+    }
+}
+
+/// Queue a line of task output for the terminal
+pub(crate) fn write_line(stream: OutputStream, line: &str) {
+    let terminal = Terminal::get();
+    let mut batch = terminal.lock();
+    let was_empty = batch.is_empty();
+    batch.push(stream, line);
+    if batch.is_full() {
+        batch.write_to(&mut std::io::stdout().lock(), &mut std::io::stderr().lock());
+    } else if was_empty {
+        terminal.pending.notify_one();
+    }
+}
+
+/// Write every queued line now, such as before reporting that a task ended
+///
+/// The batch stays locked while it is written, so lines reach the terminal
+/// in the order they were queued.
+pub(crate) fn flush() {
+    if let Some(terminal) = TERMINAL.get() {
+        terminal
+            .lock()
+            .write_to(&mut std::io::stdout().lock(), &mut std::io::stderr().lock());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_writes_each_stream_at_once() {
+        let mut batch = Batch::default();
+        batch.push(OutputStream::Stdout, "compiling a");
+        batch.push(OutputStream::Stderr, "warning: unused");
+        batch.push(OutputStream::Stdout, "compiling b");
+        assert!(!batch.is_full());
+
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        batch.write_to(&mut stdout, &mut stderr);
+        assert_eq!(stdout, b"compiling a\ncompiling b\n");
+        assert_eq!(stderr, b"warning: unused\n");
+        assert!(batch.is_empty());
+
+        batch.push(OutputStream::Stderr, &"x".repeat(MAX_BATCH));
+        assert!(batch.is_full());
+    }
+}
@@ -1,11 +1,13 @@
 //! Task execution and management for cuenv
 //!
 //! This crate handles task execution, dependency resolution,
-//! cross-package references, and command execution.
+//! cross-package references, and command execution. The [`embed`] module is
+//! its stable API for running tasks from other programs.
 
 pub mod builder;
 pub mod command_executor;
 pub mod cross_package;
+pub mod embed;
 pub mod executor;
 // pub mod executor_v2;  // Complex version with compilation issues
 // pub mod executor_tui;
@@ -16,6 +18,7 @@ pub mod source;
 pub use builder::*;
 pub use command_executor::*;
 pub use cross_package::*;
+pub use embed::{Environment, LoadOptions, RunOutcome, Runner, TaskGraph};
 pub use executor::*;
 // pub use executor_tui::*;
 pub use protocol::*;
@@ -60,6 +60,7 @@ pub async fn parse_env_readonly(
     let options = ParseOptions {
         environment,
         capabilities: capabilities.unwrap_or_default(),
+        ..Default::default()
     };
 
     CueParser::eval_package_with_options(
@@ -81,6 +81,18 @@ mod protocol_tests {
             task_nodes: HashMap::new(),
             hooks: HashMap::new(),
             config: None,
+            root: false,
+            constraints: HashMap::new(),
+            list_variables: HashMap::new(),
+            command_variables: HashMap::new(),
+            terraform_variables: HashMap::new(),
+            overlays: Default::default(),
+            environment_sources: HashMap::new(),
+            typed_values: HashMap::new(),
+            environments: Vec::new(),
+            notify: None,
+            nix: None,
+            devenv: None,
         };
         let config = Arc::new(cuenv_config::Config::new(
             temp_dir.path().to_path_buf(),
@@ -114,6 +126,18 @@ mod protocol_tests {
             task_nodes: HashMap::new(),
             hooks: HashMap::new(),
             config: None,
+            root: false,
+            constraints: HashMap::new(),
+            list_variables: HashMap::new(),
+            command_variables: HashMap::new(),
+            terraform_variables: HashMap::new(),
+            overlays: Default::default(),
+            environment_sources: HashMap::new(),
+            typed_values: HashMap::new(),
+            environments: Vec::new(),
+            notify: None,
+            nix: None,
+            devenv: None,
         };
         let config = Arc::new(cuenv_config::Config::new(
             temp_dir.path().to_path_buf(),
@@ -147,6 +171,18 @@ mod protocol_tests {
             task_nodes: HashMap::new(),
             hooks: HashMap::new(),
             config: None,
+            root: false,
+            constraints: HashMap::new(),
+            list_variables: HashMap::new(),
+            command_variables: HashMap::new(),
+            terraform_variables: HashMap::new(),
+            overlays: Default::default(),
+            environment_sources: HashMap::new(),
+            typed_values: HashMap::new(),
+            environments: Vec::new(),
+            notify: None,
+            nix: None,
+            devenv: None,
         };
         let config = Arc::new(cuenv_config::Config::new(
             temp_dir.path().to_path_buf(),
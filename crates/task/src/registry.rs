@@ -124,23 +124,23 @@ impl MonorepoTaskRegistry {
             })?;
 
         // Check if the task declares this output
-        if let Some(ref outputs) = task.config.outputs {
-            if !outputs
-                .iter()
-                .any(|o| o == output_name || o.ends_with(output_name))
-            {
-                return Err(Error::Configuration {
-                    message: format!("Task '{task_ref}' does not declare output '{output_name}'"),
-                });
-            }
-        } else {
+        let Some(ref outputs) = task.config.outputs else {
             return Err(Error::Configuration {
                 message: format!("Task '{task_ref}' does not declare any outputs"),
             });
+        };
+        if outputs.path_of(output_name).is_none()
+            && !outputs.paths().iter().any(|o| o.ends_with(output_name))
+        {
+            return Err(Error::Configuration {
+                message: format!("Task '{task_ref}' does not declare output '{output_name}'"),
+            });
         }
 
-        // Construct the output path
-        let output_path = task.package_path.join(output_name);
+        // Construct the output path, named artifacts resolving to their path
+        let output_path = task
+            .package_path
+            .join(outputs.path_of(output_name).unwrap_or(output_name));
 
         // Check if the output exists
         if !output_path.exists() {
@@ -160,40 +160,38 @@ impl MonorepoTaskRegistry {
     /// Validate that all task dependencies exist
     pub fn validate_all_dependencies(&self) -> Result<()> {
         for (task_name, task) in &self.tasks {
-            if let Some(ref deps) = task.config.dependencies {
-                for dep in deps {
-                    // Parse the dependency reference
-                    let dep_ref = parse_reference(dep)?;
-
-                    // For cross-package dependencies, check if the task exists
-                    if dep_ref.is_cross_package() {
-                        let full_dep_name = match dep_ref {
-                            CrossPackageReference::PackageTask { package, task } => {
-                                format!("{package}:{task}")
-                            }
-                            CrossPackageReference::PackageTaskOutput { package, task, .. } => {
-                                format!("{package}:{task}")
-                            }
-                            _ => dep.clone(),
-                        };
-
-                        if !self.tasks.contains_key(&full_dep_name) {
-                            return Err(Error::Configuration {
-                                message: format!(
-                                    "Task '{task_name}' depends on non-existent task '{full_dep_name}'"
-                                ),
-                            });
+            for (dep, _) in task.config.dependencies_by_kind() {
+                // Parse the dependency reference
+                let dep_ref = parse_reference(dep)?;
+
+                // For cross-package dependencies, check if the task exists
+                if dep_ref.is_cross_package() {
+                    let full_dep_name = match dep_ref {
+                        CrossPackageReference::PackageTask { package, task } => {
+                            format!("{package}:{task}")
                         }
-                    } else {
-                        // For local dependencies, check in the same package
-                        let local_task_name = format!("{}:{}", task.package_name, dep);
-                        if !self.tasks.contains_key(&local_task_name) {
-                            return Err(Error::Configuration {
-                                message: format!(
-                                    "Task '{task_name}' depends on non-existent local task '{dep}'"
-                                ),
-                            });
+                        CrossPackageReference::PackageTaskOutput { package, task, .. } => {
+                            format!("{package}:{task}")
                         }
+                        _ => dep.clone(),
+                    };
+
+                    if !self.tasks.contains_key(&full_dep_name) {
+                        return Err(Error::Configuration {
+                            message: format!(
+                                "Task '{task_name}' depends on non-existent task '{full_dep_name}'"
+                            ),
+                        });
+                    }
+                } else {
+                    // For local dependencies, check in the same package
+                    let local_task_name = format!("{}:{}", task.package_name, dep);
+                    if !self.tasks.contains_key(&local_task_name) {
+                        return Err(Error::Configuration {
+                            message: format!(
+                                "Task '{task_name}' depends on non-existent local task '{dep}'"
+                            ),
+                        });
                     }
                 }
             }
@@ -216,7 +214,7 @@ impl MonorepoTaskRegistry {
                         // Check if we can resolve this output (don't fail if it doesn't exist yet)
                         if let Some(ref_task) = self.get_task(&task_ref) {
                             if let Some(ref outputs) = ref_task.config.outputs {
-                                if !outputs.iter().any(|o| o == output) {
+                                if outputs.path_of(output).is_none() {
                                     return Err(Error::Configuration { message: format!(
                                         "Task '{task_name}' references non-existent output '{output}' from task '{task_ref}'"
                                     ) });
@@ -39,10 +39,10 @@ impl EventHandler for TuiApp {
                         | TaskEvent::Progress { task_name, .. }
                         | TaskEvent::Completed { task_name, .. }
                         | TaskEvent::Failed { task_name, .. }
-                        | TaskEvent::Cancelled { task_name } => {
-                            if current_task == task_name {
-                                self.focus_pane.update_task_info().await;
-                            }
+                        | TaskEvent::Cancelled { task_name }
+                            if current_task == task_name =>
+                        {
+                            self.focus_pane.update_task_info().await;
                         }
                         _ => {}
                     }
@@ -429,7 +429,12 @@ mod tests {
             vec!["grandchild".to_string()],
         ];
 
-        TaskExecutionPlan { tasks, levels }
+        TaskExecutionPlan {
+            tasks,
+            levels,
+            dependencies: Default::default(),
+            cache_keys: Default::default(),
+        }
     }
 
     #[tokio::test]
@@ -534,6 +539,8 @@ mod tests {
         let plan = TaskExecutionPlan {
             tasks: HashMap::new(),
             levels: vec![],
+            dependencies: HashMap::new(),
+            cache_keys: Default::default(),
         };
 
         let ascii_output = renderer.generate_ascii_dag(&plan).await;
@@ -476,11 +476,14 @@ mod tests {
             dependencies: resolved_deps,
             working_directory: PathBuf::from("/tmp"),
             shell: "sh".to_string(),
+            shell_args: vec!["-e".to_string(), "-c".to_string()],
             inputs: Vec::new(),
             outputs: Vec::new(),
+            artifacts: HashMap::new(),
             security: None,
             cache: Default::default(),
             timeout: Duration::from_secs(60),
+            max_output_bytes: None,
         }
     }
 
@@ -504,7 +507,12 @@ mod tests {
             vec!["task2".to_string()],
         ];
 
-        TaskExecutionPlan { tasks, levels }
+        TaskExecutionPlan {
+            tasks,
+            levels,
+            dependencies: Default::default(),
+            cache_keys: Default::default(),
+        }
     }
 
     #[test]
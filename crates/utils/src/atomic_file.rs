@@ -59,6 +59,39 @@ pub fn write_atomic_string(path: &Path, content: &str) -> Result<()> {
     write_atomic(path, content.as_bytes())
 }
 
+/// Write data to a file atomically, readable by the current user only
+///
+/// Missing parent directories are created with mode 0700, and the data is
+/// written to a temporary file created with mode 0600 before it replaces
+/// the file, so it is never readable by others, not even while written.
+pub fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(parent)
+        .map_err(|e| Error::file_system(parent, "create parent directory", e))?;
+
+    // Temporary files are created with mode 0600 and removed when dropped
+    let mut file = tempfile::NamedTempFile::new_in(parent)
+        .map_err(|e| Error::file_system(parent, "create temporary file", e))?;
+    file.write_all(content)
+        .and_then(|()| file.as_file().sync_all())
+        .map_err(|e| Error::file_system(file.path(), "write to temporary file", e))?;
+    file.persist(path)
+        .map_err(|e| Error::file_system(path, "atomic rename", e.error))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +138,31 @@ mod tests {
         let content = fs::read_to_string(&file_path).unwrap();
         assert_eq!(content, "New content");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("private");
+        let file_path = dir.join("secret.txt");
+        // A file written before with the default mode
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file_path, "Old content").unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&file_path, b"s3cr3t").unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "s3cr3t");
+        let mode = fs::metadata(&file_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Missing directories are created private
+        let nested = temp_dir.path().join("a").join("b").join("secret.txt");
+        write_private(&nested, b"s3cr3t").unwrap();
+        for dir in [nested.parent().unwrap(), &temp_dir.path().join("a")] {
+            let mode = fs::metadata(dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+    }
 }
@@ -33,6 +33,11 @@ impl FileTimes {
         self.files.remove(path.as_ref());
     }
 
+    /// The watched files
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
     /// Check if any watched files have changed
     pub fn has_changed(&self) -> bool {
         for (path, old_time) in &self.files {
@@ -0,0 +1,322 @@
+//! Chrome trace event output for profiling runs
+//!
+//! Records the spans of a run, such as the pipeline, its levels, tasks,
+//! hashing and cache lookups, as complete events, and tracing events as
+//! instant events, in the Chrome trace event format that chrome://tracing
+//! and Perfetto open. Each task gets its own track, named after the task;
+//! scheduling happens on the first track.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Record};
+use tracing::{Event, Id, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Name of span that gets a track of its own
+const TASK_SPAN: &str = "task";
+/// The track of everything outside tasks
+const SCHEDULER_TRACK: u64 = 0;
+
+/// A recording of a run, shared with the layer that records it
+#[derive(Clone)]
+pub struct ChromeTrace {
+    recording: Arc<Mutex<Recording>>,
+}
+
+struct Recording {
+    start: Instant,
+    events: Vec<TraceEvent>,
+    next_track: u64,
+}
+
+/// An event of the trace event format
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: String,
+    ph: &'static str,
+    /// Microseconds since the recording started
+    ts: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<u64>,
+    /// Scope of instant events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<&'static str>,
+    pid: u32,
+    tid: u64,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    args: Map<String, Value>,
+}
+
+/// What the layer keeps about an open span
+struct SpanTiming {
+    start: Instant,
+    track: u64,
+    args: Map<String, Value>,
+}
+
+impl ChromeTrace {
+    /// Start an empty recording
+    pub fn new() -> Self {
+        let trace = Self {
+            recording: Arc::new(Mutex::new(Recording {
+                start: Instant::now(),
+                events: Vec::new(),
+                next_track: SCHEDULER_TRACK + 1,
+            })),
+        };
+        trace.push_metadata("process_name", SCHEDULER_TRACK, "cuenv");
+        trace.push_metadata("thread_name", SCHEDULER_TRACK, "scheduler");
+        trace
+    }
+
+    /// The layer recording into this trace
+    pub fn layer(&self) -> ChromeTraceLayer {
+        ChromeTraceLayer {
+            trace: self.clone(),
+        }
+    }
+
+    /// The trace as a JSON object with its `traceEvents`
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let recording = self.recording.lock();
+        serde_json::to_string(&serde_json::json!({
+            "traceEvents": recording.events,
+            "displayTimeUnit": "ms",
+        }))
+    }
+
+    /// Write the trace to `path`
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json()?)
+    }
+
+    fn next_track(&self) -> u64 {
+        let mut recording = self.recording.lock();
+        let track = recording.next_track;
+        recording.next_track += 1;
+        track
+    }
+
+    fn push_metadata(&self, name: &str, track: u64, value: &str) {
+        let mut args = Map::new();
+        args.insert("name".to_string(), Value::from(value));
+        self.push(TraceEvent {
+            name: name.to_string(),
+            cat: "__metadata".to_string(),
+            ph: "M",
+            ts: 0,
+            dur: None,
+            s: None,
+            pid: std::process::id(),
+            tid: track,
+            args,
+        });
+    }
+
+    fn push(&self, event: TraceEvent) {
+        self.recording.lock().events.push(event);
+    }
+
+    fn micros_since_start(&self, instant: Instant) -> u64 {
+        let start = self.recording.lock().start;
+        instant.saturating_duration_since(start).as_micros() as u64
+    }
+}
+
+impl Default for ChromeTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A tracing layer recording spans and events into a [`ChromeTrace`]
+pub struct ChromeTraceLayer {
+    trace: ChromeTrace,
+}
+
+impl<S> Layer<S> for ChromeTraceLayer
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = ArgsVisitor(Map::new());
+        attrs.record(&mut visitor);
+        let args = visitor.0;
+
+        let track = if attrs.metadata().name() == TASK_SPAN {
+            let track = self.trace.next_track();
+            let name = args
+                .get("name")
+                .or_else(|| args.get("task_name"))
+                .and_then(Value::as_str)
+                .unwrap_or(TASK_SPAN);
+            self.trace.push_metadata("thread_name", track, name);
+            track
+        } else {
+            span.parent()
+                .and_then(|parent| {
+                    parent
+                        .extensions()
+                        .get::<SpanTiming>()
+                        .map(|timing| timing.track)
+                })
+                .unwrap_or(SCHEDULER_TRACK)
+        };
+
+        span.extensions_mut().insert(SpanTiming {
+            start: Instant::now(),
+            track,
+            args,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                let mut visitor = ArgsVisitor(std::mem::take(&mut timing.args));
+                values.record(&mut visitor);
+                timing.args = visitor.0;
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = ArgsVisitor(Map::new());
+        event.record(&mut visitor);
+        let mut args = visitor.0;
+        let name = match args.remove("message") {
+            Some(Value::String(message)) => message,
+            _ => event.metadata().name().to_string(),
+        };
+        let track = ctx
+            .event_span(event)
+            .and_then(|span| {
+                span.extensions()
+                    .get::<SpanTiming>()
+                    .map(|timing| timing.track)
+            })
+            .unwrap_or(SCHEDULER_TRACK);
+
+        self.trace.push(TraceEvent {
+            name,
+            cat: event.metadata().target().to_string(),
+            ph: "i",
+            ts: self.trace.micros_since_start(Instant::now()),
+            dur: None,
+            s: Some("t"),
+            pid: std::process::id(),
+            tid: track,
+            args,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+
+        self.trace.push(TraceEvent {
+            name: span.name().to_string(),
+            cat: span.metadata().target().to_string(),
+            ph: "X",
+            ts: self.trace.micros_since_start(timing.start),
+            dur: Some(timing.start.elapsed().as_micros() as u64),
+            s: None,
+            pid: std::process::id(),
+            tid: timing.track,
+            args: timing.args,
+        });
+    }
+}
+
+/// Collects the fields of a span or event as trace event arguments
+pub(super) struct ArgsVisitor(pub(super) Map<String, Value>);
+
+impl Visit for ArgsVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), Value::from(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_records_spans_on_task_tracks() {
+        let trace = ChromeTrace::new();
+        let subscriber = tracing_subscriber::registry().with(trace.layer());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let level = tracing::info_span!("level", idx = 0);
+            let _level = level.enter();
+            tracing::info!(tasks = 2, "Starting execution level");
+            for name in ["build", "test"] {
+                let task = tracing::info_span!("task", name = name);
+                let _task = task.enter();
+                let lookup = tracing::debug_span!("cache_lookup", hit = tracing::field::Empty);
+                lookup.record("hit", false);
+            }
+        });
+
+        let json: Value = serde_json::from_str(&trace.to_json().unwrap()).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+        let find = |ph: &str, name: &str| {
+            events
+                .iter()
+                .filter(|event| event["ph"] == ph && event["name"] == name)
+                .collect::<Vec<_>>()
+        };
+
+        let tasks = find("X", "task");
+        assert_eq!(tasks.len(), 2);
+        assert_ne!(tasks[0]["tid"], tasks[1]["tid"]);
+        assert_eq!(tasks[0]["args"]["name"], "build");
+
+        let lookups = find("X", "cache_lookup");
+        assert_eq!(lookups[0]["tid"], tasks[0]["tid"]);
+        assert_eq!(lookups[0]["args"]["hit"], false);
+
+        assert_eq!(find("X", "level")[0]["tid"], SCHEDULER_TRACK);
+        assert_eq!(find("i", "Starting execution level")[0]["args"]["tasks"], 2);
+        assert!(find("M", "thread_name")
+            .iter()
+            .any(|event| event["args"]["name"] == "test"));
+    }
+}
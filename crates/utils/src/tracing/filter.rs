@@ -0,0 +1,154 @@
+//! Which log events are shown, from `-v`, `-q` and `CUENV_LOG`
+//!
+//! `CUENV_LOG` takes `tracing` filter directives, with short names for the
+//! cuenv crates so that one subsystem can be debugged on its own:
+//! `CUENV_LOG=cache=debug,task_executor=trace` is
+//! `cuenv_cache=debug,cuenv_task::executor=trace`. A short name is a crate
+//! name without its `cuenv_` prefix, optionally joined to a module of the
+//! crate with `_` or `::`. Without `CUENV_LOG`, `-v` and `-q` pick one level
+//! for everything.
+
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+
+/// The cuenv crates, by their short name
+const CRATES: &[(&str, &str)] = &[
+    ("cache", "cuenv_cache"),
+    ("cli", "cuenv"),
+    ("config", "cuenv_config"),
+    ("core", "cuenv_core"),
+    ("env", "cuenv_env"),
+    ("hooks", "cuenv_hooks"),
+    ("security", "cuenv_security"),
+    ("shell", "cuenv_shell"),
+    ("task", "cuenv_task"),
+    ("tui", "cuenv_tui"),
+    ("utils", "cuenv_utils"),
+];
+
+/// How much is logged, from the `-q` and `-v` flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Only warnings and errors
+    Quiet,
+    /// Messages for the user, and no diagnostics in text output
+    #[default]
+    Normal,
+    /// Diagnostics too, at debug level for `-v` and trace level for `-vv`
+    Verbose(u8),
+}
+
+impl Verbosity {
+    /// The verbosity of `-v` given `verbose` times, or of `-q`
+    pub fn from_flags(verbose: u8, quiet: bool) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, count) => Self::Verbose(count),
+        }
+    }
+
+    /// The most detailed level logged
+    pub fn level(self) -> LevelFilter {
+        match self {
+            Self::Quiet => LevelFilter::WARN,
+            Self::Normal => LevelFilter::INFO,
+            Self::Verbose(1) => LevelFilter::DEBUG,
+            Self::Verbose(_) => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// The filter of `CUENV_LOG`-style directives, with short crate names
+/// expanded
+pub fn parse_filter(directives: &str) -> Result<EnvFilter, String> {
+    let expanded = directives
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(expand_directive)
+        .collect::<Vec<_>>()
+        .join(",");
+    EnvFilter::builder()
+        .parse(&expanded)
+        .map_err(|e| format!("Invalid log filter '{directives}': {e}"))
+}
+
+/// Expand the target of a `target=level` directive; levels alone and span
+/// filters are kept as they are
+fn expand_directive(directive: &str) -> String {
+    match directive.split_once('=') {
+        Some((target, level)) if !target.contains('[') => {
+            format!("{}={level}", expand_target(target))
+        }
+        _ => directive.to_string(),
+    }
+}
+
+fn expand_target(target: &str) -> String {
+    let (head, rest) = match target.split_once("::") {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (target, None),
+    };
+    let crate_name = |short: &str| {
+        CRATES
+            .iter()
+            .find(|(name, _)| *name == short)
+            .map(|(_, crate_name)| *crate_name)
+    };
+
+    let path = if let Some(crate_name) = crate_name(head) {
+        crate_name.to_string()
+    } else if let Some((crate_name, module)) = head
+        .split_once('_')
+        .and_then(|(short, module)| Some((crate_name(short)?, module)))
+    {
+        format!("{crate_name}::{module}")
+    } else {
+        return target.to_string();
+    };
+
+    match rest {
+        Some(rest) => format!("{path}::{rest}"),
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_directive() {
+        assert_eq!(expand_directive("cache=debug"), "cuenv_cache=debug");
+        assert_eq!(
+            expand_directive("task_executor=trace"),
+            "cuenv_task::executor=trace"
+        );
+        assert_eq!(
+            expand_directive("task::executor::cache=trace"),
+            "cuenv_task::executor::cache=trace"
+        );
+        assert_eq!(expand_directive("cli=info"), "cuenv=info");
+        assert_eq!(
+            expand_directive("cuenv_env::daemon=debug"),
+            "cuenv_env::daemon=debug"
+        );
+        assert_eq!(expand_directive("hyper=warn"), "hyper=warn");
+        assert_eq!(expand_directive("warn"), "warn");
+        assert_eq!(expand_directive("[task]=debug"), "[task]=debug");
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert!(parse_filter("warn,cache=debug, task_executor=trace").is_ok());
+        assert!(parse_filter("cache=loud").is_err());
+    }
+
+    #[test]
+    fn test_verbosity() {
+        assert_eq!(Verbosity::from_flags(0, false).level(), LevelFilter::INFO);
+        assert_eq!(Verbosity::from_flags(1, false).level(), LevelFilter::DEBUG);
+        assert_eq!(Verbosity::from_flags(3, false).level(), LevelFilter::TRACE);
+        assert_eq!(Verbosity::from_flags(2, true), Verbosity::Quiet);
+    }
+}
@@ -0,0 +1,191 @@
+//! JSON log lines for `--log-format json`
+//!
+//! Every event becomes one JSON object on stderr, with its level, the run
+//! it belongs to and the task it happened in, so that CI log aggregation
+//! can filter and group the lines without parsing human messages. Lines of
+//! a task with `owners` name them too:
+//!
+//! ```json
+//! {"timestamp":1760000000000,"level":"WARN","run_id":"…","task":"build","target":"cuenv","message":"…","owners":["team-platform"]}
+//! ```
+
+use super::chrome_trace::ArgsVisitor;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::time::SystemTime;
+use tracing::span::Attributes;
+use tracing::{Event, Id, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Name of the span a task runs in
+const TASK_SPAN: &str = "task";
+
+/// A tracing layer writing events as JSON lines to stderr
+pub struct JsonLogLayer {
+    run_id: String,
+}
+
+/// The name of the task a span runs
+struct TaskName(String);
+
+/// The owners of the task a span runs, recorded comma-separated
+struct TaskOwners(Vec<String>);
+
+impl JsonLogLayer {
+    /// Log the events of the run `run_id`
+    pub fn new(run_id: impl Into<String>) -> Self {
+        Self {
+            run_id: run_id.into(),
+        }
+    }
+
+    /// The JSON object of an event
+    fn format(
+        &self,
+        level: &str,
+        target: &str,
+        task: Option<String>,
+        fields: Map<String, Value>,
+    ) -> String {
+        let mut line = fields;
+        let message = line.remove("message").unwrap_or_default();
+        let task = task.map(Value::from).or_else(|| line.remove("task"));
+
+        // Fields cannot replace the ones every line has
+        let mut object = Map::new();
+        object.insert(
+            "timestamp".to_string(),
+            Value::from(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_millis() as u64)
+                    .unwrap_or_default(),
+            ),
+        );
+        object.insert("level".to_string(), Value::from(level));
+        object.insert("run_id".to_string(), Value::from(self.run_id.as_str()));
+        if let Some(task) = task {
+            object.insert("task".to_string(), task);
+        }
+        object.insert("target".to_string(), Value::from(target));
+        object.insert("message".to_string(), message);
+        for (name, value) in line {
+            object.entry(name).or_insert(value);
+        }
+        Value::Object(object).to_string()
+    }
+}
+
+impl<S> Layer<S> for JsonLogLayer
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != TASK_SPAN {
+            return;
+        }
+        let mut visitor = ArgsVisitor(Map::new());
+        attrs.record(&mut visitor);
+        let name = visitor
+            .0
+            .get("name")
+            .or_else(|| visitor.0.get("task_name"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let owners: Vec<String> = visitor
+            .0
+            .get("owners")
+            .and_then(Value::as_str)
+            .map(|owners| {
+                owners
+                    .split(',')
+                    .filter(|owner| !owner.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if let Some(name) = name {
+                extensions.insert(TaskName(name));
+            }
+            if !owners.is_empty() {
+                extensions.insert(TaskOwners(owners));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = ArgsVisitor(Map::new());
+        event.record(&mut visitor);
+        let mut fields = visitor.0;
+
+        // A task field of the event names its task; otherwise the innermost
+        // task span it happened in does
+        let task = if fields.contains_key("task") {
+            None
+        } else {
+            ctx.event_scope(event).and_then(|scope| {
+                scope.into_iter().find_map(|span| {
+                    span.extensions()
+                        .get::<TaskName>()
+                        .map(|task| task.0.clone())
+                })
+            })
+        };
+
+        let owners = ctx.event_scope(event).and_then(|scope| {
+            scope.into_iter().find_map(|span| {
+                span.extensions()
+                    .get::<TaskOwners>()
+                    .map(|owners| owners.0.clone())
+            })
+        });
+        if let Some(owners) = owners {
+            fields.entry("owners").or_insert(Value::from(owners));
+        }
+
+        let metadata = event.metadata();
+        let line = self.format(metadata.level().as_str(), metadata.target(), task, fields);
+
+        // Messages can quote values, so they go through the secret masker
+        // like all other output
+        let _ = writeln!(
+            std::io::stderr().lock(),
+            "{}",
+            cuenv_core::masking::mask(&line)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let layer = JsonLogLayer::new("run-1");
+        let mut fields = Map::new();
+        fields.insert("message".to_string(), Value::from("Task failed"));
+        fields.insert("exit_code".to_string(), Value::from(2));
+        fields.insert("level".to_string(), Value::from("shadowed"));
+
+        let line: Value = serde_json::from_str(&layer.format(
+            "ERROR",
+            "cuenv",
+            Some("build".to_string()),
+            fields,
+        ))
+        .unwrap();
+        assert_eq!(line["level"], "ERROR");
+        assert_eq!(line["run_id"], "run-1");
+        assert_eq!(line["task"], "build");
+        assert_eq!(line["message"], "Task failed");
+        assert_eq!(line["exit_code"], 2);
+        assert!(line["timestamp"].is_u64());
+    }
+}
@@ -1,6 +1,14 @@
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use cuenv_core::constants::{CUENV_LOG_VAR, CUENV_RUN_ID_VAR};
+use cuenv_core::style::Role;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
 pub mod bridge_layer;
+pub mod chrome_trace;
+pub mod filter;
+pub mod json_log;
 pub mod progress;
 pub mod task_span;
 pub mod tree_formatter;
@@ -8,6 +16,9 @@ pub mod tree_subscriber;
 
 // Re-export tracing macros for convenience
 pub use bridge_layer::EventBridgeLayer;
+pub use chrome_trace::{ChromeTrace, ChromeTraceLayer};
+pub use filter::Verbosity;
+pub use json_log::JsonLogLayer;
 pub use tracing::{debug, error, info, instrument, span, trace, warn, Level, Span};
 
 /// Initialize the tracing system
@@ -36,7 +47,7 @@ pub fn init() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>
     } else {
         // Non-TTY environment - use simple formatter for compatibility
         let fmt_layer = fmt::layer()
-            .with_writer(std::io::stderr)
+            .with_writer(|| cuenv_core::masking::MaskingWriter::new(std::io::stderr()))
             .with_ansi(false)
             .compact()
             .with_target(false)
@@ -53,6 +64,163 @@ pub fn init() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>
     Ok(())
 }
 
+/// How messages for the user are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Plain text, as printed by each command
+    #[default]
+    Text,
+    /// One JSON object per line on stderr
+    Json,
+}
+
+impl LogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Unknown log format '{s}', expected 'text' or 'json'"
+            )),
+        }
+    }
+}
+
+static LOG_FORMAT: OnceLock<LogFormat> = OnceLock::new();
+static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+static RUN_ID: OnceLock<String> = OnceLock::new();
+static PROFILE: OnceLock<ChromeTrace> = OnceLock::new();
+
+/// The log format chosen by [`init_run`]
+pub fn log_format() -> LogFormat {
+    LOG_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// The ID of this run, inherited from a parent cuenv through `CUENV_RUN_ID`
+pub fn run_id() -> &'static str {
+    RUN_ID.get_or_init(|| {
+        std::env::var(CUENV_RUN_ID_VAR).unwrap_or_else(|_| uuid::Uuid::new_v4().to_string())
+    })
+}
+
+/// Initialize the tracing system of a command line run
+///
+/// `CUENV_LOG`, or `verbosity` without it, selects the events that are
+/// logged; see [`filter`]. With [`LogFormat::Json`] they are written as JSON
+/// lines, info and above by default. In text output they are written to
+/// stderr only when `-v` or `CUENV_LOG` asks for them. With `profile`, spans
+/// and events down to debug level are recorded into the trace
+/// [`recorded_trace`] returns.
+pub fn init_run(
+    log_format: LogFormat,
+    verbosity: Verbosity,
+    profile: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let _ = LOG_FORMAT.set(log_format);
+    let _ = VERBOSITY.set(verbosity);
+
+    let directives = std::env::var(CUENV_LOG_VAR)
+        .ok()
+        .filter(|directives| !directives.trim().is_empty());
+    let log_filter = || match &directives {
+        Some(directives) => filter::parse_filter(directives),
+        None => Ok(EnvFilter::default().add_directive(verbosity.level().into())),
+    };
+
+    let json_layer = match log_format {
+        LogFormat::Json => Some(JsonLogLayer::new(run_id()).with_filter(log_filter()?)),
+        LogFormat::Text => None,
+    };
+    let text_layer = match log_format {
+        LogFormat::Text if directives.is_some() || matches!(verbosity, Verbosity::Verbose(_)) => {
+            Some(
+                fmt::layer()
+                    .with_writer(|| cuenv_core::masking::MaskingWriter::new(std::io::stderr()))
+                    .with_ansi(false)
+                    .compact()
+                    .with_target(true)
+                    .with_filter(log_filter()?),
+            )
+        }
+        _ => None,
+    };
+    let profile_layer = profile.then(|| {
+        PROFILE
+            .get_or_init(ChromeTrace::new)
+            .layer()
+            .with_filter(LevelFilter::DEBUG)
+    });
+
+    if json_layer.is_none() && text_layer.is_none() && profile_layer.is_none() {
+        return Ok(());
+    }
+
+    tracing_subscriber::registry()
+        .with(json_layer)
+        .with(text_layer)
+        .with(profile_layer)
+        .try_init()?;
+
+    Ok(())
+}
+
+/// The trace of this run, when [`init_run`] was asked to profile it
+pub fn recorded_trace() -> Option<&'static ChromeTrace> {
+    PROFILE.get()
+}
+
+/// Print a message for the user
+///
+/// With [`LogFormat::Json`] the message is logged instead, to become a JSON
+/// line. Otherwise info messages go to stdout, and warnings and errors to
+/// stderr. With `-q` only warnings and errors are printed.
+pub fn message(level: Level, message: &str) {
+    log_message(level, None, message);
+}
+
+/// Print a message for the user about the task `task_name`, like [`message`]
+pub fn task_message(level: Level, task_name: &str, message: &str) {
+    log_message(level, Some(task_name), message);
+}
+
+fn log_message(level: Level, task: Option<&str>, message: &str) {
+    if log_format() == LogFormat::Text {
+        if VERBOSITY.get() == Some(&Verbosity::Quiet) && level > Level::WARN {
+            return;
+        }
+        if level <= Level::WARN {
+            let role = if level == Level::ERROR {
+                Role::Failure
+            } else {
+                Role::Warning
+            };
+            eprintln!("{}", cuenv_core::style::stderr().paint(role, message));
+        } else {
+            println!("{message}");
+        }
+        return;
+    }
+
+    match level {
+        Level::ERROR => error!(target: "cuenv", task, "{message}"),
+        Level::WARN => warn!(target: "cuenv", task, "{message}"),
+        Level::INFO => info!(target: "cuenv", task, "{message}"),
+        Level::DEBUG => debug!(target: "cuenv", task, "{message}"),
+        _ => trace!(target: "cuenv", task, "{message}"),
+    }
+}
+
 /// Check if we're running in a TTY environment
 fn is_tty() -> bool {
     std::io::IsTerminal::is_terminal(&std::io::stderr())
@@ -1,5 +1,6 @@
 use super::task_span::{TaskSpan, TaskState};
 use crossterm::terminal;
+use cuenv_core::style::{self, Painter, Role};
 use std::collections::HashMap;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
@@ -185,34 +186,22 @@ impl TreeFormatter {
             task.state.symbol_ascii()
         };
 
-        if self.config.use_colors {
-            let colored_symbol = match task.state {
-                TaskState::Waiting => format!("\x1b[90m{symbol}\x1b[0m"), // Gray
-                TaskState::Running { .. } => format!("\x1b[33m{symbol}\x1b[0m"), // Yellow
-                TaskState::Completed { .. } => format!("\x1b[32m{symbol}\x1b[0m"), // Green
-                TaskState::Failed { .. } => format!("\x1b[31m{symbol}\x1b[0m"), // Red
-            };
-            line.push_str(&colored_symbol);
-        } else {
-            line.push_str(symbol);
-        }
+        let painter = self.painter();
+        let role = match task.state {
+            TaskState::Waiting => Role::Muted,
+            TaskState::Running { .. } => Role::Warning,
+            TaskState::Completed { .. } => Role::Success,
+            TaskState::Failed { .. } => Role::Failure,
+        };
+        line.push_str(&painter.paint(role, symbol));
         line.push(' ');
 
-        // Add task name
-        if self.config.use_colors {
-            match task.state {
-                TaskState::Failed { .. } => {
-                    line.push_str(&format!("\x1b[31m{}\x1b[0m", task.name)); // Red
-                }
-                TaskState::Completed { .. } => {
-                    line.push_str(&format!("\x1b[32m{}\x1b[0m", task.name)); // Green
-                }
-                _ => {
-                    line.push_str(&task.name);
-                }
+        // Add task name, colored once it finished
+        match task.state {
+            TaskState::Failed { .. } | TaskState::Completed { .. } => {
+                line.push_str(&painter.paint(role, &task.name));
             }
-        } else {
-            line.push_str(&task.name);
+            _ => line.push_str(&task.name),
         }
 
         // Add progress bar if task is running and has progress
@@ -233,11 +222,7 @@ impl TreeFormatter {
             let duration_str = task.duration_string();
             if !duration_str.is_empty() {
                 line.push_str(" [");
-                if self.config.use_colors {
-                    line.push_str(&format!("\x1b[90m{duration_str}\x1b[0m")); // Gray
-                } else {
-                    line.push_str(&duration_str);
-                }
+                line.push_str(&painter.paint(Role::Muted, duration_str));
                 line.push(']');
             }
         }
@@ -245,11 +230,7 @@ impl TreeFormatter {
         // Add target information if available
         if let Some(target) = &task.target {
             line.push(' ');
-            if self.config.use_colors {
-                line.push_str(&format!("\x1b[90m({target})\x1b[0m")); // Gray
-            } else {
-                line.push_str(&format!("({target})"));
-            }
+            line.push_str(&painter.paint(Role::Muted, format!("({target})")));
         }
 
         line
@@ -280,17 +261,22 @@ impl TreeFormatter {
         }
 
         if failed_tasks > 0 {
-            if self.config.use_colors {
-                summary.push_str(&format!(", \x1b[31m{failed_tasks} failed\x1b[0m"));
-            } else {
-                summary.push_str(&format!(", {failed_tasks} failed"));
-            }
+            let failed = format!("{failed_tasks} failed");
+            summary.push_str(&format!(
+                ", {}",
+                self.painter().paint(Role::Failure, failed)
+            ));
         }
 
         summary.push(']');
         summary
     }
 
+    /// The painter of the theme, coloring only with `use_colors`
+    fn painter(&self) -> Painter {
+        Painter::new(self.config.use_colors, style::theme())
+    }
+
     /// Get the maximum line width for proper terminal handling
     pub fn get_terminal_width(&self) -> usize {
         terminal::size().map(|(w, _)| w as usize).unwrap_or(80)
@@ -348,12 +334,8 @@ fn supports_unicode() -> bool {
 
 /// Check if the terminal supports colors
 fn supports_colors() -> bool {
-    // Check if we're in a TTY and common color environment variables
-    std::io::IsTerminal::is_terminal(&std::io::stderr())
-        && std::env::var("NO_COLOR").is_err()
-        && std::env::var("TERM")
-            .map(|term| term != "dumb")
-            .unwrap_or(true)
+    // The tree is drawn on stderr
+    style::stderr().enabled()
 }
 
 #[cfg(test)]
@@ -269,7 +269,12 @@
 
         # Common build environment for all Crane builds
         commonArgs = {
-          src = craneLib.cleanCargoSource ./.;
+          # Keep the CUE schema, which cuenv-config embeds
+          src = pkgs.lib.cleanSourceWith {
+            src = ./.;
+            filter =
+              path: type: (pkgs.lib.hasSuffix ".cue" path) || (craneLib.filterCargoSources path type);
+          };
           strictDeps = true;
           inherit buildInputs nativeBuildInputs;
 
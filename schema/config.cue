@@ -7,6 +7,13 @@ package schema
 	// Cache configuration
 	cacheMode?: "off" | "read" | "read-write" | "write"
 	cacheEnabled?: bool
+
+	// Remote cache and retention of this package, over those of its workspace
+	cache?: {
+		remote?:  string
+		maxSize?: int | string
+		maxAge?:  string
+	}
 	
 	// Security and debugging
 	auditMode?: bool
@@ -15,4 +22,19 @@ package schema
 	// Default environment settings
 	defaultEnvironment?: string
 	defaultCapabilities?: [...string]
+
+	// Defer secret resolution until a task or `cuenv exec` starts a process
+	lazySecrets?: bool
+
+	// Separator used to join list values when they are exported
+	listSeparator?: string
+
+	// Run the one task a name is a prefix of, as `cuenv task dep` for deploy
+	taskPrefixMatch?: bool
+
+	// Expose package.json scripts as `npm:<script>` tasks, on by default
+	npmScripts?: bool
+
+	// Put the versions pinned for asdf and mise in front of PATH, on by default
+	toolVersions?: bool
 }
\ No newline at end of file
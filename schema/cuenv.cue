@@ -1,9 +1,19 @@
 package schema
 
 #Cuenv: {
+	// Stop inheriting env.cue files from parent directories
+	root?: bool
 	config?: #Config
 	capabilities?: [string]: #Capability
 	env?: #Env
+	constraints?: [=~"^[A-Z][A-Z0-9_]*$"]: #Constraint
 	hooks?: #Hooks
+	notify?: #Notify
+	// Nix development shell whose variables env is layered on
+	nix?: #Nix
+	// devenv.sh environment whose variables env is layered on
+	devenv?: #DevenvShell
+	// Shells tasks name in `shell`, as "bash-strict"
+	shells?: [string]: #ShellProfile
 	tasks: [string]: #Tasks | *{}
 }
@@ -1,17 +1,79 @@
 package schema
 
 #Environment: {
-	[=~"^[A-Z][A-Z0-9_]*$"]: string | #Secret
+	[=~"^[A-Z][A-Z0-9_]*$"]: string | #Secret | #ListModifier | #CommandValue | #TerraformValue | #Sensitive | #LocalStore | #Typed
 }
 
 
 // #Env defines the structure for environment variable configuration
 #Env: {
 	// Environment variables - keys must be valid environment variable names
-	[=~"^[A-Z][A-Z0-9_]*$"]: string | #Secret
+	[=~"^[A-Z][A-Z0-9_]*$"]: string | #Secret | #ListModifier | #CommandValue | #TerraformValue | #Sensitive | #LocalStore | #Typed
 
 	// Environment-specific overrides
 	environment?: [string]: {
-		[=~"^[A-Z][A-Z0-9_]*$"]: string | #Secret
+		[=~"^[A-Z][A-Z0-9_]*$"]: string | #Secret | #TerraformValue | #Typed
+	}
+
+	// Commands granted each capability
+	capabilities?: [string]: #Capability
+
+	// Overrides for an OS, an architecture, or both, e.g. "darwin", "arm64", "linux-arm64"
+	platform?: [string]: {
+		[=~"^[A-Z][A-Z0-9_]*$"]: string
+	}
+
+	// Overrides for hosts whose name matches a glob pattern, e.g. "ci-*"
+	hosts?: [string]: {
+		[=~"^[A-Z][A-Z0-9_]*$"]: string
 	}
 }
+
+// #Constraint validates an environment variable when the environment loads
+#Constraint: {
+	required?: bool
+	pattern?:  string
+	oneOf?: [...string]
+}
+
+// #ListModifier extends a path-like variable instead of replacing it
+#ListModifier: {
+	prepend?: [...string]
+	append?: [...string]
+	separator?: string
+}
+
+// #CommandValue sets a variable to the output of a command
+#CommandValue: {
+	fromCommand: string
+	// How long the output stays cached, e.g. "30s", "10m", "1h", "1d"
+	ttl?: =~"^[0-9]+[smhd]?$"
+	// Files whose changes invalidate the cached output
+	inputs?: [...string]
+}
+
+// #TerraformValue sets a variable to an output of a Terraform or OpenTofu module
+#TerraformValue: {
+	fromTerraform: {
+		// Directory of the root module, relative to this file
+		dir: string | *"."
+		output: string
+		// Program reading the outputs; terraform, or tofu when it is not installed
+		binary?: "terraform" | "tofu" | string
+	}
+}
+
+// #Sensitive marks a value to be masked in all output
+#Sensitive: {
+	value:     string
+	sensitive: bool
+}
+
+// #LocalStore reads a secret set with `cuenv secret set NAME` on this machine
+#LocalStore: {
+	fromLocalStore: string
+}
+
+// #Typed values are exported as strings: numbers and bools literally, lists
+// joined with config.listSeparator (default ":") and structs as JSON
+#Typed: number | bool | [...] | {[string]: _}
@@ -1,5 +1,19 @@
 package schema
 
+// A flake's development shell, loaded with `nix print-dev-env --json`
+// and cached until flake.lock or flake.nix change
+#Nix: {
+	// Flake reference of the shell, relative to this env.cue
+	flake: string | *"."
+}
+
+// A devenv.sh environment, loaded with `devenv print-dev-env --json` and
+// cached until devenv.nix, devenv.lock or devenv.yaml change
+#DevenvShell: {
+	// Directory of devenv.nix, relative to this env.cue
+	root: string | *"."
+}
+
 #NixFlake: #ExecHook & {
 	command: "nix"
 	args: [ "print-dev-env" ]
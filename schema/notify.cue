@@ -0,0 +1,16 @@
+package schema
+
+// Notifications sent when `cuenv task` finishes, with a JSON payload
+// describing the run
+#Notify: {
+	onFailure?:  #Notification
+	onSuccess?:  #Notification
+	onComplete?: #Notification
+}
+
+#Notification: {
+	// Shell command, run with the payload on stdin
+	command?: string
+	// URL the payload is POSTed to
+	webhook?: =~"^https?://"
+}
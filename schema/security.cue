@@ -0,0 +1,21 @@
+package schema
+
+// #Security restricts what a task can reach while it runs
+#Security: {
+	restrictDisk?:    bool
+	restrictNetwork?: bool
+	readOnlyPaths?: [...string]
+	readWritePaths?: [...string]
+	denyPaths?: [...string]
+	allowedHosts?: [...string]
+	// Derive disk restrictions from the task's inputs and outputs
+	inferFromInputsOutputs?: bool
+	// Syscall filter: "default", "strict" or the path of a JSON profile
+	seccomp?: "default" | "strict" | string
+	// Fail the task when its output contains a secret, after redacting it
+	failOnSecretLeak?: bool
+	// User to run the task as, by name or numeric ID. Needs cuenv to run as root.
+	user?: string
+	// Run the task without Linux capabilities
+	dropCapabilities?: bool
+}
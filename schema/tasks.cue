@@ -5,14 +5,76 @@ package schema
 	#TaskGroup | #Task
 }
 
+// A shell and the arguments before the command or script, the last one
+// taking it: `-e -c` for sh, bash and zsh and `-c` for others by default
+#ShellProfile: {
+	program: "sh" | "bash" | "zsh" | "fish" | "pwsh" | "powershell"
+	args?: [...string] & [_, ...]
+}
+
 #Task: {
-	shell: string | *"bash"
-	command!: string
+	// A shell program, the name of a profile of `shells`, or a profile
+	shell: string | #ShellProfile | *"bash"
+	// Either a command or a script to run
+	command?: string
+	script?: string
 	args?: [...string]
+	workingDir?: string
 
 	dependencies?: [...string]
+	// Run before this task, without it building on their results: they
+	// do not keep it from being taken from the cache
+	orderOnlyDependencies?: [...string]
+	// Run before this task only when they run anyway
+	weakDependencies?: [...string]
 	inputs?: [...string]
-	outputs?: [...string]
+	// Output paths, or named artifacts referenced by other tasks
+	// as ${outputs.<task>.<name>}
+	outputs?: [...string] | {[string]: #Output}
+
+	security?: #Security
+	cache?: bool | #TaskCache
+	cacheKey?: string
+	// Deprecated, use cache.env instead
+	cache_env?: #CacheEnv
+	// Timeout in seconds
+	timeout?: int & >0
+	// Bytes of stdout and of stderr each kept; past it, the first and
+	// the last half are kept and the task is reported as truncated
+	maxOutputBytes?: int & >0
+	// Labels to select tasks by, as in `cuenv task list --tagged ci`
+	tags?: [...string]
+	// Teams or people to ask about the task, named in listings and
+	// when it fails, as in ["team-platform"]
+	owners?: [...string]
+	// A long-running task, run as a systemd user service by
+	// `cuenv service install`
+	service?: #Service
+}
+
+#Service: {
+	restart: *"on-failure" | "always" | "no"
+	// Restarts allowed within five minutes before giving up
+	retries?: int & >=0
+	// Seconds to wait before restarting
+	delay?: int & >=0
+}
+
+#Output: {
+	// Relative to the task's working directory
+	path: string
+	type: *"file" | "directory"
+}
+
+#TaskCache: {
+	enabled?: bool
+	env?: #CacheEnv
+}
+
+#CacheEnv: {
+	include?: [...string]
+	exclude?: [...string]
+	useSmartDefaults?: bool
 }
 
 // Execution modes for task groups:
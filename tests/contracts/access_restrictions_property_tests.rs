@@ -193,6 +193,10 @@ proptest! {
             deny_paths: None,
             allowed_hosts: None,
             infer_from_inputs_outputs: Some(infer),
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let task_config = TaskConfig {
@@ -209,6 +213,7 @@ proptest! {
             cache_key: None,
             cache_env: None,
             timeout: None,
+            tags: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config_with_task(&security, &task_config);
@@ -78,6 +78,7 @@ fn test_cache_config_resolver_simple_task_config() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let should_cache = CacheConfigResolver::should_cache_task(
@@ -111,6 +112,7 @@ fn test_cache_config_resolver_advanced_task_config() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let should_cache = CacheConfigResolver::should_cache_task(
@@ -148,6 +150,7 @@ fn test_cache_config_resolver_global_disabled() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let should_cache = CacheConfigResolver::should_cache_task(
@@ -179,6 +182,7 @@ fn test_cache_config_resolver_no_task_config() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let should_cache = CacheConfigResolver::should_cache_task(
@@ -210,6 +214,7 @@ fn test_cache_config_resolver_task_disabled() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let should_cache = CacheConfigResolver::should_cache_task(
@@ -308,6 +313,7 @@ fn test_cache_config_env_filtering() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let should_cache = CacheConfigResolver::should_cache_task(
@@ -389,6 +395,7 @@ fn test_cache_config_migration() {
             use_smart_defaults: Some(true),
         }),
         timeout: None,
+        tags: None,
     };
 
     // The cache_env should be converted to the new format
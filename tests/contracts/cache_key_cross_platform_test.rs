@@ -28,6 +28,7 @@ fn test_platform_specific_env_vars() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Linux-specific environment variables
@@ -158,6 +159,7 @@ fn test_path_separator_normalization() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let env_vars = HashMap::from([
@@ -251,6 +253,7 @@ fn test_case_sensitivity_handling() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Test with case-sensitive environment variables
@@ -321,6 +324,7 @@ fn test_windows_path_handling() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let env_vars = HashMap::from([
@@ -438,6 +442,7 @@ fn test_cross_platform_build_tools() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Linux build environment
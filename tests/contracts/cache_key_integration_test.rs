@@ -50,6 +50,7 @@ fn test_cargo_build_cache_key_generation() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Simulate real Cargo build environment
@@ -170,6 +171,7 @@ fn test_npm_build_cache_key_generation() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Simulate real npm build environment
@@ -292,6 +294,7 @@ fn test_python_build_cache_key_generation() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Simulate real Python build environment
@@ -416,6 +419,7 @@ fn test_make_build_cache_key_generation() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Simulate real Make build environment
@@ -529,6 +533,7 @@ fn test_mixed_build_tools_cache_key_generation() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     let npm_task = TaskConfig {
@@ -545,6 +550,7 @@ fn test_mixed_build_tools_cache_key_generation() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Mixed environment with both Cargo and npm variables
@@ -623,6 +629,7 @@ async fn test_cache_manager_integration() {
         inline_threshold: 4096, // 4KB default
         env_filter: Default::default(),
         task_env_filters: std::collections::HashMap::new(),
+        remote: None,
     };
     let cache_manager = CacheManager::new(config).await.unwrap();
 
@@ -641,6 +648,7 @@ async fn test_cache_manager_integration() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
     };
 
     // Test environment variables
@@ -102,10 +102,14 @@ mod landlock_audit_tests {
                 "/usr/lib/libc.so".to_string(),
                 "/home/user/data.txt".to_string(),
             ],
+            read_files: vec!["/etc/passwd".to_string(), "/usr/lib/libc.so".to_string()],
+            written_files: vec!["/home/user/data.txt".to_string()],
             network_connections: vec![
                 "tcp:example.com:443".to_string(),
                 "udp:8.8.8.8:53".to_string(),
             ],
+            blocked_syscalls: vec!["ptrace".to_string()],
+            blocked_syscalls: vec![],
         };
 
         // Just test that print_summary doesn't panic
@@ -118,7 +122,10 @@ mod landlock_audit_tests {
 
         let report = AuditReport {
             accessed_files: vec![],
+            read_files: vec![],
+            written_files: vec![],
             network_connections: vec![],
+            blocked_syscalls: vec![],
         };
 
         // Should handle empty report gracefully
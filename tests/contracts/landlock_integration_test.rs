@@ -17,6 +17,10 @@ mod landlock_tests {
             deny_paths: None,
             allowed_hosts: None,
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
@@ -43,6 +47,10 @@ mod landlock_tests {
             deny_paths: None,
             allowed_hosts: None, // No allowed hosts = block all
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
@@ -67,6 +75,10 @@ mod landlock_tests {
             deny_paths: None,
             allowed_hosts: Some(vec!["443".to_string()]),
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
@@ -14,6 +14,10 @@ mod landlock_unit_tests {
             deny_paths: Some(vec!["/etc/shadow".into()]),
             allowed_hosts: Some(vec!["443".to_string(), "80".to_string()]),
             infer_from_inputs_outputs: Some(false),
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
@@ -36,6 +40,10 @@ mod landlock_unit_tests {
             deny_paths: None,
             allowed_hosts: None,
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
@@ -58,6 +66,10 @@ mod landlock_unit_tests {
             deny_paths: None,
             allowed_hosts: None,
             infer_from_inputs_outputs: Some(true),
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let task_config = TaskConfig {
@@ -74,6 +86,7 @@ mod landlock_unit_tests {
             cache_key: None,
             cache_env: None,
             timeout: None,
+            tags: None,
         };
 
         let restrictions =
@@ -102,6 +115,10 @@ mod landlock_unit_tests {
             deny_paths: None,
             allowed_hosts: None,
             infer_from_inputs_outputs: Some(true),
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let task_config = TaskConfig {
@@ -118,6 +135,7 @@ mod landlock_unit_tests {
             cache_key: None,
             cache_env: None,
             timeout: None,
+            tags: None,
         };
 
         let restrictions =
@@ -151,6 +169,10 @@ mod landlock_unit_tests {
             deny_paths: None,
             allowed_hosts: Some(vec![]), // Empty list should block all
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
@@ -174,6 +196,10 @@ mod landlock_unit_tests {
                 "invalid-port".to_string(), // Should be handled gracefully
             ]),
             infer_from_inputs_outputs: None,
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
@@ -235,6 +261,10 @@ mod landlock_unit_tests {
                 "3000".to_string(), // Custom app
             ]),
             infer_from_inputs_outputs: Some(false),
+            seccomp: None,
+            fail_on_secret_leak: None,
+            user: None,
+            drop_capabilities: None,
         };
 
         let restrictions = AccessRestrictions::from_security_config(&security_config);
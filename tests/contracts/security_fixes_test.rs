@@ -79,6 +79,7 @@ async fn test_action_cache_signing_integration() {
         cache_key: None,
         cache_env: None,
         timeout: None,
+        tags: None,
         security: None,
     };
 
@@ -196,6 +197,7 @@ async fn test_cache_manager_lock_permissions() {
         inline_threshold: 4096, // 4KB default
         env_filter: Default::default(),
         task_env_filters: std::collections::HashMap::new(),
+        remote: None,
     };
     let cache_manager = CacheManager::new(config).await.unwrap();
 
@@ -84,6 +84,7 @@ mod comprehensive_concurrent_tests {
                             cache_key: None,
                             cache_env: None,
                             timeout: Some(task_timeout.as_secs() as u32),
+                            tags: None,
                         };
 
                         // Simulate task execution with reduced load
@@ -191,6 +192,7 @@ mod comprehensive_concurrent_tests {
                                 cache_key: None,
                                 cache_env: None,
                                 timeout: None,
+                                tags: None,
                             };
 
                             // Generate cache key
@@ -449,6 +451,7 @@ tasks: {
             cache_key: None,
             cache_env: None,
             timeout: Some(1), // 1 second timeout
+            tags: None,
         };
 
         // Thread 1: Perform cache operation with large file
@@ -34,6 +34,7 @@ mod concurrent_cache_tests {
             cache_key: None,
             cache_env: None,
             timeout: None,
+            tags: None,
         }
     }
 